@@ -1,11 +1,11 @@
-/// Benchmarks for Mr. Hedgehog SCIP ingestion pipeline.
-/// 
-/// Run with: `cargo bench`
-/// 
-/// Phase 3.4: Comprehensive benchmark suite including:
-/// - Full pipeline benchmarks at various scales
-/// - Mmap loading vs traditional read comparison
-/// - Parallel processing overhead measurement
+//! Benchmarks for Mr. Hedgehog SCIP ingestion pipeline.
+//! 
+//! Run with: `cargo bench`
+//! 
+//! Phase 3.4: Comprehensive benchmark suite including:
+//! - Full pipeline benchmarks at various scales
+//! - Mmap loading vs traditional read comparison
+//! - Parallel processing overhead measurement
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId, Throughput};
 use std::fs::File;
@@ -0,0 +1,164 @@
+//! rustc driver behind `--mir`.
+//!
+//! Cargo runs it as `RUSTC_WORKSPACE_WRAPPER`, so it is invoked as
+//! `mr_hedgehog_mir_driver <rustc> <args>..` for every workspace crate and
+//! compiles the crate in-process. Once analysis is done it walks the MIR of
+//! every function body and writes the functions and their resolved calls
+//! as JSON lines into `$MR_HEDGEHOG_MIR_OUT`, one file per compilation.
+//!
+//! Links the nightly compiler, so it is a package of its own outside the
+//! workspace: `cargo +nightly install --path mir_driver` puts it next to an
+//! installed `mr_hedgehog`, after `rustup component add rustc-dev llvm-tools
+//! --toolchain nightly`.
+
+#![feature(rustc_private)]
+
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_span;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use rustc_driver::{Callbacks, Compilation};
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
+use rustc_interface::interface::Compiler;
+use rustc_middle::mir::TerminatorKind;
+use rustc_middle::ty::{self, Instance, InstanceKind, TyCtxt, TypingEnv, Unnormalized};
+use rustc_span::Span;
+
+use mr_hedgehog::infrastructure::mir_builder::{MirItem, MirRecord, OUT_DIR_ENV};
+
+struct Collect {
+    out_dir: Option<PathBuf>,
+}
+
+impl Callbacks for Collect {
+    fn after_analysis(&mut self, _compiler: &Compiler, tcx: TyCtxt<'_>) -> Compilation {
+        if let Some(out_dir) = &self.out_dir {
+            if let Err(e) = write_records(out_dir, &collect(tcx), tcx) {
+                eprintln!("{}: cannot write MIR records: {}", env!("CARGO_BIN_NAME"), e);
+            }
+        }
+        Compilation::Continue
+    }
+}
+
+fn main() -> ExitCode {
+    // argv: driver, rustc, rustc's arguments; rustc_driver wants argv[0] first
+    let mut args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        let rustc = args.remove(1);
+        // The driver lives outside the toolchain, so it cannot find the sysroot on its own
+        if !args.iter().any(|a| a == "--sysroot" || a.starts_with("--sysroot=")) {
+            if let Some(sysroot) = sysroot(&rustc) {
+                args.extend(["--sysroot".to_string(), sysroot]);
+            }
+        }
+        // Unoptimized MIR keeps every call; inlining would hide callees
+        args.push("-Zmir-opt-level=0".to_string());
+    }
+    let mut callbacks = Collect { out_dir: std::env::var_os(OUT_DIR_ENV).map(PathBuf::from) };
+    rustc_driver::install_ice_hook("https://github.com/plokm5151/Mr-Hedgehog/issues", |_| ());
+    rustc_driver::catch_with_exit_code(|| rustc_driver::compiler_entrypoint(&args, &mut callbacks))
+}
+
+fn sysroot(rustc: &str) -> Option<String> {
+    let output = Command::new(rustc).args(["--print", "sysroot"]).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Functions with a body in this crate and the calls in them. Closures have
+/// their calls attributed to the function they are written in.
+fn collect(tcx: TyCtxt<'_>) -> Vec<MirRecord> {
+    let mut records = Vec::new();
+    for local in tcx.hir_body_owners() {
+        let def_id = local.to_def_id();
+        match tcx.def_kind(def_id) {
+            DefKind::Fn | DefKind::AssocFn => {
+                let (file, line) = location(tcx, tcx.def_span(def_id));
+                records.push(MirRecord::Fn { item: item(tcx, def_id), file, line });
+            }
+            DefKind::Closure => {}
+            _ => continue,
+        }
+        let caller = item(tcx, tcx.typeck_root_def_id(def_id));
+        let body = tcx.optimized_mir(def_id);
+        let typing_env = TypingEnv::post_analysis(tcx, def_id);
+        for block in body.basic_blocks.iter() {
+            let TerminatorKind::Call { func, fn_span, .. } = &block.terminator().kind else { continue };
+            let ty::FnDef(callee, args) = *func.ty(body, tcx).kind() else { continue };
+            // Resolving needs the arguments normalized and free of bound variables
+            let args = tcx.try_normalize_erasing_regions(typing_env, Unnormalized::new_wip(args)).ok().and_then(|a| a.no_bound_vars());
+            let resolved = args.map(|args| Instance::try_resolve(tcx, typing_env, callee, args));
+            let (target, dynamic) = match resolved {
+                Some(Ok(Some(instance))) => (instance.def_id(), matches!(instance.def, InstanceKind::Virtual(..))),
+                // Still generic here: the call goes to whatever implements the trait
+                _ => (callee, tcx.trait_of_assoc(callee).is_some()),
+            };
+            let (file, line) = location(tcx, fn_span.source_callsite());
+            records.push(MirRecord::Call { caller: caller.clone(), callee: item(tcx, target), file, line, dynamic });
+        }
+    }
+    records
+}
+
+/// `def_id` split into crate, modules, impl or trait owner and name.
+fn item(tcx: TyCtxt<'_>, def_id: DefId) -> MirItem {
+    let mut owner = None;
+    let mut modules = Vec::new();
+    let mut parent = tcx.opt_parent(def_id);
+    while let Some(id) = parent {
+        match tcx.def_kind(id) {
+            DefKind::Impl { .. } if owner.is_none() => owner = Some(self_type(tcx, id)),
+            DefKind::Trait if owner.is_none() => owner = Some(tcx.item_name(id).to_string()),
+            DefKind::Mod if id.index != rustc_hir::def_id::CRATE_DEF_INDEX => modules.push(tcx.item_name(id).to_string()),
+            _ => {}
+        }
+        parent = tcx.opt_parent(id);
+    }
+    modules.reverse();
+    MirItem {
+        krate: tcx.crate_name(def_id.krate).to_string(),
+        modules,
+        owner,
+        name: tcx.item_name(def_id).to_string(),
+    }
+}
+
+/// Name of the type an impl is for, without generic arguments, as the
+/// syn builder writes method owners.
+fn self_type(tcx: TyCtxt<'_>, impl_id: DefId) -> String {
+    let ty = tcx.type_of(impl_id).instantiate_identity().skip_normalization();
+    match ty.kind() {
+        ty::Adt(adt, _) => tcx.item_name(adt.did()).to_string(),
+        _ => ty.to_string(),
+    }
+}
+
+fn location(tcx: TyCtxt<'_>, span: Span) -> (String, usize) {
+    let source_map = tcx.sess.source_map();
+    let line = source_map.lookup_char_pos(span.lo()).line;
+    let file = source_map.span_to_filename(span).prefer_local_unconditionally().to_string();
+    // Cargo passes paths relative to the workspace root it runs rustc in
+    let file = match std::env::current_dir() {
+        Ok(dir) if Path::new(&file).is_relative() => dir.join(&file).to_string_lossy().into_owned(),
+        _ => file,
+    };
+    (file, line)
+}
+
+fn write_records(out_dir: &Path, records: &[MirRecord], tcx: TyCtxt<'_>) -> std::io::Result<()> {
+    // A crate compiles once per target kind (lib, test harness, ..); keep them apart
+    let name = format!("{}-{}.jsonl", tcx.crate_name(LOCAL_CRATE), std::process::id());
+    let mut file = std::io::BufWriter::new(std::fs::File::create(out_dir.join(name))?);
+    for record in records {
+        serde_json::to_writer(&mut file, record)?;
+        writeln!(file)?;
+    }
+    file.flush()
+}
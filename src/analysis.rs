@@ -0,0 +1,288 @@
+//! Library entry point: build a call graph in one call.
+//!
+//! Wraps the orchestration the binary performs (workspace loading, engine
+//! selection, SCIP indexing and merging) so other tools can embed it:
+//!
+//! ```no_run
+//! use mr_hedgehog::{analyze, AnalysisConfig};
+//!
+//! let graph = analyze(&AnalysisConfig::new("path/to/Cargo.toml"))?;
+//! println!("{} functions", graph.nodes.len());
+//! # Ok::<(), mr_hedgehog::Error>(())
+//! ```
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+
+use crate::domain::callgraph::CallGraph;
+use crate::domain::cfg::CfgOptions;
+use crate::domain::index::AnalysisError;
+use crate::domain::language::Language;
+use crate::domain::lsif_ingest::LsifIngestor;
+use crate::domain::paths::ProjectRoot;
+use crate::domain::progress::{NoProgress, Progress};
+use crate::domain::scip_ingest::{ScipIngestor, ScipOptions};
+use crate::domain::source::SourceSet;
+use crate::domain::store::SymbolStore;
+use crate::error::{Error, Result};
+use crate::infrastructure::analysis_cache::AnalysisCache;
+use crate::infrastructure::hybrid_builder::HybridCallGraphBuilder;
+#[cfg(feature = "mir")]
+use crate::infrastructure::mir_builder::MirCallGraphBuilder;
+use crate::infrastructure::project_loader::{ProjectLoader, SourceFilter};
+use crate::infrastructure::{scip_runner, SimpleCallGraphBuilder};
+
+/// How call edges are discovered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// AST-based analysis of the workspace sources.
+    #[default]
+    Syn,
+    /// Run the language's SCIP indexer and use its graph as-is.
+    Scip(Language),
+    /// Index with rust-analyzer (cached under `target/tracecraft/`) and merge
+    /// the result with the syn graph. `None` runs the default indexing command.
+    RustAnalyzer(Option<String>),
+    /// Check the workspace through the rustc driver and build the graph from
+    /// its MIR. `None` looks for the driver next to the running executable.
+    #[cfg(feature = "mir")]
+    Mir(Option<PathBuf>),
+}
+
+/// What to analyze and how.
+#[derive(Clone, Default)]
+pub struct AnalysisConfig {
+    /// Workspace `Cargo.toml`, or the directory containing it.
+    pub workspace: Option<PathBuf>,
+    pub engine: Engine,
+    /// Pre-built SCIP indices, ingested into one graph. Merged with the syn
+    /// graph when `workspace` is set, used on their own otherwise.
+    pub scip_indices: Vec<PathBuf>,
+    /// LSIF dump to build the graph from instead; sources, when given, only
+    /// enrich traces.
+    pub lsif_index: Option<PathBuf>,
+    /// Analyze `cargo expand` output instead of the raw sources.
+    pub expand_macros: bool,
+    /// Also analyze local path dependencies outside the workspace
+    /// (`shared = { path = "../shared" }`), so calls into them resolve.
+    pub follow_path_deps: bool,
+    /// Files to leave out of the analysis.
+    pub filter: SourceFilter,
+    /// Symbol store for the syn engine (in-memory when unset).
+    pub store: Option<Arc<dyn SymbolStore>>,
+    /// Per-file summary cache for the syn engine.
+    pub cache: Option<Arc<AnalysisCache>>,
+    /// Features and target `#[cfg(..)]` attributes are evaluated against
+    /// (syn engine; nothing is evaluated by default). Given features are
+    /// joined by the workspace's `default` ones unless
+    /// [`CfgOptions::no_default_features`] is set.
+    pub cfg: CfgOptions,
+    /// Add type nodes and their ownership edges (syn engine and SCIP indices).
+    pub types: bool,
+    /// Only take call edges from SCIP indices, leaving out imports and
+    /// functions used as values.
+    pub scip_calls_only: bool,
+    /// Stub nodes, tagged with their package, for the functions SCIP indices
+    /// call but do not define.
+    pub include_external: bool,
+    /// Receives parsing and linking progress (nothing is reported by default).
+    pub progress: Option<Arc<dyn Progress>>,
+    /// Sources to analyze instead of loading `workspace`, e.g. a snippet
+    /// read from stdin.
+    pub sources: Option<SourceSet>,
+}
+
+/// A call graph together with the sources it was built from.
+pub struct Analysis {
+    pub graph: CallGraph,
+    /// Every analyzed file; empty when the graph came from a SCIP index alone.
+    pub files: SourceSet,
+    /// Files that failed to parse and are missing from the graph.
+    pub parse_errors: Vec<AnalysisError>,
+}
+
+impl AnalysisConfig {
+    /// Syn analysis of the given workspace.
+    pub fn new(workspace: impl Into<PathBuf>) -> Self {
+        Self { workspace: Some(workspace.into()), ..Default::default() }
+    }
+
+    /// Syn analysis of in-memory sources.
+    pub fn from_sources(sources: SourceSet) -> Self {
+        Self { sources: Some(sources), ..Default::default() }
+    }
+
+    /// Workspace manifest path, accepting a directory for convenience.
+    pub fn manifest_path(&self) -> Option<PathBuf> {
+        self.workspace.as_ref().map(|ws| {
+            if ws.is_dir() { ws.join("Cargo.toml") } else { ws.clone() }
+        })
+    }
+
+    /// Directory containing the workspace manifest.
+    pub fn workspace_root(&self) -> Option<PathBuf> {
+        self.manifest_path().map(|m| match m.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        })
+    }
+
+    /// What node locations are relative to: the workspace directory.
+    pub fn project_root(&self) -> Option<ProjectRoot> {
+        self.workspace_root().map(ProjectRoot::new)
+    }
+
+    /// Load the workspace sources, paths relative to the workspace
+    /// directory, or return the in-memory ones.
+    pub fn load_files(&self) -> Result<SourceSet> {
+        if let Some(sources) = &self.sources {
+            return Ok(sources.clone());
+        }
+        let Some(manifest) = self.manifest_path() else {
+            return Err(Error::NoInput);
+        };
+        if !manifest.is_file() {
+            return Err(Error::ManifestNotFound(manifest));
+        }
+        let files = ProjectLoader::load_workspace(&manifest.to_string_lossy(), self.expand_macros, self.follow_path_deps, &self.filter)
+            .map_err(|source| Error::WorkspaceLoad { manifest: manifest.clone(), source })?;
+        if files.is_empty() {
+            return Err(Error::NoSources(manifest));
+        }
+        Ok(files.relative_to(&ProjectRoot::new(self.workspace_root().unwrap_or_default())))
+    }
+
+    fn has_sources(&self) -> bool {
+        self.workspace.is_some() || self.sources.is_some()
+    }
+
+    /// `cfg` with the `default` features cargo would enable next to the
+    /// given ones.
+    fn build_cfg(&self) -> CfgOptions {
+        let mut cfg = self.cfg.clone();
+        let (Some(features), false, Some(manifest)) = (&mut cfg.features, cfg.no_default_features, self.manifest_path()) else {
+            return cfg;
+        };
+        match ProjectLoader::default_features(&manifest) {
+            Ok(defaults) => features.extend(defaults),
+            Err(e) => tracing::warn!("default features of {} unknown: {:#}", manifest.display(), e),
+        }
+        cfg
+    }
+
+    fn syn_builder(&self) -> SimpleCallGraphBuilder {
+        let builder = match &self.store {
+            Some(store) => SimpleCallGraphBuilder::new_with_store(store.clone()),
+            None => SimpleCallGraphBuilder::new(),
+        }.with_cfg(self.build_cfg());
+        let builder = match &self.cache {
+            Some(cache) => builder.with_cache(cache.clone()),
+            None => builder,
+        };
+        let builder = if self.types { builder.with_types() } else { builder };
+        match &self.progress {
+            Some(progress) => builder.with_progress(progress.clone()),
+            None => builder,
+        }
+    }
+
+    fn hybrid(&self, indices: &[PathBuf], files: SourceSet) -> Analysis {
+        let builder = match &self.store {
+            Some(store) => HybridCallGraphBuilder::new_with_store(indices.to_vec(), store.clone()),
+            None => HybridCallGraphBuilder::new(indices.to_vec()),
+        }.with_cfg(self.build_cfg());
+        let builder = match &self.progress {
+            Some(progress) => builder.with_progress(progress.clone()),
+            None => builder,
+        };
+        let builder = match self.project_root() {
+            Some(root) => builder.with_root(root),
+            None => builder,
+        };
+        let builder = if self.types { builder.with_types() } else { builder };
+        let builder = if self.scip_calls_only { builder.with_calls_only() } else { builder };
+        let builder = if self.include_external { builder.with_external() } else { builder };
+        let (graph, parse_errors) = builder.build_with_errors(&files);
+        Analysis { graph, files, parse_errors }
+    }
+}
+
+/// Build the call graph described by `config`.
+///
+/// Files that fail to parse are reported as [`Error::Parse`]; use
+/// [`analyze_with_sources`] to keep the partial graph instead.
+pub fn analyze(config: &AnalysisConfig) -> Result<CallGraph> {
+    let analysis = analyze_with_sources(config)?;
+    if !analysis.parse_errors.is_empty() {
+        return Err(Error::Parse(analysis.parse_errors));
+    }
+    Ok(analysis.graph)
+}
+
+/// Graph of SCIP indices with locations relative to `root`, keeping what
+/// `config` asks for.
+fn ingest(indices: &[PathBuf], config: &AnalysisConfig, root: &ProjectRoot) -> Result<CallGraph> {
+    let progress = config.progress.as_deref().unwrap_or(&NoProgress);
+    let options = ScipOptions { types: config.types, calls_only: config.scip_calls_only, include_external: config.include_external };
+    ScipIngestor::ingest_all(indices, progress, Some(root), options).map_err(Error::Scip)
+}
+
+/// Like [`analyze`], but also returns the loaded sources (for trace snippets).
+pub fn analyze_with_sources(config: &AnalysisConfig) -> Result<Analysis> {
+    if let Some(dump) = &config.lsif_index {
+        let root = config.project_root().unwrap_or_else(|| ProjectRoot::new("."));
+        let graph = LsifIngestor::ingest_relative_to(dump, &root).map_err(Error::Lsif)?;
+        let files = if config.has_sources() { config.load_files().unwrap_or_default() } else { SourceSet::default() };
+        return Ok(Analysis { graph, files, parse_errors: Vec::new() });
+    }
+    if !config.scip_indices.is_empty() {
+        let indices = &config.scip_indices;
+        if !config.has_sources() {
+            return Ok(Analysis { graph: ingest(indices, config, &ProjectRoot::new("."))?, files: SourceSet::default(), parse_errors: Vec::new() });
+        }
+        return Ok(config.hybrid(indices, config.load_files()?));
+    }
+
+    match &config.engine {
+        Engine::Syn => {
+            let files = config.load_files()?;
+            let (graph, parse_errors) = config.syn_builder().build_with_errors(&files);
+            Ok(Analysis { graph, files, parse_errors })
+        }
+        Engine::RustAnalyzer(command) => {
+            let Some(root) = config.workspace_root() else {
+                return Err(Error::NoInput);
+            };
+            let files = config.load_files()?;
+            let project = ProjectRoot::new(&root);
+            let sources: Vec<String> = files.iter().map(|f| project.resolve(&f.path).to_string_lossy().into_owned()).collect();
+            let index = scip_runner::index_with_rust_analyzer(&root, command.as_deref(), &sources)
+                .map_err(Error::Index)?;
+            Ok(config.hybrid(&[index], files))
+        }
+        Engine::Scip(language) => {
+            let root = config.workspace_root().unwrap_or_else(|| PathBuf::from("."));
+            let index = scip_runner::generate_scip_index_for_language(&root, *language, &[])
+                .map_err(Error::Index)?;
+            let graph = ingest(&[index], config, &ProjectRoot::new(&root))?;
+            // Sources are optional here; they only enrich traces
+            let files = if config.has_sources() { config.load_files().unwrap_or_default() } else { SourceSet::default() };
+            Ok(Analysis { graph, files, parse_errors: Vec::new() })
+        }
+        #[cfg(feature = "mir")]
+        Engine::Mir(driver) => {
+            let Some(root) = config.workspace_root() else {
+                return Err(Error::NoInput);
+            };
+            let builder = MirCallGraphBuilder::new(root);
+            let builder = match driver {
+                Some(driver) => builder.with_driver(driver),
+                None => builder,
+            };
+            let graph = builder.build().map_err(Error::Mir)?;
+            let files = config.load_files().unwrap_or_default();
+            Ok(Analysis { graph, files, parse_errors: Vec::new() })
+        }
+    }
+}
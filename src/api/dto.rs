@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+
 use serde::{Serialize, Deserialize};
-use crate::domain::callgraph::CallGraph;
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode, EdgeKind, TypeKind};
+use crate::domain::links::SourceLinks;
+use crate::domain::metrics::FnMetrics;
+use crate::domain::panics::PanicSite;
+use crate::domain::source::SourceOrigin;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GraphDto {
@@ -13,6 +19,49 @@ pub struct NodeDto {
     pub label: String,
     pub package: Option<String>,
     pub location: Option<String>,
+    #[serde(default)]
+    pub is_public: bool,
+    #[serde(default)]
+    pub is_async: bool,
+    #[serde(default)]
+    pub is_unsafe: bool,
+    #[serde(default)]
+    pub is_test: bool,
+    #[serde(default)]
+    pub is_test_fn: bool,
+    /// `#[cfg(..)]` condition the node needs, e.g. `unix` or `feature = "tls"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfg: Option<String>,
+    /// Crate of an external stub node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external: Option<String>,
+    /// Complexity of the function body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<FnMetrics>,
+    /// Times the function ran according to a coverage report.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<u64>,
+    /// `unsafe` blocks in the function body.
+    #[serde(default)]
+    pub unsafe_blocks: usize,
+    /// Potential panics in the function body.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panics: Vec<PanicSite>,
+    /// `fn run(op: &dyn Op) -> i32`, as written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// First line of the doc comment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// `struct`, `enum`, `union` or `alias` for type nodes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_kind: Option<TypeKind>,
+    /// `test`, `bench` or `example` for nodes from those targets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<SourceOrigin>,
+    /// Definition on a source host, with `--link-base`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,30 +69,190 @@ pub struct EdgeDto {
     pub from: String,
     pub to: String,
     pub label: Option<String>,
+    /// Call site in `file:line` form, when the builder recorded one.
+    pub location: Option<String>,
+    /// Call sites the edge stands for.
+    #[serde(default = "one")]
+    pub weight: usize,
+}
+
+fn one() -> usize {
+    1
+}
+
+/// A node together with its direct callees and callers.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeDetailDto {
+    pub node: NodeDto,
+    pub callees: Vec<EdgeDto>,
+    pub callers: Vec<EdgeDto>,
+}
+
+impl NodeDto {
+    pub fn from_node(node: &CallGraphNode) -> Self {
+        NodeDto {
+            id: node.id.to_string(),
+            label: node.label.clone().unwrap_or_else(|| node.id.to_string()),
+            package: None, // Mr. Hedgehog domain doesn't reliably store package yet
+            location: node.location.clone(),
+            is_public: node.is_public,
+            is_async: node.is_async,
+            is_unsafe: node.is_unsafe,
+            is_test: node.is_test,
+            is_test_fn: node.is_test_fn,
+            cfg: node.cfg.clone(),
+            external: node.external.clone(),
+            metrics: node.metrics,
+            coverage: node.coverage,
+            unsafe_blocks: node.unsafe_blocks,
+            panics: node.panics.clone(),
+            signature: node.signature.clone(),
+            doc: node.doc.clone(),
+            type_kind: node.type_kind,
+            origin: node.origin,
+            url: None,
+        }
+    }
+}
+
+impl EdgeDto {
+    pub fn from_edge(from: &str, edge: &CallEdge) -> Self {
+        EdgeDto {
+            from: from.to_string(),
+            to: edge.target.to_string(),
+            label: Some(edge.kind.as_str().to_string()),
+            location: edge.location(),
+            weight: edge.weight,
+        }
+    }
 }
 
 impl From<CallGraph> for GraphDto {
     fn from(cg: CallGraph) -> Self {
-        let nodes = cg.nodes.iter().map(|n| {
-            NodeDto {
-                id: n.id.clone(),
-                label: n.label.clone().unwrap_or_else(|| n.id.clone()),
-                package: None, // Mr. Hedgehog domain doesn't reliably store package yet
-                location: None, // Location info is deep in SourceManager, optional for now.
-            }
-        }).collect();
+        GraphDto::from(&cg)
+    }
+}
+
+impl From<&CallGraph> for GraphDto {
+    fn from(cg: &CallGraph) -> Self {
+        let nodes = cg.nodes.iter().map(NodeDto::from_node).collect();
 
+        // Repeated calls to one callee become one edge carrying their count
         let mut edges = Vec::new();
         for node in &cg.nodes {
-            for callee in &node.callees {
-                edges.push(EdgeDto {
-                    from: node.id.clone(),
-                    to: callee.clone(),
-                    label: Some("call".to_string()),
-                });
+            for (callee, weight) in node.weighted_callees() {
+                edges.push(EdgeDto { weight, ..EdgeDto::from_edge(&node.id, callee) });
             }
         }
 
         GraphDto { nodes, edges }
     }
 }
+
+impl GraphDto {
+    /// Set each node's `url` to its location under `links`.
+    pub fn with_links(mut self, links: &SourceLinks) -> Self {
+        for node in &mut self.nodes {
+            node.url = node.location.as_deref().and_then(|loc| links.location_url(loc));
+        }
+        self
+    }
+}
+
+/// Rebuild a CallGraph from exported JSON (e.g. `--format json` output).
+impl From<GraphDto> for CallGraph {
+    fn from(dto: GraphDto) -> Self {
+        let mut callees: HashMap<String, Vec<CallEdge>> = HashMap::new();
+        for e in dto.edges {
+            let mut edge = CallEdge::new(e.to);
+            if let Some((file, line)) = e.location.as_deref().and_then(|l| l.rsplit_once(':')) {
+                if let Ok(line) = line.parse() {
+                    edge = CallEdge::at(edge.target, file, line);
+                }
+            }
+            if let Some(kind) = e.label.as_deref().and_then(EdgeKind::parse) {
+                edge = edge.with_kind(kind);
+            }
+            edge = edge.with_weight(e.weight);
+            callees.entry(e.from).or_default().push(edge);
+        }
+        CallGraph::new(dto.nodes.into_iter().map(|n| CallGraphNode {
+            callees: callees.remove(&n.id).unwrap_or_default(),
+            id: n.id.into(),
+            label: Some(n.label),
+            is_public: n.is_public,
+            is_async: n.is_async,
+            is_unsafe: n.is_unsafe,
+            is_test: n.is_test,
+            is_test_fn: n.is_test_fn,
+            cfg: n.cfg,
+            external: n.external,
+            location: n.location,
+            metrics: n.metrics,
+            coverage: n.coverage,
+            unsafe_blocks: n.unsafe_blocks,
+            panics: n.panics,
+            signature: n.signature,
+            doc: n.doc,
+            type_kind: n.type_kind,
+            origin: n.origin,
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_roundtrip() {
+        let cg = CallGraph::new(vec![
+            CallGraphNode {
+                id: "app::run".into(),
+                callees: vec![
+                    CallEdge::at("app::helper", "src/lib.rs", 4).with_weight(3),
+                    CallEdge::new("Add::apply@app").with_kind(EdgeKind::Dynamic),
+                    CallEdge::at("app::run::{async#0}", "src/lib.rs", 5).with_kind(EdgeKind::Spawn),
+                ],
+                label: Some("app::run".to_string()),
+                is_public: true,
+                is_async: true,
+                ..Default::default()
+            },
+            CallGraphNode {
+                id: "app::helper".into(),
+                label: Some("app::helper".to_string()),
+                is_unsafe: true,
+                is_test: true,
+                cfg: Some("unix".to_string()),
+                ..Default::default()
+            },
+        ]);
+
+        let back = CallGraph::from(GraphDto::from(&cg));
+        assert_eq!(back.nodes.len(), 2);
+        assert_eq!(back.nodes[0].callees, cg.nodes[0].callees);
+        assert!(back.nodes[0].is_async && back.nodes[0].is_public);
+        assert!(back.nodes[1].is_unsafe && back.nodes[1].is_test);
+        assert_eq!(back.nodes[1].cfg.as_deref(), Some("unix"));
+        assert!(back.nodes[1].callees.is_empty());
+    }
+
+    #[test]
+    fn test_repeated_calls_fold_into_one_weighted_edge() {
+        let cg = CallGraph::new(vec![CallGraphNode {
+            id: "app::run".into(),
+            callees: vec![
+                CallEdge::at("app::log", "src/lib.rs", 2),
+                CallEdge::at("app::log", "src/lib.rs", 3),
+                CallEdge::at("app::log", "src/lib.rs", 4).with_kind(EdgeKind::Reference),
+            ],
+            ..Default::default()
+        }]);
+
+        let edges: Vec<(Option<String>, usize)> = GraphDto::from(&cg).edges.into_iter()
+            .map(|e| (e.location, e.weight))
+            .collect();
+        assert_eq!(edges, vec![(Some("src/lib.rs:2".to_string()), 2), (Some("src/lib.rs:4".to_string()), 1)]);
+    }
+}
@@ -0,0 +1,228 @@
+//! HTTP API
+//!
+//! Read-only JSON endpoints over a pre-built call graph, for editor plugins
+//! and web UIs:
+//!
+//! - `GET /graph` - the whole graph ([`GraphDto`])
+//! - `GET /node/{id}` - one node with its callees and callers ([`NodeDetailDto`])
+//! - `GET /callers/{id}` - edges into a node
+//! - `GET /trace?from={id}` - call paths starting at a node
+//!
+//! `{id}` may be a full node id or anything `resolve_symbol` accepts
+//! (e.g. a bare function name), percent-encoded.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use crate::api::dto::{EdgeDto, GraphDto, NodeDetailDto, NodeDto};
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::paths;
+use crate::domain::search::resolve_symbol;
+use crate::domain::source::SourceSet;
+use crate::domain::trace::TraceGenerator;
+use crate::infrastructure::source_manager::SourceManager;
+
+/// Graph plus the sources used for trace snippets.
+pub struct GraphService {
+    graph: CallGraph,
+    sources: SourceManager,
+}
+
+impl GraphService {
+    pub fn new(graph: CallGraph, sources: &SourceSet) -> Self {
+        Self { graph, sources: SourceManager::new(sources) }
+    }
+
+    /// Answer a request; returns the HTTP status and JSON body.
+    pub fn handle(&self, method: &str, target: &str) -> (u16, serde_json::Value) {
+        if method != "GET" {
+            return (405, json!({ "error": format!("Method {} not allowed", method) }));
+        }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = parse_query(query);
+        let segments: Vec<&str> = path.trim_matches('/').splitn(2, '/').collect();
+
+        match segments.as_slice() {
+            ["graph"] => (200, to_json(&GraphDto::from(&self.graph))),
+            ["node", id] => match self.lookup(id) {
+                Ok(node) => (200, to_json(&NodeDetailDto {
+                    node: NodeDto::from_node(node),
+                    callees: node.callees.iter().map(|e| EdgeDto::from_edge(&node.id, e)).collect(),
+                    callers: self.callers(&node.id),
+                })),
+                Err(err) => err,
+            },
+            ["callers", id] => match self.lookup(id) {
+                Ok(node) => (200, to_json(&self.callers(&node.id))),
+                Err(err) => err,
+            },
+            ["trace"] => {
+                let Some(from) = query.get("from") else {
+                    return (400, json!({ "error": "Missing 'from' query parameter" }));
+                };
+                match self.lookup(from) {
+                    Ok(node) => {
+                        let paths = TraceGenerator::new(&self.graph, &self.sources).generate_paths(&node.id);
+                        (200, json!({ "from": node.id, "paths": paths }))
+                    }
+                    Err(err) => err,
+                }
+            }
+            _ => (404, json!({ "error": format!("Unknown endpoint: {}", path) })),
+        }
+    }
+
+    fn lookup(&self, raw_id: &str) -> Result<&CallGraphNode, (u16, serde_json::Value)> {
+        let id = percent_decode(raw_id);
+        let resolved = resolve_symbol(&self.graph, &id)
+            .map_err(|e| (404, json!({ "error": e.to_string() })))?;
+        self.graph.nodes.iter()
+            .find(|n| n.id == resolved)
+            .ok_or_else(|| (404, json!({ "error": format!("Unknown node: {}", id) })))
+    }
+
+    fn callers(&self, id: &str) -> Vec<EdgeDto> {
+        self.graph.nodes.iter()
+            .flat_map(|n| n.callees.iter().filter(|e| e.target == id).map(|e| EdgeDto::from_edge(&n.id, e)))
+            .collect()
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).expect("DTOs are serializable")
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter(|p| !p.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes and `+` (as space); malformed escapes are kept verbatim.
+fn percent_decode(s: &str) -> String {
+    String::from_utf8_lossy(&paths::percent_decode(&s.replace('+', " "))).into_owned()
+}
+
+/// Serve `service` on `address` until the process exits.
+pub fn serve(service: GraphService, address: &str) -> Result<()> {
+    let listener = TcpListener::bind(address)
+        .with_context(|| format!("Failed to bind to {}", address))?;
+    println!("HTTP API listening on http://{}", listener.local_addr()?);
+    serve_on(listener, Arc::new(service));
+    Ok(())
+}
+
+/// Accept loop over an already bound listener (one thread per connection).
+pub fn serve_on(listener: TcpListener, service: Arc<GraphService>) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let service = service.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &service) {
+                        tracing::warn!("[HTTP] Connection error: {}", e);
+                    }
+                });
+            }
+            Err(e) => tracing::warn!("[HTTP] Accept error: {}", e),
+        }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, service: &GraphService) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers are not needed; drain them up to the blank line
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => service.handle(method, target),
+        _ => (400, json!({ "error": "Malformed request line" })),
+    };
+
+    let body = serde_json::to_string(&body)?;
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    use crate::domain::callgraph::CallEdge;
+
+    fn service() -> GraphService {
+        let node = |id: &str, callees: Vec<CallEdge>| CallGraphNode { callees, ..fixtures::node(id, &[]) };
+        let cg = CallGraph::new(vec![
+            node("app::main", vec![CallEdge::at("app::helper", "src/main.rs", 2)]),
+            node("app::helper", vec![CallEdge::at("Tool::run@app", "src/main.rs", 5)]),
+            node("Tool::run@app", vec![]),
+        ]);
+        GraphService::new(cg, &SourceSet::default())
+    }
+
+    #[test]
+    fn test_routes() {
+        let svc = service();
+
+        let (status, graph) = svc.handle("GET", "/graph");
+        assert_eq!(status, 200);
+        assert_eq!(graph["nodes"].as_array().unwrap().len(), 3);
+
+        // Bare names resolve like --entry; ids are percent-decoded
+        let (status, detail) = svc.handle("GET", "/node/helper");
+        assert_eq!(status, 200);
+        assert_eq!(detail["node"]["id"], "app::helper");
+        assert_eq!(detail["callers"][0]["from"], "app::main");
+        assert_eq!(detail["callees"][0]["location"], "src/main.rs:5");
+
+        let (status, callers) = svc.handle("GET", "/callers/Tool%3A%3Arun%40app");
+        assert_eq!(status, 200);
+        assert_eq!(callers[0]["from"], "app::helper");
+
+        let (status, trace) = svc.handle("GET", "/trace?from=app%3A%3Amain");
+        assert_eq!(status, 200);
+        let steps: Vec<&str> = trace["paths"][0]["steps"].as_array().unwrap()
+            .iter().map(|s| s["id"].as_str().unwrap()).collect();
+        assert_eq!(steps, vec!["app::main", "app::helper", "Tool::run@app"]);
+
+        assert_eq!(svc.handle("GET", "/trace").0, 400);
+        assert_eq!(svc.handle("GET", "/node/nope").0, 404);
+        assert_eq!(svc.handle("GET", "/nope").0, 404);
+        assert_eq!(svc.handle("POST", "/graph").0, 405);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%3A%3Ab+c"), "a::b c");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("a%2Bb%20c"), "a+b c");
+    }
+}
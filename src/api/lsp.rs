@@ -0,0 +1,414 @@
+//! Language Server
+//!
+//! `lsp` speaks the Language Server Protocol on stdin/stdout and answers the
+//! call hierarchy requests from the analyzed graph, so editors browse the
+//! same graph the exports show (SCIP-precise with `--scip`):
+//!
+//! - `textDocument/prepareCallHierarchy` - the function called, or else the
+//!   one defined, at the cursor
+//! - `callHierarchy/incomingCalls` - its callers, with the call sites
+//! - `callHierarchy/outgoingCalls` - its callees, with the call sites
+//!
+//! The graph is built once at startup; edits made afterwards are not
+//! re-analyzed.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode};
+use crate::domain::paths::uri_to_path;
+use crate::domain::search::display_path;
+use crate::domain::source::SourceSet;
+use crate::infrastructure::source_manager::SourceManager;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+/// `SymbolKind.Function`
+const FUNCTION: u32 = 12;
+/// `TextDocumentSyncKind.Full`
+const FULL_SYNC: u32 = 1;
+
+/// The graph and what the client told us: workspace root and open documents.
+pub struct LspServer {
+    graph: CallGraph,
+    nodes: HashMap<String, usize>,
+    /// First and last line of each node's definition, by node index.
+    extents: Vec<Option<(usize, usize)>>,
+    /// Distinct files of the node locations.
+    files: Vec<String>,
+    /// Relative source paths are taken from here.
+    root: PathBuf,
+    /// Text of open documents by URI.
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl LspServer {
+    pub fn new(graph: CallGraph, sources: &SourceSet) -> Self {
+        let mut nodes = HashMap::new();
+        let mut files: Vec<String> = Vec::new();
+        let sources = SourceManager::new(sources);
+        let mut extents = Vec::with_capacity(graph.nodes.len());
+        for (i, node) in graph.nodes.iter().enumerate() {
+            nodes.entry(node.id.to_string()).or_insert(i);
+            let location = node.location.as_deref().and_then(split_location);
+            if let Some((file, _)) = location {
+                if !files.iter().any(|f| f == file) {
+                    files.push(file.to_string());
+                }
+            }
+            extents.push(location.map(|(file, start)| (start, sources.item_end(file, start).unwrap_or(start))));
+        }
+        Self {
+            graph,
+            nodes,
+            extents,
+            files,
+            root: std::env::current_dir().unwrap_or_default(),
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Serve messages from `input` until `exit` or end of input; whether
+    /// the client asked for `shutdown` first.
+    pub fn run(mut self, mut input: impl BufRead, mut output: impl Write) -> Result<bool> {
+        while let Some(body) = read_message(&mut input)? {
+            let reply = match serde_json::from_slice::<Value>(&body) {
+                Ok(message) if message["method"] == "exit" => break,
+                Ok(message) => self.handle(&message),
+                Err(e) => Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": PARSE_ERROR, "message": e.to_string() } })),
+            };
+            if let Some(reply) = reply {
+                write_message(&mut output, &reply)?;
+            }
+        }
+        Ok(self.shutdown)
+    }
+
+    /// Answer one message; `None` for notifications.
+    pub fn handle(&mut self, message: &Value) -> Option<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let result = match method {
+            "initialize" => {
+                let root = params["rootUri"].as_str()
+                    .or_else(|| params["workspaceFolders"][0]["uri"].as_str())
+                    .and_then(uri_to_path);
+                if let Some(root) = root {
+                    self.root = root;
+                }
+                Ok(json!({
+                    "capabilities": { "callHierarchyProvider": true, "textDocumentSync": FULL_SYNC },
+                    "serverInfo": { "name": "mr_hedgehog", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = params["textDocument"]["text"].as_str()
+                    .or_else(|| params["contentChanges"].as_array()?.last()?["text"].as_str());
+                if let (Some(uri), Some(text)) = (params["textDocument"]["uri"].as_str(), text) {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                return None;
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.documents.remove(uri);
+                }
+                return None;
+            }
+            "textDocument/prepareCallHierarchy" => self.prepare(params),
+            "callHierarchy/incomingCalls" => self.incoming(params),
+            "callHierarchy/outgoingCalls" => self.outgoing(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method {}", method))),
+        };
+        // Notifications (`initialized`, `$/cancelRequest`, ..) get no reply
+        let id = message.get("id")?.clone();
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+        })
+    }
+
+    fn prepare(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let (Some(line), Some(character)) = (params["position"]["line"].as_u64(), params["position"]["character"].as_u64()) else {
+            return Err((INVALID_PARAMS, "missing position".to_string()));
+        };
+        let (line, character) = (line as usize + 1, character as usize);
+        let Some(file) = self.file_for(uri) else {
+            return Ok(Value::Null);
+        };
+
+        // Innermost function whose body spans the cursor
+        let enclosing = self.graph.nodes.iter().zip(&self.extents)
+            .filter_map(|(n, extent)| {
+                let (f, _) = split_location(n.location.as_deref()?)?;
+                let (start, end) = (*extent)?;
+                (f == file && start <= line && line <= end).then_some((start, n))
+            })
+            .max_by_key(|(start, _)| *start)
+            .map(|(_, n)| n);
+        let Some(enclosing) = enclosing else {
+            return Ok(Value::Null);
+        };
+
+        // A call at the cursor names its callee
+        let text = self.documents.get(uri).cloned()
+            .or_else(|| std::fs::read_to_string(uri_to_path(uri)?).ok());
+        let word = text.as_deref().and_then(|t| t.lines().nth(line - 1)).and_then(|l| word_at(l, character));
+        let called = word.and_then(|word| {
+            enclosing.callees.iter()
+                .filter(|e| e.line == Some(line))
+                .filter_map(|e| self.node(&e.target))
+                .find(|n| name(&n.id) == word)
+        });
+        Ok(self.item(called.unwrap_or(enclosing)).map_or(Value::Null, |item| json!([item])))
+    }
+
+    fn incoming(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let id = item_id(params)?;
+        let calls: Vec<Value> = self.graph.nodes.iter()
+            .filter_map(|caller| {
+                let sites: Vec<&CallEdge> = caller.callees.iter().filter(|e| e.target == id).collect();
+                if sites.is_empty() {
+                    return None;
+                }
+                Some(json!({ "from": self.item(caller)?, "fromRanges": call_ranges(&sites) }))
+            })
+            .collect();
+        Ok(Value::Array(calls))
+    }
+
+    fn outgoing(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let id = item_id(params)?;
+        let node = self.node(id).ok_or_else(|| (INVALID_PARAMS, format!("unknown function {}", id)))?;
+        let mut targets: Vec<&str> = Vec::new();
+        for callee in node.callee_ids() {
+            if !targets.contains(&callee) {
+                targets.push(callee);
+            }
+        }
+        // Callees without a location (std, other crates) have nothing to open
+        let calls: Vec<Value> = targets.into_iter()
+            .filter_map(|target| {
+                let sites: Vec<&CallEdge> = node.callees.iter().filter(|e| e.target == target).collect();
+                Some(json!({ "to": self.item(self.node(target)?)?, "fromRanges": call_ranges(&sites) }))
+            })
+            .collect();
+        Ok(Value::Array(calls))
+    }
+
+    fn node(&self, id: &str) -> Option<&CallGraphNode> {
+        self.nodes.get(id).map(|&i| &self.graph.nodes[i])
+    }
+
+    /// `CallHierarchyItem` for a node with a location.
+    fn item(&self, node: &CallGraphNode) -> Option<Value> {
+        let (file, line) = split_location(node.location.as_deref()?)?;
+        let end = self.nodes.get(node.id.as_str())
+            .and_then(|&i| self.extents[i])
+            .map_or(line, |(_, end)| end);
+        Some(json!({
+            "name": name(&node.id),
+            "kind": FUNCTION,
+            "detail": node.id,
+            "uri": path_to_uri(&self.root.join(file)),
+            "range": range(line, end),
+            "selectionRange": range(line, line),
+            "data": { "id": node.id },
+        }))
+    }
+
+    /// The graph's spelling of the file behind `uri`.
+    fn file_for(&self, uri: &str) -> Option<&str> {
+        let path = uri_to_path(uri)?;
+        self.files.iter()
+            .find(|f| self.root.join(f.as_str()) == path)
+            .or_else(|| self.files.iter().find(|f| Path::new(f.as_str()).is_relative() && path.ends_with(f.as_str())))
+            .map(String::as_str)
+    }
+}
+
+fn item_id(params: &Value) -> Result<&str, (i64, String)> {
+    params["item"]["data"]["id"].as_str()
+        .or_else(|| params["item"]["detail"].as_str())
+        .ok_or((INVALID_PARAMS, "missing item.data.id".to_string()))
+}
+
+/// Function name of a node id: `app::Parser::parse@app` -> `parse`.
+fn name(id: &str) -> &str {
+    let path = display_path(id);
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// `file:line` -> (`file`, `line`).
+fn split_location(location: &str) -> Option<(&str, usize)> {
+    let (file, line) = location.rsplit_once(':')?;
+    Some((file, line.parse().ok()?))
+}
+
+/// Whole lines `start..=end` (1-based) as an LSP range.
+fn range(start: usize, end: usize) -> Value {
+    json!({ "start": { "line": start.saturating_sub(1), "character": 0 }, "end": { "line": end, "character": 0 } })
+}
+
+fn call_ranges(sites: &[&CallEdge]) -> Vec<Value> {
+    sites.iter().filter_map(|e| e.line).map(|line| range(line, line)).collect()
+}
+
+/// Identifier touching column `character` of `line`.
+fn word_at(line: &str, character: usize) -> Option<&str> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    // LSP columns count UTF-16 units; identifiers here are ASCII in practice
+    let at = line.char_indices().nth(character).map_or(line.len(), |(i, _)| i);
+    let start = line[..at].rfind(|c: char| !is_ident(c)).map_or(0, |i| i + 1);
+    let end = line[at..].find(|c: char| !is_ident(c)).map_or(line.len(), |i| at + i);
+    Some(&line[start..end]).filter(|w| !w.is_empty())
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(b as char),
+            _ => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+/// Body of the next `Content-Length` framed message, `None` at end of input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>().context("bad Content-Length")?);
+            }
+        }
+    }
+    let mut body = vec![0; length.context("message without Content-Length")?];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::source::SourceFile;
+    use crate::infrastructure::SimpleCallGraphBuilder;
+    use crate::ports::CallGraphBuilder;
+
+    const CODE: &str = "fn main() {\n    parse();\n}\n\nfn parse() {\n    emit();\n    std::mem::drop(1);\n}\n\nfn emit() {}\n";
+    const URI: &str = "file:///proj/src/main.rs";
+
+    fn server() -> LspServer {
+        let files = SourceSet::new(vec![SourceFile::new("app", "src/main.rs", CODE)]);
+        let mut server = LspServer::new(SimpleCallGraphBuilder::new().build_call_graph(&files), &files);
+        server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "rootUri": "file:///proj" } }));
+        server.handle(&json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "languageId": "rust", "version": 1, "text": CODE } } }));
+        server
+    }
+
+    fn request(server: &mut LspServer, method: &str, params: Value) -> Value {
+        server.handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": method, "params": params })).unwrap()["result"].clone()
+    }
+
+    fn prepare(server: &mut LspServer, line: usize, character: usize) -> Value {
+        request(server, "textDocument/prepareCallHierarchy",
+            json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } }))
+    }
+
+    #[test]
+    fn test_prepare_call_hierarchy() {
+        let mut server = server();
+        // On the call to emit(), then elsewhere in parse's body
+        let emit = prepare(&mut server, 5, 6);
+        assert_eq!(emit[0]["detail"], "app::emit");
+        assert_eq!(emit[0]["uri"], URI);
+        assert_eq!(emit[0]["range"]["start"]["line"], 9);
+        assert_eq!(prepare(&mut server, 7, 0)[0]["detail"], "app::parse");
+        assert_eq!(prepare(&mut server, 7, 0)[0]["range"]["end"]["line"], 8);
+        assert_eq!(prepare(&mut server, 3, 0), Value::Null);
+    }
+
+    #[test]
+    fn test_incoming_and_outgoing_calls() {
+        let mut server = server();
+        let parse = prepare(&mut server, 4, 4)[0].clone();
+        assert_eq!(parse["name"], "parse");
+
+        let incoming = request(&mut server, "callHierarchy/incomingCalls", json!({ "item": parse }));
+        assert_eq!(incoming[0]["from"]["detail"], "app::main");
+        assert_eq!(incoming[0]["fromRanges"][0]["start"]["line"], 1);
+
+        // std::mem::drop has no location and is left out
+        let outgoing = request(&mut server, "callHierarchy/outgoingCalls", json!({ "item": parse }));
+        assert_eq!(outgoing.as_array().unwrap().len(), 1);
+        assert_eq!(outgoing[0]["to"]["detail"], "app::emit");
+        assert_eq!(outgoing[0]["fromRanges"][0]["start"]["line"], 5);
+    }
+
+    #[test]
+    fn test_framing_and_lifecycle() {
+        let frame = |message: Value| format!("Content-Length: {}\r\n\r\n{}", message.to_string().len(), message);
+        let input = [
+            frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" })),
+            frame(json!({ "jsonrpc": "2.0", "method": "exit" })),
+        ].concat();
+        let files = SourceSet::new(Vec::new());
+        let mut output = Vec::new();
+        assert!(LspServer::new(CallGraph::new(Vec::new()), &files).run(input.as_bytes(), &mut output).unwrap());
+
+        let mut output = output.as_slice();
+        let mut replies = Vec::new();
+        while let Some(body) = read_message(&mut output).unwrap() {
+            replies.push(serde_json::from_slice::<Value>(&body).unwrap());
+        }
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["result"]["capabilities"]["callHierarchyProvider"], true);
+        assert_eq!(replies[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(replies[2]["id"], 3);
+    }
+
+    #[test]
+    fn test_uris() {
+        let path = Path::new("/my proj/src/main.rs");
+        assert_eq!(path_to_uri(path), "file:///my%20proj/src/main.rs");
+        assert_eq!(uri_to_path(&path_to_uri(path)).as_deref(), Some(path));
+        assert_eq!(word_at("    emit();", 6), Some("emit"));
+        assert_eq!(word_at("    emit();", 8), Some("emit"));
+        assert_eq!(word_at("    emit();", 10), None);
+        // A call site without a usable line doesn't underflow
+        assert_eq!(range(0, 0)["start"]["line"], 0);
+    }
+}
@@ -1,2 +1,6 @@
 pub mod dto;
 pub mod server;
+pub mod http;
+pub mod lsp;
+pub mod repl;
+pub mod tui;
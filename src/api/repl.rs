@@ -0,0 +1,276 @@
+//! Interactive Shell
+//!
+//! `repl` analyzes once, then answers questions about the graph until the
+//! user quits, instead of re-running the whole analysis per question:
+//!
+//! ```text
+//! > callers send
+//! > path main send
+//! > show send
+//! > export send.dot send 2
+//! ```
+//!
+//! Symbols are resolved like `--entry` (bare names work), and complete with
+//! Tab.
+
+use std::borrow::Cow;
+
+use anyhow::Result;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::{Context, Editor, Helper, Highlighter, Hinter, Validator};
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::filter::neighborhood;
+use crate::domain::search::{find_symbols, format_matches, resolve_symbol, MatchMode};
+use crate::domain::source::SourceSet;
+use crate::domain::trace::TraceGenerator;
+use crate::infrastructure::source_manager::SourceManager;
+use crate::infrastructure::DotExporter;
+use crate::ports::html_exporter::HtmlExporter;
+use crate::ports::json_exporter::JsonExporter;
+use crate::ports::sqlite_exporter::SqliteExporter;
+use crate::ports::{format_for_path, OutputExporter};
+
+const COMMANDS: [&str; 9] = ["callers", "callees", "path", "show", "find", "export", "help", "quit", "exit"];
+
+const HELP: &str = "\
+callers <fn>                 functions calling <fn>
+callees <fn>                 functions <fn> calls
+path <from> <to>             call paths from one function to another
+show <fn>                    source of <fn>
+find <text>                  functions whose id contains <text>
+export <file> [<fn> [depth]] write the graph, or the part within depth (default 2) of <fn>;
+                             the format follows the extension (.dot, .json, .html, .db)
+quit                         leave
+";
+
+/// Picks the exporter for an output path, `None` for unknown extensions.
+pub type ExporterFor<'a> = Box<dyn Fn(&str) -> Option<Box<dyn OutputExporter>> + 'a>;
+
+/// A loaded graph and the commands that query it.
+pub struct ReplSession<'a> {
+    graph: CallGraph,
+    sources: SourceManager,
+    exporter: ExporterFor<'a>,
+}
+
+impl<'a> ReplSession<'a> {
+    pub fn new(graph: CallGraph, sources: &SourceSet) -> Self {
+        Self { graph, sources: SourceManager::new(sources), exporter: Box::new(default_exporter) }
+    }
+
+    /// Use `exporter` for `export` (e.g. one honoring the DOT style flags).
+    pub fn with_exporter(mut self, exporter: impl Fn(&str) -> Option<Box<dyn OutputExporter>> + 'a) -> Self {
+        self.exporter = Box::new(exporter);
+        self
+    }
+
+    /// Run one command line; the text to print, or what went wrong.
+    pub fn execute(&self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            ["help"] => Ok(HELP.to_string()),
+            ["callers", name] => {
+                let node = self.lookup(name)?;
+                let mut lines: Vec<String> = self.graph.nodes.iter()
+                    .flat_map(|caller| caller.callees.iter().filter(|e| e.target == node.id).map(move |e| (caller, e)))
+                    .map(|(caller, e)| edge_line(&caller.id, e.kind.as_str(), e.location()))
+                    .collect();
+                lines.sort();
+                Ok(listing(lines, &format!("nothing calls {}", node.id)))
+            }
+            ["callees", name] => {
+                let node = self.lookup(name)?;
+                let lines = node.callees.iter().map(|e| edge_line(&e.target, e.kind.as_str(), e.location())).collect();
+                Ok(listing(lines, &format!("{} calls nothing", node.id)))
+            }
+            ["path", from, to] => {
+                let (from, to) = (self.lookup(from)?, self.lookup(to)?);
+                let paths = TraceGenerator::new(&self.graph, &self.sources).paths_between(&from.id, &to.id);
+                if paths.is_empty() {
+                    return Ok(format!("No call path from {} to {}\n", from.id, to.id));
+                }
+                let mut out = String::new();
+                for (i, path) in paths.iter().enumerate() {
+                    out.push_str(&format!("Path {}:\n", i + 1));
+                    for step in &path.steps {
+                        match &step.location {
+                            Some(location) if step.depth > 0 => out.push_str(&format!("  -> {} ({})\n", step.id, location)),
+                            _ => out.push_str(&format!("  {}\n", step.id)),
+                        }
+                    }
+                }
+                Ok(out)
+            }
+            ["show", name] => {
+                let node = self.lookup(name)?;
+                let location = node.location.as_deref().ok_or_else(|| format!("{} has no source location", node.id))?;
+                let body = location.rsplit_once(':')
+                    .and_then(|(file, line)| self.sources.get_function_body(file, line.parse().ok()?))
+                    .ok_or_else(|| format!("source of {} ({}) is not loaded", node.id, location))?;
+                Ok(format!("// {} ({})\n{}\n", node.id, location, body))
+            }
+            ["find", text] => {
+                let matches = find_symbols(&self.graph, text, MatchMode::Substring, true).map_err(|e| e.to_string())?;
+                if matches.is_empty() {
+                    return Ok(format!("no function matches '{}'\n", text));
+                }
+                Ok(format_matches(&matches))
+            }
+            ["export", path, rest @ ..] => {
+                let graph = match rest {
+                    [] => Cow::Borrowed(&self.graph),
+                    [name] | [name, _] => {
+                        let depth = match rest.get(1) {
+                            Some(depth) => depth.parse().map_err(|_| format!("depth must be a number, got {}", depth))?,
+                            None => 2,
+                        };
+                        Cow::Owned(neighborhood(&self.graph, &self.lookup(name)?.id, depth))
+                    }
+                    _ => return Err("usage: export <file> [<fn> [depth]]".to_string()),
+                };
+                let exporter = (self.exporter)(path)
+                    .ok_or_else(|| format!("unknown format for {} (expected .dot, .json, .html or .db)", path))?;
+                exporter.export(&graph, path).map_err(|e| format!("failed to write {}: {}", path, e))?;
+                Ok(format!("Saved {} nodes to {}\n", graph.nodes.len(), path))
+            }
+            [command, ..] if COMMANDS.contains(command) => Err(format!("wrong arguments for {}; type help", command)),
+            [command, ..] => Err(format!("unknown command {}; type help", command)),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Result<&CallGraphNode, String> {
+        let id = resolve_symbol(&self.graph, name).map_err(|e| e.to_string())?;
+        self.graph.nodes.iter().find(|n| n.id == id).ok_or_else(|| format!("no function matches '{}'", name))
+    }
+}
+
+/// `--format`-style exporter choice by extension, with default DOT styling.
+fn default_exporter(path: &str) -> Option<Box<dyn OutputExporter>> {
+    match format_for_path(path)? {
+        "dot" => Some(Box::new(DotExporter::default())),
+        "json" => Some(Box::new(JsonExporter)),
+        "html" => Some(Box::new(HtmlExporter::default())),
+        "sqlite" => Some(Box::new(SqliteExporter)),
+        _ => None,
+    }
+}
+
+fn edge_line(id: &str, kind: &str, location: Option<String>) -> String {
+    match location {
+        Some(location) => format!("  {} [{}] {}", id, kind, location),
+        None => format!("  {} [{}]", id, kind),
+    }
+}
+
+fn listing(lines: Vec<String>, empty: &str) -> String {
+    if lines.is_empty() {
+        return format!("{}\n", empty);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Tab completion: command names first, node ids after.
+#[derive(Helper, Highlighter, Hinter, Validator)]
+struct ReplHelper {
+    ids: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        if start == 0 {
+            return Ok((0, COMMANDS.iter().filter(|c| c.starts_with(word)).map(|c| c.to_string()).collect()));
+        }
+        // Full ids, or the ids whose function name starts with the word
+        let mut candidates: Vec<String> = self.ids.iter()
+            .filter(|id| id.starts_with(word) || id.rsplit("::").next().is_some_and(|name| name.starts_with(word)))
+            .cloned()
+            .collect();
+        candidates.dedup();
+        Ok((start, candidates))
+    }
+}
+
+/// Read commands from the terminal until `quit` or end of input.
+pub fn run(session: &ReplSession) -> Result<()> {
+    let mut ids: Vec<String> = session.graph.nodes.iter().map(|n| n.id.to_string()).collect();
+    ids.sort();
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper { ids }));
+    println!("{} functions loaded; type help for commands, Tab to complete names", session.graph.nodes.len());
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+        if line == "quit" || line == "exit" {
+            return Ok(());
+        }
+        match session.execute(line) {
+            Ok(text) => print!("{}", text),
+            Err(message) => eprintln!("Error: {}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::source::SourceFile;
+    use crate::infrastructure::SimpleCallGraphBuilder;
+    use crate::ports::CallGraphBuilder;
+
+    fn session() -> ReplSession<'static> {
+        let code = "fn main() {\n    parse();\n}\n\nfn parse() {\n    emit();\n}\n\nfn emit() {}\n";
+        let files = SourceSet::new(vec![SourceFile::new("app", "src/main.rs", code)]);
+        ReplSession::new(SimpleCallGraphBuilder::new().build_call_graph(&files), &files)
+    }
+
+    #[test]
+    fn test_queries() {
+        let repl = session();
+        assert_eq!(repl.execute("callers emit").unwrap(), "  app::parse [call] src/main.rs:6\n");
+        assert_eq!(repl.execute("callees main").unwrap(), "  app::parse [call] src/main.rs:2\n");
+        assert_eq!(repl.execute("callers main").unwrap(), "nothing calls app::main\n");
+        assert_eq!(repl.execute("path main emit").unwrap(), "Path 1:\n  app::main\n  -> app::parse (src/main.rs:2)\n  -> app::emit (src/main.rs:6)\n");
+        assert_eq!(repl.execute("show parse").unwrap(), "// app::parse (src/main.rs:5)\nfn parse() {\n    emit();\n}\n");
+        assert!(repl.execute("find PAR").unwrap().starts_with("app::parse"));
+        assert!(repl.execute("callers nope").unwrap_err().contains("no function matches"));
+        assert!(repl.execute("frobnicate").unwrap_err().starts_with("unknown command"));
+    }
+
+    #[test]
+    fn test_export_subgraph() {
+        let repl = session();
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("sub.json");
+        let path = path.to_str().unwrap();
+        assert_eq!(repl.execute(&format!("export {} emit 1", path)).unwrap(), format!("Saved 2 nodes to {}\n", path));
+        assert!(std::fs::read_to_string(path).unwrap().contains("app::parse"));
+        assert!(repl.execute("export graph.png").unwrap_err().contains("unknown format"));
+    }
+
+    #[test]
+    fn test_completion() {
+        let helper = ReplHelper { ids: vec!["app::emit".to_string(), "app::main".to_string(), "app::parse".to_string()] };
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        assert_eq!(helper.complete("cal", 3, &ctx).unwrap(), (0, vec!["callers".to_string(), "callees".to_string()]));
+        assert_eq!(helper.complete("show pa", 7, &ctx).unwrap(), (5, vec!["app::parse".to_string()]));
+        assert_eq!(helper.complete("path app::m", 11, &ctx).unwrap(), (5, vec!["app::main".to_string()]));
+    }
+}
@@ -26,11 +26,11 @@ pub fn start_server(port: u16) -> Result<()> {
             Ok(stream) => {
                 thread::spawn(move || {
                     if let Err(e) = handle_connection(stream) {
-                        eprintln!("[API] Connection error: {}", e);
+                        tracing::warn!("[API] Connection error: {}", e);
                     }
                 });
             }
-            Err(e) => eprintln!("[API] Accept error: {}", e),
+            Err(e) => tracing::warn!("[API] Accept error: {}", e),
         }
     }
 
@@ -74,7 +74,7 @@ fn handle_connection(mut stream: TcpStream) -> Result<()> {
         // Actually command logic might want to terminate the process.
         if let Ok(req) = serde_json::from_str::<CommandReq>(trimmed) {
              if req.command == "SHUTDOWN" {
-                 println!("[API] Shutdown requested.");
+                 tracing::info!("[API] Shutdown requested.");
                  std::process::exit(0);
              }
         }
@@ -115,7 +115,7 @@ fn handle_analyze(params: Option<serde_json::Value>) -> Result<serde_json::Value
          anyhow::bail!("Workspace path not found: {}", path_str);
     }
     
-    println!("[API] Analyzing: {}", path_str);
+    tracing::info!("[API] Analyzing: {}", path_str);
     
     // 1. Generate SCIP index
     // Assume Rust for now, or infer from params
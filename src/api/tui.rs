@@ -0,0 +1,456 @@
+//! Terminal Graph Browser
+//!
+//! `browse` shows the call hierarchy below an entry point as a collapsible
+//! tree, next to the source of the selected function:
+//!
+//! ```text
+//! ┌ app::main ──────────────┐┌ app::parse  src/main.rs:5 ───────┐
+//! │ ▾ app::main             ││ fn parse() {                     │
+//! │   ▸ app::parse          ││     emit();                      │
+//! │   · std::println        ││ }                                │
+//! └─────────────────────────┘└──────────────────────────────────┘
+//! ```
+//!
+//! [`TreeBrowser`] holds the navigation state and knows nothing about the
+//! terminal; [`run`] draws it with ratatui and maps keys onto it.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, List, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::infrastructure::source_manager::SourceManager;
+
+const KEYS: &str = " ←/→ collapse/expand · enter toggle · c callers · r root here · u back · b bookmark · B bookmarks · q quit ";
+
+/// One visible line of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRow {
+    /// Ids from the root down to this row.
+    pub path: Vec<String>,
+    pub expanded: bool,
+    /// Has callees to expand.
+    pub expandable: bool,
+    /// Already on the path above it: a recursive call, not expanded again.
+    pub recursive: bool,
+}
+
+impl TreeRow {
+    pub fn id(&self) -> &str {
+        self.path.last().map(String::as_str).unwrap_or_default()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+}
+
+/// Navigation state: the root, which rows are expanded, the selection,
+/// previous roots and bookmarks.
+pub struct TreeBrowser<'a> {
+    nodes: HashMap<&'a str, &'a CallGraphNode>,
+    callers: HashMap<&'a str, Vec<&'a str>>,
+    root: String,
+    expanded: HashSet<Vec<String>>,
+    rows: Vec<TreeRow>,
+    selected: usize,
+    /// Roots left by `reroot`, most recent last.
+    history: Vec<String>,
+    bookmarks: Vec<String>,
+}
+
+impl<'a> TreeBrowser<'a> {
+    /// A tree rooted at `root`, expanded one level.
+    pub fn new(graph: &'a CallGraph, root: &str) -> Self {
+        let nodes: HashMap<&str, &CallGraphNode> = graph.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for node in &graph.nodes {
+            for callee in node.callee_ids() {
+                let list = callers.entry(callee).or_default();
+                if !list.contains(&node.id.as_str()) {
+                    list.push(&node.id);
+                }
+            }
+        }
+        for list in callers.values_mut() {
+            list.sort();
+        }
+        let mut browser = Self {
+            nodes,
+            callers,
+            root: root.to_string(),
+            expanded: HashSet::from([vec![root.to_string()]]),
+            rows: Vec::new(),
+            selected: 0,
+            history: Vec::new(),
+            bookmarks: Vec::new(),
+        };
+        browser.rebuild();
+        browser
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn rows(&self) -> &[TreeRow] {
+        &self.rows
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected(&self) -> &TreeRow {
+        &self.rows[self.selected]
+    }
+
+    pub fn node(&self, id: &str) -> Option<&'a CallGraphNode> {
+        self.nodes.get(id).copied()
+    }
+
+    /// Distinct callees of `id`, in call order.
+    fn children(&self, id: &str) -> Vec<&'a str> {
+        let mut children: Vec<&str> = Vec::new();
+        if let Some(node) = self.nodes.get(id) {
+            for callee in node.callee_ids() {
+                if !children.contains(&callee) {
+                    children.push(callee);
+                }
+            }
+        }
+        children
+    }
+
+    fn rebuild(&mut self) {
+        let mut rows = Vec::new();
+        self.visit(vec![self.root.clone()], &mut rows);
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len() - 1);
+    }
+
+    fn visit(&self, path: Vec<String>, rows: &mut Vec<TreeRow>) {
+        let id = path.last().expect("paths are never empty");
+        let children = self.children(id);
+        let recursive = path[..path.len() - 1].contains(id);
+        let expandable = !children.is_empty() && !recursive;
+        let expanded = expandable && self.expanded.contains(&path);
+        rows.push(TreeRow { path: path.clone(), expanded, expandable, recursive });
+        if expanded {
+            for child in children {
+                let mut child_path = path.clone();
+                child_path.push(child.to_string());
+                self.visit(child_path, rows);
+            }
+        }
+    }
+
+    /// Move the selection by `delta` rows, stopping at either end.
+    pub fn move_by(&mut self, delta: isize) {
+        self.selected = self.selected.saturating_add_signed(delta).min(self.rows.len() - 1);
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected = self.rows.len() - 1;
+    }
+
+    /// Expand the selected row, or step into it if it already is.
+    pub fn expand(&mut self) {
+        let row = self.selected().clone();
+        if !row.expandable {
+            return;
+        }
+        if row.expanded {
+            self.move_by(1);
+        } else {
+            self.expanded.insert(row.path);
+            self.rebuild();
+        }
+    }
+
+    /// Collapse the selected row, or select its parent if it is collapsed.
+    pub fn collapse(&mut self) {
+        let row = self.selected().clone();
+        if row.expanded {
+            self.expanded.remove(&row.path);
+            self.rebuild();
+        } else if row.depth() > 0 {
+            let parent = &row.path[..row.path.len() - 1];
+            if let Some(index) = self.rows[..self.selected].iter().rposition(|r| r.path == parent) {
+                self.selected = index;
+            }
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if self.selected().expanded {
+            self.collapse();
+        } else {
+            self.expand();
+        }
+    }
+
+    /// Show the tree below `id` instead; [`back`](Self::back) returns.
+    pub fn reroot(&mut self, id: &str) {
+        let previous = std::mem::replace(&mut self.root, id.to_string());
+        self.history.push(previous);
+        self.expanded = HashSet::from([vec![id.to_string()]]);
+        self.selected = 0;
+        self.rebuild();
+    }
+
+    /// Return to the root before the last [`reroot`](Self::reroot).
+    pub fn back(&mut self) {
+        if let Some(root) = self.history.pop() {
+            self.expanded = HashSet::from([vec![root.clone()]]);
+            self.root = root;
+            self.selected = 0;
+            self.rebuild();
+        }
+    }
+
+    /// Functions calling the selected one, sorted.
+    pub fn callers(&self) -> Vec<String> {
+        self.callers.get(self.selected().id()).map_or_else(Vec::new, |c| c.iter().map(|s| s.to_string()).collect())
+    }
+
+    /// Root the tree at `caller` with the previously selected function
+    /// selected right below it.
+    pub fn jump_to_caller(&mut self, caller: &str) {
+        let callee = self.selected().id().to_string();
+        self.reroot(caller);
+        if let Some(index) = self.rows.iter().position(|r| r.path.len() == 2 && r.path[1] == callee) {
+            self.selected = index;
+        }
+    }
+
+    /// Bookmark the selected function, or drop its bookmark.
+    pub fn toggle_bookmark(&mut self) {
+        let id = self.selected().id().to_string();
+        match self.bookmarks.iter().position(|b| *b == id) {
+            Some(index) => {
+                self.bookmarks.remove(index);
+            }
+            None => self.bookmarks.push(id),
+        }
+    }
+
+    pub fn bookmarks(&self) -> &[String] {
+        &self.bookmarks
+    }
+
+    pub fn is_bookmarked(&self, id: &str) -> bool {
+        self.bookmarks.iter().any(|b| b == id)
+    }
+}
+
+/// A list shown over the tree; choosing an entry roots the tree there.
+enum Popup {
+    Callers(Vec<String>, ListState),
+    Bookmarks(ListState),
+}
+
+/// Browse the graph below `entry` until the user quits; returns the
+/// bookmarked functions.
+pub fn run(graph: &CallGraph, sources: &SourceManager, entry: &str) -> Result<Vec<String>> {
+    let mut browser = TreeBrowser::new(graph, entry);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut browser, sources);
+    ratatui::restore();
+    result.map(|()| browser.bookmarks().to_vec())
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, browser: &mut TreeBrowser, sources: &SourceManager) -> Result<()> {
+    let mut popup: Option<Popup> = None;
+    let mut list = ListState::default();
+    loop {
+        list.select(Some(browser.selected_index()));
+        terminal.draw(|frame| draw(frame, browser, sources, &mut list, popup.as_mut()))?;
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        if let Some(open) = popup.as_mut() {
+            let (entries, state) = match open {
+                Popup::Callers(callers, state) => (callers.clone(), state),
+                Popup::Bookmarks(state) => (browser.bookmarks().to_vec(), state),
+            };
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => state.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+                KeyCode::Enter => {
+                    if let Some(id) = state.selected().and_then(|i| entries.get(i)) {
+                        match open {
+                            Popup::Callers(..) => browser.jump_to_caller(id),
+                            Popup::Bookmarks(_) => browser.reroot(id),
+                        }
+                    }
+                    popup = None;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => popup = None,
+                _ => {}
+            }
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => browser.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => browser.move_by(1),
+            KeyCode::PageUp => browser.move_by(-10),
+            KeyCode::PageDown => browser.move_by(10),
+            KeyCode::Home | KeyCode::Char('g') => browser.select_first(),
+            KeyCode::End | KeyCode::Char('G') => browser.select_last(),
+            KeyCode::Right | KeyCode::Char('l') => browser.expand(),
+            KeyCode::Left | KeyCode::Char('h') => browser.collapse(),
+            KeyCode::Enter | KeyCode::Char(' ') => browser.toggle(),
+            KeyCode::Char('r') => {
+                let id = browser.selected().id().to_string();
+                browser.reroot(&id);
+            }
+            KeyCode::Char('u') | KeyCode::Backspace => browser.back(),
+            KeyCode::Char('b') => browser.toggle_bookmark(),
+            KeyCode::Char('c') => {
+                let callers = browser.callers();
+                if !callers.is_empty() {
+                    popup = Some(Popup::Callers(callers, ListState::default().with_selected(Some(0))));
+                }
+            }
+            KeyCode::Char('B') if !browser.bookmarks().is_empty() => {
+                popup = Some(Popup::Bookmarks(ListState::default().with_selected(Some(0))));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, browser: &TreeBrowser, sources: &SourceManager, list: &mut ListState, popup: Option<&mut Popup>) {
+    let [main, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+    let [tree_area, source_area] = Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(main);
+
+    let items: Vec<Line> = browser.rows().iter().map(|row| {
+        let marker = match (row.recursive, row.expandable, row.expanded) {
+            (true, _, _) => "↻",
+            (_, true, true) => "▾",
+            (_, true, false) => "▸",
+            _ => "·",
+        };
+        let star = if browser.is_bookmarked(row.id()) { " ★" } else { "" };
+        let line = Line::from(format!("{}{} {}{}", "  ".repeat(row.depth()), marker, row.id(), star));
+        if browser.node(row.id()).is_none() || row.recursive { line.dim() } else { line }
+    }).collect();
+    let tree = List::new(items)
+        .block(Block::bordered().title(format!(" {} ", browser.root())))
+        .highlight_style(Style::new().reversed());
+    frame.render_stateful_widget(tree, tree_area, list);
+
+    let id = browser.selected().id();
+    let location = browser.node(id).and_then(|n| n.location.clone());
+    let source = location.as_deref()
+        .and_then(|l| l.rsplit_once(':'))
+        .and_then(|(file, line)| sources.get_function_body(file, line.parse().ok()?))
+        .unwrap_or_else(|| "(no source: outside the analyzed crates)".to_string());
+    let title = format!(" {}  {} ", id, location.as_deref().unwrap_or("-"));
+    frame.render_widget(Paragraph::new(source).block(Block::bordered().title(title)), source_area);
+    frame.render_widget(Line::from(KEYS).dim(), status);
+
+    if let Some(popup) = popup {
+        let (title, entries, state) = match popup {
+            Popup::Callers(callers, state) => (format!(" callers of {} ", id), callers.clone(), state),
+            Popup::Bookmarks(state) => (" bookmarks ".to_string(), browser.bookmarks().to_vec(), state),
+        };
+        let area = centered(main, entries.len());
+        let list = List::new(entries).block(Block::bordered().title(title)).highlight_style(Style::new().reversed());
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, state);
+    }
+}
+
+/// Two thirds of `area`'s width, tall enough for `rows` entries if possible.
+fn centered(area: Rect, rows: usize) -> Rect {
+    let height = (rows as u16).saturating_add(2).min(area.height);
+    let width = area.width * 2 / 3;
+    Rect { x: area.x + (area.width - width) / 2, y: area.y + (area.height - height) / 2, width, height }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    fn graph() -> CallGraph {
+        CallGraph::new(vec![
+            node("main", &["parse", "std::println", "parse"]),
+            node("parse", &["expr"]),
+            node("expr", &["parse", "term"]),
+            node("term", &[]),
+            node("repl", &["expr"]),
+        ])
+    }
+
+    fn ids(browser: &TreeBrowser) -> Vec<String> {
+        browser.rows().iter().map(|r| format!("{}{}", "  ".repeat(r.depth()), r.id())).collect()
+    }
+
+    #[test]
+    fn test_expand_and_collapse() {
+        let cg = graph();
+        let mut browser = TreeBrowser::new(&cg, "main");
+        assert_eq!(ids(&browser), vec!["main", "  parse", "  std::println"]);
+
+        browser.move_by(1);
+        browser.expand();
+        browser.expand(); // already expanded: steps into it
+        browser.expand();
+        assert_eq!(ids(&browser), vec!["main", "  parse", "    expr", "      parse", "      term", "  std::println"]);
+        // The recursive call is shown but not expanded again
+        let recursive = &browser.rows()[3];
+        assert!(recursive.recursive && !recursive.expandable);
+        assert!(!browser.rows()[5].expandable);
+
+        browser.move_by(1);
+        browser.collapse(); // a leaf: selects its parent
+        assert_eq!(browser.selected().id(), "expr");
+        browser.collapse();
+        assert_eq!(ids(&browser), vec!["main", "  parse", "    expr", "  std::println"]);
+        browser.move_by(100);
+        assert_eq!(browser.selected().id(), "std::println");
+    }
+
+    #[test]
+    fn test_callers_reroot_and_bookmarks() {
+        let cg = graph();
+        let mut browser = TreeBrowser::new(&cg, "main");
+        browser.move_by(1);
+        browser.expand();
+        browser.move_by(1);
+        assert_eq!(browser.selected().id(), "expr");
+        assert_eq!(browser.callers(), vec!["parse", "repl"]);
+
+        browser.toggle_bookmark();
+        browser.jump_to_caller("repl");
+        assert_eq!(browser.root(), "repl");
+        assert_eq!(browser.selected().path, vec!["repl", "expr"]);
+        assert!(browser.is_bookmarked("expr"));
+
+        browser.back();
+        assert_eq!(browser.root(), "main");
+        assert_eq!(browser.bookmarks(), ["expr"]);
+        browser.toggle_bookmark();
+        assert_eq!(browser.bookmarks(), ["expr", "main"]);
+        browser.toggle_bookmark();
+        assert_eq!(browser.bookmarks(), ["expr"]);
+    }
+}
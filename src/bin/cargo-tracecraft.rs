@@ -0,0 +1,50 @@
+//! `cargo tracecraft`: runs `mr_hedgehog` on the workspace around the
+//! current directory, with its binaries' `main` as entry points and the
+//! graph written to `target/tracecraft/` unless told otherwise.
+
+use std::process::Command;
+
+use mr_hedgehog::config::ProjectConfig;
+use mr_hedgehog::infrastructure::cargo_subcommand::SubcommandDefaults;
+
+fn main() {
+    // Cargo passes the subcommand name on; `cargo-tracecraft` run directly does not
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("tracecraft") {
+        args.remove(0);
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_else(|_| ".".into());
+    let defaults = match SubcommandDefaults::detect(&cwd) {
+        Ok(defaults) => defaults,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let root = defaults.manifest.parent().map(std::path::Path::to_path_buf).unwrap_or_default();
+    let config = match args.iter().any(|a| a == "--no-config" || a.starts_with("--config")) {
+        true => None,
+        false => ProjectConfig::find(&root).and_then(|path| ProjectConfig::load(&path).ok()),
+    };
+    if defaults.output(&args, config.as_ref()).is_some() {
+        if let Err(e) = std::fs::create_dir_all(&defaults.output_dir) {
+            eprintln!("Error: cannot create {}: {}", defaults.output_dir.display(), e);
+            std::process::exit(1);
+        }
+    }
+    let args = defaults.apply(args, config.as_ref());
+
+    let exe = std::env::current_exe().ok();
+    let tool = exe.as_ref()
+        .map(|exe| exe.with_file_name(format!("mr_hedgehog{}", std::env::consts::EXE_SUFFIX)))
+        .filter(|tool| tool.is_file())
+        .unwrap_or_else(|| "mr_hedgehog".into());
+    match Command::new(&tool).args(&args).status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("Error: cannot run {}: {}", tool.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
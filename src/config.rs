@@ -0,0 +1,275 @@
+//! Project Configuration
+//!
+//! Settings for a project kept in a `tracecraft.toml` at its root, so
+//! everyday runs need no long command lines. Every key mirrors a CLI flag;
+//! flags given on the command line win over the file. Relative paths are
+//! taken from the file's directory.
+//!
+//! ```toml
+//! entry = ["main"]
+//!
+//! [input]
+//! workspace = "Cargo.toml"
+//!
+//! [filter]
+//! exclude = ["std::*"]
+//!
+//! [output]
+//! path = "graph.dot"
+//!
+//! [dot]
+//! rankdir = "LR"
+//! edge_colors = { dynamic = "red" }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::domain::source::SourceOrigin;
+
+/// File looked for in the project root.
+pub const CONFIG_FILE_NAME: &str = "tracecraft.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Entry points, by name or node id (`--entry`).
+    pub entry: Vec<String>,
+    pub input: InputConfig,
+    pub filter: FilterConfig,
+    pub output: OutputConfig,
+    pub dot: DotConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// Workspace `Cargo.toml` or its directory (`--workspace`).
+    pub workspace: Option<PathBuf>,
+    /// Pre-built SCIP indices (`--scip`), one path or a list.
+    #[serde(deserialize_with = "one_or_many")]
+    pub scip: Vec<PathBuf>,
+    /// Only call edges from SCIP indices (`--scip-calls-only`).
+    pub scip_calls_only: Option<bool>,
+    /// Stub nodes for calls into crates that are not analyzed (`--include-external`).
+    pub include_external: Option<bool>,
+    /// LSIF dump to use instead of a SCIP index (`--lsif`).
+    pub lsif: Option<PathBuf>,
+    /// Single source files (`--input`).
+    pub files: Vec<PathBuf>,
+    /// Folders searched for sources (`--folder`).
+    pub folders: Vec<PathBuf>,
+    /// Enabled features for `#[cfg(feature = "..")]` (`--features`).
+    pub features: Option<Vec<String>>,
+    /// Leave the default features out (`--no-default-features`).
+    pub no_default_features: Option<bool>,
+    /// Target triple for `#[cfg(unix)]` and the like (`--target`).
+    pub target: Option<String>,
+    /// Gitignore-style patterns for sources to skip (`--ignore`).
+    pub ignore: Vec<String>,
+    /// `--gitignore`
+    pub gitignore: Option<bool>,
+    /// Integration tests, benchmarks and examples (`--tests`, `--benches`, `--examples`).
+    pub tests: Option<bool>,
+    pub benches: Option<bool>,
+    pub examples: Option<bool>,
+    /// `false` leaves out `#[cfg(test)]` code (`--no-cfg-test`).
+    pub cfg_test: Option<bool>,
+    /// Struct, enum and alias nodes with their ownership edges (`--types`).
+    pub types: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterConfig {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub exclude_tests: Option<bool>,
+    /// `test`, `bench` or `example` (`--exclude-origin`).
+    pub exclude_origins: Vec<SourceOrigin>,
+    /// `--collapse-excluded`
+    pub collapse_excluded: Option<bool>,
+    /// `--condense`
+    pub condense: Option<bool>,
+    /// Crates whose stub nodes are folded into one (`--collapse-external`).
+    pub collapse_external: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// `--output`
+    pub path: Option<PathBuf>,
+    /// `dot`, `json`, `html` or `sqlite` (`--format`).
+    pub format: Option<String>,
+    /// `callgraph` or `flowchart` (`--mode`).
+    pub mode: Option<String>,
+    /// `functions`, `modules` or `crates` (`--view`).
+    pub view: Option<String>,
+    /// `svg` or `png` (`--render`).
+    pub render: Option<String>,
+    /// `--link-base`
+    pub link_base: Option<String>,
+    /// `--signatures`
+    pub signatures: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DotConfig {
+    pub rankdir: Option<String>,
+    pub node_shape: Option<String>,
+    pub font: Option<String>,
+    /// `crate`, `module` or `none` (`--cluster`).
+    pub cluster: Option<String>,
+    pub cluster_colors: Vec<String>,
+    pub edge_labels: Option<bool>,
+    /// Edge kind -> color (`--dot-edge-color KIND=COLOR`).
+    pub edge_colors: BTreeMap<String, String>,
+    pub max_label: Option<usize>,
+    pub source_url: Option<String>,
+}
+
+impl ProjectConfig {
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Read `path`, resolving the paths in it against its directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut config = Self::parse(&text)?;
+        config.resolve_paths(path.parent().unwrap_or(Path::new("")));
+        Ok(config)
+    }
+
+    /// The config file in `dir`, if there is one.
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        Some(dir.join(CONFIG_FILE_NAME)).filter(|path| path.is_file())
+    }
+
+    fn resolve_paths(&mut self, base: &Path) {
+        let paths = self.input.workspace.iter_mut()
+            .chain(&mut self.input.scip)
+            .chain(&mut self.input.lsif)
+            .chain(&mut self.input.files)
+            .chain(&mut self.input.folders)
+            .chain(&mut self.output.path);
+        for path in paths {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        }
+    }
+}
+
+/// A list, or a single value standing for a list of one.
+fn one_or_many<'de, D, T>(deserializer: D) -> std::result::Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Commented starting point written by `init`; `workspace` is set when the
+/// project has a `Cargo.toml`.
+pub fn template(has_manifest: bool) -> String {
+    let workspace = if has_manifest { "workspace = \"Cargo.toml\"" } else { "# workspace = \"Cargo.toml\"" };
+    format!(r##"# Mr. Hedgehog project settings. Every key mirrors a command line flag,
+# and flags given on the command line win over this file.
+
+# Functions to trace from, by name or node id (--entry)
+entry = ["main"]
+
+[input]
+{workspace}
+# scip = ["crates/core/index.scip", "crates/cli/index.scip"]
+# scip_calls_only = true
+# include_external = true
+# lsif = "dump.lsif"
+# files = ["src/main.rs"]
+# folders = ["src"]
+# features = ["tls"]
+# no_default_features = true
+# target = "x86_64-unknown-linux-gnu"
+# ignore = ["vendor/", "*_generated.rs"]
+# gitignore = true
+# tests = true
+# benches = true
+# examples = true
+# cfg_test = false
+# types = true
+
+[filter]
+# include = ["my_crate::*"]
+exclude = []
+# exclude_tests = true
+# exclude_origins = ["example"]
+# collapse_excluded = false
+# condense = true
+# collapse_external = ["*"]
+
+[output]
+path = "callgraph.dot"
+format = "dot"             # dot, json, html or sqlite
+# mode = "flowchart"
+# view = "modules"
+# render = "svg"
+# link_base = "https://github.com/org/repo/blob/main/"
+# signatures = true
+
+[dot]
+# rankdir = "LR"
+# node_shape = "box"
+# font = "Helvetica"
+# cluster = "module"       # crate, module or none (the default)
+# cluster_colors = ["#eef4fb", "#fdf1e6"]
+# edge_labels = true
+# edge_colors = {{ dynamic = "red", spawn = "blue" }}
+# max_label = 40
+# source_url = "vscode://file/{{file}}:{{line}}"
+"##)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_parses() {
+        let config = ProjectConfig::parse(&template(true)).unwrap();
+        assert_eq!(config.entry, vec!["main"]);
+        assert_eq!(config.input.workspace, Some(PathBuf::from("Cargo.toml")));
+        assert_eq!(config.output.format.as_deref(), Some("dot"));
+        assert_eq!(ProjectConfig::parse(&template(false)).unwrap().input.workspace, None);
+    }
+
+    #[test]
+    fn test_load_resolves_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "[input]\nworkspace = \"Cargo.toml\"\nfolders = [\"/abs/src\"]\n\n[dot]\nedge_colors = { dynamic = \"red\" }\n").unwrap();
+        assert_eq!(ProjectConfig::find(tmp.path()), Some(path.clone()));
+
+        let config = ProjectConfig::load(&path).unwrap();
+        assert_eq!(config.input.workspace, Some(tmp.path().join("Cargo.toml")));
+        assert_eq!(config.input.folders, vec![PathBuf::from("/abs/src")]);
+        assert_eq!(config.dot.edge_colors.get("dynamic").map(String::as_str), Some("red"));
+        assert!(ProjectConfig::parse("[output]\nformats = \"dot\"\n").is_err());
+        assert_eq!(ProjectConfig::parse("[input]\nscip = \"index.scip\"\n").unwrap().input.scip, vec![PathBuf::from("index.scip")]);
+        assert_eq!(ProjectConfig::parse("[input]\nscip = [\"a.scip\", \"b.scip\"]\n").unwrap().input.scip.len(), 2);
+    }
+}
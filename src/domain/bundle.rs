@@ -0,0 +1,316 @@
+//! Context Bundles
+//!
+//! Everything needed to read the code reachable from one entry point in a
+//! single document: the call tree, then the full source of every function
+//! on it, each once, callers before callees. Meant to be pasted into an
+//! LLM prompt or a review; `BundleExporter` renders it. A token budget
+//! keeps the functions closest to the entry point and most called, cuts
+//! the rest down to their signature and leaves out what still does not fit.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::infrastructure::source_manager::SourceManager;
+
+/// One line of the call tree.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TreeNode {
+    pub id: String,
+    pub location: Option<String>,
+    pub depth: usize,
+    /// Why the node is not expanded here: `[see above]`, `[Cycle Detected]`
+    /// or `[depth limit]`.
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BundledFunction {
+    pub id: String,
+    /// Definition site, `file:line`.
+    pub location: String,
+    pub body: String,
+    /// The body was cut down to the signature and a summary of its calls
+    /// to fit the token budget.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// A function left out to fit the token budget.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OmittedFunction {
+    pub id: String,
+    pub location: String,
+    /// Estimated tokens of its full body.
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TokenBudget {
+    pub limit: usize,
+    /// Estimated tokens of the bundle after fitting.
+    pub used: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextBundle {
+    pub entry: String,
+    pub tree: Vec<TreeNode>,
+    /// Functions in the tree with their source, ordered topologically.
+    pub functions: Vec<BundledFunction>,
+    /// Functions in the tree without source (external stubs, nodes from a
+    /// SCIP index only).
+    pub missing: Vec<String>,
+    /// Functions dropped by [`fit_to_budget`](Self::fit_to_budget), most
+    /// important first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub omitted: Vec<OmittedFunction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<TokenBudget>,
+}
+
+impl ContextBundle {
+    /// Walk the graph from `entry`, expanding each function once and none
+    /// deeper than `max_depth` calls.
+    pub fn build(graph: &CallGraph, sources: &SourceManager, entry: &str, max_depth: usize) -> Self {
+        let nodes: HashMap<&str, &CallGraphNode> = graph.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        let mut walk = Walk { nodes: &nodes, max_depth, tree: Vec::new(), expanded: HashSet::new(), stack: Vec::new(), finished: Vec::new(), listed: HashSet::new() };
+        walk.visit(entry, 0);
+
+        // Reverse post-order: callers before their callees (back edges of
+        // cycles aside)
+        let mut functions = Vec::new();
+        let mut missing = Vec::new();
+        for id in walk.finished.iter().rev() {
+            // Closures and spawned blocks are part of their owner's body
+            if id.contains('{') {
+                continue;
+            }
+            let body = nodes.get(id.as_str())
+                .and_then(|n| n.location.as_deref())
+                .and_then(|loc| {
+                    let (file, line) = loc.rsplit_once(':')?;
+                    Some((loc, sources.get_function_body(file, line.parse().ok()?)?))
+                });
+            match body {
+                Some((location, body)) => functions.push(BundledFunction { id: id.clone(), location: location.to_string(), body, truncated: false }),
+                None => missing.push(id.clone()),
+            }
+        }
+        ContextBundle { entry: entry.to_string(), tree: walk.tree, functions, missing, omitted: Vec::new(), budget: None }
+    }
+
+    /// Shrink the bundle to about `limit` tokens. The call tree is always
+    /// kept; functions are taken by distance from the entry point, then by
+    /// how many functions in the tree call them, each with its full body if
+    /// it fits, its signature and calls if that fits, or not at all.
+    pub fn fit_to_budget(&mut self, graph: &CallGraph, limit: usize) {
+        let nodes: HashMap<&str, &CallGraphNode> = graph.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        let mut depth: HashMap<&str, usize> = HashMap::new();
+        for t in &self.tree {
+            let d = depth.entry(t.id.as_str()).or_insert(t.depth);
+            *d = (*d).min(t.depth);
+        }
+        let mut callers: HashMap<&str, usize> = HashMap::new();
+        for id in depth.keys() {
+            let callees: HashSet<&str> = nodes.get(id).into_iter().flat_map(|n| n.callee_ids()).collect();
+            for callee in callees {
+                *callers.entry(callee).or_default() += 1;
+            }
+        }
+
+        let mut used = self.tree.iter()
+            .map(|t| t.depth + estimate_tokens(&t.id) + t.location.as_deref().map_or(0, estimate_tokens) + 1)
+            .sum::<usize>()
+            + self.missing.iter().map(|id| estimate_tokens(id) + 2).sum::<usize>();
+        let mut order: Vec<usize> = (0..self.functions.len()).collect();
+        order.sort_by_key(|&i| {
+            let id = self.functions[i].id.as_str();
+            (depth.get(id).copied().unwrap_or(usize::MAX), Reverse(callers.get(id).copied().unwrap_or(0)), id)
+        });
+
+        let mut keep = vec![true; self.functions.len()];
+        let mut omitted = Vec::new();
+        for i in order {
+            let f = &mut self.functions[i];
+            // Heading and code fence
+            let header = estimate_tokens(&f.id) + estimate_tokens(&f.location) + 8;
+            let full = header + estimate_tokens(&f.body);
+            if used + full <= limit {
+                used += full;
+                continue;
+            }
+            let calls: Vec<&str> = nodes.get(f.id.as_str()).map_or_else(Vec::new, |n| n.callee_ids().collect());
+            let short = summarize_body(&f.body, &calls);
+            let cost = header + estimate_tokens(&short);
+            if cost < full && used + cost <= limit {
+                used += cost;
+                f.body = short;
+                f.truncated = true;
+            } else {
+                keep[i] = false;
+                omitted.push(OmittedFunction { id: f.id.clone(), location: f.location.clone(), tokens: full });
+            }
+        }
+        let mut keep = keep.into_iter();
+        self.functions.retain(|_| keep.next().unwrap_or(true));
+        self.omitted = omitted;
+        self.budget = Some(TokenBudget { limit, used });
+    }
+}
+
+/// Rough token count for budgeting: about four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// The signature of `body` (through its opening brace), a comment with the
+/// distinct functions it calls, and the closing brace.
+fn summarize_body(body: &str, calls: &[&str]) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let open = lines.iter().position(|l| l.contains('{')).unwrap_or(0);
+    let mut short: Vec<String> = lines[..=open.min(lines.len().saturating_sub(1))].iter().map(|l| l.to_string()).collect();
+    let mut seen = HashSet::new();
+    let calls: Vec<&str> = calls.iter().copied().filter(|c| !c.contains('{') && seen.insert(*c)).collect();
+    let omitted = lines.len().saturating_sub(open + 2);
+    let comment = if calls.is_empty() {
+        format!("    // … {} lines omitted", omitted)
+    } else {
+        format!("    // … {} lines omitted; calls {}", omitted, calls.join(", "))
+    };
+    short.push(comment);
+    short.push("}".to_string());
+    short.join("\n")
+}
+
+struct Walk<'a> {
+    nodes: &'a HashMap<&'a str, &'a CallGraphNode>,
+    max_depth: usize,
+    tree: Vec<TreeNode>,
+    expanded: HashSet<String>,
+    stack: Vec<String>,
+    /// Nodes in the order their subtrees were completed.
+    finished: Vec<String>,
+    listed: HashSet<String>,
+}
+
+impl Walk<'_> {
+    fn visit(&mut self, id: &str, depth: usize) {
+        let node = self.nodes.get(id);
+        let has_callees = node.is_some_and(|n| !n.callees.is_empty());
+        let note = if self.stack.iter().any(|s| s == id) {
+            Some("[Cycle Detected]")
+        } else if self.expanded.contains(id) {
+            Some("[see above]").filter(|_| has_callees)
+        } else if depth >= self.max_depth && has_callees {
+            Some("[depth limit]")
+        } else {
+            None
+        };
+        let location = node.and_then(|n| n.location.clone());
+        self.tree.push(TreeNode { id: id.to_string(), location, depth, note: note.map(str::to_string) });
+        if note == Some("[depth limit]") {
+            self.finish(id);
+        }
+        if note.is_some() || !self.expanded.insert(id.to_string()) {
+            return;
+        }
+
+        self.stack.push(id.to_string());
+        let mut seen = HashSet::new();
+        for callee in node.into_iter().flat_map(|n| n.callee_ids()) {
+            // Several calls to the same function are one branch of the tree
+            if seen.insert(callee) {
+                self.visit(callee, depth + 1);
+            }
+        }
+        self.stack.pop();
+        self.finish(id);
+    }
+
+    fn finish(&mut self, id: &str) {
+        if self.listed.insert(id.to_string()) {
+            self.finished.push(id.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    
+    use crate::domain::source::{SourceFile, SourceSet};
+
+    #[test]
+    fn test_build() {
+        let code = "fn leaf() {}\n\
+                    fn helper() {\n    leaf();\n}\n\
+                    fn main() {\n    helper();\n    leaf();\n    main();\n}\n";
+        let sources = SourceManager::new(&SourceSet::new(vec![SourceFile::new("app", "src/main.rs", code)]));
+        let node = |id: &str, line: Option<usize>, callees: &[&str]| CallGraphNode {
+            location: line.map(|l| format!("src/main.rs:{}", l)),
+            ..fixtures::node(id, callees)
+        };
+        let graph = CallGraph::new(vec![
+            node("app::leaf", Some(1), &[]),
+            node("app::helper", Some(2), &["app::leaf", "app::leaf"]),
+            node("app::main", Some(5), &["app::helper", "app::leaf", "app::main", "log::info"]),
+        ]);
+
+        let bundle = ContextBundle::build(&graph, &sources, "app::main", 30);
+        let tree: Vec<(&str, usize, Option<&str>)> = bundle.tree.iter().map(|t| (t.id.as_str(), t.depth, t.note.as_deref())).collect();
+        assert_eq!(tree, vec![
+            ("app::main", 0, None),
+            ("app::helper", 1, None),
+            ("app::leaf", 2, None),
+            ("app::leaf", 1, None),
+            ("app::main", 1, Some("[Cycle Detected]")),
+            ("log::info", 1, None),
+        ]);
+        let order: Vec<&str> = bundle.functions.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(order, vec!["app::main", "app::helper", "app::leaf"]);
+        assert_eq!(bundle.functions[1].body, "fn helper() {\n    leaf();\n}");
+        assert_eq!(bundle.missing, vec!["log::info"]);
+
+        let shallow = ContextBundle::build(&graph, &sources, "app::main", 1);
+        assert_eq!(shallow.tree[1].note.as_deref(), Some("[depth limit]"));
+        assert_eq!(shallow.functions.len(), 3);
+    }
+
+    #[test]
+    fn test_fit_to_budget() {
+        let code = "fn leaf() {}\n\
+                    fn helper() {\n    leaf();\n}\n\
+                    fn main() {\n    let config = load_configuration_from_disk();\n    let state = initialize_state(config);\n    helper();\n    leaf();\n    finish(state);\n}\n";
+        let sources = SourceManager::new(&SourceSet::new(vec![SourceFile::new("app", "src/main.rs", code)]));
+        let node = |id: &str, line: usize, callees: &[&str]| CallGraphNode {
+            location: Some(format!("src/main.rs:{}", line)),
+            ..fixtures::node(id, callees)
+        };
+        let graph = CallGraph::new(vec![
+            node("app::leaf", 1, &[]),
+            node("app::helper", 2, &["app::leaf"]),
+            node("app::main", 5, &["app::helper", "app::leaf"]),
+        ]);
+        let bundle = ContextBundle::build(&graph, &sources, "app::main", 30);
+
+        let mut fitted = bundle.clone();
+        fitted.fit_to_budget(&graph, 1000);
+        assert_eq!(fitted.functions, bundle.functions);
+        assert!(fitted.omitted.is_empty());
+
+        // Room for `main` cut to its signature and for `leaf`, which both
+        // others call; `helper` is as far away but called once
+        let mut fitted = bundle.clone();
+        fitted.fit_to_budget(&graph, 88);
+        let kept: Vec<(&str, bool)> = fitted.functions.iter().map(|f| (f.id.as_str(), f.truncated)).collect();
+        assert_eq!(kept, vec![("app::main", true), ("app::leaf", false)], "{:?}", fitted);
+        assert_eq!(fitted.functions[0].body, "fn main() {\n    // … 5 lines omitted; calls app::helper, app::leaf\n}");
+        assert_eq!(fitted.omitted.iter().map(|o| o.id.as_str()).collect::<Vec<_>>(), vec!["app::helper"]);
+        let budget = fitted.budget.unwrap();
+        assert!(budget.used <= 88, "{:?}", budget);
+    }
+}
@@ -1,28 +1,267 @@
 // Call graph structures for Mr. Hedgehog.
 // Represents function/module call relationships.
 
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::domain::interner::{Symbol, SymbolPool};
+use crate::domain::metrics::FnMetrics;
+use crate::domain::panics::PanicSite;
+use crate::domain::source::SourceOrigin;
+
+/// How a caller reaches a callee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum EdgeKind {
+    /// Direct, statically resolved call.
+    #[default]
+    Call,
+    /// Call through a trait object, linked to every known implementor.
+    Dynamic,
+    /// Task spawn (`tokio::spawn`, `thread::spawn`, ...): the callee runs
+    /// concurrently with the caller.
+    Spawn,
+    /// The callee is passed as a value (callback, function pointer) rather
+    /// than called directly.
+    Reference,
+    /// Call through a generic parameter, devirtualized to the concrete type
+    /// the call sites instantiate it with.
+    Inferred,
+    /// Not a call: from a trait method declaration to a method of an
+    /// `impl Trait for Type` implementing it.
+    Implements,
+    /// Not a call: from a type node to a method of its inherent or trait impls.
+    DefinesMethod,
+    /// Not a call: from a type node to the type of one of its fields (or
+    /// variant fields, or the aliased type).
+    HasFieldOfType,
+    /// From a function to a type it builds with a struct literal, tuple
+    /// struct or enum variant constructor.
+    Constructs,
+    /// Not a call: the callee is named in a `use` inside the caller (SCIP
+    /// indices only).
+    Import,
+}
+
+impl EdgeKind {
+    /// Stable lowercase name used by exporters.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::Call => "call",
+            EdgeKind::Dynamic => "dynamic",
+            EdgeKind::Spawn => "spawn",
+            EdgeKind::Reference => "reference",
+            EdgeKind::Inferred => "inferred",
+            EdgeKind::Implements => "implements",
+            EdgeKind::DefinesMethod => "defines-method",
+            EdgeKind::HasFieldOfType => "has-field-of-type",
+            EdgeKind::Constructs => "constructs",
+            EdgeKind::Import => "import",
+        }
+    }
+
+    /// Whether control flows along the edge; the structural kinds relating
+    /// types and trait declarations do not.
+    pub fn is_call(&self) -> bool {
+        !matches!(self, EdgeKind::Implements | EdgeKind::DefinesMethod | EdgeKind::HasFieldOfType | EdgeKind::Constructs | EdgeKind::Import)
+    }
+
+    /// Inverse of [`as_str`](Self::as_str).
+    pub fn parse(name: &str) -> Option<Self> {
+        [EdgeKind::Call, EdgeKind::Dynamic, EdgeKind::Spawn, EdgeKind::Reference, EdgeKind::Inferred, EdgeKind::Implements, EdgeKind::DefinesMethod, EdgeKind::HasFieldOfType, EdgeKind::Constructs, EdgeKind::Import].into_iter().find(|k| k.as_str() == name)
+    }
+}
+
+/// A call edge from the owning node to `target`, with the call-site location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallEdge {
+    pub target: Symbol, // callee node ID
+    pub file: Option<String>, // file containing the call site
+    pub line: Option<usize>, // 1-based line of the call site
+    pub kind: EdgeKind,
+    pub weight: usize, // call sites the edge stands for: 1 per syn call site, all of a caller's calls to the callee from SCIP
+}
+
+impl Default for CallEdge {
+    fn default() -> Self {
+        Self { target: Symbol::default(), file: None, line: None, kind: EdgeKind::Call, weight: 1 }
+    }
+}
+
+impl CallEdge {
+    /// Edge without call-site information.
+    pub fn new(target: impl Into<Symbol>) -> Self {
+        Self { target: target.into(), ..Default::default() }
+    }
+
+    /// Edge with a known call-site location.
+    pub fn at(target: impl Into<Symbol>, file: impl Into<String>, line: usize) -> Self {
+        Self {
+            target: target.into(),
+            file: Some(file.into()),
+            line: Some(line),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_kind(mut self, kind: EdgeKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: usize) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Call site in `file:line` form, if known.
+    pub fn location(&self) -> Option<String> {
+        match (&self.file, self.line) {
+            (Some(f), Some(l)) => Some(format!("{}:{}", f, l)),
+            (Some(f), None) => Some(f.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// What a type node stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypeKind {
+    Struct,
+    Enum,
+    Union,
+    /// `type Name = ..;`
+    Alias,
+}
+
+impl TypeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TypeKind::Struct => "struct",
+            TypeKind::Enum => "enum",
+            TypeKind::Union => "union",
+            TypeKind::Alias => "alias",
+        }
+    }
+}
+
 /// A node in the call graph.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CallGraphNode {
-    pub id: String, // function/module/unique identifier
-    pub callees: Vec<String>, // list of IDs this node calls
+    pub id: Symbol, // function/module/unique identifier
+    pub callees: Vec<CallEdge>, // outgoing call edges
     pub label: Option<String>, // label for DOT (file:line etc)
+    pub is_public: bool, // `pub` item or trait impl method; a root for reachability
+    pub is_async: bool, // `async fn` or spawned `async` block
+    pub is_unsafe: bool, // `unsafe fn`
+    pub is_test: bool, // `#[test]` function or item under `#[cfg(test)]` (closures inherit it)
+    pub is_test_fn: bool, // carries `#[test]`, `#[tokio::test]`, ...: a test the harness runs
+    pub cfg: Option<String>, // `#[cfg(..)]` condition the node is compiled under, e.g. `unix`
+    pub external: Option<String>, // crate of a stub node standing for code outside the analyzed sources
+    pub location: Option<String>, // definition site, `file:line`
+    pub metrics: Option<FnMetrics>, // complexity of a function body (closures count towards their owner)
+    pub coverage: Option<u64>, // times the function ran according to a coverage report; `Some(0)` = never
+    pub unsafe_blocks: usize, // `unsafe { .. }` blocks in the body (closures count towards their owner)
+    pub panics: Vec<PanicSite>, // potential panics in the body (closures count towards their owner)
+    pub signature: Option<String>, // `fn run(op: &dyn Op) -> i32`, as written
+    pub doc: Option<String>, // first line of the `///` comment
+    pub type_kind: Option<TypeKind>, // `Some` for type nodes (`Config@app`), `None` for functions
+    pub origin: Option<SourceOrigin>, // test, bench or example target the node comes from; `None` for library and binary code
+}
+
+/// Crate and module path a node id belongs to: `app::net::send` and
+/// `net::Conn::send@app` -> (`app`, [`net`]), `Client::send@app` ->
+/// (`app`, []). Closure suffixes are dropped first.
+pub fn node_module(id: &str) -> (&str, Vec<&str>) {
+    if let Some((path, krate)) = id.rsplit_once('@') {
+        let mut segments: Vec<&str> = path.split("::").filter(|s| !s.starts_with('{')).collect();
+        segments.truncate(segments.len().saturating_sub(2));
+        return (krate, segments);
+    }
+    let mut segments: Vec<&str> = id.split("::").filter(|s| !s.starts_with('{')).collect();
+    segments.pop();
+    match segments.split_first() {
+        Some((krate, module)) => (krate, module.to_vec()),
+        None => (id, Vec::new()),
+    }
+}
+
+impl CallGraphNode {
+    /// IDs of all callees, in call order (may repeat).
+    pub fn callee_ids(&self) -> impl Iterator<Item = &str> {
+        self.callees.iter().map(|e| e.target.as_str())
+    }
+
+    /// Edges control flows along, leaving out the structural ones.
+    pub fn call_edges(&self) -> impl Iterator<Item = &CallEdge> {
+        self.callees.iter().filter(|e| e.kind.is_call())
+    }
+
+    /// Whether this node has an edge to `target`.
+    pub fn calls(&self, target: &str) -> bool {
+        self.callees.iter().any(|e| e.target == target)
+    }
+
+    /// One edge per callee and kind (the first), with the summed weight of
+    /// all of them, in call order.
+    pub fn weighted_callees(&self) -> Vec<(&CallEdge, usize)> {
+        let mut out: Vec<(&CallEdge, usize)> = Vec::new();
+        let mut index: HashMap<(&str, EdgeKind), usize> = HashMap::new();
+        for edge in &self.callees {
+            match index.get(&(edge.target.as_str(), edge.kind)) {
+                Some(&i) => out[i].1 += edge.weight,
+                None => {
+                    index.insert((edge.target.as_str(), edge.kind), out.len());
+                    out.push((edge, edge.weight));
+                }
+            }
+        }
+        out
+    }
 }
 
 /// The call graph itself.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallGraph {
+    #[serde(deserialize_with = "interned_nodes")]
     pub nodes: Vec<CallGraphNode>,
 }
 
+/// Loaded nodes with their symbols shared, as [`CallGraph::new`] leaves them.
+fn interned_nodes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<CallGraphNode>, D::Error> {
+    Vec::deserialize(deserializer).map(|nodes| CallGraph::new(nodes).nodes)
+}
+
 impl CallGraph {
     pub fn new(nodes: Vec<CallGraphNode>) -> Self {
-        Self { nodes }
+        let mut graph = Self { nodes };
+        graph.intern();
+        graph
+    }
+
+    /// Make every node id and edge target naming the same symbol share one
+    /// string. Builders produce a copy per edge; nodes added after
+    /// [`new`](Self::new) keep theirs until this runs again.
+    pub fn intern(&mut self) {
+        let mut pool = SymbolPool::default();
+        for node in &mut self.nodes {
+            node.id = pool.share(&node.id);
+        }
+        for node in &mut self.nodes {
+            for edge in &mut node.callees {
+                edge.target = pool.share(&edge.target);
+            }
+        }
     }
 
     pub fn add_edge(&mut self, caller_id: &str, callee_id: &str) {
+        self.add_call_edge(caller_id, CallEdge::new(callee_id));
+    }
+
+    pub fn add_call_edge(&mut self, caller_id: &str, edge: CallEdge) {
         if let Some(node) = self.nodes.iter_mut().find(|n| n.id == caller_id) {
-            node.callees.push(callee_id.to_string());
+            node.callees.push(edge);
         }
     }
 }
@@ -32,3 +271,18 @@ pub struct FileCallGraph {
     pub filename: String,
     pub callgraph: CallGraph,
 }
+
+/// Graph fixtures for unit tests.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::{CallEdge, CallGraphNode};
+
+    /// Node `id` with one plain call edge to each of `callees`.
+    pub(crate) fn node(id: &str, callees: &[&str]) -> CallGraphNode {
+        CallGraphNode {
+            id: id.into(),
+            callees: callees.iter().map(|c| CallEdge::new(*c)).collect(),
+            ..Default::default()
+        }
+    }
+}
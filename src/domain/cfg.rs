@@ -0,0 +1,296 @@
+//! Conditional Compilation
+//!
+//! Evaluates `#[cfg(..)]` attributes against a build configuration
+//! (`--features`, `--target`) so items a real build would not compile are
+//! left out of the graph. Only the parts of the configuration that were
+//! given are evaluated: without `--target`, `cfg(unix)` stays open and the
+//! item is kept; `test` is only decided when asked to (`--no-cfg-test`), and
+//! `debug_assertions` and custom cfgs are always open. As with cargo,
+//! `--features` adds to the `default` features unless
+//! `--no-default-features` is given.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use syn::punctuated::Punctuated;
+
+/// The build configuration cfg attributes are evaluated against.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CfgOptions {
+    /// Enabled features; `None` leaves `feature = ".."` unevaluated.
+    pub features: Option<BTreeSet<String>>,
+    /// Leave the packages' `default` features out of `features`.
+    pub no_default_features: bool,
+    pub target: Option<TargetCfg>,
+    /// Whether `cfg(test)` holds; `None` keeps test code and tags it.
+    pub test: Option<bool>,
+}
+
+impl CfgOptions {
+    /// Nothing is evaluated; every item is kept.
+    pub fn is_empty(&self) -> bool {
+        self.features.is_none() && self.target.is_none() && self.test.is_none()
+    }
+
+    fn eval_flag(&self, name: &str) -> Option<bool> {
+        if name == "test" {
+            return self.test;
+        }
+        let target = self.target.as_ref()?;
+        match name {
+            "unix" | "windows" => Some(target.family.iter().any(|f| f == name)),
+            _ => None,
+        }
+    }
+
+    fn eval_key(&self, key: &str, value: &str) -> Option<bool> {
+        if key == "feature" {
+            return self.features.as_ref().map(|f| f.contains(value));
+        }
+        let target = self.target.as_ref()?;
+        let actual = match key {
+            "target_family" => return Some(target.family.iter().any(|f| f == value)),
+            "target_os" => &target.os,
+            "target_arch" => &target.arch,
+            "target_env" => &target.env,
+            "target_vendor" => &target.vendor,
+            "target_endian" => &target.endian,
+            "target_pointer_width" => &target.pointer_width,
+            _ => return None,
+        };
+        Some(actual == value)
+    }
+}
+
+/// The `target_*` values of a target triple.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TargetCfg {
+    pub arch: String,
+    pub vendor: String,
+    pub os: String,
+    pub env: String,
+    pub family: Vec<String>,
+    pub endian: String,
+    pub pointer_width: String,
+}
+
+impl TargetCfg {
+    /// Derive the cfg values from a triple such as `x86_64-unknown-linux-gnu`,
+    /// `aarch64-apple-darwin` or `wasm32-unknown-unknown`.
+    pub fn from_triple(triple: &str) -> Self {
+        let parts: Vec<&str> = triple.split('-').collect();
+        let arch = match parts[0] {
+            a if a.starts_with("i") && a.ends_with("86") => "x86",
+            a if a.starts_with("armv") || a.starts_with("thumbv") => "arm",
+            a if a.starts_with("riscv64") => "riscv64",
+            a if a.starts_with("riscv32") => "riscv32",
+            a => a,
+        }.to_string();
+        // Vendor-less triples (`x86_64-linux-android`) put the OS second
+        let vendor = match parts.get(1) {
+            Some(p) if parts.len() >= 3 && os_name(p).is_none() => p.to_string(),
+            _ => "unknown".to_string(),
+        };
+        // `linux-android` is Android, not Linux: the last OS name wins
+        let os = parts.iter().skip(1).rev().find_map(|p| os_name(p))
+            .unwrap_or(if arch.starts_with("wasm") { "unknown" } else { "none" }).to_string();
+        let env = match parts.last().copied().unwrap_or_default() {
+            e if e.starts_with("gnu") => "gnu",
+            e if e.starts_with("musl") => "musl",
+            "msvc" => "msvc",
+            _ => "",
+        }.to_string();
+
+        let mut family = Vec::new();
+        if os == "windows" {
+            family.push("windows".to_string());
+        } else if !matches!(os.as_str(), "none" | "unknown" | "wasi") {
+            family.push("unix".to_string());
+        }
+        if arch.starts_with("wasm") {
+            family.push("wasm".to_string());
+        }
+        let endian = match arch.as_str() {
+            "s390x" | "powerpc" | "powerpc64" | "mips" | "mips64" | "sparc64" => "big",
+            _ => "little",
+        }.to_string();
+        let pointer_width = match arch.as_str() {
+            "x86_64" | "aarch64" | "riscv64" | "wasm64" | "powerpc64" | "powerpc64le" | "mips64" | "s390x" | "sparc64" | "loongarch64" => "64",
+            "avr" | "msp430" => "16",
+            _ => "32",
+        }.to_string();
+
+        Self { arch, vendor, os, env, family, endian, pointer_width }
+    }
+}
+
+/// `target_os` of a triple component that names an OS.
+fn os_name(part: &str) -> Option<&str> {
+    match part {
+        "darwin" | "macos" => Some("macos"),
+        p if p.starts_with("android") => Some("android"),
+        "linux" | "windows" | "ios" | "freebsd" | "netbsd" | "openbsd" | "dragonfly"
+        | "solaris" | "illumos" | "fuchsia" | "emscripten" | "wasi" | "none" => Some(part),
+        _ => None,
+    }
+}
+
+/// A parsed cfg predicate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CfgExpr {
+    /// `unix`, `test`, ...
+    Flag(String),
+    /// `feature = "x"`, `target_os = "linux"`, ...
+    KeyValue(String, String),
+    All(Vec<CfgExpr>),
+    Any(Vec<CfgExpr>),
+    Not(Box<CfgExpr>),
+}
+
+impl CfgExpr {
+    /// Combined condition of every `#[cfg(..)]` on an item, if any.
+    /// Predicates that do not parse are ignored.
+    pub fn from_attrs(attrs: &[syn::Attribute]) -> Option<CfgExpr> {
+        let mut exprs: Vec<CfgExpr> = attrs.iter()
+            .filter(|a| a.path().is_ident("cfg"))
+            .filter_map(|a| a.parse_args::<syn::Meta>().ok())
+            .filter_map(|meta| Self::from_meta(&meta))
+            .collect();
+        match exprs.len() {
+            0 => None,
+            1 => exprs.pop(),
+            _ => Some(CfgExpr::All(exprs)),
+        }
+    }
+
+    fn from_meta(meta: &syn::Meta) -> Option<CfgExpr> {
+        match meta {
+            syn::Meta::Path(path) => Some(CfgExpr::Flag(path.get_ident()?.to_string())),
+            syn::Meta::NameValue(nv) => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) => {
+                    Some(CfgExpr::KeyValue(nv.path.get_ident()?.to_string(), s.value()))
+                }
+                _ => None,
+            },
+            syn::Meta::List(list) => {
+                let nested = list.parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated).ok()?;
+                let mut args = nested.iter().map(Self::from_meta).collect::<Option<Vec<_>>>()?;
+                match list.path.get_ident()?.to_string().as_str() {
+                    "all" => Some(CfgExpr::All(args)),
+                    "any" => Some(CfgExpr::Any(args)),
+                    "not" if args.len() == 1 => Some(CfgExpr::Not(Box::new(args.pop()?))),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// `Some(false)` when the configuration rules the item out, `Some(true)`
+    /// when it is known to be compiled, `None` when that depends on options
+    /// that were not given.
+    pub fn eval(&self, options: &CfgOptions) -> Option<bool> {
+        match self {
+            CfgExpr::Flag(name) => options.eval_flag(name),
+            CfgExpr::KeyValue(key, value) => options.eval_key(key, value),
+            CfgExpr::Not(inner) => inner.eval(options).map(|v| !v),
+            CfgExpr::All(args) => {
+                let values: Vec<Option<bool>> = args.iter().map(|a| a.eval(options)).collect();
+                if values.contains(&Some(false)) {
+                    Some(false)
+                } else if values.iter().all(|v| *v == Some(true)) {
+                    Some(true)
+                } else {
+                    None
+                }
+            }
+            CfgExpr::Any(args) => {
+                let values: Vec<Option<bool>> = args.iter().map(|a| a.eval(options)).collect();
+                if values.contains(&Some(true)) {
+                    Some(true)
+                } else if values.iter().all(|v| *v == Some(false)) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Rust syntax, e.g. `all(unix, feature = "tls")`.
+impl fmt::Display for CfgExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter<'_>, name: &str, args: &[CfgExpr]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            write!(f, "{}({})", name, args.join(", "))
+        };
+        match self {
+            CfgExpr::Flag(name) => write!(f, "{}", name),
+            CfgExpr::KeyValue(key, value) => write!(f, "{} = {:?}", key, value),
+            CfgExpr::All(args) => list(f, "all", args),
+            CfgExpr::Any(args) => list(f, "any", args),
+            CfgExpr::Not(inner) => write!(f, "not({})", inner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(attrs: &str) -> CfgExpr {
+        let item: syn::ItemFn = syn::parse_str(&format!("{} fn f() {{}}", attrs)).unwrap();
+        CfgExpr::from_attrs(&item.attrs).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(cfg("#[cfg(unix)]"), CfgExpr::Flag("unix".to_string()));
+        assert_eq!(cfg("#[cfg(all(unix, not(feature = \"tls\")))]").to_string(), "all(unix, not(feature = \"tls\"))");
+        assert_eq!(cfg("#[cfg(unix)] #[cfg(test)]").to_string(), "all(unix, test)");
+        assert!(CfgExpr::from_attrs(&[]).is_none());
+    }
+
+    #[test]
+    fn test_eval() {
+        let linux = CfgOptions {
+            features: Some(BTreeSet::from(["tls".to_string()])),
+            target: Some(TargetCfg::from_triple("x86_64-unknown-linux-gnu")),
+            ..CfgOptions::default()
+        };
+        assert_eq!(cfg("#[cfg(unix)]").eval(&linux), Some(true));
+        assert_eq!(cfg("#[cfg(windows)]").eval(&linux), Some(false));
+        assert_eq!(cfg("#[cfg(target_os = \"macos\")]").eval(&linux), Some(false));
+        assert_eq!(cfg("#[cfg(all(target_pointer_width = \"64\", feature = \"tls\"))]").eval(&linux), Some(true));
+        assert_eq!(cfg("#[cfg(not(feature = \"tls\"))]").eval(&linux), Some(false));
+        // Not decided by the options: kept
+        assert_eq!(cfg("#[cfg(test)]").eval(&linux), None);
+        assert_eq!(cfg("#[cfg(any(windows, test))]").eval(&linux), None);
+        assert_eq!(cfg("#[cfg(any(windows, feature = \"tls\"))]").eval(&linux), Some(true));
+
+        let features_only = CfgOptions { target: None, ..linux };
+        assert_eq!(cfg("#[cfg(windows)]").eval(&features_only), None);
+        assert_eq!(cfg("#[cfg(feature = \"gui\")]").eval(&features_only), Some(false));
+        assert_eq!(cfg("#[cfg(feature = \"gui\")]").eval(&CfgOptions::default()), None);
+
+        let no_tests = CfgOptions { test: Some(false), ..CfgOptions::default() };
+        assert_eq!(cfg("#[cfg(test)]").eval(&no_tests), Some(false));
+        assert_eq!(cfg("#[cfg(not(test))]").eval(&no_tests), Some(true));
+        assert_eq!(cfg("#[cfg(any(unix, test))]").eval(&no_tests), None);
+    }
+
+    #[test]
+    fn test_target_from_triple() {
+        let mac = TargetCfg::from_triple("aarch64-apple-darwin");
+        assert_eq!((mac.arch.as_str(), mac.os.as_str(), mac.family.as_slice()), ("aarch64", "macos", &["unix".to_string()][..]));
+        let win = TargetCfg::from_triple("x86_64-pc-windows-msvc");
+        assert_eq!((win.os.as_str(), win.env.as_str(), win.family.as_slice()), ("windows", "msvc", &["windows".to_string()][..]));
+        let wasm = TargetCfg::from_triple("wasm32-unknown-unknown");
+        assert_eq!((wasm.pointer_width.as_str(), wasm.family.as_slice()), ("32", &["wasm".to_string()][..]));
+        assert_eq!(TargetCfg::from_triple("i686-unknown-linux-musl").arch, "x86");
+        let android = TargetCfg::from_triple("x86_64-linux-android");
+        assert_eq!((android.vendor.as_str(), android.os.as_str(), android.env.as_str()), ("unknown", "android", ""));
+        let gnu = TargetCfg::from_triple("x86_64-unknown-linux-gnu");
+        assert_eq!((gnu.vendor.as_str(), gnu.os.as_str(), gnu.env.as_str()), ("unknown", "linux", "gnu"));
+    }
+}
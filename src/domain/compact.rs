@@ -0,0 +1,230 @@
+//! Compact Call Graph
+//!
+//! [`CallGraph`] stores one shared string per symbol, but walking it still
+//! means hashing those strings, which is slow on SCIP-scale graphs.
+//! `CompactGraph` is a read-only view built for one analysis: it numbers the
+//! ids once and stores the edges as one flat array of numeric ids with
+//! per-node offsets, so traversals and cycle checks run on `u32`s and bit
+//! vectors instead of hashed strings.
+
+use crate::domain::callgraph::CallGraph;
+use crate::domain::interner::{Interner, SymbolId};
+
+pub struct CompactGraph {
+    symbols: Interner,
+    /// Callees of symbol `i` are `targets[offsets[i]..offsets[i + 1]]`.
+    offsets: Vec<u32>,
+    targets: Vec<SymbolId>,
+}
+
+impl CompactGraph {
+    /// Intern every node and call target of `cg`; structural edges are left
+    /// out. Targets without a node of their own get an id and no callees;
+    /// edges of nodes sharing an id are combined.
+    pub fn new(cg: &CallGraph) -> Self {
+        let mut symbols = Interner::new();
+        for node in &cg.nodes {
+            symbols.intern(&node.id);
+        }
+        let mut adjacency: Vec<Vec<SymbolId>> = vec![Vec::new(); symbols.len()];
+        for node in &cg.nodes {
+            let from = symbols.intern(&node.id).index();
+            for callee in node.call_edges() {
+                let to = symbols.intern(&callee.target);
+                adjacency[from].push(to);
+            }
+        }
+        adjacency.resize(symbols.len(), Vec::new());
+
+        let mut offsets = Vec::with_capacity(adjacency.len() + 1);
+        let mut targets = Vec::with_capacity(adjacency.iter().map(Vec::len).sum());
+        offsets.push(0);
+        for callees in adjacency {
+            targets.extend(callees);
+            offsets.push(u32::try_from(targets.len()).expect("more than u32::MAX edges"));
+        }
+        Self { symbols, offsets, targets }
+    }
+
+    /// Number of symbols: nodes plus edge targets without a node.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Option<SymbolId> {
+        self.symbols.get(name)
+    }
+
+    pub fn name(&self, id: SymbolId) -> &str {
+        self.symbols.resolve(id)
+    }
+
+    pub fn callees(&self, id: SymbolId) -> &[SymbolId] {
+        let i = id.index();
+        &self.targets[self.offsets[i] as usize..self.offsets[i + 1] as usize]
+    }
+
+    /// Ids of `names`, skipping unknown ones.
+    pub fn ids<'n>(&self, names: impl IntoIterator<Item = &'n str>) -> Vec<SymbolId> {
+        names.into_iter().filter_map(|name| self.id(name)).collect()
+    }
+
+    /// Breadth-first distance of every symbol from the nearest root, `None`
+    /// for those no root reaches.
+    pub fn depths(&self, roots: &[SymbolId]) -> Vec<Option<usize>> {
+        let mut depth = vec![None; self.len()];
+        let mut frontier = Vec::new();
+        for &root in roots {
+            if depth[root.index()].is_none() {
+                depth[root.index()] = Some(0);
+                frontier.push(root);
+            }
+        }
+        let mut level = 0;
+        while !frontier.is_empty() {
+            level += 1;
+            let mut next = Vec::new();
+            for id in frontier {
+                for &callee in self.callees(id) {
+                    if depth[callee.index()].is_none() {
+                        depth[callee.index()] = Some(level);
+                        next.push(callee);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        depth
+    }
+
+    /// Which symbols the roots reach, roots included, indexed by id.
+    pub fn reachable(&self, roots: &[SymbolId]) -> Vec<bool> {
+        self.depths(roots).iter().map(Option::is_some).collect()
+    }
+
+    /// Call cycles: strongly connected components with more than one
+    /// function, or a single function calling itself. Members are sorted by
+    /// name and the cycles by their first member.
+    pub fn cycles(&self) -> Vec<Vec<SymbolId>> {
+        let mut cycles: Vec<Vec<SymbolId>> = self.components().into_iter()
+            .filter(|c| c.len() > 1 || self.callees(c[0]).contains(&c[0]))
+            .map(|mut c| {
+                c.sort_by(|a, b| self.name(*a).cmp(self.name(*b)));
+                c
+            })
+            .collect();
+        cycles.sort_by(|a, b| self.name(a[0]).cmp(self.name(b[0])));
+        cycles
+    }
+
+    /// All strongly connected components, single functions included, each
+    /// listed after every component it calls into (callees first).
+    pub fn components(&self) -> Vec<Vec<SymbolId>> {
+        const UNVISITED: u32 = u32::MAX;
+        let n = self.len();
+        let mut index = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack: Vec<SymbolId> = Vec::new();
+        let mut next = 0;
+        let mut components = Vec::new();
+
+        // Tarjan's algorithm with an explicit stack of (node, next callee position)
+        for root in 0..n {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            let mut work = vec![(root, 0)];
+            index[root] = next;
+            low[root] = next;
+            next += 1;
+            stack.push(SymbolId::from_index(root));
+            on_stack[root] = true;
+
+            while let Some(&(v, child)) = work.last() {
+                let callees = self.callees(SymbolId::from_index(v));
+                if let Some(&w) = callees.get(child) {
+                    work.last_mut().unwrap().1 += 1;
+                    let w = w.index();
+                    if index[w] == UNVISITED {
+                        index[w] = next;
+                        low[w] = next;
+                        next += 1;
+                        stack.push(SymbolId::from_index(w));
+                        on_stack[w] = true;
+                        work.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                    continue;
+                }
+                work.pop();
+                if let Some(&(u, _)) = work.last() {
+                    low[u] = low[u].min(low[v]);
+                }
+                if low[v] == index[v] {
+                    let mut component = Vec::new();
+                    while let Some(id) = stack.pop() {
+                        on_stack[id.index()] = false;
+                        component.push(id);
+                        if id.index() == v {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    fn graph() -> CallGraph {
+        CallGraph::new(vec![
+            node("app::main", &["app::parse", "std::println"]),
+            node("app::parse", &["app::expr"]),
+            node("app::expr", &["app::term", "app::parse"]),
+            node("app::term", &["app::term"]),
+            node("app::unused", &["app::main"]),
+        ])
+    }
+
+    #[test]
+    fn test_layout() {
+        let cg = CompactGraph::new(&graph());
+        assert_eq!(cg.len(), 6);
+        let main = cg.id("app::main").unwrap();
+        let callees: Vec<&str> = cg.callees(main).iter().map(|&c| cg.name(c)).collect();
+        assert_eq!(callees, vec!["app::parse", "std::println"]);
+        assert!(cg.callees(cg.id("std::println").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_depths_and_reachable() {
+        let cg = CompactGraph::new(&graph());
+        let depths = cg.depths(&cg.ids(["app::main"]));
+        let at = |name: &str| depths[cg.id(name).unwrap().index()];
+        assert_eq!((at("app::main"), at("app::expr"), at("app::term"), at("app::unused")), (Some(0), Some(2), Some(3), None));
+        let reachable = cg.reachable(&cg.ids(["app::main", "app::missing"]));
+        assert_eq!(reachable.iter().filter(|r| **r).count(), 5);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let cg = CompactGraph::new(&graph());
+        let cycles: Vec<Vec<&str>> = cg.cycles().iter()
+            .map(|c| c.iter().map(|&id| cg.name(id)).collect())
+            .collect();
+        assert_eq!(cycles, vec![vec!["app::expr", "app::parse"], vec!["app::term"]]);
+    }
+}
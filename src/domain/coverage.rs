@@ -0,0 +1,273 @@
+//! Test Coverage
+//!
+//! Maps the records of an LCOV report (`cargo llvm-cov --lcov`, grcov, ...)
+//! or of `cargo llvm-cov --json` onto the definition sites of the graph's
+//! nodes, and reports the functions an entry point reaches that the tests
+//! never ran. Report and graph paths are matched on their trailing path
+//! components, so absolute paths in one and relative ones in the other
+//! still line up.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::domain::callgraph::CallGraph;
+use crate::domain::reachability::reachable_from;
+
+/// Lines a function record may sit from the `fn` keyword (attributes,
+/// multi-line signatures) and still be matched to it.
+const FN_LINE_SLACK: usize = 2;
+
+/// Hit counts from a coverage report, per file.
+#[derive(Debug, Clone, Default)]
+pub struct CoverageData {
+    /// Function start line -> times the function ran.
+    functions: HashMap<String, BTreeMap<usize, u64>>,
+    /// Line -> times it ran.
+    lines: HashMap<String, BTreeMap<usize, u64>>,
+}
+
+impl CoverageData {
+    /// Parse an LCOV tracefile or `llvm-cov export` JSON, told apart by content.
+    pub fn parse(text: &str) -> Result<Self> {
+        if text.trim_start().starts_with('{') {
+            Self::parse_llvm_json(text)
+        } else {
+            Self::parse_lcov(text)
+        }
+    }
+
+    pub fn parse_lcov(text: &str) -> Result<Self> {
+        let mut data = CoverageData::default();
+        let mut file: Option<String> = None;
+        // FN names of the current record -> start line
+        let mut fn_lines: HashMap<String, usize> = HashMap::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let (tag, value) = line.split_once(':').unwrap_or((line, ""));
+            let bad = || format!("line {}: malformed `{}` record", n + 1, tag);
+            match tag {
+                "SF" => {
+                    file = Some(normalize(value));
+                    fn_lines.clear();
+                }
+                "FN" => {
+                    let (start, name) = value.split_once(',').with_context(bad)?;
+                    fn_lines.insert(name.to_string(), start.parse().with_context(bad)?);
+                }
+                "FNDA" => {
+                    let (hits, name) = value.split_once(',').with_context(bad)?;
+                    let (Some(file), Some(&start)) = (&file, fn_lines.get(name)) else { continue };
+                    // Monomorphized copies of a generic function share its line
+                    *data.functions.entry(file.clone()).or_default().entry(start).or_default() += hits.parse::<u64>().with_context(bad)?;
+                }
+                "DA" => {
+                    let mut fields = value.split(',');
+                    let (Some(number), Some(hits)) = (fields.next(), fields.next()) else { bail!(bad()) };
+                    let Some(file) = &file else { continue };
+                    let hits = hits.parse::<u64>().with_context(bad)?;
+                    *data.lines.entry(file.clone()).or_default().entry(number.parse().with_context(bad)?).or_default() += hits;
+                }
+                "end_of_record" => file = None,
+                _ => {}
+            }
+        }
+        if data.functions.is_empty() && data.lines.is_empty() {
+            bail!("no coverage records found");
+        }
+        Ok(data)
+    }
+
+    /// `llvm-cov export` format: `data[].functions[]` with their regions and
+    /// `data[].files[].segments`.
+    pub fn parse_llvm_json(text: &str) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(text)?;
+        let Some(exports) = json["data"].as_array() else {
+            bail!("not an llvm-cov export: no `data` array");
+        };
+        let mut data = CoverageData::default();
+        for export in exports {
+            for func in export["functions"].as_array().into_iter().flatten() {
+                // Region: [line_start, col_start, line_end, col_end, count, file_id, ...]
+                let Some(region) = func["regions"].get(0) else { continue };
+                let file_id = region[5].as_u64().unwrap_or(0) as usize;
+                let (Some(file), Some(start)) = (func["filenames"][file_id].as_str(), region[0].as_u64()) else { continue };
+                let hits = func["count"].as_u64().unwrap_or(0);
+                *data.functions.entry(normalize(file)).or_default().entry(start as usize).or_default() += hits;
+            }
+            for file in export["files"].as_array().into_iter().flatten() {
+                let Some(name) = file["filename"].as_str() else { continue };
+                let lines = data.lines.entry(normalize(name)).or_default();
+                // Segment: [line, col, count, has_count, is_region_entry, is_gap_region]
+                for segment in file["segments"].as_array().into_iter().flatten() {
+                    if segment[3].as_bool() == Some(true) && segment[4].as_bool() == Some(true) {
+                        let (line, count) = (segment[0].as_u64().unwrap_or(0) as usize, segment[2].as_u64().unwrap_or(0));
+                        let hits = lines.entry(line).or_default();
+                        *hits = (*hits).max(count);
+                    }
+                }
+            }
+        }
+        if data.functions.is_empty() && data.lines.is_empty() {
+            bail!("no coverage records found");
+        }
+        Ok(data)
+    }
+
+    /// Report file standing for `file`: the same path, or the one sharing
+    /// the most trailing components with it.
+    fn file_key(&self, file: &str) -> Option<&str> {
+        let file = normalize(file);
+        let wanted: Vec<&str> = file.rsplit('/').collect();
+        self.functions.keys().chain(self.lines.keys())
+            .map(|key| (key, key.rsplit('/').zip(&wanted).take_while(|(a, b)| a == *b).count()))
+            .filter(|&(key, common)| common == wanted.len() || common == key.rsplit('/').count())
+            .max_by_key(|&(key, common)| (common, std::cmp::Reverse(key.len())))
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Times the function defined at `file:line` ran: its function record,
+    /// else the hits of its first line.
+    pub fn hits(&self, file: &str, line: usize) -> Option<u64> {
+        let key = self.file_key(file)?;
+        let near = |map: Option<&BTreeMap<usize, u64>>| -> Option<u64> {
+            let range = map?.range(line.saturating_sub(FN_LINE_SLACK)..=line + FN_LINE_SLACK);
+            range.min_by_key(|(start, _)| start.abs_diff(line)).map(|(_, hits)| *hits)
+        };
+        near(self.functions.get(key)).or_else(|| near(self.lines.get(key)))
+    }
+}
+
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_string()
+}
+
+/// Set `coverage` on every node with a matching record; returns how many matched.
+pub fn annotate(cg: &mut CallGraph, data: &CoverageData) -> usize {
+    let mut matched = 0;
+    for node in &mut cg.nodes {
+        let hits = node.location.as_deref()
+            .and_then(|loc| loc.rsplit_once(':'))
+            .and_then(|(file, line)| data.hits(file, line.parse().ok()?));
+        node.coverage = hits;
+        matched += hits.is_some() as usize;
+    }
+    matched
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UncoveredFn {
+    pub id: String,
+    pub location: Option<String>,
+}
+
+/// Functions the roots reach that no test ran.
+#[derive(Debug, Clone, Serialize)]
+pub struct UncoveredReport {
+    pub roots: Vec<String>,
+    /// Reachable functions with coverage data.
+    pub reachable_functions: usize,
+    pub covered: usize,
+    pub uncovered: Vec<UncoveredFn>,
+}
+
+/// Report the reachable functions whose coverage is zero. Closures count
+/// through their owner and functions without data are left out.
+pub fn uncovered_reachable(cg: &CallGraph, roots: &[String]) -> UncoveredReport {
+    let reachable = reachable_from(cg, roots);
+    let measured: Vec<_> = cg.nodes.iter()
+        .filter(|n| reachable.contains(n.id.as_str()) && !n.id.contains('{'))
+        .filter_map(|n| Some((n, n.coverage?)))
+        .collect();
+    let mut uncovered: Vec<UncoveredFn> = measured.iter()
+        .filter(|(_, hits)| *hits == 0)
+        .map(|(n, _)| UncoveredFn { id: n.id.to_string(), location: n.location.clone() })
+        .collect();
+    uncovered.sort_by(|a, b| a.id.cmp(&b.id));
+    UncoveredReport {
+        roots: roots.to_vec(),
+        reachable_functions: measured.len(),
+        covered: measured.len() - uncovered.len(),
+        uncovered,
+    }
+}
+
+impl fmt::Display for UncoveredReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Uncovered functions reachable from {}: {} of {} ({} covered)",
+            self.roots.join(", "), self.uncovered.len(), self.reachable_functions, self.covered)?;
+        for u in &self.uncovered {
+            match &u.location {
+                Some(location) => writeln!(f, "  {} ({})", u.id, location)?,
+                None => writeln!(f, "  {}", u.id)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    use crate::domain::callgraph::CallGraphNode;
+
+    fn node(id: &str, location: Option<&str>, callees: &[&str]) -> CallGraphNode {
+        CallGraphNode { location: location.map(str::to_string), ..fixtures::node(id, callees) }
+    }
+
+    const LCOV: &str = "TN:\n\
+        SF:/home/ci/app/src/main.rs\n\
+        FN:1,_RNvCs_3app4main\n\
+        FN:5,_RNvCs_3app6helper\n\
+        FN:9,_RNvCs_3app4dead\n\
+        FNDA:1,_RNvCs_3app4main\n\
+        FNDA:4,_RNvCs_3app6helper\n\
+        FNDA:0,_RNvCs_3app4dead\n\
+        DA:1,1\n\
+        DA:13,0\n\
+        end_of_record\n";
+
+    #[test]
+    fn test_lcov_annotation_and_report() {
+        let data = CoverageData::parse(LCOV).unwrap();
+        let mut cg = CallGraph::new(vec![
+            node("app::main", Some("src/main.rs:1"), &["app::helper", "app::dead", "app::lonely"]),
+            node("app::helper", Some("src/main.rs:6"), &[]),
+            node("app::dead", Some("src/main.rs:9"), &[]),
+            node("app::lonely", Some("src/main.rs:13"), &[]),
+            node("app::other", Some("src/lib.rs:1"), &[]),
+            node("app::unused", Some("src/main.rs:30"), &[]),
+        ]);
+        assert_eq!(annotate(&mut cg, &data), 4);
+        let hits: Vec<Option<u64>> = cg.nodes.iter().map(|n| n.coverage).collect();
+        // `helper`'s record is a line off; `lonely` falls back to line data
+        assert_eq!(hits, vec![Some(1), Some(4), Some(0), Some(0), None, None]);
+
+        let report = uncovered_reachable(&cg, &["app::main".to_string()]);
+        assert_eq!((report.reachable_functions, report.covered), (4, 2));
+        let ids: Vec<&str> = report.uncovered.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::dead", "app::lonely"]);
+        assert!(report.to_string().contains("  app::dead (src/main.rs:9)"), "{}", report);
+    }
+
+    #[test]
+    fn test_llvm_json() {
+        let json = r#"{"type": "llvm.coverage.json.export", "data": [{
+            "functions": [
+                {"name": "_RNvCs_3app4main", "count": 2, "filenames": ["/ci/app/src/main.rs"], "regions": [[3, 1, 6, 2, 2, 0, 0, 0]]},
+                {"name": "_RNvCs_3app4dead", "count": 0, "filenames": ["/ci/app/src/main.rs"], "regions": [[8, 1, 8, 14, 0, 0, 0, 0]]}
+            ],
+            "files": [{"filename": "/ci/app/src/main.rs", "segments": [[11, 5, 7, true, true, false]]}]
+        }]}"#;
+        let data = CoverageData::parse(json).unwrap();
+        assert_eq!(data.hits("src/main.rs", 3), Some(2));
+        assert_eq!(data.hits("./src/main.rs", 8), Some(0));
+        assert_eq!(data.hits("src/main.rs", 11), Some(7));
+        assert_eq!(data.hits("other/main.rs", 3), None);
+        assert!(CoverageData::parse("{\"data\": []}").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Graph Diffing
+//!
+//! Compares two call graphs by node id and by `caller -> callee` pair, so a
+//! rebuild can report what changed. Edges that only moved to another line
+//! are not counted as changes.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::domain::callgraph::CallGraph;
+
+/// Nodes and edges that differ between two graphs, each list sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub added_edges: Vec<(String, String)>,
+    pub removed_edges: Vec<(String, String)>,
+}
+
+impl GraphDiff {
+    pub fn between(old: &CallGraph, new: &CallGraph) -> Self {
+        let node_ids = |cg: &CallGraph| cg.nodes.iter().map(|n| n.id.to_string()).collect::<BTreeSet<_>>();
+        let edges = |cg: &CallGraph| cg.nodes.iter()
+            .flat_map(|n| n.callee_ids().map(move |c| (n.id.to_string(), c.to_string())))
+            .collect::<BTreeSet<_>>();
+        let (old_nodes, new_nodes) = (node_ids(old), node_ids(new));
+        let (old_edges, new_edges) = (edges(old), edges(new));
+
+        GraphDiff {
+            added_nodes: new_nodes.difference(&old_nodes).cloned().collect(),
+            removed_nodes: old_nodes.difference(&new_nodes).cloned().collect(),
+            added_edges: new_edges.difference(&old_edges).cloned().collect(),
+            removed_edges: old_edges.difference(&new_edges).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty() && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty() && self.removed_edges.is_empty()
+    }
+}
+
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Graph changes: +{} / -{} nodes, +{} / -{} edges",
+            self.added_nodes.len(), self.removed_nodes.len(),
+            self.added_edges.len(), self.removed_edges.len())?;
+        for id in &self.added_nodes {
+            writeln!(f, "  + {}", id)?;
+        }
+        for id in &self.removed_nodes {
+            writeln!(f, "  - {}", id)?;
+        }
+        for (from, to) in &self.added_edges {
+            writeln!(f, "  + {} -> {}", from, to)?;
+        }
+        for (from, to) in &self.removed_edges {
+            writeln!(f, "  - {} -> {}", from, to)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    use crate::domain::callgraph::{CallEdge, CallGraphNode};
+
+    fn node(id: &str, callees: &[(&str, usize)]) -> CallGraphNode {
+        let callees = callees.iter().map(|(c, line)| CallEdge::at(*c, "src/lib.rs", *line)).collect();
+        CallGraphNode { callees, ..fixtures::node(id, &[]) }
+    }
+
+    #[test]
+    fn test_diff_nodes_and_edges() {
+        let old = CallGraph::new(vec![
+            node("app::main", &[("app::a", 2), ("app::b", 3)]),
+            node("app::a", &[]),
+            node("app::b", &[]),
+        ]);
+        let new = CallGraph::new(vec![
+            // `a` moved down a line: not a change
+            node("app::main", &[("app::a", 5), ("app::c", 6)]),
+            node("app::a", &[]),
+            node("app::c", &[]),
+        ]);
+
+        let diff = GraphDiff::between(&old, &new);
+        assert_eq!(diff.added_nodes, vec!["app::c"]);
+        assert_eq!(diff.removed_nodes, vec!["app::b"]);
+        assert_eq!(diff.added_edges, vec![("app::main".to_string(), "app::c".to_string())]);
+        assert_eq!(diff.removed_edges, vec![("app::main".to_string(), "app::b".to_string())]);
+        assert!(GraphDiff::between(&new, &new).is_empty());
+    }
+}
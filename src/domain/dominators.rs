@@ -0,0 +1,238 @@
+//! Dominator Tree
+//!
+//! A function dominates another when every call path from the entry points
+//! to the other passes through it. The functions dominating others are the
+//! gatekeepers of the code below them: one probe, log line or feature check
+//! there covers everything they dominate. Computed over call edges with the
+//! iterative algorithm of Cooper, Harvey and Kennedy; with several entry
+//! points a virtual root stands above them.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::domain::callgraph::CallGraph;
+use crate::domain::compact::CompactGraph;
+use crate::domain::interner::SymbolId;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DominatorNode {
+    pub id: String,
+    /// Immediate dominator; `None` for the entry points and, with several,
+    /// for functions they reach independently.
+    pub idom: Option<String>,
+    /// Functions it dominates, itself excluded.
+    pub dominates: usize,
+    /// Depth in the tree, 0 for the entry points.
+    pub depth: usize,
+}
+
+/// The dominator tree of the functions the entry points reach.
+#[derive(Debug, Clone, Serialize)]
+pub struct DominatorReport {
+    pub entries: Vec<String>,
+    /// Every reached function in tree preorder, children by id.
+    pub nodes: Vec<DominatorNode>,
+    /// Functions other than the entries dominating at least one other,
+    /// most dominated first.
+    pub gatekeepers: Vec<String>,
+}
+
+/// Dominator tree of everything `entries` reach.
+pub fn dominator_tree(cg: &CallGraph, entries: &[String]) -> DominatorReport {
+    let compact = CompactGraph::new(cg);
+    let n = compact.len();
+    let root = n;
+    let roots: Vec<usize> = compact.ids(entries.iter().map(String::as_str)).iter().map(|id| id.index()).collect();
+    let successors = |v: usize| -> Vec<usize> {
+        if v == root {
+            roots.clone()
+        } else {
+            compact.callees(SymbolId::from_index(v)).iter().map(|id| id.index()).collect()
+        }
+    };
+
+    // Reverse postorder from the virtual root
+    let mut order = Vec::new();
+    let mut visited = vec![false; n + 1];
+    let mut stack = vec![(root, successors(root), 0)];
+    visited[root] = true;
+    while let Some((v, succ, next)) = stack.last_mut() {
+        if let Some(&w) = succ.get(*next) {
+            *next += 1;
+            if !visited[w] {
+                visited[w] = true;
+                stack.push((w, successors(w), 0));
+            }
+            continue;
+        }
+        order.push(*v);
+        stack.pop();
+    }
+    order.reverse();
+    let mut rpo = vec![usize::MAX; n + 1];
+    for (i, &v) in order.iter().enumerate() {
+        rpo[v] = i;
+    }
+    let mut preds: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+    for &v in &order {
+        for w in successors(v) {
+            preds[w].push(v);
+        }
+    }
+
+    const UNDEFINED: usize = usize::MAX;
+    let mut idom = vec![UNDEFINED; n + 1];
+    idom[root] = root;
+    let intersect = |idom: &[usize], mut a: usize, mut b: usize| {
+        while a != b {
+            while rpo[a] > rpo[b] {
+                a = idom[a];
+            }
+            while rpo[b] > rpo[a] {
+                b = idom[b];
+            }
+        }
+        a
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &v in order.iter().skip(1) {
+            let mut new_idom = UNDEFINED;
+            for &p in &preds[v] {
+                if idom[p] == UNDEFINED {
+                    continue;
+                }
+                new_idom = if new_idom == UNDEFINED { p } else { intersect(&idom, p, new_idom) };
+            }
+            if idom[v] != new_idom {
+                idom[v] = new_idom;
+                changed = true;
+            }
+        }
+    }
+
+    // Tree preorder, children sorted by id, counting each subtree
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+    for &v in order.iter().skip(1) {
+        children[idom[v]].push(v);
+    }
+    let name = |v: usize| compact.name(SymbolId::from_index(v));
+    for list in &mut children {
+        list.sort_by(|a, b| name(*a).cmp(name(*b)));
+    }
+    let mut size = vec![0usize; n + 1];
+    for &v in order.iter().rev() {
+        size[v] = 1 + children[v].iter().map(|&c| size[c]).sum::<usize>();
+    }
+    let mut nodes = Vec::with_capacity(order.len().saturating_sub(1));
+    let mut stack: Vec<(usize, usize)> = children[root].iter().rev().map(|&c| (c, 0)).collect();
+    while let Some((v, depth)) = stack.pop() {
+        nodes.push(DominatorNode {
+            id: name(v).to_string(),
+            idom: (idom[v] != root).then(|| name(idom[v]).to_string()),
+            dominates: size[v] - 1,
+            depth,
+        });
+        stack.extend(children[v].iter().rev().map(|&c| (c, depth + 1)));
+    }
+
+    let mut gatekeepers: Vec<&DominatorNode> = nodes.iter().filter(|d| d.idom.is_some() && d.dominates > 0).collect();
+    gatekeepers.sort_by(|a, b| b.dominates.cmp(&a.dominates).then_with(|| a.id.cmp(&b.id)));
+    let gatekeepers = gatekeepers.into_iter().map(|d| d.id.clone()).collect();
+    DominatorReport { entries: entries.to_vec(), nodes, gatekeepers }
+}
+
+impl DominatorReport {
+    /// The tree as Graphviz DOT, edges from each dominator to the functions
+    /// it immediately dominates; gatekeepers are filled.
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph Dominators {".to_string(),
+            "    node [shape=box, style=rounded];".to_string(),
+        ];
+        for d in &self.nodes {
+            let fill = if self.gatekeepers.contains(&d.id) { ", style=\"rounded,filled\", fillcolor=\"#fde2b3\"" } else { "" };
+            let label = match d.dominates {
+                0 => d.id.clone(),
+                n => format!("{}\\n{} dominated", d.id, n),
+            };
+            lines.push(format!("    \"{}\" [label=\"{}\"{}];", d.id, label, fill));
+        }
+        for d in &self.nodes {
+            if let Some(idom) = &d.idom {
+                lines.push(format!("    \"{}\" -> \"{}\";", idom, d.id));
+            }
+        }
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+}
+
+impl fmt::Display for DominatorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Dominator tree from {}: {} functions", self.entries.join(", "), self.nodes.len())?;
+        for d in &self.nodes {
+            let indent = "  ".repeat(d.depth + 1);
+            match d.dominates {
+                0 => writeln!(f, "{}{}", indent, d.id)?,
+                n => writeln!(f, "{}{} ({})", indent, d.id, n)?,
+            }
+        }
+        if !self.gatekeepers.is_empty() {
+            writeln!(f, "Gatekeepers (every path to the functions they dominate passes through them):")?;
+        }
+        for id in &self.gatekeepers {
+            let dominates = self.nodes.iter().find(|d| &d.id == id).map_or(0, |d| d.dominates);
+            writeln!(f, "  {:>5}  {}", dominates, id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    #[test]
+    fn test_dominator_tree() {
+        // main -> dispatch -> {a, b}; a -> log; b -> log; main -> init -> log; dispatch <- a (loop)
+        let cg = CallGraph::new(vec![
+            node("main", &["dispatch", "init"]),
+            node("dispatch", &["a", "b"]),
+            node("a", &["log", "dispatch"]),
+            node("b", &["log"]),
+            node("init", &["log"]),
+            node("log", &[]),
+            node("unused", &["log"]),
+        ]);
+        let report = dominator_tree(&cg, &["main".to_string()]);
+        let tree: Vec<(&str, Option<&str>, usize, usize)> = report.nodes.iter()
+            .map(|d| (d.id.as_str(), d.idom.as_deref(), d.dominates, d.depth))
+            .collect();
+        assert_eq!(tree, vec![
+            ("main", None, 5, 0),
+            ("dispatch", Some("main"), 2, 1),
+            ("a", Some("dispatch"), 0, 2),
+            ("b", Some("dispatch"), 0, 2),
+            ("init", Some("main"), 0, 1),
+            ("log", Some("main"), 0, 1),
+        ]);
+        assert_eq!(report.gatekeepers, vec!["dispatch"]);
+
+        let text = report.to_string();
+        assert!(text.contains("  main (5)\n    dispatch (2)\n      a\n"), "{}", text);
+        assert!(text.contains("      2  dispatch"), "{}", text);
+        let dot = report.to_dot();
+        assert!(dot.contains("\"dispatch\" [label=\"dispatch\\n2 dominated\", style=\"rounded,filled\", fillcolor=\"#fde2b3\"];"), "{}", dot);
+        assert!(dot.contains("\"main\" -> \"log\";"), "{}", dot);
+
+        // Two entries: nothing dominates both
+        let report = dominator_tree(&cg, &["main".to_string(), "unused".to_string()]);
+        let log = report.nodes.iter().find(|d| d.id == "log").unwrap();
+        assert_eq!((log.idom.as_deref(), log.depth), (None, 0));
+    }
+}
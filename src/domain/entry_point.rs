@@ -89,26 +89,24 @@ impl EntryPointDetector {
             }
             
             // #[test]
-            if trimmed.starts_with("fn test_") || trimmed.starts_with("async fn test_") {
-                if line_num > 0 {
-                    let prev_line = source.lines().nth(line_num - 1).unwrap_or("");
-                    if prev_line.contains("#[test]") || prev_line.contains("#[tokio::test]") {
-                        let fn_name = trimmed
-                            .split('(')
-                            .next()
-                            .unwrap_or("test")
-                            .replace("fn ", "")
-                            .replace("async ", "")
-                            .trim()
-                            .to_string();
-                        entries.push(EntryPoint {
-                            id: format!("{}::{}", file_path, fn_name),
-                            name: fn_name,
-                            kind: EntryPointKind::Test,
-                            file_path: file_path.to_string(),
-                            line: Some(line_num + 1),
-                        });
-                    }
+            if (trimmed.starts_with("fn test_") || trimmed.starts_with("async fn test_")) && line_num > 0 {
+                let prev_line = source.lines().nth(line_num - 1).unwrap_or("");
+                if prev_line.contains("#[test]") || prev_line.contains("#[tokio::test]") {
+                    let fn_name = trimmed
+                        .split('(')
+                        .next()
+                        .unwrap_or("test")
+                        .replace("fn ", "")
+                        .replace("async ", "")
+                        .trim()
+                        .to_string();
+                    entries.push(EntryPoint {
+                        id: format!("{}::{}", file_path, fn_name),
+                        name: fn_name,
+                        kind: EntryPointKind::Test,
+                        file_path: file_path.to_string(),
+                        line: Some(line_num + 1),
+                    });
                 }
             }
         }
@@ -0,0 +1,144 @@
+//! External Dependencies
+//!
+//! Calls into crates that are not analyzed (`serde_json::from_str`,
+//! `tokio::spawn`) resolve to ids no node carries. [`add_external_stubs`]
+//! gives each such target a stub node tagged with the crate it belongs to;
+//! [`collapse_external`] folds all stubs of a crate into a single node.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::filter::glob_match;
+
+/// Crate of stubs whose origin cannot be told from the id (unresolved
+/// method calls like `unwrap@app`, bare names like `drop@app`).
+pub const UNKNOWN_CRATE: &str = "?";
+
+/// Path a stub stands for: the id without the `@caller_crate` suffix the
+/// builder appends to unresolved calls.
+fn stub_path(id: &str) -> &str {
+    id.rsplit_once('@').map_or(id, |(path, _)| path)
+}
+
+/// Crate an unresolved callee belongs to: the first segment of a qualified
+/// path (`serde_json` for `serde_json::from_str@app`), or [`UNKNOWN_CRATE`].
+pub fn external_crate(id: &str) -> String {
+    match stub_path(id).split_once("::") {
+        // `Type::method` is a method on an unknown type, not a crate path
+        Some((first, _)) if first.starts_with(|c: char| c.is_ascii_lowercase()) => first.to_string(),
+        _ => UNKNOWN_CRATE.to_string(),
+    }
+}
+
+fn stub(id: String, label: String, krate: String) -> CallGraphNode {
+    CallGraphNode {
+        id: id.into(),
+        label: Some(label),
+        external: Some(krate),
+        ..Default::default()
+    }
+}
+
+/// Add a stub node for every edge target that has no node, in first-seen order.
+pub fn add_external_stubs(cg: &CallGraph) -> CallGraph {
+    let mut known: HashSet<&str> = cg.nodes.iter().map(|n| n.id.as_str()).collect();
+    let mut stubs = Vec::new();
+    for target in cg.nodes.iter().flat_map(|n| n.callee_ids()) {
+        if known.insert(target) {
+            stubs.push(stub(target.to_string(), stub_path(target).to_string(), external_crate(target)));
+        }
+    }
+    let mut nodes = cg.nodes.clone();
+    nodes.extend(stubs);
+    CallGraph::new(nodes)
+}
+
+/// Id of the node standing for all of `krate` after collapsing.
+pub fn collapsed_id(krate: &str) -> String {
+    format!("{}::*", krate)
+}
+
+/// Replace the stubs of every external crate matching one of `crates`
+/// (globs, `*` for all) with one node per crate. Each caller keeps its
+/// first edge into the crate.
+pub fn collapse_external(cg: &CallGraph, crates: &[String]) -> CallGraph {
+    let collapsed: BTreeMap<&str, String> = cg.nodes.iter()
+        .filter_map(|n| Some((n.id.as_str(), n.external.as_deref()?)))
+        .filter(|(_, krate)| crates.iter().any(|pat| glob_match(pat, krate)))
+        .map(|(id, krate)| (id, krate.to_string()))
+        .collect();
+    if collapsed.is_empty() {
+        return cg.clone();
+    }
+
+    let mut nodes = Vec::new();
+    for node in cg.nodes.iter().filter(|n| !collapsed.contains_key(n.id.as_str())) {
+        let mut seen = HashSet::new();
+        let callees = node.callees.iter()
+            .filter_map(|edge| match collapsed.get(edge.target.as_str()) {
+                Some(krate) => seen.insert(krate).then(|| {
+                    let mut edge = edge.clone();
+                    edge.target = collapsed_id(krate).into();
+                    edge
+                }),
+                None => Some(edge.clone()),
+            })
+            .collect();
+        nodes.push(CallGraphNode { callees, ..node.clone() });
+    }
+    let crates: BTreeSet<&String> = collapsed.values().collect();
+    nodes.extend(crates.into_iter().map(|krate| stub(collapsed_id(krate), krate.clone(), krate.clone())));
+    CallGraph::new(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    use crate::domain::callgraph::CallEdge;
+
+    fn graph() -> CallGraph {
+        let node = |id: &str, callees: Vec<CallEdge>| CallGraphNode { callees, ..fixtures::node(id, &[]) };
+        CallGraph::new(vec![
+            node("app::main", vec![
+                CallEdge::at("serde_json::from_str@app", "src/main.rs", 2),
+                CallEdge::at("serde_json::to_string@app", "src/main.rs", 3),
+                CallEdge::at("tokio::spawn@app", "src/main.rs", 4),
+                CallEdge::at("unwrap@app", "src/main.rs", 5),
+                CallEdge::at("app::helper", "src/main.rs", 6),
+            ]),
+            node("app::helper", vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_external_crate() {
+        assert_eq!(external_crate("serde_json::from_str@app"), "serde_json");
+        assert_eq!(external_crate("std::mem::swap@app"), "std");
+        assert_eq!(external_crate("Vec::new@app"), UNKNOWN_CRATE);
+        assert_eq!(external_crate("unwrap@app"), UNKNOWN_CRATE);
+    }
+
+    #[test]
+    fn test_stubs_and_collapse() {
+        let with_stubs = add_external_stubs(&graph());
+        let stubs: Vec<(&str, &str, &str)> = with_stubs.nodes.iter()
+            .filter_map(|n| Some((n.id.as_str(), n.label.as_deref()?, n.external.as_deref()?)))
+            .collect();
+        assert_eq!(stubs, vec![
+            ("serde_json::from_str@app", "serde_json::from_str", "serde_json"),
+            ("serde_json::to_string@app", "serde_json::to_string", "serde_json"),
+            ("tokio::spawn@app", "tokio::spawn", "tokio"),
+            ("unwrap@app", "unwrap", UNKNOWN_CRATE),
+        ]);
+        // Only stubs carry a crate; the analyzed functions do not
+        assert!(with_stubs.nodes[..2].iter().all(|n| n.external.is_none()));
+
+        let collapsed = collapse_external(&with_stubs, &["serde_*".to_string()]);
+        let main = &collapsed.nodes[0];
+        assert_eq!(main.callee_ids().collect::<Vec<_>>(), vec!["serde_json::*", "tokio::spawn@app", "unwrap@app", "app::helper"]);
+        assert_eq!(main.callees[0].line, Some(2));
+        let ids: Vec<&str> = collapsed.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::main", "app::helper", "tokio::spawn@app", "unwrap@app", "serde_json::*"]);
+    }
+}
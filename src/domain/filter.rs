@@ -0,0 +1,362 @@
+//! Graph Filtering
+//!
+//! Keeps only the nodes whose ids match `--include` globs and none of the
+//! `--exclude` globs, optionally dropping test code (`--exclude-tests`) and
+//! the code of other targets (`--exclude-origin`). Edges into removed nodes
+//! are dropped, or redirected to a single `external` node when collapsing.
+//! Also extracts the neighborhood around a single function (`--around`) and
+//! condenses call cycles into one node each (`--condense`).
+
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode};
+use crate::domain::compact::CompactGraph;
+use crate::domain::source::SourceOrigin;
+
+/// Id of the node that stands in for everything filtered out.
+pub const EXTERNAL_NODE_ID: &str = "external";
+
+/// `*` matches any run of characters (including `::`), `?` matches one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last `*` and the text index it currently absorbs up to
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Include/exclude filter over node ids.
+#[derive(Debug, Clone, Default)]
+pub struct GraphFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Redirect edges into removed nodes to [`EXTERNAL_NODE_ID`] instead of dropping them.
+    pub collapse_external: bool,
+    /// Drop `#[test]` functions, everything under `#[cfg(test)]` and the
+    /// code of integration tests.
+    pub exclude_tests: bool,
+    /// Drop the code of these targets (benches, examples, ...).
+    pub exclude_origins: Vec<SourceOrigin>,
+}
+
+impl GraphFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && !self.exclude_tests && self.exclude_origins.is_empty()
+    }
+
+    /// Whether a node id survives the filter. Method ids (`Type::m@crate`) are
+    /// also tested as `crate::Type::m` so crate globs like `my_crate::*` cover them.
+    pub fn keeps(&self, id: &str) -> bool {
+        let alt = id.rsplit_once('@').map(|(path, krate)| format!("{}::{}", krate, path));
+        let matches = |pat: &String| glob_match(pat, id) || alt.as_deref().is_some_and(|a| glob_match(pat, a));
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    pub fn apply(&self, cg: &CallGraph) -> CallGraph {
+        if self.is_empty() {
+            return cg.clone();
+        }
+
+        let dropped_origin = |origin: Option<SourceOrigin>| origin.is_some_and(|o| {
+            self.exclude_origins.contains(&o) || (self.exclude_tests && o == SourceOrigin::Test)
+        });
+        let tests: HashSet<&str> = cg.nodes.iter()
+            .filter(|n| (self.exclude_tests && n.is_test) || dropped_origin(n.origin))
+            .map(|n| n.id.as_str())
+            .collect();
+        let keeps = |id: &str| self.keeps(id) && !tests.contains(id);
+
+        let mut nodes = Vec::new();
+        let mut any_external = false;
+        for node in cg.nodes.iter().filter(|n| keeps(&n.id)) {
+            let mut callees = Vec::new();
+            let mut external_seen = false;
+            for edge in &node.callees {
+                if keeps(&edge.target) {
+                    callees.push(edge.clone());
+                } else if self.collapse_external && !external_seen {
+                    // One edge per caller is enough to show it leaves the filtered set
+                    external_seen = true;
+                    callees.push(CallEdge { target: EXTERNAL_NODE_ID.into(), ..edge.clone() });
+                }
+            }
+            any_external |= external_seen;
+            nodes.push(CallGraphNode { callees, ..node.clone() });
+        }
+
+        if any_external {
+            let ids: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+            if !ids.contains(EXTERNAL_NODE_ID) {
+                nodes.push(CallGraphNode {
+                    id: EXTERNAL_NODE_ID.into(),
+                    label: Some("(external)".to_string()),
+                    ..Default::default()
+                });
+            }
+        }
+
+        CallGraph::new(nodes)
+    }
+}
+
+/// Subgraph of everything within `depth` hops of `center`: callees reached
+/// by following edges forward and callers reached by following them backward.
+/// Only edges between kept nodes survive.
+pub fn neighborhood(cg: &CallGraph, center: &str, depth: usize) -> CallGraph {
+    let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for node in &cg.nodes {
+        for callee in node.callee_ids() {
+            callers.entry(callee).or_default().push(node.id.as_str());
+        }
+    }
+    let callees: HashMap<&str, Vec<&str>> = cg.nodes.iter()
+        .map(|n| (n.id.as_str(), n.callee_ids().collect()))
+        .collect();
+
+    let mut keep: HashSet<&str> = HashSet::from([center]);
+    for adj in [&callees, &callers] {
+        let mut frontier = vec![center];
+        let mut seen: HashSet<&str> = HashSet::from([center]);
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for id in frontier {
+                for n in adj.get(id).into_iter().flatten() {
+                    if seen.insert(n) {
+                        next.push(*n);
+                    }
+                }
+            }
+            keep.extend(next.iter().copied());
+            frontier = next;
+        }
+    }
+
+    subgraph(cg, &keep)
+}
+
+/// The nodes in `keep` and the edges between them.
+pub fn subgraph(cg: &CallGraph, keep: &HashSet<&str>) -> CallGraph {
+    let nodes = cg.nodes.iter()
+        .filter(|n| keep.contains(n.id.as_str()))
+        .map(|n| CallGraphNode {
+            callees: n.callees.iter().filter(|e| keep.contains(e.target.as_str())).cloned().collect(),
+            ..n.clone()
+        })
+        .collect();
+    CallGraph::new(nodes)
+}
+
+/// Id of the node standing for the call cycle `first` (its first member by
+/// name) belongs to: `app::parse::{cycle}`, `Parser::expr::{cycle}@app`.
+pub fn cycle_id(first: &str) -> String {
+    match first.rsplit_once('@') {
+        Some((path, krate)) => format!("{}::{{cycle}}@{}", path, krate),
+        None => format!("{}::{{cycle}}", first),
+    }
+}
+
+/// Collapse every group of mutually recursive functions into one node
+/// labeled with its members, so what calls into the cycle and what it calls
+/// stay connected and the edges inside it are dropped. The cycle node has no
+/// location, metrics, signature or doc of its own; flags are combined and a
+/// `cfg` or origin is kept only when every member shares it. Functions
+/// calling only themselves are kept as they are.
+pub fn condense(cg: &CallGraph) -> CallGraph {
+    let compact = CompactGraph::new(cg);
+    let mut cycle_of: HashMap<&str, usize> = HashMap::new();
+    let mut cycles: Vec<Vec<&str>> = Vec::new();
+    for members in compact.cycles().into_iter().filter(|c| c.len() > 1) {
+        let members: Vec<&str> = members.into_iter().map(|id| compact.name(id)).collect();
+        for id in &members {
+            cycle_of.insert(id, cycles.len());
+        }
+        cycles.push(members);
+    }
+    if cycles.is_empty() {
+        return cg.clone();
+    }
+
+    let ids: Vec<String> = cycles.iter().map(|members| cycle_id(members[0])).collect();
+    let node_of = |id: &str| cycle_of.get(id).copied();
+    let mut nodes: Vec<CallGraphNode> = Vec::with_capacity(cg.nodes.len());
+    let mut placed: HashMap<usize, usize> = HashMap::new();
+    for node in &cg.nodes {
+        let cycle = node_of(&node.id);
+        let callees = node.callees.iter()
+            .filter(|e| cycle.is_none() || node_of(&e.target) != cycle)
+            .map(|e| match node_of(&e.target) {
+                Some(target) => CallEdge { target: ids[target].as_str().into(), ..e.clone() },
+                None => e.clone(),
+            });
+        let Some(cycle) = cycle else {
+            nodes.push(CallGraphNode { callees: callees.collect(), ..node.clone() });
+            continue;
+        };
+        if let Some(&at) = placed.get(&cycle) {
+            let merged = &mut nodes[at];
+            merged.callees.extend(callees);
+            merged.is_public |= node.is_public;
+            merged.is_async |= node.is_async;
+            merged.is_unsafe |= node.is_unsafe;
+            merged.is_test &= node.is_test;
+            merged.is_test_fn |= node.is_test_fn;
+            merged.unsafe_blocks += node.unsafe_blocks;
+            merged.panics.extend(node.panics.iter().cloned());
+            if merged.cfg != node.cfg {
+                merged.cfg = None;
+            }
+            if merged.origin != node.origin {
+                merged.origin = None;
+            }
+            continue;
+        }
+        placed.insert(cycle, nodes.len());
+        let members = &cycles[cycle];
+        nodes.push(CallGraphNode {
+            id: ids[cycle].as_str().into(),
+            callees: callees.collect(),
+            label: Some(format!("cycle: {}", members.join(", "))),
+            location: None,
+            metrics: None,
+            coverage: None,
+            signature: None,
+            doc: None,
+            ..node.clone()
+        });
+    }
+    CallGraph::new(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("my_crate::*", "my_crate::a::b"));
+        assert!(glob_match("*::helper", "app::utils::helper"));
+        assert!(glob_match("std::*", "std::mem::swap@app"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("my_crate::*", "other::f"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_filter_drop_and_collapse() {
+        let cg = CallGraph::new(vec![
+            node("app::main", &["app::helper", "std::mem::swap@app", "lib::f"]),
+            node("app::helper", &[]),
+            node("Tool::run@app", &["lib::f"]),
+            node("lib::f", &[]),
+        ]);
+
+        let filter = GraphFilter {
+            include: vec!["app::*".to_string()],
+            exclude: vec!["std::*".to_string()],
+            collapse_external: false,
+            exclude_tests: false,
+            exclude_origins: Vec::new(),
+        };
+        let out = filter.apply(&cg);
+        let ids: Vec<&str> = out.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::main", "app::helper", "Tool::run@app"]);
+        assert_eq!(out.nodes[0].callee_ids().collect::<Vec<_>>(), vec!["app::helper"]);
+
+        let collapsed = GraphFilter { collapse_external: true, ..filter }.apply(&cg);
+        assert_eq!(collapsed.nodes[0].callee_ids().collect::<Vec<_>>(), vec!["app::helper", EXTERNAL_NODE_ID]);
+        assert_eq!(collapsed.nodes[2].callee_ids().collect::<Vec<_>>(), vec![EXTERNAL_NODE_ID]);
+        assert!(collapsed.nodes.iter().any(|n| n.id == EXTERNAL_NODE_ID));
+    }
+
+    #[test]
+    fn test_exclude_tests() {
+        let cg = CallGraph::new(vec![
+            node("app::run", &["app::helper"]),
+            node("app::helper", &[]),
+            CallGraphNode { is_test: true, ..node("app::tests::test_run", &["app::run"]) },
+            CallGraphNode { origin: Some(SourceOrigin::Test), ..node("app::setup", &["app::run"]) },
+            CallGraphNode { origin: Some(SourceOrigin::Example), ..node("app::demo", &["app::run"]) },
+        ]);
+        let ids = |filter: GraphFilter| filter.apply(&cg).nodes.into_iter().map(|n| n.id).collect::<Vec<_>>();
+        assert_eq!(ids(GraphFilter { exclude_tests: true, ..Default::default() }), vec!["app::run", "app::helper", "app::demo"]);
+        let no_examples = GraphFilter { exclude_origins: vec![SourceOrigin::Example], ..Default::default() };
+        assert_eq!(ids(no_examples), vec!["app::run", "app::helper", "app::tests::test_run", "app::setup"]);
+    }
+
+    #[test]
+    fn test_neighborhood() {
+        // a -> b -> c -> d, x -> b
+        let cg = CallGraph::new(vec![
+            node("a", &["b"]),
+            node("b", &["c"]),
+            node("c", &["d"]),
+            node("d", &[]),
+            node("x", &["b"]),
+        ]);
+        let ids = |g: &CallGraph| g.nodes.iter().map(|n| n.id.clone()).collect::<Vec<_>>();
+
+        assert_eq!(ids(&neighborhood(&cg, "b", 0)), vec!["b"]);
+        assert_eq!(ids(&neighborhood(&cg, "b", 1)), vec!["a", "b", "c", "x"]);
+        let two = neighborhood(&cg, "b", 2);
+        assert_eq!(ids(&two), vec!["a", "b", "c", "d", "x"]);
+
+        // Callers of callers are not pulled in through the callee direction
+        let from_c = neighborhood(&cg, "c", 1);
+        assert_eq!(ids(&from_c), vec!["b", "c", "d"]);
+        assert_eq!(from_c.nodes[0].callee_ids().collect::<Vec<_>>(), vec!["c"]);
+    }
+
+    #[test]
+    fn test_condense() {
+        // main -> expr <-> term <-> factor -> lit, rec -> rec
+        let at = |id: &str, callees: &[&str], line: usize, cfg: &str| CallGraphNode {
+            location: Some(format!("src/lib.rs:{}", line)),
+            cfg: Some(cfg.to_string()),
+            ..node(id, callees)
+        };
+        let cg = CallGraph::new(vec![
+            node("app::main", &["app::expr", "app::rec"]),
+            at("app::expr", &["app::term", "app::lit"], 1, "unix"),
+            at("app::term", &["app::factor", "app::expr"], 5, "unix"),
+            at("app::factor", &["app::term", "app::lit"], 9, "windows"),
+            node("app::lit", &[]),
+            node("app::rec", &["app::rec"]),
+        ]);
+        let condensed = condense(&cg);
+        let ids: Vec<&str> = condensed.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::main", "app::expr::{cycle}", "app::lit", "app::rec"]);
+
+        let cycle = &condensed.nodes[1];
+        assert_eq!(cycle.label.as_deref(), Some("cycle: app::expr, app::factor, app::term"));
+        assert_eq!(cycle.callee_ids().collect::<Vec<_>>(), vec!["app::lit", "app::lit"]);
+        // Member-specific facts don't carry over to the whole cycle
+        assert_eq!(cycle.location, None);
+        assert_eq!(cycle.cfg, None);
+        assert_eq!(condensed.nodes[0].callee_ids().collect::<Vec<_>>(), vec!["app::expr::{cycle}", "app::rec"]);
+        // Self-recursion is not a group to collapse
+        assert!(condensed.nodes[3].calls("app::rec"));
+        assert_eq!(cycle_id("Parser::expr@app"), "Parser::expr::{cycle}@app");
+
+        let acyclic = CallGraph::new(vec![node("a", &["b"]), node("b", &[])]);
+        assert_eq!(condense(&acyclic).nodes.len(), 2);
+    }
+}
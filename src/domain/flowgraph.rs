@@ -80,7 +80,7 @@ impl FlowGraph {
         let adj_map: HashMap<String, Vec<String>> = callgraph
             .nodes
             .iter()
-            .map(|n| (n.id.clone(), n.callees.clone()))
+            .map(|n| (n.id.to_string(), n.callee_ids().map(String::from).collect()))
             .collect();
 
         // Process each entry point
@@ -126,6 +126,7 @@ impl FlowGraph {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn expand_node(
         node_id: &str,
         depth: usize,
@@ -220,31 +221,33 @@ impl FlowGraph {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::callgraph::CallGraphNode;
+    use crate::domain::callgraph::{CallEdge, CallGraphNode};
 
     #[test]
     fn test_flowgraph_from_callgraph() {
         let callgraph = CallGraph {
             nodes: vec![
                 CallGraphNode {
-                    id: "main".to_string(),
-                    callees: vec!["foo".to_string(), "bar".to_string()],
+                    id: "main".into(),
+                    callees: vec![CallEdge::new("foo"), CallEdge::new("bar")],
                     label: Some("main".to_string()),
+                    ..Default::default()
                 },
                 CallGraphNode {
-                    id: "foo".to_string(),
-                    callees: vec!["baz".to_string()],
+                    id: "foo".into(),
+                    callees: vec![CallEdge::new("baz")],
                     label: Some("foo".to_string()),
+                    ..Default::default()
                 },
                 CallGraphNode {
-                    id: "bar".to_string(),
-                    callees: vec![],
+                    id: "bar".into(),
                     label: Some("bar".to_string()),
+                    ..Default::default()
                 },
                 CallGraphNode {
-                    id: "baz".to_string(),
-                    callees: vec![],
+                    id: "baz".into(),
                     label: Some("baz".to_string()),
+                    ..Default::default()
                 },
             ],
         };
@@ -0,0 +1,170 @@
+//! Hybrid Graph Merging
+//!
+//! Maps SCIP symbol strings onto the node ids used by `SimpleCallGraphBuilder`
+//! and merges the two graphs: functions the SCIP index defines take their
+//! precise edges from SCIP, everything else keeps the heuristic syn edges.
+
+use std::collections::{HashMap, HashSet};
+
+use scip::types::descriptor::Suffix;
+
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode, EdgeKind};
+use crate::domain::index::method_id;
+
+/// Translate a SCIP symbol into a syn-style node id.
+///
+/// - `rust-analyzer cargo app 0.1.0 utils/helper().` -> `app::utils::helper`
+/// - `rust-analyzer cargo app 0.1.0 impl#[Tool]work().` -> `Tool::work@app`
+/// - `rust-analyzer cargo app 0.1.0 impl#[Add][Op]apply().` -> `Add::apply@app`
+/// - `rust-analyzer cargo app 0.1.0 Op#apply().` -> `Op::apply@app`
+/// - `rust-analyzer cargo app 0.1.0 net/impl#[Conn]send().` -> `net::Conn::send@app`
+///
+/// Returns `None` for anything that is not function-like (types, fields,
+/// locals) or does not parse as a SCIP symbol.
+pub fn scip_symbol_to_id(symbol: &str) -> Option<String> {
+    let parsed = scip::symbol::parse_symbol(symbol).ok()?;
+    let crate_name = parsed.package.as_ref().map(|p| p.name.clone()).filter(|n| !n.is_empty())?;
+
+    let mut modules = Vec::new();
+    let mut owner: Option<String> = None;
+    let mut method: Option<String> = None;
+    for d in &parsed.descriptors {
+        // Anything below a method (its type parameters, locals) is not the method itself
+        if method.is_some() {
+            return None;
+        }
+        match d.suffix.enum_value_or_default() {
+            Suffix::Namespace | Suffix::Package => modules.push(d.name.clone()),
+            // `impl#[SelfType][Trait]`: the first type parameter names the owner
+            Suffix::Type if d.name == "impl" => {}
+            Suffix::Type => owner = Some(d.name.clone()),
+            Suffix::TypeParameter => {
+                owner.get_or_insert_with(|| d.name.clone());
+            }
+            Suffix::Method => method = Some(d.name.clone()),
+            // Parameters, fields, macros, ... are not call graph nodes
+            _ => return None,
+        }
+    }
+
+    let name = method?;
+    Some(match owner {
+        Some(ty) => method_id(&crate_name, &modules, &ty, &name),
+        None => {
+            let mut parts = vec![crate_name];
+            parts.extend(modules);
+            parts.push(name);
+            parts.join("::")
+        }
+    })
+}
+
+/// Merge a syn graph with a SCIP graph (whose ids are raw SCIP symbols).
+pub fn merge_graphs(syn_graph: &CallGraph, scip_graph: &CallGraph) -> CallGraph {
+    // SCIP nodes keyed by their syn-style id; non-function symbols drop out here
+    let mut precise: HashMap<String, &CallGraphNode> = HashMap::new();
+    for node in &scip_graph.nodes {
+        if let Some(id) = scip_symbol_to_id(&node.id) {
+            precise.entry(id).or_insert(node);
+        }
+    }
+
+    // Symbols that map to the same id fold into one edge per kind, their weights summed
+    let remap = |scip_node: &CallGraphNode| {
+        let mut edges: Vec<CallEdge> = Vec::new();
+        let mut seen: HashMap<(String, EdgeKind), usize> = HashMap::new();
+        for e in &scip_node.callees {
+            let Some(target) = scip_symbol_to_id(&e.target) else { continue };
+            match seen.get(&(target.clone(), e.kind)) {
+                Some(&i) => edges[i].weight += e.weight,
+                None => {
+                    seen.insert((target.clone(), e.kind), edges.len());
+                    edges.push(CallEdge { target: target.into(), ..e.clone() });
+                }
+            }
+        }
+        edges
+    };
+
+    let mut nodes = Vec::with_capacity(syn_graph.nodes.len());
+    let mut merged_ids = HashSet::new();
+    for node in &syn_graph.nodes {
+        let mut merged = node.clone();
+        if let Some(scip_node) = precise.get(node.id.as_str()) {
+            // SCIP only sees the trait method behind `dyn Trait` and knows nothing of
+            // spawned tasks; keep syn's devirtualized and spawn edges
+            let precise_edges = remap(scip_node);
+            let dynamic: Vec<CallEdge> = node.callees.iter()
+                .filter(|e| e.kind != EdgeKind::Call)
+                .filter(|e| !precise_edges.iter().any(|p| p.target == e.target && p.kind == e.kind))
+                .cloned()
+                .collect();
+            merged.callees = precise_edges;
+            merged.callees.extend(dynamic);
+        }
+        merged_ids.insert(merged.id.clone());
+        nodes.push(merged);
+    }
+
+    // Functions only SCIP knows about (e.g. files the syn pass skipped)
+    let mut extra: Vec<(&String, &&CallGraphNode)> = precise.iter()
+        .filter(|(id, _)| !merged_ids.contains(id.as_str()))
+        .collect();
+    extra.sort_by(|a, b| a.0.cmp(b.0));
+    for (id, scip_node) in extra {
+        nodes.push(CallGraphNode { id: id.as_str().into(), callees: remap(scip_node), ..(*scip_node).clone() });
+    }
+
+    CallGraph::new(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    const RA: &str = "rust-analyzer cargo app 0.1.0";
+
+    #[test]
+    fn test_scip_symbol_mapping() {
+        let sym = |s: &str| format!("{} {}", RA, s);
+        assert_eq!(scip_symbol_to_id(&sym("main().")), Some("app::main".to_string()));
+        assert_eq!(scip_symbol_to_id(&sym("utils/helper().")), Some("app::utils::helper".to_string()));
+        assert_eq!(scip_symbol_to_id(&sym("impl#[Tool]work().")), Some("Tool::work@app".to_string()));
+        assert_eq!(scip_symbol_to_id(&sym("impl#[Add][Op]apply().")), Some("Add::apply@app".to_string()));
+        assert_eq!(scip_symbol_to_id(&sym("Op#apply().")), Some("Op::apply@app".to_string()));
+        assert_eq!(scip_symbol_to_id(&sym("Tool#")), None);
+        assert_eq!(scip_symbol_to_id(&sym("Tool#field.")), None);
+        assert_eq!(scip_symbol_to_id(&sym("helper().[T]")), None);
+        assert_eq!(scip_symbol_to_id("local 3"), None);
+    }
+
+    #[test]
+    fn test_merge_prefers_scip_edges() {
+        let syn_graph = CallGraph::new(vec![
+            node("app::main", &["app::guess"]),
+            node("app::helper", &[]),
+            node("app::uncovered", &["app::helper"]),
+        ]);
+        let main_sym = format!("{} main().", RA);
+        let helper_sym = format!("{} helper().", RA);
+        let scip_graph = CallGraph::new(vec![
+            node(&main_sym, &[&helper_sym, &format!("{} Tool#", RA)]),
+            node(&helper_sym, &[]),
+            node(&format!("{} only_scip().", RA), &[&helper_sym]),
+        ]);
+
+        let merged = merge_graphs(&syn_graph, &scip_graph);
+        let callees = |id: &str| merged.nodes.iter().find(|n| n.id == id).unwrap()
+            .callee_ids().map(String::from).collect::<Vec<_>>();
+
+        // Covered by SCIP: precise edges replace the heuristic ones, type refs drop out
+        assert_eq!(callees("app::main"), vec!["app::helper"]);
+        // Not in the index: syn edges survive
+        assert_eq!(callees("app::uncovered"), vec!["app::helper"]);
+        // SCIP-only functions are added
+        assert_eq!(callees("app::only_scip"), vec!["app::helper"]);
+        assert_eq!(merged.nodes.len(), 4);
+    }
+}
@@ -0,0 +1,213 @@
+//! Change Impact
+//!
+//! Maps the line ranges a diff touches onto the functions spanning them,
+//! then follows calls backwards to everything that can reach a changed
+//! function: the code to review and the tests to run for a change.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::source::SourceOrigin;
+use crate::infrastructure::source_manager::SourceManager;
+
+/// Lines (1-based, inclusive) a diff touches on the new side, per file.
+pub type ChangedLines = BTreeMap<String, Vec<(usize, usize)>>;
+
+/// Changed line ranges of a `git diff --unified=0`. Deleted files are left
+/// out; a hunk removing lines counts as touching the line it removed them
+/// after.
+pub fn changed_lines(diff: &str) -> ChangedLines {
+    let mut changes = ChangedLines::new();
+    let mut file: Option<String> = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").or_else(|| (path != "/dev/null").then_some(path)).map(str::to_string);
+            continue;
+        }
+        let (Some(file), Some(hunk)) = (&file, line.strip_prefix("@@ ")) else { continue };
+        // @@ -a,b +c,d @@
+        let Some(new) = hunk.split_whitespace().find_map(|part| part.strip_prefix('+')) else { continue };
+        let (start, count) = match new.split_once(',') {
+            Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(1)),
+            None => (new.parse().unwrap_or(0), 1),
+        };
+        let start = usize::max(start, 1);
+        changes.entry(file.clone()).or_default().push((start, start + usize::max(count, 1) - 1));
+    }
+    changes
+}
+
+/// A function some change can reach through calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AffectedFn {
+    pub id: String,
+    /// Calls between it and the nearest changed function.
+    pub distance: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpactReport {
+    /// Revision the changes are measured against.
+    pub since: String,
+    /// Functions whose source the diff touches, sorted.
+    pub changed: Vec<String>,
+    /// Transitive callers of the changed functions, nearest first.
+    pub affected: Vec<AffectedFn>,
+    /// Changed or affected functions nothing calls: where to start
+    /// exercising the change.
+    pub entry_points: Vec<String>,
+    /// Changed or affected test functions.
+    pub tests: Vec<String>,
+}
+
+fn is_test(node: &CallGraphNode) -> bool {
+    node.is_test || node.origin == Some(SourceOrigin::Test)
+}
+
+/// Which functions `changes` touch and what calls into them. Function
+/// spans run from the node's location to the brace closing its body.
+pub fn impact(cg: &CallGraph, changes: &ChangedLines, sources: &SourceManager, since: &str) -> ImpactReport {
+    let mut changed: Vec<String> = cg.nodes.iter()
+        .filter(|n| n.type_kind.is_none())
+        .filter(|n| {
+            let Some((file, line)) = n.location.as_deref().and_then(|l| l.rsplit_once(':')) else { return false };
+            let (Some(ranges), Ok(start)) = (changes.get(file.strip_prefix("./").unwrap_or(file)), line.parse::<usize>()) else {
+                return false;
+            };
+            let end = sources.item_end(file, start).unwrap_or(start);
+            ranges.iter().any(|&(from, to)| from <= end && start <= to)
+        })
+        .map(|n| n.id.to_string())
+        .collect();
+    changed.sort();
+    changed.dedup();
+
+    let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for node in &cg.nodes {
+        for edge in node.call_edges() {
+            callers.entry(edge.target.as_str()).or_default().push(node.id.as_str());
+        }
+    }
+    let mut distance: HashMap<&str, usize> = changed.iter().map(|id| (id.as_str(), 0)).collect();
+    let mut queue: VecDeque<&str> = changed.iter().map(String::as_str).collect();
+    let mut affected = Vec::new();
+    while let Some(id) = queue.pop_front() {
+        let next = distance[id] + 1;
+        let mut found: Vec<&str> = callers.get(id).into_iter().flatten().copied().filter(|c| !distance.contains_key(c)).collect();
+        found.sort();
+        found.dedup();
+        for caller in found {
+            distance.insert(caller, next);
+            affected.push(AffectedFn { id: caller.to_string(), distance: next });
+            queue.push_back(caller);
+        }
+    }
+
+    let nodes: HashMap<&str, &CallGraphNode> = cg.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let impacted: HashSet<&str> = distance.keys().copied().collect();
+    let mut entry_points: Vec<String> = impacted.iter()
+        .filter(|id| !callers.contains_key(**id) && !nodes.get(**id).is_some_and(|n| is_test(n)))
+        .map(|id| id.to_string())
+        .collect();
+    entry_points.sort();
+    let mut tests: Vec<String> = impacted.iter()
+        .filter(|id| nodes.get(**id).is_some_and(|n| is_test(n)))
+        .map(|id| id.to_string())
+        .collect();
+    tests.sort();
+    ImpactReport { since: since.to_string(), changed, affected, entry_points, tests }
+}
+
+impl fmt::Display for ImpactReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Impact since {}: {} changed, {} affected", self.since, self.changed.len(), self.affected.len())?;
+        let list = |f: &mut fmt::Formatter<'_>, title: &str, ids: &[String]| -> fmt::Result {
+            if !ids.is_empty() {
+                writeln!(f, "{}", title)?;
+            }
+            for id in ids {
+                writeln!(f, "  {}", id)?;
+            }
+            Ok(())
+        };
+        list(f, "Changed:", &self.changed)?;
+        if !self.affected.is_empty() {
+            writeln!(f, "Affected (calls away from a change):")?;
+        }
+        for a in &self.affected {
+            writeln!(f, "  {:>5}  {}", a.distance, a.id)?;
+        }
+        list(f, "Entry points:", &self.entry_points)?;
+        list(f, "Tests:", &self.tests)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    
+    use crate::domain::source::{SourceFile, SourceSet};
+
+    #[test]
+    fn test_changed_lines() {
+        let diff = "\
+diff --git a/src/net.rs b/src/net.rs
+--- a/src/net.rs
++++ b/src/net.rs
+@@ -3 +3 @@ fn send() {
+-    old();
++    new();
+@@ -10,2 +9,0 @@ fn recv() {
+diff --git a/src/gone.rs b/src/gone.rs
+--- a/src/gone.rs
++++ /dev/null
+@@ -1,4 +0,0 @@
+diff --git a/src/new.rs b/src/new.rs
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1,5 @@
+";
+        let changes = changed_lines(diff);
+        assert_eq!(changes.into_iter().collect::<Vec<_>>(), vec![
+            ("src/net.rs".to_string(), vec![(3, 3), (9, 9)]),
+            ("src/new.rs".to_string(), vec![(1, 5)]),
+        ]);
+    }
+
+    #[test]
+    fn test_impact() {
+        let code = "fn main() {\n    handle();\n}\nfn handle() {\n    send();\n}\nfn send() {\n    write();\n}\nfn write() {}\n#[test]\nfn sends() {\n    send();\n}\n";
+        let sources = SourceManager::new(&SourceSet::from(vec![SourceFile::new("app", "src/main.rs", code)]));
+        let node = |id: &str, line: usize, callees: &[&str]| CallGraphNode {
+            location: Some(format!("src/main.rs:{}", line)),
+            ..fixtures::node(id, callees)
+        };
+        let cg = CallGraph::new(vec![
+            node("app::main", 1, &["app::handle"]),
+            node("app::handle", 4, &["app::send"]),
+            node("app::send", 7, &["app::write"]),
+            node("app::write", 10, &[]),
+            CallGraphNode { is_test: true, ..node("app::sends", 12, &["app::send"]) },
+        ]);
+
+        // The body of send, not its first line
+        let changes = ChangedLines::from([("src/main.rs".to_string(), vec![(8, 8)])]);
+        let report = impact(&cg, &changes, &sources, "HEAD~1");
+        assert_eq!(report.changed, vec!["app::send"]);
+        let affected: Vec<(&str, usize)> = report.affected.iter().map(|a| (a.id.as_str(), a.distance)).collect();
+        assert_eq!(affected, vec![("app::handle", 1), ("app::sends", 1), ("app::main", 2)]);
+        assert_eq!(report.entry_points, vec!["app::main"]);
+        assert_eq!(report.tests, vec!["app::sends"]);
+        let text = report.to_string();
+        assert!(text.starts_with("Impact since HEAD~1: 1 changed, 3 affected\nChanged:\n  app::send\nAffected"), "{}", text);
+        assert!(text.contains("      2  app::main\nEntry points:\n  app::main\nTests:\n  app::sends\n"), "{}", text);
+
+        let untouched = ChangedLines::from([("src/main.rs".to_string(), vec![(11, 11)])]);
+        assert!(impact(&cg, &untouched, &sources, "HEAD").changed.is_empty());
+    }
+}
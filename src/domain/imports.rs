@@ -0,0 +1,262 @@
+//! Import Tracking
+//!
+//! Collects `use` declarations per file so bare call paths like `bar()` can be
+//! attributed to the module or crate they were imported from, and follows
+//! `pub use` re-export chains across workspace crates.
+
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use syn::{Item, UseTree};
+
+/// Maximum number of re-export hops followed before giving up (guards cycles).
+const MAX_REEXPORT_HOPS: usize = 8;
+
+/// A flattened `use` item as written: local alias, path (not yet made
+/// absolute), whether it is a `pub use`, and the module it sits in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UseEntry {
+    pub alias: String,
+    pub path: Vec<String>,
+    pub is_pub: bool,
+    pub module: Vec<String>,
+}
+
+/// Imports visible in a single file: local alias -> the modules importing
+/// it, each with the absolute path segments it stands for there.
+#[derive(Debug, Default, Clone)]
+pub struct ImportMap {
+    aliases: HashMap<String, Vec<ScopedImport>>,
+}
+
+/// Module holding a `use`, and the absolute path it imports.
+type ScopedImport = (Vec<String>, Vec<String>);
+
+impl ImportMap {
+    /// Collect every `use` item (including inside inline modules) of a file.
+    pub fn from_items(items: &[Item], crate_name: &str, known_crates: &HashSet<String>) -> Self {
+        Self::from_entries(&collect_use_entries(items, &[]), crate_name, known_crates)
+    }
+
+    /// Build from previously collected entries, resolving paths against the workspace.
+    pub fn from_entries(entries: &[UseEntry], crate_name: &str, known_crates: &HashSet<String>) -> Self {
+        let mut map = ImportMap::default();
+        for entry in entries {
+            let target = normalize_path(entry.path.clone(), crate_name, known_crates);
+            let modules = map.aliases.entry(entry.alias.clone()).or_default();
+            match modules.iter_mut().find(|(module, _)| *module == entry.module) {
+                Some(existing) => existing.1 = target,
+                None => modules.push((entry.module.clone(), target)),
+            }
+        }
+        map
+    }
+
+    /// Look up the absolute path an alias stands for in code at `scope` (a
+    /// module path, possibly followed by the item it is in). A `use` applies
+    /// to its own module and what is nested in it, the innermost one winning;
+    /// those of sibling or nested modules don't.
+    pub fn get(&self, alias: &str, scope: &[String]) -> Option<&Vec<String>> {
+        self.aliases.get(alias)?.iter()
+            .filter(|(module, _)| scope.starts_with(module))
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, target)| target)
+    }
+
+    /// Expand a call path at `scope` whose first segment is an imported alias.
+    /// `bar` with `use foo::bar` becomes `foo::bar`; `m::f` with `use a::m` becomes `a::m::f`.
+    pub fn expand(&self, segments: &[String], scope: &[String]) -> Option<Vec<String>> {
+        let first = segments.first()?;
+        let target = self.get(first, scope)?;
+        let mut expanded = target.clone();
+        expanded.extend(segments[1..].iter().cloned());
+        Some(expanded)
+    }
+}
+
+/// Workspace-wide `pub use` re-exports: (crate, alias) -> absolute target path.
+#[derive(Debug, Default, Clone)]
+pub struct ReexportTable {
+    entries: HashMap<(String, String), Vec<String>>,
+}
+
+impl ReexportTable {
+    /// Record the `pub use` items of one file.
+    pub fn add_items(&mut self, items: &[Item], crate_name: &str, known_crates: &HashSet<String>) {
+        self.add_entries(&collect_use_entries(items, &[]), crate_name, known_crates);
+    }
+
+    /// Record the `pub use` entries of one file.
+    pub fn add_entries(&mut self, entries: &[UseEntry], crate_name: &str, known_crates: &HashSet<String>) {
+        let crate_ident = crate_name.replace('-', "_");
+        for entry in entries.iter().filter(|e| e.is_pub) {
+            let path = normalize_path(entry.path.clone(), crate_name, known_crates);
+            self.entries.insert((crate_ident.clone(), entry.alias.clone()), path);
+        }
+    }
+
+    /// Follow re-export hops until the path no longer names a re-export.
+    pub fn resolve(&self, mut path: Vec<String>) -> Vec<String> {
+        for _ in 0..MAX_REEXPORT_HOPS {
+            if path.len() < 2 {
+                break;
+            }
+            let key = (path[0].clone(), path[path.len() - 1].clone());
+            match self.entries.get(&key) {
+                Some(target) if *target != path => path = target.clone(),
+                _ => break,
+            }
+        }
+        path
+    }
+}
+
+/// Flatten all `use` trees of a file (including inline modules) into entries;
+/// `module` is the path of the module holding `items`.
+pub fn collect_use_entries(items: &[Item], module: &[String]) -> Vec<UseEntry> {
+    let mut out = Vec::new();
+    for item in items {
+        match item {
+            Item::Use(item_use) => {
+                let is_pub = matches!(item_use.vis, syn::Visibility::Public(_));
+                let mut flat = Vec::new();
+                flatten_use_tree(&item_use.tree, Vec::new(), &mut flat);
+                for (alias, path) in flat {
+                    out.push(UseEntry { alias, path, is_pub, module: module.to_vec() });
+                }
+            }
+            Item::Mod(module_item) => {
+                if let Some((_, content)) = &module_item.content {
+                    let mut inner = module.to_vec();
+                    inner.push(module_item.ident.to_string());
+                    out.extend(collect_use_entries(content, &inner));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn flatten_use_tree(tree: &UseTree, prefix: Vec<String>, out: &mut Vec<(String, Vec<String>)>) {
+    match tree {
+        UseTree::Path(p) => {
+            let mut next = prefix;
+            next.push(p.ident.to_string());
+            flatten_use_tree(&p.tree, next, out);
+        }
+        UseTree::Name(n) => {
+            let name = n.ident.to_string();
+            if name == "self" {
+                // `use foo::{self}` imports the module `foo` itself
+                if let Some(last) = prefix.last() {
+                    out.push((last.clone(), prefix.clone()));
+                }
+            } else {
+                let mut path = prefix;
+                path.push(name.clone());
+                out.push((name, path));
+            }
+        }
+        UseTree::Rename(r) => {
+            let mut path = prefix;
+            path.push(r.ident.to_string());
+            out.push((r.rename.to_string(), path));
+        }
+        UseTree::Group(g) => {
+            for t in &g.items {
+                flatten_use_tree(t, prefix.clone(), out);
+            }
+        }
+        // Glob imports can't be resolved without the target module's contents
+        UseTree::Glob(_) => {}
+    }
+}
+
+/// Make a use path absolute: `crate`/`self`/`super` and local module paths are
+/// anchored at the current crate; workspace crates and std paths are kept.
+fn normalize_path(mut path: Vec<String>, crate_name: &str, known_crates: &HashSet<String>) -> Vec<String> {
+    let crate_ident = crate_name.replace('-', "_");
+    match path.first().map(|s| s.as_str()) {
+        Some("crate") | Some("self") | Some("super") => {
+            path[0] = crate_ident;
+            path
+        }
+        Some(first) if known_crates.contains(first) || matches!(first, "std" | "core" | "alloc") => path,
+        _ => {
+            path.insert(0, crate_ident);
+            path
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crates(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn segs(path: &str) -> Vec<String> {
+        path.split("::").map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_import_map_flattens_groups_and_renames() {
+        let file = syn::parse_file(r#"
+            use lib_derive::{super_base_fn, SuperOp as Op};
+            use crate::util::helper;
+            use std::collections::HashMap;
+        "#).unwrap();
+        let known = crates(&["lib_derive", "bin_demo"]);
+        let imports = ImportMap::from_items(&file.items, "bin_demo", &known);
+
+        assert_eq!(imports.get("super_base_fn", &[]), Some(&segs("lib_derive::super_base_fn")));
+        assert_eq!(imports.get("Op", &[]), Some(&segs("lib_derive::SuperOp")));
+        assert_eq!(imports.get("helper", &[]), Some(&segs("bin_demo::util::helper")));
+        assert_eq!(imports.get("HashMap", &[]), Some(&segs("std::collections::HashMap")));
+        assert_eq!(imports.expand(&segs("helper"), &segs("main")), Some(segs("bin_demo::util::helper")));
+        assert_eq!(imports.expand(&segs("unknown"), &[]), None);
+    }
+
+    #[test]
+    fn test_imports_are_scoped_to_their_module() {
+        let file = syn::parse_file(r#"
+            use crate::util::helper;
+            mod net {
+                use crate::wire::helper;
+                fn send() {}
+            }
+            mod db {
+                use crate::sql::query;
+            }
+        "#).unwrap();
+        let imports = ImportMap::from_items(&file.items, "app", &crates(&["app"]));
+
+        assert_eq!(imports.expand(&segs("helper"), &segs("main")), Some(segs("app::util::helper")));
+        assert_eq!(imports.expand(&segs("helper"), &segs("net::send")), Some(segs("app::wire::helper")));
+        assert_eq!(imports.expand(&segs("query"), &segs("db::run")), Some(segs("app::sql::query")));
+        // `db`'s import doesn't leak to the file or to `net`
+        assert_eq!(imports.expand(&segs("query"), &segs("main")), None);
+        assert_eq!(imports.expand(&segs("query"), &segs("net::send")), None);
+    }
+
+    #[test]
+    fn test_reexport_chain() {
+        let known = crates(&["lib_base", "lib_derive"]);
+        let base = syn::parse_file(r#"
+            pub fn base_fn() {}
+            pub use base_fn as alias_base_fn;
+        "#).unwrap();
+        let derive = syn::parse_file(r#"
+            pub use lib_base::alias_base_fn as super_base_fn;
+        "#).unwrap();
+
+        let mut table = ReexportTable::default();
+        table.add_items(&base.items, "lib_base", &known);
+        table.add_items(&derive.items, "lib_derive", &known);
+
+        assert_eq!(table.resolve(segs("lib_derive::super_base_fn")), segs("lib_base::base_fn"));
+        assert_eq!(table.resolve(segs("lib_base::other")), segs("lib_base::other"));
+    }
+}
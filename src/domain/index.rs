@@ -1,6 +1,6 @@
 
 use rayon::prelude::*;
-use syn::{Item, Type, Visibility};
+use syn::Item;
 
 use serde::{Serialize, Deserialize};
 
@@ -11,18 +11,143 @@ pub struct FunctionSignature {
     pub receiver: Option<String>, // "&self", "self", or None for static
     pub location: String,         // file:line
     pub crate_name: String,
+    pub module: Vec<String>,      // module path of the definition (of the impl, for methods)
+}
+
+impl FunctionSignature {
+    /// Id of this method on `type_name`, see [`method_id`].
+    pub fn method_id(&self, type_name: &str) -> String {
+        method_id(&self.crate_name, &self.module, type_name, &self.name)
+    }
 }
 
 use std::sync::Arc;
+use crate::domain::cfg::CfgOptions;
+use crate::domain::source::SourceSet;
 use crate::domain::store::SymbolStore;
+use crate::domain::summary::{FileSummary, SymbolDef};
+
+/// Build the id of a method or trait method: `Type::name@crate`, behind the
+/// module path of the impl (or trait) when that is not the crate root, so
+/// same-named types of different modules keep their own nodes:
+/// `net::Conn::send@app`.
+pub fn method_id(crate_name: &str, module_path: &[String], type_name: &str, name: &str) -> String {
+    let mut parts: Vec<&str> = module_path.iter().map(String::as_str).collect();
+    parts.extend([type_name, name]);
+    format!("{}@{}", parts.join("::"), crate_name)
+}
+
+/// Build the id of a free function: `crate::module::name`.
+/// Nested functions use the enclosing function as a module segment.
+pub fn qualified_fn_id(crate_name: &str, module_path: &[String], name: &str) -> String {
+    let mut parts = Vec::with_capacity(module_path.len() + 2);
+    parts.push(crate_name);
+    parts.extend(module_path.iter().map(|s| s.as_str()));
+    parts.push(name);
+    parts.join("::")
+}
+
+/// Module path of a file within its crate, from where `mod` declarations
+/// look for it: `src/net/tcp.rs` and `src/net/tcp/mod.rs` are `net::tcp`;
+/// crate roots (`src/lib.rs`, `src/main.rs`, `src/bin/tool.rs`,
+/// `src/bin/tool/main.rs`, `tests/it.rs`, ...) are the empty path. Paths
+/// outside `src/`, `tests/`, `benches/` and `examples/` are taken as roots.
+///
+/// With the directory of the file's package, only what is below it counts,
+/// so directories named `src` above the package or inside `src/` (a module
+/// `src`) are not mistaken for the anchor; without one, the last `src` is.
+///
+/// Only the location counts, not the `mod` declaration that loads the file:
+/// a module loaded with `#[path = "..."]` is named after where the file
+/// sits, and files of a non-standard layout (`[lib] path = "lib.rs"` and
+/// its modules next to it) all share the crate root, so same-named
+/// functions in them can still end up with one id.
+pub fn file_module_path(file_path: &str, package_dir: Option<&str>) -> Vec<String> {
+    let split = |path: &str| -> Vec<String> {
+        path.split(['/', '\\']).filter(|c| !c.is_empty() && *c != ".").map(str::to_string).collect()
+    };
+    let components = split(file_path);
+    let (components, anchored) = match package_dir.map(split) {
+        Some(package) if components.starts_with(&package) => (&components[package.len()..], true),
+        _ => (&components[..], false),
+    };
+    let components: Vec<&str> = components.iter().map(String::as_str).collect();
+    let Some((file, dirs)) = components.split_last() else {
+        return Vec::new();
+    };
+    let Some(file) = file.strip_suffix(".rs") else {
+        return Vec::new();
+    };
+    let anchor = |names: &[&str], last: bool| {
+        if anchored {
+            dirs.first().filter(|c| names.contains(c)).map(|_| 0)
+        } else if last {
+            dirs.iter().rposition(|c| names.contains(c))
+        } else {
+            dirs.iter().position(|c| names.contains(c))
+        }
+    };
+    let dirs: &[&str] = match anchor(&["src"], true) {
+        // `src/bin/tool.rs` is a root; `src/bin/tool/` holds the root `main.rs` and its modules
+        Some(src) if dirs.get(src + 1) == Some(&"bin") => match dirs.get(src + 2..) {
+            Some([]) | None => return Vec::new(),
+            Some(target) => &target[1..],
+        },
+        Some(src) => {
+            if src + 1 == dirs.len() && matches!(file, "lib" | "main") {
+                return Vec::new();
+            }
+            &dirs[src + 1..]
+        }
+        // Each file is a root, or a directory with a `main.rs` root
+        None => match anchor(&["tests", "benches", "examples"], false) {
+            Some(anchor) if anchor + 1 == dirs.len() => return Vec::new(),
+            Some(anchor) if anchor + 2 == dirs.len() && file == "main" => return Vec::new(),
+            Some(anchor) => &dirs[anchor + 1..],
+            None => return Vec::new(),
+        },
+    };
+    let mut path: Vec<String> = dirs.iter().map(|d| d.to_string()).collect();
+    if !(file == "mod" || (dirs.is_empty() && file == "main")) {
+        path.push(file.to_string());
+    }
+    path
+}
+
+/// Items declared directly in a function body (nested fns, impls, modules).
+pub fn nested_items(block: &syn::Block) -> Vec<&Item> {
+    block.stmts.iter().filter_map(|stmt| match stmt {
+        syn::Stmt::Item(item) => Some(item),
+        _ => None,
+    }).collect()
+}
 
 /// Error encountered during analysis/parsing.
 #[derive(Debug, Clone)]
 pub struct AnalysisError {
     pub file: String,
+    /// 1-based line of the error, when known.
+    pub line: Option<usize>,
     pub error: String,
 }
 
+impl AnalysisError {
+    /// Diagnostic for a file syn could not parse, located at the error span.
+    pub fn from_syn(file: &str, err: &syn::Error) -> Self {
+        let line = err.span().start().line;
+        Self { file: file.to_string(), line: (line > 0).then_some(line), error: err.to_string() }
+    }
+}
+
+impl std::fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.error),
+            None => write!(f, "{}: {}", self.file, self.error),
+        }
+    }
+}
+
 /// Thread-safe symbol index that delegates storage to a SymbolStore backend.
 /// Enables parallel parsing and indexing with either memory or disk persistence.
 pub struct SymbolIndex {
@@ -35,23 +160,18 @@ impl SymbolIndex {
     }
 
     /// Build the symbol index from source files in parallel and return any errors.
-    pub fn build(sources: &[(String, String, String)], store: Arc<dyn SymbolStore>) -> (Self, Vec<AnalysisError>) {
+    pub fn build(sources: &SourceSet, store: Arc<dyn SymbolStore>) -> (Self, Vec<AnalysisError>) {
         let index = SymbolIndex::new(store);
 
         // Parallel parsing and indexing
-        let errors: Vec<AnalysisError> = sources.par_iter()
-            .filter_map(|(crate_name, file_path, code)| {
-                match syn::parse_file(code) {
-                    Ok(ast) => {
-                        index.index_items(crate_name, file_path, &ast.items);
+        let errors: Vec<AnalysisError> = sources.files().par_iter()
+            .filter_map(|file| {
+                match FileSummary::extract_with_cfg(file, &CfgOptions::default()) {
+                    Ok(summary) => {
+                        index.register_summary(&summary);
                         None
                     }
-                    Err(e) => {
-                        Some(AnalysisError {
-                            file: file_path.clone(),
-                            error: e.to_string(),
-                        })
-                    }
+                    Err(e) => Some(AnalysisError::from_syn(&file.path, &e)),
                 }
             })
             .collect();
@@ -60,75 +180,93 @@ impl SymbolIndex {
     }
 
     /// Find all methods with a given name (for conservative resolution).
-    pub fn find_methods_by_name(&self, method_name: &str) -> Vec<FunctionSignature> {
+    /// Returns `(type_name, signature)` pairs.
+    pub fn find_methods_by_name(&self, method_name: &str) -> Vec<(String, FunctionSignature)> {
         self.store.find_methods_by_name(method_name)
     }
 
-    /// Index all items in a list (recursive for nested modules).
-    fn index_items(&self, crate_name: &str, file_path: &str, items: &[Item]) {
-        for item in items {
-            match item {
-                Item::Fn(func) => {
-                    let name = func.sig.ident.to_string();
-                    let is_public = matches!(func.vis, Visibility::Public(_));
-                    let span = func.sig.ident.span();
-                    let line = span.start().line;
-                    
-                    let qualified_name = format!("{}::{}", crate_name, name);
-
-                    let sig = FunctionSignature {
-                        name: name.clone(),
-                        is_public,
-                        receiver: None,
-                        location: format!("{}:{}", file_path, line),
-                        crate_name: crate_name.to_string(),
-                    };
-                    self.store.insert_function(qualified_name, sig);
-                }
-                Item::Impl(imp) => {
-                    if let Type::Path(tp) = &*imp.self_ty {
-                        if let Some(segment) = tp.path.segments.last() {
-                            let type_name = segment.ident.to_string();
-                            
-                            for impl_item in &imp.items {
-                                if let syn::ImplItem::Fn(method) = impl_item {
-                                    let method_name = method.sig.ident.to_string();
-                                    let is_public = matches!(method.vis, Visibility::Public(_));
-                                    let span = method.sig.ident.span();
-                                    let line = span.start().line;
-
-                                    let receiver = method.sig.inputs.first().and_then(|arg| {
-                                        match arg {
-                                            syn::FnArg::Receiver(r) => {
-                                                if r.reference.is_some() { Some("&self".to_string()) }
-                                                else { Some("self".to_string()) }
-                                            },
-                                            _ => None,
-                                        }
-                                    });
-
-                                    let sig = FunctionSignature {
-                                        name: method_name.clone(),
-                                        is_public,
-                                        receiver,
-                                        location: format!("{}:{}", file_path, line),
-                                        crate_name: crate_name.to_string(),
-                                    };
-
-                                    self.store.insert_method(type_name.clone(), method_name.clone(), sig);
-                                    self.store.register_method_lookup(method_name, type_name.clone());
-                                }
-                            }
-                        }
-                    }
+    /// Find all free functions with a given name across every indexed crate.
+    /// Returns `(qualified_key, signature)` pairs.
+    pub fn find_functions_by_name(&self, name: &str) -> Vec<(String, FunctionSignature)> {
+        self.store.find_functions_by_name(name)
+    }
+
+    /// Register every symbol a file summary defines.
+    pub fn register_summary(&self, summary: &FileSummary) {
+        for def in &summary.defs {
+            match def {
+                SymbolDef::Function { key, sig } => {
+                    self.store.insert_function(key.clone(), sig.clone());
+                    self.store.register_function_lookup(sig.name.clone(), key.clone());
                 }
-                Item::Mod(module) => {
-                    if let Some((_, content)) = &module.content {
-                        self.index_items(crate_name, file_path, content);
-                    }
+                SymbolDef::Method { type_name, sig } => {
+                    self.store.insert_method(type_name.clone(), sig.name.clone(), sig.clone());
+                    self.store.register_method_lookup(sig.name.clone(), type_name.clone());
                 }
-                _ => {}
             }
         }
+        for (trait_path, type_name, _) in &summary.trait_impls {
+            if let Some(trait_name) = trait_path.last() {
+                self.store.register_trait_impl(trait_name.clone(), type_name.clone());
+            }
+        }
+        for (type_name, field, field_type) in &summary.field_types {
+            self.store.register_field_type(type_name.clone(), field.clone(), field_type.clone());
+        }
+    }
+
+    /// Build the index from already extracted (possibly cached) summaries.
+    pub fn from_summaries(summaries: &[FileSummary], store: Arc<dyn SymbolStore>) -> Self {
+        let index = SymbolIndex::new(store);
+        summaries.par_iter().for_each(|summary| index.register_summary(summary));
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(file: &str) -> Vec<String> {
+        file_module_path(file, None)
+    }
+
+    #[test]
+    fn test_file_module_path() {
+        assert!(path("/ws/app/src/lib.rs").is_empty());
+        assert!(path("src/main.rs").is_empty());
+        assert_eq!(path("src/net/tcp.rs"), ["net", "tcp"]);
+        assert_eq!(path("src/net/mod.rs"), ["net"]);
+        assert!(path("src/bin/tool.rs").is_empty());
+        assert!(path("src/bin/tool/main.rs").is_empty());
+        assert_eq!(path("src/bin/tool/cli.rs"), ["cli"]);
+        assert!(path("tests/it.rs").is_empty());
+        assert_eq!(path("tests/common/mod.rs"), ["common"]);
+        assert!(path("examples/demo/main.rs").is_empty());
+        assert!(path("<eval>").is_empty());
+    }
+
+    #[test]
+    fn test_file_module_path_ignores_mod_declarations() {
+        // `#[path = "generated/api.rs"] mod api;` in src/lib.rs is `crate::api`
+        assert_eq!(path("src/generated/api.rs"), ["generated", "api"]);
+        // `[lib] path = "lib.rs"`: the root and its modules all map to the crate root
+        assert!(path("/ws/app/lib.rs").is_empty());
+        assert!(path("/ws/app/util.rs").is_empty());
+    }
+
+    #[test]
+    fn test_file_module_path_below_the_package() {
+        let module = |file: &str| file_module_path(file, Some("/home/me/src/app"));
+        // `src` above the package is not the anchor
+        assert!(module("/home/me/src/app/src/lib.rs").is_empty());
+        assert_eq!(module("/home/me/src/app/src/net/tcp.rs"), ["net", "tcp"]);
+        // nor is a module named `src`
+        assert_eq!(module("/home/me/src/app/src/gen/src/api.rs"), ["gen", "src", "api"]);
+        assert_eq!(path("/home/me/src/app/src/gen/src/api.rs"), ["api"]);
+        assert_eq!(module("/home/me/src/app/tests/common/mod.rs"), ["common"]);
+        assert!(module("/home/me/src/app/lib.rs").is_empty());
+        // workspace-relative paths of the root package
+        assert_eq!(file_module_path("src/net/mod.rs", Some("")), ["net"]);
     }
 }
@@ -0,0 +1,243 @@
+//! Symbol Interner
+//!
+//! Maps symbol strings to dense numeric ids and back, storing each distinct
+//! string once. Graph algorithms work on the ids (see
+//! [`CompactGraph`](crate::domain::compact::CompactGraph)); the strings are
+//! only looked up again for output.
+//!
+//! [`Symbol`] is how [`CallGraph`](crate::domain::callgraph::CallGraph)
+//! stores node ids and edge targets: a shared string, so a node's id and
+//! every edge naming it use one allocation instead of a copy each.
+
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An interned symbol string. Clones share the allocation; it reads,
+/// compares, hashes and serializes as the plain string.
+#[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol(Arc::from(name))
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol(Arc::from(name))
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Symbol(Arc::from(name.as_str()))
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.0.to_string()
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == &*other.0
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == &*other.0
+    }
+}
+
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool {
+        **self == *other.0
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Symbol::from)
+    }
+}
+
+/// Shares one allocation between equal [`Symbol`]s.
+#[derive(Debug, Default)]
+pub struct SymbolPool {
+    symbols: HashSet<Symbol>,
+}
+
+impl SymbolPool {
+    /// The pooled symbol equal to `symbol`, pooling `symbol` if it is new.
+    pub fn share(&mut self, symbol: &Symbol) -> Symbol {
+        if let Some(pooled) = self.symbols.get(symbol.as_str()) {
+            return pooled.clone();
+        }
+        self.symbols.insert(symbol.clone());
+        symbol.clone()
+    }
+}
+
+/// Dense id of an interned symbol, usable as an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Ids are dense, so every index below [`Interner::len`] is one.
+    pub(crate) fn from_index(index: usize) -> Self {
+        SymbolId(index as u32)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    ids: HashMap<Arc<str>, SymbolId>,
+    names: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Id of `name`, assigning the next one if it is new.
+    pub fn intern(&mut self, name: &str) -> SymbolId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = SymbolId(u32::try_from(self.names.len()).expect("more than u32::MAX symbols"));
+        let name: Arc<str> = Arc::from(name);
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        id
+    }
+
+    /// Id of `name` if it was interned.
+    pub fn get(&self, name: &str) -> Option<SymbolId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn resolve(&self, id: SymbolId) -> &str {
+        &self.names[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = Interner::new();
+        let main = interner.intern("app::main");
+        let run = interner.intern("app::run");
+        assert_eq!(interner.intern("app::main"), main);
+        assert_eq!((main.index(), run.index()), (0, 1));
+        assert_eq!(interner.resolve(run), "app::run");
+        assert_eq!(interner.get("app::missing"), None);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_symbol_pool_shares_allocations() {
+        let mut pool = SymbolPool::default();
+        let first = pool.share(&Symbol::from("app::main"));
+        let second = pool.share(&Symbol::from("app::main".to_string()));
+        assert!(Arc::ptr_eq(&first.0, &second.0));
+        assert_eq!(second, "app::main");
+        assert_eq!(serde_json::to_string(&second).unwrap(), "\"app::main\"");
+    }
+
+    #[test]
+    fn test_call_graph_shares_symbols() {
+        use crate::domain::callgraph::{fixtures::node, CallGraph};
+        let shared = |cg: &CallGraph| std::ptr::eq(cg.nodes[1].id.as_str(), cg.nodes[0].callees[0].target.as_str());
+        let cg = CallGraph::new(vec![node("app::main", &["app::run"]), node("app::run", &[])]);
+        assert!(shared(&cg));
+        let loaded: CallGraph = serde_json::from_str(&serde_json::to_string(&cg).unwrap()).unwrap();
+        assert!(shared(&loaded));
+    }
+}
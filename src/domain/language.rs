@@ -1,18 +1,20 @@
-/// Language Domain Module
-/// 
-/// Defines supported programming languages for Mr. Hedgehog analysis.
+//! Language Domain Module
+//! 
+//! Defines supported programming languages for Mr. Hedgehog analysis.
 
 use std::path::Path;
 
 /// Supported programming languages for SCIP analysis.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Language {
+    #[default]
     Rust,
     Python,
 }
 
 impl Language {
     /// Parse language from string (CLI input).
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Language> {
         match s.to_lowercase().as_str() {
             "rust" | "rs" => Some(Language::Rust),
@@ -70,12 +72,6 @@ impl Language {
     }
 }
 
-impl Default for Language {
-    fn default() -> Self {
-        Language::Rust
-    }
-}
-
 impl std::fmt::Display for Language {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name())
@@ -0,0 +1,119 @@
+//! Source Links
+//!
+//! Turns `file:line` locations into URLs on a source host, for
+//! `--link-base https://github.com/org/repo/blob/<sha>/`:
+//! `/work/repo/src/main.rs:12` becomes
+//! `https://github.com/org/repo/blob/<sha>/src/main.rs#L12`. Paths are made
+//! relative to the repository root, so the same base works when the
+//! workspace is a subdirectory of the repository.
+
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLinks {
+    /// Ends with `/`.
+    base: String,
+    root: PathBuf,
+    /// Relative locations are taken from here.
+    cwd: PathBuf,
+}
+
+impl SourceLinks {
+    /// Links under `base` for files below `root`.
+    pub fn new(base: &str, root: impl Into<PathBuf>) -> Self {
+        let mut base = base.to_string();
+        if !base.ends_with('/') {
+            base.push('/');
+        }
+        let root = root.into();
+        Self { base, cwd: root.clone(), root }
+    }
+
+    /// Links for files below the git repository holding the project
+    /// directory `dir` (its first ancestor with a `.git`), or below `dir`
+    /// outside a repository; relative locations are taken from `dir`.
+    pub fn for_repository(base: &str, dir: &Path) -> Self {
+        let root = dir.ancestors().find(|d| d.join(".git").exists()).unwrap_or(dir);
+        Self { cwd: dir.to_path_buf(), ..Self::new(base, root) }
+    }
+
+    /// URL of `line` in `file`, `None` for files outside the root.
+    pub fn url(&self, file: &str, line: Option<usize>) -> Option<String> {
+        let mut url = self.base.clone();
+        for (i, part) in self.relative(file)?.iter().enumerate() {
+            if i > 0 {
+                url.push('/');
+            }
+            encode(part, &mut url);
+        }
+        if let Some(line) = line {
+            url.push_str(&format!("#L{}", line));
+        }
+        Some(url)
+    }
+
+    /// URL of a `file:line` (or bare `file`) location.
+    pub fn location_url(&self, location: &str) -> Option<String> {
+        match location.rsplit_once(':') {
+            Some((file, line)) if !file.is_empty() && line.parse::<usize>().is_ok() => self.url(file, line.parse().ok()),
+            _ => self.url(location, None),
+        }
+    }
+
+    /// Path components of `file` below the root, `.` and `..` resolved.
+    fn relative(&self, file: &str) -> Option<Vec<String>> {
+        let path = self.cwd.join(file.replace('\\', "/"));
+        let mut absolute = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    absolute.pop();
+                }
+                Component::CurDir => {}
+                other => absolute.push(other),
+            }
+        }
+        let parts: Vec<String> = absolute.strip_prefix(&self.root).ok()?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        Some(parts).filter(|p| !p.is_empty())
+    }
+}
+
+/// Percent-encode a path segment.
+fn encode(part: &str, out: &mut String) {
+    for b in part.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls() {
+        let links = SourceLinks::new("https://github.com/org/repo/blob/abc123", "/work/repo");
+        assert_eq!(links.location_url("/work/repo/src/main.rs:12").as_deref(), Some("https://github.com/org/repo/blob/abc123/src/main.rs#L12"));
+        assert_eq!(links.location_url("./src/a b.rs:3").as_deref(), Some("https://github.com/org/repo/blob/abc123/src/a%20b.rs#L3"));
+        assert_eq!(links.location_url("crates/x/../y/lib.rs").as_deref(), Some("https://github.com/org/repo/blob/abc123/crates/y/lib.rs"));
+        assert_eq!(links.url("src\\win.rs", Some(1)).as_deref(), Some("https://github.com/org/repo/blob/abc123/src/win.rs#L1"));
+        assert_eq!(links.location_url("/elsewhere/main.rs:1"), None);
+        assert_eq!(links.location_url("../outside.rs:1"), None);
+    }
+
+    #[test]
+    fn test_repository_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join(".git")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("crates/app")).unwrap();
+        let links = SourceLinks::for_repository("https://host/r/", &tmp.path().join("crates/app"));
+        let file = tmp.path().join("crates/app/src/lib.rs");
+        assert_eq!(links.url(file.to_str().unwrap(), Some(7)).as_deref(), Some("https://host/r/crates/app/src/lib.rs#L7"));
+        assert_eq!(links.url("src/lib.rs", Some(7)).as_deref(), Some("https://host/r/crates/app/src/lib.rs#L7"));
+    }
+}
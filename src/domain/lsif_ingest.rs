@@ -0,0 +1,367 @@
+//! LSIF Dump Ingestor.
+//! Builds the same CallGraph as [`ScipIngestor`](crate::domain::scip_ingest::ScipIngestor)
+//! from an LSIF dump (JSON lines, or one JSON array in older dumps), for CI
+//! setups that produce LSIF (lsif-node, sourcegraph tooling) rather than SCIP.
+//!
+//! A symbol is the `textDocument/definition` result its ranges lead to
+//! (through `next` edges and result sets), its definitions that result's
+//! ranges. Only definitions tagged as functions, methods or constructors
+//! become nodes; untagged definitions are skipped. A node's id is its
+//! moniker's identifier when it has one, `path::name` otherwise (with the
+//! line appended when a file defines the name twice).
+//!
+//! A reference is attributed to the innermost function whose extent holds
+//! it: the tag's `fullRange`, or up to the next definition in the document
+//! when the indexer gives none. LSIF has no reference roles, so every edge
+//! is a call; references to symbols the dump does not define are dropped.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode};
+use crate::domain::paths::{uri_to_path, ProjectRoot};
+use crate::domain::scip_ingest::{extend_bare_functions, DefinitionInfo, SourceRange};
+
+/// LSP `SymbolKind`s of function-like symbols: method, constructor, function.
+const FUNCTION_KINDS: [u32; 3] = [6, 9, 12];
+/// LSP `SymbolKind::Variable`: locals, which do not end a function's extent.
+const VARIABLE_KIND: u32 = 13;
+
+/// Vertex or edge id; indexers use numbers or strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(untagged)]
+enum Id {
+    Number(u64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct Position {
+    line: i32,
+    character: i32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+impl From<Range> for SourceRange {
+    fn from(range: Range) -> Self {
+        SourceRange {
+            start_line: range.start.line,
+            start_col: range.start.character,
+            end_line: range.end.line,
+            end_col: range.end.character,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Tag {
+    #[serde(rename = "type")]
+    tag_type: String,
+    #[serde(default)]
+    text: String,
+    kind: Option<u32>,
+    full_range: Option<Range>,
+}
+
+/// One line of the dump, vertex or edge; fields the graph does not need are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Element {
+    id: Id,
+    #[serde(rename = "type")]
+    element_type: String,
+    label: String,
+    // Vertices
+    uri: Option<String>,
+    project_root: Option<String>,
+    start: Option<Position>,
+    end: Option<Position>,
+    tag: Option<Tag>,
+    identifier: Option<String>,
+    // Edges
+    out_v: Option<Id>,
+    in_v: Option<Id>,
+    #[serde(default)]
+    in_vs: Vec<Id>,
+}
+
+/// A range vertex, with the tag of a definition.
+struct RangeVertex {
+    range: SourceRange,
+    tag: Option<Tag>,
+}
+
+/// The vertices and edges of a dump the graph is built from.
+#[derive(Default)]
+struct Dump {
+    project_root: Option<String>,
+    /// Document id to its URI, in dump order.
+    documents: Vec<(Id, String)>,
+    ranges: HashMap<Id, RangeVertex>,
+    /// Ranges of each document, in dump order.
+    contains: HashMap<Id, Vec<Id>>,
+    next: HashMap<Id, Id>,
+    definition_results: HashMap<Id, Id>,
+    items: HashMap<Id, Vec<Id>>,
+    monikers: HashMap<Id, Id>,
+    identifiers: HashMap<Id, String>,
+}
+
+impl Dump {
+    fn parse(text: &str) -> Result<Self> {
+        let elements: Vec<Element> = if text.trim_start().starts_with('[') {
+            serde_json::from_str(text).context("Failed to parse LSIF dump")?
+        } else {
+            text.lines().enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("Failed to parse LSIF dump line {}", i + 1)))
+                .collect::<Result<_>>()?
+        };
+
+        let mut dump = Dump::default();
+        for element in elements {
+            if element.element_type == "vertex" {
+                match element.label.as_str() {
+                    "metaData" => dump.project_root = element.project_root,
+                    "document" => dump.documents.push((element.id, element.uri.unwrap_or_default())),
+                    "range" => {
+                        let (Some(start), Some(end)) = (element.start, element.end) else { continue };
+                        dump.ranges.insert(element.id, RangeVertex { range: Range { start, end }.into(), tag: element.tag });
+                    }
+                    "moniker" => {
+                        if let Some(identifier) = element.identifier {
+                            dump.identifiers.insert(element.id, identifier);
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            let Some(out_v) = element.out_v else { continue };
+            let in_vs = element.in_v.into_iter().chain(element.in_vs);
+            match element.label.as_str() {
+                "contains" => dump.contains.entry(out_v).or_default().extend(in_vs),
+                "item" => dump.items.entry(out_v).or_default().extend(in_vs),
+                label => {
+                    let Some(in_v) = in_vs.into_iter().next() else { continue };
+                    match label {
+                        "next" => { dump.next.insert(out_v, in_v); }
+                        "textDocument/definition" => { dump.definition_results.insert(out_v, in_v); }
+                        "moniker" => { dump.monikers.insert(out_v, in_v); }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(dump)
+    }
+
+    /// `vertex` followed by the result sets its `next` edges lead to.
+    fn chain<'a>(&'a self, vertex: &'a Id) -> impl Iterator<Item = &'a Id> {
+        let mut seen = HashSet::new();
+        std::iter::successors(Some(vertex), move |v| self.next.get(*v)).take_while(move |v| seen.insert(*v))
+    }
+
+    /// The definition result of the symbol at `range`, which stands for the symbol.
+    fn symbol(&self, range: &Id) -> Option<&Id> {
+        self.chain(range).find_map(|v| self.definition_results.get(v))
+    }
+
+    /// Whether `range` is one of the definitions of `symbol`.
+    fn defines(&self, symbol: &Id, range: &Id) -> bool {
+        self.items.get(symbol).is_some_and(|defs| defs.contains(range))
+    }
+
+    fn moniker(&self, range: &Id) -> Option<&str> {
+        self.chain(range).find_map(|v| self.identifiers.get(self.monikers.get(v)?)).map(String::as_str)
+    }
+}
+
+/// LSIF Ingestor for building CallGraphs from LSIF dumps.
+pub struct LsifIngestor;
+
+impl LsifIngestor {
+    /// Ingest an LSIF dump, locations relative to the project root its
+    /// metadata names.
+    pub fn ingest_and_build_graph(lsif_path: &Path) -> Result<CallGraph> {
+        Self::ingest(lsif_path, None)
+    }
+
+    /// [`Self::ingest_and_build_graph`] with locations relative to `root`.
+    pub fn ingest_relative_to(lsif_path: &Path, root: &ProjectRoot) -> Result<CallGraph> {
+        Self::ingest(lsif_path, Some(root))
+    }
+
+    fn ingest(lsif_path: &Path, root: Option<&ProjectRoot>) -> Result<CallGraph> {
+        tracing::debug!("[LSIF Ingest] Loading dump from: {}", lsif_path.display());
+        let text = std::fs::read_to_string(lsif_path)
+            .with_context(|| format!("Failed to open LSIF dump {}", lsif_path.display()))?;
+        let dump = Dump::parse(&text).with_context(|| lsif_path.display().to_string())?;
+
+        // Each document's path as reported in locations
+        let dump_root = dump.project_root.as_deref().and_then(ProjectRoot::from_uri);
+        let path_of = |uri: &str| -> String {
+            let path = uri_to_path(uri).map(|p| p.to_string_lossy().into_owned()).unwrap_or_else(|| uri.to_string());
+            match root.or(dump_root.as_ref()) {
+                Some(root) => root.relativize(&path),
+                None => path,
+            }
+        };
+
+        // Pass 1: function definitions, by symbol vertex
+        let mut nodes: Vec<CallGraphNode> = Vec::new();
+        let mut node_of: HashMap<&Id, usize> = HashMap::new();
+        let mut ids: HashSet<String> = HashSet::new();
+        let mut definitions: HashMap<&Id, Vec<DefinitionInfo>> = HashMap::new();
+        for (document, uri) in &dump.documents {
+            let path = path_of(uri);
+            let mut file_defs = Vec::new();
+            let mut starts = Vec::new();
+            for range_id in dump.contains.get(document).into_iter().flatten() {
+                let Some(vertex) = dump.ranges.get(range_id) else { continue };
+                let Some(tag) = vertex.tag.as_ref().filter(|t| t.tag_type == "definition") else { continue };
+                let Some(symbol) = dump.symbol(range_id) else { continue };
+                if !dump.defines(symbol, range_id) || tag.kind.is_none_or(|kind| kind == VARIABLE_KIND) {
+                    continue;
+                }
+                starts.push((vertex.range.start_line, vertex.range.start_col));
+                if !tag.kind.is_some_and(|kind| FUNCTION_KINDS.contains(&kind)) {
+                    continue;
+                }
+
+                if !node_of.contains_key(symbol) {
+                    let id = match dump.moniker(range_id) {
+                        Some(identifier) => identifier.to_string(),
+                        None if ids.contains(&format!("{}::{}", path, tag.text)) => format!("{}::{}:{}", path, tag.text, vertex.range.start_line + 1),
+                        None => format!("{}::{}", path, tag.text),
+                    };
+                    ids.insert(id.clone());
+                    node_of.insert(symbol, nodes.len());
+                    nodes.push(CallGraphNode {
+                        id: id.into(),
+                        label: Some(tag.text.clone()),
+                        location: Some(format!("{}:{}", path, vertex.range.start_line + 1)),
+                        ..Default::default()
+                    });
+                }
+                file_defs.push(DefinitionInfo {
+                    symbol: nodes[node_of[symbol]].id.to_string(),
+                    enclosing: tag.full_range.is_some(),
+                    range: tag.full_range.map_or_else(|| vertex.range.clone(), SourceRange::from),
+                });
+            }
+            extend_bare_functions(&mut file_defs, &mut starts);
+            // Smallest extent first: the first definition holding a reference is its caller
+            file_defs.sort_by_key(|def| def.range.size());
+            definitions.insert(document, file_defs);
+        }
+        let index: HashMap<String, usize> = nodes.iter().enumerate().map(|(i, n)| (n.id.to_string(), i)).collect();
+
+        // Pass 2: references to those functions, attributed to their callers
+        for (document, uri) in &dump.documents {
+            let path = path_of(uri);
+            let file_defs = &definitions[document];
+            for range_id in dump.contains.get(document).into_iter().flatten() {
+                let Some(vertex) = dump.ranges.get(range_id) else { continue };
+                let Some(symbol) = dump.symbol(range_id) else { continue };
+                let Some(&callee) = node_of.get(symbol) else { continue };
+                if dump.defines(symbol, range_id) {
+                    continue;
+                }
+                let Some(caller) = file_defs.iter().find(|def| def.range.contains(&vertex.range)) else { continue };
+                let caller = index[&caller.symbol];
+                if caller == callee {
+                    continue;
+                }
+                let target = nodes[callee].id.clone();
+                // One edge per callee at its first call site, weighted by the number of calls
+                match nodes[caller].callees.iter_mut().find(|e| e.target == target) {
+                    Some(edge) => edge.weight += 1,
+                    None => nodes[caller].callees.push(CallEdge::at(target, path.clone(), (vertex.range.start_line + 1) as usize)),
+                }
+            }
+        }
+
+        // Sort by ID for deterministic output
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(CallGraph::new(nodes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// `fn main() { helper(); helper(); }` and `fn helper() { let x = 1; }`
+    /// in one document, `main` located by its full range only.
+    fn dump(root: &Path) -> String {
+        let lines = [
+            format!(r#"{{"id":1,"type":"vertex","label":"metaData","version":"0.4.3","projectRoot":"file://{}"}}"#, root.display()),
+            format!(r#"{{"id":2,"type":"vertex","label":"document","uri":"file://{}/src/main.rs","languageId":"rust"}}"#, root.display()),
+            r#"{"id":3,"type":"vertex","label":"range","start":{"line":0,"character":3},"end":{"line":0,"character":7},"tag":{"type":"definition","text":"main","kind":12,"fullRange":{"start":{"line":0,"character":0},"end":{"line":3,"character":1}}}}"#.to_string(),
+            r#"{"id":4,"type":"vertex","label":"resultSet"}"#.to_string(),
+            r#"{"id":5,"type":"vertex","label":"definitionResult"}"#.to_string(),
+            r#"{"id":6,"type":"vertex","label":"range","start":{"line":5,"character":3},"end":{"line":5,"character":9},"tag":{"type":"definition","text":"helper","kind":12}}"#.to_string(),
+            r#"{"id":7,"type":"vertex","label":"resultSet"}"#.to_string(),
+            r#"{"id":8,"type":"vertex","label":"definitionResult"}"#.to_string(),
+            r#"{"id":9,"type":"vertex","label":"range","start":{"line":1,"character":4},"end":{"line":1,"character":10}}"#.to_string(),
+            r#"{"id":10,"type":"vertex","label":"range","start":{"line":2,"character":4},"end":{"line":2,"character":10}}"#.to_string(),
+            r#"{"id":11,"type":"vertex","label":"range","start":{"line":6,"character":8},"end":{"line":6,"character":9},"tag":{"type":"definition","text":"x","kind":13}}"#.to_string(),
+            r#"{"id":12,"type":"vertex","label":"moniker","scheme":"rust","identifier":"app::helper","kind":"export"}"#.to_string(),
+            r#"{"id":20,"type":"edge","label":"contains","outV":2,"inVs":[3,6,9,10,11]}"#.to_string(),
+            r#"{"id":21,"type":"edge","label":"next","outV":3,"inV":4}"#.to_string(),
+            r#"{"id":22,"type":"edge","label":"textDocument/definition","outV":4,"inV":5}"#.to_string(),
+            r#"{"id":23,"type":"edge","label":"item","outV":5,"inVs":[3],"document":2}"#.to_string(),
+            r#"{"id":24,"type":"edge","label":"next","outV":6,"inV":7}"#.to_string(),
+            r#"{"id":25,"type":"edge","label":"next","outV":9,"inV":7}"#.to_string(),
+            r#"{"id":26,"type":"edge","label":"next","outV":10,"inV":7}"#.to_string(),
+            r#"{"id":27,"type":"edge","label":"textDocument/definition","outV":7,"inV":8}"#.to_string(),
+            r#"{"id":28,"type":"edge","label":"item","outV":8,"inVs":[6],"document":2}"#.to_string(),
+            r#"{"id":29,"type":"edge","label":"moniker","outV":7,"inV":12}"#.to_string(),
+        ];
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_lsif_dump_to_graph() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("dump.lsif");
+        std::fs::write(&path, dump(dir.path())).unwrap();
+
+        let graph = LsifIngestor::ingest_and_build_graph(&path).unwrap();
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::helper", "src/main.rs::main"]);
+        let main = &graph.nodes[1];
+        assert_eq!(main.location.as_deref(), Some("src/main.rs:1"));
+        assert_eq!(main.callees.len(), 1);
+        assert_eq!(main.callees[0].target, "app::helper");
+        assert_eq!(main.callees[0].location().as_deref(), Some("src/main.rs:2"));
+        assert_eq!(main.callees[0].weight, 2);
+        assert!(graph.nodes[0].callees.is_empty());
+        assert_eq!(graph.nodes[0].label.as_deref(), Some("helper"));
+    }
+
+    #[test]
+    fn test_lsif_json_array_and_errors() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("dump.json");
+        let array = format!("[{}]", dump(dir.path()).replace('\n', ","));
+        std::fs::write(&path, array).unwrap();
+        assert_eq!(LsifIngestor::ingest_and_build_graph(&path).unwrap().nodes.len(), 2);
+
+        std::fs::write(&path, "{\"id\":1,\"type\":\"vertex\",\"label\":\"metaData\"}\nnot json\n").unwrap();
+        let err = LsifIngestor::ingest_and_build_graph(&path).unwrap_err();
+        assert!(format!("{:#}", err).contains("line 2"));
+        assert!(LsifIngestor::ingest_and_build_graph(&dir.path().join("none.lsif")).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Function Metrics
+//!
+//! Structural complexity of each function body, measured while the syn
+//! builder walks it, and the `--metrics` report of the worst offenders.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::callgraph::CallGraph;
+
+/// Complexity of one function body; closures count towards their owner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FnMetrics {
+    /// Cyclomatic complexity: 1 plus one per `if`, `while`, `for`, extra
+    /// `match` arm, match guard, `&&`, `||` and `?`.
+    pub complexity: usize,
+    /// Statements, including those of nested blocks.
+    pub statements: usize,
+    /// Deepest nesting of control-flow blocks (`if`, `match`, loops, closures).
+    pub nesting: usize,
+}
+
+impl FnMetrics {
+    /// Metrics of an empty body.
+    pub fn new() -> Self {
+        FnMetrics { complexity: 1, statements: 0, nesting: 0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FnMetricsEntry {
+    pub id: String,
+    pub location: Option<String>,
+    #[serde(flatten)]
+    pub metrics: FnMetrics,
+}
+
+/// Functions ordered from most to least complex.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsReport {
+    /// Functions with metrics in the graph.
+    pub total_functions: usize,
+    pub functions: Vec<FnMetricsEntry>,
+}
+
+impl MetricsReport {
+    /// The `top` functions by complexity, then nesting, then statements.
+    pub fn worst(cg: &CallGraph, top: usize) -> Self {
+        let mut functions: Vec<FnMetricsEntry> = cg.nodes.iter()
+            .filter_map(|n| Some(FnMetricsEntry { id: n.id.to_string(), location: n.location.clone(), metrics: n.metrics? }))
+            .collect();
+        let total_functions = functions.len();
+        functions.sort_by(|a, b| {
+            let key = |e: &FnMetricsEntry| (e.metrics.complexity, e.metrics.nesting, e.metrics.statements);
+            key(b).cmp(&key(a)).then_with(|| a.id.cmp(&b.id))
+        });
+        functions.truncate(top);
+        MetricsReport { total_functions, functions }
+    }
+}
+
+impl fmt::Display for MetricsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Most complex functions: {} of {}", self.functions.len(), self.total_functions)?;
+        writeln!(f, "{:>10}  {:>7}  {:>10}  Function", "Complexity", "Nesting", "Statements")?;
+        for e in &self.functions {
+            let location = e.location.as_ref().map(|l| format!(" ({})", l)).unwrap_or_default();
+            writeln!(f, "{:>10}  {:>7}  {:>10}  {}{}", e.metrics.complexity, e.metrics.nesting, e.metrics.statements, e.id, location)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::CallGraphNode;
+
+    #[test]
+    fn test_worst() {
+        let node = |id: &str, complexity: usize, nesting: usize| CallGraphNode {
+            id: id.into(),
+            location: Some(format!("src/lib.rs:{}", complexity)),
+            metrics: Some(FnMetrics { complexity, statements: 3, nesting }),
+            ..Default::default()
+        };
+        let closure = CallGraphNode { metrics: None, ..node("app::b::{closure#0}", 9, 9) };
+        let cg = CallGraph::new(vec![node("app::a", 2, 1), node("app::b", 7, 3), node("app::c", 7, 4), closure]);
+
+        let report = MetricsReport::worst(&cg, 2);
+        assert_eq!(report.total_functions, 3);
+        let ids: Vec<&str> = report.functions.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::c", "app::b"]);
+        assert!(report.to_string().contains("         7        4           3  app::c (src/lib.rs:7)"), "{}", report);
+    }
+}
@@ -1,9 +1,36 @@
 pub mod ast;
 pub mod callgraph;
 pub mod index;
+pub mod imports;
 pub mod trace;
+pub mod bundle;
 pub mod store;
 pub mod scip_ingest;
+pub mod lsif_ingest;
 pub mod language;
 pub mod entry_point;
 pub mod flowgraph;
+pub mod modules;
+pub mod search;
+pub mod query;
+pub mod reachability;
+pub mod dominators;
+pub mod impact;
+pub mod filter;
+pub mod hybrid;
+pub mod summary;
+pub mod source;
+pub mod diff;
+pub mod cfg;
+pub mod external;
+pub mod stats;
+pub mod metrics;
+pub mod coverage;
+pub mod unsafety;
+pub mod panics;
+pub mod taint;
+pub mod progress;
+pub mod interner;
+pub mod compact;
+pub mod links;
+pub mod paths;
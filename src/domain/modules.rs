@@ -0,0 +1,133 @@
+//! Module Graph
+//!
+//! The call graph zoomed out for architecture reviews: functions are folded
+//! into their module (or crate) and the calls between two modules into one
+//! edge weighted by how many calls it stands for. Method ids carry no module
+//! (`Client::send@app`), so methods count towards their crate's root module.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::domain::callgraph::{node_module, CallGraph, CallGraphNode};
+
+/// What functions are folded into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleLevel {
+    Module,
+    Crate,
+}
+
+impl ModuleLevel {
+    /// `modules` / `crates`, as `--view` spells them.
+    pub fn parse(view: &str) -> Option<Self> {
+        match view {
+            "modules" => Some(ModuleLevel::Module),
+            "crates" => Some(ModuleLevel::Crate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleNode {
+    /// `app::net`, or `app` for the crate root.
+    pub id: String,
+    pub krate: String,
+    /// Function nodes folded into it.
+    pub functions: usize,
+    /// Stub nodes of crates that were not analyzed.
+    pub external: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleEdge {
+    pub from: String,
+    pub to: String,
+    /// Call edges between functions of the two modules.
+    pub calls: usize,
+}
+
+/// Modules sorted by id, edges by caller then callee; calls within a module
+/// are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleGraph {
+    pub nodes: Vec<ModuleNode>,
+    pub edges: Vec<ModuleEdge>,
+}
+
+impl ModuleGraph {
+    pub fn from_callgraph(cg: &CallGraph, level: ModuleLevel) -> Self {
+        let module_of = |node: &CallGraphNode| -> (String, String) {
+            if let Some(krate) = &node.external {
+                return (krate.clone(), krate.clone());
+            }
+            let (krate, module) = node_module(&node.id);
+            let id = match level {
+                ModuleLevel::Crate => krate.to_string(),
+                ModuleLevel::Module => std::iter::once(krate).chain(module).collect::<Vec<_>>().join("::"),
+            };
+            (id, krate.to_string())
+        };
+
+        let mut modules: BTreeMap<String, ModuleNode> = BTreeMap::new();
+        let mut owner: HashMap<&str, String> = HashMap::with_capacity(cg.nodes.len());
+        for node in &cg.nodes {
+            let (id, krate) = module_of(node);
+            let module = modules.entry(id.clone()).or_insert_with(|| ModuleNode {
+                id: id.clone(),
+                krate,
+                functions: 0,
+                external: node.external.is_some(),
+            });
+            if node.type_kind.is_none() {
+                module.functions += 1;
+            }
+            owner.entry(node.id.as_str()).or_insert(id);
+        }
+
+        let mut calls: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for node in &cg.nodes {
+            let from = &owner[node.id.as_str()];
+            for edge in node.call_edges() {
+                match owner.get(edge.target.as_str()) {
+                    Some(to) if to != from => *calls.entry((from.as_str(), to.as_str())).or_default() += 1,
+                    _ => {}
+                }
+            }
+        }
+        let edges = calls.into_iter()
+            .map(|((from, to), calls)| ModuleEdge { from: from.to_string(), to: to.to_string(), calls })
+            .collect();
+        ModuleGraph { nodes: modules.into_values().collect(), edges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    #[test]
+    fn test_module_edges_are_weighted() {
+        let cg = CallGraph::new(vec![
+            node("app::main", &["app::net::send", "app::net::recv", "app::util"]),
+            node("app::util", &["app::net::send", "app::util"]),
+            node("app::net::send", &["Client::send@app", "serde_json::to_string"]),
+            node("app::net::recv", &[]),
+            node("Client::send@app", &["lib::parse"]),
+            node("lib::parse", &[]),
+            CallGraphNode { external: Some("serde_json".into()), ..node("serde_json::to_string", &[]) },
+        ]);
+
+        let modules = ModuleGraph::from_callgraph(&cg, ModuleLevel::Module);
+        let ids: Vec<(&str, usize, bool)> = modules.nodes.iter().map(|n| (n.id.as_str(), n.functions, n.external)).collect();
+        assert_eq!(ids, vec![("app", 3, false), ("app::net", 2, false), ("lib", 1, false), ("serde_json", 1, true)]);
+        let edges: Vec<(&str, &str, usize)> = modules.edges.iter().map(|e| (e.from.as_str(), e.to.as_str(), e.calls)).collect();
+        assert_eq!(edges, vec![("app", "app::net", 3), ("app", "lib", 1), ("app::net", "app", 1), ("app::net", "serde_json", 1)]);
+
+        let crates = ModuleGraph::from_callgraph(&cg, ModuleLevel::Crate);
+        let edges: Vec<(&str, &str, usize)> = crates.edges.iter().map(|e| (e.from.as_str(), e.to.as_str(), e.calls)).collect();
+        assert_eq!(edges, vec![("app", "lib", 1), ("app", "serde_json", 1)]);
+        assert_eq!(crates.nodes[0].functions, 5);
+    }
+}
@@ -0,0 +1,53 @@
+//! Potential Panics
+//!
+//! Panic sites recorded while the syn builder walks a function body:
+//! `panic!`, `todo!`, `unimplemented!` and `unreachable!`, `.unwrap()` and
+//! `.expect(..)` calls, and indexing. The receiver's type is unknown at that
+//! point, so `unwrap` on anything counts and so does `map[key]`.
+//! `TraceGenerator::panic_paths` turns them into call paths.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanicKind {
+    Panic,
+    Todo,
+    Unimplemented,
+    Unreachable,
+    Unwrap,
+    Expect,
+    Index,
+}
+
+impl PanicKind {
+    /// Kind of a call to the macro `name`, if it always panics.
+    pub fn of_macro(name: &str) -> Option<Self> {
+        match name {
+            "panic" => Some(PanicKind::Panic),
+            "todo" => Some(PanicKind::Todo),
+            "unimplemented" => Some(PanicKind::Unimplemented),
+            "unreachable" => Some(PanicKind::Unreachable),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PanicKind::Panic => "panic!",
+            PanicKind::Todo => "todo!",
+            PanicKind::Unimplemented => "unimplemented!",
+            PanicKind::Unreachable => "unreachable!",
+            PanicKind::Unwrap => "unwrap()",
+            PanicKind::Expect => "expect()",
+            PanicKind::Index => "indexing",
+        }
+    }
+}
+
+/// One place in a function body that can panic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PanicSite {
+    pub kind: PanicKind,
+    pub line: usize,
+}
@@ -0,0 +1,149 @@
+//! Project Root
+//!
+//! Node locations are reported relative to the project root (the workspace
+//! directory) whichever builder produced them: cargo hands the syn builder
+//! absolute paths, while SCIP indices store paths relative to the directory
+//! they were generated in. [`ProjectRoot`] maps both onto one spelling, so
+//! snippets, links and diffs of the same file line up.
+
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectRoot {
+    /// Absolute, symlinks resolved when it exists.
+    dir: PathBuf,
+}
+
+impl ProjectRoot {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        let dir = dir.canonicalize().unwrap_or_else(|_| normalize(&std::env::current_dir().unwrap_or_default().join(dir)));
+        Self { dir }
+    }
+
+    /// Root named by a `file://` URI, as in SCIP metadata.
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        uri_to_path(uri).map(Self::new)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// `path` relative to the root, with `/` separators and `.`/`..`
+    /// resolved. Relative paths are taken to be relative to the root
+    /// already; absolute ones outside it are kept as they are.
+    pub fn relativize(&self, path: &str) -> String {
+        let path = PathBuf::from(path.replace('\\', "/"));
+        if path.is_relative() {
+            return to_slashes(&normalize(&path));
+        }
+        let path = normalize(&path);
+        if let Ok(relative) = path.strip_prefix(&self.dir) {
+            return to_slashes(relative);
+        }
+        // The root is canonical; the path may go through a symlink
+        if let Some(relative) = path.canonicalize().ok().and_then(|p| p.strip_prefix(&self.dir).ok().map(to_slashes)) {
+            return relative;
+        }
+        path.to_string_lossy().into_owned()
+    }
+
+    /// `relativize` for a path relative to another root.
+    pub fn relativize_from(&self, other: &ProjectRoot, path: &str) -> String {
+        if other == self {
+            return self.relativize(path);
+        }
+        self.relativize(&other.resolve(path).to_string_lossy())
+    }
+
+    /// Absolute path of a root-relative one.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        normalize(&self.dir.join(path))
+    }
+}
+
+/// Lexically resolve `.` and `..`; leading `..` of relative paths are kept.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                let up = matches!(out.components().next_back(), None | Some(Component::ParentDir));
+                if up {
+                    out.push("..");
+                } else if !matches!(out.components().next_back(), Some(Component::RootDir | Component::Prefix(_))) {
+                    out.pop();
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn to_slashes(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+/// Path of a `file://` URI, percent-decoded.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    Some(PathBuf::from(String::from_utf8(percent_decode(path)).ok()?))
+}
+
+/// Decode `%XX` escapes; malformed escapes are kept verbatim.
+pub fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%').then(|| std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok())
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relativize() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("crates/app/src")).unwrap();
+        let root = ProjectRoot::new(tmp.path());
+        let abs = |p: &str| root.dir().join(p).to_string_lossy().into_owned();
+
+        assert_eq!(root.relativize(&abs("crates/app/src/lib.rs")), "crates/app/src/lib.rs");
+        assert_eq!(root.relativize(&abs("crates/app/../app/./src/lib.rs")), "crates/app/src/lib.rs");
+        assert_eq!(root.relativize("./src\\main.rs"), "src/main.rs");
+        assert_eq!(root.relativize("../shared/src/lib.rs"), "../shared/src/lib.rs");
+        assert_eq!(root.relativize("/elsewhere/lib.rs"), "/elsewhere/lib.rs");
+        assert_eq!(root.relativize("<eval>"), "<eval>");
+        assert_eq!(root.resolve("src/main.rs"), root.dir().join("src/main.rs"));
+
+        // A SCIP index generated in a subdirectory
+        let app = ProjectRoot::new(tmp.path().join("crates/app"));
+        assert_eq!(root.relativize_from(&app, "src/lib.rs"), "crates/app/src/lib.rs");
+        assert_eq!(app.relativize_from(&root, "crates/app/src/lib.rs"), "src/lib.rs");
+    }
+
+    #[test]
+    fn test_uri_to_path() {
+        assert_eq!(uri_to_path("file:///my%20proj/src").as_deref(), Some(Path::new("/my proj/src")));
+        assert_eq!(uri_to_path("https://example.com"), None);
+    }
+}
@@ -0,0 +1,70 @@
+//! Progress Reporting
+//!
+//! Long analyses report how far along they are through a [`Progress`]: each
+//! phase (parsing files, resolving calls, reading a SCIP index) announces
+//! its number of steps, then counts them off together with what it found on
+//! the way. The library only counts; drawing is up to the caller.
+
+/// Receives progress from the builders, from worker threads.
+pub trait Progress: Send + Sync {
+    /// A phase of `total` steps begins; `found` names what it discovers
+    /// ("functions", "edges").
+    fn start(&self, phase: &str, total: usize, found: &str);
+    /// `steps` more steps are done, having discovered `found` more items.
+    fn advance(&self, steps: usize, found: usize);
+    /// The current phase is done.
+    fn finish(&self);
+}
+
+/// Reports nowhere.
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn start(&self, _phase: &str, _total: usize, _found: &str) {}
+    fn advance(&self, _steps: usize, _found: usize) {}
+    fn finish(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use crate::domain::source::{SourceFile, SourceSet};
+    use crate::infrastructure::SimpleCallGraphBuilder;
+
+    /// Totals per phase: (name, total, steps, found).
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, usize, usize, usize)>>);
+
+    impl Progress for Recorder {
+        fn start(&self, phase: &str, total: usize, found: &str) {
+            self.0.lock().unwrap().push((format!("{} ({})", phase, found), total, 0, 0));
+        }
+        fn advance(&self, steps: usize, found: usize) {
+            let mut phases = self.0.lock().unwrap();
+            let last = phases.last_mut().unwrap();
+            last.2 += steps;
+            last.3 += found;
+        }
+        fn finish(&self) {}
+    }
+
+    #[test]
+    fn test_syn_phases() {
+        let files = SourceSet::new(vec![
+            SourceFile::new("app", "src/main.rs", "mod util;\nfn main() { util::a(); util::b(); }\n"),
+            SourceFile::new("app", "src/util.rs", "pub fn a() { b(); }\npub fn b() {}\n"),
+            SourceFile::new("app", "src/broken.rs", "fn oops( {\n"),
+        ]);
+        let recorder = Arc::new(Recorder::default());
+        let (graph, errors) = SimpleCallGraphBuilder::new().with_progress(recorder.clone()).build_with_errors(&files);
+        assert_eq!(errors.len(), 1);
+
+        let edges = graph.nodes.iter().map(|n| n.callees.len()).sum::<usize>();
+        assert_eq!(edges, 3);
+        assert_eq!(*recorder.0.lock().unwrap(), vec![
+            ("Parsing files (functions)".to_string(), 3, 3, 3),
+            ("Resolving calls (edges)".to_string(), 2, 2, edges),
+        ]);
+    }
+}
@@ -0,0 +1,453 @@
+//! Set Queries
+//!
+//! A small expression language over sets of functions, for questions a
+//! single filter flag cannot answer:
+//!
+//! ```text
+//! reach(main) & calls(unsafe) - crate(test_utils)
+//! ```
+//!
+//! Sets are built from names (`main`, `"Client::send@app"`, resolved like
+//! `--entry`), id globs (`app::net::*`, or `id(...)`), `crate(...)` and
+//! `file(...)` globs, and the attributes `all`, `pub`, `async`, `unsafe`
+//! (unsafe fns), `unsafe_blocks` (functions with unsafe blocks), `test`,
+//! `external` and `panics`. `reach(s)` is `s` plus everything it calls, directly or not;
+//! `callers(s)` everything calling into `s`, directly or not; `calls(s)` and
+//! `callees(s)` go one call deep. `&` intersects, `|` unites, `-` subtracts
+//! and `!` complements; `&` binds tighter than `|` and `-`. Quote a name
+//! that is also an attribute (`"test"`).
+
+use std::fmt;
+
+use crate::domain::callgraph::{node_module, CallGraph, CallGraphNode};
+use crate::domain::compact::CompactGraph;
+use crate::domain::filter::{glob_match, GraphFilter};
+use crate::domain::interner::SymbolId;
+use crate::domain::search::{resolve_symbol, LookupError};
+use crate::domain::source::SourceOrigin;
+
+/// Node flags a query can select on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    All,
+    Public,
+    Async,
+    Unsafe,
+    UnsafeBlocks,
+    Test,
+    External,
+    Panics,
+}
+
+impl Attribute {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word {
+            "all" => Attribute::All,
+            "pub" => Attribute::Public,
+            "async" => Attribute::Async,
+            "unsafe" => Attribute::Unsafe,
+            "unsafe_blocks" => Attribute::UnsafeBlocks,
+            "test" => Attribute::Test,
+            "external" => Attribute::External,
+            "panics" => Attribute::Panics,
+            _ => return None,
+        })
+    }
+
+    fn matches(self, node: &CallGraphNode) -> bool {
+        match self {
+            Attribute::All => true,
+            Attribute::Public => node.is_public,
+            Attribute::Async => node.is_async,
+            Attribute::Unsafe => node.is_unsafe,
+            Attribute::UnsafeBlocks => node.unsafe_blocks > 0,
+            Attribute::Test => node.is_test || node.origin == Some(SourceOrigin::Test),
+            Attribute::External => node.external.is_some(),
+            Attribute::Panics => !node.panics.is_empty(),
+        }
+    }
+}
+
+/// A parsed query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Attribute(Attribute),
+    /// A function, resolved like `--entry`.
+    Name(String),
+    /// Node ids matching a glob, as `--include` matches them.
+    Id(String),
+    Crate(String),
+    File(String),
+    Reach(Box<Expr>),
+    Callers(Box<Expr>),
+    Calls(Box<Expr>),
+    Callees(Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Minus(Box<Expr>, Box<Expr>),
+}
+
+/// Why a query could not be answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// `column` is 1-based.
+    Syntax { column: usize, message: String },
+    Lookup(LookupError),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Syntax { column, message } => write!(f, "{} at column {}", message, column),
+            QueryError::Lookup(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(char),
+    End,
+}
+
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let is_word = |c: char| c.is_alphanumeric() || "_:@*?./<>".contains(c);
+    // Positions count characters, so columns line up under non-ASCII input
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if "&|-!()".contains(c) {
+            tokens.push((i, Token::Op(c)));
+            i += 1;
+        } else if c == '"' {
+            let end = chars[i + 1..].iter().position(|&c| c == '"')
+                .ok_or(QueryError::Syntax { column: i + 1, message: "unterminated string".to_string() })?;
+            tokens.push((i, Token::Quoted(chars[i + 1..i + 1 + end].iter().collect())));
+            i += end + 2;
+        } else if is_word(c) {
+            let len = chars[i..].iter().take_while(|&&c| is_word(c)).count();
+            tokens.push((i, Token::Word(chars[i..i + len].iter().collect())));
+            i += len;
+        } else {
+            return Err(QueryError::Syntax { column: i + 1, message: format!("unexpected '{}'", c) });
+        }
+    }
+    tokens.push((chars.len(), Token::End));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> (usize, Token) {
+        let token = self.tokens[self.pos].clone();
+        if token.1 != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, QueryError> {
+        Err(QueryError::Syntax { column: self.tokens[self.pos].0 + 1, message: message.into() })
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), QueryError> {
+        match self.peek() {
+            Token::Op(c) if *c == op => {
+                self.next();
+                Ok(())
+            }
+            _ => self.error(format!("expected '{}'", op)),
+        }
+    }
+
+    /// `term (('|' | '-') term)*`
+    fn expr(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.term()?;
+        loop {
+            match self.peek() {
+                Token::Op('|') => {
+                    self.next();
+                    left = Expr::Or(Box::new(left), Box::new(self.term()?));
+                }
+                Token::Op('-') => {
+                    self.next();
+                    left = Expr::Minus(Box::new(left), Box::new(self.term()?));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    /// `unary ('&' unary)*`
+    fn term(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.unary()?;
+        while *self.peek() == Token::Op('&') {
+            self.next();
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        match self.peek() {
+            Token::Op('!') => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Token::Op('(') => {
+                self.next();
+                let inner = self.expr()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Token::Quoted(_) => {
+                let Token::Quoted(text) = self.next().1 else { unreachable!() };
+                Ok(name_or_glob(text))
+            }
+            Token::Word(_) => {
+                let Token::Word(word) = self.next().1 else { unreachable!() };
+                if *self.peek() == Token::Op('(') {
+                    return self.call(&word);
+                }
+                Ok(Attribute::parse(&word).map_or_else(|| name_or_glob(word), Expr::Attribute))
+            }
+            _ => self.error("expected a set"),
+        }
+    }
+
+    /// `reach(...)` and friends take a set, `crate(...)` and friends a pattern.
+    fn call(&mut self, function: &str) -> Result<Expr, QueryError> {
+        let wrap: fn(Box<Expr>) -> Expr = match function {
+            "reach" => Expr::Reach,
+            "callers" => Expr::Callers,
+            "calls" => Expr::Calls,
+            "callees" => Expr::Callees,
+            "id" | "crate" | "file" => {
+                self.expect('(')?;
+                let pattern = match self.peek().clone() {
+                    Token::Word(p) | Token::Quoted(p) => {
+                        self.next();
+                        p
+                    }
+                    _ => return self.error(format!("{}() expects a pattern", function)),
+                };
+                self.expect(')')?;
+                return Ok(match function {
+                    "id" => Expr::Id(pattern),
+                    "crate" => Expr::Crate(pattern),
+                    _ => Expr::File(pattern),
+                });
+            }
+            _ => {
+                self.pos -= 1;
+                return self.error(format!(
+                    "unknown function {}() (expected reach, callers, calls, callees, id, crate or file)",
+                    function
+                ));
+            }
+        };
+        self.expect('(')?;
+        let inner = self.expr()?;
+        self.expect(')')?;
+        Ok(wrap(Box::new(inner)))
+    }
+}
+
+fn name_or_glob(text: String) -> Expr {
+    if text.contains(['*', '?']) { Expr::Id(text) } else { Expr::Name(text) }
+}
+
+/// Parse a query.
+pub fn parse(src: &str) -> Result<Expr, QueryError> {
+    let mut parser = Parser { tokens: tokenize(src)?, pos: 0 };
+    let expr = parser.expr()?;
+    match parser.peek() {
+        Token::End => Ok(expr),
+        Token::Op(')') => parser.error("unbalanced ')'"),
+        _ => parser.error("expected '&', '|' or '-'"),
+    }
+}
+
+/// Evaluates queries against one graph.
+struct Evaluator<'g> {
+    cg: &'g CallGraph,
+    compact: CompactGraph,
+    /// Node of each symbol; `None` for call targets without one.
+    nodes: Vec<Option<&'g CallGraphNode>>,
+    callers: Vec<Vec<SymbolId>>,
+}
+
+impl<'g> Evaluator<'g> {
+    fn new(cg: &'g CallGraph) -> Self {
+        let compact = CompactGraph::new(cg);
+        let mut nodes = vec![None; compact.len()];
+        let mut callers = vec![Vec::new(); compact.len()];
+        for node in &cg.nodes {
+            let id = compact.id(&node.id).expect("every node is interned");
+            nodes[id.index()].get_or_insert(node);
+        }
+        for i in 0..compact.len() {
+            let caller = SymbolId::from_index(i);
+            for &callee in compact.callees(caller) {
+                callers[callee.index()].push(caller);
+            }
+        }
+        Self { cg, compact, nodes, callers }
+    }
+
+    fn select(&self, keep: impl Fn(&CallGraphNode) -> bool) -> Vec<bool> {
+        self.nodes.iter().map(|n| n.is_some_and(&keep)).collect()
+    }
+
+    /// Symbols at least one call away from `set` along `next`.
+    fn beyond<'a>(&self, set: &[bool], next: impl Fn(SymbolId) -> &'a [SymbolId]) -> Vec<bool> {
+        let mut found = vec![false; set.len()];
+        let mut stack: Vec<SymbolId> = (0..set.len()).filter(|&i| set[i]).map(SymbolId::from_index).collect();
+        while let Some(id) = stack.pop() {
+            for &other in next(id) {
+                if !found[other.index()] {
+                    found[other.index()] = true;
+                    stack.push(other);
+                }
+            }
+        }
+        found
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<Vec<bool>, QueryError> {
+        let n = self.compact.len();
+        Ok(match expr {
+            Expr::Attribute(attribute) => self.select(|node| attribute.matches(node)),
+            Expr::Name(name) => {
+                let id = resolve_symbol(self.cg, name).map_err(QueryError::Lookup)?;
+                let mut set = vec![false; n];
+                set[self.compact.id(&id).expect("resolved ids are nodes").index()] = true;
+                set
+            }
+            Expr::Id(pattern) => {
+                let filter = GraphFilter { include: vec![pattern.clone()], ..GraphFilter::default() };
+                self.select(|node| filter.keeps(&node.id))
+            }
+            Expr::Crate(pattern) => self.select(|node| {
+                let krate = node.external.as_deref().unwrap_or_else(|| node_module(&node.id).0);
+                glob_match(pattern, krate)
+            }),
+            Expr::File(pattern) => self.select(|node| {
+                node.location.as_deref()
+                    .and_then(|location| location.rsplit_once(':'))
+                    .is_some_and(|(file, _)| glob_match(pattern, file))
+            }),
+            Expr::Reach(inner) => {
+                let set = self.eval(inner)?;
+                let beyond = self.beyond(&set, |id| self.compact.callees(id));
+                set.iter().zip(beyond).map(|(a, b)| *a || b).collect()
+            }
+            Expr::Callers(inner) => self.beyond(&self.eval(inner)?, |id| &self.callers[id.index()]),
+            Expr::Calls(inner) => {
+                let set = self.eval(inner)?;
+                (0..n).map(|i| self.compact.callees(SymbolId::from_index(i)).iter().any(|c| set[c.index()])).collect()
+            }
+            Expr::Callees(inner) => {
+                let set = self.eval(inner)?;
+                (0..n).map(|i| self.callers[i].iter().any(|c| set[c.index()])).collect()
+            }
+            Expr::Not(inner) => self.eval(inner)?.iter().map(|b| !b).collect(),
+            Expr::And(a, b) => combine(self.eval(a)?, self.eval(b)?, |a, b| a && b),
+            Expr::Or(a, b) => combine(self.eval(a)?, self.eval(b)?, |a, b| a || b),
+            Expr::Minus(a, b) => combine(self.eval(a)?, self.eval(b)?, |a, b| a && !b),
+        })
+    }
+}
+
+fn combine(a: Vec<bool>, b: Vec<bool>, op: impl Fn(bool, bool) -> bool) -> Vec<bool> {
+    a.into_iter().zip(b).map(|(a, b)| op(a, b)).collect()
+}
+
+/// Ids of the nodes `expr` selects, sorted. Call targets without a node
+/// are never part of the result.
+pub fn evaluate(cg: &CallGraph, expr: &Expr) -> Result<Vec<String>, QueryError> {
+    let evaluator = Evaluator::new(cg);
+    let set = evaluator.eval(expr)?;
+    let mut ids: Vec<String> = set.iter().enumerate()
+        .filter(|&(i, &selected)| selected && evaluator.nodes[i].is_some())
+        .map(|(i, _)| evaluator.compact.name(SymbolId::from_index(i)).to_string())
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+/// Parse and evaluate `src`.
+pub fn query(cg: &CallGraph, src: &str) -> Result<Vec<String>, QueryError> {
+    evaluate(cg, &parse(src)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    fn graph() -> CallGraph {
+        CallGraph::new(vec![
+            node("app::main", &["app::net::send", "app::util::log"]),
+            CallGraphNode { unsafe_blocks: 1, ..node("app::net::send", &["app::net::raw_write", "libc::write"]) },
+            CallGraphNode { is_unsafe: true, location: Some("src/net.rs:20".into()), ..node("app::net::raw_write", &["app::util::log"]) },
+            node("app::util::log", &[]),
+            node("test_utils::fake_send", &["app::net::raw_write"]),
+            CallGraphNode { is_test: true, ..node("app::tests::it_sends", &["app::net::send"]) },
+            node("app::unused", &["app::util::log"]),
+        ])
+    }
+
+    fn run(src: &str) -> Vec<String> {
+        query(&graph(), src).unwrap_or_else(|e| panic!("{}: {}", src, e))
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        assert_eq!(run("reach(main) & calls(unsafe) - crate(test_utils)"), vec!["app::net::send"]);
+        assert_eq!(run("calls(unsafe)"), vec!["app::net::send", "test_utils::fake_send"]);
+        assert_eq!(run("reach(main)"), vec!["app::main", "app::net::raw_write", "app::net::send", "app::util::log"]);
+        assert_eq!(run("callers(log) - test"), vec!["app::main", "app::net::raw_write", "app::net::send", "app::unused", "test_utils::fake_send"]);
+        assert_eq!(run("callees(send)"), vec!["app::net::raw_write"]);
+        assert_eq!(run("all - reach(main) - test"), vec!["app::unused", "test_utils::fake_send"]);
+        assert_eq!(run("!crate(app)"), vec!["test_utils::fake_send"]);
+        assert_eq!(run("calls(unsafe_blocks)"), vec!["app::main", "app::tests::it_sends"]);
+        assert_eq!(run("app::net::* | file(src/*.rs)"), vec!["app::net::raw_write", "app::net::send"]);
+        // `&` binds tighter than `|`
+        assert_eq!(run("test | unsafe & crate(test_utils)"), vec!["app::tests::it_sends"]);
+        assert_eq!(run("(test | unused) & unused"), vec!["app::unused"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let cg = graph();
+        let syntax = |src: &str| match query(&cg, src) {
+            Err(QueryError::Syntax { column, message }) => (column, message),
+            other => panic!("{}: {:?}", src, other),
+        };
+        assert_eq!(syntax("reach(main"), (11, "expected ')'".to_string()));
+        assert_eq!(syntax("main main"), (6, "expected '&', '|' or '-'".to_string()));
+        assert_eq!(syntax("reaches(main)"), (1, "unknown function reaches() (expected reach, callers, calls, callees, id, crate or file)".to_string()));
+        assert_eq!(syntax("main & $"), (8, "unexpected '$'".to_string()));
+        assert_eq!(syntax("\"é\" & $"), (7, "unexpected '$'".to_string()));
+        assert!(matches!(query(&cg, "reach(nope)"), Err(QueryError::Lookup(LookupError::NotFound { .. }))));
+    }
+}
@@ -0,0 +1,271 @@
+//! Reachability Analysis
+//!
+//! Finds functions that no entry point can reach (dead code candidates),
+//! and which tests reach each production function (test gaps).
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::compact::CompactGraph;
+use crate::domain::interner::SymbolId;
+use crate::domain::source::SourceOrigin;
+
+/// A function no root reaches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnreachableFn {
+    pub id: String,
+    pub label: String,
+}
+
+/// Result of an unreachable-code pass.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreachableReport {
+    /// Roots the search started from.
+    pub roots: Vec<String>,
+    /// Number of functions considered (synthetic closure nodes excluded).
+    pub total_functions: usize,
+    pub unreachable: Vec<UnreachableFn>,
+}
+
+/// Whether a node is a default root: `main` or a public item.
+pub fn is_default_root(node: &CallGraphNode) -> bool {
+    node.is_public || node.id.starts_with("main@") || node.id.ends_with("::main")
+}
+
+/// Closures are reported through their owning function.
+fn is_synthetic(id: &str) -> bool {
+    id.contains("{closure#")
+}
+
+/// Set of node ids reachable from `roots` (roots included).
+pub fn reachable_from(cg: &CallGraph, roots: &[String]) -> HashSet<String> {
+    let compact = CompactGraph::new(cg);
+    let mut seen: HashSet<String> = compact.reachable(&compact.ids(roots.iter().map(String::as_str)))
+        .into_iter()
+        .enumerate()
+        .filter(|(_, reached)| *reached)
+        .map(|(i, _)| compact.name(SymbolId::from_index(i)).to_string())
+        .collect();
+    // Roots without a node are still in the set
+    seen.extend(roots.iter().cloned());
+    seen
+}
+
+/// Report every function not reachable from the default roots plus `extra_roots`.
+pub fn unreachable_functions(cg: &CallGraph, extra_roots: &[String]) -> UnreachableReport {
+    let mut roots: Vec<String> = cg.nodes.iter()
+        .filter(|n| is_default_root(n))
+        .map(|n| n.id.to_string())
+        .collect();
+    for r in extra_roots {
+        if !roots.contains(r) {
+            roots.push(r.clone());
+        }
+    }
+
+    let reachable = reachable_from(cg, &roots);
+    // External stubs are not code of the analyzed workspace, type nodes not functions
+    let functions: Vec<&CallGraphNode> = cg.nodes.iter()
+        .filter(|n| !is_synthetic(&n.id) && n.external.is_none() && n.type_kind.is_none())
+        .collect();
+    let mut unreachable: Vec<UnreachableFn> = functions.iter()
+        .filter(|n| !reachable.contains(n.id.as_str()))
+        .map(|n| UnreachableFn {
+            id: n.id.to_string(),
+            label: n.label.clone().unwrap_or_else(|| n.id.to_string()),
+        })
+        .collect();
+    unreachable.sort_by(|a, b| a.id.cmp(&b.id));
+
+    UnreachableReport { roots, total_functions: functions.len(), unreachable }
+}
+
+impl fmt::Display for UnreachableReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Unreachable functions: {} of {} (from {} roots)",
+            self.unreachable.len(), self.total_functions, self.roots.len())?;
+        for u in &self.unreachable {
+            writeln!(f, "  {}", u.id)?;
+        }
+        Ok(())
+    }
+}
+
+/// A production function and the tests reaching it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestedFn {
+    pub id: String,
+    pub tests: Vec<String>,
+}
+
+/// Which tests reach which production functions.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestMap {
+    /// Test code nothing calls: the functions the test harness runs.
+    pub tests: Vec<String>,
+    /// Library and binary functions considered.
+    pub total_functions: usize,
+    /// Production functions at least one test reaches, by id.
+    pub functions: Vec<TestedFn>,
+    /// Production functions no test reaches, by id.
+    pub untested: Vec<String>,
+}
+
+fn is_test_code(node: &CallGraphNode) -> bool {
+    node.is_test || node.origin == Some(SourceOrigin::Test)
+}
+
+/// Map every library and binary function to the tests that can reach it.
+/// Tests are the functions marked `#[test]` (or `#[tokio::test]`, ...);
+/// helpers shared by tests are followed through rather than counted.
+pub fn test_map(cg: &CallGraph) -> TestMap {
+    let compact = CompactGraph::new(cg);
+    let mut tests: Vec<String> = cg.nodes.iter()
+        .filter(|n| n.is_test_fn && !is_synthetic(&n.id))
+        .map(|n| n.id.to_string())
+        .collect();
+    tests.sort();
+    tests.dedup();
+
+    // One pass over the call cycles, callers first: every component hands
+    // the tests reaching it on to the components it calls
+    let components = compact.components();
+    let mut component_of = vec![0; compact.len()];
+    for (c, members) in components.iter().enumerate() {
+        for id in members {
+            component_of[id.index()] = c;
+        }
+    }
+    let mut reached_by: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); components.len()];
+    for (t, test) in tests.iter().enumerate() {
+        if let Some(id) = compact.id(test) {
+            reached_by[component_of[id.index()]].insert(t);
+        }
+    }
+    for c in (0..components.len()).rev() {
+        let by = std::mem::take(&mut reached_by[c]);
+        for id in &components[c] {
+            for callee in compact.callees(*id) {
+                let target = component_of[callee.index()];
+                if target != c {
+                    reached_by[target].extend(&by);
+                }
+            }
+        }
+        reached_by[c] = by;
+    }
+
+    let mut production: Vec<&CallGraphNode> = cg.nodes.iter()
+        .filter(|n| !is_test_code(n) && n.origin.is_none())
+        .filter(|n| !is_synthetic(&n.id) && n.external.is_none() && n.type_kind.is_none())
+        .collect();
+    production.sort_by(|a, b| a.id.cmp(&b.id));
+    production.dedup_by(|a, b| a.id == b.id);
+    let (mut functions, mut untested) = (Vec::new(), Vec::new());
+    for node in &production {
+        let by = compact.id(&node.id).map(|id| &reached_by[component_of[id.index()]]).filter(|by| !by.is_empty());
+        match by {
+            Some(by) => functions.push(TestedFn { id: node.id.to_string(), tests: by.iter().map(|&t| tests[t].clone()).collect() }),
+            None => untested.push(node.id.to_string()),
+        }
+    }
+    TestMap { tests, total_functions: production.len(), functions, untested }
+}
+
+impl fmt::Display for TestMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Test reachability: {} tests reach {} of {} functions",
+            self.tests.len(), self.functions.len(), self.total_functions)?;
+        if !self.untested.is_empty() {
+            writeln!(f, "Reached by no test ({}):", self.untested.len())?;
+        }
+        for id in &self.untested {
+            writeln!(f, "  {}", id)?;
+        }
+        if !self.functions.is_empty() {
+            writeln!(f, "Tests reaching each function:")?;
+        }
+        for tested in &self.functions {
+            writeln!(f, "  {:>5}  {}", tested.tests.len(), tested.id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    
+    use crate::domain::filter::GraphFilter;
+
+    fn node(id: &str, callees: &[&str], is_public: bool) -> CallGraphNode {
+        CallGraphNode { is_public, ..fixtures::node(id, callees) }
+    }
+
+    #[test]
+    fn test_unreachable_from_main_and_public() {
+        let cg = CallGraph::new(vec![
+            node("app::main", &["app::used"], false),
+            node("app::used", &["app::main::{closure#0}"], false),
+            node("app::main::{closure#0}", &["app::via_closure"], false),
+            node("app::via_closure", &[], false),
+            node("app::api", &["app::helper"], true),
+            node("app::helper", &[], false),
+            node("app::dead", &["app::dead_callee"], false),
+            node("app::dead_callee", &[], false),
+            node("app::dead::{closure#0}", &[], false),
+        ]);
+
+        let report = unreachable_functions(&cg, &[]);
+        let ids: Vec<&str> = report.unreachable.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::dead", "app::dead_callee"]);
+        assert_eq!(report.total_functions, 7);
+
+        // A user-specified root keeps its callees alive
+        let report = unreachable_functions(&cg, &["app::dead".to_string()]);
+        assert!(report.unreachable.is_empty());
+    }
+
+    #[test]
+    fn test_map_of_tests_to_functions() {
+        let test = |id: &str, callees: &[&str]| CallGraphNode { is_test: true, is_test_fn: true, ..node(id, callees, false) };
+        let cg = CallGraph::new(vec![
+            node("app::main", &["app::parse", "app::run"], false),
+            node("app::parse", &["app::lex"], false),
+            node("app::lex", &["app::lex_more"], false),
+            node("app::lex_more", &["app::lex"], false),
+            node("app::run", &[], false),
+            test("app::tests::parses", &["app::tests::setup", "app::parse"]),
+            test("app::tests::lexes", &["app::tests::setup", "app::lex"]),
+            // Test code without `#[test]` is a helper, not a test
+            CallGraphNode { is_test: true, ..node("app::tests::setup", &[], false) },
+            CallGraphNode { origin: Some(SourceOrigin::Test), is_test_fn: true, ..node("it::runs_main", &["app::main"], false) },
+            CallGraphNode { origin: Some(SourceOrigin::Example), ..node("demo::main", &["app::run"], false) },
+        ]);
+
+        let map = test_map(&cg);
+        assert_eq!(map.tests, vec!["app::tests::lexes", "app::tests::parses", "it::runs_main"]);
+        assert_eq!(map.total_functions, 5);
+        let reached: Vec<(&str, Vec<&str>)> = map.functions.iter()
+            .map(|t| (t.id.as_str(), t.tests.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(reached, vec![
+            ("app::lex", vec!["app::tests::lexes", "app::tests::parses", "it::runs_main"]),
+            ("app::lex_more", vec!["app::tests::lexes", "app::tests::parses", "it::runs_main"]),
+            ("app::main", vec!["it::runs_main"]),
+            ("app::parse", vec!["app::tests::parses", "it::runs_main"]),
+            ("app::run", vec!["it::runs_main"]),
+        ]);
+        assert!(map.untested.is_empty());
+
+        let map = test_map(&GraphFilter { exclude: vec!["it::*".to_string()], ..GraphFilter::default() }.apply(&cg));
+        assert_eq!(map.untested, vec!["app::main", "app::run"]);
+        let text = map.to_string();
+        assert!(text.starts_with("Test reachability: 2 tests reach 3 of 5 functions\nReached by no test (2):\n  app::main\n"), "{}", text);
+        assert!(text.contains("Tests reaching each function:\n      2  app::lex\n"), "{}", text);
+    }
+}
@@ -1,27 +1,65 @@
-/// SCIP Index Ingestor.
-/// Parses SCIP indices and builds a precise CallGraph using semantic information.
-/// 
-/// Phase 3.1: Parallel processing with rayon and DashMap for high performance.
-
-use std::path::Path;
+//! SCIP Index Ingestor.
+//! Parses SCIP indices and builds a precise CallGraph using semantic information.
+//! 
+//! Phase 3.1: Parallel processing with rayon and DashMap for high performance.
+//!
+//! Only functions and methods become nodes (and edge targets): locals,
+//! fields, modules and types are left out, or types kept as type nodes on
+//! request. What a symbol is comes from its `SymbolInformation.kind`, or
+//! the suffix of its last descriptor when the indexer does not say.
+//!
+//! A reference is attributed to the innermost definition whose extent holds
+//! it. Extents come from the occurrence's `enclosing_range` (the whole item,
+//! body included). Older indexers only report the identifier's range; there
+//! a function is taken to run up to the next definition in the file.
+//!
+//! A reference's roles decide its edge's kind: an `Import` is an `import`
+//! edge, a read or write access a `reference` (the function is used as a
+//! value), anything else a call. [`ScipOptions::calls_only`] keeps just the
+//! calls.
+//!
+//! Functions called but defined in no index ingested (std, dependencies) are
+//! left as dangling edge targets, or given stub nodes tagged with the
+//! package the symbol names under [`ScipOptions::include_external`].
+//!
+//! Each node's location is `path:line` of its definition, in the document
+//! listed first when several define it. Several indices (one per crate of a
+//! monorepo, say) ingest into one graph, their symbols shared. Document paths are relative to the
+//! index's own project root; given a [`ProjectRoot`], locations are
+//! rewritten relative to that instead, so they match the syn builder's.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use anyhow::{Context, Result};
 use dashmap::DashMap;
 use rayon::prelude::*;
 
-use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use scip::types::descriptor::Suffix;
+use scip::types::symbol_information::Kind;
+use scip::types::SymbolRole;
+
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode, EdgeKind, TypeKind};
+use crate::domain::external::UNKNOWN_CRATE;
+use crate::domain::paths::ProjectRoot;
+use crate::domain::progress::{NoProgress, Progress};
 
 /// Represents a range in source code.
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct SourceRange {
-    start_line: i32,
-    start_col: i32,
-    end_line: i32,
-    end_col: i32,
+pub(crate) struct SourceRange {
+    pub(crate) start_line: i32,
+    pub(crate) start_col: i32,
+    pub(crate) end_line: i32,
+    pub(crate) end_col: i32,
 }
 
 impl SourceRange {
-    fn contains(&self, other: &SourceRange) -> bool {
+    /// Lines, then columns, covered; for ordering extents innermost first.
+    pub(crate) fn size(&self) -> (i32, i64) {
+        (self.end_line - self.start_line, i64::from(self.end_col) - i64::from(self.start_col))
+    }
+
+    pub(crate) fn contains(&self, other: &SourceRange) -> bool {
         // Check if `other` is fully contained within `self`
         if self.start_line > other.start_line || self.end_line < other.end_line {
             return false;
@@ -36,11 +74,58 @@ impl SourceRange {
     }
 }
 
-/// A definition occurrence extracted from SCIP.
+/// A definition occurrence extracted from SCIP (or LSIF).
 #[derive(Debug, Clone)]
-struct DefinitionInfo {
-    symbol: String,
-    range: SourceRange,
+pub(crate) struct DefinitionInfo {
+    pub(crate) symbol: String,
+    /// The item's extent, or just its identifier without `enclosing`.
+    pub(crate) range: SourceRange,
+    /// Whether `range` is the indexer's `enclosing_range`.
+    pub(crate) enclosing: bool,
+}
+
+/// Stretch functions known only by their identifier up to the next of
+/// `starts`, the start positions of the definitions of the file other than
+/// locals, so their bodies hold their references.
+pub(crate) fn extend_bare_functions(defs: &mut [DefinitionInfo], starts: &mut [(i32, i32)]) {
+    starts.sort();
+    for def in defs.iter_mut().filter(|def| !def.enclosing) {
+        let start = (def.range.start_line, def.range.start_col);
+        let (end_line, end_col) = match starts.iter().find(|&&next| next > start) {
+            Some(&(line, _)) if line > def.range.start_line => (line - 1, i32::MAX),
+            Some(&(line, col)) => (line, col),
+            None => (i32::MAX, i32::MAX),
+        };
+        if (end_line, end_col) > (def.range.end_line, def.range.end_col) {
+            def.range.end_line = end_line;
+            def.range.end_col = end_col;
+        }
+    }
+}
+
+/// What to keep from a SCIP index beyond its functions and calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScipOptions {
+    /// Also keep structs, enums, unions and type aliases as type nodes with
+    /// `defines-method` edges to their methods.
+    pub types: bool,
+    /// Only references that call the function: no `import` or `reference`
+    /// edges.
+    pub calls_only: bool,
+    /// A stub node, tagged with its package, for every function called but
+    /// defined in no index (std, third-party crates).
+    pub include_external: bool,
+}
+
+/// Kind of the edge an occurrence with `roles` adds.
+fn reference_kind(roles: i32) -> EdgeKind {
+    if roles & SymbolRole::Import as i32 != 0 {
+        EdgeKind::Import
+    } else if roles & (SymbolRole::ReadAccess as i32 | SymbolRole::WriteAccess as i32) != 0 {
+        EdgeKind::Reference
+    } else {
+        EdgeKind::Call
+    }
 }
 
 /// SCIP Ingestor for building CallGraphs from SCIP indices.
@@ -54,39 +139,115 @@ impl ScipIngestor {
     /// 
     /// Phase 3.3: Uses memory-mapped file I/O to avoid large allocations.
     pub fn ingest_and_build_graph(scip_path: &Path) -> Result<CallGraph> {
+        Self::ingest(&[scip_path], &NoProgress, None, ScipOptions::default())
+    }
+
+    /// Ingest with what to keep given by `options`, locations relative to
+    /// `root` if given.
+    pub fn ingest_with_options(scip_path: &Path, progress: &dyn Progress, root: Option<&ProjectRoot>, options: ScipOptions) -> Result<CallGraph> {
+        Self::ingest(&[scip_path], progress, root, options)
+    }
+
+    /// One graph from several indices, e.g. one per crate of a monorepo.
+    ///
+    /// A symbol defined in several indices is one node, placed at its
+    /// definition in the index listed first; references resolve across
+    /// indices. A document indexed twice only counts once.
+    pub fn ingest_all(scip_paths: &[PathBuf], progress: &dyn Progress, root: Option<&ProjectRoot>, options: ScipOptions) -> Result<CallGraph> {
+        let scip_paths: Vec<&Path> = scip_paths.iter().map(PathBuf::as_path).collect();
+        Self::ingest(&scip_paths, progress, root, options)
+    }
+
+    fn load(scip_path: &Path) -> Result<scip::types::Index> {
         use std::fs::File;
         use memmap2::Mmap;
         use protobuf::Message;
 
-        println!("[SCIP Ingest] Loading index from: {}", scip_path.display());
+        tracing::debug!("[SCIP Ingest] Loading index from: {}", scip_path.display());
         
         // Memory-map the SCIP index file for efficient access
         let file = File::open(scip_path)
-            .context("Failed to open SCIP index file")?;
+            .with_context(|| format!("Failed to open SCIP index file {}", scip_path.display()))?;
         
         // SAFETY: We assume the file won't be modified while we're reading it.
         // The mmap provides a zero-copy view into the file.
         let mmap = unsafe { Mmap::map(&file) }
-            .context("Failed to memory-map SCIP index file")?;
+            .with_context(|| format!("Failed to memory-map SCIP index file {}", scip_path.display()))?;
         
-        let index = scip::types::Index::parse_from_bytes(&mmap)
-            .context("Failed to parse SCIP index protobuf")?;
+        scip::types::Index::parse_from_bytes(&mmap)
+            .with_context(|| format!("Failed to parse SCIP index protobuf {}", scip_path.display()))
+    }
+
+    fn ingest(scip_paths: &[&Path], progress: &dyn Progress, root: Option<&ProjectRoot>, options: ScipOptions) -> Result<CallGraph> {
+        let types = options.types;
+        let indices = scip_paths.iter().map(|path| Self::load(path)).collect::<Result<Vec<_>>>()?;
+
+        // Every index's documents, each with its path as reported in locations;
+        // a file several indices cover is read from the first. Only absolute
+        // paths tell that: without a project root, `src/lib.rs` of two
+        // indices may be two different files
+        let mut documents: Vec<&scip::types::Document> = Vec::new();
+        let mut paths: Vec<String> = Vec::new();
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        for index in &indices {
+            let index_root = ProjectRoot::from_uri(&index.metadata.project_root);
+            for document in &index.documents {
+                let path = match (root, &index_root) {
+                    (Some(root), Some(index_root)) => root.relativize_from(index_root, &document.relative_path),
+                    (Some(root), None) => root.relativize(&document.relative_path),
+                    (None, _) => document.relative_path.clone(),
+                };
+                if let (true, Some(index_root)) = (indices.len() > 1, &index_root) {
+                    if !seen.insert(index_root.resolve(&document.relative_path)) {
+                        continue;
+                    }
+                }
+                documents.push(document);
+                paths.push(path);
+            }
+        }
 
         // ═══════════════════════════════════════════════════════════════════
         // Pass 1: Parallel Definition Collection
         // ═══════════════════════════════════════════════════════════════════
         
         // Thread-safe maps for parallel access
-        let definitions_by_file: DashMap<String, Vec<DefinitionInfo>> = DashMap::new();
+        let definitions_by_file: DashMap<usize, Vec<DefinitionInfo>> = DashMap::new();
         let symbol_to_node: DashMap<String, usize> = DashMap::new();
         let node_counter = AtomicUsize::new(0);
         
         // Collect nodes in parallel (we'll sort them later)
         let node_data: DashMap<usize, CallGraphNode> = DashMap::new();
+        // Document and line of each node's first definition, in index order
+        let definition_sites: DashMap<usize, (usize, i32)> = DashMap::new();
+
+        // Kinds the indexer reports, for symbols of any document
+        let kinds: HashMap<&str, Kind> = documents.iter()
+            .flat_map(|document| &document.symbols)
+            .chain(indices.iter().flat_map(|index| &index.external_symbols))
+            .map(|info| (info.symbol.as_str(), info.kind.enum_value_or_default()))
+            .filter(|(_, kind)| *kind != Kind::UnspecifiedKind)
+            .collect();
+        let classes: DashMap<String, SymbolClass> = DashMap::new();
+        let class_of = |symbol: &str| -> SymbolClass {
+            if let Some(class) = classes.get(symbol) {
+                return *class;
+            }
+            let class = classify(symbol, kinds.get(symbol).copied().unwrap_or_default());
+            classes.insert(symbol.to_string(), class);
+            class
+        };
 
-        index.documents.par_iter().for_each(|document| {
-            let file_path = document.relative_path.clone();
+        progress.start("Reading SCIP definitions", documents.len(), "definitions");
+        documents.par_iter().enumerate().for_each(|(doc_idx, document)| {
             let mut file_defs: Vec<DefinitionInfo> = Vec::new();
+            let mut starts: Vec<(i32, i32)> = Vec::new();
+            let mut created = 0;
+            // rust-analyzer documents each symbol's signature (`fn run(x: i32) -> i32`)
+            let signatures: HashMap<&str, &str> = document.symbols.iter()
+                .filter_map(|info| Some((info.symbol.as_str(), info.signature_documentation.as_ref()?.text.as_str())))
+                .filter(|(_, text)| !text.is_empty())
+                .collect();
 
             for occurrence in &document.occurrences {
                 // Check if this is a Definition (bit 0 of symbol_roles)
@@ -94,45 +255,67 @@ impl ScipIngestor {
                 
                 if is_definition && !occurrence.symbol.is_empty() {
                     let range = parse_scip_range(&occurrence.range);
+                    let extent = (!occurrence.enclosing_range.is_empty()).then(|| parse_scip_range(&occurrence.enclosing_range));
+                    let class = class_of(&occurrence.symbol);
+                    if class != SymbolClass::Local {
+                        starts.push((range.start_line, range.start_col));
+                    }
+                    let type_kind = match class {
+                        SymbolClass::Function => None,
+                        SymbolClass::Type(kind) if types => Some(kind),
+                        _ => continue,
+                    };
                     
                     // Atomically get or create node ID for this symbol
                     let node_id = *symbol_to_node
                         .entry(occurrence.symbol.clone())
                         .or_insert_with(|| {
                             let id = node_counter.fetch_add(1, Ordering::SeqCst);
+                            created += 1;
                             let label = extract_label_from_symbol(&occurrence.symbol);
                             node_data.insert(id, CallGraphNode {
-                                id: occurrence.symbol.clone(),
-                                callees: Vec::new(),
+                                id: occurrence.symbol.as_str().into(),
                                 label: Some(label),
+                                signature: signatures.get(occurrence.symbol.as_str()).map(|s| s.to_string()),
+                                type_kind,
+                                ..Default::default()
                             });
                             id
                         });
 
-                    // We don't use node_id here directly, just ensure it's registered
-                    let _ = node_id;
+                    // A symbol defined in several documents is placed at the first
+                    let site = (doc_idx, range.start_line);
+                    definition_sites.entry(node_id).and_modify(|s| *s = (*s).min(site)).or_insert(site);
 
+                    // Types are not callers: references in a struct body are not calls
+                    if type_kind.is_some() {
+                        continue;
+                    }
                     file_defs.push(DefinitionInfo {
                         symbol: occurrence.symbol.clone(),
-                        range,
+                        enclosing: extent.is_some(),
+                        range: extent.unwrap_or(range),
                     });
                 }
             }
 
-            // Sort definitions by range size (largest first) for containment lookup
-            file_defs.sort_by(|a, b| {
-                let a_size = (a.range.end_line - a.range.start_line) * 1000 
-                           + (a.range.end_col - a.range.start_col);
-                let b_size = (b.range.end_line - b.range.start_line) * 1000 
-                           + (b.range.end_col - b.range.start_col);
-                b_size.cmp(&a_size) // Largest first
-            });
+            extend_bare_functions(&mut file_defs, &mut starts);
+            // Smallest extent first: the first definition holding a reference is its caller
+            file_defs.sort_by_key(|def| def.range.size());
 
-            definitions_by_file.insert(file_path, file_defs);
+            definitions_by_file.insert(doc_idx, file_defs);
+            progress.advance(1, created);
         });
+        progress.finish();
+        for entry in definition_sites.iter() {
+            let (doc_idx, line) = *entry.value();
+            if let Some(mut node) = node_data.get_mut(entry.key()) {
+                node.location = Some(format!("{}:{}", paths[doc_idx], line + 1));
+            }
+        }
 
         let def_count = node_counter.load(Ordering::SeqCst);
-        println!("[SCIP Ingest] Found {} definitions (parallel)", def_count);
+        tracing::debug!("[SCIP Ingest] Found {} definitions (parallel)", def_count);
 
         // ═══════════════════════════════════════════════════════════════════
         // Pass 2: Parallel Reference Resolution
@@ -140,12 +323,14 @@ impl ScipIngestor {
         
         let edge_counter = AtomicUsize::new(0);
 
-        index.documents.par_iter().for_each(|document| {
-            let file_path = &document.relative_path;
+        progress.start("Resolving SCIP references", documents.len(), "edges");
+        documents.par_iter().enumerate().for_each(|(doc_idx, document)| {
+            let file_path = &paths[doc_idx];
+            let mut edges = 0;
             
             // Get definitions for this file (if any)
             let file_defs = definitions_by_file
-                .get(file_path)
+                .get(&doc_idx)
                 .map(|r| r.clone())
                 .unwrap_or_default();
 
@@ -153,7 +338,11 @@ impl ScipIngestor {
                 // Check if this is a Reference (not a definition)
                 let is_definition = occurrence.symbol_roles & 1 != 0;
                 
-                if !is_definition && !occurrence.symbol.is_empty() {
+                if !is_definition && !occurrence.symbol.is_empty() && class_of(&occurrence.symbol) == SymbolClass::Function {
+                    let kind = reference_kind(occurrence.symbol_roles);
+                    if options.calls_only && kind != EdgeKind::Call {
+                        continue;
+                    }
                     let ref_range = parse_scip_range(&occurrence.range);
                     let callee_symbol = &occurrence.symbol;
 
@@ -168,9 +357,15 @@ impl ScipIngestor {
                                 if caller_symbol != callee_symbol {
                                     // Thread-safe edge insertion
                                     if let Some(mut node) = node_data.get_mut(&*caller_idx) {
-                                        if !node.callees.contains(callee_symbol) {
-                                            node.callees.push(callee_symbol.clone());
+                                        // One edge per callee and kind at its first site (SCIP
+                                        // lines are 0-based), weighted by the number of references
+                                        if let Some(edge) = node.callees.iter_mut().find(|e| e.target == *callee_symbol && e.kind == kind) {
+                                            edge.weight += 1;
+                                        } else {
+                                            let line = (ref_range.start_line + 1) as usize;
+                                            node.callees.push(CallEdge::at(callee_symbol.clone(), file_path.clone(), line).with_kind(kind));
                                             edge_counter.fetch_add(1, Ordering::Relaxed);
+                                            edges += 1;
                                         }
                                     }
                                 }
@@ -180,10 +375,12 @@ impl ScipIngestor {
                     }
                 }
             }
+            progress.advance(1, edges);
         });
+        progress.finish();
 
         let edge_count = edge_counter.load(Ordering::Relaxed);
-        println!("[SCIP Ingest] Created {} edges (parallel)", edge_count);
+        tracing::debug!("[SCIP Ingest] Created {} edges (parallel)", edge_count);
 
         // ═══════════════════════════════════════════════════════════════════
         // Finalize: Convert DashMap to sorted Vec
@@ -196,11 +393,118 @@ impl ScipIngestor {
             .map(|(_, node)| node)
             .collect();
         
+        if options.include_external {
+            let signatures: HashMap<&str, &str> = indices.iter()
+                .flat_map(|index| &index.external_symbols)
+                .filter_map(|info| Some((info.symbol.as_str(), info.signature_documentation.as_ref()?.text.as_str())))
+                .filter(|(_, text)| !text.is_empty())
+                .collect();
+            let stubs = external_stubs(&nodes, &signatures);
+            nodes.extend(stubs);
+        }
+
         // Sort by ID for deterministic output
         nodes.sort_by(|a, b| a.id.cmp(&b.id));
 
-        Ok(CallGraph { nodes })
+        if types {
+            add_defined_methods(&mut nodes);
+        }
+
+        Ok(CallGraph::new(nodes))
+    }
+}
+
+/// What a symbol names, as far as the call graph is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SymbolClass {
+    Function,
+    Type(TypeKind),
+    Local,
+    /// Modules, fields, traits, parameters, macros, ...
+    Other,
+}
+
+/// Class of `symbol` by its `kind`, or by its last descriptor's suffix when
+/// the kind is unspecified (`run().` is a function, `Config#` a type).
+fn classify(symbol: &str, kind: Kind) -> SymbolClass {
+    match kind {
+        Kind::Function | Kind::Method | Kind::StaticMethod | Kind::AbstractMethod | Kind::TraitMethod
+        | Kind::ProtocolMethod | Kind::PureVirtualMethod | Kind::SingletonMethod | Kind::TypeClassMethod
+        | Kind::MethodSpecification | Kind::Constructor | Kind::Getter | Kind::Setter | Kind::Accessor => SymbolClass::Function,
+        Kind::Struct | Kind::Class => SymbolClass::Type(TypeKind::Struct),
+        Kind::Enum => SymbolClass::Type(TypeKind::Enum),
+        Kind::Union => SymbolClass::Type(TypeKind::Union),
+        Kind::TypeAlias => SymbolClass::Type(TypeKind::Alias),
+        Kind::UnspecifiedKind => {
+            let Ok(parsed) = scip::symbol::parse_symbol(symbol) else { return SymbolClass::Other };
+            match parsed.descriptors.last().map(|d| d.suffix.enum_value_or_default()) {
+                Some(Suffix::Method) => SymbolClass::Function,
+                Some(Suffix::Type) => SymbolClass::Type(TypeKind::Struct),
+                Some(Suffix::Local) => SymbolClass::Local,
+                _ => SymbolClass::Other,
+            }
+        }
+        _ => SymbolClass::Other,
+    }
+}
+
+/// Type symbol a method symbol is declared under: `Config#load().` and
+/// rust-analyzer's `impl#[Config]load().` (or `impl#[Config][Trait]load().`)
+/// both belong to `Config#`.
+fn method_owner(method: &str) -> Option<String> {
+    let name_start = method[..method.rfind('(')?].rfind(['#', '/', ' ', ']'])?;
+    let owner = &method[..=name_start];
+    if let Some(impl_start) = owner.find("impl#[") {
+        let ty = &owner[impl_start + "impl#[".len()..];
+        return Some(format!("{}{}#", &owner[..impl_start], &ty[..ty.find(']')?]));
     }
+    owner.ends_with('#').then(|| owner.to_string())
+}
+
+/// `defines-method` edges from each type node to the methods declared in
+/// its namespace.
+fn add_defined_methods(nodes: &mut [CallGraphNode]) {
+    let index: HashMap<String, usize> = nodes.iter().enumerate()
+        .filter(|(_, n)| n.type_kind.is_some())
+        .map(|(i, n)| (n.id.to_string(), i))
+        .collect();
+    let mut methods: Vec<(usize, CallEdge)> = Vec::new();
+    for node in nodes.iter().filter(|n| n.type_kind.is_none()) {
+        let Some(&owner) = method_owner(&node.id).and_then(|owner| index.get(&owner)) else { continue };
+        let edge = match node.location.as_deref().and_then(|l| l.rsplit_once(':')) {
+            Some((file, line)) => CallEdge::at(node.id.clone(), file, line.parse().unwrap_or(0)),
+            None => CallEdge::new(node.id.clone()),
+        };
+        methods.push((owner, edge.with_kind(EdgeKind::DefinesMethod)));
+    }
+    for (owner, edge) in methods {
+        nodes[owner].callees.push(edge);
+    }
+}
+
+/// A stub for each edge target no node defines, its `external` the package
+/// named in the symbol (`std` for `rust-analyzer cargo std 1.0 mem/swap().`).
+fn external_stubs(nodes: &[CallGraphNode], signatures: &HashMap<&str, &str>) -> Vec<CallGraphNode> {
+    let mut known: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    let mut stubs = Vec::new();
+    for target in nodes.iter().flat_map(|n| n.callee_ids()) {
+        if !known.insert(target) {
+            continue;
+        }
+        let package = scip::symbol::parse_symbol(target).ok()
+            .and_then(|parsed| parsed.package.into_option())
+            .map(|package| package.name)
+            .filter(|name| !name.is_empty() && name != ".")
+            .unwrap_or_else(|| UNKNOWN_CRATE.to_string());
+        stubs.push(CallGraphNode {
+            id: target.into(),
+            label: Some(extract_label_from_symbol(target)),
+            external: Some(package),
+            signature: signatures.get(target).map(|s| s.to_string()),
+            ..Default::default()
+        });
+    }
+    stubs
 }
 
 /// Parse SCIP range format: [start_line, start_col, end_line, end_col] or [start_line, start_col, end_col]
@@ -231,7 +535,7 @@ fn extract_label_from_symbol(symbol: &str) -> String {
     let parts: Vec<&str> = symbol.split(' ').collect();
     if let Some(last) = parts.last() {
         // Remove trailing punctuation like `().` or `#`
-        let cleaned = last.trim_end_matches(|c| c == '(' || c == ')' || c == '.' || c == '#');
+        let cleaned = last.trim_end_matches(['(', ')', '.', '#']);
         // Replace path separators
         cleaned.replace('/', "::").to_string()
     } else {
@@ -297,7 +601,7 @@ mod tests {
 
             for def_idx in 0..defs_per_doc {
                 let mut occ = scip::types::Occurrence::new();
-                occ.symbol = format!("pkg::file_{}::func_{}", doc_idx, def_idx);
+                occ.symbol = format!("pkg . . . file_{}/func_{}().", doc_idx, def_idx);
                 let start_line = (def_idx * 20) as i32;
                 occ.range = vec![start_line, 0, start_line + 15, 0];
                 occ.symbol_roles = 1; // Definition bit
@@ -393,16 +697,19 @@ mod tests {
         let mut doc1 = scip::types::Document::new();
         doc1.relative_path = "src/a.rs".to_string();
         let mut def_a = scip::types::Occurrence::new();
-        def_a.symbol = "pkg::func_a".to_string();
+        def_a.symbol = "pkg . . . func_a().".to_string();
         def_a.range = vec![0, 0, 20, 0];
         def_a.symbol_roles = 1; // Definition
         doc1.occurrences.push(def_a);
         
         // Reference to func_b inside func_a
         let mut ref_b = scip::types::Occurrence::new();
-        ref_b.symbol = "pkg::func_b".to_string();
+        ref_b.symbol = "pkg . . . func_b().".to_string();
         ref_b.range = vec![10, 5, 15]; // Inside func_a
         ref_b.symbol_roles = 0; // Reference
+        doc1.occurrences.push(ref_b.clone());
+        // A second call adds to the edge's weight
+        ref_b.range = vec![12, 5, 15];
         doc1.occurrences.push(ref_b);
         
         index.documents.push(doc1);
@@ -411,7 +718,7 @@ mod tests {
         let mut doc2 = scip::types::Document::new();
         doc2.relative_path = "src/b.rs".to_string();
         let mut def_b = scip::types::Occurrence::new();
-        def_b.symbol = "pkg::func_b".to_string();
+        def_b.symbol = "pkg . . . func_b().".to_string();
         def_b.range = vec![0, 0, 10, 0];
         def_b.symbol_roles = 1; // Definition
         doc2.occurrences.push(def_b);
@@ -433,9 +740,265 @@ mod tests {
         assert_eq!(graph.nodes.len(), 2);
         
         // func_a should call func_b
-        let func_a = graph.nodes.iter().find(|n| n.id == "pkg::func_a");
+        let func_a = graph.nodes.iter().find(|n| n.id == "pkg . . . func_a().");
         assert!(func_a.is_some());
-        assert!(func_a.unwrap().callees.contains(&"pkg::func_b".to_string()));
+        let edge = func_a.unwrap().callees.iter().find(|e| e.target == "pkg . . . func_b().");
+        assert!(edge.is_some());
+        // Call site of the reference, converted to a 1-based line
+        assert_eq!(edge.unwrap().location().as_deref(), Some("src/a.rs:11"));
+        assert_eq!(edge.unwrap().weight, 2);
+        assert_eq!(func_a.unwrap().callees.len(), 1);
     }
-}
 
+    fn occurrence(symbol: &str, line: i32, roles: i32) -> scip::types::Occurrence {
+        let mut occ = scip::types::Occurrence::new();
+        occ.symbol = symbol.to_string();
+        occ.range = vec![line, 4, 8];
+        occ.symbol_roles = roles;
+        occ
+    }
+
+    /// Write an index of `documents` (relative path, occurrences) to `path`;
+    /// `prepare` fills in the rest (project root, symbol information).
+    fn write_index(path: PathBuf, documents: Vec<(&str, Vec<scip::types::Occurrence>)>, prepare: impl FnOnce(&mut scip::types::Index)) -> PathBuf {
+        let mut index = scip::types::Index::new();
+        for (relative_path, occurrences) in documents {
+            let mut doc = scip::types::Document::new();
+            doc.relative_path = relative_path.to_string();
+            doc.occurrences = occurrences;
+            index.documents.push(doc);
+        }
+        prepare(&mut index);
+        std::fs::write(&path, index.write_to_bytes().unwrap()).unwrap();
+        path
+    }
+
+    fn project_root(index: &mut scip::types::Index, dir: &Path) {
+        index.metadata.mut_or_insert_default().project_root = format!("file://{}", dir.display());
+    }
+
+    #[test]
+    fn test_enclosing_ranges_give_function_extents() {
+        let dir = tempdir().unwrap();
+        let at = |symbol: &str, range: Vec<i32>, enclosing_range: Vec<i32>, roles: i32| {
+            scip::types::Occurrence { range, enclosing_range, ..occurrence(symbol, 0, roles) }
+        };
+        let write = |occurrences: Vec<scip::types::Occurrence>| {
+            let path = write_index(dir.path().join("index.scip"), vec![("src/a.rs", occurrences)], |_| {});
+            ScipIngestor::ingest_and_build_graph(&path).unwrap()
+        };
+        let callees = |graph: &CallGraph, id: &str| -> Vec<String> {
+            graph.nodes.iter().find(|n| n.id == id).unwrap().callees.iter().map(|e| e.target.to_string()).collect()
+        };
+
+        // mod net { fn send() { log(); fn inner() { log(); } } }, identifiers on one line each
+        let graph = write(vec![
+            at("pkg . . . net/", vec![0, 4, 7], vec![0, 0, 9, 1], 1),
+            at("pkg . . . net/send().", vec![1, 7, 11], vec![1, 4, 8, 5], 1),
+            at("pkg . . . log().", vec![2, 8, 11], vec![], 0),
+            at("pkg . . . net/send().inner().", vec![4, 11, 16], vec![4, 8, 6, 9], 1),
+            at("pkg . . . log().", vec![5, 12, 15], vec![], 0),
+            at("pkg . . . log().", vec![10, 0, 3], vec![], 0),
+        ]);
+        assert_eq!(callees(&graph, "pkg . . . net/send()."), vec!["pkg . . . log()."]);
+        assert_eq!(callees(&graph, "pkg . . . net/send().inner()."), vec!["pkg . . . log()."]);
+        assert!(!graph.nodes.iter().any(|n| n.id == "pkg . . . net/"));
+        assert_eq!(graph.nodes.iter().find(|n| n.id == "pkg . . . net/send().").unwrap().location.as_deref(), Some("src/a.rs:2"));
+
+        // Without enclosing ranges a function runs up to the next definition, locals aside
+        let graph = write(vec![
+            occurrence("pkg . . . first().", 0, 1),
+            occurrence("local 0", 1, 1),
+            occurrence("pkg . . . log().", 2, 0),
+            occurrence("pkg . . . second().", 4, 1),
+            occurrence("pkg . . . flush().", 6, 0),
+        ]);
+        assert_eq!(callees(&graph, "pkg . . . first()."), vec!["pkg . . . log()."]);
+        assert_eq!(callees(&graph, "pkg . . . second()."), vec!["pkg . . . flush()."]);
+    }
+
+    #[test]
+    fn test_only_functions_become_nodes() {
+        let dir = tempdir().unwrap();
+        let path = write_index(dir.path().join("index.scip"), vec![("src/a.rs", vec![
+            occurrence("pkg . . . Config#", 0, 1),
+            occurrence("pkg . . . Config#path.", 1, 1),
+            occurrence("pkg . . . Config#load().", 3, 1),
+            occurrence("local 0", 4, 1),
+            occurrence("pkg . . . Config#path.", 5, 0),
+            occurrence("local 0", 6, 0),
+            occurrence("pkg . . . impl#[Config][Default]default().", 8, 1),
+            occurrence("pkg . . . Config#", 9, 0),
+            occurrence("pkg . . . Config#load().", 10, 0),
+        ])], |_| {});
+
+        let graph = ScipIngestor::ingest_and_build_graph(&path).unwrap();
+        let mut ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["pkg . . . Config#load().", "pkg . . . impl#[Config][Default]default()."]);
+        assert!(graph.nodes.iter().find(|n| n.id == "pkg . . . Config#load().").unwrap().callees.is_empty());
+        let default = graph.nodes.iter().find(|n| n.id == "pkg . . . impl#[Config][Default]default().").unwrap();
+        let targets: Vec<&str> = default.callees.iter().map(|e| e.target.as_str()).collect();
+        assert_eq!(targets, vec!["pkg . . . Config#load()."]);
+
+        let root = ProjectRoot::new(dir.path());
+        let graph = ScipIngestor::ingest_with_options(&path, &NoProgress, Some(&root), ScipOptions { types: true, ..Default::default() }).unwrap();
+        let config = graph.nodes.iter().find(|n| n.id == "pkg . . . Config#").unwrap();
+        assert_eq!(config.type_kind, Some(TypeKind::Struct));
+        let mut methods: Vec<&str> = config.callees.iter().map(|e| e.target.as_str()).collect();
+        methods.sort();
+        assert_eq!(methods, vec!["pkg . . . Config#load().", "pkg . . . impl#[Config][Default]default()."]);
+        assert!(config.callees.iter().all(|e| e.kind == EdgeKind::DefinesMethod));
+        assert!(!graph.nodes.iter().any(|n| n.id == "pkg . . . Config#path."));
+    }
+
+    #[test]
+    fn test_locations_relative_to_project_root() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("crates/app")).unwrap();
+
+        // Indexed inside crates/app, analyzed from the repository root
+        let path = write_index(dir.path().join("index.scip"), vec![("src/a.rs", vec![
+            scip::types::Occurrence { range: vec![2, 3, 20, 0], ..occurrence("pkg . . . func_a().", 0, 1) },
+            scip::types::Occurrence { range: vec![10, 5, 15], ..occurrence("pkg . . . func_b().", 0, 0) },
+        ])], |index| project_root(index, &dir.path().join("crates/app")));
+
+        let root = ProjectRoot::new(dir.path());
+        let graph = ScipIngestor::ingest_with_options(&path, &NoProgress, Some(&root), ScipOptions::default()).unwrap();
+        let func_a = &graph.nodes[0];
+        assert_eq!(func_a.location.as_deref(), Some("crates/app/src/a.rs:3"));
+        assert_eq!(func_a.callees[0].location().as_deref(), Some("crates/app/src/a.rs:11"));
+
+        // Without a root the index's own paths are kept
+        let graph = ScipIngestor::ingest_and_build_graph(&path).unwrap();
+        assert_eq!(graph.nodes[0].location.as_deref(), Some("src/a.rs:3"));
+    }
+
+    #[test]
+    fn test_symbol_defined_in_several_documents() {
+        let dir = tempdir().unwrap();
+        // Same function under two cfgs, many documents so both are read concurrently
+        let names: Vec<String> = (0..32).map(|i| format!("src/f{:02}.rs", i)).collect();
+        let documents = names.iter().zip(0..)
+            .map(|(name, i)| (name.as_str(), vec![occurrence("pkg . . . run().", i + 4, 1)]))
+            .collect();
+        let path = write_index(dir.path().join("index.scip"), documents, |_| {});
+
+        let graph = ScipIngestor::ingest_and_build_graph(&path).unwrap();
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].location.as_deref(), Some("src/f00.rs:5"));
+    }
+
+    #[test]
+    fn test_signature_documentation() {
+        let dir = tempdir().unwrap();
+        let path = write_index(dir.path().join("index.scip"), vec![("src/a.rs", vec![occurrence("pkg . . . run().", 0, 1)])], |index| {
+            let mut info = scip::types::SymbolInformation::new();
+            info.symbol = "pkg . . . run().".to_string();
+            info.signature_documentation.mut_or_insert_default().text = "fn run(x: i32) -> i32".to_string();
+            index.documents[0].symbols.push(info);
+        });
+
+        let graph = ScipIngestor::ingest_and_build_graph(&path).unwrap();
+        assert_eq!(graph.nodes[0].signature.as_deref(), Some("fn run(x: i32) -> i32"));
+    }
+
+    #[test]
+    fn test_edge_kinds_from_symbol_roles() {
+        let dir = tempdir().unwrap();
+        // fn run() { use log::flush; flush(); let f = flush; spawn(handler); flush!(); }
+        let path = write_index(dir.path().join("index.scip"), vec![("src/a.rs", vec![
+            occurrence("pkg . . . run().", 0, 1),
+            occurrence("pkg . . . log/flush().", 1, SymbolRole::Import as i32),
+            occurrence("pkg . . . log/flush().", 2, 0),
+            occurrence("pkg . . . log/flush().", 3, SymbolRole::ReadAccess as i32),
+            occurrence("pkg . . . handler().", 4, SymbolRole::ReadAccess as i32),
+            occurrence("pkg . . . log/flush().", 5, SymbolRole::Generated as i32),
+        ])], |_| {});
+        let edges = |graph: &CallGraph| -> Vec<(String, &'static str, usize)> {
+            graph.nodes.iter().find(|n| n.id == "pkg . . . run().").unwrap().callees.iter()
+                .map(|e| (e.target.to_string(), e.kind.as_str(), e.weight))
+                .collect()
+        };
+
+        let graph = ScipIngestor::ingest_and_build_graph(&path).unwrap();
+        assert_eq!(edges(&graph), vec![
+            ("pkg . . . log/flush().".to_string(), "import", 1),
+            ("pkg . . . log/flush().".to_string(), "call", 2),
+            ("pkg . . . log/flush().".to_string(), "reference", 1),
+            ("pkg . . . handler().".to_string(), "reference", 1),
+        ]);
+
+        let options = ScipOptions { calls_only: true, ..Default::default() };
+        let graph = ScipIngestor::ingest_with_options(&path, &NoProgress, None, options).unwrap();
+        assert_eq!(edges(&graph), vec![("pkg . . . log/flush().".to_string(), "call", 2)]);
+    }
+
+    #[test]
+    fn test_several_indices_merge() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, crate_dir: &str, documents| {
+            write_index(dir.path().join(name), documents, |index| project_root(index, &dir.path().join(crate_dir)))
+        };
+        // cli's main calls into core, whose shared file both indices cover
+        let cli = write("cli.scip", "crates", vec![
+            ("cli/src/main.rs", vec![occurrence("pkg . cli . main().", 0, 1), occurrence("pkg . core . parse().", 1, 0)]),
+            ("core/src/lib.rs", vec![occurrence("pkg . core . parse().", 0, 1), occurrence("pkg . core . lex().", 1, 0), occurrence("pkg . core . lex().", 3, 1)]),
+        ]);
+        let core = write("core.scip", "crates/core", vec![
+            ("src/lib.rs", vec![occurrence("pkg . core . parse().", 0, 1), occurrence("pkg . core . lex().", 1, 0), occurrence("pkg . core . lex().", 3, 1)]),
+        ]);
+
+        let root = ProjectRoot::new(dir.path());
+        let graph = ScipIngestor::ingest_all(&[cli, core], &NoProgress, Some(&root), ScipOptions::default()).unwrap();
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["pkg . cli . main().", "pkg . core . lex().", "pkg . core . parse()."]);
+        let node = |id: &str| graph.nodes.iter().find(|n| n.id == id).unwrap();
+        assert_eq!(node("pkg . cli . main().").callees[0].target, "pkg . core . parse().");
+        // The shared file is read once
+        assert_eq!(node("pkg . core . parse().").callees[0].weight, 1);
+        assert_eq!(node("pkg . core . parse().").location.as_deref(), Some("crates/core/src/lib.rs:1"));
+        assert_eq!(node("pkg . core . lex().").location.as_deref(), Some("crates/core/src/lib.rs:4"));
+
+        // Without a project root, one relative path in two indices may be two files
+        let a = write_index(dir.path().join("a.scip"), vec![("src/lib.rs", vec![occurrence("pkg . a . run().", 0, 1)])], |_| {});
+        let b = write_index(dir.path().join("b.scip"), vec![("src/lib.rs", vec![occurrence("pkg . b . run().", 0, 1)])], |_| {});
+        let graph = ScipIngestor::ingest_all(&[a, b], &NoProgress, None, ScipOptions::default()).unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+
+        let missing = ScipIngestor::ingest_all(&[dir.path().join("none.scip")], &NoProgress, None, ScipOptions::default());
+        assert!(missing.unwrap_err().to_string().contains("none.scip"));
+    }
+
+    #[test]
+    fn test_external_stubs() {
+        let dir = tempdir().unwrap();
+        let swap = "rust-analyzer cargo std 1.0.0 mem/swap().";
+        let path = write_index(dir.path().join("index.scip"), vec![("src/a.rs", vec![
+            occurrence("rust-analyzer cargo app 0.1.0 run().", 0, 1),
+            occurrence(swap, 1, 0),
+            occurrence("rust-analyzer cargo serde_json 1.0.0 from_str().", 2, 0),
+            occurrence(swap, 3, 0),
+        ])], |index| {
+            let mut info = scip::types::SymbolInformation::new();
+            info.symbol = swap.to_string();
+            info.signature_documentation.mut_or_insert_default().text = "fn swap<T>(x: &mut T, y: &mut T)".to_string();
+            index.external_symbols.push(info);
+        });
+
+        // Left as dangling targets by default
+        let graph = ScipIngestor::ingest_and_build_graph(&path).unwrap();
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].callees.len(), 2);
+
+        let options = ScipOptions { include_external: true, ..Default::default() };
+        let graph = ScipIngestor::ingest_with_options(&path, &NoProgress, None, options).unwrap();
+        let stubs: Vec<(&str, &str)> = graph.nodes.iter()
+            .filter_map(|n| Some((n.label.as_deref()?, n.external.as_deref()?)))
+            .collect();
+        assert_eq!(stubs, vec![("from_str", "serde_json"), ("mem::swap", "std")]);
+        let stub = graph.nodes.iter().find(|n| n.id == swap).unwrap();
+        assert_eq!(stub.signature.as_deref(), Some("fn swap<T>(x: &mut T, y: &mut T)"));
+        assert!(stub.location.is_none());
+    }
+}
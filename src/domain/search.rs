@@ -0,0 +1,274 @@
+//! Symbol Lookup
+//!
+//! Resolves user-supplied names (`main`, `helper`, `utils::helper`,
+//! `Type::method@crate`, `bin:tool2`) to call graph node ids, with fuzzy "did you mean"
+//! suggestions when nothing matches, and lists the nodes matching a
+//! substring, glob or regex (`find`).
+
+use std::fmt;
+
+use regex::RegexBuilder;
+use serde::Serialize;
+
+use crate::domain::callgraph::{node_module, CallGraph, CallGraphNode};
+use crate::domain::filter::glob_match;
+
+/// Maximum number of suggestions listed when a lookup fails.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Why a name could not be resolved to a single node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupError {
+    /// Nothing matched; `suggestions` are the closest ids, best first.
+    NotFound { query: String, suggestions: Vec<String> },
+    /// Several nodes match equally well.
+    Ambiguous { query: String, candidates: Vec<String> },
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::NotFound { query, suggestions } => {
+                write!(f, "no function matches '{}'", query)?;
+                if !suggestions.is_empty() {
+                    write!(f, "\n  did you mean:")?;
+                    for s in suggestions {
+                        write!(f, "\n    {}", s)?;
+                    }
+                }
+                Ok(())
+            }
+            LookupError::Ambiguous { query, candidates } => {
+                write!(f, "'{}' is ambiguous, candidates:", query)?;
+                for c in candidates {
+                    write!(f, "\n    {}", c)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LookupError {}
+
+/// Node path without the `@crate` suffix: `Type::m@app` -> `Type::m`.
+pub(crate) fn display_path(id: &str) -> &str {
+    id.rsplit_once('@').map(|(p, _)| p).unwrap_or(id)
+}
+
+/// Resolve `query` to exactly one node id.
+///
+/// Tries, in order: exact id, exact path without the `@crate` suffix, a
+/// `::`-boundary suffix match (`helper` matches `app::utils::helper`), then
+/// the `main` of a target (`bin:tool2` matches `app[bin:tool2]::main`).
+pub fn resolve_symbol(cg: &CallGraph, query: &str) -> Result<String, LookupError> {
+    if cg.nodes.iter().any(|n| n.id == query) {
+        return Ok(query.to_string());
+    }
+
+    let query_path = display_path(query);
+    let tiers: [&dyn Fn(&str) -> bool; 3] = [
+        &|path: &str| path == query_path,
+        &|path: &str| path.ends_with(&format!("::{}", query_path)),
+        &|path: &str| path.ends_with(&format!("[{}]::main", query_path)),
+    ];
+    for matches_tier in tiers {
+        let mut found: Vec<String> = cg.nodes.iter()
+            .filter(|n| matches_tier(display_path(&n.id)))
+            .map(|n| n.id.to_string())
+            .collect();
+        found.sort();
+        found.dedup();
+        match found.len() {
+            0 => continue,
+            1 => return Ok(found.remove(0)),
+            _ => return Err(LookupError::Ambiguous { query: query.to_string(), candidates: found }),
+        }
+    }
+
+    Err(LookupError::NotFound { query: query.to_string(), suggestions: suggest(cg, query) })
+}
+
+/// Closest node ids to `query`: substring hits first, then by edit distance
+/// of the last path segment.
+pub fn suggest(cg: &CallGraph, query: &str) -> Vec<String> {
+    let needle = display_path(query).to_lowercase();
+    let needle_name = needle.rsplit("::").next().unwrap_or(&needle).to_string();
+    // Allow roughly one typo per three characters
+    let max_distance = (needle_name.len() / 3).max(1);
+
+    let mut scored: Vec<(usize, &str)> = cg.nodes.iter()
+        .filter_map(|n| {
+            let path = display_path(&n.id).to_lowercase();
+            if path.contains(&needle) {
+                return Some((0, n.id.as_str()));
+            }
+            let name = path.rsplit("::").next().unwrap_or(&path);
+            let d = levenshtein(name, &needle_name);
+            (d <= max_distance).then_some((d, n.id.as_str()))
+        })
+        .collect();
+    scored.sort();
+    scored.dedup();
+    scored.into_iter().take(MAX_SUGGESTIONS).map(|(_, id)| id.to_string()).collect()
+}
+
+/// How [`find_symbols`] matches its pattern against ids and labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// Anywhere in the id or label.
+    #[default]
+    Substring,
+    /// The whole id or label, `*` and `?` as wildcards.
+    Glob,
+    /// Anywhere in the id or label, unless anchored.
+    Regex,
+}
+
+/// A node found by [`find_symbols`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SymbolMatch {
+    pub id: String,
+    pub label: Option<String>,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Definition site, `file:line`.
+    pub location: Option<String>,
+}
+
+impl SymbolMatch {
+    pub fn of(node: &CallGraphNode) -> Self {
+        SymbolMatch {
+            id: node.id.to_string(),
+            label: node.label.clone().filter(|l| *l != node.id),
+            krate: node.external.clone().unwrap_or_else(|| node_module(&node.id).0.to_string()),
+            location: node.location.clone(),
+        }
+    }
+}
+
+/// Nodes whose id or label matches `pattern`, sorted by id. Fails only on
+/// an invalid regex.
+pub fn find_symbols(cg: &CallGraph, pattern: &str, mode: MatchMode, ignore_case: bool) -> Result<Vec<SymbolMatch>, regex::Error> {
+    let fold = |s: &str| if ignore_case { s.to_lowercase() } else { s.to_string() };
+    let needle = fold(pattern);
+    let regex = match mode {
+        MatchMode::Regex => Some(RegexBuilder::new(pattern).case_insensitive(ignore_case).build()?),
+        _ => None,
+    };
+    let matches = |text: &str| match (mode, &regex) {
+        (MatchMode::Regex, Some(regex)) => regex.is_match(text),
+        (MatchMode::Glob, _) => glob_match(&needle, &fold(text)),
+        _ => fold(text).contains(&needle),
+    };
+
+    let mut found: Vec<SymbolMatch> = cg.nodes.iter()
+        .filter(|n| matches(&n.id) || n.label.as_deref().is_some_and(|l| l != n.id && matches(l)))
+        .map(SymbolMatch::of)
+        .collect();
+    found.sort_by(|a, b| a.id.cmp(&b.id));
+    found.dedup_by(|a, b| a.id == b.id);
+    Ok(found)
+}
+
+/// One match per line: id, crate and location in aligned columns.
+pub fn format_matches(matches: &[SymbolMatch]) -> String {
+    let id_width = matches.iter().map(|m| m.id.len()).max().unwrap_or(0);
+    let crate_width = matches.iter().map(|m| m.krate.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for m in matches {
+        let line = format!("{:id_width$}  {:crate_width$}  {}", m.id, m.krate, m.location.as_deref().unwrap_or("-"));
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::CallGraphNode;
+
+    fn graph(ids: &[&str]) -> CallGraph {
+        CallGraph::new(ids.iter().map(|id| CallGraphNode {
+            id: (*id).into(),
+            ..Default::default()
+        }).collect())
+    }
+
+    #[test]
+    fn test_resolve_exact_and_suffix() {
+        let cg = graph(&["app::main", "app::utils::helper", "Tool::work@app", "lib::api::start"]);
+        assert_eq!(resolve_symbol(&cg, "app::main"), Ok("app::main".to_string()));
+        assert_eq!(resolve_symbol(&cg, "Tool::work"), Ok("Tool::work@app".to_string()));
+        assert_eq!(resolve_symbol(&cg, "helper"), Ok("app::utils::helper".to_string()));
+        assert_eq!(resolve_symbol(&cg, "api::start"), Ok("lib::api::start".to_string()));
+        assert_eq!(resolve_symbol(&cg, "work"), Ok("Tool::work@app".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_ambiguous() {
+        let cg = graph(&["a::run", "b::run", "b::run_all"]);
+        let err = resolve_symbol(&cg, "run").unwrap_err();
+        assert_eq!(err, LookupError::Ambiguous {
+            query: "run".to_string(),
+            candidates: vec!["a::run".to_string(), "b::run".to_string()],
+        });
+    }
+
+    #[test]
+    fn test_resolve_target_main() {
+        let cg = graph(&["app::main", "app[bin:tool2]::main", "app[example:demo]::main", "app[bin:tool2]::run"]);
+        assert_eq!(resolve_symbol(&cg, "bin:tool2"), Ok("app[bin:tool2]::main".to_string()));
+        assert_eq!(resolve_symbol(&cg, "example:demo"), Ok("app[example:demo]::main".to_string()));
+        assert_eq!(resolve_symbol(&cg, "app::main"), Ok("app::main".to_string()));
+        assert!(matches!(resolve_symbol(&cg, "main"), Err(LookupError::Ambiguous { .. })));
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let cg = graph(&["app::process_items", "app::helper", "app::unrelated"]);
+        match resolve_symbol(&cg, "helpr") {
+            Err(LookupError::NotFound { suggestions, .. }) => assert_eq!(suggestions, vec!["app::helper"]),
+            other => panic!("unexpected {:?}", other),
+        }
+        match resolve_symbol(&cg, "process") {
+            Err(LookupError::NotFound { suggestions, .. }) => assert_eq!(suggestions, vec!["app::process_items"]),
+            other => panic!("unexpected {:?}", other),
+        }
+        let msg = resolve_symbol(&cg, "helpr").unwrap_err().to_string();
+        assert!(msg.contains("did you mean"), "{}", msg);
+    }
+
+    #[test]
+    fn test_find_symbols() {
+        let mut cg = graph(&["app::main", "app::net::send_all", "Client::send@app", "app::Sender::new"]);
+        cg.nodes[1].location = Some("src/net.rs:4".to_string());
+        let ids = |mode: MatchMode, pattern: &str, ignore_case: bool| {
+            find_symbols(&cg, pattern, mode, ignore_case).unwrap().into_iter().map(|m| m.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(MatchMode::Substring, "send", false), vec!["Client::send@app", "app::net::send_all"]);
+        assert_eq!(ids(MatchMode::Substring, "send", true), vec!["Client::send@app", "app::Sender::new", "app::net::send_all"]);
+        assert_eq!(ids(MatchMode::Glob, "app::*::send*", false), vec!["app::net::send_all"]);
+        assert_eq!(ids(MatchMode::Regex, "::(main|new)$", false), vec!["app::Sender::new", "app::main"]);
+        assert!(find_symbols(&cg, "(", MatchMode::Regex, false).is_err());
+
+        let found = find_symbols(&cg, "send_all", MatchMode::Substring, false).unwrap();
+        assert_eq!(found[0].krate, "app");
+        assert_eq!(format_matches(&found), "app::net::send_all  app  src/net.rs:4\n");
+    }
+}
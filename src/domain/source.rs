@@ -0,0 +1,177 @@
+//! Source Model
+//!
+//! The input every `CallGraphBuilder` consumes: the Rust files of a
+//! workspace, each tagged with the crate it belongs to.
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::paths::ProjectRoot;
+
+/// Cargo targets whose code is not part of the library or binaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceOrigin {
+    /// Integration tests under `tests/`.
+    Test,
+    /// Benchmarks under `benches/`.
+    Bench,
+    /// Examples under `examples/`.
+    Example,
+}
+
+impl SourceOrigin {
+    /// The origin of a Cargo target kind (`test`, `bench`, `example`);
+    /// `None` for `lib`, `bin` and the like.
+    pub fn from_target_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "test" => Some(SourceOrigin::Test),
+            "bench" => Some(SourceOrigin::Bench),
+            "example" => Some(SourceOrigin::Example),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SourceOrigin::Test => "test",
+            SourceOrigin::Bench => "bench",
+            SourceOrigin::Example => "example",
+        }
+    }
+}
+
+/// Crate name of a binary, example or bench target other than the
+/// package's `src/main.rs`, e.g. `app[bin:tool2]`. Each such target is a
+/// crate with its own `main`; the library and the default binary keep the
+/// package name.
+pub fn target_crate(package: &str, kind: &str, target: &str) -> String {
+    format!("{}[{}:{}]", package, kind, target)
+}
+
+/// One source file of a crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub crate_name: String,
+    /// Path as reported by the loader; used in node locations and edge labels.
+    pub path: String,
+    pub content: String,
+    /// Set for files of test, bench and example targets.
+    pub origin: Option<SourceOrigin>,
+    /// Directory of the file's package (its `Cargo.toml`), spelled like
+    /// `path`; module paths are read from below it.
+    #[serde(default)]
+    pub package_dir: Option<String>,
+}
+
+impl SourceFile {
+    pub fn new(crate_name: impl Into<String>, path: impl Into<String>, content: impl Into<String>) -> Self {
+        Self { crate_name: crate_name.into(), path: path.into(), content: content.into(), origin: None, package_dir: None }
+    }
+
+    pub fn with_origin(mut self, origin: Option<SourceOrigin>) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_package_dir(mut self, dir: impl Into<String>) -> Self {
+        self.package_dir = Some(dir.into());
+        self
+    }
+}
+
+/// The files to analyze, in a stable order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSet {
+    files: Vec<SourceFile>,
+}
+
+impl SourceSet {
+    pub fn new(files: Vec<SourceFile>) -> Self {
+        Self { files }
+    }
+
+    pub fn push(&mut self, file: SourceFile) {
+        self.files.push(file);
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, SourceFile> {
+        self.files.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, SourceFile> {
+        self.files.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The same files with their paths relative to `root`.
+    pub fn relative_to(mut self, root: &ProjectRoot) -> Self {
+        for file in &mut self.files {
+            file.path = root.relativize(&file.path);
+            file.package_dir = file.package_dir.as_deref().map(|dir| root.relativize(dir));
+        }
+        self
+    }
+
+    /// Sort by path and drop files listed twice (e.g. by several targets),
+    /// keeping the library or binary copy over a test, bench or example one.
+    pub fn dedup_by_path(&mut self) {
+        self.files.sort_by(|a, b| a.path.cmp(&b.path).then(a.origin.is_some().cmp(&b.origin.is_some())));
+        self.files.dedup_by(|a, b| a.path == b.path);
+    }
+}
+
+impl From<Vec<SourceFile>> for SourceSet {
+    fn from(files: Vec<SourceFile>) -> Self {
+        Self::new(files)
+    }
+}
+
+/// `(crate, path, content)` triples, as older callers build them.
+impl From<Vec<(String, String, String)>> for SourceSet {
+    fn from(files: Vec<(String, String, String)>) -> Self {
+        files.into_iter().map(|(c, p, s)| SourceFile::new(c, p, s)).collect()
+    }
+}
+
+impl FromIterator<SourceFile> for SourceSet {
+    fn from_iter<I: IntoIterator<Item = SourceFile>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a SourceSet {
+    type Item = &'a SourceFile;
+    type IntoIter = std::slice::Iter<'a, SourceFile>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_by_path() {
+        let mut set = SourceSet::from(vec![
+            ("app".to_string(), "src/main.rs".to_string(), "fn main() {}".to_string()),
+            ("app".to_string(), "src/a.rs".to_string(), String::new()),
+            ("app".to_string(), "src/main.rs".to_string(), "fn main() {}".to_string()),
+        ]);
+        set.dedup_by_path();
+        let paths: Vec<&str> = set.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/a.rs", "src/main.rs"]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Graph Statistics
+//!
+//! Size and shape metrics of a call graph (per-crate counts, fan-in/fan-out
+//! distributions, call depth, recursion cycles, most-called functions) for
+//! the `stats` subcommand, printed as a table or as JSON to track over time.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::domain::callgraph::{node_module, CallGraph, EdgeKind};
+use crate::domain::compact::CompactGraph;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CrateStats {
+    pub nodes: usize,
+    /// Edges leaving the crate's nodes.
+    pub edges: usize,
+}
+
+/// Summary of one per-node quantity.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Distribution {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub median: usize,
+    pub p90: usize,
+}
+
+impl Distribution {
+    fn of(mut values: Vec<usize>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+        let at = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize];
+        Distribution {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<usize>() as f64 / values.len() as f64,
+            median: at(0.5),
+            p90: at(0.9),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalledFn {
+    pub id: String,
+    /// Distinct callers.
+    pub callers: usize,
+}
+
+/// A caller and a callee it calls directly from several places.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HotEdge {
+    pub caller: String,
+    pub callee: String,
+    /// Call sites, summed over the edge weights.
+    pub calls: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub crates: BTreeMap<String, CrateStats>,
+    /// Distinct callers per node.
+    pub fan_in: Distribution,
+    /// Distinct callees per node.
+    pub fan_out: Distribution,
+    /// Mean of fan-in plus fan-out.
+    pub average_degree: f64,
+    pub entries: Vec<String>,
+    /// Hops from the entry points to the farthest function they reach.
+    pub max_depth: usize,
+    /// Groups of mutually recursive functions, and functions calling themselves.
+    pub cycles: usize,
+    /// Functions of the graph with the most distinct callers.
+    pub most_called: Vec<CalledFn>,
+    /// Edges into functions that are not nodes (unanalyzed crates, unresolved calls).
+    pub external_calls: usize,
+    /// Static calls (`call` edges) with the most call sites.
+    pub hottest_edges: Vec<HotEdge>,
+}
+
+impl GraphStats {
+    /// Compute the metrics, listing the `top` most-called functions and
+    /// hottest edges.
+    pub fn compute(cg: &CallGraph, entries: &[String], top: usize) -> Self {
+        let mut crates: BTreeMap<String, CrateStats> = BTreeMap::new();
+        let mut callers: HashMap<&str, HashSet<&str>> = HashMap::new();
+        let mut fan_out = Vec::with_capacity(cg.nodes.len());
+        for node in &cg.nodes {
+            let krate = node.external.as_deref().unwrap_or_else(|| node_module(&node.id).0);
+            let stats = crates.entry(krate.to_string()).or_default();
+            stats.nodes += 1;
+            stats.edges += node.callees.len();
+
+            let callees: HashSet<&str> = node.callee_ids().collect();
+            fan_out.push(callees.len());
+            for callee in callees {
+                callers.entry(callee).or_default().insert(node.id.as_str());
+            }
+        }
+        let fan_in: Vec<usize> = cg.nodes.iter()
+            .map(|n| callers.get(n.id.as_str()).map_or(0, HashSet::len))
+            .collect();
+        let average_degree = if cg.nodes.is_empty() {
+            0.0
+        } else {
+            (fan_in.iter().sum::<usize>() + fan_out.iter().sum::<usize>()) as f64 / cg.nodes.len() as f64
+        };
+
+        // Targets without a node would crowd out the graph's own functions
+        let ids: HashSet<&str> = cg.nodes.iter().map(|n| n.id.as_str()).collect();
+        let mut most_called: Vec<CalledFn> = callers.iter()
+            .filter(|(id, _)| ids.contains(*id))
+            .map(|(id, from)| CalledFn { id: id.to_string(), callers: from.len() })
+            .collect();
+        most_called.sort_by(|a, b| b.callers.cmp(&a.callers).then_with(|| a.id.cmp(&b.id)));
+        most_called.truncate(top);
+
+        let mut hottest_edges: Vec<HotEdge> = cg.nodes.iter()
+            .flat_map(|n| n.weighted_callees().into_iter()
+                .filter(|(e, _)| e.kind == EdgeKind::Call)
+                .map(|(e, calls)| HotEdge { caller: n.id.to_string(), callee: e.target.to_string(), calls }))
+            .collect();
+        hottest_edges.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| (&a.caller, &a.callee).cmp(&(&b.caller, &b.callee))));
+        hottest_edges.truncate(top);
+        let external_calls = cg.nodes.iter()
+            .flat_map(|n| n.callee_ids())
+            .filter(|id| !ids.contains(id))
+            .count();
+        let compact = CompactGraph::new(cg);
+
+        GraphStats {
+            nodes: cg.nodes.len(),
+            edges: cg.nodes.iter().map(|n| n.callees.len()).sum(),
+            crates,
+            fan_in: Distribution::of(fan_in),
+            fan_out: Distribution::of(fan_out),
+            average_degree,
+            entries: entries.to_vec(),
+            max_depth: compact.depths(&compact.ids(entries.iter().map(String::as_str))).into_iter().flatten().max().unwrap_or(0),
+            cycles: compact.cycles().len(),
+            most_called,
+            external_calls,
+            hottest_edges,
+        }
+    }
+}
+
+impl fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Nodes: {}  Edges: {}  Average degree: {:.2}", self.nodes, self.edges, self.average_degree)?;
+        if self.external_calls > 0 {
+            writeln!(f, "Calls out of the graph: {}", self.external_calls)?;
+        }
+        writeln!(f, "Max call depth: {} (from {})", self.max_depth,
+            if self.entries.is_empty() { "no entry points".to_string() } else { self.entries.join(", ") })?;
+        writeln!(f, "Call cycles: {} (see --condense)", self.cycles)?;
+
+        let width = self.crates.keys().map(String::len).max().unwrap_or(0).max("Crate".len());
+        writeln!(f)?;
+        writeln!(f, "{:<width$}  {:>7}  {:>7}", "Crate", "Nodes", "Edges")?;
+        for (name, stats) in &self.crates {
+            writeln!(f, "{:<width$}  {:>7}  {:>7}", name, stats.nodes, stats.edges)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:<8}  {:>5}  {:>5}  {:>7}  {:>6}  {:>5}", "", "Min", "Max", "Mean", "Median", "P90")?;
+        for (name, d) in [("Fan-in", &self.fan_in), ("Fan-out", &self.fan_out)] {
+            writeln!(f, "{:<8}  {:>5}  {:>5}  {:>7.2}  {:>6}  {:>5}", name, d.min, d.max, d.mean, d.median, d.p90)?;
+        }
+
+        if !self.most_called.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Most called:")?;
+            for called in &self.most_called {
+                writeln!(f, "  {:>5}  {}", called.callers, called.id)?;
+            }
+        }
+        if !self.hottest_edges.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Hottest calls:")?;
+            for edge in &self.hottest_edges {
+                writeln!(f, "  {:>5}  {} -> {}", edge.calls, edge.caller, edge.callee)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    #[test]
+    fn test_compute() {
+        let cg = CallGraph::new(vec![
+            node("app::main", &["app::a", "app::b", "util::log"]),
+            node("app::a", &["util::log", "util::log"]),
+            node("app::b", &["util::log", "app::a"]),
+            node("util::log", &[]),
+            node("Tool::run@util", &["serde_json::to_string@util"]),
+        ]);
+        let stats = GraphStats::compute(&cg, &["app::main".to_string()], 2);
+
+        assert_eq!((stats.nodes, stats.edges), (5, 8));
+        assert_eq!(stats.crates["app"], CrateStats { nodes: 3, edges: 7 });
+        assert_eq!(stats.crates["util"], CrateStats { nodes: 2, edges: 1 });
+        assert_eq!((stats.fan_out.min, stats.fan_out.max, stats.fan_out.median), (0, 3, 1));
+        assert_eq!(stats.fan_in.max, 3);
+        assert!((stats.average_degree - 2.6).abs() < 1e-9);
+        assert_eq!((stats.max_depth, stats.cycles), (1, 0));
+        assert_eq!(stats.most_called, vec![
+            CalledFn { id: "util::log".to_string(), callers: 3 },
+            CalledFn { id: "app::a".to_string(), callers: 2 },
+        ]);
+        assert_eq!(stats.external_calls, 1);
+
+        assert_eq!(stats.hottest_edges[0], HotEdge { caller: "app::a".to_string(), callee: "util::log".to_string(), calls: 2 });
+        assert_eq!(stats.hottest_edges[1].calls, 1);
+
+        let table = stats.to_string();
+        assert!(table.contains("Nodes: 5  Edges: 8"), "{}", table);
+        assert!(table.contains("Calls out of the graph: 1"), "{}", table);
+        assert!(table.contains("\nCall cycles: 0 (see --condense)\n"), "{}", table);
+        assert!(table.contains("      3  util::log"), "{}", table);
+        assert!(table.contains("Hottest calls:\n      2  app::a -> util::log"), "{}", table);
+    }
+}
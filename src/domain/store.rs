@@ -9,8 +9,16 @@ pub trait SymbolStore: Send + Sync {
     fn insert_method(&self, type_name: String, method_name: String, sig: FunctionSignature);
     fn get_function(&self, key: &str) -> Option<FunctionSignature>;
     fn get_method(&self, type_name: &str, method_name: &str) -> Option<FunctionSignature>;
-    fn find_methods_by_name(&self, method_name: &str) -> Vec<FunctionSignature>;
+    /// Returns `(type_name, signature)` pairs for every type defining `method_name`.
+    fn find_methods_by_name(&self, method_name: &str) -> Vec<(String, FunctionSignature)>;
     fn register_method_lookup(&self, method_name: String, type_name: String);
+    fn find_functions_by_name(&self, name: &str) -> Vec<(String, FunctionSignature)>;
+    fn register_function_lookup(&self, name: String, key: String);
+    fn register_trait_impl(&self, trait_name: String, type_name: String);
+    fn find_trait_impls(&self, trait_name: &str) -> Vec<String>;
+    /// Record that field `field` of struct `type_name` holds a `field_type`.
+    fn register_field_type(&self, type_name: String, field: String, field_type: String);
+    fn get_field_type(&self, type_name: &str, field: &str) -> Option<String>;
 }
 
 // ============================================================================
@@ -21,6 +29,9 @@ pub struct MemorySymbolStore {
     pub global_functions: DashMap<String, FunctionSignature>,
     pub type_methods: DashMap<(String, String), FunctionSignature>,
     pub method_lookup: DashMap<String, Vec<String>>, // method_name -> Vec<type_name>
+    pub function_lookup: DashMap<String, Vec<String>>, // fn name -> Vec<qualified key>
+    pub trait_impls: DashMap<String, Vec<String>>, // trait name -> Vec<type_name>
+    pub field_types: DashMap<(String, String), String>, // (struct, field) -> field type
 }
 
 impl Default for MemorySymbolStore {
@@ -29,6 +40,9 @@ impl Default for MemorySymbolStore {
             global_functions: DashMap::new(),
             type_methods: DashMap::new(),
             method_lookup: DashMap::new(),
+            function_lookup: DashMap::new(),
+            trait_impls: DashMap::new(),
+            field_types: DashMap::new(),
         }
     }
 }
@@ -50,11 +64,11 @@ impl SymbolStore for MemorySymbolStore {
         self.type_methods.get(&(type_name.to_string(), method_name.to_string())).map(|r| r.clone())
     }
 
-    fn find_methods_by_name(&self, method_name: &str) -> Vec<FunctionSignature> {
+    fn find_methods_by_name(&self, method_name: &str) -> Vec<(String, FunctionSignature)> {
         if let Some(type_names) = self.method_lookup.get(method_name) {
             type_names
                 .iter()
-                .filter_map(|tn| self.get_method(tn, method_name))
+                .filter_map(|tn| self.get_method(tn, method_name).map(|sig| (tn.clone(), sig)))
                 .collect()
         } else {
             Vec::new()
@@ -64,6 +78,42 @@ impl SymbolStore for MemorySymbolStore {
     fn register_method_lookup(&self, method_name: String, type_name: String) {
         self.method_lookup.entry(method_name).or_default().push(type_name);
     }
+
+    fn find_functions_by_name(&self, name: &str) -> Vec<(String, FunctionSignature)> {
+        if let Some(keys) = self.function_lookup.get(name) {
+            keys.iter()
+                .filter_map(|k| self.get_function(k).map(|sig| (k.clone(), sig)))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn register_function_lookup(&self, name: String, key: String) {
+        let mut keys = self.function_lookup.entry(name).or_default();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    fn register_trait_impl(&self, trait_name: String, type_name: String) {
+        let mut types = self.trait_impls.entry(trait_name).or_default();
+        if !types.contains(&type_name) {
+            types.push(type_name);
+        }
+    }
+
+    fn find_trait_impls(&self, trait_name: &str) -> Vec<String> {
+        self.trait_impls.get(trait_name).map(|t| t.clone()).unwrap_or_default()
+    }
+
+    fn register_field_type(&self, type_name: String, field: String, field_type: String) {
+        self.field_types.insert((type_name, field), field_type);
+    }
+
+    fn get_field_type(&self, type_name: &str, field: &str) -> Option<String> {
+        self.field_types.get(&(type_name.to_string(), field.to_string())).map(|t| t.clone())
+    }
 }
 
 // ============================================================================
@@ -76,6 +126,9 @@ pub struct DiskSymbolStore {
     functions_tree: sled::Tree,
     methods_tree: sled::Tree,
     lookup_tree: sled::Tree,
+    function_lookup_tree: sled::Tree,
+    trait_impls_tree: sled::Tree,
+    field_types_tree: sled::Tree,
 }
 
 impl DiskSymbolStore {
@@ -84,12 +137,18 @@ impl DiskSymbolStore {
         let functions_tree = db.open_tree("functions")?;
         let methods_tree = db.open_tree("methods")?;
         let lookup_tree = db.open_tree("method_lookup")?;
+        let function_lookup_tree = db.open_tree("function_lookup")?;
+        let trait_impls_tree = db.open_tree("trait_impls")?;
+        let field_types_tree = db.open_tree("field_types")?;
         
         Ok(Self {
             _db: db,
             functions_tree,
             methods_tree,
             lookup_tree,
+            function_lookup_tree,
+            trait_impls_tree,
+            field_types_tree,
         })
     }
 
@@ -129,7 +188,7 @@ impl SymbolStore for DiskSymbolStore {
             .and_then(|bytes| bincode::deserialize(&bytes).ok())
     }
 
-    fn find_methods_by_name(&self, method_name: &str) -> Vec<FunctionSignature> {
+    fn find_methods_by_name(&self, method_name: &str) -> Vec<(String, FunctionSignature)> {
         self.lookup_tree
             .get(method_name.as_bytes())
             .ok()
@@ -138,7 +197,7 @@ impl SymbolStore for DiskSymbolStore {
             .map(|type_names| {
                 type_names
                     .iter()
-                    .filter_map(|tn| self.get_method(tn, method_name))
+                    .filter_map(|tn| self.get_method(tn, method_name).map(|sig| (tn.clone(), sig)))
                     .collect()
             })
             .unwrap_or_default()
@@ -160,6 +219,70 @@ impl SymbolStore for DiskSymbolStore {
             }
         }
     }
+
+    fn find_functions_by_name(&self, name: &str) -> Vec<(String, FunctionSignature)> {
+        self.function_lookup_tree
+            .get(name.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|bytes| bincode::deserialize::<Vec<String>>(&bytes).ok())
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|k| self.get_function(k).map(|sig| (k.clone(), sig)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn register_function_lookup(&self, name: String, key: String) {
+        // Same read-modify-write pattern as the method lookup list
+        let mut keys: Vec<String> = self.function_lookup_tree
+            .get(name.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok())
+            .unwrap_or_default();
+
+        if !keys.contains(&key) {
+            keys.push(key);
+            if let Ok(bytes) = bincode::serialize(&keys) {
+                let _ = self.function_lookup_tree.insert(name.as_bytes(), bytes);
+            }
+        }
+    }
+
+    fn register_trait_impl(&self, trait_name: String, type_name: String) {
+        let mut types = self.find_trait_impls(&trait_name);
+        if !types.contains(&type_name) {
+            types.push(type_name);
+            if let Ok(bytes) = bincode::serialize(&types) {
+                let _ = self.trait_impls_tree.insert(trait_name.as_bytes(), bytes);
+            }
+        }
+    }
+
+    fn find_trait_impls(&self, trait_name: &str) -> Vec<String> {
+        self.trait_impls_tree
+            .get(trait_name.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    fn register_field_type(&self, type_name: String, field: String, field_type: String) {
+        let key = format!("{}.{}", type_name, field);
+        let _ = self.field_types_tree.insert(key.as_bytes(), field_type.as_bytes());
+    }
+
+    fn get_field_type(&self, type_name: &str, field: &str) -> Option<String> {
+        let key = format!("{}.{}", type_name, field);
+        self.field_types_tree
+            .get(key.as_bytes())
+            .ok()
+            .flatten()
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    }
 }
 
 // ============================================================================
@@ -178,6 +301,7 @@ mod tests {
             receiver: Some("&self".to_string()),
             location: "test.rs:1".to_string(),
             crate_name: "test_crate".to_string(),
+            module: Vec::new(),
         }
     }
 
@@ -205,7 +329,51 @@ mod tests {
         
         let by_name = store.find_methods_by_name("bar");
         assert_eq!(by_name.len(), 1);
-        assert_eq!(by_name[0].name, "bar");
+        assert_eq!(by_name[0].0, "MyType");
+        assert_eq!(by_name[0].1.name, "bar");
+    }
+
+    #[test]
+    fn test_trait_impls() {
+        let mem = MemorySymbolStore::default();
+        let dir = tempdir().unwrap();
+        let disk = DiskSymbolStore::new(dir.path().to_str().unwrap()).unwrap();
+
+        for store in [&mem as &dyn SymbolStore, &disk as &dyn SymbolStore] {
+            store.register_trait_impl("Op".to_string(), "Add".to_string());
+            store.register_trait_impl("Op".to_string(), "Mul".to_string());
+            store.register_trait_impl("Op".to_string(), "Add".to_string());
+            assert_eq!(store.find_trait_impls("Op"), vec!["Add".to_string(), "Mul".to_string()]);
+            assert!(store.find_trait_impls("Missing").is_empty());
+        }
+    }
+
+    #[test]
+    fn test_field_types() {
+        let mem = MemorySymbolStore::default();
+        let dir = tempdir().unwrap();
+        let disk = DiskSymbolStore::new(dir.path().to_str().unwrap()).unwrap();
+
+        for store in [&mem as &dyn SymbolStore, &disk as &dyn SymbolStore] {
+            store.register_field_type("App".to_string(), "client".to_string(), "Client".to_string());
+            assert_eq!(store.get_field_type("App", "client").as_deref(), Some("Client"));
+            assert!(store.get_field_type("App", "missing").is_none());
+        }
+    }
+
+    #[test]
+    fn test_memory_store_function_lookup() {
+        let store = MemorySymbolStore::default();
+        store.insert_function("crate_a::foo".to_string(), sample_sig("foo"));
+        store.insert_function("crate_b::foo".to_string(), sample_sig("foo"));
+        store.register_function_lookup("foo".to_string(), "crate_a::foo".to_string());
+        store.register_function_lookup("foo".to_string(), "crate_b::foo".to_string());
+        store.register_function_lookup("foo".to_string(), "crate_b::foo".to_string());
+
+        let mut keys: Vec<String> = store.find_functions_by_name("foo").into_iter().map(|(k, _)| k).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["crate_a::foo".to_string(), "crate_b::foo".to_string()]);
+        assert!(store.find_functions_by_name("missing").is_empty());
     }
 
     #[test]
@@ -234,4 +402,17 @@ mod tests {
         let by_name = store.find_methods_by_name("method");
         assert_eq!(by_name.len(), 1);
     }
+
+    #[test]
+    fn test_disk_store_function_lookup() {
+        let dir = tempdir().unwrap();
+        let store = DiskSymbolStore::new(dir.path().to_str().unwrap()).unwrap();
+
+        store.insert_function("crate_a::qux".to_string(), sample_sig("qux"));
+        store.register_function_lookup("qux".to_string(), "crate_a::qux".to_string());
+
+        let found = store.find_functions_by_name("qux");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "crate_a::qux");
+    }
 }
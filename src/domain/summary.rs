@@ -0,0 +1,1515 @@
+//! Per-File Summaries
+//!
+//! Everything the call graph builder needs from one source file, extracted in a
+//! single pass over its AST: symbol definitions for the index, `use` entries,
+//! function nodes and their *unresolved* calls. Summaries depend only on the
+//! file's own contents, so they can be cached by content hash and re-linked
+//! against the rest of the workspace without re-parsing.
+
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+use syn::spanned::Spanned;
+use syn::{Expr, Item, Stmt, Type, Visibility};
+
+use crate::domain::callgraph::TypeKind;
+use crate::domain::cfg::{CfgExpr, CfgOptions};
+use crate::domain::imports::{collect_use_entries, UseEntry};
+use crate::domain::index::{file_module_path, method_id, nested_items, qualified_fn_id, FunctionSignature};
+use crate::domain::metrics::FnMetrics;
+use crate::domain::panics::{PanicKind, PanicSite};
+use crate::domain::source::SourceFile;
+
+/// Bump when the summary layout or extraction rules change so cached
+/// summaries from older builds are ignored.
+pub const SUMMARY_FORMAT_VERSION: u32 = 27;
+
+/// 64-bit FNV-1a. `DefaultHasher` may change its algorithm between Rust
+/// releases, which would silently orphan every persisted cache entry; this
+/// one only depends on the bytes written.
+#[derive(Debug, Clone)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Stable hash of a file's identity and contents, used as the cache key.
+pub fn content_hash(crate_name: &str, file_path: &str, code: &str) -> u64 {
+    let mut hasher = StableHasher::default();
+    SUMMARY_FORMAT_VERSION.hash(&mut hasher);
+    crate_name.hash(&mut hasher);
+    file_path.hash(&mut hasher);
+    code.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// [`content_hash`] of the summary of `file` extracted under `cfg`; the
+/// same as `content_hash` when nothing is evaluated and the file's package
+/// directory is unknown.
+pub fn cfg_content_hash(file: &SourceFile, cfg: &CfgOptions) -> u64 {
+    let hash = content_hash(&file.crate_name, &file.path, &file.content);
+    if cfg.is_empty() && file.package_dir.is_none() {
+        return hash;
+    }
+    let mut hasher = StableHasher::default();
+    hash.hash(&mut hasher);
+    cfg.hash(&mut hasher);
+    file.package_dir.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Where an item sits: its module path and the conditions of the items
+/// around it.
+#[derive(Debug, Clone, Default)]
+struct ItemScope {
+    module_path: Vec<String>,
+    /// Under `#[cfg(test)]` or inside a test function.
+    in_test: bool,
+    /// `#[cfg(..)]` conditions of the item and everything enclosing it.
+    cfg: Vec<CfgExpr>,
+}
+
+impl ItemScope {
+    /// Scope of an item with `attrs`, or `None` if `options` rule it out.
+    fn enter(&self, attrs: &[syn::Attribute], options: &CfgOptions) -> Option<ItemScope> {
+        let mut scope = self.clone();
+        if let Some(cfg) = CfgExpr::from_attrs(attrs) {
+            if cfg.eval(options) == Some(false) {
+                return None;
+            }
+            scope.cfg.push(cfg);
+        }
+        scope.in_test |= is_cfg_test(attrs);
+        Some(scope)
+    }
+
+    fn nested(&self, name: &str, in_test: bool) -> ItemScope {
+        let mut scope = self.clone();
+        scope.module_path.push(name.to_string());
+        scope.in_test = in_test;
+        scope
+    }
+
+    /// Conditions a build needs for the item, in Rust syntax.
+    fn cfg_condition(&self) -> Option<String> {
+        match self.cfg.as_slice() {
+            [] => None,
+            [one] => Some(one.to_string()),
+            all => Some(CfgExpr::All(all.to_vec()).to_string()),
+        }
+    }
+}
+
+/// A symbol the file contributes to the global index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SymbolDef {
+    /// Free function under its qualified key (`crate::module::name`).
+    Function { key: String, sig: FunctionSignature },
+    /// Method or associated function of `type_name`.
+    Method { type_name: String, sig: FunctionSignature },
+}
+
+/// What a call expression names, before resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RawTarget {
+    /// `foo()`, `m::foo()`, `Type::new()`.
+    Path(Vec<String>),
+    /// `recv.method()`; `receiver` is the receiver's name when it is a plain path.
+    Method { receiver: Option<String>, method: String },
+    /// `Path::f(recv, ..)` or `<T as Trait>::f(..)`: an associated call that
+    /// may be a trait method in UFCS form. `receiver` is the first argument's
+    /// name, or the type `T`.
+    AssocCall { path: Vec<String>, receiver: Option<String> },
+    /// `base.a.b.method()`: a method called through field accesses. `base` is
+    /// a local name, or the impl type for `self`.
+    FieldMethod { base: String, fields: Vec<String>, method: String },
+    /// Already-final node id (closures and `if`/`match` markers).
+    Node(String),
+    /// The task handed to a spawn function (`tokio::spawn(..)`, `thread::spawn(..)`).
+    Spawn(Box<RawTarget>),
+    /// A path used as a value (`iter.map(process_item)`, `register(handler)`);
+    /// linked only if it names a known function.
+    Reference(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawCall {
+    pub target: RawTarget,
+    pub line: usize,
+}
+
+/// A path call with what the call site says about the callee's type
+/// parameters: `run::<Add>(x)` or `run(Add::new())`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallSite {
+    pub path: Vec<String>,
+    /// Turbofish arguments in order (`None` for `_` and non-path types).
+    pub type_args: Vec<Option<String>>,
+    /// Per argument: a constructed type name (`Add`) or a local name (`x`).
+    pub args: Vec<Option<String>>,
+}
+
+/// A synthetic closure (or spawned `async` block) node owned by a function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosureSummary {
+    pub id: String,
+    pub label: String,
+    /// Line the closure or `async` block starts on.
+    pub line: usize,
+    pub is_async: bool,
+    pub calls: Vec<RawCall>,
+}
+
+/// A function or method node together with its unresolved calls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FnSummary {
+    pub id: String,
+    pub label: String,
+    /// `fn run(op: &dyn Op) -> i32`, as written, on one line.
+    pub signature: String,
+    /// First line of the doc comment.
+    pub doc: Option<String>,
+    /// Line of the `fn` keyword.
+    pub line: usize,
+    pub metrics: FnMetrics,
+    pub is_public: bool,
+    pub is_async: bool,
+    pub is_unsafe: bool,
+    /// `unsafe { .. }` blocks in the body, those in closures included.
+    pub unsafe_blocks: usize,
+    /// Potential panics in the body, those in closures included.
+    pub panics: Vec<PanicSite>,
+    /// `#[test]` function, or declared under `#[cfg(test)]`.
+    pub is_test: bool,
+    /// Carries `#[test]` (or `#[tokio::test]`, ...): the harness runs it.
+    pub is_test_fn: bool,
+    /// `#[cfg(..)]` conditions the function and its enclosing items require.
+    pub cfg: Option<String>,
+    /// Module path of the body, including the function itself.
+    pub scope: Vec<String>,
+    /// Parameters typed `&dyn Trait`, `Box<dyn Trait>`, `impl Trait`, ...: (name, trait path).
+    pub dyn_params: Vec<(String, Vec<String>)>,
+    /// Parameters and `let` bindings of a known concrete type (`c: &Config`,
+    /// `let s = Server::new()`): (name, type name).
+    pub local_types: Vec<(String, String)>,
+    pub calls: Vec<RawCall>,
+    /// Closures in the body, numbered in source order.
+    pub closures: Vec<ClosureSummary>,
+    /// Type parameters of the function, in declaration order.
+    pub type_params: Vec<String>,
+    /// Parameters typed by a type parameter (`t: T`, `t: &mut T`): (input position, parameter).
+    pub generic_args: Vec<(usize, String)>,
+    /// Path calls (including those in closures) that may instantiate generic callees.
+    pub call_sites: Vec<CallSite>,
+    /// Names that may be types the body builds (`Config { .. }`, `Point(..)`,
+    /// `Mode::Fast`), with their line; closures count towards their owner.
+    pub constructs: Vec<(String, usize)>,
+}
+
+/// A struct, enum, union or type alias.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeSummary {
+    pub name: String,
+    pub kind: TypeKind,
+    pub line: usize,
+    pub is_public: bool,
+    pub is_test: bool,
+    pub cfg: Option<String>,
+    pub doc: Option<String>,
+    /// CamelCase names in the types of its fields (variant fields, the
+    /// aliased type), in order: `Vec<Item>` gives `Vec` and `Item`.
+    pub field_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSummary {
+    pub crate_name: String,
+    pub file_path: String,
+    pub content_hash: u64,
+    pub defs: Vec<SymbolDef>,
+    /// `impl Trait for Type` items: (trait path as written, type name,
+    /// module path of the impl).
+    pub trait_impls: Vec<(Vec<String>, String, Vec<String>)>,
+    /// Methods traits declare (provided or not): (trait name, method name,
+    /// module path of the trait).
+    pub trait_methods: Vec<(String, String, Vec<String>)>,
+    /// Named struct fields: (struct name, field name, field type name).
+    pub field_types: Vec<(String, String, String)>,
+    pub uses: Vec<UseEntry>,
+    /// Function nodes in declaration order (nested items follow their parent).
+    pub functions: Vec<FnSummary>,
+    pub types: Vec<TypeSummary>,
+}
+
+impl FileSummary {
+    /// Parse `code` and extract its summary.
+    pub fn extract(crate_name: &str, file_path: &str, code: &str) -> syn::Result<Self> {
+        Self::extract_with_cfg(&SourceFile::new(crate_name, file_path, code), &CfgOptions::default())
+    }
+
+    /// Like [`extract`](Self::extract) for a loaded file, leaving out items
+    /// whose `#[cfg(..)]` the build configuration rules out.
+    pub fn extract_with_cfg(file: &SourceFile, cfg: &CfgOptions) -> syn::Result<Self> {
+        let ast = syn::parse_file(&file.content)?;
+        Ok(Self::from_ast(file, cfg_content_hash(file, cfg), &ast, cfg))
+    }
+
+    pub fn from_ast(file: &SourceFile, content_hash: u64, ast: &syn::File, cfg: &CfgOptions) -> Self {
+        let module_path = file_module_path(&file.path, file.package_dir.as_deref());
+        let mut summary = FileSummary {
+            crate_name: file.crate_name.clone(),
+            file_path: file.path.clone(),
+            content_hash,
+            defs: Vec::new(),
+            trait_impls: Vec::new(),
+            trait_methods: Vec::new(),
+            field_types: Vec::new(),
+            uses: collect_use_entries(&ast.items, &module_path),
+            functions: Vec::new(),
+            types: Vec::new(),
+        };
+        let items: Vec<&Item> = ast.items.iter().collect();
+        let scope = ItemScope { module_path, ..ItemScope::default() };
+        summary.extract_items(&items, &scope, cfg);
+        summary
+    }
+
+    fn extract_items(&mut self, items: &[&Item], scope: &ItemScope, options: &CfgOptions) {
+        for item in items.iter().copied() {
+            match item {
+                Item::Fn(func) => {
+                    let Some(fn_scope) = scope.enter(&func.attrs, options) else { continue };
+                    let is_test = fn_scope.in_test || is_test_item(&func.attrs);
+                    let name = func.sig.ident.to_string();
+                    let id = qualified_fn_id(&self.crate_name, &scope.module_path, &name);
+                    let is_public = matches!(func.vis, Visibility::Public(_));
+                    let sig = self.signature(&func.sig, is_public, &scope.module_path);
+                    self.defs.push(SymbolDef::Function { key: id.clone(), sig });
+
+                    let inner = fn_scope.nested(&name, is_test);
+                    let summary = summarize_fn(id.clone(), id, is_public, inner.module_path.clone(), None, &func.sig, &func.block);
+                    self.functions.push(FnSummary { is_test, is_test_fn: has_test_attr(&func.attrs), cfg: inner.cfg_condition(), doc: doc_summary(&func.attrs), ..summary });
+
+                    self.extract_items(&nested_items(&func.block), &inner, options);
+                }
+                Item::Impl(imp) => {
+                    let Type::Path(tp) = &*imp.self_ty else { continue };
+                    let Some(segment) = tp.path.segments.last() else { continue };
+                    let Some(impl_scope) = scope.enter(&imp.attrs, options) else { continue };
+                    let type_name = segment.ident.to_string();
+
+                    // `impl Trait for Type` -> dynamic dispatch candidates
+                    if let Some((_, trait_path, _)) = &imp.trait_ {
+                        if !trait_path.segments.is_empty() {
+                            let path = trait_path.segments.iter().map(|seg| seg.ident.to_string()).collect();
+                            self.trait_impls.push((path, type_name.clone(), scope.module_path.clone()));
+                        }
+                    }
+
+                    for impl_item in &imp.items {
+                        if let syn::ImplItem::Fn(method) = impl_item {
+                            let Some(method_scope) = impl_scope.enter(&method.attrs, options) else { continue };
+                            let is_test = method_scope.in_test || is_test_item(&method.attrs);
+                            let method_name = method.sig.ident.to_string();
+                            let sig = self.signature(&method.sig, matches!(method.vis, Visibility::Public(_)), &scope.module_path);
+                            let id = sig.method_id(&type_name);
+                            self.defs.push(SymbolDef::Method { type_name: type_name.clone(), sig });
+
+                            let label = format!("{}::{}", type_name, method_name);
+                            // Trait methods are callable wherever the trait is
+                            let is_public = imp.trait_.is_some() || matches!(method.vis, Visibility::Public(_));
+                            let inner = method_scope.nested(&type_name, is_test).nested(&method_name, is_test);
+                            let summary = summarize_fn(id, label, is_public, inner.module_path.clone(), Some(&type_name), &method.sig, &method.block);
+                            self.functions.push(FnSummary { is_test, is_test_fn: has_test_attr(&method.attrs), cfg: inner.cfg_condition(), doc: doc_summary(&method.attrs), ..summary });
+
+                            self.extract_items(&nested_items(&method.block), &inner, options);
+                        }
+                    }
+                }
+                Item::Trait(item) => {
+                    let Some(trait_scope) = scope.enter(&item.attrs, options) else { continue };
+                    let trait_name = item.ident.to_string();
+                    let is_public = matches!(item.vis, Visibility::Public(_));
+                    for trait_item in &item.items {
+                        let syn::TraitItem::Fn(method) = trait_item else { continue };
+                        let Some(method_scope) = trait_scope.enter(&method.attrs, options) else { continue };
+                        let is_test = method_scope.in_test || is_test_item(&method.attrs);
+                        let method_name = method.sig.ident.to_string();
+                        self.trait_methods.push((trait_name.clone(), method_name.clone(), scope.module_path.clone()));
+
+                        let id = method_id(&self.crate_name, &scope.module_path, &trait_name, &method_name);
+                        let label = format!("{}::{}", trait_name, method_name);
+                        let inner = method_scope.nested(&trait_name, is_test).nested(&method_name, is_test);
+                        // Declarations get an empty body; trait methods are
+                        // callable wherever the trait is visible
+                        let empty = syn::Block { brace_token: Default::default(), stmts: Vec::new() };
+                        let body = method.default.as_ref().unwrap_or(&empty);
+                        let summary = summarize_fn(id, label, is_public, inner.module_path.clone(), Some(&trait_name), &method.sig, body);
+                        self.functions.push(FnSummary { is_test, cfg: inner.cfg_condition(), doc: doc_summary(&method.attrs), ..summary });
+
+                        self.extract_items(&nested_items(body), &inner, options);
+                    }
+                }
+                Item::Struct(item) => {
+                    let Some(type_scope) = scope.enter(&item.attrs, options) else { continue };
+                    let type_name = item.ident.to_string();
+                    for field in &item.fields {
+                        if let (Some(ident), Some(ty)) = (&field.ident, type_name_of(&field.ty)) {
+                            self.field_types.push((type_name.clone(), ident.to_string(), ty));
+                        }
+                    }
+                    let fields = item.fields.iter().map(|f| &f.ty);
+                    self.push_type(&item.ident, TypeKind::Struct, &item.vis, &item.attrs, &type_scope, fields);
+                }
+                Item::Enum(item) => {
+                    let Some(type_scope) = scope.enter(&item.attrs, options) else { continue };
+                    let fields = item.variants.iter().flat_map(|v| v.fields.iter()).map(|f| &f.ty);
+                    self.push_type(&item.ident, TypeKind::Enum, &item.vis, &item.attrs, &type_scope, fields);
+                }
+                Item::Union(item) => {
+                    let Some(type_scope) = scope.enter(&item.attrs, options) else { continue };
+                    let fields = item.fields.named.iter().map(|f| &f.ty);
+                    self.push_type(&item.ident, TypeKind::Union, &item.vis, &item.attrs, &type_scope, fields);
+                }
+                Item::Type(item) => {
+                    let Some(type_scope) = scope.enter(&item.attrs, options) else { continue };
+                    self.push_type(&item.ident, TypeKind::Alias, &item.vis, &item.attrs, &type_scope, std::iter::once(&*item.ty));
+                }
+                Item::Mod(module) => {
+                    if let Some((_, content)) = &module.content {
+                        let Some(mod_scope) = scope.enter(&module.attrs, options) else { continue };
+                        let in_test = mod_scope.in_test;
+                        let content: Vec<&Item> = content.iter().collect();
+                        self.extract_items(&content, &mod_scope.nested(&module.ident.to_string(), in_test), options);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn push_type<'t>(
+        &mut self,
+        ident: &syn::Ident,
+        kind: TypeKind,
+        vis: &Visibility,
+        attrs: &[syn::Attribute],
+        scope: &ItemScope,
+        fields: impl Iterator<Item = &'t Type>,
+    ) {
+        let mut field_types = Vec::new();
+        for ty in fields {
+            type_names(ty, &mut field_types);
+        }
+        let mut seen = HashSet::new();
+        field_types.retain(|name| seen.insert(name.clone()));
+        self.types.push(TypeSummary {
+            name: ident.to_string(),
+            kind,
+            line: ident.span().start().line,
+            is_public: matches!(vis, Visibility::Public(_)),
+            is_test: scope.in_test,
+            cfg: scope.cfg_condition(),
+            doc: doc_summary(attrs),
+            field_types,
+        });
+    }
+
+    fn signature(&self, sig: &syn::Signature, is_public: bool, module: &[String]) -> FunctionSignature {
+        let receiver = sig.inputs.first().and_then(|arg| match arg {
+            syn::FnArg::Receiver(r) => {
+                if r.reference.is_some() { Some("&self".to_string()) } else { Some("self".to_string()) }
+            }
+            _ => None,
+        });
+        FunctionSignature {
+            name: sig.ident.to_string(),
+            is_public,
+            receiver,
+            location: format!("{}:{}", self.file_path, sig.ident.span().start().line),
+            crate_name: self.crate_name.clone(),
+            module: module.to_vec(),
+        }
+    }
+}
+
+/// `self_type` is the enclosing impl's type for methods; `self.m()` and
+/// `Self::f()` in the body are recorded against it.
+fn summarize_fn(
+    id: String,
+    label: String,
+    is_public: bool,
+    scope: Vec<String>,
+    self_type: Option<&str>,
+    sig: &syn::Signature,
+    block: &syn::Block,
+) -> FnSummary {
+    let type_params: Vec<String> = sig.generics.type_params().map(|p| p.ident.to_string()).collect();
+    let mut dyn_params = Vec::new();
+    let mut local_types = Vec::new();
+    let mut generic_args = Vec::new();
+    let mut bound = HashSet::new();
+    for (position, input) in sig.inputs.iter().enumerate() {
+        if let syn::FnArg::Typed(pat_type) = input {
+            pat_names(&pat_type.pat, &mut bound);
+            if let Some(param) = type_name_of(&pat_type.ty).filter(|t| type_params.contains(t)) {
+                generic_args.push((position, param));
+            }
+            if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                if let Some(trait_path) = dyn_trait_path(&pat_type.ty) {
+                    dyn_params.push((pat_ident.ident.to_string(), trait_path));
+                } else if let Some(type_name) = type_name_of(&pat_type.ty) {
+                    local_types.push((pat_ident.ident.to_string(), type_name));
+                }
+            }
+        }
+    }
+
+    let mut recorder = BodyRecorder {
+        owner_id: &id,
+        self_type,
+        closures: Vec::new(),
+        async_blocks: 0,
+        locals: Vec::new(),
+        bound,
+        call_sites: Vec::new(),
+        metrics: FnMetrics::new(),
+        nesting: 0,
+        unsafe_blocks: 0,
+        panics: Vec::new(),
+        constructs: Vec::new(),
+    };
+    let mut calls = Vec::new();
+    recorder.block(block, &mut calls);
+    let (closures, call_sites, metrics, unsafe_blocks, panics, constructs) =
+        (recorder.closures, recorder.call_sites, recorder.metrics, recorder.unsafe_blocks, recorder.panics, recorder.constructs);
+    local_types.extend(recorder.locals);
+
+    let (is_async, is_unsafe) = (sig.asyncness.is_some(), sig.unsafety.is_some());
+    FnSummary {
+        id, label, signature: signature_text(sig), doc: None, line: sig.fn_token.span().start().line, metrics, is_public, is_async, is_unsafe, unsafe_blocks, panics, is_test: false, is_test_fn: false, cfg: None, scope, dyn_params, local_types, calls, closures,
+        type_params, generic_args, call_sites, constructs,
+    }
+}
+
+/// `sig` on one line with its generics and `where` clause, line comments
+/// dropped and the trailing commas of multi-line parameter lists removed.
+fn signature_text(sig: &syn::Signature) -> String {
+    let Some(text) = sig.span().source_text() else {
+        return format!("fn {}", sig.ident);
+    };
+    let words: Vec<&str> = text.lines()
+        .flat_map(|line| line.split("//").next().unwrap_or_default().split_whitespace())
+        .collect();
+    words.join(" ")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(",)", ")")
+        .trim_end_matches(',')
+        .to_string()
+}
+
+/// CamelCase type names anywhere in `ty`: `Vec<Arc<Item>>` gives `Vec`,
+/// `Arc` and `Item`; module segments are skipped.
+fn type_names(ty: &Type, out: &mut Vec<String>) {
+    match ty {
+        Type::Path(tp) if tp.qself.is_none() => {
+            if let Some(last) = tp.path.segments.last().map(|s| s.ident.to_string()) {
+                if last.starts_with(|c: char| c.is_ascii_uppercase()) {
+                    out.push(last);
+                }
+            }
+            for segment in &tp.path.segments {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        if let syn::GenericArgument::Type(inner) = arg {
+                            type_names(inner, out);
+                        }
+                    }
+                }
+            }
+        }
+        Type::Reference(r) => type_names(&r.elem, out),
+        Type::Paren(p) => type_names(&p.elem, out),
+        Type::Group(g) => type_names(&g.elem, out),
+        Type::Array(a) => type_names(&a.elem, out),
+        Type::Slice(s) => type_names(&s.elem, out),
+        Type::Ptr(p) => type_names(&p.elem, out),
+        Type::Tuple(t) => t.elems.iter().for_each(|elem| type_names(elem, out)),
+        _ => {}
+    }
+}
+
+/// Name of the type a value of `ty` derefs to: `Client` for `Client`,
+/// `&mut Client`, `Arc<Client>` or `crate::net::Client`.
+fn type_name_of(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Reference(r) => type_name_of(&r.elem),
+        syn::Type::Paren(p) => type_name_of(&p.elem),
+        syn::Type::Path(tp) if tp.qself.is_none() => {
+            let last = tp.path.segments.last()?;
+            if !matches!(last.ident.to_string().as_str(), "Box" | "Arc" | "Rc") {
+                return Some(last.ident.to_string());
+            }
+            if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+                for arg in &args.args {
+                    if let syn::GenericArgument::Type(inner) = arg {
+                        return type_name_of(inner);
+                    }
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// First non-empty line of the item's `///` (or `#[doc = ".."]`) comment.
+fn doc_summary(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            syn::Meta::NameValue(syn::MetaNameValue { value: Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(text), .. }), .. }) => Some(text.value()),
+            _ => None,
+        })
+        .find_map(|text| text.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string))
+}
+
+/// `#[cfg(..)]` conditions only met in test builds: `cfg(test)`,
+/// `cfg(all(test, unix))`, ...
+fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+    let no_tests = CfgOptions { test: Some(false), ..CfgOptions::default() };
+    CfgExpr::from_attrs(attrs).is_some_and(|cfg| cfg.eval(&no_tests) == Some(false))
+}
+
+/// `#[test]`, `#[tokio::test]`, ... or `#[cfg(test)]`.
+fn is_test_item(attrs: &[syn::Attribute]) -> bool {
+    is_cfg_test(attrs) || has_test_attr(attrs)
+}
+
+/// `#[test]`, `#[tokio::test]`, `#[async_std::test]`, ...
+fn has_test_attr(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|a| a.path().segments.last().is_some_and(|s| s.ident == "test"))
+}
+
+/// `tokio::spawn`, `task::spawn_blocking`, `thread::spawn`, `spawn_local`, ...
+fn is_spawn_fn(path: &syn::Path) -> bool {
+    path.segments.last().is_some_and(|s| matches!(s.ident.to_string().as_str(), "spawn" | "spawn_blocking" | "spawn_local"))
+}
+
+/// `a.b.c` -> `("a", ["b", "c"])`; `None` unless every step is a named field.
+fn field_chain(expr: &Expr) -> Option<(String, Vec<String>)> {
+    match expr {
+        Expr::Field(field) => {
+            let syn::Member::Named(name) = &field.member else { return None };
+            let (base, mut fields) = field_chain(&field.base)?;
+            fields.push(name.to_string());
+            Some((base, fields))
+        }
+        Expr::Path(expr_path) => expr_path.path.get_ident().map(|i| (i.to_string(), Vec::new())),
+        Expr::Paren(paren) => field_chain(&paren.expr),
+        _ => None,
+    }
+}
+
+/// Extract the trait path from `&dyn Trait`, `Box<dyn Trait>`, `impl Trait` and friends.
+fn dyn_trait_path(ty: &syn::Type) -> Option<Vec<String>> {
+    match ty {
+        syn::Type::Reference(r) => dyn_trait_path(&r.elem),
+        syn::Type::Paren(p) => dyn_trait_path(&p.elem),
+        syn::Type::TraitObject(obj) => first_trait_bound(&obj.bounds),
+        syn::Type::ImplTrait(imp) => first_trait_bound(&imp.bounds),
+        // Smart pointers wrapping a trait object: Box<dyn T>, Arc<dyn T>, Rc<dyn T>
+        syn::Type::Path(tp) => {
+            let last = tp.path.segments.last()?;
+            if !matches!(last.ident.to_string().as_str(), "Box" | "Arc" | "Rc") {
+                return None;
+            }
+            if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+                for arg in &args.args {
+                    if let syn::GenericArgument::Type(inner) = arg {
+                        return dyn_trait_path(inner);
+                    }
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+fn first_trait_bound(bounds: &syn::punctuated::Punctuated<syn::TypeParamBound, syn::Token![+]>) -> Option<Vec<String>> {
+    bounds.iter().find_map(|b| match b {
+        syn::TypeParamBound::Trait(t) => Some(t.path.segments.iter().map(|s| s.ident.to_string()).collect()),
+        _ => None,
+    })
+}
+
+/// Names a pattern binds: `x`, `(a, b)`, `Some(v)`, `Point { x, .. }`.
+fn pat_names(pat: &syn::Pat, out: &mut HashSet<String>) {
+    match pat {
+        syn::Pat::Ident(p) => {
+            out.insert(p.ident.to_string());
+            if let Some((_, sub)) = &p.subpat {
+                pat_names(sub, out);
+            }
+        }
+        syn::Pat::Tuple(p) => p.elems.iter().for_each(|e| pat_names(e, out)),
+        syn::Pat::TupleStruct(p) => p.elems.iter().for_each(|e| pat_names(e, out)),
+        syn::Pat::Slice(p) => p.elems.iter().for_each(|e| pat_names(e, out)),
+        syn::Pat::Struct(p) => p.fields.iter().for_each(|f| pat_names(&f.pat, out)),
+        syn::Pat::Or(p) => p.cases.iter().for_each(|c| pat_names(c, out)),
+        syn::Pat::Reference(p) => pat_names(&p.pat, out),
+        syn::Pat::Type(p) => pat_names(&p.pat, out),
+        syn::Pat::Paren(p) => pat_names(&p.pat, out),
+        _ => {}
+    }
+}
+
+/// Walks one function body recording calls; closures get their own call lists.
+struct BodyRecorder<'a> {
+    owner_id: &'a str,
+    /// Type named by `Self` (and of `self`) inside an impl block.
+    self_type: Option<&'a str>,
+    closures: Vec<ClosureSummary>,
+    /// Spawned `async` blocks seen so far (numbered separately from closures).
+    async_blocks: usize,
+    /// `let` bindings whose type is evident from the statement.
+    locals: Vec<(String, String)>,
+    /// Every name a parameter or pattern in the body binds; such a path is a
+    /// value, not a function passed along.
+    bound: HashSet<String>,
+    call_sites: Vec<CallSite>,
+    metrics: FnMetrics,
+    /// Control-flow blocks enclosing the expression being walked.
+    nesting: usize,
+    unsafe_blocks: usize,
+    panics: Vec<PanicSite>,
+    constructs: Vec<(String, usize)>,
+}
+
+impl BodyRecorder<'_> {
+    /// Next closure id within the owning function:
+    /// `crate::foo` -> `crate::foo::{closure#0}`, `T::m@crate` -> `T::m::{closure#0}@crate`.
+    fn next_closure_id(&self) -> String {
+        let closures = self.closures.iter().filter(|c| !c.is_async).count();
+        self.synthetic_id(&format!("{{closure#{}}}", closures))
+    }
+
+    fn synthetic_id(&self, suffix: &str) -> String {
+        match self.owner_id.rsplit_once('@') {
+            Some((path, krate)) => format!("{}::{}@{}", path, suffix, krate),
+            None => format!("{}::{}", self.owner_id, suffix),
+        }
+    }
+
+    /// Record a synthetic node for a closure or async block body and return its id.
+    fn synthetic_node(&mut self, id: String, line: usize, is_async: bool, body: impl FnOnce(&mut Self, &mut Vec<RawCall>)) -> String {
+        let label = id.rsplit_once('@').map(|(p, _)| p).unwrap_or(&id).to_string();
+        // Reserve the slot first so nested closures get later indices
+        self.closures.push(ClosureSummary { id: id.clone(), label, line, is_async, calls: Vec::new() });
+        let slot = self.closures.len() - 1;
+        let mut calls = Vec::new();
+        body(self, &mut calls);
+        self.closures[slot].calls = calls;
+        id
+    }
+
+    /// Record the task passed to a spawn function. An `async` block becomes
+    /// its own `{async#N}` node; for closures and calls (`spawn(handle(c))`)
+    /// the first recorded target is the task itself.
+    fn spawned(&mut self, arg: &Expr, out: &mut Vec<RawCall>) {
+        let mut calls = Vec::new();
+        if let Expr::Async(expr_async) = arg {
+            let id = self.synthetic_id(&format!("{{async#{}}}", self.async_blocks));
+            self.async_blocks += 1;
+            let id = self.synthetic_node(id, expr_async.span().start().line, true, |this, calls| this.block(&expr_async.block, calls));
+            calls.push(RawCall { target: RawTarget::Node(id), line: expr_async.span().start().line });
+        } else {
+            self.expr(arg, &mut calls);
+        }
+        if matches!(arg, Expr::Async(_) | Expr::Closure(_) | Expr::Call(_) | Expr::MethodCall(_)) {
+            if let Some(task) = calls.first_mut() {
+                let target = std::mem::replace(&mut task.target, RawTarget::Node(String::new()));
+                task.target = RawTarget::Spawn(Box::new(target));
+            }
+        }
+        out.extend(calls);
+    }
+
+    /// Walk a control-flow body one nesting level deeper.
+    fn nested(&mut self, walk: impl FnOnce(&mut Self)) {
+        self.nesting += 1;
+        self.metrics.nesting = self.metrics.nesting.max(self.nesting);
+        walk(self);
+        self.nesting -= 1;
+    }
+
+    fn block(&mut self, block: &syn::Block, out: &mut Vec<RawCall>) {
+        self.metrics.statements += block.stmts.len();
+        for stmt in &block.stmts {
+            self.stmt(stmt, out);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, out: &mut Vec<RawCall>) {
+        match stmt {
+            Stmt::Expr(expr, _) => self.expr(expr, out),
+            Stmt::Macro(stmt_mac) => self.macro_args(&stmt_mac.mac, out),
+            Stmt::Local(local) => {
+                pat_names(&local.pat, &mut self.bound);
+                if let Some(binding) = self.binding_type(&local.pat, local.init.as_ref().map(|i| &*i.expr)) {
+                    self.locals.push(binding);
+                }
+                if let Some(init) = &local.init {
+                    self.expr(&init.expr, out);
+                    // `let ... else { .. }`
+                    if let Some((_, diverge)) = &init.diverge {
+                        self.expr(diverge, out);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Type of `let name[: Ty] = init`, from the annotation or a constructor-like
+    /// initializer: `Type::new(..)`, `Type::open(..)?`, `Type { .. }`, `Type`.
+    fn binding_type(&self, pat: &syn::Pat, init: Option<&Expr>) -> Option<(String, String)> {
+        let (name, annotated) = match pat {
+            syn::Pat::Ident(p) => (p.ident.to_string(), None),
+            syn::Pat::Type(p) => match &*p.pat {
+                syn::Pat::Ident(inner) => (inner.ident.to_string(), type_name_of(&p.ty)),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let type_name = annotated.or_else(|| init.and_then(|e| self.constructed_type(e)))?;
+        Some((name, type_name))
+    }
+
+    fn constructed_type(&self, expr: &Expr) -> Option<String> {
+        let type_segment = |path: &syn::Path, skip_last: bool| {
+            let mut segments = path.segments.iter().rev().skip(usize::from(skip_last));
+            let ident = segments.next()?.ident.to_string();
+            match (ident.as_str(), self.self_type) {
+                ("Self", Some(ty)) => Some(ty.to_string()),
+                // Types are CamelCase; `module::func()` tells us nothing
+                _ if ident.starts_with(|c: char| c.is_ascii_uppercase()) => Some(ident),
+                _ => None,
+            }
+        };
+        match expr {
+            Expr::Call(call) => match &*call.func {
+                Expr::Path(p) if p.path.segments.len() >= 2 => type_segment(&p.path, true),
+                _ => None,
+            },
+            Expr::Try(expr_try) => self.constructed_type(&expr_try.expr),
+            Expr::Struct(lit) => type_segment(&lit.path, false),
+            Expr::Path(p) => type_segment(&p.path, false),
+            Expr::Paren(p) => self.constructed_type(&p.expr),
+            _ => None,
+        }
+    }
+
+    /// Remember the type a struct literal, tuple struct call or enum variant
+    /// path builds: `Config { .. }`, `Point(..)`, `Mode::Fast` and `Self`.
+    fn record_construct(&mut self, path: &syn::Path) {
+        let is_type = |name: &str| name.starts_with(|c: char| c.is_ascii_uppercase()) && name.contains(|c: char| c.is_ascii_lowercase());
+        let mut segments = path.segments.iter().rev().map(|s| s.ident.to_string());
+        let Some(last) = segments.next().filter(|name| is_type(name)) else { return };
+        let name = match segments.next() {
+            Some(parent) if is_type(&parent) => parent,
+            _ => last,
+        };
+        self.constructs.push((self.type_ident(name), path.span().start().line));
+    }
+
+    /// `Self` resolved to the impl type, other names unchanged.
+    fn type_ident(&self, ident: String) -> String {
+        match self.self_type {
+            Some(ty) if ident == "Self" => ty.to_string(),
+            _ => ident,
+        }
+    }
+
+    /// Target of a path call. `Trait::method(&x)` and `<T as Trait>::method(x)`
+    /// keep the receiver so the linker can pick the impl; `<T>::f()` is `T::f()`.
+    fn call_target(&self, expr_path: &syn::ExprPath, first_arg: Option<&Expr>) -> Option<RawTarget> {
+        let mut segments = self.path_segments(&expr_path.path);
+        if let Some(qself) = &expr_path.qself {
+            let self_ty = type_name_of(&qself.ty).map(|t| self.type_ident(t))?;
+            if qself.position == 0 {
+                segments.insert(0, self_ty);
+                return Some(RawTarget::Path(segments));
+            }
+            return Some(RawTarget::AssocCall { path: segments, receiver: Some(self_ty) });
+        }
+        if segments.len() >= 2 {
+            if let Some(receiver) = first_arg.and_then(|arg| self.arg_name(arg)) {
+                return Some(RawTarget::AssocCall { path: segments, receiver: Some(receiver) });
+            }
+        }
+        (!segments.is_empty()).then_some(RawTarget::Path(segments))
+    }
+
+    /// Remember what `path::<..>(args)` tells about the callee's type
+    /// parameters, if anything.
+    fn record_call_site(&mut self, expr_path: &syn::ExprPath, args: &syn::punctuated::Punctuated<Expr, syn::Token![,]>) {
+        if expr_path.qself.is_some() {
+            return;
+        }
+        let type_args: Vec<Option<String>> = match expr_path.path.segments.last().map(|s| &s.arguments) {
+            Some(syn::PathArguments::AngleBracketed(generic)) => generic.args.iter()
+                .filter_map(|arg| match arg {
+                    syn::GenericArgument::Type(ty) => Some(type_name_of(ty).map(|t| self.type_ident(t))),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let args: Vec<Option<String>> = args.iter()
+            .map(|arg| self.constructed_type(arg).or_else(|| self.arg_name(arg)))
+            .collect();
+        if type_args.iter().chain(&args).any(Option::is_some) {
+            let path = self.path_segments(&expr_path.path);
+            self.call_sites.push(CallSite { path, type_args, args });
+        }
+    }
+
+    /// Segment names of `path`, with a leading `Self` resolved to the impl type.
+    fn path_segments(&self, path: &syn::Path) -> Vec<String> {
+        let mut segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        if let Some(first) = segments.first_mut() {
+            *first = self.type_ident(std::mem::take(first));
+        }
+        segments
+    }
+
+    /// `x`, `&x`, `&mut x` -> `x`; `self` becomes the impl type.
+    fn arg_name(&self, arg: &Expr) -> Option<String> {
+        match arg {
+            Expr::Reference(r) => self.arg_name(&r.expr),
+            Expr::Paren(p) => self.arg_name(&p.expr),
+            Expr::Path(p) if p.path.is_ident("self") => Some(self.self_type.unwrap_or("self").to_string()),
+            Expr::Path(p) => p.path.get_ident().map(|i| i.to_string()),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, out: &mut Vec<RawCall>) {
+        let at = |target: RawTarget, node: &dyn Spanned| RawCall { target, line: node.span().start().line };
+        match expr {
+            Expr::Call(expr_call) => {
+                let mut spawn = false;
+                if let Expr::Path(ref expr_path) = *expr_call.func {
+                    spawn = is_spawn_fn(&expr_path.path);
+                    self.record_construct(&expr_path.path);
+                    if let Some(target) = self.call_target(expr_path, expr_call.args.first()) {
+                        out.push(at(target, expr_call));
+                    }
+                    self.record_call_site(expr_path, &expr_call.args);
+                } else {
+                    // `(self.callback)()`, `make()()`: calls inside the callee expression
+                    self.expr(&expr_call.func, out);
+                }
+                for arg in &expr_call.args {
+                    if spawn {
+                        self.spawned(arg, out);
+                    } else {
+                        self.expr(arg, out);
+                    }
+                }
+            }
+            Expr::MethodCall(expr_method) => {
+                // 嘗試靜態取得 receiver 型別 (Best effort inference)
+                let receiver = match &*expr_method.receiver {
+                    Expr::Path(expr_path) if expr_path.path.is_ident("self") => {
+                        Some(self.self_type.unwrap_or("self").to_string())
+                    }
+                    Expr::Path(expr_path) => expr_path.path.segments.last().map(|s| s.ident.to_string()),
+                    _ => None,
+                };
+                let method = expr_method.method.to_string();
+                match (method.as_str(), expr_method.args.len()) {
+                    ("unwrap", 0) => self.panic_at(PanicKind::Unwrap, &expr_method.method),
+                    ("expect", 1) => self.panic_at(PanicKind::Expect, &expr_method.method),
+                    _ => {}
+                }
+                let target = match field_chain(&expr_method.receiver) {
+                    Some((base, fields)) if !fields.is_empty() => {
+                        let base = match (base.as_str(), self.self_type) {
+                            ("self", Some(ty)) => ty.to_string(),
+                            _ => base,
+                        };
+                        RawTarget::FieldMethod { base, fields, method }
+                    }
+                    _ => RawTarget::Method { receiver, method },
+                };
+                // Point at the method name, not the start of a multi-line receiver chain
+                out.push(at(target, &expr_method.method));
+                for arg in &expr_method.args {
+                    self.expr(arg, out);
+                }
+                // A plain receiver is a value being called on, not a callback
+                if !matches!(*expr_method.receiver, Expr::Path(_)) {
+                    self.expr(&expr_method.receiver, out);
+                }
+            }
+            Expr::Block(expr_block) => self.block(&expr_block.block, out),
+            Expr::Closure(closure) => {
+                // Synthetic node: owner -> closure -> whatever the closure body calls
+                let id = self.next_closure_id();
+                let is_async = closure.asyncness.is_some();
+                for input in &closure.inputs {
+                    pat_names(input, &mut self.bound);
+                }
+                let id = self.synthetic_node(id, closure.span().start().line, is_async, |this, calls| {
+                    this.nested(|this| this.expr(&closure.body, calls))
+                });
+                out.push(at(RawTarget::Node(id), closure));
+            }
+            Expr::If(expr_if) => {
+                out.push(at(RawTarget::Node("if(...)".to_string()), expr_if));
+                self.metrics.complexity += 1;
+                self.expr(&expr_if.cond, out);
+                self.nested(|this| this.block(&expr_if.then_branch, out));
+                match expr_if.else_branch.as_ref().map(|(_, e)| &**e) {
+                    // `else if` continues the chain at the same level
+                    Some(else_if @ Expr::If(_)) => self.expr(else_if, out),
+                    Some(else_branch) => self.nested(|this| this.expr(else_branch, out)),
+                    None => {}
+                }
+            }
+            Expr::Match(expr_match) => {
+                out.push(at(RawTarget::Node("match(...)".to_string()), expr_match));
+                self.metrics.complexity += expr_match.arms.len().saturating_sub(1);
+                self.expr(&expr_match.expr, out);
+                for (i, arm) in expr_match.arms.iter().enumerate() {
+                    pat_names(&arm.pat, &mut self.bound);
+                    out.push(at(RawTarget::Node(format!("match_arm_{}", i)), arm));
+                    if let Some((_, guard)) = &arm.guard {
+                        self.metrics.complexity += 1;
+                        self.expr(guard, out);
+                    }
+                    self.nested(|this| this.expr(&arm.body, out));
+                }
+            }
+            // Everything else only matters for the calls nested inside it
+            Expr::While(expr_while) => {
+                self.metrics.complexity += 1;
+                self.expr(&expr_while.cond, out);
+                self.nested(|this| this.block(&expr_while.body, out));
+            }
+            Expr::ForLoop(expr_for) => {
+                self.metrics.complexity += 1;
+                pat_names(&expr_for.pat, &mut self.bound);
+                self.expr(&expr_for.expr, out);
+                self.nested(|this| this.block(&expr_for.body, out));
+            }
+            Expr::Loop(expr_loop) => self.nested(|this| this.block(&expr_loop.body, out)),
+            Expr::Unsafe(expr_unsafe) => {
+                self.unsafe_blocks += 1;
+                self.block(&expr_unsafe.block, out);
+            }
+            Expr::Async(expr_async) => self.block(&expr_async.block, out),
+            Expr::TryBlock(expr_try) => self.block(&expr_try.block, out),
+            Expr::Const(expr_const) => self.block(&expr_const.block, out),
+            Expr::Return(ret) => self.opt_expr(ret.expr.as_deref(), out),
+            Expr::Break(brk) => self.opt_expr(brk.expr.as_deref(), out),
+            Expr::Yield(y) => self.opt_expr(y.expr.as_deref(), out),
+            Expr::Try(expr_try) => {
+                self.metrics.complexity += 1;
+                self.expr(&expr_try.expr, out);
+            }
+            Expr::Await(expr_await) => self.expr(&expr_await.base, out),
+            Expr::Field(field) if !matches!(*field.base, Expr::Path(_)) => self.expr(&field.base, out),
+            // Possibly a function passed as a value
+            Expr::Path(expr_path) if expr_path.qself.is_none() => {
+                self.record_construct(&expr_path.path);
+                let local = expr_path.path.get_ident().is_some_and(|ident| self.bound.contains(&ident.to_string()));
+                if !local {
+                    out.push(at(RawTarget::Reference(self.path_segments(&expr_path.path)), expr_path));
+                }
+            }
+            Expr::Paren(paren) => self.expr(&paren.expr, out),
+            Expr::Group(group) => self.expr(&group.expr, out),
+            Expr::Reference(reference) => self.expr(&reference.expr, out),
+            Expr::Unary(unary) => self.expr(&unary.expr, out),
+            Expr::Cast(cast) => self.expr(&cast.expr, out),
+            Expr::Let(expr_let) => {
+                pat_names(&expr_let.pat, &mut self.bound);
+                self.expr(&expr_let.expr, out);
+            }
+            Expr::Binary(bin) => {
+                if matches!(bin.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) {
+                    self.metrics.complexity += 1;
+                }
+                self.expr(&bin.left, out);
+                self.expr(&bin.right, out);
+            }
+            Expr::Assign(assign) => {
+                self.expr(&assign.left, out);
+                self.expr(&assign.right, out);
+            }
+            Expr::Index(index) => {
+                self.panic_at(PanicKind::Index, &index.index);
+                self.expr(&index.expr, out);
+                self.expr(&index.index, out);
+            }
+            Expr::Range(range) => {
+                self.opt_expr(range.start.as_deref(), out);
+                self.opt_expr(range.end.as_deref(), out);
+            }
+            Expr::Repeat(repeat) => {
+                self.expr(&repeat.expr, out);
+                self.expr(&repeat.len, out);
+            }
+            Expr::Array(array) => array.elems.iter().for_each(|e| self.expr(e, out)),
+            Expr::Tuple(tuple) => tuple.elems.iter().for_each(|e| self.expr(e, out)),
+            Expr::Struct(lit) => {
+                self.record_construct(&lit.path);
+                for field in &lit.fields {
+                    self.expr(&field.expr, out);
+                }
+                self.opt_expr(lit.rest.as_deref(), out);
+            }
+            Expr::Macro(expr_mac) => self.macro_args(&expr_mac.mac, out),
+            // Literals and plain paths contain no calls
+            _ => {}
+        }
+    }
+
+    /// Best effort for unexpanded macros: if the arguments parse as
+    /// comma-separated expressions (`println!`, `assert_eq!`, `vec!`, ...) or
+    /// as statements, record the calls inside them. Anything else is skipped.
+    fn macro_args(&mut self, mac: &syn::Macro, out: &mut Vec<RawCall>) {
+        use syn::parse::Parser;
+        use syn::punctuated::Punctuated;
+
+        if let Some(kind) = mac.path.segments.last().and_then(|s| PanicKind::of_macro(&s.ident.to_string())) {
+            self.panic_at(kind, &mac.path);
+        }
+        let as_exprs = Punctuated::<Expr, syn::Token![,]>::parse_terminated;
+        if let Ok(args) = as_exprs.parse2(mac.tokens.clone()) {
+            args.iter().for_each(|arg| self.expr(arg, out));
+        } else if let Ok(stmts) = syn::Block::parse_within.parse2(mac.tokens.clone()) {
+            stmts.iter().for_each(|stmt| self.stmt(stmt, out));
+        }
+    }
+
+    fn panic_at(&mut self, kind: PanicKind, node: &dyn Spanned) {
+        self.panics.push(PanicSite { kind, line: node.span().start().line });
+    }
+
+    fn opt_expr(&mut self, expr: Option<&Expr>, out: &mut Vec<RawCall>) {
+        if let Some(expr) = expr {
+            self.expr(expr, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_hasher() {
+        // FNV-1a reference value, the same on every build
+        let mut hasher = StableHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_extract_summary() {
+        let code = "use crate::util::helper;\n\
+                    pub fn run(op: &dyn Op) {\n\
+                        helper();\n\
+                        op.apply();\n\
+                        let f = || helper();\n\
+                    }\n\
+                    struct S;\n\
+                    impl Op for S {\n\
+                        ///\n\
+                        /// Applies `self`.\n\
+                        /// Does nothing.\n\
+                        fn apply(&self) {}\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        assert_eq!(summary.uses.len(), 1);
+        assert_eq!(summary.uses[0].path, vec!["crate", "util", "helper"]);
+        assert_eq!(summary.trait_impls, vec![(vec!["Op".to_string()], "S".to_string(), Vec::new())]);
+        assert_eq!(summary.defs.len(), 2);
+
+        let ids: Vec<&str> = summary.functions.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::run", "S::apply@app"]);
+
+        let run = &summary.functions[0];
+        assert!(run.is_public);
+        assert_eq!(run.dyn_params, vec![("op".to_string(), vec!["Op".to_string()])]);
+        assert_eq!(run.calls, vec![
+            RawCall { target: RawTarget::Path(vec!["helper".to_string()]), line: 3 },
+            RawCall { target: RawTarget::Method { receiver: Some("op".to_string()), method: "apply".to_string() }, line: 4 },
+            RawCall { target: RawTarget::Node("app::run::{closure#0}".to_string()), line: 5 },
+        ]);
+        assert_eq!(run.closures[0].calls.len(), 1);
+        // Trait impl methods count as public
+        assert!(summary.functions[1].is_public);
+        assert_eq!(run.signature, "fn run(op: &dyn Op)");
+        assert_eq!(summary.functions[1].signature, "fn apply(&self)");
+        assert_eq!(run.doc, None);
+        assert_eq!(summary.functions[1].doc.as_deref(), Some("Applies `self`."));
+    }
+
+    #[test]
+    fn test_signature_text() {
+        let code = "pub async fn fetch<'a, T: Into<String>>(\n    url: &'a str, // where to\n    body: T,\n) -> Result<Vec<u8>, Error>\nwhere\n    T: Send,\n{\n    todo!()\n}\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+        assert_eq!(summary.functions[0].signature, "async fn fetch<'a, T: Into<String>>(url: &'a str, body: T) -> Result<Vec<u8>, Error> where T: Send");
+    }
+
+    #[test]
+    fn test_self_calls_use_impl_type() {
+        let code = "struct Engine;\n\
+                    impl Engine {\n\
+                        fn new() -> Self { Self::default_config(); Engine }\n\
+                        fn run(&self) { self.step(); }\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        assert_eq!(summary.functions[0].calls[0].target, RawTarget::Path(vec!["Engine".to_string(), "default_config".to_string()]));
+        assert_eq!(summary.functions[1].calls[0].target, RawTarget::Method { receiver: Some("Engine".to_string()), method: "step".to_string() });
+    }
+
+    #[test]
+    fn test_field_types_and_field_calls() {
+        let code = "struct App { client: std::sync::Arc<Client>, retries: u32 }\n\
+                    impl App {\n\
+                        fn run(&self, cfg: &Config) { self.client.send(); cfg.loader.load(); }\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        assert_eq!(summary.field_types, vec![
+            ("App".to_string(), "client".to_string(), "Client".to_string()),
+            ("App".to_string(), "retries".to_string(), "u32".to_string()),
+        ]);
+        let run = &summary.functions[0];
+        assert_eq!(run.local_types, vec![("cfg".to_string(), "Config".to_string())]);
+        assert_eq!(run.calls[0].target, RawTarget::FieldMethod {
+            base: "App".to_string(),
+            fields: vec!["client".to_string()],
+            method: "send".to_string(),
+        });
+        assert_eq!(run.calls[1].target, RawTarget::FieldMethod {
+            base: "cfg".to_string(),
+            fields: vec!["loader".to_string()],
+            method: "load".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_let_binding_types() {
+        let code = "fn main() {\n\
+                        let s = Server::new(8080);\n\
+                        let c: std::rc::Rc<Conn> = make();\n\
+                        let f = File::open(path)?;\n\
+                        let p = Point { x: 1 };\n\
+                        let u = Unit;\n\
+                        let n = util::make();\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/main.rs", code).unwrap();
+
+        let locals: Vec<(&str, &str)> = summary.functions[0].local_types.iter()
+            .map(|(n, t)| (n.as_str(), t.as_str()))
+            .collect();
+        assert_eq!(locals, vec![("s", "Server"), ("c", "Conn"), ("f", "File"), ("p", "Point"), ("u", "Unit")]);
+    }
+
+    #[test]
+    fn test_calls_in_every_expression_position() {
+        let code = "async fn run() -> Result<(), E> {\n\
+                        while a() { b(); }\n\
+                        for x in c() { d(x); }\n\
+                        loop { break e(); }\n\
+                        let v = f() + g();\n\
+                        let s = S { field: h() };\n\
+                        i().await;\n\
+                        j()?;\n\
+                        let Some(y) = k() else { return l(); };\n\
+                        [m()][0];\n\
+                        return n();\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        let called: Vec<String> = summary.functions[0].calls.iter()
+            .filter_map(|c| match &c.target {
+                RawTarget::Path(p) => Some(p.join("::")),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(called, vec!["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n"]);
+    }
+
+    #[test]
+    fn test_qualified_and_ufcs_calls() {
+        let code = "fn run(w: &Widget) {\n\
+                        <Widget as Draw>::draw(w);\n\
+                        <Widget>::new();\n\
+                        Draw::draw(&w);\n\
+                        Widget::new();\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        let path = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let targets: Vec<&RawTarget> = summary.functions[0].calls.iter()
+            .map(|c| &c.target)
+            .filter(|t| !matches!(t, RawTarget::Reference(_)))
+            .collect();
+        assert_eq!(targets, vec![
+            &RawTarget::AssocCall { path: path(&["Draw", "draw"]), receiver: Some("Widget".to_string()) },
+            &RawTarget::Path(path(&["Widget", "new"])),
+            &RawTarget::AssocCall { path: path(&["Draw", "draw"]), receiver: Some("w".to_string()) },
+            &RawTarget::Path(path(&["Widget", "new"])),
+        ]);
+    }
+
+    #[test]
+    fn test_calls_in_macro_arguments() {
+        let code = "fn main() {\n\
+                        println!(\"{}\", run_trait(&add, 1));\n\
+                        let v = vec![make(); count()];\n\
+                        assert!(matches!(check(), Some(_)));\n\
+                        debug_assert_eq!(left(), vec![right()]);\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/main.rs", code).unwrap();
+
+        let called: Vec<(String, usize)> = summary.functions[0].calls.iter()
+            .filter_map(|c| match &c.target {
+                RawTarget::Path(p) => Some((p.join("::"), c.line)),
+                _ => None,
+            })
+            .collect();
+        // `Some(_)` happens to parse as an expression, like a call anywhere else
+        assert_eq!(called, vec![
+            ("run_trait".to_string(), 2),
+            ("make".to_string(), 3),
+            ("count".to_string(), 3),
+            ("check".to_string(), 4),
+            ("Some".to_string(), 4),
+            ("left".to_string(), 5),
+            ("right".to_string(), 5),
+        ]);
+    }
+
+    #[test]
+    fn test_spawned_tasks() {
+        let code = "async fn serve() {\n\
+                        tokio::spawn(async move { handle().await; });\n\
+                        tokio::spawn(handle());\n\
+                        std::thread::spawn(|| work());\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+        let serve = &summary.functions[0];
+        assert!(serve.is_async);
+
+        let spawned: Vec<&RawTarget> = serve.calls.iter()
+            .filter_map(|c| match &c.target {
+                RawTarget::Spawn(task) => Some(&**task),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(spawned, vec![
+            &RawTarget::Node("app::serve::{async#0}".to_string()),
+            &RawTarget::Path(vec!["handle".to_string()]),
+            &RawTarget::Node("app::serve::{closure#0}".to_string()),
+        ]);
+        assert!(serve.closures[0].is_async);
+        assert_eq!(serve.closures[0].calls[0].target, RawTarget::Path(vec!["handle".to_string()]));
+        assert!(!serve.closures[1].is_async);
+    }
+
+    #[test]
+    fn test_function_values_are_references() {
+        let code = "fn run(items: Vec<u32>, limit: u32) {\n\
+                        items.iter().map(process_item);\n\
+                        register(Self::handler, &fallback);\n\
+                        let count = items.len();\n\
+                        check(limit, count, |n| keep(n));\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        let refs: Vec<String> = summary.functions[0].calls.iter()
+            .filter_map(|c| match &c.target {
+                RawTarget::Reference(p) => Some(p.join("::")),
+                _ => None,
+            })
+            .collect();
+        // `items` is only a receiver, so it is not a candidate; parameters,
+        // `let` bindings and closure parameters are values
+        assert_eq!(refs, vec!["process_item", "Self::handler", "fallback"]);
+    }
+
+    #[test]
+    fn test_generic_params_and_call_sites() {
+        let code = "fn run<T: Op, U>(t: &T, n: u32) {}\n\
+                    fn main() {\n\
+                        run::<Add, _>(x, 1);\n\
+                        run(Mul::new(), count);\n\
+                        log();\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        let run = &summary.functions[0];
+        assert_eq!(run.type_params, vec!["T", "U"]);
+        assert_eq!(run.generic_args, vec![(0, "T".to_string())]);
+
+        let sites = &summary.functions[1].call_sites;
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].type_args, vec![Some("Add".to_string()), None]);
+        assert_eq!(sites[0].args, vec![Some("x".to_string()), None]);
+        assert_eq!(sites[1].path, vec!["run"]);
+        assert_eq!(sites[1].args, vec![Some("Mul".to_string()), Some("count".to_string())]);
+    }
+
+    #[test]
+    fn test_node_attributes() {
+        let code = "pub unsafe fn raw() {}\n\
+                    #[test]\n\
+                    fn smoke() { fn inner() {} }\n\
+                    #[cfg(test)]\n\
+                    mod tests {\n\
+                        fn fixture() {}\n\
+                    }\n\
+                    struct S;\n\
+                    impl S { #[tokio::test] async fn check() {} }\n\
+                    #[cfg(all(test, unix))]\n\
+                    fn unix_fixture() {}\n\
+                    #[cfg(any(test, feature = \"mock\"))]\n\
+                    fn mock() {}\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+
+        let attrs: Vec<(&str, bool, bool, bool)> = summary.functions.iter()
+            .map(|f| (f.label.as_str(), f.is_public, f.is_unsafe, f.is_test))
+            .collect();
+        assert_eq!(attrs, vec![
+            ("app::raw", true, true, false),
+            ("app::smoke", false, false, true),
+            ("app::smoke::inner", false, false, true),
+            ("app::tests::fixture", false, false, true),
+            ("S::check", false, false, true),
+            ("app::unix_fixture", false, false, true),
+            // Also built without tests
+            ("app::mock", false, false, false),
+        ]);
+    }
+
+    #[test]
+    fn test_unsafe_blocks() {
+        let code = "fn safe() { a(); }\n\
+                    fn peek(p: *const u8) -> u8 { let f = || unsafe { *p }; unsafe { g() }; f() }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+        let blocks: Vec<usize> = summary.functions.iter().map(|f| f.unsafe_blocks).collect();
+        // The one in the closure counts towards `peek`
+        assert_eq!(blocks, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_panic_sites() {
+        let code = "fn risky(v: Vec<u8>, m: Option<u8>) -> u8 {\n\
+                        let a = m.unwrap();\n\
+                        let b = m.expect(\"set\") + v[0];\n\
+                        let f = || todo!();\n\
+                        if a > b { panic!(\"{}\", a) }\n\
+                        m.unwrap_or(0)\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+        let sites: Vec<(PanicKind, usize)> = summary.functions[0].panics.iter().map(|p| (p.kind, p.line)).collect();
+        assert_eq!(sites, vec![
+            (PanicKind::Unwrap, 2),
+            (PanicKind::Expect, 3),
+            (PanicKind::Index, 3),
+            (PanicKind::Todo, 4),
+            (PanicKind::Panic, 5),
+        ]);
+    }
+
+    #[test]
+    fn test_fn_metrics() {
+        let code = "fn flat() { a(); b(); }\n\
+                    fn branchy(x: Option<u8>) -> Result<u8, E> {\n\
+                        let v = x.ok_or(E)?;\n\
+                        if v > 1 && v < 9 {\n\
+                            for i in 0..v { match i { 0 => a(), n if n > 2 => b(), _ => {} } }\n\
+                        } else if v == 0 {\n\
+                            a();\n\
+                        }\n\
+                        Ok(v)\n\
+                    }\n";
+        let summary = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+        let metrics: Vec<FnMetrics> = summary.functions.iter().map(|f| f.metrics).collect();
+        assert_eq!(metrics[0], FnMetrics { complexity: 1, statements: 2, nesting: 0 });
+        // `?`, if, &&, for, two extra arms, guard, else if
+        assert_eq!(metrics[1].complexity, 9);
+        // if -> for -> match arm
+        assert_eq!(metrics[1].nesting, 3);
+        assert_eq!(metrics[1].statements, 6);
+    }
+
+    #[test]
+    fn test_cfg_evaluation() {
+        use crate::domain::cfg::TargetCfg;
+
+        let code = "#[cfg(unix)] fn open() {}\n\
+                    #[cfg(windows)] fn open() {}\n\
+                    #[cfg(feature = \"tls\")]\n\
+                    mod tls { #[cfg(test)] fn handshake() {} }\n\
+                    #[cfg(not(feature = \"tls\"))] fn plain() {}\n";
+        let cfgs = |s: &FileSummary| s.functions.iter()
+            .map(|f| (f.label.clone(), f.cfg.clone().unwrap_or_default()))
+            .collect::<Vec<_>>();
+
+        // Nothing evaluated: everything is kept and annotated
+        let all = FileSummary::extract("app", "src/lib.rs", code).unwrap();
+        assert_eq!(all.functions.len(), 4);
+        assert_eq!(cfgs(&all)[2], ("app::tls::handshake".to_string(), "all(feature = \"tls\", test)".to_string()));
+
+        let options = CfgOptions {
+            features: Some(["tls".to_string()].into()),
+            target: Some(TargetCfg::from_triple("x86_64-unknown-linux-gnu")),
+            ..CfgOptions::default()
+        };
+        let linux = FileSummary::extract_with_cfg(&SourceFile::new("app", "src/lib.rs", code), &options).unwrap();
+        assert_eq!(cfgs(&linux), vec![
+            ("app::open".to_string(), "unix".to_string()),
+            ("app::tls::handshake".to_string(), "all(feature = \"tls\", test)".to_string()),
+        ]);
+        assert_eq!(linux.defs.len(), 2);
+        assert_ne!(linux.content_hash, all.content_hash);
+    }
+
+    #[test]
+    fn test_content_hash_changes_with_code() {
+        let a = content_hash("app", "src/lib.rs", "fn a() {}");
+        assert_eq!(a, content_hash("app", "src/lib.rs", "fn a() {}"));
+        assert_ne!(a, content_hash("app", "src/lib.rs", "fn b() {}"));
+        assert_ne!(a, content_hash("app", "src/other.rs", "fn a() {}"));
+    }
+}
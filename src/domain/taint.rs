@@ -0,0 +1,217 @@
+//! Taint Reachability
+//!
+//! Call paths from "source" functions (where untrusted data comes from:
+//! `std::env::args`, HTTP handlers) to "sink" functions (where it must not
+//! arrive unchecked: `Command::new`, `fs::write`), both listed as patterns
+//! in a TOML file:
+//!
+//! ```toml
+//! sources = ["std::env::args", "app::handlers::*"]
+//! sinks = ["Command::new", "std::fs::write"]
+//! ```
+//!
+//! This is reachability over the call graph, not data flow: a path means
+//! the sink can run after the source, not that the data gets there.
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::filter::glob_match;
+use crate::domain::trace::{TraceGenerator, TracePath};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaintConfig {
+    pub sources: Vec<String>,
+    pub sinks: Vec<String>,
+}
+
+impl TaintConfig {
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: TaintConfig = toml::from_str(text)?;
+        if config.sources.is_empty() || config.sinks.is_empty() {
+            bail!("`sources` and `sinks` must each list at least one pattern");
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text)
+    }
+}
+
+/// Whether `pattern` names the node `id`. Patterns are globs over the id,
+/// over the id without its `@crate` suffix and, for methods, over
+/// `crate::Type::m`; one without `*` or `?` also matches at a `::`
+/// boundary, so `fs::write` names `std::fs::write`.
+pub fn matches_pattern(pattern: &str, id: &str) -> bool {
+    let (path, krate) = id.rsplit_once('@').map_or((id, None), |(path, krate)| (path, Some(krate)));
+    let qualified = krate.map(|k| format!("{}::{}", k, path));
+    let exact = !pattern.contains(['*', '?']);
+    let hit = |candidate: &str| glob_match(pattern, candidate) || (exact && candidate.ends_with(&format!("::{}", pattern)));
+    hit(id) || hit(path) || qualified.as_deref().is_some_and(hit)
+}
+
+/// Call paths from one source to one sink.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaintFlow {
+    pub source: String,
+    /// Where the paths start: the source itself, or for a source that calls
+    /// nothing (library functions like `std::env::args`) a function calling it.
+    pub origin: String,
+    pub sink: String,
+    pub paths: Vec<TracePath>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaintReport {
+    /// Node ids the source and sink patterns matched.
+    pub sources: Vec<String>,
+    pub sinks: Vec<String>,
+    /// Patterns that matched no node.
+    pub unmatched: Vec<String>,
+    pub flows: Vec<TaintFlow>,
+}
+
+/// Connect every matched source to every matched sink. `tracer` must be
+/// built over `cg`; its path limit applies per flow.
+pub fn taint_flows(cg: &CallGraph, config: &TaintConfig, tracer: &TraceGenerator) -> TaintReport {
+    let mut unmatched = Vec::new();
+    let mut matching = |patterns: &[String]| -> Vec<&CallGraphNode> {
+        let mut found: Vec<&CallGraphNode> = Vec::new();
+        for pattern in patterns {
+            let hits: Vec<&CallGraphNode> = cg.nodes.iter().filter(|n| matches_pattern(pattern, &n.id)).collect();
+            if hits.is_empty() {
+                unmatched.push(pattern.clone());
+            }
+            found.extend(hits);
+        }
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+        found.dedup_by(|a, b| a.id == b.id);
+        found
+    };
+    let sources = matching(&config.sources);
+    let sinks = matching(&config.sinks);
+
+    let mut flows = Vec::new();
+    for source in &sources {
+        let origins: Vec<&str> = if source.callees.is_empty() {
+            let mut callers: Vec<&str> = cg.nodes.iter()
+                .filter(|n| n.callee_ids().any(|c| c == source.id))
+                .map(|n| n.id.as_str())
+                .collect();
+            callers.sort();
+            callers
+        } else {
+            vec![source.id.as_str()]
+        };
+        for origin in origins {
+            for sink in &sinks {
+                let paths = tracer.paths_between(origin, &sink.id);
+                if !paths.is_empty() {
+                    flows.push(TaintFlow { source: source.id.to_string(), origin: origin.to_string(), sink: sink.id.to_string(), paths });
+                }
+            }
+        }
+    }
+
+    TaintReport {
+        sources: sources.iter().map(|n| n.id.to_string()).collect(),
+        sinks: sinks.iter().map(|n| n.id.to_string()).collect(),
+        unmatched,
+        flows,
+    }
+}
+
+impl fmt::Display for TaintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Taint flows from {} source(s) to {} sink(s): {}", self.sources.len(), self.sinks.len(), self.flows.len())?;
+        for flow in &self.flows {
+            if flow.origin == flow.source {
+                writeln!(f, "  {} -> {}", flow.source, flow.sink)?;
+            } else {
+                writeln!(f, "  {} -> {} (via {})", flow.source, flow.sink, flow.origin)?;
+            }
+            for path in &flow.paths {
+                let steps: Vec<String> = path.steps.iter().map(|s| match (&s.location, s.depth) {
+                    (Some(location), 1..) => format!("{} ({})", s.id, location),
+                    _ => s.id.clone(),
+                }).collect();
+                writeln!(f, "    {}", steps.join(" -> "))?;
+            }
+        }
+        if !self.unmatched.is_empty() {
+            writeln!(f, "Patterns matching no function: {}", self.unmatched.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    use crate::domain::callgraph::CallEdge;
+    use crate::domain::source::SourceSet;
+    use crate::infrastructure::source_manager::SourceManager;
+
+    fn node(id: &str, callees: Vec<CallEdge>) -> CallGraphNode {
+        CallGraphNode { callees, ..fixtures::node(id, &[]) }
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("std::env::args", "std::env::args@app"));
+        assert!(matches_pattern("fs::write", "std::fs::write@app"));
+        assert!(matches_pattern("Command::new", "Command::new@app"));
+        assert!(matches_pattern("app::Server::*", "Server::handle@app"));
+        assert!(matches_pattern("app::handlers::*", "app::handlers::upload"));
+        assert!(!matches_pattern("write", "app::rewrite"));
+        assert!(!matches_pattern("app::h*", "app::run"));
+    }
+
+    #[test]
+    fn test_config() {
+        let config = TaintConfig::parse("sources = [\"std::env::args\"]\nsinks = [\"Command::new\"]\n").unwrap();
+        assert_eq!(config.sinks, vec!["Command::new"]);
+        assert!(TaintConfig::parse("sources = [\"a\"]\n").is_err());
+        assert!(TaintConfig::parse("sources = [\"a\"]\nsinks = [\"b\"]\nsink = [\"c\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_taint_flows() {
+        let cg = CallGraph::new(vec![
+            node("app::main", vec![CallEdge::at("std::env::args@app", "src/main.rs", 2), CallEdge::at("app::run", "src/main.rs", 3)]),
+            node("app::run", vec![CallEdge::at("Command::new@app", "src/main.rs", 7)]),
+            node("app::handlers::upload", vec![CallEdge::at("std::fs::write@app", "src/handlers.rs", 4)]),
+            node("app::idle", vec![CallEdge::new("std::fs::write@app")]),
+            node("std::env::args@app", vec![]),
+            node("Command::new@app", vec![]),
+            node("std::fs::write@app", vec![]),
+        ]);
+        let config = TaintConfig {
+            sources: vec!["std::env::args".to_string(), "app::handlers::*".to_string(), "app::missing".to_string()],
+            sinks: vec!["Command::new".to_string(), "fs::write".to_string()],
+        };
+        let sources = SourceManager::new(&SourceSet::default());
+        let report = taint_flows(&cg, &config, &TraceGenerator::new(&cg, &sources));
+
+        assert_eq!(report.sources, vec!["app::handlers::upload", "std::env::args@app"]);
+        assert_eq!(report.unmatched, vec!["app::missing"]);
+        let flows: Vec<(&str, &str, &str)> = report.flows.iter()
+            .map(|f| (f.source.as_str(), f.origin.as_str(), f.sink.as_str()))
+            .collect();
+        assert_eq!(flows, vec![
+            ("app::handlers::upload", "app::handlers::upload", "std::fs::write@app"),
+            ("std::env::args@app", "app::main", "Command::new@app"),
+        ]);
+        let text = report.to_string();
+        assert!(text.contains("  std::env::args@app -> Command::new@app (via app::main)\n    app::main -> app::run (src/main.rs:3) -> Command::new@app (src/main.rs:7)\n"), "{}", text);
+        assert!(text.contains("Patterns matching no function: app::missing"), "{}", text);
+    }
+}
@@ -1,39 +1,126 @@
-use crate::domain::callgraph::CallGraph;
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode};
 use crate::infrastructure::source_manager::SourceManager;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-#[derive(Debug, Clone)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct TraceStep {
     pub id: String,
+    /// With [`TraceOptions::signatures`], when the builder recorded one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// First line of the function's doc comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
     pub location: Option<String>,
     pub depth: usize,
     pub snippet: Option<String>,
     pub note: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TracePath {
     pub steps: Vec<TraceStep>,
 }
 
+/// How paths are enumerated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceStrategy {
+    /// Every path, depth-first.
+    #[default]
+    Dfs,
+    /// Every path, shortest first (level order).
+    Bfs,
+    /// One shortest path to each reachable leaf.
+    Shortest,
+}
+
+impl TraceStrategy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "dfs" => Some(TraceStrategy::Dfs),
+            "bfs" => Some(TraceStrategy::Bfs),
+            "shortest" => Some(TraceStrategy::Shortest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceOptions {
+    /// Paths are cut after this many steps.
+    pub max_depth: usize,
+    /// At most this many paths per start node.
+    pub max_paths: usize,
+    pub strategy: TraceStrategy,
+    /// Multi-line snippets: the function signature plus this many lines of
+    /// its body, or this many lines around a call site. `None` shows the
+    /// single line.
+    pub context: Option<usize>,
+    /// Show the whole body of each function (overrides `context` for functions).
+    pub full_body: bool,
+    /// Give each step its function's signature.
+    pub signatures: bool,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self { max_depth: 30, max_paths: 50, strategy: TraceStrategy::Dfs, context: None, full_body: false, signatures: false }
+    }
+}
+
 pub struct TraceGenerator<'a> {
-    graph: &'a CallGraph,
+    nodes: HashMap<&'a str, &'a CallGraphNode>,
+    /// Callee id -> ids of the nodes calling it.
+    callers: HashMap<&'a str, Vec<&'a str>>,
     source_manager: &'a SourceManager,
     max_depth: usize,
     max_paths: usize,
+    strategy: TraceStrategy,
+    context: Option<usize>,
+    full_body: bool,
+    signatures: bool,
 }
 
 impl<'a> TraceGenerator<'a> {
     pub fn new(graph: &'a CallGraph, source_manager: &'a SourceManager) -> Self {
+        Self::with_options(graph, source_manager, TraceOptions::default())
+    }
+
+    pub fn with_options(graph: &'a CallGraph, source_manager: &'a SourceManager, options: TraceOptions) -> Self {
+        let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for node in &graph.nodes {
+            for callee in node.call_edges() {
+                callers.entry(callee.target.as_str()).or_default().push(node.id.as_str());
+            }
+        }
         Self {
-            graph,
+            nodes: graph.nodes.iter().fold(HashMap::new(), |mut nodes, n| {
+                // The first node wins for duplicate ids, as in the builder
+                nodes.entry(n.id.as_str()).or_insert(n);
+                nodes
+            }),
+            callers,
             source_manager,
-            max_depth: 30, // Hardcap depth
-            max_paths: 50, // Hardcap paths
+            max_depth: options.max_depth,
+            max_paths: options.max_paths,
+            strategy: options.strategy,
+            context: options.context,
+            full_body: options.full_body,
+            signatures: options.signatures,
         }
     }
 
     pub fn generate_paths(&self, start_node_id: &str) -> Vec<TracePath> {
+        match self.strategy {
+            TraceStrategy::Dfs => self.all_paths_dfs(start_node_id),
+            TraceStrategy::Bfs => self.all_paths_bfs(start_node_id),
+            TraceStrategy::Shortest => self.shortest_paths(start_node_id),
+        }
+    }
+
+    fn all_paths_dfs(&self, start_node_id: &str) -> Vec<TracePath> {
         let mut results = Vec::new();
         let mut current_path = Vec::new();
         let mut visited = HashSet::new();
@@ -68,31 +155,9 @@ impl<'a> TraceGenerator<'a> {
         }
 
         // Find node in graph
-        let node_opt = self.graph.nodes.iter().find(|n| n.id == current_id);
-        
-        // Prepare trace step
-        let location = node_opt.and_then(|n| n.label.clone());
-        let snippet = location.as_ref().and_then(|loc| {
-             // Location format "file:line"
-             let parts: Vec<&str> = loc.split(':').collect();
-             if parts.len() >= 2 {
-                 let file = parts[0];
-                 let line = parts[1].parse::<usize>().ok()?;
-                 self.source_manager.get_snippet(file, line)
-             } else {
-                 None
-             }
-        });
+        let node_opt = self.nodes.get(current_id);
 
-        let step = TraceStep {
-            id: current_id.to_string(),
-            location,
-            depth,
-            snippet,
-            note: if visited.contains(current_id) { Some("[Cycle Detected]".to_string()) } else { None },
-        };
-
-        path_stack.push(step);
+        path_stack.push(self.step(current_id, depth, visited.contains(current_id)));
         
         // Cycle check: If current node is already in the recursion stack (represented here by `path_stack` IDs? No, usually separate set)
         // Actually for DFS path enumeration, `visited` usually tracks nodes in the *current path* to detect cycles.
@@ -108,12 +173,13 @@ impl<'a> TraceGenerator<'a> {
 
         // Recurse
         if let Some(node) = node_opt {
-            if node.callees.is_empty() {
+            let callees: Vec<&CallEdge> = node.call_edges().collect();
+            if callees.is_empty() {
                 // Leaf node
                 results.push(TracePath { steps: path_stack.clone() });
             } else {
-                for callee in &node.callees {
-                    self.dfs(callee, depth + 1, path_stack, visited, results);
+                for callee in callees {
+                    self.dfs(&callee.target, depth + 1, path_stack, visited, results);
                     if results.len() >= self.max_paths {
                         break;
                     }
@@ -127,4 +193,387 @@ impl<'a> TraceGenerator<'a> {
         visited.remove(current_id);
         path_stack.pop();
     }
+
+    /// Same paths as the DFS, extended one level at a time so shorter
+    /// paths come first. Every queued path ends in at least one result, so
+    /// the queue never holds more than the paths still wanted.
+    fn all_paths_bfs(&self, start_node_id: &str) -> Vec<TracePath> {
+        let mut results = Vec::new();
+        let mut queue = VecDeque::from([vec![start_node_id]]);
+        while let Some(path) = queue.pop_front() {
+            let (&current, ancestors) = path.split_last().expect("paths are never empty");
+            let cycle = ancestors.contains(&current);
+            let callees = self.callees(current);
+            if cycle || callees.is_empty() || path.len() == self.max_depth {
+                results.push(self.to_trace(&path));
+                continue;
+            }
+            for callee in callees {
+                if results.len() + queue.len() >= self.max_paths {
+                    break;
+                }
+                let mut next = path.clone();
+                next.push(callee.target.as_str());
+                queue.push_back(next);
+            }
+        }
+        results
+    }
+
+    /// Breadth-first search recording the first (shortest) way each node is
+    /// reached; yields the path to every leaf, nearest first.
+    fn shortest_paths(&self, start_node_id: &str) -> Vec<TracePath> {
+        let mut parent: HashMap<&str, &str> = HashMap::new();
+        let mut seen = HashSet::from([start_node_id]);
+        let mut queue = VecDeque::from([(start_node_id, 1)]);
+        let mut results = Vec::new();
+        while let Some((current, len)) = queue.pop_front() {
+            if results.len() >= self.max_paths {
+                break;
+            }
+            let callees = self.callees(current);
+            if callees.is_empty() {
+                let mut path = vec![current];
+                while let Some(&prev) = parent.get(path[path.len() - 1]) {
+                    path.push(prev);
+                }
+                path.reverse();
+                results.push(self.to_trace(&path));
+                continue;
+            }
+            if len >= self.max_depth {
+                continue;
+            }
+            for callee in callees {
+                if seen.insert(callee.target.as_str()) {
+                    parent.insert(callee.target.as_str(), current);
+                    queue.push_back((callee.target.as_str(), len + 1));
+                }
+            }
+        }
+        results
+    }
+
+    /// Call paths from `from` to `to` that visit no node twice, up to the
+    /// path limit, shortest first. Steps after the first carry the call site
+    /// they are reached through as location and snippet.
+    pub fn paths_between(&self, from: &str, to: &str) -> Vec<TracePath> {
+        // Only nodes that can still reach `to` are worth extending into
+        let mut reaches = HashSet::from([to]);
+        let mut pending = vec![to];
+        while let Some(id) = pending.pop() {
+            for &caller in self.callers.get(id).into_iter().flatten() {
+                if reaches.insert(caller) {
+                    pending.push(caller);
+                }
+            }
+        }
+        if !reaches.contains(from) {
+            return Vec::new();
+        }
+
+        let mut results = Vec::new();
+        self.dfs_to(&mut vec![(from, None)], to, &reaches, &mut results);
+        // Found depth-first so only one path is held at a time
+        results.sort_by_key(|path| path.steps.len());
+        results
+    }
+
+    /// Depth-first search for `paths_between`, through `reaches` only.
+    fn dfs_to<'p>(&'p self, path: &mut Vec<(&'p str, Option<&'p CallEdge>)>, to: &str, reaches: &HashSet<&str>, results: &mut Vec<TracePath>)
+    where
+        'a: 'p,
+    {
+        let current = path[path.len() - 1].0;
+        if current == to && path.len() > 1 {
+            results.push(self.to_call_trace(path));
+            return;
+        }
+        if path.len() >= self.max_depth {
+            return;
+        }
+        for edge in self.callees(current) {
+            if results.len() >= self.max_paths {
+                return;
+            }
+            let target = edge.target.as_str();
+            // `to` is only on the path already when it is also `from`
+            let revisit = target != to && path.iter().any(|(id, _)| *id == target);
+            if reaches.contains(target) && !revisit {
+                path.push((target, Some(edge)));
+                self.dfs_to(path, to, reaches, results);
+                path.pop();
+            }
+        }
+    }
+
+    /// Shortest call path from `from` to each function with a potential
+    /// panic, nearest first. Each path ends in one step per panic site,
+    /// noting its kind, with the site's line as snippet.
+    pub fn panic_paths(&self, from: &str) -> Vec<TracePath> {
+        let mut results = Vec::new();
+        // Node -> caller and call edge it was first reached through
+        let mut parent: HashMap<&str, (&str, &CallEdge)> = HashMap::new();
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([(from, 1)]);
+        while let Some((current, len)) = queue.pop_front() {
+            if results.len() >= self.max_paths {
+                break;
+            }
+            let Some(node) = self.nodes.get(current) else { continue };
+            if !node.panics.is_empty() {
+                let mut path = vec![(current, None)];
+                let mut at = current;
+                while let Some(&(caller, edge)) = parent.get(at) {
+                    let last = path.len() - 1;
+                    path[last].1 = Some(edge);
+                    path.push((caller, None));
+                    at = caller;
+                }
+                path.reverse();
+                let mut trace = self.to_call_trace(&path);
+                let file = node.location.as_deref().and_then(|loc| loc.rsplit_once(':')).map(|(file, _)| file);
+                trace.steps.extend(node.panics.iter().map(|site| TraceStep {
+                    id: current.to_string(),
+                    signature: None,
+                    doc: None,
+                    location: file.map(|f| format!("{}:{}", f, site.line)),
+                    depth: len,
+                    snippet: file.and_then(|f| match self.context {
+                        Some(n) => self.source_manager.get_snippet_range(f, site.line, n, n),
+                        None => self.source_manager.get_snippet(f, site.line),
+                    }),
+                    note: Some(format!("[{}]", site.kind.as_str())),
+                }));
+                results.push(trace);
+            }
+            if len >= self.max_depth {
+                continue;
+            }
+            for edge in node.call_edges() {
+                if seen.insert(edge.target.as_str()) {
+                    parent.insert(edge.target.as_str(), (current, edge));
+                    queue.push_back((edge.target.as_str(), len + 1));
+                }
+            }
+        }
+        results
+    }
+
+    fn to_call_trace(&self, path: &[(&str, Option<&CallEdge>)]) -> TracePath {
+        let steps = path.iter().enumerate()
+            .map(|(depth, (id, edge))| match edge {
+                None => self.step(id, depth, false),
+                Some(edge) => TraceStep {
+                    id: id.to_string(),
+                    signature: self.signature(id),
+                    doc: self.doc(id),
+                    location: edge.location(),
+                    depth,
+                    snippet: edge.file.as_deref().zip(edge.line).and_then(|(file, line)| match self.context {
+                        Some(n) => self.source_manager.get_snippet_range(file, line, n, n),
+                        None => self.source_manager.get_snippet(file, line),
+                    }),
+                    note: None,
+                },
+            })
+            .collect();
+        TracePath { steps }
+    }
+
+    /// Steps for a path of node ids; a final id seen earlier is a cycle.
+    fn to_trace(&self, path: &[&str]) -> TracePath {
+        let steps = path.iter().enumerate()
+            .map(|(depth, id)| self.step(id, depth, path[..depth].contains(id)))
+            .collect();
+        TracePath { steps }
+    }
+
+    /// Call edges of `id`; structural edges (`implements`, `constructs`, ..)
+    /// are not followed.
+    fn callees(&self, id: &str) -> Vec<&'a CallEdge> {
+        self.nodes.get(id).map(|n| n.call_edges().collect()).unwrap_or_default()
+    }
+
+    fn signature(&self, id: &str) -> Option<String> {
+        self.signatures.then(|| self.nodes.get(id)?.signature.clone()).flatten()
+    }
+
+    fn doc(&self, id: &str) -> Option<String> {
+        self.nodes.get(id)?.doc.clone()
+    }
+
+    fn step(&self, id: &str, depth: usize, cycle: bool) -> TraceStep {
+        // Definition site when the builder recorded one, else the label
+        let location = self.nodes.get(id).and_then(|n| n.location.clone().or_else(|| n.label.clone()));
+        let snippet = location.as_ref().and_then(|loc| {
+             // Location format "file:line"
+             let (file, line) = loc.rsplit_once(':')?;
+             let line = line.parse::<usize>().ok()?;
+             match self.context {
+                 _ if self.full_body => self.source_manager.get_function_body(file, line),
+                 Some(n) => self.source_manager.get_signature_snippet(file, line, n),
+                 None => self.source_manager.get_snippet(file, line),
+             }
+        });
+
+        TraceStep {
+            id: id.to_string(),
+            signature: self.signature(id),
+            doc: self.doc(id),
+            location,
+            depth,
+            snippet,
+            note: if cycle { Some("[Cycle Detected]".to_string()) } else { None },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    use crate::domain::source::SourceSet;
+
+    fn graph() -> CallGraph {
+        // main -> a -> b -> c, main -> c, c -> main
+        CallGraph::new(vec![
+            node("main", &["a", "c"]),
+            node("a", &["b"]),
+            node("b", &["c"]),
+            node("c", &["main", "leaf"]),
+            node("leaf", &[]),
+        ])
+    }
+
+    #[test]
+    fn test_paths_between_locations() {
+        let node = |id: &str, callees: Vec<CallEdge>| CallGraphNode { id: id.into(), callees, ..Default::default() };
+        let cg = CallGraph::new(vec![
+            node("main", vec![CallEdge::at("helper", "src/main.rs", 3)]),
+            node("helper", vec![CallEdge::at("unsafe_write", "src/main.rs", 7)]),
+            node("unsafe_write", vec![]),
+        ]);
+        let sources = SourceManager::new(&SourceSet::default());
+        let paths = TraceGenerator::new(&cg, &sources).paths_between("main", "unsafe_write");
+        let locations: Vec<Option<&str>> = paths[0].steps.iter().map(|s| s.location.as_deref()).collect();
+        assert_eq!(locations, vec![None, Some("src/main.rs:3"), Some("src/main.rs:7")]);
+    }
+
+    #[test]
+    fn test_signature_context() {
+        use crate::domain::source::SourceFile;
+        let code = "fn main() {\n    let a = 1;\n    let b = 2;\n}\n";
+        let files = SourceSet::from(vec![SourceFile::new("app", "src/main.rs", code)]);
+        let sources = SourceManager::new(&files);
+        let cg = CallGraph::new(vec![CallGraphNode { location: Some("src/main.rs:1".to_string()), ..node("leaf", &[]) }]);
+
+        let single = TraceGenerator::new(&cg, &sources).generate_paths("leaf");
+        assert_eq!(single[0].steps[0].snippet.as_deref(), Some("fn main() {"));
+        let options = TraceOptions { context: Some(1), ..Default::default() };
+        let multi = TraceGenerator::with_options(&cg, &sources, options).generate_paths("leaf");
+        assert_eq!(multi[0].steps[0].snippet.as_deref(), Some("fn main() {\n    let a = 1;"));
+        let options = TraceOptions { full_body: true, ..Default::default() };
+        let body = TraceGenerator::with_options(&cg, &sources, options).generate_paths("leaf");
+        assert_eq!(body[0].steps[0].snippet.as_deref(), Some(code.trim_end()));
+
+        let doc = Some("Entry point.".to_string());
+        let cg = CallGraph::new(vec![CallGraphNode { signature: Some("fn main()".to_string()), doc: doc.clone(), ..cg.nodes[0].clone() }]);
+        let unsigned = TraceGenerator::new(&cg, &sources).generate_paths("leaf");
+        assert_eq!((&unsigned[0].steps[0].signature, &unsigned[0].steps[0].doc), (&None, &doc));
+        let options = TraceOptions { signatures: true, ..Default::default() };
+        let signed = TraceGenerator::with_options(&cg, &sources, options).generate_paths("leaf");
+        assert_eq!(signed[0].steps[0].signature.as_deref(), Some("fn main()"));
+    }
+
+    #[test]
+    fn test_panic_paths() {
+        use crate::domain::panics::{PanicKind, PanicSite};
+        use crate::domain::source::SourceFile;
+        let code = "fn main() {\n    parse();\n}\nfn parse() -> u8 {\n    input().unwrap()\n}\n";
+        let files = SourceSet::from(vec![SourceFile::new("app", "src/main.rs", code)]);
+        let sources = SourceManager::new(&files);
+        let node = |id: &str, line: usize, callees: Vec<CallEdge>, panics: Vec<PanicSite>| CallGraphNode {
+            id: id.into(),
+            callees,
+            location: Some(format!("src/main.rs:{}", line)),
+            panics,
+            ..Default::default()
+        };
+        let cg = CallGraph::new(vec![
+            node("main", 1, vec![CallEdge::at("parse", "src/main.rs", 2), CallEdge::new("leaf")], Vec::new()),
+            node("parse", 4, vec![CallEdge::at("input", "src/main.rs", 5)], vec![PanicSite { kind: PanicKind::Unwrap, line: 5 }]),
+            node("leaf", 7, Vec::new(), Vec::new()),
+        ]);
+
+        let paths = TraceGenerator::new(&cg, &sources).panic_paths("main");
+        assert_eq!(ids(&paths), vec!["main>parse>parse"]);
+        let steps: Vec<(Option<&str>, Option<&str>, Option<&str>)> = paths[0].steps.iter()
+            .map(|s| (s.location.as_deref(), s.snippet.as_deref(), s.note.as_deref()))
+            .collect();
+        assert_eq!(steps, vec![
+            (Some("src/main.rs:1"), Some("fn main() {"), None),
+            (Some("src/main.rs:2"), Some("parse();"), None),
+            (Some("src/main.rs:5"), Some("input().unwrap()"), Some("[unwrap()]")),
+        ]);
+    }
+
+    fn ids(paths: &[TracePath]) -> Vec<String> {
+        paths.iter().map(|p| p.steps.iter().map(|s| s.id.as_str()).collect::<Vec<_>>().join(">")).collect()
+    }
+
+    #[test]
+    fn test_strategies() {
+        let cg = graph();
+        let sources = SourceManager::new(&SourceSet::default());
+        let trace = |strategy, max_depth| {
+            let options = TraceOptions { strategy, max_depth, ..Default::default() };
+            ids(&TraceGenerator::with_options(&cg, &sources, options).generate_paths("main"))
+        };
+
+        assert_eq!(trace(TraceStrategy::Dfs, 30), vec!["main>a>b>c>main", "main>a>b>c>leaf", "main>c>main", "main>c>leaf"]);
+        assert_eq!(trace(TraceStrategy::Bfs, 30), vec!["main>c>main", "main>c>leaf", "main>a>b>c>main", "main>a>b>c>leaf"]);
+        assert_eq!(trace(TraceStrategy::Shortest, 30), vec!["main>c>leaf"]);
+        assert_eq!(trace(TraceStrategy::Bfs, 2), vec!["main>a", "main>c"]);
+        assert_eq!(trace(TraceStrategy::Shortest, 2), Vec::<String>::new());
+
+        let between = TraceGenerator::new(&cg, &sources);
+        assert_eq!(ids(&between.paths_between("main", "c")), vec!["main>c", "main>a>b>c"]);
+        assert_eq!(ids(&between.paths_between("a", "main")), vec!["a>b>c>main"]);
+        assert!(between.paths_between("leaf", "main").is_empty());
+
+        let limited = TraceOptions { max_paths: 1, ..Default::default() };
+        assert_eq!(TraceGenerator::with_options(&cg, &sources, limited).generate_paths("main").len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_ids_trace_the_first_node() {
+        let cg = CallGraph::new(vec![node("main", &["new"]), node("new", &["helper"]), node("new", &[]), node("helper", &[])]);
+        let sources = SourceManager::new(&SourceSet::default());
+        assert_eq!(ids(&TraceGenerator::new(&cg, &sources).generate_paths("main")), vec!["main>new>helper"]);
+    }
+
+    #[test]
+    fn test_path_limit_on_exponential_graphs() {
+        // 12 layers of 8 functions, each calling all of the next layer: 8^11 paths
+        let id = |layer: usize, i: usize| format!("f{}_{}", layer, i);
+        let mut nodes = vec![node("main", &(0..8).map(|i| id(0, i)).collect::<Vec<_>>().iter().map(String::as_str).collect::<Vec<_>>())];
+        for layer in 0..12 {
+            let next: Vec<String> = if layer == 11 { vec!["sink".to_string()] } else { (0..8).map(|i| id(layer + 1, i)).collect() };
+            let next: Vec<&str> = next.iter().map(String::as_str).collect();
+            nodes.extend((0..8).map(|i| node(&id(layer, i), &next)));
+        }
+        nodes.push(node("sink", &[]));
+        let cg = CallGraph::new(nodes);
+        let sources = SourceManager::new(&SourceSet::default());
+        let options = TraceOptions { strategy: TraceStrategy::Bfs, max_paths: 5, ..Default::default() };
+        let tracer = TraceGenerator::with_options(&cg, &sources, options);
+
+        let paths = tracer.generate_paths("main");
+        assert_eq!(paths.len(), 5);
+        assert!(paths.iter().all(|p| p.steps.len() == 14));
+        let between = tracer.paths_between("main", "sink");
+        assert_eq!(between.len(), 5);
+        assert!(between.iter().all(|p| p.steps.last().unwrap().id == "sink"));
+    }
 }
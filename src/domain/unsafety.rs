@@ -0,0 +1,185 @@
+//! Unsafe Reachability
+//!
+//! Finds the `unsafe fn`s and the functions with `unsafe { .. }` blocks that
+//! the entry points reach, with the shortest call path to each, and cuts the
+//! graph down to the functions on those paths.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::reachability::reachable_from;
+
+/// Whether a node is an `unsafe fn` or contains an `unsafe` block.
+pub fn uses_unsafe(node: &CallGraphNode) -> bool {
+    node.is_unsafe || node.unsafe_blocks > 0
+}
+
+/// An entry point's shortest call path to one function using unsafe code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnsafePath {
+    pub entry: String,
+    pub target: String,
+    /// Node ids from the entry to the target, both included.
+    pub path: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnsafeFn {
+    pub id: String,
+    pub location: Option<String>,
+    pub is_unsafe: bool,
+    pub unsafe_blocks: usize,
+}
+
+/// Result of an unsafe-reachability pass.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnsafeReport {
+    pub entries: Vec<String>,
+    /// Functions using unsafe code that some entry reaches, by id.
+    pub functions: Vec<UnsafeFn>,
+    pub paths: Vec<UnsafePath>,
+}
+
+/// Report every function using unsafe code reachable from `entries`.
+pub fn unsafe_reachability(cg: &CallGraph, entries: &[String]) -> UnsafeReport {
+    let nodes: HashMap<&str, &CallGraphNode> = cg.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let mut reached: HashSet<&str> = HashSet::new();
+    let mut paths = Vec::new();
+    for entry in entries {
+        // BFS keeping each node's predecessor, so paths come out shortest
+        let mut parent: HashMap<&str, &str> = HashMap::new();
+        let mut order = Vec::new();
+        let mut seen: HashSet<&str> = HashSet::from([entry.as_str()]);
+        let mut queue = VecDeque::from([entry.as_str()]);
+        while let Some(id) = queue.pop_front() {
+            order.push(id);
+            for callee in nodes.get(id).into_iter().flat_map(|n| n.callee_ids()) {
+                if seen.insert(callee) {
+                    parent.insert(callee, id);
+                    queue.push_back(callee);
+                }
+            }
+        }
+        for &id in &order {
+            if !nodes.get(id).is_some_and(|n| uses_unsafe(n)) {
+                continue;
+            }
+            reached.insert(id);
+            let mut path = vec![id.to_string()];
+            let mut at = id;
+            while let Some(&prev) = parent.get(at) {
+                path.push(prev.to_string());
+                at = prev;
+            }
+            path.reverse();
+            paths.push(UnsafePath { entry: entry.clone(), target: id.to_string(), path });
+        }
+    }
+
+    let mut functions: Vec<UnsafeFn> = cg.nodes.iter()
+        .filter(|n| reached.contains(n.id.as_str()))
+        .map(|n| UnsafeFn { id: n.id.to_string(), location: n.location.clone(), is_unsafe: n.is_unsafe, unsafe_blocks: n.unsafe_blocks })
+        .collect();
+    functions.sort_by(|a, b| a.id.cmp(&b.id));
+    UnsafeReport { entries: entries.to_vec(), functions, paths }
+}
+
+/// The part of `cg` on call paths from `entries` to unsafe code: nodes the
+/// entries reach that reach unsafe code themselves, and the edges between them.
+pub fn unsafe_subgraph(cg: &CallGraph, entries: &[String]) -> CallGraph {
+    let forward = reachable_from(cg, entries);
+    let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for node in &cg.nodes {
+        for callee in node.callee_ids() {
+            callers.entry(callee).or_default().push(node.id.as_str());
+        }
+    }
+    let mut keep: HashSet<&str> = HashSet::new();
+    let mut stack: Vec<&str> = cg.nodes.iter()
+        .filter(|n| uses_unsafe(n) && forward.contains(n.id.as_str()))
+        .map(|n| n.id.as_str())
+        .collect();
+    while let Some(id) = stack.pop() {
+        if forward.contains(id) && keep.insert(id) {
+            stack.extend(callers.get(id).into_iter().flatten());
+        }
+    }
+
+    let nodes = cg.nodes.iter()
+        .filter(|n| keep.contains(n.id.as_str()))
+        .map(|n| CallGraphNode {
+            callees: n.callees.iter().filter(|e| keep.contains(e.target.as_str())).cloned().collect(),
+            ..n.clone()
+        })
+        .collect();
+    CallGraph::new(nodes)
+}
+
+impl fmt::Display for UnsafeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Unsafe code reachable from {}: {} functions", self.entries.join(", "), self.functions.len())?;
+        for u in &self.functions {
+            let kind = match (u.is_unsafe, u.unsafe_blocks) {
+                (true, 0) => "unsafe fn".to_string(),
+                (true, n) => format!("unsafe fn, {} unsafe block(s)", n),
+                (false, n) => format!("{} unsafe block(s)", n),
+            };
+            match &u.location {
+                Some(location) => writeln!(f, "  {} ({}) [{}]", u.id, location, kind)?,
+                None => writeln!(f, "  {} [{}]", u.id, kind)?,
+            }
+        }
+        if !self.paths.is_empty() {
+            writeln!(f, "Paths:")?;
+        }
+        for p in &self.paths {
+            writeln!(f, "  {}", p.path.join(" -> "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    
+
+    fn node(id: &str, callees: &[&str], is_unsafe: bool, unsafe_blocks: usize) -> CallGraphNode {
+        CallGraphNode { is_unsafe, unsafe_blocks, ..fixtures::node(id, callees) }
+    }
+
+    fn graph() -> CallGraph {
+        CallGraph::new(vec![
+            node("app::main", &["app::parse", "app::log"], false, 0),
+            node("app::parse", &["app::read_raw", "app::log"], false, 1),
+            node("app::read_raw", &[], true, 0),
+            node("app::log", &[], false, 0),
+            node("app::unused", &["app::read_raw"], false, 2),
+        ])
+    }
+
+    #[test]
+    fn test_unsafe_reachability() {
+        let report = unsafe_reachability(&graph(), &["app::main".to_string()]);
+        let ids: Vec<&str> = report.functions.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::parse", "app::read_raw"]);
+        let paths: Vec<String> = report.paths.iter().map(|p| p.path.join(" -> ")).collect();
+        assert_eq!(paths, vec!["app::main -> app::parse", "app::main -> app::parse -> app::read_raw"]);
+        let text = report.to_string();
+        assert!(text.contains("  app::read_raw [unsafe fn]"), "{}", text);
+        assert!(text.contains("  app::parse [1 unsafe block(s)]"), "{}", text);
+    }
+
+    #[test]
+    fn test_unsafe_subgraph() {
+        let sub = unsafe_subgraph(&graph(), &["app::main".to_string()]);
+        let ids: Vec<&str> = sub.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::main", "app::parse", "app::read_raw"]);
+        let main: Vec<&str> = sub.nodes[0].callee_ids().collect();
+        assert_eq!(main, vec!["app::parse"]);
+    }
+}
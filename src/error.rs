@@ -0,0 +1,200 @@
+//! Crate-wide error type for the library API and the CLI.
+//!
+//! Each variant carries enough context to tell the user which file failed,
+//! why, and (via [`Error::suggestion`]) what to try next. The CLI maps the
+//! variants to distinct exit codes with [`Error::exit_code`].
+
+use std::path::PathBuf;
+
+use crate::domain::index::AnalysisError;
+
+/// Generic failure (indexer, SCIP ingestion, storage, ...).
+pub const EXIT_FAILURE: i32 = 1;
+/// Nothing to analyze: no workspace given, manifest missing, no sources.
+pub const EXIT_NO_INPUT: i32 = 2;
+/// Some sources failed to parse under `--strict`.
+pub const EXIT_PARSE: i32 = 3;
+/// The graph or a report could not be written.
+pub const EXIT_EXPORT: i32 = 4;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no input given")]
+    NoInput,
+
+    #[error("workspace manifest not found: {}", .0.display())]
+    ManifestNotFound(PathBuf),
+
+    #[error("failed to load workspace {}", manifest.display())]
+    WorkspaceLoad {
+        manifest: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("no Rust sources found in {}", .0.display())]
+    NoSources(PathBuf),
+
+    #[error("failed to read source from stdin")]
+    Stdin(#[source] std::io::Error),
+
+    #[error("{} file(s) failed to parse", .0.len())]
+    Parse(Vec<AnalysisError>),
+
+    /// The cause names the index that failed when several are ingested.
+    #[error("failed to ingest SCIP index")]
+    Scip(#[source] anyhow::Error),
+
+    #[error("failed to ingest LSIF dump")]
+    Lsif(#[source] anyhow::Error),
+
+    #[error("indexing failed")]
+    Index(#[source] anyhow::Error),
+
+    #[error("MIR analysis failed")]
+    Mir(#[source] anyhow::Error),
+
+    #[error("failed to fetch crate {spec}")]
+    Fetch {
+        spec: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to check out {url}")]
+    GitClone {
+        url: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to open symbol store {}", path.display())]
+    Store {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to read graph {}", path.display())]
+    GraphLoad {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to read coverage report {}", path.display())]
+    Coverage {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to read config {}", path.display())]
+    Config {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to read taint config {}", path.display())]
+    TaintConfig {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to diff against {since}")]
+    GitDiff {
+        since: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("failed to write {}", path.display())]
+    Export {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to save graph snapshot {}", path.display())]
+    Snapshot {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// `flag` names the option whose function could not be resolved.
+    #[error("{flag} {lookup}")]
+    Lookup {
+        flag: &'static str,
+        lookup: crate::domain::search::LookupError,
+    },
+
+    #[error("failed to render {}", path.display())]
+    Render {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NoInput | Error::ManifestNotFound(_) | Error::NoSources(_) | Error::Stdin(_) => EXIT_NO_INPUT,
+            Error::Parse(_) => EXIT_PARSE,
+            Error::Export { .. } | Error::Snapshot { .. } | Error::Render { .. } => EXIT_EXPORT,
+            _ => EXIT_FAILURE,
+        }
+    }
+
+    /// What the user can do about it, when there is an obvious next step.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        match self {
+            Error::NoInput => Some("pass --workspace path/to/Cargo.toml (or --scip <index>, --load-graph <snapshot>, --input -, --eval <code>)"),
+            Error::ManifestNotFound(_) => Some("--workspace takes the workspace Cargo.toml or the directory containing it"),
+            Error::WorkspaceLoad { .. } => Some("check that `cargo metadata` succeeds for this manifest"),
+            Error::NoSources(_) => Some("make sure the workspace members have lib or bin targets"),
+            Error::Stdin(_) => Some("pipe UTF-8 Rust source in, e.g. `mr_hedgehog --input - --output - < src/main.rs`"),
+            Error::Parse(_) => Some("fix the syntax errors above, or drop --strict to build the graph without those files"),
+            Error::Scip(_) => Some("regenerate the index (e.g. `rust-analyzer scip .`) or drop --scip"),
+            Error::Lsif(_) => Some("check that the file is an LSIF dump (JSON lines) or pass a SCIP index with --scip"),
+            Error::Index(_) => Some("install the indexer or pass a pre-built index with --scip"),
+            Error::Mir(_) => Some("install a nightly toolchain with rustc-dev (`rustup component add rustc-dev llvm-tools --toolchain nightly`) and install the driver with `cargo +nightly install --path mir_driver`"),
+            Error::Fetch { .. } => Some("check the name and version on crates.io (`fetch serde_json@1.0`) and that cargo can reach the registry"),
+            Error::GitClone { .. } => Some("check the URL and revision (`--git https://github.com/org/repo#main`) and that git can reach the host"),
+            Error::Store { .. } => Some("another process may hold the database; try --store mem"),
+            Error::GraphLoad { .. } => Some("pass a file written by --save-graph or --format json"),
+            Error::Coverage { .. } => Some("pass an LCOV file (`cargo llvm-cov --lcov`) or `cargo llvm-cov --json` output"),
+            Error::Config { .. } => Some("fix the file, run `mr_hedgehog init --force` for a fresh one, or pass --no-config"),
+            Error::TaintConfig { .. } => Some("expected `sources = [..]` and `sinks = [..]` lists of function patterns"),
+            Error::GitDiff { .. } => Some("run inside a git checkout and pass a revision git knows (`--since HEAD~1`, `--since origin/main`)"),
+            Error::Export { .. } | Error::Snapshot { .. } => Some("check that the output directory exists and is writable"),
+            Error::Lookup { .. } => Some("pass the full path or `Type::method` form listed by `mr_hedgehog find`"),
+            Error::Render { .. } => Some("install Graphviz (https://graphviz.org/download/) or drop --render"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_and_messages() {
+        let parse = Error::Parse(vec![AnalysisError { file: "src/a.rs".to_string(), line: Some(3), error: "expected `;`".to_string() }]);
+        assert_eq!(parse.exit_code(), EXIT_PARSE);
+        assert_eq!(parse.to_string(), "1 file(s) failed to parse");
+
+        let export = Error::Export {
+            path: PathBuf::from("out/graph.dot"),
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "missing"),
+        };
+        assert_eq!(export.exit_code(), EXIT_EXPORT);
+        assert_eq!(export.to_string(), "failed to write out/graph.dot");
+        assert_eq!(Error::NoInput.exit_code(), EXIT_NO_INPUT);
+    }
+}
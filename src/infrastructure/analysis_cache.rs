@@ -0,0 +1,127 @@
+//! Analysis Cache Module
+//!
+//! Persists per-file summaries so that unchanged files are not re-parsed on
+//! the next run. Each file gets one bincode blob keyed by crate and path;
+//! an entry is only used when the stored content hash and format version match.
+//! Summaries are also kept in memory, so a long-running process (`--watch`)
+//! only re-parses the files that changed, with or without a cache directory.
+
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+
+use crate::domain::summary::{FileSummary, StableHasher, SUMMARY_FORMAT_VERSION};
+
+/// Cache of [`FileSummary`] values, in memory and optionally on disk.
+#[derive(Debug)]
+pub struct AnalysisCache {
+    dir: Option<PathBuf>,
+    memory: DashMap<(String, String), FileSummary>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl AnalysisCache {
+    /// Cache persisted under `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_dir(Some(dir.into()))
+    }
+
+    /// Cache that lives only as long as this process.
+    pub fn in_memory() -> Self {
+        Self::with_dir(None)
+    }
+
+    fn with_dir(dir: Option<PathBuf>) -> Self {
+        Self { dir, memory: DashMap::new(), hits: AtomicUsize::new(0), misses: AtomicUsize::new(0) }
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    fn entry_path(dir: &Path, crate_name: &str, file_path: &str) -> PathBuf {
+        let mut hasher = StableHasher::default();
+        (crate_name, file_path).hash(&mut hasher);
+        dir.join(format!("{:016x}.bin", hasher.finish()))
+    }
+
+    /// Cached summary for a file, if its contents are unchanged.
+    pub fn load(&self, crate_name: &str, file_path: &str, content_hash: u64) -> Option<FileSummary> {
+        let key = (crate_name.to_string(), file_path.to_string());
+        let found = self.memory.get(&key)
+            .map(|s| s.clone())
+            .filter(|s| s.content_hash == content_hash)
+            .or_else(|| {
+                let dir = self.dir.as_deref()?;
+                let bytes = fs::read(Self::entry_path(dir, crate_name, file_path)).ok()?;
+                let (version, s) = bincode::deserialize::<(u32, FileSummary)>(&bytes).ok()?;
+                let valid = version == SUMMARY_FORMAT_VERSION
+                    && s.content_hash == content_hash
+                    && s.crate_name == crate_name
+                    && s.file_path == file_path;
+                valid.then(|| {
+                    self.memory.insert(key, s.clone());
+                    s
+                })
+            });
+        let counter = if found.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    pub fn save(&self, summary: &FileSummary) -> Result<()> {
+        self.memory.insert((summary.crate_name.clone(), summary.file_path.clone()), summary.clone());
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+        let bytes = bincode::serialize(&(SUMMARY_FORMAT_VERSION, summary))?;
+        let path = Self::entry_path(dir, &summary.crate_name, &summary.file_path);
+        fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// `(hits, misses)` since this cache was opened.
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::summary::content_hash;
+
+    #[test]
+    fn test_cache_roundtrip_and_invalidation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = AnalysisCache::new(tmp.path().join("analysis"));
+        let code = "fn main() { helper(); }\nfn helper() {}";
+        let hash = content_hash("app", "src/main.rs", code);
+
+        assert!(cache.load("app", "src/main.rs", hash).is_none());
+        let summary = FileSummary::extract("app", "src/main.rs", code).unwrap();
+        cache.save(&summary).unwrap();
+
+        let cached = cache.load("app", "src/main.rs", hash).unwrap();
+        assert_eq!(cached.functions.len(), 2);
+        // Edited contents invalidate the entry
+        let edited = content_hash("app", "src/main.rs", "fn main() {}");
+        assert!(cache.load("app", "src/main.rs", edited).is_none());
+        assert_eq!(cache.stats(), (1, 2));
+
+        // A fresh process reads the entry back from disk
+        let reopened = AnalysisCache::new(tmp.path().join("analysis"));
+        assert!(reopened.load("app", "src/main.rs", hash).is_some());
+
+        let memory = AnalysisCache::in_memory();
+        memory.save(&summary).unwrap();
+        assert!(memory.load("app", "src/main.rs", hash).is_some());
+        assert!(memory.dir().is_none());
+    }
+}
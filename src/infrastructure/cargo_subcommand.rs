@@ -0,0 +1,159 @@
+//! `cargo tracecraft` defaults.
+//!
+//! The `cargo-tracecraft` binary is what cargo runs for `cargo tracecraft`.
+//! It finds the workspace around the current directory and fills in what the
+//! plain command line would have to spell out before handing the arguments
+//! to `mr_hedgehog`: the workspace manifest, every binary's `main` as an
+//! entry point and an output file under `target/tracecraft/`. Anything given
+//! on the command line or in `tracecraft.toml` is left alone.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
+
+use crate::config::ProjectConfig;
+use crate::domain::source::target_crate;
+use crate::ports::format_extension;
+
+/// Flags that name an input; with any of them `--workspace` is not added.
+const INPUT_FLAGS: [&str; 8] = ["--workspace", "--scip", "--lsif", "--load-graph", "--input", "-i", "--eval", "--folder"];
+
+/// Subcommands; none of them writes the graph export, so with one no
+/// `--output` is added.
+const SUBCOMMANDS: [&str; 14] = ["serve", "trace", "bundle", "stats", "find", "impact", "query", "repl", "browse", "lsp", "diff", "fetch", "init", "help"];
+
+/// Where the default output goes, relative to the workspace root.
+pub const OUTPUT_DIR: &str = "target/tracecraft";
+
+/// What `cargo tracecraft` knows about the workspace it runs in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubcommandDefaults {
+    /// Workspace root `Cargo.toml`.
+    pub manifest: PathBuf,
+    /// Directory the default output goes to.
+    pub output_dir: PathBuf,
+    /// `main` of every binary target of the workspace packages.
+    pub entries: Vec<String>,
+}
+
+impl SubcommandDefaults {
+    /// Defaults for the workspace containing `dir`.
+    pub fn detect(dir: &Path) -> Result<Self> {
+        let metadata = MetadataCommand::new().current_dir(dir).no_deps().exec()
+            .context("no Cargo workspace around the current directory")?;
+        let mut entries: Vec<String> = metadata.workspace_packages().into_iter()
+            .flat_map(|package| {
+                let default_bin = package.manifest_path.with_file_name("src").join("main.rs");
+                package.targets.iter()
+                    .filter(|t| t.kind.iter().any(|k| k == "bin"))
+                    .map(move |t| match t.src_path == default_bin {
+                        true => format!("{}::main", package.name),
+                        false => format!("{}::main", target_crate(&package.name, "bin", &t.name)),
+                    })
+            })
+            .collect();
+        entries.sort();
+        entries.dedup();
+        let root = metadata.workspace_root.into_std_path_buf();
+        Ok(Self { manifest: root.join("Cargo.toml"), output_dir: root.join(OUTPUT_DIR), entries })
+    }
+
+    /// The graph file [`apply`](Self::apply) adds as `--output`: none when
+    /// `args` or `config` name one or a subcommand is run instead.
+    pub fn output(&self, args: &[String], config: Option<&ProjectConfig>) -> Option<PathBuf> {
+        let config = config.cloned().unwrap_or_default();
+        let exports_graph = !args.iter().any(|a| SUBCOMMANDS.contains(&a.as_str()));
+        if !exports_graph || has_flag(args, "--output", Some("-o")) || config.output.path.is_some() {
+            return None;
+        }
+        let format = flag_value(args, "--format", Some("-f")).or(config.output.format).unwrap_or_else(|| "dot".to_string());
+        Some(self.output_dir.join("callgraph").with_extension(extension(&format)))
+    }
+
+    /// `args` with the defaults they and `config` leave open filled in.
+    /// Added flags go first, where top-level flags are accepted before a
+    /// subcommand; a bare `--workspace` gets the detected manifest.
+    pub fn apply(&self, args: Vec<String>, config: Option<&ProjectConfig>) -> Vec<String> {
+        let manifest = self.manifest.to_string_lossy().into_owned();
+        let mut args = args;
+        if let Some(i) = args.iter().position(|a| a == "--workspace") {
+            if args.get(i + 1).is_none_or(|next| next.starts_with('-')) {
+                args.insert(i + 1, manifest.clone());
+            }
+        }
+
+        let config = config.cloned().unwrap_or_default();
+        let mut added = Vec::new();
+        let has_input = args.iter().any(|a| INPUT_FLAGS.iter().any(|flag| flag_matches(a, flag)));
+        if !has_input && config.input.workspace.is_none() {
+            added.extend(["--workspace".to_string(), manifest]);
+        }
+        // Entry ids are the syn builder's; indices and snapshots name nodes their own way
+        let analyzes_workspace = !has_input || has_flag(&args, "--workspace", None);
+        if analyzes_workspace && !has_flag(&args, "--entry", None) && config.entry.is_empty() {
+            for entry in &self.entries {
+                added.extend(["--entry".to_string(), entry.clone()]);
+            }
+        }
+        if let Some(path) = self.output(&args, Some(&config)) {
+            added.extend(["--output".to_string(), path.to_string_lossy().into_owned()]);
+        }
+        added.extend(args);
+        added
+    }
+}
+
+/// `arg` is `flag` or `flag=value`.
+fn flag_matches(arg: &str, flag: &str) -> bool {
+    arg == flag || arg.strip_prefix(flag).is_some_and(|rest| rest.starts_with('='))
+}
+
+fn has_flag(args: &[String], long: &str, short: Option<&str>) -> bool {
+    args.iter().any(|a| flag_matches(a, long) || short.is_some_and(|s| a == s))
+}
+
+/// Value of the first occurrence of a flag, as `--flag value`, `--flag=value` or `-f value`.
+fn flag_value(args: &[String], long: &str, short: Option<&str>) -> Option<String> {
+    let i = args.iter().position(|a| flag_matches(a, long) || short.is_some_and(|s| a == s))?;
+    match args[i].split_once('=') {
+        Some((_, value)) => Some(value.to_string()),
+        None => args.get(i + 1).cloned(),
+    }
+}
+
+/// File extension of the first of comma separated output formats.
+fn extension(formats: &str) -> &'static str {
+    formats.split(',').next().and_then(|format| format_extension(format.trim())).unwrap_or("dot")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let defaults = SubcommandDefaults {
+            manifest: PathBuf::from("/ws/Cargo.toml"),
+            output_dir: PathBuf::from("/ws/target/tracecraft"),
+            entries: vec!["app::main".to_string(), "tool::main".to_string()],
+        };
+        assert_eq!(defaults.apply(args(&["--workspace", "--format", "json"]), None), args(&[
+            "--entry", "app::main", "--entry", "tool::main",
+            "--output", "/ws/target/tracecraft/callgraph.json",
+            "--workspace", "/ws/Cargo.toml", "--format", "json",
+        ]));
+        assert_eq!(defaults.apply(args(&["stats"]), None), args(&[
+            "--workspace", "/ws/Cargo.toml", "--entry", "app::main", "--entry", "tool::main", "stats",
+        ]));
+        assert_eq!(defaults.apply(args(&["--entry=run", "-o", "-"]), None), args(&["--workspace", "/ws/Cargo.toml", "--entry=run", "-o", "-"]));
+
+        let config = ProjectConfig::parse("entry = [\"serve\"]\n[output]\npath = \"graph.dot\"\n").unwrap();
+        assert_eq!(defaults.apply(args(&["--scip", "index.scip"]), Some(&config)), args(&["--scip", "index.scip"]));
+        assert_eq!(defaults.apply(args(&["--scip", "index.scip", "-o", "-"]), None), args(&["--scip", "index.scip", "-o", "-"]));
+    }
+}
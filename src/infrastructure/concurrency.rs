@@ -1,5 +1,5 @@
-/// Concurrency management for Mr. Hedgehog.
-/// Configures thread pools to reserve system capacity for UI/LSP.
+//! Concurrency management for Mr. Hedgehog.
+//! Configures thread pools to reserve system capacity for UI/LSP.
 
 use anyhow::Result;
 
@@ -14,8 +14,8 @@ pub fn init_thread_pool() -> Result<()> {
         .num_threads(workers)
         .build_global()?;
     
-    println!(
-        "[Mr. Hedgehog] Initialized thread pool: {} workers (system has {} cores)",
+    tracing::debug!(
+        "Initialized thread pool: {} workers (system has {} cores)",
         workers, cores
     );
     
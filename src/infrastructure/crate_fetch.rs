@@ -0,0 +1,155 @@
+//! Published crates as analysis input (`fetch`).
+//!
+//! `serde_json@1.0` is resolved and unpacked by cargo itself: a throwaway
+//! package depending on the crate is vendored with `cargo vendor`, and the
+//! crate's directory under `vendor/` is then analyzed like any workspace.
+//! The package lives in a cache directory per spec, so fetching the same
+//! crate again only re-checks the registry.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+
+use crate::infrastructure::project_loader::ProjectLoader;
+
+/// A crate name with an optional version requirement, as `cargo add` takes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateSpec {
+    pub name: String,
+    /// Requirement such as `1.0` or `=1.0.108`; the newest release without one.
+    pub version: Option<String>,
+}
+
+impl CrateSpec {
+    /// `name` or `name@version`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => (name, Some(version.trim())),
+            None => (spec, None),
+        };
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            bail!("'{}' is not a crate name", name);
+        }
+        if version.is_some_and(str::is_empty) {
+            bail!("missing version after '@' in '{}'", spec);
+        }
+        // The requirement goes into a manifest as a TOML string
+        let requirement = |c: char| c.is_ascii_alphanumeric() || " .*^~=<>,-+".contains(c);
+        if let Some(version) = version.filter(|v| !v.chars().all(requirement)) {
+            bail!("'{}' is not a version requirement", version);
+        }
+        Ok(Self { name: name.to_string(), version: version.map(str::to_string) })
+    }
+
+    /// Cache directory name, safe in paths.
+    fn dir_name(&self) -> String {
+        let version = self.version.as_deref().unwrap_or("latest");
+        let version: String = version.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '_' }).collect();
+        format!("{}@{}", self.name, version)
+    }
+}
+
+/// Default cache: `tracecraft/crates` in the system temp directory.
+pub fn default_cache_dir() -> PathBuf {
+    std::env::temp_dir().join("tracecraft").join("crates")
+}
+
+/// Download and unpack `spec` under `cache_dir`; returns the `Cargo.toml`
+/// of the unpacked crate.
+pub fn fetch(spec: &CrateSpec, cache_dir: &Path) -> Result<PathBuf> {
+    let dir = cache_dir.join(spec.dir_name());
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    std::fs::write(dir.join("Cargo.toml"), manifest(spec))?;
+    std::fs::write(dir.join("lib.rs"), "")?;
+
+    tracing::info!("[Fetch] Vendoring {} into {}", spec.name, dir.display());
+    let output = Command::new(ProjectLoader::find_cargo_binary())
+        .args(["vendor", "--versioned-dirs", "--quiet", "vendor"])
+        .current_dir(&dir)
+        .output()
+        .context("failed to run cargo vendor")?;
+    if !output.status.success() {
+        bail!("cargo vendor failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let lock = std::fs::read_to_string(dir.join("Cargo.lock")).context("cargo vendor wrote no Cargo.lock")?;
+    let version = resolved_version(&lock, &spec.name)?;
+    let manifest = dir.join("vendor").join(format!("{}-{}", spec.name, version)).join("Cargo.toml");
+    if !manifest.is_file() {
+        bail!("{} is missing after cargo vendor", manifest.display());
+    }
+    Ok(manifest)
+}
+
+/// The throwaway package. Its own `[workspace]` keeps cargo from looking
+/// for one above the cache directory, and excludes the vendored crates so
+/// each can be loaded as a workspace of its own.
+fn manifest(spec: &CrateSpec) -> String {
+    format!(
+        "[package]\nname = \"tracecraft-fetch\"\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\n\n[lib]\npath = \"lib.rs\"\n\n[dependencies]\n{} = \"{}\"\n\n[workspace]\nexclude = [\"vendor\"]\n",
+        spec.name,
+        spec.version.as_deref().unwrap_or("*"),
+    )
+}
+
+/// Version of `name` the throwaway package depends on, from its lock file.
+/// Lock files list a dependency as `name`, or `name version` when several
+/// versions of it are in the graph.
+fn resolved_version(lock: &str, name: &str) -> Result<String> {
+    let lock: toml::Value = toml::from_str(lock).context("unreadable Cargo.lock")?;
+    let packages = lock.get("package").and_then(|p| p.as_array()).map(Vec::as_slice).unwrap_or_default();
+    let field = |package: &toml::Value, key: &str| package.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let root_dep = packages.iter()
+        .find(|p| field(p, "name").as_deref() == Some("tracecraft-fetch"))
+        .and_then(|root| root.get("dependencies")?.as_array()?.iter().filter_map(|d| d.as_str()).find(|d| d.split(' ').next() == Some(name)).map(str::to_string));
+    if let Some((_, version)) = root_dep.as_deref().and_then(|d| d.split_once(' ')) {
+        return Ok(version.to_string());
+    }
+    packages.iter()
+        .find(|p| field(p, "name").as_deref() == Some(name))
+        .and_then(|p| field(p, "version"))
+        .with_context(|| format!("{} is not in Cargo.lock", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(CrateSpec::parse("serde_json@1.0").unwrap(), CrateSpec { name: "serde_json".into(), version: Some("1.0".into()) });
+        assert_eq!(CrateSpec::parse("regex").unwrap().version, None);
+        assert_eq!(CrateSpec::parse("syn@=2.0.1").unwrap().dir_name(), "syn@_2.0.1");
+        assert!(CrateSpec::parse("serde json").is_err());
+        assert!(CrateSpec::parse("serde@").is_err());
+        assert_eq!(CrateSpec::parse("serde@>=1.0, <2").unwrap().version.as_deref(), Some(">=1.0, <2"));
+        assert!(CrateSpec::parse("serde@1\"\n[patch]").is_err());
+        assert!(manifest(&CrateSpec::parse("itoa").unwrap()).contains("itoa = \"*\""));
+    }
+
+    #[test]
+    fn test_resolved_version() {
+        let lock = r#"
+version = 4
+
+[[package]]
+name = "itoa"
+version = "0.4.8"
+
+[[package]]
+name = "itoa"
+version = "1.0.18"
+
+[[package]]
+name = "tracecraft-fetch"
+version = "0.0.0"
+dependencies = ["itoa 1.0.18"]
+"#;
+        assert_eq!(resolved_version(lock, "itoa").unwrap(), "1.0.18");
+        let single = "[[package]]\nname = \"ryu\"\nversion = \"1.0.20\"\n\n[[package]]\nname = \"tracecraft-fetch\"\nversion = \"0.0.0\"\ndependencies = [\"ryu\"]\n";
+        assert_eq!(resolved_version(single, "ryu").unwrap(), "1.0.20");
+        assert!(resolved_version(single, "serde").is_err());
+    }
+}
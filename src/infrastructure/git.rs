@@ -0,0 +1,133 @@
+//! Git
+//!
+//! Asks `git` which lines changed, for `impact`, and checks out remote
+//! repositories for `--git`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+
+/// `git diff --unified=0` of the working tree against `since`, with paths
+/// relative to `dir` and limited to the files below it. Untracked files
+/// (not ignored) follow as wholly added ones.
+pub fn diff_since(dir: &Path, since: &str) -> Result<String> {
+    not_an_option("revision", since)?;
+    let mut diff = git_output(dir, &["diff", "--relative", "--unified=0", "--no-color", "--no-ext-diff", since, "--"])?;
+    for path in git_output(dir, &["ls-files", "--others", "--exclude-standard"])?.lines() {
+        // Binary and unreadable files have no lines to map onto functions
+        let Ok(content) = std::fs::read_to_string(dir.join(path)) else { continue };
+        diff.push_str(&format!("--- /dev/null\n+++ b/{}\n@@ -0,0 +1,{} @@\n", path, content.lines().count()));
+    }
+    Ok(diff)
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A repository to analyze, `https://github.com/foo/bar` with an optional
+/// `#rev` (branch, tag or commit).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    pub url: String,
+    /// The remote's default branch when unset.
+    pub rev: Option<String>,
+}
+
+impl GitSource {
+    pub fn parse(spec: &str) -> Self {
+        match spec.rsplit_once('#') {
+            Some((url, rev)) if !rev.is_empty() => Self { url: url.to_string(), rev: Some(rev.to_string()) },
+            Some((url, _)) => Self { url: url.to_string(), rev: None },
+            None => Self { url: spec.to_string(), rev: None },
+        }
+    }
+
+    /// Checkout directory name, safe in paths.
+    fn dir_name(&self) -> String {
+        let url = self.url.trim_end_matches('/').trim_end_matches(".git");
+        let url = url.split("://").last().unwrap_or(url);
+        let name = format!("{}@{}", url, self.rev.as_deref().unwrap_or("HEAD"));
+        name.chars().map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '@') { c } else { '_' }).collect()
+    }
+}
+
+/// Default place for checkouts: `tracecraft/git` in the system temp directory.
+pub fn default_checkout_dir() -> PathBuf {
+    std::env::temp_dir().join("tracecraft").join("git")
+}
+
+/// Shallow checkout of `source` below `parent`, one directory per URL and
+/// revision; an existing one is fetched again and reset. Returns its path.
+pub fn checkout(source: &GitSource, parent: &Path) -> Result<PathBuf> {
+    not_an_option("repository URL", &source.url)?;
+    if let Some(rev) = &source.rev {
+        not_an_option("revision", rev)?;
+    }
+    let dir = parent.join(source.dir_name());
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    if !dir.join(".git").exists() {
+        git(&dir, &["init", "--quiet"])?;
+        git(&dir, &["remote", "add", "origin", &source.url])?;
+    }
+    // Fetching the one revision works for branches, tags and (on most hosts) commit ids alike
+    git(&dir, &["fetch", "--quiet", "--depth", "1", "origin", source.rev.as_deref().unwrap_or("HEAD")])?;
+    git(&dir, &["checkout", "--quiet", "--force", "FETCH_HEAD"])?;
+    git(&dir, &["clean", "--quiet", "-fdx", "--exclude=target/"])?;
+    Ok(dir)
+}
+
+/// Values git would take for one of its own options (`--upload-pack=..`).
+fn not_an_option(what: &str, value: &str) -> Result<()> {
+    if value.starts_with('-') {
+        bail!("{} {} starts with '-'", what, value);
+    }
+    Ok(())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!("git {}: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        let source = GitSource::parse("https://github.com/foo/bar#v1.2");
+        assert_eq!(source, GitSource { url: "https://github.com/foo/bar".into(), rev: Some("v1.2".into()) });
+        assert_eq!(source.dir_name(), "github.com_foo_bar@v1.2");
+        assert_eq!(GitSource::parse("https://github.com/foo/bar.git").dir_name(), "github.com_foo_bar@HEAD");
+        assert_eq!(GitSource::parse("git@github.com:foo/bar#").rev, None);
+    }
+
+    #[test]
+    fn test_option_like_values_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = checkout(&GitSource::parse("--upload-pack=touch pwned"), dir.path()).unwrap_err();
+        assert!(err.to_string().contains("starts with '-'"), "{}", err);
+        assert!(checkout(&GitSource::parse("https://example.com/repo#--output=x"), dir.path()).is_err());
+        assert!(diff_since(dir.path(), "--output=x").unwrap_err().to_string().contains("starts with '-'"));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
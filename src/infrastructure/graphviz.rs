@@ -0,0 +1,82 @@
+//! Graphviz Rendering
+//!
+//! Turns an exported DOT file into an image by running the `dot` binary,
+//! for `--render svg|png`.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{bail, Context, Result};
+
+/// Image formats `dot` is asked to produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderFormat {
+    Svg,
+    Png,
+}
+
+impl RenderFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "svg" => Some(RenderFormat::Svg),
+            "png" => Some(RenderFormat::Png),
+            _ => None,
+        }
+    }
+
+    /// File extension, also the `-T` argument of `dot`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            RenderFormat::Svg => "svg",
+            RenderFormat::Png => "png",
+        }
+    }
+}
+
+/// Where the image rendered from `dot_path` goes: next to it, with the
+/// format's extension (`graph.dot` -> `graph.svg`).
+pub fn rendered_path(dot_path: &Path, format: RenderFormat) -> PathBuf {
+    let path = dot_path.with_extension(format.extension());
+    if path == dot_path {
+        // `graph.svg` holding DOT: do not overwrite the input
+        let mut name = dot_path.as_os_str().to_owned();
+        name.push(".");
+        name.push(format.extension());
+        PathBuf::from(name)
+    } else {
+        path
+    }
+}
+
+/// Render `dot_path` with Graphviz; returns the path of the image.
+pub fn render(dot_path: &Path, format: RenderFormat) -> Result<PathBuf> {
+    let output = rendered_path(dot_path, format);
+    let result = Command::new("dot")
+        .arg(format!("-T{}", format.extension()))
+        .arg(dot_path)
+        .arg("-o")
+        .arg(&output)
+        .output();
+
+    let result = match result {
+        Err(e) if e.kind() == ErrorKind::NotFound => bail!("Graphviz `dot` not found in PATH"),
+        other => other.context("Failed to execute dot")?,
+    };
+    if !result.status.success() {
+        bail!("dot failed with exit code {:?}: {}", result.status.code(), String::from_utf8_lossy(&result.stderr).trim());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rendered_path() {
+        assert_eq!(rendered_path(Path::new("out/graph.dot"), RenderFormat::Svg), PathBuf::from("out/graph.svg"));
+        assert_eq!(rendered_path(Path::new("graph"), RenderFormat::Png), PathBuf::from("graph.png"));
+        assert_eq!(rendered_path(Path::new("graph.svg"), RenderFormat::Svg), PathBuf::from("graph.svg.svg"));
+        assert_eq!(RenderFormat::parse("pdf"), None);
+    }
+}
@@ -0,0 +1,107 @@
+//! Hybrid Call Graph Builder.
+//!
+//! Runs the syn-based builder over the sources and overlays the precise edges
+//! from SCIP indices for every function they cover.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::domain::callgraph::CallGraph;
+use crate::domain::cfg::CfgOptions;
+use crate::domain::hybrid::merge_graphs;
+use crate::domain::paths::ProjectRoot;
+use crate::domain::scip_ingest::{ScipIngestor, ScipOptions};
+use crate::domain::source::SourceSet;
+use crate::domain::store::SymbolStore;
+use crate::domain::index::AnalysisError;
+use crate::domain::progress::{NoProgress, Progress};
+use crate::infrastructure::{warn_parse_errors, SimpleCallGraphBuilder};
+use crate::ports::CallGraphBuilder;
+
+pub struct HybridCallGraphBuilder {
+    pub scip_paths: Vec<PathBuf>,
+    pub store: Option<Arc<dyn SymbolStore>>,
+    pub cfg: CfgOptions,
+    pub progress: Arc<dyn Progress>,
+    /// What SCIP locations are made relative to, as the sources' paths are.
+    pub root: Option<ProjectRoot>,
+    /// Add type nodes to the syn graph.
+    pub types: bool,
+    /// Only take call edges from the SCIP index, not imports or references.
+    pub calls_only: bool,
+    /// Add stub nodes for the functions the SCIP indices call but do not define.
+    pub include_external: bool,
+}
+
+impl HybridCallGraphBuilder {
+    pub fn new(scip_paths: Vec<PathBuf>) -> Self {
+        Self { scip_paths, store: None, cfg: CfgOptions::default(), progress: Arc::new(NoProgress), root: None, types: false, calls_only: false, include_external: false }
+    }
+
+    pub fn new_with_store(scip_paths: Vec<PathBuf>, store: Arc<dyn SymbolStore>) -> Self {
+        Self { scip_paths, store: Some(store), cfg: CfgOptions::default(), progress: Arc::new(NoProgress), root: None, types: false, calls_only: false, include_external: false }
+    }
+
+    pub fn with_cfg(mut self, cfg: CfgOptions) -> Self {
+        self.cfg = cfg;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn with_root(mut self, root: ProjectRoot) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    pub fn with_types(mut self) -> Self {
+        self.types = true;
+        self
+    }
+
+    pub fn with_calls_only(mut self) -> Self {
+        self.calls_only = true;
+        self
+    }
+
+    pub fn with_external(mut self) -> Self {
+        self.include_external = true;
+        self
+    }
+}
+
+impl HybridCallGraphBuilder {
+    /// Like [`SimpleCallGraphBuilder::build_with_errors`], with the SCIP edges merged in.
+    pub fn build_with_errors(&self, sources: &SourceSet) -> (CallGraph, Vec<AnalysisError>) {
+        let syn_builder = match &self.store {
+            Some(store) => SimpleCallGraphBuilder::new_with_store(store.clone()),
+            None => SimpleCallGraphBuilder::new(),
+        };
+        let syn_builder = SimpleCallGraphBuilder { types: self.types, ..syn_builder };
+        let (syn_graph, errors) = syn_builder.with_cfg(self.cfg.clone())
+            .with_progress(self.progress.clone())
+            .build_with_errors(sources);
+
+        let options = ScipOptions { calls_only: self.calls_only, include_external: self.include_external, ..Default::default() };
+        let scip_graph = ScipIngestor::ingest_all(&self.scip_paths, self.progress.as_ref(), self.root.as_ref(), options);
+        let graph = match scip_graph {
+            Ok(scip_graph) => merge_graphs(&syn_graph, &scip_graph),
+            Err(e) => {
+                tracing::warn!("failed to ingest SCIP indices: {:#}. Using syn edges only.", e);
+                syn_graph
+            }
+        };
+        (graph, errors)
+    }
+}
+
+impl CallGraphBuilder for HybridCallGraphBuilder {
+    fn build_call_graph(&self, sources: &SourceSet) -> CallGraph {
+        let (graph, errors) = self.build_with_errors(sources);
+        warn_parse_errors(&errors);
+        graph
+    }
+}
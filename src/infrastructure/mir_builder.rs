@@ -0,0 +1,253 @@
+//! MIR Call Graph Builder (`mir` feature).
+//!
+//! Runs `cargo check` on a nightly toolchain with the `mr_hedgehog_mir_driver`
+//! binary (the `mir_driver` package) as `RUSTC_WORKSPACE_WRAPPER`. The
+//! driver compiles each workspace crate through `rustc_driver` and, once
+//! types are checked, walks the MIR of every function: each call terminator
+//! is resolved with `Instance::try_resolve`, so trait calls on concrete
+//! types land on the impl and generic callers link to what they are
+//! instantiated with where rustc knows it. It writes one JSON line per function and per call, which
+//! this builder turns into a [`CallGraph`] with the syn builder's node ids.
+//!
+//! Calls rustc cannot resolve statically (`dyn Trait`, calls on a type
+//! parameter) point at the trait method, as `dynamic` edges.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::callgraph::{CallEdge, CallGraph, CallGraphNode, EdgeKind};
+use crate::domain::index::method_id;
+use crate::domain::paths::ProjectRoot;
+use crate::domain::source::SourceSet;
+use crate::infrastructure::scip_runner::RA_CACHE_DIR;
+use crate::ports::CallGraphBuilder;
+
+/// File name of the driver binary, looked for next to the running executable
+/// and then on `PATH`.
+pub const DRIVER_NAME: &str = "mr_hedgehog_mir_driver";
+
+/// Environment variable naming the directory the driver writes its records to.
+pub const OUT_DIR_ENV: &str = "MR_HEDGEHOG_MIR_OUT";
+
+/// Toolchain the driver was built with; it links that toolchain's rustc.
+pub const DEFAULT_TOOLCHAIN: &str = "nightly";
+
+/// A function as rustc names it, split the way node ids are built.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MirItem {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Modules between the crate root and the item.
+    pub modules: Vec<String>,
+    /// Self type of the impl (or the trait) a method belongs to.
+    pub owner: Option<String>,
+    pub name: String,
+}
+
+impl MirItem {
+    /// Node id in the syn builder's spelling: `app::net::send` for a free
+    /// function, `Client::send@app` or `net::Client::send@app` for a method.
+    pub fn node_id(&self) -> String {
+        match &self.owner {
+            Some(owner) => method_id(&self.krate, &self.modules, owner, &self.name),
+            None => {
+                let mut parts = vec![self.krate.as_str()];
+                parts.extend(self.modules.iter().map(String::as_str));
+                parts.push(&self.name);
+                parts.join("::")
+            }
+        }
+    }
+}
+
+/// One line the driver writes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "lowercase")]
+pub enum MirRecord {
+    /// A function with a body in a workspace crate.
+    Fn { item: MirItem, file: String, line: usize },
+    /// A call terminator in `caller`'s MIR.
+    Call { caller: MirItem, callee: MirItem, file: String, line: usize, dynamic: bool },
+}
+
+/// Builds the call graph of a workspace from rustc's MIR.
+pub struct MirCallGraphBuilder {
+    pub workspace_root: PathBuf,
+    /// The driver binary; next to the running executable or on `PATH` by default.
+    pub driver: Option<PathBuf>,
+    pub toolchain: String,
+}
+
+impl MirCallGraphBuilder {
+    pub fn new(workspace_root: impl Into<PathBuf>) -> Self {
+        Self { workspace_root: workspace_root.into(), driver: None, toolchain: DEFAULT_TOOLCHAIN.to_string() }
+    }
+
+    pub fn with_driver(mut self, driver: impl Into<PathBuf>) -> Self {
+        self.driver = Some(driver.into());
+        self
+    }
+
+    fn driver_path(&self) -> Result<PathBuf> {
+        if let Some(driver) = &self.driver {
+            return Ok(driver.clone());
+        }
+        let exe = std::env::current_exe().context("cannot locate the running executable")?;
+        let driver = exe.with_file_name(format!("{}{}", DRIVER_NAME, std::env::consts::EXE_SUFFIX));
+        if driver.is_file() {
+            return Ok(driver);
+        }
+        which::which(DRIVER_NAME).map_err(|_| anyhow::anyhow!(
+            "{} not found next to {} or on PATH; install it with `cargo +{} install --path mir_driver`",
+            DRIVER_NAME, exe.display(), self.toolchain))
+    }
+
+    /// Check the workspace through the driver and build the graph from its records.
+    pub fn build(&self) -> Result<CallGraph> {
+        let driver = self.driver_path()?;
+        let cache = self.workspace_root.join(RA_CACHE_DIR);
+        let out_dir = cache.join("mir");
+        // Stale records of crates no longer in the workspace must not linger
+        if out_dir.exists() {
+            std::fs::remove_dir_all(&out_dir).with_context(|| format!("failed to clear {}", out_dir.display()))?;
+        }
+        std::fs::create_dir_all(&out_dir).with_context(|| format!("failed to create {}", out_dir.display()))?;
+
+        tracing::info!("[MIR] Checking {} with {}", self.workspace_root.display(), driver.display());
+        // A target directory of its own, so cached check results do not skip the driver
+        let status = Command::new("cargo")
+            .args(["check", "--workspace", "--all-targets"])
+            .current_dir(&self.workspace_root)
+            .env("RUSTUP_TOOLCHAIN", &self.toolchain)
+            .env("RUSTC_WORKSPACE_WRAPPER", &driver)
+            .env("CARGO_TARGET_DIR", cache.join("mir-target"))
+            .env(OUT_DIR_ENV, &out_dir)
+            .status()
+            .context("failed to run cargo check")?;
+        if !status.success() {
+            bail!("cargo check with the MIR driver failed ({})", status);
+        }
+
+        let mut records = Vec::new();
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&out_dir)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "jsonl"))
+            .collect();
+        files.sort();
+        for file in files {
+            records.extend(read_records(&file)?);
+        }
+        Ok(graph_from_records(&records, &ProjectRoot::new(&self.workspace_root)))
+    }
+}
+
+impl CallGraphBuilder for MirCallGraphBuilder {
+    /// The driver reads the workspace itself; `sources` are not used.
+    fn build_call_graph(&self, _sources: &SourceSet) -> CallGraph {
+        self.build().unwrap_or_else(|e| {
+            tracing::warn!("MIR analysis failed: {:#}", e);
+            CallGraph::new(Vec::new())
+        })
+    }
+}
+
+/// The records of one driver output file.
+pub fn read_records(path: &Path) -> Result<Vec<MirRecord>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    text.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("{}:{}: malformed MIR record", path.display(), i + 1)))
+        .collect()
+}
+
+/// Graph of the functions and calls in `records`, locations relative to
+/// `root`. Calls repeat per call site; each caller keeps one edge per
+/// callee at its first site, weighted by the number of calls. A crate
+/// checked for several targets reports its calls once per target; repeated
+/// records count once.
+pub fn graph_from_records(records: &[MirRecord], root: &ProjectRoot) -> CallGraph {
+    let mut nodes: Vec<CallGraphNode> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for record in records {
+        let MirRecord::Fn { item, file, line } = record else { continue };
+        let id = item.node_id();
+        if index.contains_key(&id) {
+            continue;
+        }
+        index.insert(id.clone(), nodes.len());
+        nodes.push(CallGraphNode {
+            id,
+            label: Some(item.name.clone()),
+            location: Some(format!("{}:{}", root.relativize(file), line)),
+            ..Default::default()
+        });
+    }
+    let mut seen = HashSet::new();
+    for record in records {
+        let MirRecord::Call { caller, callee, file, line, dynamic } = record else { continue };
+        if !seen.insert(record) {
+            continue;
+        }
+        let Some(&caller) = index.get(&caller.node_id()) else { continue };
+        let target = callee.node_id();
+        let kind = if *dynamic { EdgeKind::Dynamic } else { EdgeKind::Call };
+        let node = &mut nodes[caller];
+        if node.id == target {
+            continue;
+        }
+        match node.callees.iter_mut().find(|e| e.target == target && e.kind == kind) {
+            Some(edge) => edge.weight += 1,
+            None => node.callees.push(CallEdge::at(target, root.relativize(file), *line).with_kind(kind)),
+        }
+    }
+    CallGraph::new(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(krate: &str, modules: &[&str], owner: Option<&str>, name: &str) -> MirItem {
+        MirItem {
+            krate: krate.to_string(),
+            modules: modules.iter().map(|m| m.to_string()).collect(),
+            owner: owner.map(str::to_string),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_graph_from_records() {
+        let main = item("app", &[], None, "main");
+        let apply = item("app", &["ops"], Some("Add"), "apply");
+        let trait_apply = item("app", &["ops"], Some("Op"), "apply");
+        let records = vec![
+            MirRecord::Fn { item: main.clone(), file: "/ws/src/main.rs".into(), line: 1 },
+            MirRecord::Fn { item: apply.clone(), file: "/ws/src/ops.rs".into(), line: 5 },
+            MirRecord::Call { caller: main.clone(), callee: apply.clone(), file: "/ws/src/main.rs".into(), line: 2, dynamic: false },
+            MirRecord::Call { caller: main.clone(), callee: apply.clone(), file: "/ws/src/main.rs".into(), line: 3, dynamic: false },
+            MirRecord::Call { caller: main.clone(), callee: apply.clone(), file: "/ws/src/main.rs".into(), line: 3, dynamic: false },
+            MirRecord::Call { caller: main.clone(), callee: trait_apply, file: "/ws/src/main.rs".into(), line: 4, dynamic: true },
+            MirRecord::Call { caller: main, callee: item("std", &["mem"], None, "swap"), file: "/ws/src/main.rs".into(), line: 6, dynamic: false },
+        ];
+        let text: String = records.iter().map(|r| serde_json::to_string(r).unwrap() + "\n").collect();
+        assert!(text.starts_with(r#"{"record":"fn","item":{"crate":"app""#));
+
+        let graph = graph_from_records(&records, &ProjectRoot::new("/ws"));
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["app::main", "Add::apply@app"]);
+        let main = &graph.nodes[0];
+        let edges: Vec<(&str, EdgeKind, usize)> = main.callees.iter().map(|e| (e.target.as_str(), e.kind, e.weight)).collect();
+        assert_eq!(edges, vec![
+            ("Add::apply@app", EdgeKind::Call, 2),
+            ("Op::apply@app", EdgeKind::Dynamic, 1),
+            ("std::mem::swap", EdgeKind::Call, 1),
+        ]);
+        assert_eq!(main.callees[0].location().as_deref(), Some("src/main.rs:2"));
+        assert_eq!(graph.nodes[1].location.as_deref(), Some("src/ops.rs:5"));
+    }
+}
@@ -1,6 +1,12 @@
-use syn::{Item, Stmt, Expr};
-use crate::domain::callgraph::{CallGraph, CallGraphNode};
-use crate::domain::index::SymbolIndex;
+use rayon::prelude::*;
+use crate::domain::callgraph::{node_module, CallEdge, CallGraph, CallGraphNode, EdgeKind};
+use crate::domain::index::{method_id, qualified_fn_id, AnalysisError, SymbolIndex};
+use crate::domain::imports::{ImportMap, ReexportTable};
+use crate::domain::cfg::CfgOptions;
+use crate::domain::progress::{NoProgress, Progress};
+use crate::domain::summary::{cfg_content_hash, FileSummary, FnSummary, RawCall, RawTarget};
+use crate::domain::source::{SourceFile, SourceOrigin, SourceSet};
+use crate::domain::links::SourceLinks;
 
 pub mod project_loader;
 pub mod source_manager;
@@ -8,283 +14,959 @@ pub mod expander;
 pub mod concurrency;
 pub mod scip_runner;
 pub mod scip_cache;
+pub mod hybrid_builder;
+pub mod cargo_subcommand;
+pub mod crate_fetch;
+#[cfg(feature = "mir")]
+pub mod mir_builder;
+pub mod analysis_cache;
+pub mod watcher;
+pub mod graphviz;
+pub mod snapshot;
+pub mod git;
+pub mod progress_bar;
 
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 
+use analysis_cache::AnalysisCache;
+
 pub struct SimpleCallGraphBuilder {
     pub store: Option<Arc<dyn crate::domain::store::SymbolStore>>,
+    /// Per-file summary cache; files whose contents are unchanged skip parsing.
+    pub cache: Option<Arc<AnalysisCache>>,
+    /// Build configuration `#[cfg(..)]` attributes are evaluated against.
+    pub cfg: CfgOptions,
+    pub progress: Arc<dyn Progress>,
+    /// Add struct, enum, union and alias nodes with their ownership edges.
+    pub types: bool,
+}
+
+impl Default for SimpleCallGraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SimpleCallGraphBuilder {
     pub fn new() -> Self {
-        Self { store: None }
+        Self { store: None, cache: None, cfg: CfgOptions::default(), progress: Arc::new(NoProgress), types: false }
     }
 
     pub fn new_with_store(store: Arc<dyn crate::domain::store::SymbolStore>) -> Self {
-        Self { store: Some(store) }
+        Self { store: Some(store), cache: None, cfg: CfgOptions::default(), progress: Arc::new(NoProgress), types: false }
     }
-}
 
-impl crate::ports::CallGraphBuilder for SimpleCallGraphBuilder {
-    fn build_call_graph(&self, files: &[(String, String, String)]) -> CallGraph {
-        // Step 1: Build the global symbol index
+    pub fn with_cache(mut self, cache: Arc<AnalysisCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_cfg(mut self, cfg: CfgOptions) -> Self {
+        self.cfg = cfg;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn with_types(mut self) -> Self {
+        self.types = true;
+        self
+    }
+
+    /// Summarize every file in parallel, reusing cached summaries when possible.
+    fn summarize(&self, sources: &SourceSet) -> (Vec<FileSummary>, Vec<AnalysisError>) {
+        self.progress.start("Parsing files", sources.len(), "functions");
+        let results: Vec<Result<FileSummary, AnalysisError>> = sources.files().par_iter().map(|file| {
+            let result = self.summarize_file(file);
+            self.progress.advance(1, result.as_ref().map_or(0, |s| s.functions.len()));
+            result
+        }).collect();
+        self.progress.finish();
+
+        let mut summaries = Vec::with_capacity(results.len());
+        let mut errors = Vec::new();
+        for r in results {
+            match r {
+                Ok(s) => summaries.push(s),
+                Err(e) => errors.push(e),
+            }
+        }
+        (summaries, errors)
+    }
+
+    fn summarize_file(&self, file: &SourceFile) -> Result<FileSummary, AnalysisError> {
+        let hash = cfg_content_hash(file, &self.cfg);
+        if let Some(cached) = self.cache.as_ref().and_then(|c| c.load(&file.crate_name, &file.path, hash)) {
+            return Ok(cached);
+        }
+        let summary = FileSummary::extract_with_cfg(file, &self.cfg)
+            .map_err(|e| AnalysisError::from_syn(&file.path, &e))?;
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.save(&summary) {
+                tracing::warn!("failed to cache {}: {}", file.path, e);
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Build the graph and return the files that failed to parse instead of
+    /// printing them. Unparsable files are left out of the graph.
+    pub fn build_with_errors(&self, sources: &SourceSet) -> (CallGraph, Vec<AnalysisError>) {
+        // Step 1: Summarize files (parse, or reuse cached per-file results)
+        let (summaries, errors) = self.summarize(sources);
+
+        // Step 2: Build the global symbol index
         // Use injected store or default to MemorySymbolStore
         let store = self.store.clone().unwrap_or_else(|| {
             Arc::new(crate::domain::store::MemorySymbolStore::default())
         });
-        
-        let (index, errors) = SymbolIndex::build(files, store);
-        
-        if !errors.is_empty() {
-             eprintln!(" WARN: Encountered {} parse errors:", errors.len());
-             for e in &errors {
-                 eprintln!("  - {}: {}", e.file, e.error);
-             }
-        }
-
-        let mut func_defs = Vec::new();
-
-        // Step 2: Re-parse files to collect nodes (since we can't share ASTs across threads efficiently yet)
-        let asts: Vec<(String, String, syn::File)> = files.iter().filter_map(|(crate_name, file_path, code)| {
-            match syn::parse_file(code) {
-                Ok(ast) => Some((crate_name.clone(), file_path.clone(), ast)),
-                Err(_) => None // Errors already logged
+        let index = SymbolIndex::from_summaries(&summaries, store);
+
+        // Step 3: Collect Nodes (functions, methods and nested items), tagged
+        // with the target kind of test, bench and example files
+        let origins: HashMap<&str, SourceOrigin> = sources.iter()
+            .filter_map(|f| Some((f.path.as_str(), f.origin?)))
+            .collect();
+        let origin = |s: &FileSummary| origins.get(s.file_path.as_str()).copied();
+        let func_defs = summaries.iter()
+            .flat_map(|s| s.functions.iter().map(move |f| (s, f)))
+            .map(|(s, f)| CallGraphNode {
+                id: f.id.as_str().into(),
+                label: Some(f.label.clone()),
+                is_public: f.is_public,
+                is_async: f.is_async,
+                is_unsafe: f.is_unsafe,
+                is_test: f.is_test,
+                is_test_fn: f.is_test_fn,
+                cfg: f.cfg.clone(),
+                location: Some(format!("{}:{}", s.file_path, f.line)),
+                metrics: Some(f.metrics),
+                unsafe_blocks: f.unsafe_blocks,
+                panics: f.panics.clone(),
+                signature: Some(f.signature.clone()),
+                doc: f.doc.clone(),
+                origin: origin(s),
+                ..Default::default()
+            })
+            .collect();
+        let mut graph = CallGraph::new(func_defs);
+
+        // Step 4: Collect `use` imports per file and workspace-wide re-exports
+        let known_crates: HashSet<String> = summaries.iter().map(|s| crate_ident(&s.crate_name)).collect();
+        let mut reexports = ReexportTable::default();
+        for s in &summaries {
+            reexports.add_entries(&s.uses, &s.crate_name, &known_crates);
+        }
+
+        // Step 5: Propagate concrete type arguments from call sites into generic bodies
+        let instantiations = infer_instantiations(&summaries, &index, &known_crates, &reexports);
+
+        // Step 6: Link every file's calls against the index in parallel
+        self.progress.start("Resolving calls", summaries.len(), "edges");
+        let linked: Vec<LinkedFile> = summaries.par_iter().map(|s| {
+            let imports = ImportMap::from_entries(&s.uses, &s.crate_name, &known_crates);
+            let file_ctx = LinkCtx::new(&index, s, &imports, &reexports);
+            let mut calls = Vec::with_capacity(s.functions.len());
+            let mut closures = Vec::new();
+            for func in &s.functions {
+                let ctx = file_ctx.for_fn(func).with_instantiations(instantiations.get(&func.id));
+                calls.push((func.id.as_str(), link_calls(&func.calls, &ctx)));
+                closures.extend(func.closures.iter().map(|c| CallGraphNode {
+                    id: c.id.as_str().into(),
+                    callees: link_calls(&c.calls, &ctx),
+                    label: Some(c.label.clone()),
+                    is_async: c.is_async,
+                    is_test: func.is_test,
+                    cfg: func.cfg.clone(),
+                    location: Some(format!("{}:{}", s.file_path, c.line)),
+                    origin: origin(s),
+                    ..Default::default()
+                }));
             }
+            let edges = calls.iter().map(|(_, e)| e.len()).chain(closures.iter().map(|n| n.callees.len())).sum();
+            self.progress.advance(1, edges);
+            LinkedFile { calls, closures }
         }).collect();
+        self.progress.finish();
 
-        // Step 3: Collect Nodes
-        for (crate_name, _file, ast) in &asts {
-            for item in &ast.items {
-                 if let Item::Fn(func) = item {
-                     let name = func.sig.ident.to_string();
-                     let id = format!("{}::{}", crate_name, name);
-                     let label = Some(format!("{}::{}", crate_name, name));
-                     
-                     func_defs.push(CallGraphNode {
-                         id,
-                         callees: Vec::new(),
-                         label,
-                         // We could store file/line in CallGraphNode if expanded, for now sticking to struct definition
-                     });
-                 }
-                 if let Item::Impl(imp) = item {
-                     if let syn::Type::Path(tp) = &*imp.self_ty {
-                         if let Some(segment) = tp.path.segments.last() {
-                             let type_name = segment.ident.to_string();
-                             for item in &imp.items {
-                                 if let syn::ImplItem::Fn(method) = item {
-                                     let method_name = method.sig.ident.to_string();
-                                     let id = format!("{}::{}@{}", type_name, method_name, crate_name);
-                                     let label = Some(format!("{}::{}", type_name, method_name));
-                                     
-                                     func_defs.push(CallGraphNode {
-                                         id, 
-                                         callees: Vec::new(),
-                                         label,
-                                     });
-                                 }
-                             }
-                         }
-                     }
-                 }
+        // Step 7: Attach the edges in file order (the first node wins for duplicate ids)
+        let mut position: HashMap<String, usize> = HashMap::with_capacity(graph.nodes.len());
+        for (i, node) in graph.nodes.iter().enumerate() {
+            position.entry(node.id.to_string()).or_insert(i);
+        }
+        for file in linked {
+            for (id, edges) in file.calls {
+                if let Some(&i) = position.get(id) {
+                    graph.nodes[i].callees.extend(edges);
+                }
             }
+            graph.nodes.extend(file.closures);
         }
 
-        let mut graph = CallGraph::new(func_defs);
+        // Step 8: Link trait method declarations to the impl methods implementing
+        // them; the trait is matched by name and crate, so same-named traits of
+        // different crates stay apart
+        let mut declared: HashMap<(String, String), Vec<(&str, String)>> = HashMap::new();
+        for s in &summaries {
+            for (trait_name, method, module) in &s.trait_methods {
+                declared.entry((trait_name.clone(), crate_ident(&s.crate_name))).or_default()
+                    .push((method.as_str(), method_id(&s.crate_name, module, trait_name, method)));
+            }
+        }
+        for s in &summaries {
+            let imports = ImportMap::from_entries(&s.uses, &s.crate_name, &known_crates);
+            for (trait_path, type_name, module) in &s.trait_impls {
+                let Some(key) = trait_key(trait_path, module, &s.crate_name, &imports, &reexports, &known_crates) else { continue };
+                for (method, declaration) in declared.get(&key).into_iter().flatten() {
+                    let implementor = method_id(&s.crate_name, module, type_name, method);
+                    if let (true, Some(&i)) = (position.contains_key(&implementor), position.get(declaration)) {
+                        graph.nodes[i].callees.push(CallEdge::new(implementor).with_kind(EdgeKind::Implements));
+                    }
+                }
+            }
+        }
 
-        // Step 4: Add Edges
-        for (crate_name, _, ast) in &asts {
-             self.visit_ast_items(&ast.items, &mut graph, &index, crate_name);
+        // Step 9: Type nodes, linked to their methods, field types and constructors
+        if self.types {
+            add_type_nodes(&mut graph, &summaries, &mut position, origin);
         }
 
-        graph
+        // Step 10: One string per symbol, shared by its node and the edges to it
+        graph.intern();
+
+        (graph, errors)
     }
 }
 
-impl SimpleCallGraphBuilder {
-    fn visit_ast_items(&self, items: &[Item], graph: &mut CallGraph, index: &SymbolIndex, crate_name: &str) {
-        for item in items {
-            match item {
-                Item::Fn(func) => {
-                     let caller_id = format!("{}::{}", crate_name, func.sig.ident);
-                     let mut callees = Vec::new();
-                     for stmt in &func.block.stmts {
-                         visit_stmt(stmt, &mut callees, index, crate_name);
-                     }
-                     for callee in callees {
-                         graph.add_edge(&caller_id, &callee);
-                     }
-                }
-                Item::Impl(imp) => {
-                     if let syn::Type::Path(tp) = &*imp.self_ty {
-                         if let Some(segment) = tp.path.segments.last() {
-                             let type_name = segment.ident.to_string();
-                             for item in &imp.items {
-                                 if let syn::ImplItem::Fn(method) = item {
-                                     let method_name = method.sig.ident.to_string();
-                                     let caller_id = format!("{}::{}@{}", type_name, method_name, crate_name);
-                                     let mut callees = Vec::new();
-                                     for stmt in &method.block.stmts {
-                                         visit_stmt(stmt, &mut callees, index, crate_name);
-                                     }
-                                     for callee in callees {
-                                         graph.add_edge(&caller_id, &callee);
-                                     }
-                                 }
-                             }
-                         }
-                     }
+/// `Name@crate` nodes for every struct, enum, union and alias, with
+/// `defines-method` edges to `Name::m@crate`, `has-field-of-type` edges to
+/// the types its fields name and `constructs` edges from functions that
+/// build it. Names resolve to the same crate first, then to any crate.
+fn add_type_nodes(
+    graph: &mut CallGraph,
+    summaries: &[FileSummary],
+    position: &mut HashMap<String, usize>,
+    origin: impl Fn(&FileSummary) -> Option<SourceOrigin>,
+) {
+    let mut by_name: HashMap<&str, Vec<String>> = HashMap::new();
+    for s in summaries {
+        for t in &s.types {
+            let id = format!("{}@{}", t.name, s.crate_name);
+            if position.contains_key(&id) {
+                continue;
+            }
+            position.insert(id.clone(), graph.nodes.len());
+            by_name.entry(t.name.as_str()).or_default().push(id.clone());
+            graph.nodes.push(CallGraphNode {
+                id: id.into(),
+                label: Some(t.name.clone()),
+                is_public: t.is_public,
+                is_test: t.is_test,
+                cfg: t.cfg.clone(),
+                location: Some(format!("{}:{}", s.file_path, t.line)),
+                doc: t.doc.clone(),
+                type_kind: Some(t.kind),
+                origin: origin(s),
+                ..Default::default()
+            });
+        }
+    }
+    let resolve = |name: &str, krate: &str| -> Option<usize> {
+        let ids = by_name.get(name)?;
+        let id = ids.iter().find(|id| id.rsplit_once('@').is_some_and(|(_, c)| c == krate)).unwrap_or(&ids[0]);
+        position.get(id).copied()
+    };
+
+    let mut edges: Vec<(usize, CallEdge)> = Vec::new();
+    for s in summaries {
+        for t in &s.types {
+            let Some(owner) = resolve(&t.name, &s.crate_name) else { continue };
+            for field_type in &t.field_types {
+                if let Some(target) = resolve(field_type, &s.crate_name) {
+                    edges.push((owner, CallEdge::new(graph.nodes[target].id.clone()).with_kind(EdgeKind::HasFieldOfType)));
                 }
-                Item::Mod(module) => {
-                    if let Some((_, content)) = &module.content {
-                         self.visit_ast_items(content, graph, index, crate_name);
-                    }
+            }
+        }
+        for f in &s.functions {
+            let Some(&from) = position.get(&f.id) else { continue };
+            let method_of = f.id.rsplit_once('@')
+                .and_then(|(path, _)| path.rsplit_once("::"))
+                .map(|(owner, _)| owner.rsplit("::").next().unwrap_or(owner));
+            if let Some(owner) = method_of.and_then(|type_name| resolve(type_name, &s.crate_name)) {
+                let method = CallEdge::at(f.id.clone(), s.file_path.clone(), f.line).with_kind(EdgeKind::DefinesMethod);
+                edges.push((owner, method));
+            }
+            let mut seen = HashSet::new();
+            for (type_name, line) in &f.constructs {
+                let Some(target) = resolve(type_name, &s.crate_name) else { continue };
+                if seen.insert(target) {
+                    let target = graph.nodes[target].id.clone();
+                    edges.push((from, CallEdge::at(target, s.file_path.clone(), *line).with_kind(EdgeKind::Constructs)));
                 }
-                _ => {}
             }
         }
     }
+    for (from, edge) in edges {
+        graph.nodes[from].callees.push(edge);
+    }
 }
 
-// 遍歷語法樹、分析函式呼叫
-fn visit_stmt(
-    stmt: &Stmt,
-    callees: &mut Vec<String>,
-    index: &SymbolIndex,
-    crate_name: &str,
-) {
-    match stmt {
-        Stmt::Expr(expr, _) => visit_expr(expr, callees, index, crate_name),
-        Stmt::Local(local) => {
-             if let Some(init) = &local.init {
-                 visit_expr(&init.expr, callees, index, crate_name);
-             }
+/// Print parse errors the way the builders report them when nobody collects them.
+pub(crate) fn warn_parse_errors(errors: &[AnalysisError]) {
+    if !errors.is_empty() {
+        tracing::warn!("Encountered {} parse errors:", errors.len());
+        for e in errors {
+            tracing::warn!("  - {}", e);
+        }
+    }
+}
+
+impl crate::ports::CallGraphBuilder for SimpleCallGraphBuilder {
+    fn build_call_graph(&self, sources: &SourceSet) -> CallGraph {
+        let (graph, errors) = self.build_with_errors(sources);
+        warn_parse_errors(&errors);
+        graph
+    }
+}
+
+/// One file's resolved calls, waiting to be attached to the graph.
+struct LinkedFile<'a> {
+    /// Function id -> its call edges.
+    calls: Vec<(&'a str, Vec<CallEdge>)>,
+    /// Closure nodes, already linked.
+    closures: Vec<CallGraphNode>,
+}
+
+/// Per-function state used while resolving raw calls.
+struct LinkCtx<'a> {
+    index: &'a SymbolIndex,
+    crate_name: &'a str,
+    /// File being linked, recorded on every call edge.
+    file_path: &'a str,
+    imports: &'a ImportMap,
+    reexports: &'a ReexportTable,
+    /// Statically known types of local bindings (currently function parameters).
+    locals: HashMap<String, LocalType>,
+    /// Module path of the body being linked, including the function itself
+    /// (so items nested in the body are in scope).
+    scope: &'a [String],
+    /// Concrete types the body's type parameters are instantiated with.
+    instantiations: Option<&'a Instantiations>,
+}
+
+/// Type parameter -> concrete types seen at a generic function's call sites.
+type Instantiations = HashMap<String, BTreeSet<String>>;
+
+/// What the builder knows about the type of a local binding.
+#[derive(Debug, Clone)]
+enum LocalType {
+    /// `&dyn Trait`, `Box<dyn Trait>` or `impl Trait`: dispatches to any implementor.
+    DynTrait(String),
+    /// A concrete type, possibly behind a reference or smart pointer.
+    Named(String),
+}
+
+impl<'a> LinkCtx<'a> {
+    /// File-level context; use [`for_fn`](Self::for_fn) for a body.
+    fn new(index: &'a SymbolIndex, summary: &'a FileSummary, imports: &'a ImportMap, reexports: &'a ReexportTable) -> Self {
+        LinkCtx {
+            index,
+            crate_name: &summary.crate_name,
+            file_path: &summary.file_path,
+            imports,
+            reexports,
+            locals: HashMap::new(),
+            scope: &[],
+            instantiations: None,
+        }
+    }
+
+    /// Derive the context for a function body from its summary.
+    fn for_fn(&self, func: &'a FnSummary) -> LinkCtx<'a> {
+        let mut ctx = LinkCtx {
+            index: self.index,
+            crate_name: self.crate_name,
+            file_path: self.file_path,
+            imports: self.imports,
+            reexports: self.reexports,
+            locals: HashMap::new(),
+            scope: &func.scope,
+            instantiations: None,
+        };
+        // Trait paths are resolved through the imports in scope of the body
+        ctx.locals = func.dyn_params.iter()
+            .map(|(name, trait_path)| (name.clone(), LocalType::DynTrait(ctx.trait_name(trait_path))))
+            .chain(func.local_types.iter().map(|(name, ty)| (name.clone(), LocalType::Named(ty.clone()))))
+            .collect();
+        ctx
+    }
+
+    fn with_instantiations(self, instantiations: Option<&'a Instantiations>) -> Self {
+        LinkCtx { instantiations, ..self }
+    }
+
+    /// Type of a call-site argument: a local's known type, or a type name
+    /// itself (`run(Add)`, `run(Add::new())`).
+    fn concrete_type(&self, arg: &str) -> Option<String> {
+        match self.locals.get(arg) {
+            Some(LocalType::Named(type_name)) => Some(type_name.clone()),
+            Some(LocalType::DynTrait(_)) => None,
+            None => arg.starts_with(|c: char| c.is_ascii_uppercase()).then(|| arg.to_string()),
         }
-        _ => {}
+    }
+
+    /// Resolve a trait path through imports and re-exports to the trait's own name.
+    fn trait_name(&self, path: &[String]) -> String {
+        let resolved = self.imports.expand(path, self.scope).map(|p| self.reexports.resolve(p));
+        resolved.as_deref().unwrap_or(path).last().cloned().unwrap_or_default()
     }
 }
 
-fn visit_expr(
-    expr: &Expr,
-    callees: &mut Vec<String>,
+/// Concrete types each generic function is instantiated with, keyed by its
+/// id. Only one level deep: a caller's own type parameters are not concrete,
+/// so instantiations are not propagated through chains of generic calls.
+fn infer_instantiations(
+    summaries: &[FileSummary],
     index: &SymbolIndex,
-    crate_name: &str,
-) {
-    match expr {
-        Expr::Call(expr_call) => {
-            if let Expr::Path(ref expr_path) = *expr_call.func {
-                let segments: Vec<_> = expr_path.path.segments.iter().map(|s| s.ident.to_string()).collect();
-                if !segments.is_empty() {
-                    // Try to resolve global function: crate::mod::func
-                    // Currently we don't have full path resolution (imports), 
-                    // so we do a best-effort guess or strictly rely on our simplified index keys (crate::func) 
-                    // OR just default "name@crate".
-                    
-                    // If it looks like "func", we assume local or same-crate.
-                    // If "mod::func", we check if we can resolve it.
-                    // For Stage 2, let's keep the existing logic:
-                    // format!("{}@{}", segments.join("::"), crate_name)
-                    callees.push(format!("{}@{}", segments.join("::"), crate_name));
+    known_crates: &HashSet<String>,
+    reexports: &ReexportTable,
+) -> HashMap<String, Instantiations> {
+    let generics: HashMap<&str, &FnSummary> = summaries.iter()
+        .flat_map(|s| &s.functions)
+        .filter(|f| !f.type_params.is_empty())
+        .map(|f| (f.id.as_str(), f))
+        .collect();
+    let mut out: HashMap<String, Instantiations> = HashMap::new();
+    if generics.is_empty() {
+        return out;
+    }
+    for s in summaries {
+        let imports = ImportMap::from_entries(&s.uses, &s.crate_name, known_crates);
+        let file_ctx = LinkCtx::new(index, s, &imports, reexports);
+        for func in &s.functions {
+            let ctx = file_ctx.for_fn(func);
+            for site in &func.call_sites {
+                let Some(callee) = find_function(&site.path, &ctx).and_then(|id| generics.get(id.as_str()).copied()) else {
+                    continue;
+                };
+                // `run::<Add>(..)` binds by position, `run(add)` through the parameter types
+                let explicit = callee.type_params.iter().zip(&site.type_args)
+                    .filter_map(|(param, ty)| Some((param, ty.clone()?)));
+                let from_args = callee.generic_args.iter()
+                    .filter_map(|(position, param)| Some((param, ctx.concrete_type(site.args.get(*position)?.as_deref()?)?)));
+                for (param, ty) in explicit.chain(from_args) {
+                    if !func.type_params.contains(&ty) {
+                        out.entry(callee.id.clone()).or_default().entry(param.clone()).or_default().insert(ty);
+                    }
                 }
             }
-            for arg in &expr_call.args {
-                visit_expr(arg, callees, index, crate_name);
-            }
         }
-        Expr::MethodCall(expr_method) => {
-            let method_name = expr_method.method.to_string();
-            // 嘗試靜態取得 receiver 型別 (Best effort inference)
-            let receiver_type = match &*expr_method.receiver {
-                Expr::Path(expr_path) => expr_path.path.segments.last().map(|s| s.ident.to_string()),
-                _ => None,
+    }
+    out
+}
+
+/// `T::method` for every concrete type the call sites instantiate `T` with.
+fn inferred_targets(type_param: &str, method_name: &str, ctx: &LinkCtx) -> Vec<(String, EdgeKind)> {
+    ctx.instantiations.and_then(|i| i.get(type_param)).into_iter().flatten()
+        .filter_map(|ty| {
+            let sig = ctx.index.store.get_method(ty, method_name)?;
+            Some((sig.method_id(ty), EdgeKind::Inferred))
+        })
+        .collect()
+}
+
+/// Resolve raw calls to edges, in call order. A method call may fan out to
+/// several candidates.
+fn link_calls(calls: &[RawCall], ctx: &LinkCtx) -> Vec<CallEdge> {
+    calls.iter()
+        .flat_map(|call| {
+            resolve_target(&call.target, ctx).into_iter()
+                .map(|(target, kind)| CallEdge::at(target, ctx.file_path, call.line).with_kind(kind))
+        })
+        .collect()
+}
+
+/// Candidate callees of one raw call, with the kind of each edge.
+fn resolve_target(target: &RawTarget, ctx: &LinkCtx) -> Vec<(String, EdgeKind)> {
+    match target {
+        RawTarget::Path(segments) => {
+            // `T::create()` on an instantiated type parameter
+            let inferred = match segments.as_slice() {
+                [type_param, method] => inferred_targets(type_param, method, ctx),
+                _ => Vec::new(),
             };
-            
-            let mut resolved = false;
-
-            // Strategy 1: Exact match via inferred type
-            if let Some(rt) = &receiver_type {
-                if let Some(sig_ref) = index.store.get_method(rt, &method_name) {
-                     // Found it! Use canonical ID.
-                     let callee_id = format!("{}::{}@{}", rt, method_name, sig_ref.crate_name);
-                     callees.push(callee_id);
-                     resolved = true;
-                }
-            }
-            
-            // Strategy 2: Conservative Lookup (Name-based resolution)
-            if !resolved {
-                let candidates = index.find_methods_by_name(&method_name);
-                if !candidates.is_empty() {
-                    // Link to ALL matching methods (conservative approach)
-                    for sig in candidates {
-                        let callee_id = format!("{}::{}@{}", sig.name, method_name, sig.crate_name);
-                        callees.push(callee_id);
-                    }
-                    resolved = true;
-                }
-            }
+            if inferred.is_empty() { vec![(resolve_call_path(segments, ctx), EdgeKind::Call)] } else { inferred }
+        }
+        RawTarget::Node(id) => vec![(id.clone(), EdgeKind::Call)],
+        RawTarget::Method { receiver, method } => resolve_method_call(receiver.as_deref(), method, ctx),
+        RawTarget::AssocCall { path, receiver } => resolve_assoc_call(path, receiver.as_deref(), ctx),
+        RawTarget::FieldMethod { base, fields, method } => resolve_field_method_call(base, fields, method, ctx),
+        RawTarget::Reference(segments) => find_function(segments, ctx).into_iter()
+            .map(|target| (target, EdgeKind::Reference))
+            .collect(),
+        RawTarget::Spawn(task) => resolve_target(task, ctx).into_iter()
+            .map(|(target, _)| (target, EdgeKind::Spawn))
+            .collect(),
+    }
+}
 
-            // Strategy 3: Fallback (Unknown local call)
-            if !resolved {
-                if let Some(rt) = receiver_type {
-                    callees.push(format!("{}::{}@{}", rt, method_name, crate_name));
-                } else {
-                    callees.push(format!("{}@{}", method_name, crate_name));
-                }
-            }
-            
-            for arg in &expr_method.args {
-                visit_expr(arg, callees, index, crate_name);
-            }
-            visit_expr(&expr_method.receiver, callees, index, crate_name);
-        }
-        Expr::Block(expr_block) => visit_block(&expr_block.block, callees, index, crate_name),
-        Expr::If(expr_if) => {
-            callees.push("if(...)".to_string());
-            visit_expr(&expr_if.cond, callees, index, crate_name);
-            visit_block(&expr_if.then_branch, callees, index, crate_name);
-            if let Some((_, else_branch)) = &expr_if.else_branch {
-                visit_expr(else_branch, callees, index, crate_name);
+/// Resolve `receiver.method()` to one or more callee ids.
+fn resolve_method_call(receiver_type: Option<&str>, method_name: &str, ctx: &LinkCtx) -> Vec<(String, EdgeKind)> {
+    let (index, crate_name) = (ctx.index, ctx.crate_name);
+    let mut out = Vec::new();
+    // Parameters with a concrete type stand for that type
+    let receiver_type = match receiver_type.and_then(|r| ctx.locals.get(r)) {
+        Some(LocalType::Named(type_name)) => Some(type_name.as_str()),
+        _ => receiver_type,
+    };
+
+    // Strategy 0a: Generic parameter -> the types its call sites instantiate it with
+    if let Some(rt) = receiver_type {
+        let inferred = inferred_targets(rt, method_name, ctx);
+        if !inferred.is_empty() {
+            return inferred;
+        }
+    }
+
+    // Strategy 0: Trait object receiver -> every known implementor (devirtualization)
+    if let Some(LocalType::DynTrait(trait_name)) = receiver_type.and_then(|r| ctx.locals.get(r)) {
+        for type_name in index.store.find_trait_impls(trait_name) {
+            if let Some(sig) = index.store.get_method(&type_name, method_name) {
+                out.push((sig.method_id(&type_name), EdgeKind::Dynamic));
             }
         }
-        Expr::Match(expr_match) => {
-            callees.push("match(...)".to_string());
-            visit_expr(&expr_match.expr, callees, index, crate_name);
-            for (i, arm) in expr_match.arms.iter().enumerate() {
-                let label = format!("match_arm_{}", i);
-                callees.push(label.clone());
-                visit_expr(&arm.body, callees, index, crate_name);
+        if !out.is_empty() {
+            return out;
+        }
+    }
+
+    // Strategy 1: Exact match via inferred type
+    if let Some(rt) = receiver_type {
+        if let Some(sig_ref) = index.store.get_method(rt, method_name) {
+             // Found it! Use canonical ID.
+             return vec![(sig_ref.method_id(rt), EdgeKind::Call)];
+        }
+    }
+
+    // Strategy 2: Conservative Lookup (Name-based resolution)
+    let candidates = index.find_methods_by_name(method_name);
+    if !candidates.is_empty() {
+        // Link to ALL matching methods (conservative approach)
+        return candidates.into_iter()
+            .map(|(type_name, sig)| (sig.method_id(&type_name), EdgeKind::Call))
+            .collect();
+    }
+
+    // Strategy 3: Fallback (Unknown local call)
+    match receiver_type {
+        Some(rt) => vec![(format!("{}::{}@{}", rt, method_name, crate_name), EdgeKind::Call)],
+        None => vec![(format!("{}@{}", method_name, crate_name), EdgeKind::Call)],
+    }
+}
+
+/// Resolve `base.a.b.method()` by following struct field types from the type
+/// of `base`. Falls back to name-based resolution when a field or the method
+/// is not defined in the analyzed sources.
+fn resolve_field_method_call(base: &str, fields: &[String], method_name: &str, ctx: &LinkCtx) -> Vec<(String, EdgeKind)> {
+    let store = &ctx.index.store;
+    let base_type = match ctx.locals.get(base) {
+        Some(LocalType::Named(type_name)) => Some(type_name.clone()),
+        Some(LocalType::DynTrait(_)) => None,
+        // The impl type (recorded for `self`) or an untyped local
+        None => Some(base.to_string()),
+    };
+    let field_type = base_type.and_then(|ty| fields.iter().try_fold(ty, |ty, field| store.get_field_type(&ty, field)));
+    if let Some(ty) = field_type {
+        if let Some(sig) = store.get_method(&ty, method_name) {
+            return vec![(sig.method_id(&ty), EdgeKind::Call)];
+        }
+    }
+    resolve_method_call(None, method_name, ctx)
+}
+
+/// Resolve `Trait::method(recv)` / `<T as Trait>::method(..)` to the impl for
+/// the receiver's type, or to every implementor when that type is unknown.
+/// Paths that do not name a trait with impls resolve like any other call path.
+fn resolve_assoc_call(path: &[String], receiver: Option<&str>, ctx: &LinkCtx) -> Vec<(String, EdgeKind)> {
+    let Some((method_name, qualifier)) = path.split_last() else {
+        return Vec::new();
+    };
+    let store = &ctx.index.store;
+    let implementors = store.find_trait_impls(&ctx.trait_name(qualifier));
+    if implementors.is_empty() {
+        return vec![(resolve_call_path(path, ctx), EdgeKind::Call)];
+    }
+
+    let receiver_type = match receiver.and_then(|r| ctx.locals.get(r)) {
+        Some(LocalType::Named(type_name)) => Some(type_name.as_str()),
+        Some(LocalType::DynTrait(_)) => None,
+        None => receiver,
+    };
+    if let Some(rt) = receiver_type {
+        if let Some(sig) = store.get_method(rt, method_name) {
+            return vec![(sig.method_id(rt), EdgeKind::Call)];
+        }
+    }
+    let candidates: Vec<_> = implementors.iter()
+        .filter_map(|t| store.get_method(t, method_name).map(|sig| (sig.method_id(t), EdgeKind::Dynamic)))
+        .collect();
+    if candidates.is_empty() {
+        vec![(resolve_call_path(path, ctx), EdgeKind::Call)]
+    } else {
+        candidates
+    }
+}
+
+/// Resolve a call path like `foo`, `other_crate::foo` or `Type::new` against the
+/// file's imports and the global symbol index. Falls back to `path@crate` when
+/// nothing matches.
+fn resolve_call_path(segments: &[String], ctx: &LinkCtx) -> String {
+    find_function(segments, ctx).unwrap_or_else(|| {
+        let expanded = expand_path(segments, ctx);
+        format!("{}@{}", expanded.as_deref().unwrap_or(segments).join("::"), ctx.crate_name)
+    })
+}
+
+/// Rewrite imported aliases to their absolute path, then follow `pub use` chains.
+fn expand_path(segments: &[String], ctx: &LinkCtx) -> Option<Vec<String>> {
+    ctx.imports.expand(segments, ctx.scope).map(|p| ctx.reexports.resolve(p))
+}
+
+/// The known function or associated function a path names, if any.
+fn find_function(segments: &[String], ctx: &LinkCtx) -> Option<String> {
+    let (index, crate_name) = (ctx.index, ctx.crate_name);
+    let expanded = expand_path(segments, ctx);
+    let segments = expanded.as_deref().unwrap_or(segments);
+
+    let name = segments.last().map(|s| s.as_str()).unwrap_or_default();
+    // `crate::`/`self::` prefixes refer to the calling crate
+    let qualifier = match segments.first().map(|s| s.as_str()) {
+        Some("crate") | Some("self") if segments.len() > 1 => Some(crate_ident(crate_name)),
+        Some(first) if segments.len() > 1 => Some(first.to_string()),
+        _ => None,
+    };
+
+    // `Type::func` -> associated function on a known type
+    if segments.len() >= 2 {
+        let type_name = &segments[segments.len() - 2];
+        if let Some(sig) = index.store.get_method(type_name, name) {
+            return Some(sig.method_id(type_name));
+        }
+    }
+
+    // Lexical scope: the caller's function body, then each enclosing module
+    if !matches!(segments.first().map(|s| s.as_str()), Some("crate") | Some("self"))
+        && qualifier.as_ref().is_none_or(|q| crate_ident(crate_name) != *q)
+    {
+        for depth in (0..=ctx.scope.len()).rev() {
+            let key = qualified_fn_id(crate_name, &ctx.scope[..depth], &segments.join("::"));
+            if index.store.get_function(&key).is_some() {
+                return Some(key);
             }
         }
-        _ => {}
     }
+
+    let candidates = index.find_functions_by_name(name);
+
+    // Module-qualified path: `utils::helper` matches `crate::utils::helper`
+    if segments.len() > 1 {
+        let tail = segments.iter()
+            .skip(usize::from(matches!(segments[0].as_str(), "crate" | "self")))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("::");
+        let mut matches: Vec<&(String, _)> = candidates.iter()
+            .filter(|(key, _)| {
+                let key = crate_ident(key);
+                key == tail || key.ends_with(&format!("::{}", tail))
+            })
+            .collect();
+        matches.sort_by_key(|(_, sig)| sig.crate_name != crate_name);
+        if let Some((key, _)) = matches.first() {
+            return Some(key.clone());
+        }
+    }
+
+    // Explicit crate qualifier: `other_crate::foo` (package names may use '-')
+    if let Some(q) = &qualifier {
+        if let Some((key, _)) = candidates.iter().find(|(_, sig)| crate_ident(&sig.crate_name) == *q) {
+            return Some(key.clone());
+        }
+    }
+
+    // Same crate wins over other crates
+    if let Some((key, _)) = candidates.iter().find(|(_, sig)| sig.crate_name == crate_name) {
+        return Some(key.clone());
+    }
+
+    // Unique match somewhere else in the workspace
+    if candidates.len() == 1 {
+        return Some(candidates[0].0.clone());
+    }
+
+    None
 }
 
-fn visit_block(
-    block: &syn::Block,
-    callees: &mut Vec<String>,
-    index: &SymbolIndex,
+/// Package names may contain '-', but paths in code always use '_'.
+fn crate_ident(crate_name: &str) -> String {
+    crate_name.replace('-', "_")
+}
+
+/// Name and crate of the trait `path` names in `module` of a file of
+/// `crate_name`, through imports and re-exports; `None` for traits of crates
+/// outside the workspace.
+fn trait_key(
+    path: &[String],
+    module: &[String],
     crate_name: &str,
-) {
-    for stmt in &block.stmts {
-        visit_stmt(stmt, callees, index, crate_name);
+    imports: &ImportMap,
+    reexports: &ReexportTable,
+    known_crates: &HashSet<String>,
+) -> Option<(String, String)> {
+    let resolved = imports.expand(path, module).map(|p| reexports.resolve(p)).unwrap_or_else(|| path.to_vec());
+    let name = resolved.last()?.clone();
+    match resolved.first().map(String::as_str) {
+        Some(first) if resolved.len() > 1 && known_crates.contains(first) => Some((name, first.to_string())),
+        Some("std" | "core" | "alloc") if resolved.len() > 1 => None,
+        // `Trait` or `module::Trait` of the crate itself
+        _ => Some((name, crate_ident(crate_name))),
+    }
+}
+
+/// How the DOT exporter groups nodes into `subgraph cluster_*` boxes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DotClustering {
+    /// Flat graph.
+    #[default]
+    None,
+    /// One box per crate.
+    Crate,
+    /// One box per crate with a nested box per module.
+    Module,
+}
+
+/// Fill colors cycled through for crate clusters.
+pub const DEFAULT_CLUSTER_COLORS: &[&str] = &["#eef4fb", "#fdf1e6", "#eef8ee", "#f7eefa", "#fcf8e3", "#eaf6f6"];
+
+/// Presentation options for [`DotExporter`]; unset fields keep Graphviz defaults.
+#[derive(Debug, Clone, Default)]
+pub struct DotStyle {
+    /// Layout direction: `TB`, `LR`, `BT` or `RL`.
+    pub rankdir: Option<String>,
+    /// Default node shape (`ellipse`, `box`, ...).
+    pub node_shape: Option<String>,
+    /// Font for node and edge labels.
+    pub font: Option<String>,
+    /// Edge colors overriding the per-kind defaults.
+    pub edge_colors: HashMap<EdgeKind, String>,
+    /// Truncate node labels longer than this many characters.
+    pub max_label_len: Option<usize>,
+    /// Link call edges to their source: `{file}` and `{line}` are replaced
+    /// with the call site (e.g. `vscode://file/{file}:{line}`).
+    pub source_url: Option<String>,
+    /// Link nodes to their definition on a source host (`--link-base`).
+    pub links: Option<SourceLinks>,
+    /// Label nodes with their signature, when recorded, instead of their name.
+    pub signatures: bool,
+}
+
+impl DotStyle {
+    fn label(&self, label: &str) -> String {
+        match self.max_label_len {
+            Some(max) if label.chars().count() > max => {
+                let mut short: String = label.chars().take(max.saturating_sub(1)).collect();
+                short.push('…');
+                short
+            }
+            _ => label.to_string(),
+        }
+    }
+
+    /// `style`/`color` attributes of an edge of `kind`.
+    fn edge_attrs(&self, kind: EdgeKind) -> Vec<String> {
+        let (style, color) = match kind {
+            EdgeKind::Call => (None, None),
+            EdgeKind::Dynamic => (Some("dashed"), None),
+            EdgeKind::Spawn => (Some("dotted"), None),
+            EdgeKind::Reference => (Some("dashed"), Some("gray")),
+            EdgeKind::Inferred => (Some("dashed"), Some("blue")),
+            EdgeKind::Implements => (Some("dashed"), Some("darkgreen")),
+            EdgeKind::DefinesMethod => (Some("dotted"), Some("gray")),
+            EdgeKind::HasFieldOfType => (None, Some("sienna")),
+            EdgeKind::Constructs => (Some("dashed"), Some("purple")),
+            EdgeKind::Import => (Some("dotted"), Some("darkgray")),
+        };
+        let color = self.edge_colors.get(&kind).map(String::as_str).or(color);
+        // UML heads: hollow at the trait, a diamond at the type owning the field
+        let arrows = match kind {
+            EdgeKind::Implements => Some("dir=back, arrowtail=empty"),
+            EdgeKind::HasFieldOfType => Some("dir=both, arrowtail=diamond"),
+            _ => None,
+        };
+        style.map(|s| format!("style={}", s)).into_iter()
+            .chain(color.map(|c| format!("color=\"{}\"", c)))
+            .chain(arrows.map(str::to_string))
+            .collect()
     }
 }
 
-pub struct DotExporter;
+/// Graphviz DOT exporter.
+#[derive(Debug, Clone, Default)]
+pub struct DotExporter {
+    /// Label each edge with its call site (`file:line`).
+    pub edge_labels: bool,
+    pub clustering: DotClustering,
+    /// Cluster fill colors, one per crate in order (cycled);
+    /// [`DEFAULT_CLUSTER_COLORS`] when empty.
+    pub cluster_colors: Vec<String>,
+    pub style: DotStyle,
+}
+
+impl DotExporter {
+    fn node_line(&self, n: &CallGraphNode, indent: &str) -> String {
+        let name = n.label.as_deref().unwrap_or(&n.id);
+        // `fn Add::apply(&self)`: the bare signature reads the same for every impl
+        let signature = n.signature.as_ref().filter(|_| self.style.signatures).map(|sig| {
+            let ident = name.rsplit("::").next().unwrap_or(name);
+            sig.replacen(&format!("fn {}", ident), &format!("fn {}", name), 1)
+        });
+        let lbl = self.style.label(signature.as_deref().unwrap_or(name));
+        // Double outline marks async functions and spawned async blocks,
+        // red unsafe functions and those with unsafe blocks, boxes tests;
+        // types are notes, `struct Config`
+        let mut extra = String::new();
+        let lbl = match n.type_kind {
+            Some(kind) => {
+                extra.push_str(", shape=note");
+                format!("{} {}", kind.as_str(), lbl)
+            }
+            None => lbl,
+        };
+        if n.is_async {
+            extra.push_str(", peripheries=2");
+        }
+        if n.is_unsafe || n.unsafe_blocks > 0 {
+            extra.push_str(", color=red");
+        }
+        if n.is_test {
+            extra.push_str(", shape=box");
+        }
+        if let Some(cfg) = &n.cfg {
+            extra.push_str(&format!(", tooltip=\"cfg({})\"", cfg.replace('\"', "\\\"")));
+        }
+        if n.external.is_some() {
+            extra.push_str(", style=dashed, color=gray, fontcolor=gray");
+        }
+        // Fill from a coverage report: green ran, red never ran
+        match n.coverage {
+            Some(0) => extra.push_str(", style=filled, fillcolor=\"#f8d7da\""),
+            Some(_) => extra.push_str(", style=filled, fillcolor=\"#d4edda\""),
+            None => {}
+        }
+        if let Some(url) = self.style.links.as_ref().zip(n.location.as_deref()).and_then(|(links, loc)| links.location_url(loc)) {
+            extra.push_str(&format!(", URL=\"{}\"", url.replace('\"', "%22")));
+        }
+        format!("{}\"{}\" [label=\"{}\"{}];", indent, n.id, lbl.replace('\"', "\\\""), extra)
+    }
+
+    fn cluster_color(&self, index: usize) -> String {
+        if self.cluster_colors.is_empty() {
+            DEFAULT_CLUSTER_COLORS[index % DEFAULT_CLUSTER_COLORS.len()].to_string()
+        } else {
+            self.cluster_colors[index % self.cluster_colors.len()].clone()
+        }
+    }
+}
 
 impl crate::ports::OutputExporter for DotExporter {
-    fn export(&self, cg: &CallGraph, path: &str) -> std::io::Result<()> {
-        let mut out = vec![];
-        out.push("digraph G {".to_string());
+    fn export_to_writer(&self, cg: &CallGraph, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        use std::collections::BTreeMap;
+
+        writeln!(out, "digraph G {{")?;
+        let style = &self.style;
+        if let Some(rankdir) = &style.rankdir {
+            writeln!(out, "    rankdir=\"{}\";", rankdir.replace('\"', "\\\""))?;
+        }
+        let font = style.font.as_ref().map(|f| format!("fontname=\"{}\"", f.replace('\"', "\\\"")));
+        let node_defaults: Vec<String> = style.node_shape.iter().map(|s| format!("shape=\"{}\"", s.replace('\"', "\\\""))).chain(font.clone()).collect();
+        if !node_defaults.is_empty() {
+            writeln!(out, "    node [{}];", node_defaults.join(", "))?;
+        }
+        if let Some(font) = font {
+            writeln!(out, "    edge [{}];", font)?;
+        }
+
+        // Nodes, grouped by crate (and module), then stubs for code outside
+        // the analyzed sources grouped by their crate
+        let mut crates: BTreeMap<&str, BTreeMap<String, Vec<&CallGraphNode>>> = BTreeMap::new();
+        let mut external: BTreeMap<&str, Vec<&CallGraphNode>> = BTreeMap::new();
+        for n in &cg.nodes {
+            if let Some(krate) = &n.external {
+                external.entry(krate).or_default().push(n);
+            } else if self.clustering == DotClustering::None {
+                writeln!(out, "{}", self.node_line(n, "    "))?;
+            } else {
+                let (krate, module) = node_module(&n.id);
+                let module = if self.clustering == DotClustering::Module { module.join("::") } else { String::new() };
+                crates.entry(krate).or_default().entry(module).or_default().push(n);
+            }
+        }
+        for (index, (krate, modules)) in crates.into_iter().enumerate() {
+            writeln!(out, "    subgraph \"cluster_{}\" {{", krate)?;
+            writeln!(out, "        label=\"{}\"; style=\"filled,rounded\"; fillcolor=\"{}\"; color=\"#999999\";", krate, self.cluster_color(index))?;
+            for (module, nodes) in modules {
+                if module.is_empty() {
+                    for n in nodes {
+                        writeln!(out, "{}", self.node_line(n, "        "))?;
+                    }
+                    continue;
+                }
+                writeln!(out, "        subgraph \"cluster_{}::{}\" {{", krate, module)?;
+                writeln!(out, "            label=\"{}\"; style=\"rounded\"; color=\"#bbbbbb\";", module)?;
+                for n in nodes {
+                    writeln!(out, "{}", self.node_line(n, "            "))?;
+                }
+                writeln!(out, "        }}")?;
+            }
+            writeln!(out, "    }}")?;
+        }
+        for (krate, nodes) in external {
+            writeln!(out, "    subgraph \"cluster_external_{}\" {{", krate)?;
+            writeln!(out, "        label=\"{} (external)\"; style=dashed; color=gray;", krate)?;
+            for n in nodes {
+                writeln!(out, "{}", self.node_line(n, "        "))?;
+            }
+            writeln!(out, "    }}")?;
+        }
+
+        // Parallel edges are drawn once, thicker the more calls they stand for
         for n in &cg.nodes {
-            let lbl = n.label.clone().unwrap_or_else(|| n.id.clone());
-            out.push(format!("    \"{}\" [label=\"{}\"];", n.id, lbl.replace('\"', "\\\"")));
-            for c in &n.callees {
-                out.push(format!("    \"{}\" -> \"{}\";", n.id, c));
+            for (c, weight) in n.weighted_callees() {
+                let mut attrs = Vec::new();
+                if self.edge_labels {
+                    if let Some(loc) = c.location() {
+                        attrs.push(format!("label=\"{}\"", loc.replace('\"', "\\\"")));
+                    }
+                }
+                attrs.extend(style.edge_attrs(c.kind));
+                if weight > 1 {
+                    attrs.push(format!("penwidth={:.1}, weight={}, tooltip=\"{} calls\"", 1.0 + (weight as f64).log2(), weight, weight));
+                }
+                if let (Some(template), Some(file), Some(line)) = (&style.source_url, &c.file, c.line) {
+                    let url = template.replace("{file}", file).replace("{line}", &line.to_string());
+                    attrs.push(format!("URL=\"{}\"", url.replace('\"', "\\\"")));
+                }
+                if attrs.is_empty() {
+                    writeln!(out, "    \"{}\" -> \"{}\";", n.id, c.target)?;
+                } else {
+                    writeln!(out, "    \"{}\" -> \"{}\" [{}];", n.id, c.target, attrs.join(", "))?;
+                }
             }
         }
-        out.push("}".to_string());
-        std::fs::write(path, out.join("\n"))
+        writeln!(out, "}}")
     }
 }
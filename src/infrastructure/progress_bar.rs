@@ -0,0 +1,68 @@
+//! Terminal Progress Bars
+//!
+//! Draws analysis progress on stderr with indicatif, one bar per phase,
+//! cleared when the phase ends:
+//!
+//! ```text
+//!            Parsing files [###########>-----------] 1520/3041 · 18230 functions
+//! ```
+//!
+//! Nothing is drawn when stderr is not a terminal, so CI logs stay clean.
+
+use std::sync::Mutex;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::domain::progress::Progress;
+
+pub struct TerminalProgress {
+    phase: Mutex<Option<Phase>>,
+}
+
+struct Phase {
+    bar: ProgressBar,
+    found: usize,
+    what: String,
+}
+
+impl TerminalProgress {
+    pub fn new() -> Self {
+        Self { phase: Mutex::new(None) }
+    }
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Progress for TerminalProgress {
+    fn start(&self, phase: &str, total: usize, found: &str) {
+        let bar = ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stderr());
+        let style = ProgressStyle::with_template("{prefix:>26} [{bar:30}] {pos}/{len} · {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("#>-");
+        bar.set_style(style);
+        bar.set_prefix(phase.to_string());
+        bar.set_message(format!("0 {}", found));
+        let previous = self.phase.lock().unwrap().replace(Phase { bar, found: 0, what: found.to_string() });
+        if let Some(previous) = previous {
+            previous.bar.finish_and_clear();
+        }
+    }
+
+    fn advance(&self, steps: usize, found: usize) {
+        if let Some(phase) = self.phase.lock().unwrap().as_mut() {
+            phase.found += found;
+            phase.bar.set_message(format!("{} {}", phase.found, phase.what));
+            phase.bar.inc(steps as u64);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(phase) = self.phase.lock().unwrap().take() {
+            phase.bar.finish_and_clear();
+        }
+    }
+}
@@ -1,129 +1,272 @@
-use cargo_metadata::MetadataCommand;
+use cargo_metadata::camino::Utf8PathBuf;
+use cargo_metadata::{Metadata, MetadataCommand, Package};
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+
+use crate::domain::source::{target_crate, SourceFile, SourceOrigin, SourceSet};
 
 pub struct ProjectLoader;
 
+/// Which targets of the packages are loaded, and which files under their
+/// directories. The library and binaries always are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceFilter {
+    /// Integration tests (`tests/`).
+    pub tests: bool,
+    /// Benchmarks (`benches/`).
+    pub benches: bool,
+    /// Examples (`examples/`).
+    pub examples: bool,
+    /// Gitignore-style patterns relative to the workspace root, e.g.
+    /// `vendor/`, `/examples` or `*_generated.rs`.
+    pub ignore: Vec<String>,
+    /// Also skip what `.gitignore`, `.ignore` and `.git/info/exclude` exclude.
+    pub gitignore: bool,
+}
+
+impl SourceFilter {
+    /// Whether targets of `kind` (as `cargo metadata` names them) are loaded.
+    fn loads(&self, kind: &str) -> bool {
+        match SourceOrigin::from_target_kind(kind) {
+            Some(SourceOrigin::Test) => self.tests,
+            Some(SourceOrigin::Bench) => self.benches,
+            Some(SourceOrigin::Example) => self.examples,
+            None => kind == "lib" || kind == "bin" || kind == "proc-macro",
+        }
+    }
+
+    fn matcher(&self, root: &Path) -> Result<Gitignore> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in &self.ignore {
+            builder.add_line(None, pattern).with_context(|| format!("Invalid ignore pattern {:?}", pattern))?;
+        }
+        Ok(builder.build()?)
+    }
+}
+
 impl ProjectLoader {
+    /// Manifest to analyze a checkout by: its top-level `Cargo.toml`, else
+    /// the shallowest one in the first three levels below (a `[workspace]`
+    /// root first), leaving out `target` and hidden directories.
+    pub fn find_manifest(dir: &Path) -> Option<PathBuf> {
+        let top = dir.join("Cargo.toml");
+        if top.is_file() {
+            return Some(top);
+        }
+        let mut found: Vec<(bool, usize, PathBuf)> = Vec::new();
+        let mut pending = vec![(dir.to_path_buf(), 0)];
+        while let Some((current, depth)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&current) else { continue };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if path.is_dir() && depth < 3 && !name.starts_with('.') && name != "target" {
+                    pending.push((path, depth + 1));
+                } else if name == "Cargo.toml" {
+                    let workspace = fs::read_to_string(&path).is_ok_and(|text| text.lines().any(|l| l.trim() == "[workspace]"));
+                    found.push((!workspace, depth, path));
+                }
+            }
+        }
+        found.sort();
+        found.into_iter().next().map(|(_, _, path)| path)
+    }
+
     /// Load all source files from a Cargo workspace manifest.
-    /// Returns a vector of (crate_name, file_path, file_content).
-    pub fn load_workspace(manifest_path: &str, expand_macros: bool) -> Result<Vec<(String, String, String)>> {
-        let cargo_bin = Self::find_cargo_binary();
-        eprintln!("DEBUG: executing cargo metadata with binary: {} on manifest: {}", cargo_bin, manifest_path);
-        
-        let metadata = MetadataCommand::new()
+    /// Returns every source file, tagged with its crate.
+    ///
+    /// Members come from `cargo metadata`, so glob members, `exclude` and
+    /// virtual manifests behave as in Cargo; all members are loaded, not just
+    /// `default-members`. With `follow_path_deps`, local path dependencies
+    /// that are not members (`foo = { path = "../foo" }`) are analyzed as well.
+    /// `filter` picks the targets and leaves files out; `target` and `.git`
+    /// directories always are. Files of test, bench and example targets are
+    /// tagged with their [`SourceOrigin`].
+    pub fn load_workspace(manifest_path: &str, expand_macros: bool, follow_path_deps: bool, filter: &SourceFilter) -> Result<SourceSet> {
+        let (root, packages) = Self::local_packages(Path::new(manifest_path), follow_path_deps)?;
+        // Anchored patterns match against the absolute paths cargo reports
+        let ignore = filter.matcher(&root)?;
+        let mut files = SourceSet::default();
+        for package in packages {
+            let crate_name = &package.name;
+            let package_dir = package.manifest_path.parent().unwrap_or(&package.manifest_path);
+            if expand_macros {
+                // cargo expand works on the whole package, once for all its targets
+                match crate::infrastructure::expander::expand_crate(package.manifest_path.as_str()) {
+                    // The expanded result is a single "virtual" file for this crate
+                    Ok(expanded_code) => files.push(SourceFile::new(crate_name.clone(), format!("<expanded:{}>", crate_name), expanded_code)),
+                    Err(e) => tracing::warn!("Failed to expand crate {}: {}", crate_name, e),
+                }
+                continue;
+            }
+            for target in &package.targets {
+                if target.kind.iter().any(|k| filter.loads(k)) {
+                    let origin = target.kind.iter().find_map(|k| SourceOrigin::from_target_kind(k));
+                    let src_path = &target.src_path;
+                    let src_dir = src_path.parent().unwrap_or(src_path);
+                    Self::collect_rs(src_dir.as_std_path(), crate_name, origin, package_dir.as_std_path(), &ignore, filter.gitignore, &mut files)?;
+                }
+            }
+            Self::tag_targets(&package, filter, &mut files);
+        }
+
+        // Targets of one package usually share a directory (src/lib.rs, src/main.rs,
+        // tests/a.rs and tests/b.rs)
+        files.dedup_by_path();
+
+        Ok(files)
+    }
+
+    /// Move the files of `package`'s extra binaries, examples and benches
+    /// (the target's root file and its module directory) to a crate of
+    /// their own, so each target's `main` is a node of its own.
+    fn tag_targets(package: &Package, filter: &SourceFilter, files: &mut SourceSet) {
+        let default_bin = package.manifest_path.with_file_name("src").join("main.rs");
+        for target in &package.targets {
+            let Some(kind) = target.kind.iter().find(|k| matches!(k.as_str(), "bin" | "example" | "bench")) else { continue };
+            if !filter.loads(kind) || target.src_path == default_bin {
+                continue;
+            }
+            let root = target.src_path.as_std_path();
+            let modules = match root.file_name() {
+                Some(name) if name == "main.rs" => root.parent().unwrap_or(root).to_path_buf(),
+                _ => root.with_extension(""),
+            };
+            let crate_name = target_crate(&package.name, kind, &target.name);
+            for file in files.iter_mut() {
+                let path = Path::new(&file.path);
+                if path == root || path.starts_with(&modules) {
+                    file.crate_name = crate_name.clone();
+                }
+            }
+        }
+    }
+
+    fn metadata(manifest_path: &Path) -> Result<Metadata> {
+        MetadataCommand::new()
             .manifest_path(manifest_path)
-            .cargo_path(&cargo_bin)
+            .cargo_path(Self::find_cargo_binary())
             .no_deps()
             .exec()
-            .context("Failed to execute cargo metadata")?;
-
-        let mut files = Vec::new();
+            .with_context(|| format!("Failed to execute cargo metadata on {}", manifest_path.display()))
+    }
 
+    /// Features the `default` feature of the workspace members turns on,
+    /// `default` included; dependency features (`serde/std`) are left out.
+    pub fn default_features(manifest_path: &Path) -> Result<BTreeSet<String>> {
+        let metadata = Self::metadata(manifest_path)?;
+        let mut enabled = BTreeSet::new();
         for package in metadata.workspace_packages() {
-            let crate_name = &package.name;
-            
-            // Skip if no targets or irrelevant (though workspace_packages usually are relevant)
-             for target in &package.targets {
-                if target.kind.iter().any(|k| k == "lib" || k == "bin" || k == "proc-macro") {
-                    // Logic Branch: Expand Macros vs Raw Files
-                    if expand_macros {
-                         // SINGLE file per target/crate? 
-                         // cargo expand works per crate (or specific target). 
-                         // Simple usage: cargo expand --manifest-path package/Cargo.toml
-                         // But we are at workspace level. We might need package manifest.
-                         let _package_manifest = &package.manifest_path;
-                         
-                         // Note: running cargo expand for EACH package.
-                         // Optimization: cargo expand runs the whole crate.
-                         // We probably only want to run it once per package.
-                         
-                         // We are inside a loop over TARGETS. We should loop over PACKAGES.
-                         // The outer loop IS package.
-                         // Just break after doing it once per package? Or careful with multiple targets?
-                         // cargo expand usually expands the library by default or bin if specified.
-                         // For simplicity, let's try to expand the package.
-                         
-                         // Check if we already processed this package (simple dedup if targets iterate same package multiple times - wait, workspace_packages() returns unique packages)
-                         // But we are iterating targets inside.
-                         
-                         // Let's perform expansion on the *first* interesting target and skip others for this package?
-                         // Or better: move expansion logic outside target loop.
-                    } else {
-                        let src_path = &target.src_path;
-                        let src_dir = src_path.parent().unwrap_or(src_path);
-                        Self::collect_rs_recursive(src_dir.as_std_path(), crate_name, &mut files)?;
+            let mut pending = vec!["default".to_string()];
+            while let Some(feature) = pending.pop() {
+                let Some(implied) = package.features.get(&feature) else {
+                    // An optional dependency is a feature of the same name
+                    let optional = package.dependencies.iter()
+                        .any(|d| d.optional && d.rename.as_deref().unwrap_or(&d.name) == feature);
+                    if optional {
+                        enabled.insert(feature);
                     }
+                    continue;
+                };
+                if !enabled.insert(feature) {
+                    continue;
                 }
+                pending.extend(implied.iter().filter(|f| !f.contains('/') && !f.starts_with("dep:")).cloned());
             }
-            
-            // Handling Expansion Outside Target Loop to avoid duplicates
-            if expand_macros {
-                // We attempt to expand the whole package
-                match crate::infrastructure::expander::expand_crate(package.manifest_path.as_str()) {
-                    Ok(expanded_code) => {
-                         // We treat the expanded result as a single "virtual" file for this crate.
-                         files.push((crate_name.clone(), format!("<expanded:{}>", crate_name), expanded_code));
-                    },
-                    Err(e) => {
-                        eprintln!("WARN: Failed to expand crate {}: {}", crate_name, e);
-                        // Fallback? Or just warn? Warn is safer.
-                    }
+        }
+        Ok(enabled)
+    }
+
+    /// The workspace root and its members, followed by the local path
+    /// dependencies they (transitively) pull in when `follow_path_deps` is
+    /// set; each package once.
+    fn local_packages(manifest_path: &Path, follow_path_deps: bool) -> Result<(PathBuf, Vec<Package>)> {
+        let metadata = Self::metadata(manifest_path)?;
+        let root = metadata.workspace_root.clone().into_std_path_buf();
+        let mut packages: Vec<Package> = metadata.workspace_packages().into_iter().cloned().collect();
+        if !follow_path_deps {
+            return Ok((root, packages));
+        }
+        let mut seen: HashSet<Utf8PathBuf> = packages.iter().map(|p| p.manifest_path.clone()).collect();
+
+        let mut next = 0;
+        while next < packages.len() {
+            let deps: Vec<Utf8PathBuf> = packages[next].dependencies.iter()
+                .filter_map(|d| d.path.as_ref().map(|p| p.join("Cargo.toml")))
+                .filter(|m| !seen.contains(m))
+                .collect();
+            next += 1;
+            for dep_manifest in deps {
+                seen.insert(dep_manifest.clone());
+                // The dependency may belong to another workspace; take just that package
+                match Self::metadata(dep_manifest.as_std_path()) {
+                    Ok(dep) => packages.extend(dep.packages.into_iter().filter(|p| p.manifest_path == dep_manifest)),
+                    Err(e) => tracing::warn!("skipping path dependency {}: {:#}", dep_manifest, e),
                 }
             }
         }
-        
-        // Dedup files if multiple targets point to same files (unlikely main.rs/lib.rs overlap, but robust)
-        files.sort_by(|a, b| a.1.cmp(&b.1));
-        files.dedup_by(|a, b| a.1 == b.1);
-
-        Ok(files)
+        Ok((root, packages))
     }
 
-    fn collect_rs_recursive(
-        dir: &Path, 
-        crate_name: &str, 
-        out: &mut Vec<(String, String, String)>
-    ) -> Result<()> {
-        if dir.ends_with("target") || dir.ends_with(".git") {
+    /// Every `.rs` file under `dir`, skipping nested packages (loaded on
+    /// their own), `target` and `.git`, and what `ignore` excludes.
+    fn collect_rs(dir: &Path, crate_name: &str, origin: Option<SourceOrigin>, package_dir: &Path, ignore: &Gitignore, gitignore: bool, out: &mut SourceSet) -> Result<()> {
+        let file = |path: &Path, content: String| {
+            SourceFile::new(crate_name, path.display().to_string(), content).with_origin(origin).with_package_dir(package_dir.display().to_string())
+        };
+        if dir.ends_with("target") || dir.ends_with(".git") || !dir.exists() {
             return Ok(());
         }
-        if !dir.exists() {
-             return Ok(());
-        }
-
         if dir.is_file() {
             // It might be a single file target (like main.rs)
-             if let Some(ext) = dir.extension() {
-                if ext == "rs" {
-                     let content = fs::read_to_string(dir)
-                        .with_context(|| format!("Failed to read file {}", dir.display()))?;
-                    out.push((crate_name.to_string(), dir.display().to_string(), content));
-                }
-             }
-             return Ok(());
+            if dir.extension().is_some_and(|ext| ext == "rs") && !ignore.matched(dir, false).is_ignore() {
+                let content = fs::read_to_string(dir)
+                    .with_context(|| format!("Failed to read file {}", dir.display()))?;
+                out.push(file(dir, content));
+            }
+            return Ok(());
         }
 
-        for entry in fs::read_dir(dir)? {
+        let matcher = ignore.clone();
+        let walker = WalkBuilder::new(dir)
+            .standard_filters(false)
+            .git_ignore(gitignore)
+            .git_exclude(gitignore)
+            .ignore(gitignore)
+            .parents(gitignore)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                if entry.depth() == 0 {
+                    return true;
+                }
+                let path = entry.path();
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                if is_dir && (path.ends_with("target") || path.ends_with(".git") || path.join("Cargo.toml").is_file()) {
+                    return false;
+                }
+                !matcher.matched(path, is_dir).is_ignore()
+            })
+            .build();
+        for entry in walker {
             let entry = entry?;
             let path = entry.path();
-            
-            if path.is_dir() {
-                Self::collect_rs_recursive(&path, crate_name, out)?;
-            } else if let Some(ext) = path.extension() {
-                if ext == "rs" {
-                    let content = fs::read_to_string(&path)
-                        .with_context(|| format!("Failed to read file {}", path.display()))?;
-                    out.push((crate_name.to_string(), path.display().to_string(), content));
-                }
+            if entry.file_type().is_some_and(|t| t.is_file()) && path.extension().is_some_and(|ext| ext == "rs") {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read file {}", path.display()))?;
+                out.push(file(path, content));
             }
         }
         Ok(())
     }
 
     /// Attempts to find the cargo binary in several common locations.
-    fn find_cargo_binary() -> String {
+    pub(crate) fn find_cargo_binary() -> String {
         if let Ok(bin) = std::env::var("CARGO") { return bin; }
         if which::which("cargo").is_ok() { return "cargo".to_string(); }
         let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
@@ -1,11 +1,11 @@
-/// SCIP Cache Module
-/// 
-/// Provides incremental indexing by caching SCIP indices and validating
-/// them against source file modifications.
-/// 
-/// Cache structure:
-/// - `index.scip` - The SCIP protobuf index
-/// - `index.scip.meta` - JSON metadata for cache validation
+//! SCIP Cache Module
+//! 
+//! Provides incremental indexing by caching SCIP indices and validating
+//! them against source file modifications.
+//! 
+//! Cache structure:
+//! - `index.scip` - The SCIP protobuf index
+//! - `index.scip.meta` - JSON metadata for cache validation
 
 use std::collections::HashMap;
 use std::fs::{self, File};
@@ -52,12 +52,36 @@ impl ScipCache {
         }
     }
 
+    /// Create a cache manager that keeps its files in `cache_dir` instead of the
+    /// workspace root (e.g. `target/tracecraft/`). The directory is created on demand.
+    pub fn in_dir(workspace_root: &Path, cache_dir: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            index_path: cache_dir.join("index.scip"),
+            meta_path: cache_dir.join("index.scip.meta"),
+        }
+    }
+
+    /// Like [`get_valid_cache`](Self::get_valid_cache), but also requires the cached
+    /// index to cover exactly `source_files` (added or removed files invalidate it).
+    pub fn get_valid_cache_for(&self, source_files: &[String]) -> Option<PathBuf> {
+        let path = self.get_valid_cache()?;
+        let meta = self.load_metadata().ok()?;
+        let same_set = meta.source_files.len() == source_files.len()
+            && source_files.iter().all(|f| meta.source_files.contains_key(f));
+        if !same_set {
+            tracing::debug!("[SCIP Cache] Source file set has changed");
+            return None;
+        }
+        Some(path)
+    }
+
     /// Check if a valid cache exists and is up-to-date.
     /// Returns the path to the cached index if valid.
     pub fn get_valid_cache(&self) -> Option<PathBuf> {
         // Check if both index and metadata exist
         if !self.index_path.exists() || !self.meta_path.exists() {
-            println!("[SCIP Cache] No cache found");
+            tracing::debug!("[SCIP Cache] No cache found");
             return None;
         }
 
@@ -65,30 +89,30 @@ impl ScipCache {
         let meta = match self.load_metadata() {
             Ok(m) => m,
             Err(e) => {
-                println!("[SCIP Cache] Failed to load metadata: {}", e);
+                tracing::debug!("[SCIP Cache] Failed to load metadata: {}", e);
                 return None;
             }
         };
 
         // Check version
         if meta.version != ScipCacheMetadata::CURRENT_VERSION {
-            println!("[SCIP Cache] Cache version mismatch");
+            tracing::debug!("[SCIP Cache] Cache version mismatch");
             return None;
         }
 
         // Validate source files haven't changed
         if !self.validate_source_files(&meta) {
-            println!("[SCIP Cache] Source files have changed");
+            tracing::debug!("[SCIP Cache] Source files have changed");
             return None;
         }
 
         // Validate Cargo.lock hasn't changed
         if !self.validate_cargo_lock(&meta) {
-            println!("[SCIP Cache] Cargo.lock has changed");
+            tracing::debug!("[SCIP Cache] Cargo.lock has changed");
             return None;
         }
 
-        println!("[SCIP Cache] Cache is valid, skipping regeneration");
+        tracing::debug!("[SCIP Cache] Cache is valid, skipping regeneration");
         Some(self.index_path.clone())
     }
 
@@ -116,13 +140,17 @@ impl ScipCache {
 
         let json = serde_json::to_string_pretty(&meta)
             .context("Failed to serialize cache metadata")?;
+
+        if let Some(dir) = self.meta_path.parent() {
+            fs::create_dir_all(dir).context("Failed to create cache directory")?;
+        }
         
         let mut file = File::create(&self.meta_path)
             .context("Failed to create cache metadata file")?;
         file.write_all(json.as_bytes())
             .context("Failed to write cache metadata")?;
 
-        println!("[SCIP Cache] Metadata updated with {} source files", meta.source_files.len());
+        tracing::debug!("[SCIP Cache] Metadata updated with {} source files", meta.source_files.len());
         Ok(())
     }
 
@@ -246,7 +274,7 @@ mod tests {
         fs::write(cache.index_path(), b"fake scip data").unwrap();
         
         // Create metadata with an OLD timestamp (simulating stale cache)
-        let source_files = vec![src_file.to_string_lossy().to_string()];
+        let source_files = [src_file.to_string_lossy().to_string()];
         
         // Manually create stale metadata with mtime = 0 (very old)
         let stale_meta = ScipCacheMetadata {
@@ -283,4 +311,25 @@ mod tests {
         assert!(!cache.index_path.exists());
         assert!(!cache.meta_path.exists());
     }
+
+    #[test]
+    fn test_cache_in_dir_tracks_file_set() {
+        let dir = tempdir().unwrap();
+        let cache = ScipCache::in_dir(dir.path(), &dir.path().join("target/tracecraft"));
+        assert!(cache.index_path().starts_with(dir.path().join("target/tracecraft")));
+
+        let a = dir.path().join("a.rs");
+        let b = dir.path().join("b.rs");
+        fs::write(&a, "fn a() {}").unwrap();
+        fs::write(&b, "fn b() {}").unwrap();
+        let a = a.to_string_lossy().to_string();
+        let b = b.to_string_lossy().to_string();
+
+        cache.update_metadata(std::slice::from_ref(&a)).unwrap();
+        fs::write(cache.index_path(), b"fake scip data").unwrap();
+
+        assert!(cache.get_valid_cache_for(std::slice::from_ref(&a)).is_some());
+        // A new source file means the index no longer covers the workspace
+        assert!(cache.get_valid_cache_for(&[a, b]).is_none());
+    }
 }
@@ -1,11 +1,11 @@
-/// SCIP Index Generator Runner.
-/// 
-/// Multi-language support for generating SCIP indices:
-/// - Rust: Uses `rust-analyzer scip`
-/// - Python: Uses `scip-python`
-/// 
-/// Phase 3.2: Caching integration for incremental regeneration.
-/// Phase 3 v2: Multi-language support (Rust + Python).
+//! SCIP Index Generator Runner.
+//! 
+//! Multi-language support for generating SCIP indices:
+//! - Rust: Uses `rust-analyzer scip`
+//! - Python: Uses `scip-python`
+//! 
+//! Phase 3.2: Caching integration for incremental regeneration.
+//! Phase 3 v2: Multi-language support (Rust + Python).
 
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -52,7 +52,7 @@ pub fn generate_fresh_index(
     // Generate the index
     let output_file = cache.index_path().to_path_buf();
     
-    println!("[SCIP] Generating {} index for: {}", language, workspace_root.display());
+    tracing::info!("[SCIP] Generating {} index for: {}", language, workspace_root.display());
     
     let status = run_indexer_command(workspace_root, language, &output_file)?;
 
@@ -68,14 +68,78 @@ pub fn generate_fresh_index(
     // Update cache metadata
     if !source_files.is_empty() {
         if let Err(e) = cache.update_metadata(source_files) {
-            eprintln!("[SCIP Cache] Warning: Failed to update metadata: {}", e);
+            tracing::warn!("[SCIP Cache] Failed to update metadata: {}", e);
         }
     }
 
-    println!("[SCIP] Generated index: {}", output_file.display());
+    tracing::info!("[SCIP] Generated index: {}", output_file.display());
     Ok(output_file)
 }
 
+/// Cache directory for `--index-with-ra`, relative to the workspace root.
+pub const RA_CACHE_DIR: &str = "target/tracecraft";
+
+/// Default indexing command; `--output <index>` is appended.
+pub const DEFAULT_RA_COMMAND: &str = "rust-analyzer scip .";
+
+/// Index the workspace with rust-analyzer (or a custom `command`) and return the
+/// index path. The index is cached under [`RA_CACHE_DIR`] and reused while
+/// `source_files` are unchanged.
+pub fn index_with_rust_analyzer(
+    workspace_root: &Path,
+    command: Option<&str>,
+    source_files: &[String],
+) -> Result<PathBuf> {
+    let cache = ScipCache::in_dir(workspace_root, &workspace_root.join(RA_CACHE_DIR));
+    if let Some(cached_path) = cache.get_valid_cache_for(source_files) {
+        return Ok(cached_path);
+    }
+
+    let output_file = std::path::absolute(cache.index_path())
+        .context("Failed to resolve index output path")?;
+    if let Some(dir) = output_file.parent() {
+        std::fs::create_dir_all(dir).context("Failed to create SCIP cache directory")?;
+    }
+
+    let spec = build_ra_command_spec(command, &output_file)?;
+    tracing::debug!("[SCIP] Running: {} {}", spec.program, spec.args.join(" "));
+    let status = Command::new(&spec.program)
+        .args(&spec.args)
+        .current_dir(workspace_root)
+        .status()
+        .with_context(|| format!("Failed to execute {}", spec.program))?;
+
+    if !status.success() {
+        bail!("{} failed with exit code: {:?}", spec.program, status.code());
+    }
+    if !output_file.exists() {
+        bail!("Indexer did not create {}", output_file.display());
+    }
+
+    if let Err(e) = cache.update_metadata(source_files) {
+        tracing::warn!("[SCIP Cache] Failed to update metadata: {}", e);
+    }
+    tracing::info!("[SCIP] Generated index: {}", output_file.display());
+    Ok(output_file)
+}
+
+/// Build the indexing command. `{output}` in a custom command is replaced by the
+/// index path; otherwise `--output <index>` is appended.
+pub fn build_ra_command_spec(command: Option<&str>, output_file: &Path) -> Result<ScipCommandSpec> {
+    let command = command.unwrap_or(DEFAULT_RA_COMMAND);
+    let output = output_file.to_string_lossy();
+    let mut parts = command.split_whitespace();
+    let program = parts.next()
+        .ok_or_else(|| anyhow::anyhow!("Empty indexing command"))?
+        .to_string();
+
+    let mut args: Vec<String> = parts.map(|a| a.replace("{output}", &output)).collect();
+    if !command.contains("{output}") {
+        args.extend(["--output".to_string(), output.to_string()]);
+    }
+    Ok(ScipCommandSpec { program, args })
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // Internal Implementation
 // ═══════════════════════════════════════════════════════════════════════════
@@ -95,7 +159,7 @@ fn check_indexer_available(language: Language) -> Result<()> {
     match check {
         Ok(output) if output.status.success() => {
             let version = String::from_utf8_lossy(&output.stdout);
-            println!("[SCIP] Using {}: {}", command, version.trim());
+            tracing::debug!("[SCIP] Using {}: {}", command, version.trim());
             Ok(())
         }
         Ok(output) => {
@@ -199,14 +263,28 @@ mod tests {
         assert_ne!(rust_spec.args[0], python_spec.args[0]); // "scip" vs "index"
     }
 
+    #[test]
+    fn test_build_ra_command_spec() {
+        let out = Path::new("/ws/target/tracecraft/index.scip");
+
+        let spec = build_ra_command_spec(None, out).unwrap();
+        assert_eq!(spec.program, "rust-analyzer");
+        assert_eq!(spec.args, vec!["scip", ".", "--output", "/ws/target/tracecraft/index.scip"]);
+
+        let spec = build_ra_command_spec(Some("my-indexer --out={output} ."), out).unwrap();
+        assert_eq!(spec.program, "my-indexer");
+        assert_eq!(spec.args, vec!["--out=/ws/target/tracecraft/index.scip", "."]);
+
+        assert!(build_ra_command_spec(Some("  "), out).is_err());
+    }
+
     #[test]
     #[ignore] // Requires rust-analyzer to be installed
     fn test_generate_scip_index() {
         use std::env;
         let workspace = env::current_dir().unwrap();
         let result = generate_scip_index(&workspace);
-        if result.is_ok() {
-            let path = result.unwrap();
+        if let Ok(path) = result {
             assert!(path.exists());
         }
     }
@@ -0,0 +1,146 @@
+//! Graph Snapshots
+//!
+//! Saves an analyzed call graph together with the sources it was built from
+//! (`--save-graph`), so later runs can query, trace, diff or re-export it
+//! (`--load-graph`) without parsing anything. Snapshots are bincode behind a
+//! magic header and format version, or JSON when the path ends in `.json`.
+//! Graphs exported with `--format json` load too, without sources.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::api::dto::GraphDto;
+use crate::domain::callgraph::CallGraph;
+use crate::domain::source::SourceSet;
+
+/// First bytes of a binary snapshot.
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"TCG\0";
+
+/// Bumped whenever the serialized graph or source types change.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    pub graph: CallGraph,
+    /// Sources for trace snippets; empty for graphs from a SCIP index alone.
+    pub files: SourceSet,
+}
+
+/// JSON form, versioned like the binary one.
+#[derive(Serialize, Deserialize)]
+struct JsonSnapshot {
+    version: u32,
+    #[serde(flatten)]
+    snapshot: GraphSnapshot,
+}
+
+/// JSON forms [`GraphSnapshot::load`] accepts.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonGraph {
+    Snapshot(JsonSnapshot),
+    Export(GraphDto),
+}
+
+impl GraphSnapshot {
+    pub fn new(graph: CallGraph, files: SourceSet) -> Self {
+        Self { graph, files }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = if path.extension().is_some_and(|e| e == "json") {
+            let json = JsonSnapshot { version: SNAPSHOT_FORMAT_VERSION, snapshot: self.clone() };
+            serde_json::to_vec(&json)?
+        } else {
+            let mut bytes = SNAPSHOT_MAGIC.to_vec();
+            bytes.extend(bincode::serialize(&(SNAPSHOT_FORMAT_VERSION, self))?);
+            bytes
+        };
+        fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let version = match bytes.strip_prefix(SNAPSHOT_MAGIC.as_slice()) {
+            Some(body) => {
+                let version = body.get(..4).map_or(0, |v| u32::from_le_bytes(v.try_into().unwrap()));
+                if version == SNAPSHOT_FORMAT_VERSION {
+                    let (_, snapshot): (u32, GraphSnapshot) = bincode::deserialize(body).context("Corrupt snapshot")?;
+                    return Ok(snapshot);
+                }
+                version
+            }
+            None => match serde_json::from_slice(&bytes).context("Neither a graph snapshot nor a JSON graph")? {
+                JsonGraph::Snapshot(json) if json.version == SNAPSHOT_FORMAT_VERSION => return Ok(json.snapshot),
+                JsonGraph::Snapshot(json) => json.version,
+                JsonGraph::Export(dto) => return Ok(Self::new(CallGraph::from(dto), SourceSet::default())),
+            },
+        };
+        bail!("snapshot format version {} is not supported (expected {}); re-run with --save-graph", version, SNAPSHOT_FORMAT_VERSION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::{CallEdge, CallGraphNode, EdgeKind};
+    use crate::domain::metrics::FnMetrics;
+    use crate::domain::panics::{PanicKind, PanicSite};
+    use crate::domain::source::{SourceFile, SourceOrigin};
+
+    fn snapshot() -> GraphSnapshot {
+        let node = CallGraphNode {
+            id: "app::main".into(),
+            callees: vec![CallEdge::at("app::run", "src/main.rs", 2).with_kind(EdgeKind::Spawn)],
+            label: Some("app::main".to_string()),
+            is_async: true,
+            cfg: Some("unix".to_string()),
+            location: Some("src/main.rs:1".to_string()),
+            metrics: Some(FnMetrics::new()),
+            coverage: Some(3),
+            unsafe_blocks: 1,
+            panics: vec![PanicSite { kind: PanicKind::Unwrap, line: 2 }],
+            signature: Some("async fn main()".to_string()),
+            doc: Some("Entry point.".to_string()),
+            origin: Some(SourceOrigin::Example),
+            ..Default::default()
+        };
+        let files = SourceSet::new(vec![SourceFile::new("app", "src/main.rs", "fn main() {\n    run();\n}\n")]);
+        GraphSnapshot::new(CallGraph::new(vec![node]), files)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["graph.tcg", "graph.json"] {
+            let path = tmp.path().join(name);
+            snapshot().save(&path).unwrap();
+            let back = GraphSnapshot::load(&path).unwrap();
+            let node = &back.graph.nodes[0];
+            assert_eq!(node.callees, snapshot().graph.nodes[0].callees, "{}", name);
+            assert_eq!((node.is_async, node.cfg.as_deref(), node.metrics, node.coverage), (true, Some("unix"), Some(FnMetrics::new()), Some(3)));
+            assert_eq!(node.panics, snapshot().graph.nodes[0].panics);
+            assert_eq!(back.files, snapshot().files);
+        }
+        assert!(fs::read(tmp.path().join("graph.tcg")).unwrap().starts_with(SNAPSHOT_MAGIC));
+    }
+
+    #[test]
+    fn test_load_rejects_other_versions_and_reads_exports() {
+        let tmp = tempfile::tempdir().unwrap();
+        let stale = tmp.path().join("stale.tcg");
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&(SNAPSHOT_FORMAT_VERSION + 1, snapshot())).unwrap());
+        fs::write(&stale, bytes).unwrap();
+        let err = GraphSnapshot::load(&stale).unwrap_err().to_string();
+        assert!(err.contains(&format!("version {} is not supported", SNAPSHOT_FORMAT_VERSION + 1)), "{}", err);
+
+        let export = tmp.path().join("export.json");
+        fs::write(&export, serde_json::to_string(&GraphDto::from(&snapshot().graph)).unwrap()).unwrap();
+        let loaded = GraphSnapshot::load(&export).unwrap();
+        assert_eq!(loaded.graph.nodes[0].location.as_deref(), Some("src/main.rs:1"));
+        assert!(loaded.files.is_empty());
+    }
+}
@@ -1,30 +1,272 @@
-use dashmap::DashMap;
+use dashmap::mapref::one::Ref;
+use dashmap::{DashMap, DashSet};
+
+use crate::domain::paths::ProjectRoot;
+use crate::domain::source::SourceSet;
 
 pub struct SourceManager {
     // path -> lines
     files: DashMap<String, Vec<String>>,
+    /// Lets absolute or `./`-prefixed paths find root-relative files.
+    root: Option<ProjectRoot>,
+    /// Read files below `root` on first use.
+    lazy: bool,
+    /// Files a lazy read did not find.
+    missing: DashSet<String>,
 }
 
 impl SourceManager {
-    pub fn new(loaded_files: &[(String, String, String)]) -> Self {
+    pub fn new(sources: &SourceSet) -> Self {
         let sm = SourceManager {
             files: DashMap::new(),
+            root: None,
+            lazy: false,
+            missing: DashSet::new(),
         };
-        for (_, file_path, content) in loaded_files {
-            let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-            sm.files.insert(file_path.clone(), lines);
+        for file in sources {
+            let lines: Vec<String> = file.content.lines().map(|s| s.to_string()).collect();
+            sm.files.insert(file.path.clone(), lines);
         }
         sm
     }
 
+    /// No contents up front: each file below `root` is read when first
+    /// asked for and kept, e.g. for graphs from a SCIP index alone.
+    pub fn lazy(root: ProjectRoot) -> Self {
+        Self { lazy: true, ..Self::new(&SourceSet::default()).with_root(root) }
+    }
+
+    /// Also find files by any spelling of their path below `root`.
+    pub fn with_root(mut self, root: ProjectRoot) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    fn lines(&self, file_path: &str) -> Option<Ref<'_, String, Vec<String>>> {
+        if let Some(lines) = self.files.get(file_path) {
+            return Some(lines);
+        }
+        let root = self.root.as_ref()?;
+        let key = root.relativize(file_path);
+        if let Some(lines) = self.files.get(&key) {
+            return Some(lines);
+        }
+        if !self.lazy || self.missing.contains(&key) {
+            return None;
+        }
+        match std::fs::read_to_string(root.resolve(&key)) {
+            Ok(text) => {
+                self.files.insert(key.clone(), text.lines().map(str::to_string).collect());
+                self.files.get(&key)
+            }
+            Err(_) => {
+                self.missing.insert(key);
+                None
+            }
+        }
+    }
+
     pub fn get_snippet(&self, file_path: &str, line_number: usize) -> Option<String> {
         if line_number == 0 { return None; }
         // Attempt to retrieve using the path as is
-        if let Some(lines) = self.files.get(file_path) {
+        if let Some(lines) = self.lines(file_path) {
             if line_number <= lines.len() {
                 return Some(lines[line_number - 1].trim().to_string());
             }
         }
         None
     }
+
+    /// Lines `line_number - context_before ..= line_number + context_after`
+    /// (1-based, clamped to the file), with their common indentation removed.
+    pub fn get_snippet_range(&self, file_path: &str, line_number: usize, context_before: usize, context_after: usize) -> Option<String> {
+        let lines = self.lines(file_path)?;
+        if line_number == 0 || line_number > lines.len() { return None; }
+        let start = line_number.saturating_sub(context_before).max(1);
+        let end = (line_number + context_after).min(lines.len());
+        Some(dedent(&lines[start - 1..end]))
+    }
+
+    /// The signature starting at `line_number`, up to the line that opens
+    /// the body, followed by at most `body_lines` lines of the body.
+    pub fn get_signature_snippet(&self, file_path: &str, line_number: usize, body_lines: usize) -> Option<String> {
+        let (open, close) = self.item_extent(file_path, line_number)?;
+        let end = (open + body_lines).min(close);
+        self.get_snippet_range(file_path, line_number, 0, end - line_number)
+    }
+
+    /// The complete function (or any braced item) starting at `start_line`:
+    /// its signature through the brace that closes the body. Braces in
+    /// strings, char literals and comments are skipped.
+    pub fn get_function_body(&self, file_path: &str, start_line: usize) -> Option<String> {
+        let (_, close) = self.item_extent(file_path, start_line)?;
+        self.get_snippet_range(file_path, start_line, 0, close - start_line)
+    }
+
+    /// Line (1-based) of the brace closing the item starting at `start_line`.
+    pub fn item_end(&self, file_path: &str, start_line: usize) -> Option<usize> {
+        self.item_extent(file_path, start_line).map(|(_, close)| close)
+    }
+
+    /// Lines (1-based) that open and close the body of the item starting at
+    /// `line_number`; a bodiless declaration (`fn f();`) opens and closes on
+    /// the line of its `;`.
+    fn item_extent(&self, file_path: &str, line_number: usize) -> Option<(usize, usize)> {
+        let lines = self.lines(file_path)?;
+        if line_number == 0 || line_number > lines.len() { return None; }
+        let (open, close) = body_extent(&lines[line_number - 1..])?;
+        Some((line_number + open, line_number + close))
+    }
+}
+
+/// Offsets of the lines holding the first `{` (or a `;` before it) and its
+/// matching `}`.
+fn body_extent(lines: &[String]) -> Option<(usize, usize)> {
+    let mut depth = 0usize;
+    let mut open = None;
+    let mut block_comments = 0usize;
+    for (offset, line) in lines.iter().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let (c, next) = (chars[i], chars.get(i + 1).copied());
+            if block_comments > 0 {
+                match (c, next) {
+                    ('*', Some('/')) => { block_comments -= 1; i += 1; }
+                    ('/', Some('*')) => { block_comments += 1; i += 1; }
+                    _ => {}
+                }
+                i += 1;
+                continue;
+            }
+            match (c, next) {
+                ('/', Some('/')) => break,
+                ('/', Some('*')) => { block_comments += 1; i += 1; }
+                ('"', _) => i = skip_string(&chars, i + 1, 0),
+                ('r', Some('#' | '"')) if i == 0 || !is_ident_char(chars[i - 1]) => {
+                    let hashes = chars[i + 1..].iter().take_while(|&&h| h == '#').count();
+                    if chars.get(i + 1 + hashes) == Some(&'"') {
+                        i = skip_string(&chars, i + 2 + hashes, hashes);
+                    }
+                }
+                // `'{'` and `'\''` are chars; `'a` is a lifetime
+                ('\'', Some('\\')) => {
+                    i += 2;
+                    while i < chars.len() && chars[i] != '\'' { i += 1; }
+                }
+                ('\'', Some(_)) if chars.get(i + 2) == Some(&'\'') => i += 2,
+                ('{', _) => {
+                    open.get_or_insert(offset);
+                    depth += 1;
+                }
+                ('}', _) if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((open?, offset));
+                    }
+                }
+                (';', _) if open.is_none() => return Some((offset, offset)),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Index of the closing quote of a string whose body starts at `i`
+/// (followed by `hashes` `#`s for raw strings). Strings spanning lines are
+/// treated as ending with the line.
+fn skip_string(chars: &[char], mut i: usize, hashes: usize) -> usize {
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if hashes == 0 => i += 1,
+            '"' if chars[i + 1..].iter().take(hashes).filter(|&&h| h == '#').count() == hashes => return i + hashes,
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn dedent(lines: &[String]) -> String {
+    let indent = lines.iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines.iter()
+        .map(|l| l.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::source::SourceFile;
+
+    #[test]
+    fn test_snippet_ranges() {
+        let code = "impl Tool {\n    pub fn run(\n        &self,\n    ) -> u32 {\n        let x = 1;\n        x + 1\n    }\n}\n";
+        let sm = SourceManager::new(&SourceSet::from(vec![SourceFile::new("app", "src/lib.rs", code)]));
+
+        assert_eq!(sm.get_snippet("src/lib.rs", 5).as_deref(), Some("let x = 1;"));
+        assert_eq!(sm.get_snippet_range("src/lib.rs", 5, 1, 1).as_deref(), Some(") -> u32 {\n    let x = 1;\n    x + 1"));
+        assert_eq!(sm.get_snippet_range("src/lib.rs", 1, 5, 0).as_deref(), Some("impl Tool {"));
+        assert_eq!(sm.get_signature_snippet("src/lib.rs", 2, 1).as_deref(), Some("pub fn run(\n    &self,\n) -> u32 {\n    let x = 1;"));
+        assert_eq!(sm.get_signature_snippet("src/lib.rs", 2, 9).as_deref(), Some("pub fn run(\n    &self,\n) -> u32 {\n    let x = 1;\n    x + 1\n}"));
+        assert_eq!(sm.get_snippet_range("src/lib.rs", 99, 1, 1), None);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = ProjectRoot::new(tmp.path());
+        let absolute = root.dir().join("src/lib.rs");
+        assert_eq!(sm.get_snippet(absolute.to_str().unwrap(), 5), None);
+        let sm = sm.with_root(root);
+        assert_eq!(sm.get_snippet(absolute.to_str().unwrap(), 5).as_deref(), Some("let x = 1;"));
+        assert_eq!(sm.get_snippet("./src/lib.rs", 5).as_deref(), Some("let x = 1;"));
+    }
+
+    #[test]
+    fn test_lazy_loading() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir(tmp.path().join("src")).unwrap();
+        std::fs::write(tmp.path().join("src/main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        let sm = SourceManager::lazy(ProjectRoot::new(tmp.path()));
+
+        assert_eq!(sm.get_snippet("src/main.rs", 2).as_deref(), Some("run();"));
+        // Read once and kept
+        std::fs::write(tmp.path().join("src/main.rs"), "").unwrap();
+        assert_eq!(sm.get_function_body("src/main.rs", 1).as_deref(), Some("fn main() {\n    run();\n}"));
+        assert_eq!(sm.get_snippet("src/missing.rs", 1), None);
+        assert_eq!(sm.get_snippet("src/missing.rs", 1), None);
+    }
+
+    #[test]
+    fn test_function_body() {
+        let code = r##"fn tricky<'a>(s: &'a str) -> usize {
+    let open = '{';
+    let text = "}}} \" {";
+    let raw = r#"}"#;
+    // }
+    /* } /* nested } */ */
+    if s.is_empty() { 0 } else { 1 }
+}
+trait T {
+    fn decl(&self);
+}
+fn after() {}
+"##;
+        let sm = SourceManager::new(&SourceSet::from(vec![SourceFile::new("app", "src/lib.rs", code)]));
+        let body = sm.get_function_body("src/lib.rs", 1).unwrap();
+        assert_eq!(body.lines().count(), 8);
+        assert!(body.ends_with("if s.is_empty() { 0 } else { 1 }\n}"), "{}", body);
+        assert_eq!(sm.get_function_body("src/lib.rs", 10).as_deref(), Some("fn decl(&self);"));
+        assert_eq!(sm.get_function_body("src/lib.rs", 12).as_deref(), Some("fn after() {}"));
+        assert_eq!(sm.get_function_body("src/lib.rs", 0), None);
+    }
 }
@@ -0,0 +1,78 @@
+//! Source Watcher
+//!
+//! Watches a workspace for changes to Rust sources and manifests and hands
+//! them over in debounced batches, so an editor's save (often several
+//! events) triggers a single rebuild.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Quiet period after the last event before a batch is reported.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub struct SourceWatcher {
+    // Dropping the watcher stops the notifications
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl SourceWatcher {
+    pub fn new(root: &Path) -> Result<Self> {
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(tx).context("Failed to create file watcher")?;
+        watcher.watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {}", root.display()))?;
+        Ok(Self { _watcher: watcher, events })
+    }
+
+    /// Block until relevant files change; returns the changed paths, sorted.
+    /// Returns `None` once the watcher has shut down.
+    pub fn next_batch(&self) -> Option<Vec<PathBuf>> {
+        let mut changed = Vec::new();
+        loop {
+            // Wait indefinitely for the first relevant event, then only for the debounce window
+            let event = if changed.is_empty() {
+                self.events.recv().ok()?
+            } else {
+                match self.events.recv_timeout(DEBOUNCE) {
+                    Ok(event) => event,
+                    Err(_) => break,
+                }
+            };
+            match event {
+                Ok(event) if !event.kind.is_access() => {
+                    changed.extend(event.paths.into_iter().filter(|p| is_relevant(p)));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("watch error: {}", e),
+            }
+        }
+        changed.sort();
+        changed.dedup();
+        Some(changed)
+    }
+}
+
+/// Rust sources and manifests outside build output directories.
+pub fn is_relevant(path: &Path) -> bool {
+    let in_target = path.components().any(|c| c == Component::Normal("target".as_ref()));
+    let is_source = path.extension().is_some_and(|e| e == "rs")
+        || path.file_name().is_some_and(|n| n == "Cargo.toml");
+    is_source && !in_target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_relevant() {
+        assert!(is_relevant(Path::new("ws/app/src/main.rs")));
+        assert!(is_relevant(Path::new("ws/app/Cargo.toml")));
+        assert!(!is_relevant(Path::new("ws/target/debug/build/out.rs")));
+        assert!(!is_relevant(Path::new("ws/graph.dot")));
+    }
+}
@@ -5,3 +5,9 @@ pub mod infrastructure;
 pub mod ports;
 pub mod common;
 pub mod api;
+pub mod analysis;
+pub mod config;
+pub mod error;
+
+pub use analysis::{analyze, AnalysisConfig, Engine};
+pub use error::Error;
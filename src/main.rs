@@ -1,24 +1,74 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::collections::HashMap;
 
-use mr_hedgehog::infrastructure::{SimpleCallGraphBuilder, DotExporter};
-use mr_hedgehog::infrastructure::project_loader::ProjectLoader;
+use mr_hedgehog::infrastructure::{DotClustering, DotExporter, DotStyle};
 use mr_hedgehog::infrastructure::source_manager::SourceManager;
 use mr_hedgehog::infrastructure::concurrency;
-use mr_hedgehog::domain::trace::TraceGenerator;
+use mr_hedgehog::infrastructure::git;
+use mr_hedgehog::infrastructure::crate_fetch::{self, CrateSpec};
+use mr_hedgehog::domain::trace::{TraceGenerator, TraceOptions, TracePath, TraceStrategy};
+use mr_hedgehog::domain::bundle::ContextBundle;
 use mr_hedgehog::domain::language::Language;
 use mr_hedgehog::domain::entry_point::EntryPointDetector;
 use mr_hedgehog::domain::flowgraph::FlowGraph;
-use mr_hedgehog::ports::{CallGraphBuilder, OutputExporter};
+use mr_hedgehog::domain::modules::{ModuleGraph, ModuleLevel};
+use mr_hedgehog::api::repl::{self, ReplSession};
+use mr_hedgehog::api::lsp::LspServer;
+use mr_hedgehog::api::tui;
+use mr_hedgehog::domain::links::SourceLinks;
+use mr_hedgehog::domain::paths::ProjectRoot;
+use mr_hedgehog::domain::search::{find_symbols, format_matches, resolve_symbol, suggest, MatchMode, SymbolMatch};
+use mr_hedgehog::domain::query;
+use mr_hedgehog::domain::metrics::MetricsReport;
+use mr_hedgehog::domain::coverage::{self, CoverageData};
+use mr_hedgehog::config::{self, ProjectConfig, CONFIG_FILE_NAME};
+use mr_hedgehog::domain::taint::{self, TaintConfig};
+use mr_hedgehog::domain::unsafety;
+use mr_hedgehog::domain::dominators;
+use mr_hedgehog::domain::impact;
+use mr_hedgehog::domain::reachability::{test_map, unreachable_functions};
+use mr_hedgehog::domain::stats::GraphStats;
+use mr_hedgehog::domain::filter::{condense, neighborhood, subgraph, GraphFilter};
+use mr_hedgehog::domain::external::{add_external_stubs, collapse_external};
+use mr_hedgehog::domain::diff::GraphDiff;
+use mr_hedgehog::domain::callgraph::{CallGraph, CallGraphNode, EdgeKind};
+use mr_hedgehog::domain::source::{SourceFile, SourceOrigin, SourceSet};
+use mr_hedgehog::domain::cfg::{CfgOptions, TargetCfg};
+use mr_hedgehog::infrastructure::analysis_cache::AnalysisCache;
+use mr_hedgehog::infrastructure::project_loader::{ProjectLoader, SourceFilter};
+use mr_hedgehog::analysis::{analyze_with_sources, Analysis, AnalysisConfig, Engine};
+use mr_hedgehog::domain::index::AnalysisError;
+use mr_hedgehog::error::{self, Error};
+use mr_hedgehog::infrastructure::watcher::SourceWatcher;
+use mr_hedgehog::infrastructure::snapshot::GraphSnapshot;
+use mr_hedgehog::infrastructure::graphviz::{self, RenderFormat};
+use mr_hedgehog::infrastructure::progress_bar::TerminalProgress;
+use mr_hedgehog::domain::progress::Progress;
+use std::io::{IsTerminal, Read};
+use std::sync::Arc;
+use mr_hedgehog::ports::{format_extension, format_for_path, OutputExporter, OUTPUT_FORMATS};
 use mr_hedgehog::ports::flowchart_exporter::FlowchartExporter;
+use mr_hedgehog::ports::module_exporter::ModuleExporter;
+use mr_hedgehog::ports::json_exporter::JsonExporter;
+use mr_hedgehog::ports::html_exporter::HtmlExporter;
+use mr_hedgehog::ports::diff_exporter::DiffExporter;
+use mr_hedgehog::ports::sqlite_exporter::SqliteExporter;
+use mr_hedgehog::ports::trace_exporter::{TraceExporter, TraceFormat};
+use mr_hedgehog::ports::bundle_exporter::{BundleExporter, BundleFormat};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Cli {
-    /// single .rs file(s)
+    /// single .rs file(s); "-" reads one from stdin
     #[arg(short, long)]
     input: Vec<String>,
 
+    /// Analyze this Rust code instead of a workspace, e.g. for one-off checks
+    /// in shell pipelines
+    #[arg(long, value_name = "CODE", conflicts_with_all = ["input", "folder", "workspace"])]
+    eval: Option<String>,
+
     /// folder(s) (recursively collect *.rs)
     #[arg(short='d', long)]
     folder: Vec<String>,
@@ -27,13 +77,192 @@ struct Cli {
     #[arg(long)]
     workspace: Option<String>,
 
-    /// output path (required for command line mode)
+    /// Shallow-clone this repository (URL[#branch, tag or commit]) to the temp
+    /// directory and analyze the workspace found in it
+    #[arg(long, value_name = "URL", conflicts_with_all = ["workspace", "eval", "load_graph", "lsif"])]
+    git: Option<String>,
+
+    /// output path (required for command line mode; "-" for stdout); repeat
+    /// it to write several formats from one analysis
     #[arg(short, long)]
-    output: Option<String>,
+    output: Vec<String>,
+
+    /// output format: "dot", "json", "html" (interactive viewer) or "sqlite";
+    /// comma separated or repeated for several outputs. Defaults to the
+    /// --output extension, else "dot"
+    #[arg(short, long, default_value="dot", value_delimiter = ',')]
+    format: Vec<String>,
+
+    /// Only export nodes whose id matches this glob (repeatable, e.g. "my_crate::*")
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Drop nodes whose id matches this glob (repeatable, e.g. "std::*")
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Redirect edges into filtered-out nodes to a single "external" node
+    #[arg(long)]
+    collapse_excluded: bool,
+
+    /// Fold the stub nodes of an external crate into one node (glob, repeatable;
+    /// "*" for all); stubs come from --include-external
+    #[arg(long, value_name = "CRATE")]
+    collapse_external: Vec<String>,
+
+    /// Collapse each group of mutually recursive functions into one node
+    #[arg(long)]
+    condense: bool,
+
+    /// Drop #[test] functions, code under #[cfg(test)] and integration test code
+    #[arg(long)]
+    exclude_tests: bool,
+
+    /// Drop the code of test, bench or example targets loaded with --tests,
+    /// --benches or --examples (comma separated or repeated)
+    #[arg(long, value_name = "KIND", value_delimiter = ',', value_parser = parse_origin)]
+    exclude_origin: Vec<SourceOrigin>,
+
+    /// Enabled features for evaluating #[cfg(feature = "..")] (comma separated or repeated)
+    #[arg(long, value_delimiter = ',')]
+    features: Option<Vec<String>>,
+
+    /// Do not add the packages' default features to --features
+    #[arg(long)]
+    no_default_features: bool,
+
+    /// Target triple for evaluating #[cfg(unix)], #[cfg(target_os = "..")], ...
+    #[arg(long, value_name = "TRIPLE")]
+    target: Option<String>,
+
+    /// Only export the neighborhood of this function (name or node id)
+    #[arg(long, value_name = "SYMBOL")]
+    around: Option<String>,
+
+    /// Hops of callers and callees kept around --around (default: 2)
+    #[arg(long, default_value = "2")]
+    depth: usize,
+
+    /// Label DOT edges with the call site (file:line)
+    #[arg(long)]
+    edge_labels: bool,
+
+    /// Group DOT nodes into boxes: "crate", "module" (nested per module) or "none" (default)
+    #[arg(long, default_value = "none")]
+    cluster: String,
+
+    /// Fill colors for the DOT crate boxes, cycled (comma separated, e.g. "#eef4fb,#fdf1e6")
+    #[arg(long, value_delimiter = ',')]
+    cluster_colors: Vec<String>,
+
+    /// Also render the DOT output with Graphviz: "svg" or "png" (written next to --output)
+    #[arg(long, value_name = "FORMAT")]
+    render: Option<String>,
+
+    /// DOT layout direction: TB (default), LR, BT or RL
+    #[arg(long, value_name = "DIR")]
+    dot_rankdir: Option<String>,
+
+    /// Default DOT node shape (ellipse, box, ...)
+    #[arg(long, value_name = "SHAPE")]
+    dot_node_shape: Option<String>,
+
+    /// Font for DOT labels
+    #[arg(long, value_name = "FONT")]
+    dot_font: Option<String>,
+
+    /// Color DOT edges of a kind, e.g. "dynamic=red" (repeatable)
+    #[arg(long, value_name = "KIND=COLOR")]
+    dot_edge_color: Vec<String>,
 
-    /// output format (ignored for now)
-    #[arg(short, long, default_value="dot")]
-    format: String,
+    /// Truncate DOT node labels to N characters
+    #[arg(long, value_name = "N")]
+    dot_max_label: Option<usize>,
+
+    /// Link DOT edges to their call site; {file} and {line} are substituted
+    /// (e.g. "vscode://file/{file}:{line}")
+    #[arg(long, value_name = "TEMPLATE")]
+    dot_source_url: Option<String>,
+
+    /// Link nodes in DOT, HTML and Markdown trace output to their file and
+    /// line under this URL (e.g. "https://github.com/org/repo/blob/<sha>/")
+    #[arg(long, value_name = "URL")]
+    link_base: Option<String>,
+
+    /// Show function signatures ("fn run(x: i32) -> i32") in DOT labels
+    /// and traces instead of bare names
+    #[arg(long)]
+    signatures: bool,
+
+    /// Entry point(s) to trace from, by name, node id or target ("bin:tool2";
+    /// repeatable; default: main)
+    #[arg(long = "entry")]
+    entry: Vec<String>,
+
+    /// Write an unreachable-function report to this path ("-" for stdout; *.json for JSON)
+    #[arg(long, value_name = "PATH")]
+    unreachable: Option<String>,
+
+    /// Write which tests reach each library and binary function, and the
+    /// functions no test reaches ("-" for stdout; *.json for JSON); add
+    /// --tests to count the integration tests too
+    #[arg(long, value_name = "PATH")]
+    test_map: Option<String>,
+
+    /// Write the dominator tree of the functions the entry points reach, and
+    /// the gatekeepers every path below them passes through ("-" for stdout;
+    /// *.json for JSON, *.dot for the tree in DOT)
+    #[arg(long, value_name = "PATH")]
+    dominators: Option<String>,
+
+    /// Annotate nodes with hit counts from an LCOV file or `cargo llvm-cov --json` output
+    #[arg(long, value_name = "PATH")]
+    coverage: Option<String>,
+
+    /// Write the functions reachable from the entry points that --coverage
+    /// shows never ran ("-" for stdout; *.json for JSON)
+    #[arg(long, value_name = "PATH", requires = "coverage")]
+    uncovered: Option<String>,
+
+    /// Write the call paths from the entry points to unsafe fns and unsafe
+    /// blocks ("-" for stdout; *.json for JSON) and export only those paths
+    #[arg(long, value_name = "PATH")]
+    unsafe_reachability: Option<String>,
+
+    /// Report the call paths from the source to the sink functions listed
+    /// in this TOML file (`sources = [..]`, `sinks = [..]`, glob patterns)
+    #[arg(long, value_name = "CONFIG")]
+    taint: Option<String>,
+
+    /// Where --taint writes its report ("-" for stdout, the default; *.json for JSON)
+    #[arg(long, value_name = "PATH", requires = "taint")]
+    taint_output: Option<String>,
+
+    /// Write the most complex functions (cyclomatic complexity, nesting,
+    /// statements) to this path ("-" for stdout; *.json for JSON)
+    #[arg(long, value_name = "PATH")]
+    metrics: Option<String>,
+
+    /// Number of functions listed by --metrics
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    metrics_top: usize,
+
+    /// List call paths from this function (name or node id) to --path-to
+    #[arg(long, value_name = "SYMBOL")]
+    path_from: Option<String>,
+
+    /// Target function of --path-from
+    #[arg(long, value_name = "SYMBOL")]
+    path_to: Option<String>,
+
+    /// Report at most this many paths for --path-from/--path-to, and per flow for --taint (default: 10)
+    #[arg(long, default_value = "10")]
+    path_limit: usize,
+
+    /// List the shortest call path from this function (name or node id) to
+    /// each potential panic (panic!, todo!, unwrap, expect, indexing, ...)
+    #[arg(long, value_name = "ENTRY")]
+    panic_paths: Option<String>,
 
     /// 反向查詢（查詢所有能呼叫到此 function 的所有路徑，例 Type::func@crate）
     #[arg(long)]
@@ -43,26 +272,147 @@ struct Cli {
     #[arg(long)]
     expand_paths: bool,
 
+    /// Write the traced call paths from the entry points to this path ("-" for stdout)
+    #[arg(long, value_name = "PATH")]
+    trace_output: Option<String>,
+
+    /// Format of --trace-output: "markdown" (default) or "json"
+    #[arg(long, default_value = "markdown")]
+    trace_format: String,
+
     /// 分支 event 摘要模式（if/match 分支遇到相同 event 只記一次，不重複展開）
     #[arg(long)]
     branch_summary: bool,
 
-    /// Enable debug output
-    #[arg(long, short='D')]
+    /// Log more: -v for debug messages, -vv for everything. RUST_LOG
+    /// (e.g. `RUST_LOG=mr_hedgehog::infrastructure=debug`) overrides this
+    #[arg(long, short = 'v', action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log warnings and errors only
+    #[arg(long, short = 'q')]
+    quiet: bool,
+
+    /// Same as -v
+    #[arg(long, short = 'D', hide = true)]
     debug: bool,
 
+    /// Print every node and its callees before tracing
+    #[arg(long)]
+    debug_nodes: bool,
+
+    /// Don't draw progress bars (they are only drawn on a terminal anyway)
+    #[arg(long)]
+    no_progress: bool,
+
     /// Expand macros using `cargo expand` before analysis
     #[arg(long)]
     expand_macros: bool,
 
+    /// Also analyze local path dependencies outside the workspace (path = "../shared")
+    #[arg(long)]
+    follow_path_deps: bool,
+
+    /// Add struct, enum and type alias nodes, linked to their methods
+    /// (defines-method), the types of their fields (has-field-of-type) and
+    /// the functions that build them (constructs)
+    #[arg(long)]
+    types: bool,
+
+    /// Skip source files matching a gitignore-style pattern, relative to the
+    /// workspace root (e.g. "vendor/", "/examples", "*_generated.rs"); repeatable
+    #[arg(long, value_name = "GLOB")]
+    ignore: Vec<String>,
+
+    /// Also skip what .gitignore, .ignore and .git/info/exclude files exclude
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Also analyze the integration tests under tests/
+    #[arg(long)]
+    tests: bool,
+
+    /// Also analyze the benchmarks under benches/
+    #[arg(long)]
+    benches: bool,
+
+    /// Also analyze the examples under examples/
+    #[arg(long)]
+    examples: bool,
+
+    /// Leave out #[cfg(test)] modules and items while parsing instead of
+    /// tagging them as test code
+    #[arg(long)]
+    no_cfg_test: bool,
+
     /// Storage backend: "mem" (default, in-memory) or "disk" (sled DB)
     #[arg(long, default_value = "mem")]
     store: String,
 
+    /// Cache per-file analysis results under target/tracecraft/analysis/ and reuse them for unchanged files
+    #[arg(long)]
+    incremental: bool,
+
+    /// Keep running: rebuild the graph and rewrite the output whenever workspace sources change
+    #[arg(long)]
+    watch: bool,
+
+    /// Fail (exit code 3) instead of skipping files that cannot be parsed
+    #[arg(long)]
+    strict: bool,
+
     /// Analysis engine: "syn" (default, AST-based) or "scip" (rust-analyzer semantic)
     #[arg(long, default_value = "syn")]
     engine: String,
 
+    /// Load a pre-built SCIP index instead of analyzing sources (implies the scip engine);
+    /// repeatable, the indices merged into one graph
+    #[arg(long, value_name = "PATH")]
+    scip: Vec<String>,
+
+    /// Only take call edges from SCIP indices, leaving out imports and
+    /// functions used as values (reads and writes)
+    #[arg(long)]
+    scip_calls_only: bool,
+
+    /// Give calls into crates that are not analyzed (std, dependencies) stub
+    /// nodes, tagged with the crate they belong to
+    #[arg(long)]
+    include_external: bool,
+
+    /// Build the graph from an LSIF dump (JSON lines, e.g. from lsif-node) instead
+    /// of a SCIP index; with --workspace the sources only feed trace snippets
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["scip", "load_graph", "index_with_ra", "watch"])]
+    lsif: Option<String>,
+
+    /// Save the analyzed graph and its sources to a snapshot (*.json for JSON)
+    #[arg(long, value_name = "PATH")]
+    save_graph: Option<String>,
+
+    /// Use a snapshot written by --save-graph (or a --format json export)
+    /// instead of analyzing anything
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["workspace", "scip", "watch"])]
+    load_graph: Option<String>,
+
+    /// Index the workspace with rust-analyzer first (cached under target/tracecraft/), then use that index
+    #[arg(long)]
+    index_with_ra: bool,
+
+    /// Indexing command for --index-with-ra ("{output}" = index path; default: "rust-analyzer scip .")
+    #[arg(long, value_name = "CMD")]
+    ra_command: Option<String>,
+
+    /// Build the graph from rustc's MIR: `cargo +nightly check` through the
+    /// mr_hedgehog_mir_driver binary, resolving trait and generic calls
+    #[cfg(feature = "mir")]
+    #[arg(long, conflicts_with_all = ["scip", "lsif", "load_graph", "index_with_ra", "watch"])]
+    mir: bool,
+
+    /// Driver binary for --mir (default: next to this executable, else on PATH)
+    #[cfg(feature = "mir")]
+    #[arg(long, value_name = "PATH", requires = "mir")]
+    mir_driver: Option<String>,
+
     /// Programming language: "rust" (default) or "python"
     #[arg(long, default_value = "rust")]
     lang: String,
@@ -79,18 +429,329 @@ struct Cli {
     #[arg(long, default_value = "callgraph")]
     mode: String,
 
+    /// Graph to export: "functions" (default), "modules" (calls between
+    /// modules, weighted by their number) or "crates"; DOT only
+    #[arg(long, default_value = "functions")]
+    view: String,
+
     /// Max depth for flowchart expansion (default: 10)
     #[arg(long, default_value = "10")]
     max_depth: usize,
+
+    /// Project settings to use instead of the tracecraft.toml in the
+    /// project root (the current directory, or that of --workspace)
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+
+    /// Ignore tracecraft.toml
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The code of `--eval` or `--input -`, read once up front
+    #[arg(skip)]
+    snippet: Option<SourceSet>,
+
+    /// `--format` was given (on the command line or in the config) rather
+    /// than defaulted; otherwise the output extensions pick the format
+    #[arg(skip)]
+    format_given: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Serve the graph over HTTP (/graph, /node/{id}, /callers/{id}, /trace?from=...)
+    Serve {
+        /// Address to bind (default: 127.0.0.1)
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// HTTP port (default: 8080)
+        #[arg(long, default_value = "8080")]
+        port: u16,
+
+        /// Serve a graph exported with `--format json` instead of analyzing sources
+        #[arg(long, value_name = "PATH")]
+        graph: Option<String>,
+    },
+
+    /// Enumerate the call paths from entry points, with source snippets
+    Trace {
+        /// Entry point(s) to trace from, by name, node id or target ("bin:tool2";
+        /// repeatable; default: main)
+        #[arg(long = "entry")]
+        entry: Vec<String>,
+
+        /// Cut paths after this many calls (default: 30)
+        #[arg(long, default_value = "30")]
+        max_depth: usize,
+
+        /// Report at most this many paths per entry point (default: 50)
+        #[arg(long, default_value = "50")]
+        max_paths: usize,
+
+        /// "dfs" (default, every path depth-first), "bfs" (every path, shortest first)
+        /// or "shortest" (one shortest path per reachable leaf)
+        #[arg(long, default_value = "dfs")]
+        strategy: String,
+
+        /// Show each function's signature plus N lines of its body (and N lines
+        /// around call sites) instead of a single line
+        #[arg(long, value_name = "N")]
+        context: Option<usize>,
+
+        /// Show the complete source of each function on the paths
+        #[arg(long)]
+        full_body: bool,
+
+        /// Write the paths to this file instead of stdout
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+
+        /// "text" (default), "markdown" or "json"
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+
+    /// Bundle the call tree of an entry point with the full source of every
+    /// function on it, e.g. as context for an LLM
+    Bundle {
+        /// Entry point, by name or node id (default: main)
+        #[arg(long)]
+        entry: Option<String>,
+
+        /// Do not expand calls deeper than this (default: 30)
+        #[arg(long, default_value = "30")]
+        max_depth: usize,
+
+        /// Fit the bundle into about N tokens: functions far from the entry
+        /// point and rarely called are cut to their signature, then left out
+        #[arg(long, value_name = "N")]
+        token_budget: Option<usize>,
+
+        /// "markdown" (default) or "json"
+        #[arg(long, default_value = "markdown")]
+        format: String,
+
+        /// Write the bundle to this file instead of stdout
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+    },
+
+    /// Report graph metrics: counts per crate, fan-in/fan-out, call depth, most-called functions
+    Stats {
+        /// Entry point(s) the call depth is measured from (repeatable; default: main)
+        #[arg(long = "entry")]
+        entry: Vec<String>,
+
+        /// Number of most-called functions to list (default: 10)
+        #[arg(long, default_value = "10")]
+        top: usize,
+
+        /// "table" (default) or "json"
+        #[arg(long, default_value = "table")]
+        format: String,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+    },
+
+    /// List the functions whose id or label matches a pattern, with crate
+    /// and file:line, to find the exact ids for --entry or --around
+    Find {
+        /// Substring to look for (or a glob with --glob, a regex with --regex)
+        pattern: String,
+
+        /// Match the whole id or label against a glob ("app::*::send*")
+        #[arg(long, conflicts_with = "regex")]
+        glob: bool,
+
+        /// Match a regular expression ("::(new|default)$")
+        #[arg(long)]
+        regex: bool,
+
+        /// Ignore upper/lower case
+        #[arg(long)]
+        ignore_case: bool,
+
+        /// "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+
+    /// Functions a git diff touches and everything calling into them, up to
+    /// the entry points, with the affected tests
+    Impact {
+        /// Revision the working tree is compared against (default: HEAD,
+        /// i.e. the uncommitted changes)
+        #[arg(long, default_value = "HEAD")]
+        since: String,
+
+        /// "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+    },
+
+    /// Evaluate a set expression over the functions and list the result or
+    /// export it as a subgraph, e.g. "reach(main) & calls(unsafe) - crate(test_utils)";
+    /// sets: names, id globs, id(..), crate(..), file(..), all, pub, async,
+    /// unsafe, unsafe_blocks, test, external, panics, reach(..), callers(..),
+    /// calls(..), callees(..); operators: & | - ! and parentheses
+    Query {
+        /// The expression (quote it for the shell)
+        expr: String,
+
+        /// "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Export the matching functions and the calls between them to this
+        /// file instead of listing them (format from the extension; "-" for
+        /// DOT on stdout)
+        #[arg(long, value_name = "PATH")]
+        export: Option<String>,
+    },
+
+    /// Analyze once, then explore the graph interactively: callers, callees,
+    /// path, show, find and export, with Tab completion of function names
+    Repl,
+
+    /// Browse the call tree of an entry point in the terminal, next to the
+    /// source of the selected function; prints the bookmarks on exit
+    Browse {
+        /// Entry point, by name or node id (default: main)
+        #[arg(long)]
+        entry: Option<String>,
+    },
+
+    /// Language server on stdin/stdout answering call hierarchy requests
+    /// (incoming and outgoing calls) from the analyzed graph
+    Lsp,
+
+    /// Compare two call graphs: added/removed nodes and edges
+    Diff {
+        /// Old graph: a `--format json` snapshot, a SCIP index (*.scip) or a
+        /// workspace (Cargo.toml or its directory)
+        old: String,
+
+        /// New graph, in any of the same forms
+        new: String,
+
+        /// "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+
+        /// Also write both graphs as DOT, additions green and removals red
+        #[arg(long, value_name = "PATH")]
+        dot: Option<String>,
+
+        /// Draw only the changes (and the endpoints of changed edges) in --dot
+        #[arg(long)]
+        changes_only: bool,
+    },
+
+    /// Download a published crate (via `cargo vendor`) and analyze it like
+    /// --workspace; the graph goes to --output, else <crate>-<version>.<format>
+    Fetch {
+        /// Crate name, optionally with a version requirement: serde_json@1.0
+        #[arg(value_name = "CRATE[@VERSION]")]
+        spec: String,
+
+        /// Where crates are unpacked (default: tracecraft/crates in the temp directory)
+        #[arg(long, value_name = "DIR")]
+        dir: Option<String>,
+    },
+
+    /// Write a commented tracecraft.toml to the project root (the current
+    /// directory, or that of --workspace)
+    Init {
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 fn main() {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    cli.format_given = matches.value_source("format") == Some(ValueSource::CommandLine);
+    init_logging(&cli);
+
     // Initialize adaptive thread pool (reserves 50% CPU for UI/LSP)
     if let Err(e) = concurrency::init_thread_pool() {
-        eprintln!("Warning: Failed to initialize thread pool: {}. Using defaults.", e);
+        tracing::warn!("Failed to initialize thread pool: {}. Using defaults.", e);
+    }
+
+    // ── Init Mode ─────────────────────────────
+    if let Some(Command::Init { force }) = &cli.command {
+        let root = project_root(&cli);
+        let path = root.join(CONFIG_FILE_NAME);
+        if path.exists() && !force {
+            eprintln!("Error: {} already exists (use --force to replace it)", path.display());
+            std::process::exit(1);
+        }
+        if let Err(source) = std::fs::write(&path, config::template(root.join("Cargo.toml").is_file())) {
+            fail(&Error::Export { path, source });
+        }
+        println!("Wrote {}", path.display());
+        return;
     }
 
-    let cli=Cli::parse();
+    // ── Fetch Mode ────────────────────────────
+    // Becomes a plain workspace run on the unpacked crate
+    if let Some(Command::Fetch { spec, dir }) = &cli.command {
+        let manifest = CrateSpec::parse(spec)
+            .and_then(|crate_spec| crate_fetch::fetch(&crate_spec, &dir.as_ref().map_or_else(crate_fetch::default_cache_dir, std::path::PathBuf::from)))
+            .unwrap_or_else(|source| fail(&Error::Fetch { spec: spec.clone(), source }));
+        if cli.output.is_empty() {
+            let name = manifest.parent().and_then(|dir| dir.file_name()).map_or_else(|| spec.clone(), |n| n.to_string_lossy().into_owned());
+            let ext = cli.format.first().and_then(|format| format_extension(format)).unwrap_or("dot");
+            cli.output = vec![format!("{}.{}", name, ext)];
+        }
+        tracing::info!("[Fetch] Analyzing {}", manifest.parent().unwrap_or(&manifest).display());
+        cli.workspace = Some(manifest.to_string_lossy().into_owned());
+        cli.command = None;
+    }
+
+    if let Some(spec) = &cli.git {
+        let source = git::GitSource::parse(spec);
+        let checkout = git::checkout(&source, &git::default_checkout_dir())
+            .unwrap_or_else(|source| fail(&Error::GitClone { url: spec.clone(), source }));
+        let Some(manifest) = ProjectLoader::find_manifest(&checkout) else {
+            fail(&Error::ManifestNotFound(checkout.join("Cargo.toml")));
+        };
+        tracing::info!("[Git] Analyzing {}", manifest.display());
+        cli.workspace = Some(manifest.to_string_lossy().into_owned());
+    }
+
+    let config_path = match &cli.config {
+        Some(path) => Some(std::path::PathBuf::from(path)),
+        None if cli.no_config => None,
+        None => ProjectConfig::find(&project_root(&cli)),
+    };
+    if let Some(path) = config_path {
+        match ProjectConfig::load(&path) {
+            Ok(config) => apply_config(&mut cli, &matches, config),
+            Err(source) => fail(&Error::Config { path, source }),
+        }
+        tracing::debug!("Using {}", path.display());
+    }
+
+    // stdin can only be read once; rebuilds reuse the snippet
+    if reads_snippet(&cli) {
+        cli.snippet = Some(snippet_sources(&cli));
+    }
 
     // ── Daemon Mode ───────────────────────────
     if cli.daemon {
@@ -102,124 +763,889 @@ fn main() {
         return;
     }
 
-    // ── Normal CLI Mode ───────────────────────
-    
-    // Validate required args for CLI mode
-    if cli.output.is_none() {
-        use clap::CommandFactory;
-        let mut cmd = Cli::command();
-        cmd.error(
-            clap::error::ErrorKind::MissingRequiredArgument,
-            "The following required arguments were not provided:\n  --output <OUTPUT>"
-        ).exit();
-    }
+    match &cli.command {
+        // ── HTTP API Mode ─────────────────────────
+        Some(Command::Serve { host, port, graph }) => {
+            let (callgraph, files) = match graph {
+                Some(path) => load_graph_file(path),
+                None if has_input(&cli) => {
+                    let analysis = load_graph_for(&cli);
+                    report_parse_errors(&analysis.parse_errors);
+                    (analysis.graph, analysis.files)
+                }
+                None => {
+                    eprintln!("Error: serve needs --graph, --load-graph, --workspace, --scip or --lsif");
+                    std::process::exit(1);
+                }
+            };
+            tracing::info!("Serving {} nodes", callgraph.nodes.len());
+            let service = mr_hedgehog::api::http::GraphService::new(callgraph, &files);
+            if let Err(e) = mr_hedgehog::api::http::serve(service, &format!("{}:{}", host, port)) {
+                eprintln!("HTTP server failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
 
-    if cli.debug {
-        println!("[DEBUG] Config: {:?}", cli);
-    }
-
-    // Branch based on engine selection
-    let (callgraph, files) = match cli.engine.as_str() {
-        "scip" => {
-            // SCIP Engine: Use language-specific indexer for precise semantic analysis
-            let language = Language::from_str(&cli.lang).unwrap_or(Language::Rust);
-            println!("[Engine] Using SCIP ({} semantic analysis)", language);
-            
-            let workspace_path = cli.workspace.as_ref()
-                .map(|ws| std::path::Path::new(ws).parent().unwrap_or(std::path::Path::new(".")))
-                .unwrap_or(std::path::Path::new("."));
-            
-            // Generate SCIP index for the specified language
-            let scip_path = match mr_hedgehog::infrastructure::scip_runner::generate_scip_index_for_language(
-                workspace_path, 
-                language,
-                &[]
-            ) {
-                Ok(path) => path,
-                Err(e) => {
-                    eprintln!("Error generating SCIP index: {}", e);
-                    if language == Language::Rust {
-                        eprintln!("Falling back to syn engine...");
-                        return run_syn_engine(&cli);
-                    } else {
-                        eprintln!("No fallback available for {} (syn only supports Rust)", language);
-                        std::process::exit(1);
+        // ── Trace Mode ────────────────────────────
+        Some(Command::Trace { entry, max_depth, max_paths, strategy, context, full_body, output, format }) => {
+            let format = TraceFormat::parse(format).unwrap_or_else(|| {
+                eprintln!("Unknown trace format: {} (expected \"text\", \"markdown\" or \"json\")", format);
+                std::process::exit(1);
+            });
+            let strategy = TraceStrategy::parse(strategy).unwrap_or_else(|| {
+                eprintln!("Unknown --strategy: {} (expected \"dfs\", \"bfs\" or \"shortest\")", strategy);
+                std::process::exit(1);
+            });
+            let Analysis { graph, files, parse_errors } = load_graph_for(&cli);
+
+            let sources = source_manager(&cli, &files);
+            let options = TraceOptions { max_depth: *max_depth, max_paths: *max_paths, strategy, context: *context, full_body: *full_body, signatures: cli.signatures };
+            let generator = TraceGenerator::with_options(&graph, &sources, options);
+            let traces: Vec<(String, Vec<TracePath>)> = resolve_entries(&graph, entry).unwrap_or_else(|e| fail(&e)).into_iter()
+                .map(|id| {
+                    let paths = generator.generate_paths(&id);
+                    (id, paths)
+                })
+                .collect();
+            match output {
+                Some(path) => {
+                    if let Err(source) = TraceExporter::export(&traces, format, source_links(&cli).as_ref(), path) {
+                        fail(&Error::Export { path: path.into(), source });
                     }
+                    println!("Traces saved to {}", path);
                 }
+                None => print!("{}", TraceExporter::render(&traces, format, source_links(&cli).as_ref())),
+            }
+            report_parse_errors(&parse_errors);
+        }
+
+        // ── Bundle Mode ───────────────────────────
+        Some(Command::Bundle { entry, max_depth, token_budget, format, output }) => {
+            let format = BundleFormat::parse(format).unwrap_or_else(|| {
+                eprintln!("Unknown bundle format: {} (expected \"markdown\" or \"json\")", format);
+                std::process::exit(1);
+            });
+            let Analysis { graph, files, parse_errors } = load_graph_for(&cli);
+
+            let Some(entry) = resolve_entries(&graph, entry.as_slice()).unwrap_or_else(|e| fail(&e)).into_iter().next() else {
+                std::process::exit(1);
             };
-            
-            // Ingest SCIP and build graph
-            match mr_hedgehog::domain::scip_ingest::ScipIngestor::ingest_and_build_graph(&scip_path) {
-                Ok(cg) => {
-                    // For SCIP engine, we still might want file contents for rich traces
-                    let loaded_files = if let Some(ws) = &cli.workspace {
-                        ProjectLoader::load_workspace(ws, cli.expand_macros).unwrap_or_default()
-                    } else {
-                        Vec::new()
-                    };
-                    (cg, loaded_files)
+            let mut bundle = ContextBundle::build(&graph, &source_manager(&cli, &files), &entry, *max_depth);
+            if let Some(limit) = token_budget {
+                bundle.fit_to_budget(&graph, *limit);
+                let truncated = bundle.functions.iter().filter(|f| f.truncated).count();
+                // stderr: stdout may be the bundle itself
+                eprintln!("Token budget: ~{} of {} tokens; {} function(s) truncated, {} omitted",
+                    bundle.budget.map_or(0, |b| b.used), limit, truncated, bundle.omitted.len());
+            }
+            match output {
+                Some(path) => {
+                    if let Err(source) = BundleExporter::export(&bundle, format, path) {
+                        fail(&Error::Export { path: path.into(), source });
+                    }
+                    println!("Bundle saved to {} ({} functions)", path, bundle.functions.len());
+                }
+                None => print!("{}", BundleExporter::render(&bundle, format)),
+            }
+            report_parse_errors(&parse_errors);
+        }
+
+        // ── Stats Mode ────────────────────────────
+        Some(Command::Stats { entry, top, format, output }) => {
+            check_format("stats", format, &["table", "json"]);
+            let Analysis { graph, parse_errors, .. } = load_graph_for(&cli);
+
+            let stats = GraphStats::compute(&graph, &resolve_entries(&graph, entry).unwrap_or_else(|e| fail(&e)), *top);
+            let path = output.as_deref().unwrap_or("-");
+            write_report(&cli, path, format == "json", &stats, || stats.to_string(), format_args!("Stats saved to {}", path))
+                .unwrap_or_else(|e| fail(&e));
+            report_parse_errors(&parse_errors);
+        }
+
+        // ── Find Mode ─────────────────────────────
+        Some(Command::Find { pattern, glob, regex, ignore_case, format }) => {
+            check_format("find", format, &["text", "json"]);
+            let Analysis { graph, parse_errors, .. } = load_graph_for(&cli);
+
+            let mode = match (glob, regex) {
+                (true, _) => MatchMode::Glob,
+                (_, true) => MatchMode::Regex,
+                _ => MatchMode::Substring,
+            };
+            let matches = find_symbols(&graph, pattern, mode, *ignore_case).unwrap_or_else(|e| {
+                eprintln!("Error: invalid --regex: {}", e);
+                std::process::exit(1);
+            });
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&matches).expect("matches are serializable"));
+            } else {
+                print!("{}", format_matches(&matches));
+            }
+            report_parse_errors(&parse_errors);
+            if matches.is_empty() {
+                eprintln!("No function matches '{}'", pattern);
+                let suggestions = suggest(&graph, pattern);
+                if !suggestions.is_empty() {
+                    eprintln!("  did you mean:");
+                    for s in suggestions {
+                        eprintln!("    {}", s);
+                    }
+                }
+                std::process::exit(1);
+            }
+        }
+
+        // ── Impact Mode ───────────────────────────
+        Some(Command::Impact { since, format, output }) => {
+            check_format("impact", format, &["text", "json"]);
+            let Analysis { graph, files, parse_errors } = load_graph_for(&cli);
+            let diff = git::diff_since(&project_root(&cli), since)
+                .unwrap_or_else(|source| fail(&Error::GitDiff { since: since.clone(), source }));
+
+            let report = impact::impact(&graph, &impact::changed_lines(&diff), &source_manager(&cli, &files), since);
+            let path = output.as_deref().unwrap_or("-");
+            write_report(&cli, path, format == "json", &report, || report.to_string(),
+                format_args!("Impact report saved to {}", path)).unwrap_or_else(|e| fail(&e));
+            report_parse_errors(&parse_errors);
+        }
+
+        // ── Query Mode ────────────────────────────
+        Some(Command::Query { expr, format, export }) => {
+            check_format("query", format, &["text", "json"]);
+            let export_format = export.as_deref().map(|path| if path == "-" { "dot" } else { format_for_path(path).unwrap_or_else(|| {
+                eprintln!("Unknown format for --export {} (expected .dot, .json, .html or .db)", path);
+                std::process::exit(1);
+            }) });
+            let Analysis { graph, parse_errors, .. } = load_graph_for(&cli);
+
+            let ids = query::query(&graph, expr).unwrap_or_else(|e| {
+                eprintln!("Error: query: {}", e);
+                std::process::exit(1);
+            });
+            if let (Some(path), Some(export_format)) = (export, export_format) {
+                let subgraph = subgraph(&graph, &ids.iter().map(String::as_str).collect());
+                let exporter = graph_exporter(&cli, export_format);
+                let written = if path == "-" {
+                    exporter.export_to_writer(&subgraph, &mut std::io::stdout().lock())
+                } else {
+                    exporter.export(&subgraph, path)
+                };
+                if let Err(source) = written {
+                    fail(&Error::Export { path: path.into(), source });
+                }
+                if path != "-" {
+                    saved(&cli, format_args!("Saved {} functions to {}", subgraph.nodes.len(), path));
+                }
+            } else {
+                let nodes: HashMap<&str, &CallGraphNode> = graph.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+                let matches: Vec<SymbolMatch> = ids.iter().map(|id| SymbolMatch::of(nodes[id.as_str()])).collect();
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&matches).expect("matches are serializable"));
+                } else {
+                    print!("{}", format_matches(&matches));
+                }
+            }
+            report_parse_errors(&parse_errors);
+            if ids.is_empty() {
+                eprintln!("No function matches the query");
+                std::process::exit(1);
+            }
+        }
+
+        // ── REPL Mode ─────────────────────────────
+        Some(Command::Repl) => {
+            let Analysis { graph, files, parse_errors } = load_graph_for(&cli);
+            report_parse_errors(&parse_errors);
+
+            let session = ReplSession::new(graph, &files)
+                .with_exporter(|path| format_for_path(path).map(|format| graph_exporter(&cli, format)));
+            if let Err(e) = repl::run(&session) {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+
+        // ── Browse Mode ───────────────────────────
+        Some(Command::Browse { entry }) => {
+            if !std::io::stdout().is_terminal() {
+                eprintln!("Error: browse needs a terminal");
+                std::process::exit(1);
+            }
+            let Analysis { graph, files, parse_errors } = load_graph_for(&cli);
+            report_parse_errors(&parse_errors);
+
+            let Some(entry) = resolve_entries(&graph, entry.as_slice()).unwrap_or_else(|e| fail(&e)).into_iter().next() else {
+                std::process::exit(1);
+            };
+            match tui::run(&graph, &source_manager(&cli, &files), &entry) {
+                Ok(bookmarks) => {
+                    for id in bookmarks {
+                        println!("{}", id);
+                    }
                 }
                 Err(e) => {
-                    eprintln!("Error ingesting SCIP index: {}", e);
-                    eprintln!("Falling back to syn engine...");
-                    return run_syn_engine(&cli);
+                    eprintln!("Error: {:#}", e);
+                    std::process::exit(1);
                 }
             }
         }
-        _ => {
-            // Syn Engine: Traditional AST-based analysis
-            println!("[Engine] Using syn (AST-based analysis)");
-            run_syn_engine_internal(&cli)
+
+        // ── Language Server Mode ──────────────────
+        Some(Command::Lsp) => {
+            let Analysis { graph, files, parse_errors } = load_graph_for(&cli);
+            report_parse_errors(&parse_errors);
+
+            // stdout carries the protocol; everything else goes to stderr
+            match LspServer::new(graph, &files).run(std::io::stdin().lock(), std::io::stdout().lock()) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("Error: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        // ── Diff Mode ─────────────────────────────
+        Some(Command::Diff { old, new, format, output, dot, changes_only }) => {
+            check_format("diff", format, &["text", "json"]);
+            let (old, new) = (load_snapshot(&cli, old), load_snapshot(&cli, new));
+            let diff = GraphDiff::between(&old, &new);
+            let path = output.as_deref().unwrap_or("-");
+            write_report(&cli, path, format == "json", &diff, || diff.to_string(), format_args!("Diff saved to {}", path))
+                .unwrap_or_else(|e| fail(&e));
+            if let Some(path) = dot {
+                if let Err(source) = DiffExporter::export_dot(&old, &new, &diff, *changes_only, path) {
+                    fail(&Error::Export { path: path.into(), source });
+                }
+                println!("Diff graph saved to {}", path);
+            }
         }
+
+        // ── Normal CLI Mode ───────────────────────
+        None => export_graph(&cli),
+        Some(Command::Init { .. } | Command::Fetch { .. }) => unreachable!("handled before the config is read"),
+    }
+}
+
+/// Normal CLI mode: analyze, write the reports asked for and export the
+/// graph to every `--output`; with `--watch`, keep doing so on changes.
+fn export_graph(cli: &Cli) {
+    // Validate required args for CLI mode
+    if cli.output.is_empty() {
+        let mut cmd = Cli::command();
+        cmd.error(
+            clap::error::ErrorKind::MissingRequiredArgument,
+            "The following required arguments were not provided:\n  --output <OUTPUT>"
+        ).exit();
+    }
+
+    tracing::debug!("Config: {:?}", cli);
+
+    if graph_to_stdout(cli) {
+        let reports = [
+            ("--unreachable", cli.unreachable.as_deref()),
+            ("--dominators", cli.dominators.as_deref()),
+            ("--test-map", cli.test_map.as_deref()),
+            ("--uncovered", cli.uncovered.as_deref()),
+            ("--unsafe-reachability", cli.unsafe_reachability.as_deref()),
+            ("--metrics", cli.metrics.as_deref()),
+            ("--trace-output", cli.trace_output.as_deref()),
+            ("--taint-output", cli.taint.as_ref().map(|_| cli.taint_output.as_deref().unwrap_or("-"))),
+            ("--path-from", cli.path_from.as_ref().map(|_| "-")),
+            ("--panic-paths", cli.panic_paths.as_ref().map(|_| "-")),
+        ];
+        if let Some((flag, _)) = reports.iter().find(|(_, path)| *path == Some("-")) {
+            eprintln!("Error: --output - and {} both write to stdout; send one of them to a file", flag);
+            std::process::exit(1);
+        }
+    }
+
+    if cli.view != "functions" {
+        if ModuleLevel::parse(&cli.view).is_none() {
+            eprintln!("Unknown --view: {} (expected \"functions\", \"modules\" or \"crates\")", cli.view);
+            std::process::exit(1);
+        }
+        if output_targets(cli).is_ok_and(|targets| targets.iter().any(|(_, format)| format != "dot")) {
+            eprintln!("Error: --view {} is only exported as DOT (--format dot)", cli.view);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(name) = cli.render.as_deref() {
+        if RenderFormat::parse(name).is_none() {
+            eprintln!("Unknown --render format: {} (expected \"svg\" or \"png\")", name);
+            std::process::exit(1);
+        }
+        let dot = output_targets(cli).is_ok_and(|targets| targets.iter().any(|(_, format)| format == "dot"));
+        if !dot && cli.mode != "flowchart" {
+            eprintln!("Error: --render needs DOT output (--format dot)");
+            std::process::exit(1);
+        }
+        if cli.output.iter().any(|o| o == "-") {
+            eprintln!("Error: --render needs an --output file, not stdout");
+            std::process::exit(1);
+        }
+    }
+
+    if cli.watch {
+        if cli.workspace.is_none() {
+            eprintln!("Error: --watch requires --workspace");
+            std::process::exit(1);
+        }
+        if !cli.scip.is_empty() || cli.index_with_ra || cli.engine == "scip" {
+            eprintln!("Error: --watch only supports the syn engine");
+            std::process::exit(1);
+        }
+    }
+    let analysis_cache = open_analysis_cache(cli);
+
+    let Analysis { graph: callgraph, files, parse_errors } = build_graph(cli, analysis_cache.as_ref());
+    check_strict(cli, &parse_errors);
+    run_post_processing(cli, &callgraph, &files).unwrap_or_else(|e| fail(&e));
+    // Diagnostics go last so they are not buried under the trace output
+    report_parse_errors(&parse_errors);
+
+    if cli.watch {
+        watch_and_rebuild(cli, callgraph, analysis_cache.as_ref());
+    }
+}
+
+/// Log to stderr at the level -q/-v ask for, unless RUST_LOG says otherwise.
+fn init_logging(cli: &Cli) {
+    use tracing_subscriber::EnvFilter;
+
+    let level = match (cli.quiet, cli.verbose.max(cli.debug as u8)) {
+        (true, _) => "warn",
+        (false, 0) => "info",
+        (false, 1) => "debug",
+        (false, _) => "trace",
     };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_target(false)
+        .without_time()
+        .init();
+}
 
-    run_post_processing(&cli, &callgraph, &files);
+/// Print an error with its causes and a hint, then exit with its code.
+fn fail(err: &Error) -> ! {
+    eprintln!("Error: {}", err);
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        eprintln!("  caused by: {}", cause);
+        source = cause.source();
+    }
+    if let Some(hint) = err.suggestion() {
+        eprintln!("  hint: {}", hint);
+    }
+    std::process::exit(err.exit_code());
 }
 
-/// Run the syn-based analysis engine (internal, returns CallGraph and Files)
-fn run_syn_engine_internal(cli: &Cli) -> (mr_hedgehog::domain::callgraph::CallGraph, Vec<(String, String, String)>) {
-    let mut files = Vec::<(String,String,String)>::new();
+/// List the files left out of the graph, one `file:line: reason` line each.
+fn report_parse_errors(errors: &[AnalysisError]) {
+    if errors.is_empty() {
+        return;
+    }
+    tracing::warn!("{} file(s) failed to parse and are missing from the graph:", errors.len());
+    for e in errors {
+        tracing::warn!("  {}", e);
+    }
+    tracing::warn!("  hint: pass --strict to fail the run instead");
+}
 
-    // workspace (primary method)
-    if let Some(ws) = &cli.workspace {
-        match ProjectLoader::load_workspace(ws, cli.expand_macros) {
-            Ok(loaded_files) => {
-                println!("Loaded {} files from workspace", loaded_files.len());
-                files.extend(loaded_files);
-            },
-            Err(e) => panic!("Failed to load workspace: {:?}", e),
+/// `--strict`: abort before anything is written if a file failed to parse.
+fn check_strict(cli: &Cli, errors: &[AnalysisError]) {
+    if cli.strict && !errors.is_empty() {
+        eprintln!("Parse errors:");
+        for e in errors {
+            eprintln!("  {}", e);
         }
+        fail(&Error::Parse(errors.to_vec()));
+    }
+}
+
+/// Where `tracecraft.toml` lives: the directory of `--workspace`, else the
+/// current one.
+fn project_root(cli: &Cli) -> std::path::PathBuf {
+    let Some(workspace) = cli.workspace.as_deref().map(std::path::Path::new) else {
+        return std::path::PathBuf::from(".");
+    };
+    if workspace.is_dir() {
+        workspace.to_path_buf()
     } else {
-        if !cli.input.is_empty() || !cli.folder.is_empty() {
-             panic!("Legacy input/folder mode is momentarily disabled during refactor. Please use --workspace.");
+        workspace.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(std::path::Path::new(".")).to_path_buf()
+    }
+}
+
+/// Fill in from the config file whatever the command line left unset.
+/// Inputs are taken as a group: naming any input on the command line
+/// replaces all of the file's.
+fn apply_config(cli: &mut Cli, matches: &ArgMatches, config: ProjectConfig) {
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let path = |p: std::path::PathBuf| p.to_string_lossy().into_owned();
+    fn set<T>(field: &mut T, value: Option<T>, given: bool) {
+        if let (Some(value), false) = (value, given) {
+            *field = value;
         }
     }
 
-    if files.is_empty() { panic!("No input provided"); }
+    let ProjectConfig { entry, input, filter, output, dot } = config;
+    let named_input = ["input", "eval", "folder", "workspace", "scip", "lsif", "load_graph"].iter().any(|id| given(id));
+    if !named_input {
+        cli.workspace = input.workspace.map(path);
+        cli.scip = input.scip.into_iter().map(path).collect();
+        cli.lsif = input.lsif.map(path);
+        cli.input = input.files.into_iter().map(path).collect();
+        cli.folder = input.folders.into_iter().map(path).collect();
+    }
+    set(&mut cli.features, input.features.map(Some), given("features"));
+    set(&mut cli.no_default_features, input.no_default_features, given("no_default_features"));
+    set(&mut cli.target, input.target.map(Some), given("target"));
+    set(&mut cli.ignore, Some(input.ignore).filter(|v| !v.is_empty()), given("ignore"));
+    set(&mut cli.gitignore, input.gitignore, given("gitignore"));
+    set(&mut cli.tests, input.tests, given("tests"));
+    set(&mut cli.benches, input.benches, given("benches"));
+    set(&mut cli.examples, input.examples, given("examples"));
+    set(&mut cli.no_cfg_test, input.cfg_test.map(|keep| !keep), given("no_cfg_test"));
+    set(&mut cli.types, input.types, given("types"));
+    set(&mut cli.scip_calls_only, input.scip_calls_only, given("scip_calls_only"));
+    set(&mut cli.include_external, input.include_external, given("include_external"));
+    set(&mut cli.entry, Some(entry).filter(|e| !e.is_empty()), given("entry"));
+
+    set(&mut cli.include, Some(filter.include).filter(|v| !v.is_empty()), given("include"));
+    set(&mut cli.exclude, Some(filter.exclude).filter(|v| !v.is_empty()), given("exclude"));
+    set(&mut cli.exclude_tests, filter.exclude_tests, given("exclude_tests"));
+    set(&mut cli.exclude_origin, Some(filter.exclude_origins).filter(|v| !v.is_empty()), given("exclude_origin"));
+    set(&mut cli.collapse_excluded, filter.collapse_excluded, given("collapse_excluded"));
+    set(&mut cli.condense, filter.condense, given("condense"));
+    set(&mut cli.collapse_external, Some(filter.collapse_external).filter(|v| !v.is_empty()), given("collapse_external"));
+
+    set(&mut cli.output, output.path.map(|p| vec![path(p)]), given("output"));
+    cli.format_given |= output.format.is_some();
+    set(&mut cli.format, output.format.map(|f| f.split(',').map(|f| f.trim().to_string()).collect()), given("format"));
+    set(&mut cli.mode, output.mode, given("mode"));
+    set(&mut cli.view, output.view, given("view"));
+    set(&mut cli.render, output.render.map(Some), given("render"));
+    set(&mut cli.link_base, output.link_base.map(Some), given("link_base"));
+    set(&mut cli.signatures, output.signatures, given("signatures"));
+
+    set(&mut cli.dot_rankdir, dot.rankdir.map(Some), given("dot_rankdir"));
+    set(&mut cli.dot_node_shape, dot.node_shape.map(Some), given("dot_node_shape"));
+    set(&mut cli.dot_font, dot.font.map(Some), given("dot_font"));
+    set(&mut cli.cluster, dot.cluster, given("cluster"));
+    set(&mut cli.cluster_colors, Some(dot.cluster_colors).filter(|v| !v.is_empty()), given("cluster_colors"));
+    set(&mut cli.edge_labels, dot.edge_labels, given("edge_labels"));
+    let edge_colors: Vec<String> = dot.edge_colors.into_iter().map(|(kind, color)| format!("{}={}", kind, color)).collect();
+    set(&mut cli.dot_edge_color, Some(edge_colors).filter(|v| !v.is_empty()), given("dot_edge_color"));
+    set(&mut cli.dot_max_label, dot.max_label.map(Some), given("dot_max_label"));
+    set(&mut cli.dot_source_url, dot.source_url.map(Some), given("dot_source_url"));
+}
+
+/// `--output -`: the graph itself goes to stdout.
+fn graph_to_stdout(cli: &Cli) -> bool {
+    cli.output.iter().any(|o| o == "-")
+}
+
+/// Print a "saved to" note: on stdout, unless the graph goes there, so that
+/// `--output - | dot -Tsvg` or `| jq` only ever see the graph.
+fn saved(cli: &Cli, note: std::fmt::Arguments) {
+    if graph_to_stdout(cli) {
+        eprintln!("{}", note);
+    } else {
+        println!("{}", note);
+    }
+}
+
+/// Write a report to `path` ("-" for stdout): `report` as JSON if `json`,
+/// else `text()`. `note` is printed once a file is written.
+fn write_report<R: serde::Serialize>(cli: &Cli, path: &str, json: bool, report: &R, text: impl FnOnce() -> String, note: std::fmt::Arguments) -> Result<(), Error> {
+    let text = if json {
+        serde_json::to_string_pretty(report).expect("reports are serializable") + "\n"
+    } else {
+        text()
+    };
+    if path == "-" {
+        print!("{}", text);
+    } else {
+        std::fs::write(path, text).map_err(|source| Error::Export { path: path.into(), source })?;
+        saved(cli, note);
+    }
+    Ok(())
+}
+/// Whether there is anything to build a graph from.
+fn has_input(cli: &Cli) -> bool {
+    cli.workspace.is_some() || !cli.scip.is_empty() || cli.lsif.is_some() || cli.load_graph.is_some() || reads_snippet(cli)
+}
+
+/// `--eval` or `--input -`.
+fn reads_snippet(cli: &Cli) -> bool {
+    cli.eval.is_some() || cli.input.iter().any(|i| i == "-")
+}
+
+/// Crate of `--eval` / `--input -` code: its functions are `snippet::main`, ...
+const SNIPPET_CRATE: &str = "snippet";
+
+/// The code of `--eval` or `--input -` as a single-file crate.
+fn snippet_sources(cli: &Cli) -> SourceSet {
+    let (path, code) = match &cli.eval {
+        Some(code) => ("<eval>", code.clone()),
+        None => {
+            let mut code = String::new();
+            if let Err(e) = std::io::stdin().read_to_string(&mut code) {
+                fail(&Error::Stdin(e));
+            }
+            ("<stdin>", code)
+        }
+    };
+    SourceSet::new(vec![SourceFile::new(SNIPPET_CRATE, path, code)])
+}
+
+/// Graph for a subcommand: exits without an input, or on parse errors
+/// under `--strict`. Reporting skipped files is left to the caller.
+fn load_graph_for(cli: &Cli) -> Analysis {
+    if !has_input(cli) {
+        fail(&Error::NoInput);
+    }
+    let analysis = build_graph(cli, open_analysis_cache(cli).as_ref());
+    check_strict(cli, &analysis.parse_errors);
+    analysis
+}
+
+/// Exit unless `format` is one of the `known` formats of `subcommand`.
+fn check_format(subcommand: &str, format: &str, known: &[&str]) {
+    if !known.contains(&format) {
+        let quoted: Vec<String> = known.iter().map(|f| format!("\"{}\"", f)).collect();
+        eprintln!("Unknown {} format: {} (expected {})", subcommand, format, quoted.join(" or "));
+        std::process::exit(1);
+    }
+}
+
+/// Build the call graph with the engine selected on the command line, or
+/// load it from `--load-graph`; saved to `--save-graph` either way.
+fn build_graph(cli: &Cli, analysis_cache: Option<&Arc<AnalysisCache>>) -> Analysis {
+    let analysis = match &cli.load_graph {
+        Some(path) => {
+            let (graph, files) = load_graph_file(path);
+            tracing::info!("Loaded graph snapshot {} ({} nodes, {} files)", path, graph.nodes.len(), files.len());
+            Analysis { graph, files, parse_errors: Vec::new() }
+        }
+        None => analyze_workspace(cli, analysis_cache),
+    };
+    let analysis = match &cli.coverage {
+        Some(path) => apply_coverage(analysis, path),
+        None => analysis,
+    };
+    if let Some(path) = &cli.save_graph {
+        // Cloned: snapshots are written rarely, and the caller keeps using the analysis
+        let snapshot = GraphSnapshot::new(analysis.graph.clone(), analysis.files.clone());
+        if let Err(source) = snapshot.save(std::path::Path::new(path)) {
+            fail(&Error::Snapshot { path: path.into(), source });
+        }
+        saved(cli, format_args!("Graph snapshot saved to {}", path));
+    }
+    analysis
+}
+
+/// `--coverage`: set each node's hit count from the report.
+fn apply_coverage(mut analysis: Analysis, path: &str) -> Analysis {
+    let data = std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| CoverageData::parse(&text));
+    match data {
+        Ok(data) => {
+            let matched = coverage::annotate(&mut analysis.graph, &data);
+            tracing::info!("[Coverage] {} of {} nodes matched records in {}", matched, analysis.graph.nodes.len(), path);
+            analysis
+        }
+        Err(source) => fail(&Error::Coverage { path: path.into(), source }),
+    }
+}
+
+fn analyze_workspace(cli: &Cli, analysis_cache: Option<&Arc<AnalysisCache>>) -> Analysis {
+    if reads_snippet(cli) && cli.input.len() > 1 {
+        eprintln!("--input - reads a single snippet; it cannot be combined with files");
+        fail(&Error::NoInput);
+    }
+    if cli.workspace.is_none() && cli.scip.is_empty() && cli.lsif.is_none() && !reads_snippet(cli) && (!cli.input.is_empty() || !cli.folder.is_empty()) {
+        eprintln!("--input/--folder are disabled; analysis now runs on whole workspaces");
+        fail(&Error::NoInput);
+    }
+    let config = analysis_config(cli, analysis_cache);
+    let indices = config.scip_indices.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ");
+    if let Some(dump) = &config.lsif_index {
+        tracing::info!("[Engine] Using LSIF dump {}", dump.display());
+    } else {
+        match (indices.is_empty(), &config.engine) {
+            (false, _) if config.workspace.is_some() => {
+                // Sources and an index: precise SCIP edges where covered, syn edges elsewhere
+                tracing::info!("[Engine] Using hybrid (syn + SCIP index {})", indices)
+            }
+            // Pre-built index only: no indexer run and no fallback, the user asked for this file
+            (false, _) => tracing::info!("[Engine] Using SCIP index {}", indices),
+            (true, Engine::RustAnalyzer(_)) => tracing::info!("[Engine] Using hybrid (syn + rust-analyzer index)"),
+            (true, Engine::Scip(language)) => tracing::info!("[Engine] Using SCIP ({} semantic analysis)", language),
+            (true, Engine::Syn) => tracing::info!("[Engine] Using syn (AST-based analysis)"),
+            #[cfg(feature = "mir")]
+            (true, Engine::Mir(_)) => tracing::info!("[Engine] Using MIR (rustc driver)"),
+        }
+    }
+
+    match run_analysis(&config) {
+        Ok(analysis) => analysis,
+        Err(e @ Error::Index(_)) => match config.engine {
+            Engine::Scip(Language::Rust) if config.scip_indices.is_empty() => {
+                tracing::warn!("Error generating SCIP index: {:#}", anyhow::Error::from(e));
+                tracing::warn!("Falling back to syn engine...");
+                let fallback = AnalysisConfig { engine: Engine::Syn, ..config };
+                run_analysis(&fallback).unwrap_or_else(|e| fail(&e))
+            }
+            Engine::Scip(language) => {
+                eprintln!("No fallback available for {} (syn only supports Rust)", language);
+                fail(&e)
+            }
+            _ => fail(&e),
+        },
+        Err(e) => fail(&e),
+    }
+}
+
+/// Engine the command line asks for.
+fn engine(cli: &Cli) -> Engine {
+    #[cfg(feature = "mir")]
+    if cli.mir {
+        return Engine::Mir(cli.mir_driver.as_ref().map(std::path::PathBuf::from));
+    }
+    if cli.index_with_ra {
+        Engine::RustAnalyzer(cli.ra_command.clone())
+    } else if cli.engine == "scip" {
+        Engine::Scip(Language::from_str(&cli.lang).unwrap_or(Language::Rust))
+    } else {
+        Engine::Syn
+    }
+}
+
+/// Translate the command line into a library [`AnalysisConfig`].
+fn analysis_config(cli: &Cli, cache: Option<&Arc<AnalysisCache>>) -> AnalysisConfig {
+    AnalysisConfig {
+        workspace: cli.workspace.as_ref().map(std::path::PathBuf::from),
+        engine: engine(cli),
+        scip_indices: cli.scip.iter().map(std::path::PathBuf::from).collect(),
+        lsif_index: cli.lsif.as_ref().map(std::path::PathBuf::from),
+        expand_macros: cli.expand_macros,
+        follow_path_deps: cli.follow_path_deps,
+        filter: source_filter(cli),
+        store: Some(open_store(cli)),
+        cache: cache.cloned(),
+        cfg: cfg_options(cli),
+        types: cli.types,
+        scip_calls_only: cli.scip_calls_only,
+        include_external: cli.include_external,
+        progress: progress(cli),
+        sources: cli.snippet.clone(),
+    }
+}
+
+/// Progress bars, unless --no-progress or -q turned them off.
+fn progress(cli: &Cli) -> Option<Arc<dyn Progress>> {
+    (!cli.no_progress && !cli.quiet).then(|| Arc::new(TerminalProgress::new()) as Arc<dyn Progress>)
+}
+
+/// `--ignore` / `--gitignore` / `--tests` / `--benches` / `--examples`.
+fn source_filter(cli: &Cli) -> SourceFilter {
+    SourceFilter {
+        tests: cli.tests,
+        benches: cli.benches,
+        examples: cli.examples,
+        ignore: cli.ignore.clone(),
+        gitignore: cli.gitignore,
+    }
+}
+
+/// `--exclude-origin test|bench|example`.
+fn parse_origin(kind: &str) -> Result<SourceOrigin, String> {
+    SourceOrigin::from_target_kind(kind).ok_or_else(|| format!("expected test, bench or example, got {:?}", kind))
+}
+
+/// `--features` / `--no-default-features` / `--target` / `--no-cfg-test`.
+fn cfg_options(cli: &Cli) -> CfgOptions {
+    CfgOptions {
+        features: cli.features.as_ref().map(|f| f.iter().filter(|f| !f.is_empty()).cloned().collect()),
+        no_default_features: cli.no_default_features,
+        target: cli.target.as_deref().map(TargetCfg::from_triple),
+        test: cli.no_cfg_test.then_some(false),
+    }
+}
+
+/// Run the analysis, reporting loaded files and cache use.
+fn run_analysis(config: &AnalysisConfig) -> error::Result<Analysis> {
+    let before = config.cache.as_ref().map(|c| c.stats());
+    let analysis = analyze_with_sources(config)?;
+    if !analysis.files.is_empty() {
+        tracing::info!("Loaded {} files from workspace", analysis.files.len());
+    }
+    if let (Some(cache), Some((hits_before, misses_before))) = (&config.cache, before) {
+        let (hits, misses) = cache.stats();
+        let location = cache.dir().map(|d| d.display().to_string()).unwrap_or_else(|| "in memory".to_string());
+        tracing::info!("[Incremental] {} cached, {} analyzed ({})", hits - hits_before, misses - misses_before, location);
+    }
+    Ok(analysis)
+}
 
-    // Initialize storage backend
+/// Read a snapshot written by `--save-graph` or a graph written by
+/// `--format json` (which has no sources).
+fn load_graph_file(path: &str) -> (CallGraph, SourceSet) {
+    match GraphSnapshot::load(std::path::Path::new(path)) {
+        Ok(snapshot) => (snapshot.graph, snapshot.files),
+        Err(source) => fail(&Error::GraphLoad { path: path.into(), source }),
+    }
+}
+
+/// One side of `diff`: a snapshot or JSON export, a SCIP index or a
+/// workspace built with the syn engine. Each gets its own in-memory symbol
+/// store so the two revisions do not see each other's symbols.
+fn load_snapshot(cli: &Cli, path: &str) -> CallGraph {
+    if path.ends_with(".json") || path.ends_with(".tcg") {
+        return load_graph_file(path).0;
+    }
+    let (workspace, scip_indices) = if path.ends_with(".scip") {
+        (None, vec![std::path::PathBuf::from(path)])
+    } else {
+        (Some(std::path::PathBuf::from(path)), Vec::new())
+    };
+    let config = AnalysisConfig {
+        workspace,
+        scip_indices,
+        expand_macros: cli.expand_macros,
+        follow_path_deps: cli.follow_path_deps,
+        filter: source_filter(cli),
+        cfg: cfg_options(cli),
+        types: cli.types,
+        scip_calls_only: cli.scip_calls_only,
+        include_external: cli.include_external,
+        progress: progress(cli),
+        ..Default::default()
+    };
+    let analysis = run_analysis(&config).unwrap_or_else(|e| fail(&e));
+    check_strict(cli, &analysis.parse_errors);
+    report_parse_errors(&analysis.parse_errors);
+    analysis.graph
+}
+
+/// Per-file summary cache: on disk next to the workspace's build output for
+/// `--incremental`, in memory for `--watch` alone.
+fn open_analysis_cache(cli: &Cli) -> Option<Arc<AnalysisCache>> {
+    if cli.incremental {
+        let root = cli.workspace.as_deref().map(workspace_root).unwrap_or_else(|| std::path::PathBuf::from("."));
+        let dir = root.join(mr_hedgehog::infrastructure::scip_runner::RA_CACHE_DIR).join("analysis");
+        Some(Arc::new(AnalysisCache::new(dir)))
+    } else if cli.watch {
+        Some(Arc::new(AnalysisCache::in_memory()))
+    } else {
+        None
+    }
+}
+
+/// `--watch`: rebuild and re-export whenever workspace sources change,
+/// re-parsing only the files whose contents differ.
+fn watch_and_rebuild(cli: &Cli, mut callgraph: CallGraph, cache: Option<&Arc<AnalysisCache>>) {
+    let config = analysis_config(cli, cache);
+    let root = config.workspace_root().expect("--watch requires --workspace");
+    let watcher = match SourceWatcher::new(&root) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    tracing::info!("[Watch] Watching {} for changes (Ctrl-C to stop)", root.display());
+
+    while let Some(changed) = watcher.next_batch() {
+        tracing::info!("[Watch] {} file(s) changed", changed.len());
+        // Reload through cargo metadata so added or removed files and crates are picked up
+        let Analysis { graph: rebuilt, files, parse_errors } = match run_analysis(&config) {
+            Ok(analysis) => analysis,
+            Err(e) => {
+                tracing::warn!("rebuild failed: {:#}", e);
+                continue;
+            }
+        };
+        if cli.strict && !parse_errors.is_empty() {
+            report_parse_errors(&parse_errors);
+            tracing::warn!("--strict: keeping the previous output until the errors are fixed");
+            continue;
+        }
+        let diff = GraphDiff::between(&callgraph, &rebuilt);
+        if diff.is_empty() {
+            tracing::info!("[Watch] No graph changes");
+        } else if graph_to_stdout(cli) || cli.trace_output.as_deref() == Some("-") {
+            // Keep the piped graph or trace intact
+            eprint!("{}", diff);
+        } else {
+            print!("{}", diff);
+        }
+        // A renamed --entry or an unwritable output should not end the session
+        if let Err(e) = run_post_processing(cli, &rebuilt, &files) {
+            tracing::warn!("{}", e);
+            if let Some(cause) = std::error::Error::source(&e) {
+                tracing::warn!("  caused by: {}", cause);
+            }
+        }
+        report_parse_errors(&parse_errors);
+        callgraph = rebuilt;
+    }
+}
+
+/// Directory containing the workspace manifest (`--workspace` may name either).
+fn workspace_root(ws: &str) -> std::path::PathBuf {
+    AnalysisConfig::new(ws).workspace_root().unwrap_or_else(|| std::path::PathBuf::from("."))
+}
+
+/// Initialize the storage backend selected by `--store`.
+fn open_store(cli: &Cli) -> std::sync::Arc<dyn mr_hedgehog::domain::store::SymbolStore> {
     let store: std::sync::Arc<dyn mr_hedgehog::domain::store::SymbolStore> = match cli.store.as_str() {
         "disk" => {
             let db_path = "mr_hedgehog_db";
-            std::sync::Arc::new(mr_hedgehog::domain::store::DiskSymbolStore::new(db_path).expect("Failed to open disk store"))
+            match mr_hedgehog::domain::store::DiskSymbolStore::new(db_path) {
+                Ok(store) => std::sync::Arc::new(store),
+                Err(source) => fail(&Error::Store { path: db_path.into(), source }),
+            }
         }
         _ => std::sync::Arc::new(mr_hedgehog::domain::store::MemorySymbolStore::default()),
     };
 
-    println!("Using storage backend: {}", cli.store);
-
-    let cg_builder = SimpleCallGraphBuilder::new_with_store(store);
-    (cg_builder.build_call_graph(&files), files)
+    tracing::debug!("Using storage backend: {}", cli.store);
+    store
 }
 
-/// Run syn engine (wrapper for fallback)
-fn run_syn_engine(cli: &Cli) {
-    let (callgraph, files) = run_syn_engine_internal(cli);
-    run_post_processing(cli, &callgraph, &files);
+/// Node ids of the `--entry` queries; `main` when none are given.
+fn resolve_entries(callgraph: &CallGraph, queries: &[String]) -> Result<Vec<String>, Error> {
+    if queries.is_empty() {
+        // The package's own binary before the extra ones (`app[bin:tool2]::main`),
+        // a crate root's `main` before one in a module
+        let is_main = |n: &&CallGraphNode| n.id.starts_with("main@") || n.id.rsplit("::").next() == Some("main");
+        Ok(callgraph.nodes.iter().filter(is_main)
+            .min_by_key(|n| (n.id.contains('['), n.id.matches("::").count()))
+            .map(|n| vec![n.id.to_string()])
+            .unwrap_or_else(|| {
+                tracing::warn!("no main() found in call graph (use --entry to pick one)");
+                Vec::new()
+            }))
+    } else {
+        queries.iter()
+            .map(|query| resolve_symbol(callgraph, query).map_err(|lookup| Error::Lookup { flag: "--entry", lookup }))
+            .collect()
+    }
 }
 
-/// Common post-processing: reverse queries, trace expansion, DOT export
-fn run_post_processing(cli: &Cli, callgraph: &mr_hedgehog::domain::callgraph::CallGraph, files: &[(String, String, String)]) {
+/// Common post-processing: reverse queries, trace expansion, DOT export.
+///
+/// Errors that depend on the graph or the filesystem are returned so
+/// `--watch` can report them and keep going.
+fn run_post_processing(cli: &Cli, callgraph: &mr_hedgehog::domain::callgraph::CallGraph, files: &SourceSet) -> Result<(), Error> {
 
     // for quick lookup
     let mut map=HashMap::new(); 
@@ -227,13 +1653,103 @@ fn run_post_processing(cli: &Cli, callgraph: &mr_hedgehog::domain::callgraph::Ca
         map.insert(n.id.clone(), n);
     }
     
-    let entry=callgraph.nodes.iter()
-        .find(|n| n.id.starts_with("main@") || n.id.contains("::main"))
-        .map(|n| n.id.clone())
-        .unwrap_or_else(|| {
-            eprintln!("WARN: no main() found in call graph");
-            "".into()
-        });
+    let entries = resolve_entries(callgraph, &cli.entry)?;
+
+    // ── unreachable function report ──────────
+    if let Some(ref report_path) = cli.unreachable {
+        let report = unreachable_functions(callgraph, &entries);
+        write_report(cli, report_path, report_path.ends_with(".json"), &report, || report.to_string(),
+            format_args!("Unreachable report saved to {} ({} functions)", report_path, report.unreachable.len()))?;
+    }
+
+    // ── tests reaching each function ──────────
+    if let Some(ref report_path) = cli.test_map {
+        let report = test_map(callgraph);
+        write_report(cli, report_path, report_path.ends_with(".json"), &report, || report.to_string(),
+            format_args!("Test map saved to {} ({} tests, {} functions untested)", report_path, report.tests.len(), report.untested.len()))?;
+    }
+
+    // ── dominator tree from the entries ───────
+    if let Some(ref report_path) = cli.dominators {
+        let report = dominators::dominator_tree(callgraph, &entries);
+        write_report(cli, report_path, report_path.ends_with(".json"), &report, || if report_path.ends_with(".dot") { report.to_dot() } else { report.to_string() },
+            format_args!("Dominator tree saved to {} ({} functions, {} gatekeepers)", report_path, report.nodes.len(), report.gatekeepers.len()))?;
+    }
+
+    // ── uncovered reachable functions ─────────
+    if let Some(ref report_path) = cli.uncovered {
+        let report = coverage::uncovered_reachable(callgraph, &entries);
+        write_report(cli, report_path, report_path.ends_with(".json"), &report, || report.to_string(),
+            format_args!("Uncovered report saved to {} ({} functions)", report_path, report.uncovered.len()))?;
+    }
+
+    // ── unsafe code reachable from the entries ─
+    if let Some(ref report_path) = cli.unsafe_reachability {
+        let report = unsafety::unsafe_reachability(callgraph, &entries);
+        write_report(cli, report_path, report_path.ends_with(".json"), &report, || report.to_string(),
+            format_args!("Unsafe reachability report saved to {} ({} functions, {} paths)", report_path, report.functions.len(), report.paths.len()))?;
+    }
+
+    // ── taint paths from sources to sinks ─────
+    if let Some(ref config_path) = cli.taint {
+        let config = TaintConfig::load(std::path::Path::new(config_path))
+            .map_err(|source| Error::TaintConfig { path: config_path.into(), source })?;
+        // Sinks are mostly library calls, which only stub nodes stand for
+        let stubbed = add_external_stubs(callgraph);
+        let source_manager = source_manager(cli, files);
+        let options = TraceOptions { max_paths: cli.path_limit, signatures: cli.signatures, ..Default::default() };
+        let tracer = TraceGenerator::with_options(&stubbed, &source_manager, options);
+        let report = taint::taint_flows(&stubbed, &config, &tracer);
+        let report_path = cli.taint_output.as_deref().unwrap_or("-");
+        write_report(cli, report_path, report_path.ends_with(".json"), &report, || report.to_string(),
+            format_args!("Taint report saved to {} ({} flows)", report_path, report.flows.len()))?;
+    }
+
+    // ── complexity report ─────────────────────
+    if let Some(ref report_path) = cli.metrics {
+        let report = MetricsReport::worst(callgraph, cli.metrics_top);
+        write_report(cli, report_path, report_path.ends_with(".json"), &report, || report.to_string(),
+            format_args!("Metrics report saved to {} ({} functions)", report_path, report.functions.len()))?;
+    }
+
+    // ── path query between two functions ──────
+    if cli.path_from.is_some() || cli.path_to.is_some() {
+        let (Some(from), Some(to)) = (&cli.path_from, &cli.path_to) else {
+            eprintln!("Error: --path-from and --path-to must be given together");
+            std::process::exit(1);
+        };
+        let resolve = |flag, query| resolve_symbol(callgraph, query).map_err(|lookup| Error::Lookup { flag, lookup });
+        let (from, to) = (resolve("--path-from", from)?, resolve("--path-to", to)?);
+        let source_manager = source_manager(cli, files);
+        let options = TraceOptions { max_paths: cli.path_limit, signatures: cli.signatures, ..Default::default() };
+        let paths = TraceGenerator::with_options(callgraph, &source_manager, options).paths_between(&from, &to);
+
+        if paths.is_empty() {
+            println!("No call path from {} to {}", from, to);
+        }
+        for (i, path) in paths.iter().enumerate() {
+            println!("Path {}:", i + 1);
+            for step in &path.steps {
+                let arrow = if step.depth == 0 { "  " } else { "  -> " };
+                match &step.location {
+                    Some(location) if step.depth > 0 => println!("{}{} ({})", arrow, step.id, location),
+                    _ => println!("{}{}", arrow, step.id),
+                }
+                if let Some(code) = &step.snippet {
+                    println!("         {}", code);
+                }
+            }
+        }
+    }
+
+    // ── call paths to potential panics ────────
+    if let Some(ref query) = cli.panic_paths {
+        let entry = resolve_symbol(callgraph, query).map_err(|lookup| Error::Lookup { flag: "--panic-paths", lookup })?;
+        let source_manager = source_manager(cli, files);
+        let options = TraceOptions { signatures: cli.signatures, ..Default::default() };
+        let paths = TraceGenerator::with_options(callgraph, &source_manager, options).panic_paths(&entry);
+        print!("{}", TraceExporter::to_text(&[(entry, paths)]));
+    }
 
     // ── reverse call查詢 ──────────────────────
     if let Some(ref target_id) = cli.reverse {
@@ -242,13 +1758,15 @@ fn run_post_processing(cli: &Cli, callgraph: &mr_hedgehog::domain::callgraph::Ca
         let mut caller_map: HashMap<String, Vec<String>> = HashMap::new();
         for node in &callgraph.nodes {
             for callee in &node.callees {
-                caller_map.entry(callee.clone()).or_default().push(node.id.clone());
+                caller_map.entry(callee.target.to_string()).or_default().push(node.id.to_string());
             }
         }
 
-        // 2. BFS/DFS 搜尋所有從 main@... 到 target_id 的完整呼叫路徑
+        // 2. BFS/DFS 搜尋所有從 entry 到 target_id 的完整呼叫路徑
         let mut all_paths: Vec<Vec<String>> = vec![];
-        let mut stack = vec![(vec![entry.clone()], entry.clone())]; // (目前路徑, 當前節點)
+        let mut stack: Vec<(Vec<String>, String)> = entries.iter()
+            .map(|e| (vec![e.clone()], e.clone())) // (目前路徑, 當前節點)
+            .collect();
 
         while let Some((path, node_id)) = stack.pop() {
             if node_id == *target_id {
@@ -256,18 +1774,18 @@ fn run_post_processing(cli: &Cli, callgraph: &mr_hedgehog::domain::callgraph::Ca
                 continue;
             }
             // 找 callee
-            if let Some(n) = map.get(&node_id) {
-                for callee in &n.callees {
-                    if !path.contains(callee) { // 防止循環
+            if let Some(n) = map.get(node_id.as_str()) {
+                for callee in n.callee_ids() {
+                    if !path.iter().any(|p| p == callee) { // 防止循環
                         let mut new_path = path.clone();
-                        new_path.push(callee.clone());
-                        stack.push((new_path, callee.clone()));
+                        new_path.push(callee.to_string());
+                        stack.push((new_path, callee.to_string()));
                     }
                 }
             }
         }
         if all_paths.is_empty() {
-            println!("找不到任何路徑從 main 到 {}", target_id);
+            println!("找不到任何路徑從 {} 到 {}", entries.join(", "), target_id);
         } else {
             for (i, path) in all_paths.iter().enumerate() {
                 println!("路徑 {}:", i+1);
@@ -276,51 +1794,81 @@ fn run_post_processing(cli: &Cli, callgraph: &mr_hedgehog::domain::callgraph::Ca
                 }
             }
         }
-        return;
+        return Ok(());
     }
 
-    // ── 3. trace from main ──────────────────
-    if cli.debug {
+    // ── 3. trace from entry points ──────────
+    if cli.debug_nodes {
         println!("\n==== [DEBUG nodes] ====");
-        for n in &callgraph.nodes{println!("{} -> {:?}",n.id,n.callees);}
+        for n in &callgraph.nodes{println!("{} -> {:?}",n.id,n.callee_ids().collect::<Vec<_>>());}
         println!("========================");
     }
 
-    if !entry.is_empty() && cli.expand_paths {
+    if cli.expand_paths || cli.trace_output.is_some() {
         // Init SourceManager
-        let source_manager = SourceManager::new(&files);
+        let source_manager = source_manager(cli, files);
+        let options = TraceOptions { signatures: cli.signatures, ..Default::default() };
+        let trace_gen = TraceGenerator::with_options(callgraph, &source_manager, options);
+        let traces: Vec<(String, Vec<TracePath>)> = entries.iter()
+            .map(|entry| (entry.clone(), trace_gen.generate_paths(entry)))
+            .collect();
 
-        println!("\n=== Rich Trace Paths from {} ===", entry);
-        let trace_gen = TraceGenerator::new(&callgraph, &source_manager);
-        let paths = trace_gen.generate_paths(&entry);
-
-        if paths.is_empty() {
-             println!("No paths found.");
+        if cli.expand_paths {
+            print!("{}", TraceExporter::to_text(&traces));
         }
 
-        for (i, path) in paths.iter().enumerate() {
-            println!("Path {}:", i + 1);
-            for (step_idx, step) in path.steps.iter().enumerate() {
-                let location = step.location.as_deref().unwrap_or("?");
-                let note = step.note.as_deref().unwrap_or("");
-                let note_str = if !note.is_empty() { format!(" {}", note) } else { "".to_string() };
-                
-                // Indentation based on depth (step.depth or just loop index? 
-                // trace.rs sets depth. Let's use it.)
-                let indent = "  ".repeat(step.depth);
-                
-                println!("{}[{}] {}{} ({})", indent, step_idx, step.id, note_str, location);
-                
-                if let Some(code) = &step.snippet {
-                    println!("{}    Code: {}", indent, code);
-                }
+        if let Some(path) = &cli.trace_output {
+            let format = TraceFormat::parse(&cli.trace_format).unwrap_or_else(|| {
+                eprintln!("Unknown --trace-format: {} (expected \"markdown\" or \"json\")", cli.trace_format);
+                std::process::exit(1);
+            });
+            if path == "-" {
+                print!("{}", TraceExporter::render(&traces, format, source_links(cli).as_ref()));
+            } else {
+                TraceExporter::export(&traces, format, source_links(cli).as_ref(), path)
+                    .map_err(|source| Error::Export { path: path.into(), source })?;
+                saved(cli, format_args!("Traces saved to {}", path));
             }
-            println!();
         }
     }
 
     // ── 4. export (callgraph or flowchart) ────────────────────────
-    let output_path = cli.output.as_ref().unwrap();
+    let targets = output_targets(cli).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
+    let filter = GraphFilter {
+        include: cli.include.clone(),
+        exclude: cli.exclude.clone(),
+        collapse_external: cli.collapse_excluded,
+        exclude_tests: cli.exclude_tests,
+        exclude_origins: cli.exclude_origin.clone(),
+    };
+    // SCIP ingestion already stubbed its targets by package; this covers syn's
+    let stubbed;
+    let with_stubs = if cli.include_external {
+        stubbed = collapse_external(&add_external_stubs(callgraph), &cli.collapse_external);
+        &stubbed
+    } else {
+        if !cli.collapse_external.is_empty() {
+            tracing::warn!("--collapse-external folds the stubs of --include-external, which is off");
+        }
+        callgraph
+    };
+    let mut filtered = filter.apply(with_stubs);
+    // Resolved after filtering, so an excluded function can't be the center
+    if let Some(ref query) = cli.around {
+        let center = resolve_symbol(&filtered, query).map_err(|lookup| Error::Lookup { flag: "--around", lookup })?;
+        filtered = neighborhood(&filtered, &center, cli.depth);
+    }
+    if cli.unsafe_reachability.is_some() {
+        filtered = unsafety::unsafe_subgraph(&filtered, &entries);
+    }
+    if cli.condense {
+        filtered = condense(&filtered);
+    }
+    let callgraph = &filtered;
     
     if cli.mode == "flowchart" {
         // Detect entry points
@@ -328,32 +1876,175 @@ fn run_post_processing(cli: &Cli, callgraph: &mr_hedgehog::domain::callgraph::Ca
         let detector = EntryPointDetector::new(lang);
         
         let mut all_entries = Vec::new();
-        for (_, file_path, content) in files.iter() {
-            let entries = detector.detect(file_path, content);
+        for file in files {
+            let entries = detector.detect(&file.path, &file.content);
             all_entries.extend(entries);
         }
         
         if all_entries.is_empty() {
-            eprintln!("Warning: No entry points detected. Flowchart will be empty.");
-        } else if cli.debug {
-            println!("\n==== [Entry Points Detected] ====");
+            tracing::warn!("No entry points detected. Flowchart will be empty.");
+        } else {
             for e in &all_entries {
-                println!("  {:?}: {} ({})", e.kind, e.name, e.file_path);
+                tracing::debug!("Entry point {:?}: {} ({})", e.kind, e.name, e.file_path);
             }
-            println!("=================================\n");
         }
         
         // Build FlowGraph from CallGraph
-        let flow = FlowGraph::from_callgraph(&callgraph, all_entries, cli.max_depth);
+        let flow = FlowGraph::from_callgraph(callgraph, all_entries, cli.max_depth);
         
-        // Export as flowchart DOT
-        FlowchartExporter::export(&flow, output_path).unwrap();
-        println!("Flowchart saved to {} ({} nodes, {} edges)", output_path, flow.nodes.len(), flow.edges.len());
+        // Export as flowchart DOT, whatever the format
+        for (output_path, _) in &targets {
+            if output_path == "-" {
+                print!("{}", FlowchartExporter::to_dot(&flow));
+                continue;
+            }
+            FlowchartExporter::export(&flow, output_path)
+                .map_err(|source| Error::Export { path: output_path.into(), source })?;
+            saved(cli, format_args!("Flowchart saved to {} ({} nodes, {} edges)", output_path, flow.nodes.len(), flow.edges.len()));
+            render_output(cli, output_path)?;
+        }
+    } else if let Some(level) = ModuleLevel::parse(&cli.view) {
+        let modules = ModuleGraph::from_callgraph(callgraph, level);
+        for (output_path, _) in &targets {
+            if output_path == "-" {
+                println!("{}", ModuleExporter::to_dot(&modules));
+                continue;
+            }
+            ModuleExporter::export(&modules, output_path)
+                .map_err(|source| Error::Export { path: output_path.into(), source })?;
+            saved(cli, format_args!("Module graph saved to {} ({} {}, {} edges)", output_path, modules.nodes.len(), cli.view, modules.edges.len()));
+            render_output(cli, output_path)?;
+        }
     } else {
         // Default: callgraph mode
-        let exporter = DotExporter{};
-        exporter.export(&callgraph, output_path).unwrap();
-        println!("Graph saved to {}", output_path);
+        for (output_path, format) in &targets {
+            let exporter = graph_exporter(cli, format);
+            if output_path == "-" {
+                let stdout = std::io::stdout();
+                if format == "sqlite" && stdout.is_terminal() {
+                    eprintln!("Error: not writing a SQLite database to the terminal; redirect stdout or pass an --output file");
+                    std::process::exit(1);
+                }
+                exporter.export_to_writer(callgraph, &mut stdout.lock())
+                    .map_err(|source| Error::Export { path: output_path.into(), source })?;
+                continue;
+            }
+            exporter.export(callgraph, output_path)
+                .map_err(|source| Error::Export { path: output_path.into(), source })?;
+            saved(cli, format_args!("Graph saved to {}", output_path));
+            if format == "dot" {
+                render_output(cli, output_path)?;
+            }
+        }
     }
+    Ok(())
+}
+
+/// `--link-base` links for files in the repository holding the workspace
+/// (or the working directory).
+fn source_links(cli: &Cli) -> Option<SourceLinks> {
+    let base = cli.link_base.as_deref()?;
+    let root = project_root(cli);
+    let dir = root.canonicalize().unwrap_or(root);
+    Some(SourceLinks::for_repository(base, &dir))
+}
+
+/// Source lines for snippets: the analyzed files, or files read from the
+/// project root on demand when there are none (a SCIP index alone).
+fn source_manager(cli: &Cli, files: &SourceSet) -> SourceManager {
+    let root = ProjectRoot::new(project_root(cli));
+    if files.is_empty() {
+        SourceManager::lazy(root)
+    } else {
+        SourceManager::new(files).with_root(root)
+    }
+}
+
+/// The exporter for `format`, styled by the `--dot-*` and cluster flags.
+fn graph_exporter(cli: &Cli, format: &str) -> Box<dyn OutputExporter> {
+    match format {
+        "dot" => {
+            let clustering = match cli.cluster.as_str() {
+                "none" => DotClustering::None,
+                "crate" => DotClustering::Crate,
+                "module" => DotClustering::Module,
+                other => {
+                    eprintln!("Unknown --cluster mode: {} (expected \"crate\", \"module\" or \"none\")", other);
+                    std::process::exit(1);
+                }
+            };
+            let mut edge_colors = HashMap::new();
+            for spec in &cli.dot_edge_color {
+                match spec.split_once('=').and_then(|(kind, color)| Some((EdgeKind::parse(kind)?, color))) {
+                    Some((kind, color)) => { edge_colors.insert(kind, color.to_string()); }
+                    None => {
+                        eprintln!("Invalid --dot-edge-color: {} (expected KIND=COLOR, KIND one of call, dynamic, spawn, reference, inferred, implements, defines-method, has-field-of-type, constructs, import)", spec);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            let style = DotStyle {
+                rankdir: cli.dot_rankdir.clone(),
+                node_shape: cli.dot_node_shape.clone(),
+                font: cli.dot_font.clone(),
+                edge_colors,
+                max_label_len: cli.dot_max_label,
+                source_url: cli.dot_source_url.clone(),
+                links: source_links(cli),
+                signatures: cli.signatures,
+            };
+            Box::new(DotExporter { edge_labels: cli.edge_labels, clustering, cluster_colors: cli.cluster_colors.clone(), style })
+        }
+        "json" => Box::new(JsonExporter),
+        "html" => Box::new(HtmlExporter { links: source_links(cli) }),
+        "sqlite" => Box::new(SqliteExporter),
+        other => {
+            eprintln!("Unknown output format: {} (expected \"dot\", \"json\", \"html\" or \"sqlite\")", other);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The (path, format) pairs to export: one `--format` per `--output`; one
+/// `--output` with several formats, its extension replaced per format; or
+/// several outputs whose format comes from their extension (else `--format`).
+/// Without an explicit `--format`, every output's extension picks its format.
+fn output_targets(cli: &Cli) -> Result<Vec<(String, String)>, String> {
+    if !cli.format_given {
+        return Ok(cli.output.iter()
+            .map(|output| (output.clone(), format_for_path(output).unwrap_or("dot").to_string()))
+            .collect());
+    }
+    if let Some(unknown) = cli.format.iter().find(|f| !OUTPUT_FORMATS.iter().any(|(name, _)| name == f)) {
+        return Err(format!("unknown output format: {} (expected \"dot\", \"json\", \"html\" or \"sqlite\")", unknown));
+    }
+    match (cli.output.as_slice(), cli.format.as_slice()) {
+        ([stdout], formats) if stdout == "-" && formats.len() > 1 => {
+            Err("--output - writes a single format; give one --format".to_string())
+        }
+        (outputs, formats) if outputs.len() == formats.len() => {
+            Ok(outputs.iter().cloned().zip(formats.iter().cloned()).collect())
+        }
+        ([output], formats) => Ok(formats.iter().map(|format| {
+            let ext = format_extension(format).unwrap_or_default();
+            let path = std::path::Path::new(output).with_extension(ext);
+            (path.to_string_lossy().into_owned(), format.clone())
+        }).collect()),
+        (outputs, [format]) => Ok(outputs.iter()
+            .map(|output| (output.clone(), format_for_path(output).unwrap_or(format).to_string()))
+            .collect()),
+        (outputs, formats) => Err(format!("{} outputs but {} formats: give one --format per --output", outputs.len(), formats.len())),
+    }
+}
+
+/// `--render`: turn the DOT file just written into an image.
+fn render_output(cli: &Cli, output_path: &str) -> Result<(), Error> {
+    let Some(format) = cli.render.as_deref().and_then(RenderFormat::parse) else {
+        return Ok(());
+    };
+    let image = graphviz::render(std::path::Path::new(output_path), format)
+        .map_err(|source| Error::Render { path: output_path.into(), source })?;
+    println!("Rendered {}", image.display());
+    Ok(())
 }
 
@@ -0,0 +1,152 @@
+//! Bundle Exporter
+//!
+//! Writes a `ContextBundle` as one Markdown document (call tree, then each
+//! function under a `file:line` heading in a fenced block, then what the
+//! token budget left out) or as JSON.
+
+use crate::domain::bundle::ContextBundle;
+use std::io::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleFormat {
+    Markdown,
+    Json,
+}
+
+impl BundleFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "markdown" | "md" => Some(BundleFormat::Markdown),
+            "json" => Some(BundleFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+pub struct BundleExporter;
+
+impl BundleExporter {
+    pub fn export(bundle: &ContextBundle, format: BundleFormat, path: &str) -> Result<()> {
+        std::fs::write(path, Self::render(bundle, format))
+    }
+
+    pub fn render(bundle: &ContextBundle, format: BundleFormat) -> String {
+        match format {
+            BundleFormat::Markdown => Self::to_markdown(bundle),
+            BundleFormat::Json => serde_json::to_string_pretty(bundle).expect("bundle is serializable") + "\n",
+        }
+    }
+
+    pub fn to_markdown(bundle: &ContextBundle) -> String {
+        let mut lines = vec![format!("# Context for `{}`", bundle.entry), String::new()];
+        if let Some(budget) = bundle.budget {
+            lines.push(format!("Token budget: ~{} of {} tokens used.", budget.used, budget.limit));
+            lines.push(String::new());
+        }
+        lines.push("## Call tree".to_string());
+        lines.push(String::new());
+        lines.push("```text".to_string());
+        for node in &bundle.tree {
+            let mut line = format!("{}{}", "  ".repeat(node.depth), node.id);
+            if let Some(location) = &node.location {
+                line.push_str(&format!(" ({})", location));
+            }
+            if let Some(note) = &node.note {
+                line.push_str(&format!(" {}", note));
+            }
+            lines.push(line);
+        }
+        lines.push("```".to_string());
+
+        lines.push(String::new());
+        lines.push(format!("## Functions ({})", bundle.functions.len()));
+        for f in &bundle.functions {
+            lines.push(String::new());
+            let truncated = if f.truncated { " (truncated)" } else { "" };
+            lines.push(format!("### `{}` — {}{}", f.id, f.location, truncated));
+            lines.push(String::new());
+            let lang = if f.location.contains(".py:") { "python" } else { "rust" };
+            lines.push(format!("```{}", lang));
+            lines.extend(f.body.lines().map(str::to_string));
+            lines.push("```".to_string());
+        }
+
+        if !bundle.missing.is_empty() {
+            lines.push(String::new());
+            lines.push("## Without source".to_string());
+            lines.push(String::new());
+            lines.extend(bundle.missing.iter().map(|id| format!("- `{}`", id)));
+        }
+
+        if !bundle.omitted.is_empty() {
+            lines.push(String::new());
+            lines.push("## Omitted to fit the token budget".to_string());
+            lines.push(String::new());
+            lines.extend(bundle.omitted.iter().map(|o| format!("- `{}` — {} (~{} tokens)", o.id, o.location, o.tokens)));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::bundle::{BundledFunction, OmittedFunction, TokenBudget, TreeNode};
+
+    #[test]
+    fn test_to_markdown() {
+        let bundle = ContextBundle {
+            entry: "app::main".to_string(),
+            tree: vec![
+                TreeNode { id: "app::main".to_string(), location: Some("src/main.rs:2".to_string()), depth: 0, note: None },
+                TreeNode { id: "app::main".to_string(), location: Some("src/main.rs:2".to_string()), depth: 1, note: Some("[Cycle Detected]".to_string()) },
+                TreeNode { id: "log::info".to_string(), location: None, depth: 1, note: None },
+            ],
+            functions: vec![BundledFunction { id: "app::main".to_string(), location: "src/main.rs:2".to_string(), body: "fn main() {\n    main();\n}".to_string(), truncated: false }],
+            missing: vec!["log::info".to_string()],
+            omitted: Vec::new(),
+            budget: None,
+        };
+        assert_eq!(BundleExporter::to_markdown(&bundle), "# Context for `app::main`
+
+## Call tree
+
+```text
+app::main (src/main.rs:2)
+  app::main (src/main.rs:2) [Cycle Detected]
+  log::info
+```
+
+## Functions (1)
+
+### `app::main` — src/main.rs:2
+
+```rust
+fn main() {
+    main();
+}
+```
+
+## Without source
+
+- `log::info`
+");
+    }
+
+    #[test]
+    fn test_budget_report() {
+        let bundle = ContextBundle {
+            entry: "app::main".to_string(),
+            tree: vec![TreeNode { id: "app::main".to_string(), location: None, depth: 0, note: None }],
+            functions: vec![BundledFunction { id: "app::main".to_string(), location: "src/main.rs:2".to_string(), body: "fn main() {\n    // … 4 lines omitted\n}".to_string(), truncated: true }],
+            missing: Vec::new(),
+            omitted: vec![OmittedFunction { id: "app::helper".to_string(), location: "src/main.rs:9".to_string(), tokens: 120 }],
+            budget: Some(TokenBudget { limit: 100, used: 40 }),
+        };
+        let md = BundleExporter::to_markdown(&bundle);
+        assert!(md.contains("Token budget: ~40 of 100 tokens used."), "{}", md);
+        assert!(md.contains("### `app::main` — src/main.rs:2 (truncated)"), "{}", md);
+        assert!(md.ends_with("## Omitted to fit the token budget\n\n- `app::helper` — src/main.rs:9 (~120 tokens)\n"), "{}", md);
+    }
+}
@@ -0,0 +1,102 @@
+//! Diff Exporter
+//!
+//! Draws two call graphs as one DOT graph with the changes between them
+//! highlighted: added nodes and edges in green, removed ones dashed red,
+//! the rest in gray. With `changes_only` the unchanged part is left out
+//! except for the endpoints of changed edges.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::domain::callgraph::{CallGraph, CallGraphNode};
+use crate::domain::diff::GraphDiff;
+
+const ADDED: &str = "color=green3, fontcolor=green3, penwidth=2";
+const REMOVED: &str = "color=red, fontcolor=red, style=dashed";
+const UNCHANGED: &str = "color=gray50";
+
+pub struct DiffExporter;
+
+impl DiffExporter {
+    pub fn export_dot(old: &CallGraph, new: &CallGraph, diff: &GraphDiff, changes_only: bool, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, Self::to_dot(old, new, diff, changes_only))
+    }
+
+    pub fn to_dot(old: &CallGraph, new: &CallGraph, diff: &GraphDiff, changes_only: bool) -> String {
+        let added_nodes: HashSet<&str> = diff.added_nodes.iter().map(String::as_str).collect();
+        let removed_nodes: HashSet<&str> = diff.removed_nodes.iter().map(String::as_str).collect();
+        let added_edges: HashSet<(&str, &str)> = diff.added_edges.iter().map(|(a, b)| (a.as_str(), b.as_str())).collect();
+        let removed_edges: HashSet<(&str, &str)> = diff.removed_edges.iter().map(|(a, b)| (a.as_str(), b.as_str())).collect();
+        // Unchanged nodes drawn under `changes_only`: endpoints of changed edges
+        let endpoints: HashSet<&str> = added_edges.iter().chain(&removed_edges).flat_map(|&(a, b)| [a, b]).collect();
+
+        let mut out = vec!["digraph G {".to_string()];
+        out.push(format!("    label=\"+{} / -{} nodes, +{} / -{} edges\"; labelloc=t;",
+            diff.added_nodes.len(), diff.removed_nodes.len(), diff.added_edges.len(), diff.removed_edges.len()));
+
+        let removed = old.nodes.iter().filter(|n| removed_nodes.contains(n.id.as_str()));
+        for n in new.nodes.iter().chain(removed) {
+            let id = n.id.as_str();
+            let attrs = if added_nodes.contains(id) {
+                ADDED
+            } else if removed_nodes.contains(id) {
+                REMOVED
+            } else if changes_only && !endpoints.contains(id) {
+                continue;
+            } else {
+                UNCHANGED
+            };
+            out.push(format!("    \"{}\" [label=\"{}\", {}];", id, Self::label(n), attrs));
+        }
+
+        let edges = |cg: &'_ CallGraph| -> BTreeSet<(String, String)> {
+            cg.nodes.iter().flat_map(|n| n.callee_ids().map(move |c| (n.id.to_string(), c.to_string()))).collect()
+        };
+        for (from, to) in edges(new).into_iter().chain(diff.removed_edges.iter().cloned()) {
+            let edge = (from.as_str(), to.as_str());
+            let attrs = if added_edges.contains(&edge) {
+                ADDED
+            } else if removed_edges.contains(&edge) {
+                REMOVED
+            } else if changes_only {
+                continue;
+            } else {
+                UNCHANGED
+            };
+            out.push(format!("    \"{}\" -> \"{}\" [{}];", from, to, attrs));
+        }
+        out.push("}".to_string());
+        out.join("\n")
+    }
+
+    fn label(n: &CallGraphNode) -> String {
+        n.label.as_deref().unwrap_or(&n.id).replace('\"', "\\\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures::node;
+    
+
+    #[test]
+    fn test_to_dot() {
+        let old = CallGraph::new(vec![node("app::main", &["app::a", "app::b"]), node("app::a", &[]), node("app::b", &[]), node("app::idle", &[])]);
+        let new = CallGraph::new(vec![node("app::main", &["app::a", "app::c"]), node("app::a", &[]), node("app::c", &[]), node("app::idle", &[])]);
+        let diff = GraphDiff::between(&old, &new);
+
+        let dot = DiffExporter::to_dot(&old, &new, &diff, false);
+        assert!(dot.contains("label=\"+1 / -1 nodes, +1 / -1 edges\""), "{}", dot);
+        assert!(dot.contains(&format!("\"app::c\" [label=\"app::c\", {}];", ADDED)), "{}", dot);
+        assert!(dot.contains(&format!("\"app::b\" [label=\"app::b\", {}];", REMOVED)), "{}", dot);
+        assert!(dot.contains(&format!("\"app::main\" -> \"app::c\" [{}];", ADDED)), "{}", dot);
+        assert!(dot.contains(&format!("\"app::main\" -> \"app::b\" [{}];", REMOVED)), "{}", dot);
+        assert!(dot.contains(&format!("\"app::main\" -> \"app::a\" [{}];", UNCHANGED)), "{}", dot);
+        assert!(dot.contains("\"app::idle\""), "{}", dot);
+
+        let changes = DiffExporter::to_dot(&old, &new, &diff, true);
+        assert!(changes.contains(&format!("\"app::main\" [label=\"app::main\", {}];", UNCHANGED)), "{}", changes);
+        assert!(!changes.contains("app::idle"), "{}", changes);
+        assert!(!changes.contains("\"app::main\" -> \"app::a\""), "{}", changes);
+    }
+}
@@ -16,17 +16,17 @@ impl FlowchartExporter {
 
     /// Convert FlowGraph to DOT string.
     pub fn to_dot(flow: &FlowGraph) -> String {
-        let mut lines = Vec::new();
-
         // Graph configuration for flowchart layout
-        lines.push("digraph FlowChart {".to_string());
-        lines.push("    rankdir=TB;".to_string()); // Top to bottom
-        lines.push("    splines=ortho;".to_string()); // Orthogonal edges
-        lines.push("    nodesep=0.8;".to_string());
-        lines.push("    ranksep=1.0;".to_string());
-        lines.push("    node [fontname=\"Helvetica\", fontsize=12];".to_string());
-        lines.push("    edge [fontname=\"Helvetica\", fontsize=10];".to_string());
-        lines.push("".to_string());
+        let mut lines = vec![
+            "digraph FlowChart {".to_string(),
+            "    rankdir=TB;".to_string(),    // Top to bottom
+            "    splines=ortho;".to_string(), // Orthogonal edges
+            "    nodesep=0.8;".to_string(),
+            "    ranksep=1.0;".to_string(),
+            "    node [fontname=\"Helvetica\", fontsize=12];".to_string(),
+            "    edge [fontname=\"Helvetica\", fontsize=10];".to_string(),
+            "".to_string(),
+        ];
 
         // Node definitions with styling
         for node in &flow.nodes {
@@ -55,7 +55,7 @@ impl FlowchartExporter {
 
         // Group nodes by depth for layered layout
         let layers = flow.nodes_by_depth();
-        for (depth, layer) in layers.iter().enumerate() {
+        for layer in layers.iter() {
             if !layer.is_empty() {
                 let node_ids: Vec<String> = layer.iter().map(|n| format!("\"{}\"", n.id)).collect();
                 lines.push(format!("    {{ rank=same; {} }}", node_ids.join("; ")));
@@ -0,0 +1,112 @@
+//! HTML Exporter
+//!
+//! Writes a single self-contained HTML page: the graph JSON (same DTOs as
+//! [`JsonExporter`](crate::ports::json_exporter::JsonExporter)) embedded in a
+//! small canvas viewer with pan/zoom, search and click-to-expand
+//! neighborhoods. No external scripts are loaded, so the file works offline.
+
+use std::io::Write;
+
+use crate::api::dto::GraphDto;
+use crate::domain::callgraph::CallGraph;
+use crate::domain::links::SourceLinks;
+use crate::ports::OutputExporter;
+
+const TEMPLATE: &str = include_str!("html_viewer.html");
+const DATA_PLACEHOLDER: &str = "/*GRAPH_DATA*/";
+
+#[derive(Debug, Clone, Default)]
+pub struct HtmlExporter {
+    /// Link each node's location to its source host (`--link-base`).
+    pub links: Option<SourceLinks>,
+}
+
+impl HtmlExporter {
+    /// Render the viewer page for a CallGraph.
+    pub fn to_html(cg: &CallGraph) -> std::io::Result<String> {
+        let mut page = Vec::new();
+        Self::default().export_to_writer(cg, &mut page)?;
+        Ok(String::from_utf8(page).expect("the page is UTF-8"))
+    }
+}
+
+impl OutputExporter for HtmlExporter {
+    fn export_to_writer(&self, cg: &CallGraph, out: &mut dyn Write) -> std::io::Result<()> {
+        let (head, tail) = TEMPLATE.split_once(DATA_PLACEHOLDER).expect("the template has a data placeholder");
+        out.write_all(head.as_bytes())?;
+        let mut graph = GraphDto::from(cg);
+        if let Some(links) = &self.links {
+            graph = graph.with_links(links);
+        }
+        serde_json::to_writer(ScriptSafe { out, after_lt: false }, &graph)?;
+        out.write_all(tail.as_bytes())
+    }
+}
+
+/// Escapes `</` as `<\/` on the way through: `</script>` inside a string
+/// literal would end the script block early. The pair may straddle writes.
+struct ScriptSafe<'a> {
+    out: &'a mut dyn Write,
+    after_lt: bool,
+}
+
+impl Write for ScriptSafe<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut start = 0;
+        for (i, &byte) in buf.iter().enumerate() {
+            if byte == b'/' && (if i == 0 { self.after_lt } else { buf[i - 1] == b'<' }) {
+                self.out.write_all(&buf[start..i])?;
+                self.out.write_all(b"\\")?;
+                start = i;
+            }
+        }
+        self.out.write_all(&buf[start..])?;
+        if let Some(&last) = buf.last() {
+            self.after_lt = last == b'<';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::{CallEdge, CallGraphNode};
+
+    #[test]
+    fn test_to_html_embeds_graph() {
+        let cg = CallGraph::new(vec![
+            CallGraphNode {
+                id: "app::main".into(),
+                callees: vec![CallEdge::at("app::helper", "src/main.rs", 3)],
+                label: Some("</script><b>".to_string()),
+                ..Default::default()
+            },
+            CallGraphNode {
+                id: "app::helper".into(),
+                ..Default::default()
+            },
+        ]);
+
+        let html = HtmlExporter::to_html(&cg).unwrap();
+        assert!(!html.contains(DATA_PLACEHOLDER));
+        assert!(html.contains("\"from\":\"app::main\",\"to\":\"app::helper\""));
+        // Only the viewer's own closing tag remains
+        assert_eq!(html.matches("</script>").count(), 1);
+        assert!(!html.contains("<script src"));
+    }
+
+    #[test]
+    fn test_script_safe_across_writes() {
+        let mut out = Vec::new();
+        let mut safe = ScriptSafe { out: &mut out, after_lt: false };
+        for chunk in ["\"a</b\" <", "/script> x/y <"] {
+            safe.write_all(chunk.as_bytes()).unwrap();
+        }
+        assert_eq!(String::from_utf8(out).unwrap(), "\"a<\\/b\" <\\/script> x/y <");
+    }
+}
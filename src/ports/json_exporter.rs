@@ -0,0 +1,55 @@
+//! JSON Exporter
+//!
+//! Exports a CallGraph as JSON using the same DTOs served by the daemon.
+
+use crate::api::dto::GraphDto;
+use crate::domain::callgraph::CallGraph;
+use crate::ports::OutputExporter;
+
+pub struct JsonExporter;
+
+impl JsonExporter {
+    /// Convert a CallGraph to a pretty-printed JSON string.
+    pub fn to_json(cg: &CallGraph) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&GraphDto::from(cg))
+    }
+}
+
+impl OutputExporter for JsonExporter {
+    fn export_to_writer(&self, cg: &CallGraph, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(out, &GraphDto::from(cg))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::{CallEdge, CallGraphNode};
+
+    #[test]
+    fn test_to_json() {
+        let cg = CallGraph::new(vec![
+            CallGraphNode {
+                id: "app::main".into(),
+                callees: vec![CallEdge::at("app::helper", "src/main.rs", 3)],
+                label: Some("app::main".to_string()),
+                ..Default::default()
+            },
+            CallGraphNode {
+                id: "app::helper".into(),
+                ..Default::default()
+            },
+        ]);
+
+        let json = JsonExporter::to_json(&cg).unwrap();
+        let dto: GraphDto = serde_json::from_str(&json).unwrap();
+        assert_eq!(dto.nodes.len(), 2);
+        assert_eq!(dto.edges.len(), 1);
+        assert_eq!(dto.edges[0].from, "app::main");
+        assert_eq!(dto.edges[0].to, "app::helper");
+        assert_eq!(dto.edges[0].location.as_deref(), Some("src/main.rs:3"));
+        // Nodes without a label fall back to their id
+        assert_eq!(dto.nodes[1].label, "app::helper");
+    }
+}
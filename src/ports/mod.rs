@@ -1,11 +1,67 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
 use crate::domain::callgraph::CallGraph;
+use crate::domain::source::SourceSet;
 
 pub mod flowchart_exporter;
+pub mod module_exporter;
+pub mod json_exporter;
+pub mod html_exporter;
+pub mod diff_exporter;
+pub mod sqlite_exporter;
+pub mod trace_exporter;
+pub mod bundle_exporter;
+
+/// Graph export formats (`--format`) and the extension their files get.
+pub const OUTPUT_FORMATS: [(&str, &str); 4] = [("dot", "dot"), ("json", "json"), ("html", "html"), ("sqlite", "db")];
+
+/// File extension of an export format; `None` for unknown formats.
+pub fn format_extension(format: &str) -> Option<&'static str> {
+    OUTPUT_FORMATS.iter().find(|(name, _)| *name == format).map(|(_, ext)| *ext)
+}
+
+/// Other extensions that name an export format when reading `--output`.
+const FORMAT_ALIASES: [(&str, &str); 4] = [("dot", "gv"), ("html", "htm"), ("sqlite", "sqlite"), ("sqlite", "sqlite3")];
 
+/// Export format a file name asks for by its extension, `graph.gv` and
+/// `graph.sqlite3` included; `None` for other extensions.
+pub fn format_for_path(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?;
+    OUTPUT_FORMATS.iter().chain(&FORMAT_ALIASES).find(|(_, e)| *e == ext).map(|(name, _)| *name)
+}
+
+/// Builds a call graph from a workspace's sources.
 pub trait CallGraphBuilder {
-    fn build_call_graph(&self, sources: &[(String, String, String)]) -> CallGraph;
+    fn build_call_graph(&self, sources: &SourceSet) -> CallGraph;
 }
 
 pub trait OutputExporter {
-    fn export(&self, cg: &CallGraph, path: &str) -> std::io::Result<()>;
+    /// Write the export to `out` as it is produced, so large graphs are
+    /// never held in memory as one string.
+    fn export_to_writer(&self, cg: &CallGraph, out: &mut dyn Write) -> std::io::Result<()>;
+
+    /// Write the export to the file at `path`, replacing it.
+    fn export(&self, cg: &CallGraph, path: &str) -> std::io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.export_to_writer(cg, &mut out)?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_for_path() {
+        for (format, ext) in OUTPUT_FORMATS {
+            assert_eq!(format_for_path(&format!("graph.{}", ext)), Some(format));
+        }
+        assert_eq!(format_for_path("out/graph.gv"), Some("dot"));
+        assert_eq!(format_for_path("graph.sqlite3"), Some("sqlite"));
+        assert_eq!(format_for_path("graph.txt"), None);
+        assert_eq!(format_for_path("graph"), None);
+    }
 }
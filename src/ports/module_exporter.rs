@@ -0,0 +1,88 @@
+//! Module Graph DOT Exporter
+//!
+//! Exports a ModuleGraph as Graphviz DOT: modules are boxes grouped by
+//! crate, edges are labeled with the number of calls they stand for and
+//! drawn thicker the more there are.
+
+use crate::domain::modules::ModuleGraph;
+use std::io::Result;
+
+pub struct ModuleExporter;
+
+impl ModuleExporter {
+    /// Export a ModuleGraph to a DOT file.
+    pub fn export(graph: &ModuleGraph, path: &str) -> Result<()> {
+        std::fs::write(path, Self::to_dot(graph))
+    }
+
+    /// Convert a ModuleGraph to a DOT string.
+    pub fn to_dot(graph: &ModuleGraph) -> String {
+        let mut lines = vec![
+            "digraph Modules {".to_string(),
+            "    rankdir=LR;".to_string(),
+            "    node [shape=box, style=rounded];".to_string(),
+            "".to_string(),
+        ];
+
+        let mut krates: Vec<&str> = Vec::new();
+        for node in &graph.nodes {
+            if !krates.contains(&node.krate.as_str()) {
+                krates.push(&node.krate);
+            }
+        }
+        for (i, krate) in krates.iter().enumerate() {
+            let modules: Vec<_> = graph.nodes.iter().filter(|n| n.krate == *krate).collect();
+            let indent = if modules.len() > 1 {
+                lines.push(format!("    subgraph cluster_{} {{", i));
+                lines.push(format!("        label=\"{}\";", krate));
+                "        "
+            } else {
+                "    "
+            };
+            for module in modules {
+                let style = if module.external { ", style=\"rounded,dashed\", color=gray, fontcolor=gray" } else { "" };
+                lines.push(format!(
+                    "{}\"{}\" [label=\"{}\", tooltip=\"{} functions\"{}];",
+                    indent, module.id, module.id, module.functions, style
+                ));
+            }
+            if indent.len() > 4 {
+                lines.push("    }".to_string());
+            }
+        }
+
+        lines.push("".to_string());
+        let max = graph.edges.iter().map(|e| e.calls).max().unwrap_or(1) as f64;
+        for edge in &graph.edges {
+            lines.push(format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\", weight={}, penwidth={:.1}];",
+                edge.from, edge.to, edge.calls, edge.calls, 1.0 + 4.0 * edge.calls as f64 / max
+            ));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::modules::{ModuleEdge, ModuleNode};
+
+    #[test]
+    fn test_to_dot() {
+        let module = |id: &str, krate: &str| ModuleNode { id: id.into(), krate: krate.into(), functions: 2, external: krate == "serde" };
+        let graph = ModuleGraph {
+            nodes: vec![module("app", "app"), module("app::net", "app"), module("serde", "serde")],
+            edges: vec![
+                ModuleEdge { from: "app".into(), to: "app::net".into(), calls: 4 },
+                ModuleEdge { from: "app::net".into(), to: "serde".into(), calls: 1 },
+            ],
+        };
+        let dot = ModuleExporter::to_dot(&graph);
+        assert!(dot.contains("subgraph cluster_0 {\n        label=\"app\";\n        \"app\" [label=\"app\", tooltip=\"2 functions\"];"), "{}", dot);
+        assert!(dot.contains("    \"serde\" [label=\"serde\", tooltip=\"2 functions\", style=\"rounded,dashed\", color=gray, fontcolor=gray];"), "{}", dot);
+        assert!(dot.contains("\"app\" -> \"app::net\" [label=\"4\", weight=4, penwidth=5.0];"), "{}", dot);
+        assert!(dot.contains("\"app::net\" -> \"serde\" [label=\"1\", weight=1, penwidth=2.0];"), "{}", dot);
+    }
+}
@@ -0,0 +1,188 @@
+//! SQLite Exporter
+//!
+//! Writes a CallGraph as a SQLite database with a `nodes` and an `edges`
+//! table, for ad-hoc SQL over large graphs (joins with coverage data,
+//! ownership maps, ...). Locations are split into `file` and `line` columns
+//! next to the `file:line` form so they join without string parsing.
+
+use rusqlite::{params, Connection};
+
+use crate::domain::callgraph::{node_module, CallGraph};
+use crate::ports::OutputExporter;
+
+const SCHEMA: &str = "
+CREATE TABLE nodes (
+    id TEXT PRIMARY KEY,
+    label TEXT NOT NULL,
+    crate TEXT NOT NULL,
+    module TEXT NOT NULL,
+    location TEXT,
+    file TEXT,
+    line INTEGER,
+    is_public INTEGER NOT NULL,
+    is_async INTEGER NOT NULL,
+    is_unsafe INTEGER NOT NULL,
+    is_test INTEGER NOT NULL,
+    cfg TEXT,
+    external INTEGER NOT NULL,
+    complexity INTEGER,
+    statements INTEGER,
+    nesting INTEGER,
+    coverage INTEGER,
+    unsafe_blocks INTEGER NOT NULL,
+    panics INTEGER NOT NULL,
+    origin TEXT,
+    signature TEXT,
+    doc TEXT,
+    type_kind TEXT
+);
+CREATE TABLE edges (
+    caller TEXT NOT NULL,
+    callee TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    location TEXT,
+    file TEXT,
+    line INTEGER,
+    weight INTEGER NOT NULL
+);
+CREATE INDEX edges_caller ON edges (caller);
+CREATE INDEX edges_callee ON edges (callee);
+CREATE INDEX nodes_file ON nodes (file);
+";
+
+pub struct SqliteExporter;
+
+impl SqliteExporter {
+    /// Write `cg` into a fresh database at `conn`.
+    pub fn write(cg: &CallGraph, conn: &mut Connection) -> rusqlite::Result<()> {
+        let tx = conn.transaction()?;
+        tx.execute_batch(SCHEMA)?;
+        {
+            let mut insert_node = tx.prepare(
+                "INSERT OR IGNORE INTO nodes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+            )?;
+            let mut insert_edge = tx.prepare("INSERT INTO edges VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?;
+            for n in &cg.nodes {
+                let (krate, module) = node_module(&n.id);
+                let (file, line) = split_location(n.location.as_deref());
+                insert_node.execute(params![
+                    n.id.as_str(),
+                    n.label.as_deref().unwrap_or(&n.id),
+                    n.external.as_deref().unwrap_or(krate),
+                    module.join("::"),
+                    n.location,
+                    file,
+                    line,
+                    n.is_public,
+                    n.is_async,
+                    n.is_unsafe,
+                    n.is_test,
+                    n.cfg,
+                    n.external.is_some(),
+                    n.metrics.map(|m| m.complexity as i64),
+                    n.metrics.map(|m| m.statements as i64),
+                    n.metrics.map(|m| m.nesting as i64),
+                    n.coverage.map(|hits| hits as i64),
+                    n.unsafe_blocks as i64,
+                    n.panics.len() as i64,
+                    n.origin.map(|o| o.as_str()),
+                    n.signature,
+                    n.doc,
+                    n.type_kind.map(|k| k.as_str()),
+                ])?;
+                // One row per callee and kind; `weight` counts the call sites
+                for (e, weight) in n.weighted_callees() {
+                    insert_edge.execute(params![n.id.as_str(), e.target.as_str(), e.kind.as_str(), e.location(), e.file, e.line.map(|l| l as i64), weight as i64])?;
+                }
+            }
+        }
+        tx.commit()
+    }
+}
+
+/// `src/a.rs:3` -> (`src/a.rs`, 3); anything else is kept as the file.
+fn split_location(location: Option<&str>) -> (Option<&str>, Option<i64>) {
+    match location.map(|l| (l, l.rsplit_once(':'))) {
+        Some((_, Some((file, line)))) if line.parse::<i64>().is_ok() => (Some(file), line.parse().ok()),
+        Some((l, _)) => (Some(l), None),
+        None => (None, None),
+    }
+}
+
+impl OutputExporter for SqliteExporter {
+    /// SQLite needs a file of its own: the database is built in a temporary
+    /// file and copied to `out`.
+    fn export_to_writer(&self, cg: &CallGraph, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        let path = std::env::temp_dir().join(format!("mr_hedgehog-{}-{:p}.db", std::process::id(), cg));
+        let result = self.export(cg, &path.to_string_lossy())
+            .and_then(|()| std::io::copy(&mut std::fs::File::open(&path)?, out).map(drop));
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    fn export(&self, cg: &CallGraph, path: &str) -> std::io::Result<()> {
+        // Replace the file like the other exporters instead of appending to it
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let mut conn = Connection::open(path).map_err(std::io::Error::other)?;
+        Self::write(cg, &mut conn).map_err(std::io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::callgraph::fixtures;
+    use crate::domain::callgraph::{CallEdge, CallGraphNode, EdgeKind};
+    use crate::domain::metrics::FnMetrics;
+
+    fn node(id: &str, callees: Vec<CallEdge>) -> CallGraphNode {
+        CallGraphNode { callees, ..fixtures::node(id, &[]) }
+    }
+
+    #[test]
+    fn test_export() {
+        let main = CallGraphNode {
+            location: Some("src/main.rs:1".to_string()),
+            metrics: Some(FnMetrics { complexity: 3, statements: 2, nesting: 1 }),
+            ..node("app::net::main", vec![
+                CallEdge::at("app::net::send", "src/main.rs", 2),
+                CallEdge::at("app::net::send", "src/main.rs", 3),
+                CallEdge::new("serde_json::to_string").with_kind(EdgeKind::Reference),
+            ])
+        };
+        let stub = CallGraphNode { external: Some("serde_json".to_string()), ..node("serde_json::to_string", vec![]) };
+        let cg = CallGraph::new(vec![main, node("app::net::send", vec![]), stub]);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("graph.db");
+        let path = path.to_str().unwrap();
+        SqliteExporter.export(&cg, path).unwrap();
+        // Exporting again replaces the database
+        SqliteExporter.export(&cg, path).unwrap();
+
+        let conn = Connection::open(path).unwrap();
+        let row: (String, String, String, i64, i64) = conn.query_row(
+            "SELECT crate, module, file, line, complexity FROM nodes WHERE id = 'app::net::main'", [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+        ).unwrap();
+        assert_eq!(row, ("app".to_string(), "net".to_string(), "src/main.rs".to_string(), 1, 3));
+
+        let external: String = conn.query_row("SELECT crate FROM nodes WHERE external = 1", [], |r| r.get(0)).unwrap();
+        assert_eq!(external, "serde_json");
+
+        let mut edges = conn.prepare("SELECT callee, kind, line, weight FROM edges ORDER BY callee").unwrap();
+        let edges: Vec<(String, String, Option<i64>, i64)> = edges.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap().collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(edges, vec![
+            ("app::net::send".to_string(), "call".to_string(), Some(2), 2),
+            ("serde_json::to_string".to_string(), "reference".to_string(), None, 1),
+        ]);
+
+        let mut streamed = Vec::new();
+        SqliteExporter.export_to_writer(&cg, &mut streamed).unwrap();
+        assert!(streamed.starts_with(b"SQLite format 3\0"));
+    }
+}
@@ -0,0 +1,249 @@
+//! Trace Exporter
+//!
+//! Writes the call paths found by `TraceGenerator` as plain text (the
+//! console listing), as a Markdown document (one section per entry point
+//! and path, steps as nested numbered lists, call sites as `file:line`
+//! links, snippets in fenced code blocks) or as JSON for tools.
+
+use crate::domain::links::SourceLinks;
+use crate::domain::trace::{TracePath, TraceStep};
+use serde_json::json;
+use std::io::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+impl TraceFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(TraceFormat::Text),
+            "markdown" | "md" => Some(TraceFormat::Markdown),
+            "json" => Some(TraceFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+pub struct TraceExporter;
+
+impl TraceExporter {
+    /// Export the paths traced from each entry point; Markdown links
+    /// locations to `links` when given, relative to the file otherwise.
+    pub fn export(traces: &[(String, Vec<TracePath>)], format: TraceFormat, links: Option<&SourceLinks>, path: &str) -> Result<()> {
+        std::fs::write(path, Self::render(traces, format, links))
+    }
+
+    pub fn render(traces: &[(String, Vec<TracePath>)], format: TraceFormat, links: Option<&SourceLinks>) -> String {
+        match format {
+            TraceFormat::Text => Self::to_text(traces),
+            TraceFormat::Markdown => Self::to_markdown(traces, links),
+            TraceFormat::Json => Self::to_json(traces),
+        }
+    }
+
+    /// One block per entry point; steps indented by depth, snippets below.
+    pub fn to_text(traces: &[(String, Vec<TracePath>)]) -> String {
+        let mut out = String::new();
+        for (entry, paths) in traces {
+            out.push_str(&format!("\n=== Rich Trace Paths from {} ===\n", entry));
+            if paths.is_empty() {
+                out.push_str("No paths found.\n");
+            }
+            for (i, path) in paths.iter().enumerate() {
+                out.push_str(&format!("Path {}:\n", i + 1));
+                for (step_idx, step) in path.steps.iter().enumerate() {
+                    let location = step.location.as_deref().unwrap_or("?");
+                    let note = step.note.as_ref().map(|n| format!(" {}", n)).unwrap_or_default();
+                    let indent = "  ".repeat(step.depth);
+                    let signature = step.signature.as_ref().map(|s| format!(": {}", s)).unwrap_or_default();
+                    out.push_str(&format!("{}[{}] {}{}{} ({})\n", indent, step_idx, step.id, signature, note, location));
+                    if let Some(doc) = &step.doc {
+                        out.push_str(&format!("{}    Doc:  {}\n", indent, doc));
+                    }
+                    if let Some(code) = &step.snippet {
+                        let mut lines = code.lines();
+                        out.push_str(&format!("{}    Code: {}\n", indent, lines.next().unwrap_or_default()));
+                        for line in lines {
+                            out.push_str(&format!("{}          {}\n", indent, line));
+                        }
+                    }
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// `[{"entry": .., "paths": [{"steps": [{"id", "location", "depth", "snippet", "note"}]}]}]`
+    pub fn to_json(traces: &[(String, Vec<TracePath>)]) -> String {
+        let traces: Vec<serde_json::Value> = traces.iter()
+            .map(|(entry, paths)| json!({ "entry": entry, "paths": paths }))
+            .collect();
+        serde_json::to_string_pretty(&traces).expect("traces are serializable")
+    }
+
+    pub fn to_markdown(traces: &[(String, Vec<TracePath>)], links: Option<&SourceLinks>) -> String {
+        let mut lines = vec!["# Call Traces".to_string()];
+        for (entry, paths) in traces {
+            lines.push(String::new());
+            lines.push(format!("## From `{}`", entry));
+            if paths.is_empty() {
+                lines.push(String::new());
+                lines.push("No paths found.".to_string());
+            }
+            for (i, path) in paths.iter().enumerate() {
+                lines.push(String::new());
+                lines.push(format!("### Path {}", i + 1));
+                lines.push(String::new());
+                // Content column of the list item at each depth: nested
+                // items must start there to render as sub-lists
+                let mut columns = vec![0];
+                for (n, step) in path.steps.iter().enumerate() {
+                    let indent = columns.get(step.depth).or(columns.last()).copied().unwrap_or(0);
+                    let content = Self::push_step(&mut lines, n + 1, indent, step, links);
+                    columns.truncate(step.depth + 1);
+                    columns.push(content);
+                }
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Push one list item at `indent`; returns the column its content starts at.
+    fn push_step(lines: &mut Vec<String>, number: usize, indent: usize, step: &TraceStep, links: Option<&SourceLinks>) -> usize {
+        let marker = format!("{}. ", number);
+        let body_indent = " ".repeat(indent + marker.len());
+        let indent = " ".repeat(indent);
+        let mut item = format!("{}{}`{}`", indent, marker, step.id);
+        if let Some(signature) = &step.signature {
+            item.push_str(&format!(": `{}`", signature));
+        }
+        // Nodes without a recorded location carry their id as label
+        if let Some(location) = step.location.as_ref().filter(|l| **l != step.id) {
+            item.push_str(" — ");
+            item.push_str(&Self::location_link(location, links));
+        }
+        if let Some(note) = &step.note {
+            item.push_str(&format!(" *{}*", note));
+        }
+        if let Some(doc) = &step.doc {
+            item.push_str(&format!(" — {}", doc));
+        }
+        lines.push(item);
+
+        if let Some(snippet) = &step.snippet {
+            let lang = step.location.as_deref().map(Self::fence_language).unwrap_or_default();
+            lines.push(format!("{}```{}", body_indent, lang));
+            lines.extend(snippet.lines().map(|line| format!("{}{}", body_indent, line).trim_end().to_string()));
+            lines.push(format!("{}```", body_indent));
+        }
+        body_indent.len()
+    }
+
+    /// `[src/a.rs:3](src/a.rs#L3)` for a `file:line` location, code otherwise.
+    fn location_link(location: &str, links: Option<&SourceLinks>) -> String {
+        match location.rsplit_once(':') {
+            Some((file, line)) if !file.is_empty() && line.parse::<usize>().is_ok() => {
+                match links.and_then(|l| l.url(file, line.parse().ok())) {
+                    Some(url) => format!("[{}]({})", location, url),
+                    None => format!("[{}]({}#L{})", location, file.replace(' ', "%20"), line),
+                }
+            }
+            _ => format!("`{}`", location),
+        }
+    }
+
+    fn fence_language(location: &str) -> &'static str {
+        let file = location.rsplit_once(':').map_or(location, |(file, _)| file);
+        if file.ends_with(".rs") {
+            "rust"
+        } else if file.ends_with(".py") {
+            "python"
+        } else {
+            ""
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(id: &str, depth: usize, location: Option<&str>, snippet: Option<&str>, note: Option<&str>) -> TraceStep {
+        TraceStep {
+            id: id.to_string(),
+            signature: None,
+            doc: None,
+            location: location.map(str::to_string),
+            depth,
+            snippet: snippet.map(str::to_string),
+            note: note.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let path = TracePath {
+            steps: vec![
+                step("app::main", 0, Some("src/main.rs:3"), Some("helper();"), None),
+                step("app::helper", 1, Some("app::helper"), None, None),
+                step("app::main", 2, Some("lib:x"), None, Some("[Cycle Detected]")),
+            ],
+        };
+        let md = TraceExporter::to_markdown(&[("app::main".to_string(), vec![path]), ("app::idle".to_string(), vec![])], None);
+        assert_eq!(md, "# Call Traces
+
+## From `app::main`
+
+### Path 1
+
+1. `app::main` — [src/main.rs:3](src/main.rs#L3)
+   ```rust
+   helper();
+   ```
+   2. `app::helper`
+      3. `app::main` — `lib:x` *[Cycle Detected]*
+
+## From `app::idle`
+
+No paths found.
+");
+
+        let links = SourceLinks::new("https://github.com/org/repo/blob/abc", "/repo");
+        let path = TracePath { steps: vec![step("app::main", 0, Some("/repo/src/main.rs:3"), None, None)] };
+        let md = TraceExporter::to_markdown(&[("app::main".to_string(), vec![path])], Some(&links));
+        assert!(md.contains("1. `app::main` — [/repo/src/main.rs:3](https://github.com/org/repo/blob/abc/src/main.rs#L3)"), "{}", md);
+
+        let step = TraceStep {
+            signature: Some("fn run(x: i32) -> i32".to_string()),
+            doc: Some("Runs once.".to_string()),
+            ..step("app::run", 0, Some("src/lib.rs:1"), None, None)
+        };
+        let traces = [("app::run".to_string(), vec![TracePath { steps: vec![step] }])];
+        assert!(TraceExporter::to_markdown(&traces, None).contains("1. `app::run`: `fn run(x: i32) -> i32` — [src/lib.rs:1](src/lib.rs#L1) — Runs once.\n"));
+        assert!(TraceExporter::to_text(&traces).contains("[0] app::run: fn run(x: i32) -> i32 (src/lib.rs:1)\n    Doc:  Runs once.\n"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let path = TracePath {
+            steps: vec![
+                step("app::main", 0, Some("src/main.rs:3"), Some("helper();"), None),
+                step("app::main", 1, None, None, Some("[Cycle Detected]")),
+            ],
+        };
+        let json: serde_json::Value = serde_json::from_str(&TraceExporter::to_json(&[("app::main".to_string(), vec![path])])).unwrap();
+        assert_eq!(json[0]["entry"], "app::main");
+        let steps = &json[0]["paths"][0]["steps"];
+        assert_eq!(steps[0]["location"], "src/main.rs:3");
+        assert_eq!(steps[0]["snippet"], "helper();");
+        assert_eq!(steps[1]["depth"], 1);
+        assert_eq!(steps[1]["note"], "[Cycle Detected]");
+        assert!(steps[1]["location"].is_null());
+    }
+}
@@ -0,0 +1,799 @@
+use std::fs;
+use std::path::Path;
+
+use mr_hedgehog::analysis::analyze_with_sources;
+use mr_hedgehog::domain::source::{SourceFile, SourceSet};
+use mr_hedgehog::error::{EXIT_EXPORT, EXIT_NO_INPUT, EXIT_PARSE};
+use mr_hedgehog::{analyze, AnalysisConfig, Error};
+
+#[test]
+fn analyze_builds_graph_for_workspace_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "mod util { pub fn helper() {} }\nfn main() { util::helper(); }\n").unwrap();
+
+    // A directory works as well as the manifest path
+    let cg = analyze(&AnalysisConfig::new(dir.path())).unwrap();
+    let main = cg.nodes.iter().find(|n| n.id == "app::main").expect("main node");
+    assert!(main.calls("app::util::helper"), "{:?}", main.callees);
+
+    let missing = analyze(&AnalysisConfig::new(dir.path().join("nope/Cargo.toml")));
+    assert!(matches!(missing, Err(Error::ManifestNotFound(_))));
+}
+
+#[test]
+fn workspace_members_come_from_cargo_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    let package = |name: &str, deps: &str| {
+        format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n{}", name, deps)
+    };
+    // Virtual manifest with glob members, an excluded member and default-members
+    write("ws/Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/skip\"]\ndefault-members = [\"crates/app\"]\nresolver = \"2\"\n");
+    write("ws/crates/app/Cargo.toml", &package("app", "core_lib = { path = \"../core_lib\" }\next = { path = \"../../../ext\" }\n"));
+    write("ws/crates/app/src/main.rs", "fn main() { core_lib::run(); ext::assist(); }\n");
+    write("ws/crates/core_lib/Cargo.toml", &package("core_lib", ""));
+    write("ws/crates/core_lib/src/lib.rs", "pub fn run() {}\n");
+    write("ws/crates/skip/Cargo.toml", &package("skip", ""));
+    write("ws/crates/skip/src/lib.rs", "pub fn skipped() {}\n");
+    // Path dependency outside the workspace
+    write("ext/Cargo.toml", &package("ext", ""));
+    write("ext/src/lib.rs", "pub fn assist() {}\n");
+
+    let ids = |cg: &mr_hedgehog::domain::callgraph::CallGraph| {
+        let mut ids: Vec<String> = cg.nodes.iter().map(|n| n.id.to_string()).collect();
+        ids.sort();
+        ids
+    };
+    let cg = analyze(&AnalysisConfig::new(dir.path().join("ws"))).unwrap();
+    assert_eq!(ids(&cg), vec!["app::main", "core_lib::run"]);
+
+    // Opt in to the path dependency outside the workspace
+    let config = AnalysisConfig { follow_path_deps: true, ..AnalysisConfig::new(dir.path().join("ws")) };
+    let cg = analyze(&config).unwrap();
+    assert_eq!(ids(&cg), vec!["app::main", "core_lib::run", "ext::assist"]);
+    let main = cg.nodes.iter().find(|n| n.id == "app::main").unwrap();
+    assert!(main.calls("core_lib::run") && main.calls("ext::assist"), "{:?}", main.callees);
+}
+
+fn broken_workspace() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "mod broken;\nfn main() {}\n").unwrap();
+    fs::write(dir.path().join("src/broken.rs"), "fn oops( {\n").unwrap();
+    dir
+}
+
+#[test]
+fn ignore_patterns_and_gitignore_skip_sources() {
+    use mr_hedgehog::infrastructure::project_loader::SourceFilter;
+
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    write("Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n");
+    write("src/main.rs", "fn main() {}\n");
+    write("src/api_generated.rs", "fn generated() {}\n");
+    write("src/vendor/zlib.rs", "fn inflate() {}\n");
+    write("src/scratch.rs", "fn scratch() {}\n");
+    write(".gitignore", "/src/scratch.rs\n");
+
+    let loaded = |filter: SourceFilter| {
+        let config = AnalysisConfig { filter, ..AnalysisConfig::new(dir.path()) };
+        let files = config.load_files().unwrap();
+        files.iter().map(|f| f.path.rsplit("src/").next().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(loaded(SourceFilter::default()), vec!["api_generated.rs", "main.rs", "scratch.rs", "vendor/zlib.rs"]);
+    let filter = SourceFilter { ignore: vec!["vendor/".to_string(), "*_generated.rs".to_string()], ..SourceFilter::default() };
+    assert_eq!(loaded(filter.clone()), vec!["main.rs", "scratch.rs"]);
+    assert_eq!(loaded(SourceFilter { gitignore: true, ..filter }), vec!["main.rs"]);
+
+    // Anchored patterns hold for a manifest given relative to the working directory
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .current_dir(dir.path().parent().unwrap())
+        .args(["--no-config".as_ref(), "-q".as_ref(), "--workspace".as_ref(), Path::new(dir.path().file_name().unwrap()).join("Cargo.toml").as_os_str()])
+        .args(["--ignore", "/src/vendor", "find", "inflate"])
+        .output().unwrap();
+    assert!(!output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_bench_and_example_targets_are_opt_in_and_tagged() {
+    use mr_hedgehog::domain::cfg::CfgOptions;
+    use mr_hedgehog::domain::filter::GraphFilter;
+    use mr_hedgehog::domain::source::SourceOrigin;
+    use mr_hedgehog::infrastructure::project_loader::SourceFilter;
+
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    write("Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n");
+    write("src/lib.rs", "pub fn run() {}\n#[cfg(test)]\nmod tests { fn check() { crate::run(); } }\n");
+    write("tests/it.rs", "mod common;\n#[test]\nfn runs() { common::setup(); }\n");
+    write("tests/common/mod.rs", "pub fn setup() {}\n");
+    write("benches/speed.rs", "fn main() {}\n");
+    write("examples/demo.rs", "fn main() {}\n");
+
+    let origins = |filter: SourceFilter| {
+        let config = AnalysisConfig { filter, ..AnalysisConfig::new(dir.path()) };
+        let files = config.load_files().unwrap();
+        files.iter().map(|f| (f.path.rsplit(['/', '\\']).next().unwrap().to_string(), f.origin)).collect::<Vec<_>>()
+    };
+    assert_eq!(origins(SourceFilter::default()), vec![("lib.rs".to_string(), None)]);
+    let all = SourceFilter { tests: true, benches: true, examples: true, ..SourceFilter::default() };
+    assert_eq!(origins(all.clone()), vec![
+        ("speed.rs".to_string(), Some(SourceOrigin::Bench)),
+        ("demo.rs".to_string(), Some(SourceOrigin::Example)),
+        ("lib.rs".to_string(), None),
+        ("mod.rs".to_string(), Some(SourceOrigin::Test)),
+        ("it.rs".to_string(), Some(SourceOrigin::Test)),
+    ]);
+
+    let graph = analyze(&AnalysisConfig { filter: all.clone(), ..AnalysisConfig::new(dir.path()) }).unwrap();
+    let origin = |id: &str| graph.nodes.iter().find(|n| n.id.ends_with(id)).map(|n| n.origin);
+    assert_eq!(origin("::setup"), Some(Some(SourceOrigin::Test)));
+    assert_eq!(origin("::run"), Some(None));
+    let production = GraphFilter { exclude_tests: true, exclude_origins: vec![SourceOrigin::Bench, SourceOrigin::Example], ..GraphFilter::default() }
+        .apply(&graph);
+    let ids: Vec<&str> = production.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["app::run"]);
+
+    // Without cfg(test), the unit test module is not analyzed at all
+    let cfg = CfgOptions { test: Some(false), ..CfgOptions::default() };
+    let graph = analyze(&AnalysisConfig { cfg, ..AnalysisConfig::new(dir.path()) }).unwrap();
+    assert!(graph.nodes.iter().all(|n| !n.id.contains("check")), "{:?}", graph.nodes);
+}
+
+#[test]
+fn binary_and_example_targets_get_their_own_main() {
+    use mr_hedgehog::infrastructure::project_loader::SourceFilter;
+
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    write("Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n");
+    write("src/lib.rs", "pub fn run() {}\n");
+    write("src/main.rs", "fn main() { app::run(); }\n");
+    write("src/bin/tool2.rs", "fn main() { helper(); }\nfn helper() { app::run(); }\n");
+    write("src/bin/tool3/main.rs", "mod cli;\nfn main() { cli::parse(); }\n");
+    write("src/bin/tool3/cli.rs", "pub fn parse() {}\n");
+    write("examples/demo.rs", "fn main() { app::run(); }\n");
+
+    let filter = SourceFilter { examples: true, ..SourceFilter::default() };
+    let graph = analyze(&AnalysisConfig { filter, ..AnalysisConfig::new(dir.path()) }).unwrap();
+    let node = |id: &str| graph.nodes.iter().find(|n| n.id == id).unwrap_or_else(|| panic!("no {} in {:?}", id, graph.nodes.iter().map(|n| &n.id).collect::<Vec<_>>()));
+    assert!(node("app::main").calls("app::run"));
+    assert!(node("app[bin:tool2]::main").calls("app[bin:tool2]::helper"));
+    assert!(node("app[bin:tool2]::helper").calls("app::run"));
+    assert!(node("app[bin:tool3]::main").calls("app[bin:tool3]::cli::parse"));
+    assert!(node("app[example:demo]::main").calls("app::run"));
+}
+
+#[test]
+fn parse_errors_are_reported_not_fatal() {
+    let dir = broken_workspace();
+    let config = AnalysisConfig::new(dir.path());
+
+    let analysis = analyze_with_sources(&config).unwrap();
+    assert!(analysis.graph.nodes.iter().any(|n| n.id == "app::main"));
+    assert_eq!(analysis.parse_errors.len(), 1);
+    assert!(analysis.parse_errors[0].file.ends_with("broken.rs"));
+
+    match analyze(&config) {
+        Err(e @ Error::Parse(_)) => assert_eq!(e.exit_code(), EXIT_PARSE),
+        other => panic!("expected parse error, got {:?}", other.map(|g| g.nodes.len())),
+    }
+}
+
+#[test]
+fn cli_exit_codes() {
+    let run = |args: &[&std::ffi::OsStr]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog")).args(args).output().unwrap()
+    };
+    let dir = broken_workspace();
+    let manifest = dir.path().join("Cargo.toml");
+    let out = dir.path().join("graph.dot");
+
+    let no_input = run(&["--output".as_ref(), out.as_os_str()]);
+    assert_eq!(no_input.status.code(), Some(EXIT_NO_INPUT));
+    assert!(String::from_utf8_lossy(&no_input.stderr).contains("hint: pass --workspace"));
+
+    // By default the broken file is skipped and reported at the end
+    let lenient = run(&["--workspace".as_ref(), manifest.as_os_str(), "--output".as_ref(), out.as_os_str()]);
+    assert!(lenient.status.success());
+    assert!(String::from_utf8_lossy(&lenient.stderr).contains("broken.rs:1:"));
+    assert!(out.exists());
+
+    let strict_out = dir.path().join("strict.dot");
+    let strict = run(&["--workspace".as_ref(), manifest.as_os_str(), "--output".as_ref(), strict_out.as_os_str(), "--strict".as_ref()]);
+    assert_eq!(strict.status.code(), Some(EXIT_PARSE));
+    assert!(!strict_out.exists());
+
+    let unwritable = dir.path().join("missing/graph.dot");
+    let export = run(&["--workspace".as_ref(), manifest.as_os_str(), "--output".as_ref(), unwritable.as_os_str()]);
+    assert_eq!(export.status.code(), Some(EXIT_EXPORT));
+    assert!(String::from_utf8_lossy(&export.stderr).contains("failed to write"));
+}
+
+#[test]
+fn log_level_flags() {
+    let dir = broken_workspace();
+    let manifest = dir.path().join("Cargo.toml");
+    let out = dir.path().join("graph.dot");
+    let run = |flags: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+            .args(["--no-config".as_ref(), "--workspace".as_ref(), manifest.as_os_str(), "--output".as_ref(), out.as_os_str()])
+            .args(flags)
+            .env_remove("RUST_LOG")
+            .output()
+            .unwrap()
+    };
+
+    let normal = run(&[]);
+    let stderr = String::from_utf8_lossy(&normal.stderr);
+    assert!(stderr.contains("files from workspace"), "{}", stderr);
+    assert!(!String::from_utf8_lossy(&normal.stdout).contains("[DEBUG nodes]"));
+
+    // -q drops progress but keeps the parse warnings
+    let quiet = run(&["-q"]);
+    let stderr = String::from_utf8_lossy(&quiet.stderr);
+    assert!(!stderr.contains("Loaded"), "{}", stderr);
+    assert!(stderr.contains("broken.rs:1:"), "{}", stderr);
+
+    let verbose = run(&["-v", "--debug-nodes"]);
+    assert!(String::from_utf8_lossy(&verbose.stderr).contains("Using storage backend: mem"));
+    assert!(String::from_utf8_lossy(&verbose.stdout).contains("==== [DEBUG nodes] ===="));
+}
+
+#[test]
+fn snippets_from_stdin_and_eval() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--no-config", "-q", "--input", "-", "--output", "-", "--format", "json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"fn main() { helper(); }\nfn helper() {}\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    let graph: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(graph["edges"][0]["from"], "snippet::main");
+    assert_eq!(graph["edges"][0]["to"], "snippet::helper");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--no-config", "-q", "--eval", "fn main() { run(); } fn run() {}", "-o", "-"])
+        .output()
+        .unwrap();
+    let dot = String::from_utf8_lossy(&out.stdout);
+    assert!(dot.starts_with("digraph"), "{}", dot);
+    assert!(dot.contains("\"snippet::main\" -> \"snippet::run\""), "{}", dot);
+
+    let code = "pub fn a() { b(); }\nfn b() {}\n";
+    let sources = SourceSet::new(vec![SourceFile::new("lib", "<eval>", code)]);
+    let graph = analyze(&AnalysisConfig::from_sources(sources)).unwrap();
+    assert!(graph.nodes.iter().any(|n| n.id == "lib::a" && n.calls("lib::b")));
+}
+
+#[test]
+fn graph_on_stdout_keeps_notes_on_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = dir.path().join("graph.tcg");
+    let run = |flags: &[&std::ffi::OsStr]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+            .args(["--no-config", "--eval", "fn main() { run(); } fn run() {}", "--output", "-"])
+            .args(flags)
+            .output()
+            .unwrap()
+    };
+
+    let out = run(&["--save-graph".as_ref(), snapshot.as_os_str()]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.starts_with("digraph") && stdout.trim_end().ends_with('}'), "{}", stdout);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Graph snapshot saved to"));
+    assert!(snapshot.is_file());
+
+    let clash = run(&["--unreachable".as_ref(), "-".as_ref()]);
+    assert!(!clash.status.success());
+    assert!(String::from_utf8_lossy(&clash.stderr).contains("both write to stdout"));
+    let clash = run(&["--panic-paths".as_ref(), "main".as_ref()]);
+    assert!(String::from_utf8_lossy(&clash.stderr).contains("--panic-paths both write to stdout"));
+
+    // Path queries print next to the exported graph instead of replacing it
+    let dot = dir.path().join("graph.dot");
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--no-config", "--eval", "fn main() { run(); } fn run() {}", "--path-from", "main", "--path-to", "run", "--output"])
+        .arg(&dot)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Path 1:"), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(fs::read_to_string(&dot).unwrap().starts_with("digraph"));
+}
+
+#[test]
+fn around_resolves_its_center_after_filtering() {
+    let around = |flags: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+            .args(["--no-config", "-q", "--eval", "mod a { pub fn run() {} }\nmod b { pub fn run() {} }\nfn main() { a::run(); b::run(); }", "--output", "-", "--around", "run"])
+            .args(flags)
+            .output()
+            .unwrap()
+    };
+
+    // Excluding one `run` leaves the other as the only match
+    let out = around(&["--exclude", "*::b::*"]);
+    let dot = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(dot.contains("a::run") && !dot.contains("b::run"), "{}", dot);
+
+    let out = around(&["--exclude", "*::run"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--around no function matches 'run'"), "{}", String::from_utf8_lossy(&out.stderr));
+}
+
+#[test]
+fn find_subcommand_lists_matches() {
+    let find = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+            .args(["--no-config", "-q", "--eval", "fn main() { send_all(); }\nfn send_all() {}\nfn recv() {}\n", "find"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let out = find(&["send"]);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "snippet::send_all  snippet  <eval>:2\n");
+
+    let out = find(&["--regex", "::(main|recv)$", "--format", "json"]);
+    let found: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let ids: Vec<&str> = found.as_array().unwrap().iter().map(|m| m["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["snippet::main", "snippet::recv"]);
+
+    let missing = find(&["sendall"]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("snippet::send_all"));
+}
+
+#[test]
+fn impact_subcommand_follows_changes_to_their_callers() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    let main = "fn main() {\n    handle();\n}\nfn handle() {\n    send();\n}\nfn send() {\n    let n = 1;\n}\nfn other() {}\n";
+    fs::write(dir.path().join("src/main.rs"), main).unwrap();
+    let git = |args: &[&str]| {
+        let out = std::process::Command::new("git").current_dir(dir.path()).args(args).output().unwrap();
+        assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-q", "-m", "init"]);
+    fs::write(dir.path().join("src/main.rs"), main.replace("let n = 1;", "let n = 2;") + "mod extra;\n").unwrap();
+    // Untracked files count as added
+    fs::write(dir.path().join("src/extra.rs"), "pub fn helper() {}\n").unwrap();
+
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--no-config", "-q", "--workspace"])
+        .arg(dir.path())
+        .args(["impact", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(report["since"], "HEAD");
+    assert_eq!(report["changed"], serde_json::json!(["app::extra::helper", "app::send"]));
+    assert_eq!(report["affected"], serde_json::json!([{ "id": "app::handle", "distance": 1 }, { "id": "app::main", "distance": 2 }]));
+    assert_eq!(report["entry_points"], serde_json::json!(["app::extra::helper", "app::main"]));
+
+    let bad = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--no-config", "-q", "--workspace"])
+        .arg(dir.path())
+        .args(["impact", "--since", "no-such-rev"])
+        .output()
+        .unwrap();
+    assert!(!bad.status.success());
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("failed to diff against no-such-rev"));
+}
+
+#[test]
+fn git_checkout_finds_nested_workspace() {
+    use mr_hedgehog::infrastructure::git::{self, GitSource};
+    use mr_hedgehog::infrastructure::project_loader::ProjectLoader;
+
+    let repo = tempfile::tempdir().unwrap();
+    fs::create_dir_all(repo.path().join("rust/src")).unwrap();
+    fs::write(repo.path().join("rust/Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::write(repo.path().join("rust/src/main.rs"), "fn main() { run(); }\nfn run() {}\n").unwrap();
+    let git = |args: &[&str]| {
+        let out = std::process::Command::new("git").current_dir(repo.path()).args(args).output().unwrap();
+        assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-q", "-m", "init"]);
+    git(&["tag", "v1"]);
+    fs::write(repo.path().join("rust/src/main.rs"), "fn main() {}\n").unwrap();
+    git(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-q", "-am", "drop run"]);
+
+    let checkouts = tempfile::tempdir().unwrap();
+    let url = format!("file://{}", repo.path().display());
+    let graph = |spec: &str| {
+        let dir = git::checkout(&GitSource::parse(spec), checkouts.path()).unwrap();
+        let manifest = ProjectLoader::find_manifest(&dir).expect("manifest");
+        assert!(manifest.ends_with("rust/Cargo.toml"), "{}", manifest.display());
+        analyze(&AnalysisConfig::new(manifest)).unwrap()
+    };
+    assert!(graph(&url).nodes.iter().all(|n| n.id != "app::run"));
+    let tagged = graph(&format!("{}#v1", url));
+    assert!(tagged.nodes.iter().any(|n| n.id == "app::main" && n.calls("app::run")));
+    assert!(git::checkout(&GitSource::parse(&format!("{}#no-such-rev", url)), checkouts.path()).is_err());
+}
+
+#[test]
+fn query_subcommand_evaluates_set_expressions() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = "fn main() { send(); }\nfn send() { unsafe { raw() } }\nunsafe fn raw() {}\nfn unused() { send(); }\n";
+    let query = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+            .args(["--no-config", "-q", "--eval", source, "query"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let out = query(&["reach(main) & calls(unsafe)"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "snippet::send  snippet  <eval>:2\n");
+
+    let export = dir.path().join("callers.json");
+    let out = query(&["callers(raw) - main", "--export", export.to_str().unwrap()]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let graph: serde_json::Value = serde_json::from_str(&fs::read_to_string(&export).unwrap()).unwrap();
+    let ids: Vec<&str> = graph["nodes"].as_array().unwrap().iter().map(|n| n["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["snippet::send", "snippet::unused"]);
+    assert_eq!((graph["edges"][0]["from"].as_str(), graph["edges"][0]["to"].as_str()), (Some("snippet::unused"), Some("snippet::send")));
+    let out = query(&["callers(raw) - main", "--export", "-"]);
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("digraph"), "{}", String::from_utf8_lossy(&out.stderr));
+
+    let bad = query(&["reach(main"]);
+    assert!(!bad.status.success());
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("expected ')' at column 11"));
+}
+
+#[test]
+fn lsp_subcommand_answers_call_hierarchy() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn leaf() {}\nfn helper() { leaf(); }\nfn main() { helper(); }\n").unwrap();
+    let root = format!("file://{}", dir.path().display());
+    let item = serde_json::json!({ "name": "helper", "data": { "id": "app::helper" } });
+    let messages = [
+        serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "rootUri": root } }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/prepareCallHierarchy",
+            "params": { "textDocument": { "uri": format!("{}/src/main.rs", root) }, "position": { "line": 2, "character": 13 } } }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 3, "method": "callHierarchy/outgoingCalls", "params": { "item": item } }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+        serde_json::json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ];
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .current_dir(dir.path())
+        .args(["--no-config", "-q", "--workspace", "Cargo.toml", "lsp"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for message in messages {
+        let body = message.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    drop(stdin);
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let replies: Vec<serde_json::Value> = stdout.split("Content-Length: ").skip(1)
+        .map(|frame| serde_json::from_str(frame.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect();
+    assert_eq!(replies.len(), 4, "{}", stdout);
+    assert_eq!(replies[1]["result"][0]["detail"], "app::helper", "{}", stdout);
+    assert_eq!(replies[1]["result"][0]["uri"], format!("{}/src/main.rs", root));
+    assert_eq!(replies[2]["result"][0]["to"]["detail"], "app::leaf");
+    assert_eq!(replies[2]["result"][0]["fromRanges"][0]["start"]["line"], 1);
+}
+
+#[test]
+fn trace_subcommand_enumerates_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn leaf() {}\nfn helper() { leaf(); }\nfn main() { helper(); }\n").unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+    let out = dir.path().join("trace.json");
+
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--workspace".as_ref(), manifest.as_os_str(), "trace".as_ref(), "--entry".as_ref(), "helper".as_ref()])
+        .args(["--format", "json", "--output"]).arg(&out)
+        .status().unwrap();
+    assert!(status.success());
+
+    let traces: serde_json::Value = serde_json::from_str(&fs::read_to_string(&out).unwrap()).unwrap();
+    assert_eq!(traces[0]["entry"], "app::helper");
+    let steps: Vec<&str> = traces[0]["paths"][0]["steps"].as_array().unwrap()
+        .iter().map(|s| s["id"].as_str().unwrap()).collect();
+    assert_eq!(steps, vec!["app::helper", "app::leaf"]);
+
+    // --max-depth cuts the path after the entry point
+    let shallow = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--workspace".as_ref(), manifest.as_os_str(), "trace".as_ref(), "--max-depth".as_ref(), "1".as_ref()])
+        .output().unwrap();
+    let stdout = String::from_utf8_lossy(&shallow.stdout);
+    assert!(stdout.contains("[0] app::main"), "{}", stdout);
+    assert!(!stdout.contains("app::leaf"), "{}", stdout);
+    // --signatures shows each step's signature
+    let signed = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--workspace".as_ref(), manifest.as_os_str(), "--signatures".as_ref(), "trace".as_ref(), "--entry".as_ref(), "helper".as_ref()])
+        .output().unwrap();
+    let stdout = String::from_utf8_lossy(&signed.stdout);
+    assert!(stdout.contains("[0] app::helper: fn helper() ("), "{}", stdout);
+}
+
+#[test]
+fn module_view_weights_calls_between_modules() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    let main_rs = "mod net {\n    pub fn send() { super::log(); }\n    pub fn recv() {}\n}\nfn log() {}\nfn main() { net::send(); net::recv(); log(); }\n";
+    fs::write(dir.path().join("src/main.rs"), main_rs).unwrap();
+    let manifest = dir.path().join("Cargo.toml");
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--workspace".as_ref(), manifest.as_os_str(), "--view".as_ref(), "modules".as_ref(), "--output".as_ref(), "-".as_ref()])
+        .output().unwrap();
+    assert!(output.status.success());
+    let dot = String::from_utf8_lossy(&output.stdout);
+    assert!(dot.contains("\"app\" -> \"app::net\" [label=\"2\""), "{}", dot);
+    assert!(dot.contains("\"app::net\" -> \"app\" [label=\"1\""), "{}", dot);
+    assert!(!dot.contains("app::main"), "{}", dot);
+
+    let json = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--workspace".as_ref(), manifest.as_os_str(), "--view".as_ref(), "crates".as_ref(), "--format".as_ref(), "json".as_ref()])
+        .arg("--output").arg(dir.path().join("modules.json"))
+        .output().unwrap();
+    assert!(!json.status.success());
+    assert!(String::from_utf8_lossy(&json.stderr).contains("only exported as DOT"));
+}
+
+#[test]
+fn test_map_lists_the_tests_reaching_each_function() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::create_dir_all(dir.path().join("tests")).unwrap();
+    let lib_rs = "pub fn parse() { lex(); }\nfn lex() {}\npub fn untested() {}\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn lexes() { super::lex(); }\n}\n";
+    fs::write(dir.path().join("src/lib.rs"), lib_rs).unwrap();
+    fs::write(dir.path().join("tests/it.rs"), "#[test]\nfn parses() { app::parse(); }\n").unwrap();
+    let map = dir.path().join("tests.json");
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--no-config", "-q", "--tests", "--workspace"])
+        .arg(dir.path())
+        .arg("--test-map").arg(&map)
+        .arg("--output").arg(dir.path().join("graph.dot"))
+        .output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&map).unwrap()).unwrap();
+    assert_eq!(report["functions"], serde_json::json!([
+        { "id": "app::lex", "tests": ["app::parses", "app::tests::lexes"] },
+        { "id": "app::parse", "tests": ["app::parses"] },
+    ]));
+    assert_eq!(report["untested"], serde_json::json!(["app::untested"]));
+}
+
+#[test]
+fn diff_subcommand_compares_two_trees() {
+    let dir = tempfile::tempdir().unwrap();
+    let write_tree = |name: &str, main_rs: &str| {
+        let root = dir.path().join(name);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+        fs::write(root.join("src/main.rs"), main_rs).unwrap();
+        root
+    };
+    let old = write_tree("old", "fn a() {}\nfn b() {}\nfn main() { a(); b(); }\n");
+    let new = write_tree("new", "fn a() {}\nfn c() {}\nfn main() { a(); c(); }\n");
+    let (report, dot) = (dir.path().join("diff.json"), dir.path().join("diff.dot"));
+
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .arg("diff").arg(&old).arg(&new)
+        .args(["--format", "json", "--output"]).arg(&report)
+        .arg("--dot").arg(&dot)
+        .status().unwrap();
+    assert!(status.success());
+
+    let diff: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(diff["added_nodes"], serde_json::json!(["app::c"]));
+    assert_eq!(diff["removed_nodes"], serde_json::json!(["app::b"]));
+    assert_eq!(diff["added_edges"], serde_json::json!([["app::main", "app::c"]]));
+    let dot = fs::read_to_string(&dot).unwrap();
+    assert!(dot.contains("\"app::main\" -> \"app::b\" [color=red"), "{}", dot);
+    assert!(dot.contains("\"app::c\" [label=\"app::c\", color=green3"), "{}", dot);
+}
+
+#[test]
+fn saved_graph_snapshot_is_reused_without_sources() {
+    let dir = tempfile::tempdir().unwrap();
+    let ws = dir.path().join("ws");
+    fs::create_dir_all(ws.join("src")).unwrap();
+    fs::write(ws.join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::write(ws.join("src/main.rs"), "fn helper() {}\nfn main() { helper(); }\n").unwrap();
+    let snapshot = dir.path().join("graph.tcg");
+
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .arg("--workspace").arg(ws.join("Cargo.toml"))
+        .arg("--output").arg(dir.path().join("graph.dot"))
+        .arg("--save-graph").arg(&snapshot)
+        .status().unwrap();
+    assert!(status.success());
+    // Snippets come from the snapshot once the sources are gone
+    fs::remove_dir_all(&ws).unwrap();
+
+    let trace = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .arg("--load-graph").arg(&snapshot)
+        .arg("trace")
+        .output().unwrap();
+    assert!(trace.status.success(), "{}", String::from_utf8_lossy(&trace.stderr));
+    let stdout = String::from_utf8_lossy(&trace.stdout);
+    assert!(stdout.contains("[1] app::helper"), "{}", stdout);
+    assert!(stdout.contains("Code: fn helper() {}"), "{}", stdout);
+}
+
+#[test]
+fn project_config_fills_in_unset_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn helper() {}\nfn main() { helper(); }\n").unwrap();
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog")).current_dir(dir.path()).args(args).output().unwrap()
+    };
+
+    assert!(run(&["init"]).status.success());
+    assert!(!run(&["init"]).status.success(), "init must not overwrite");
+    let config = dir.path().join("tracecraft.toml");
+    let scaffold = fs::read_to_string(&config).unwrap();
+    assert!(scaffold.contains("\nworkspace = \"Cargo.toml\""), "{}", scaffold);
+
+    // Inputs and output come from the file
+    fs::write(&config, scaffold.replace("path = \"callgraph.dot\"\nformat = \"dot\"", "path = \"graph.json\"\nformat = \"json\"")).unwrap();
+    let out = run(&[]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let graph: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.path().join("graph.json")).unwrap()).unwrap();
+    assert!(graph["nodes"].as_array().unwrap().iter().any(|n| n["id"] == "app::helper"));
+
+    // Flags win over the file
+    assert!(run(&["--format", "dot", "--output", "cli.dot"]).status.success());
+    assert!(fs::read_to_string(dir.path().join("cli.dot")).unwrap().starts_with("digraph"));
+    assert!(!run(&["--no-config"]).status.success());
+
+    fs::write(&config, "[output]\nformats = \"dot\"\n").unwrap();
+    let bad = run(&[]);
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("failed to read config"), "{}", String::from_utf8_lossy(&bad.stderr));
+}
+
+#[test]
+fn one_run_writes_several_outputs() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog")).current_dir(dir.path()).arg("--no-config").args(args).output().unwrap()
+    };
+    let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+
+    // Formats follow the extensions
+    assert!(run(&["--workspace", "Cargo.toml", "-o", "a.dot", "-o", "a.json", "-o", "a.html"]).status.success());
+    assert!(read("a.dot").starts_with("digraph"));
+    assert!(serde_json::from_str::<serde_json::Value>(&read("a.json")).is_ok());
+    assert!(read("a.html").contains("<html"));
+    assert!(run(&["--workspace", "Cargo.toml", "-o", "single.json"]).status.success());
+    assert!(read("single.json").starts_with('{'));
+    assert!(run(&["--workspace", "Cargo.toml", "-o", "forced.json", "--format", "dot"]).status.success());
+    assert!(read("forced.json").starts_with("digraph"));
+
+    // One output, several formats: the extension is swapped per format
+    fs::create_dir(dir.path().join("out")).unwrap();
+    assert!(run(&["--workspace", "Cargo.toml", "-o", "out/b", "--format", "dot,json"]).status.success());
+    assert!(read("out/b.dot").starts_with("digraph"));
+    assert!(read("out/b.json").starts_with('{'));
+
+    let mismatch = run(&["--workspace", "Cargo.toml", "-o", "c.dot", "-o", "c.json", "--format", "dot,json,html"]);
+    assert!(String::from_utf8_lossy(&mismatch.stderr).contains("2 outputs but 3 formats"), "{}", String::from_utf8_lossy(&mismatch.stderr));
+}
+
+#[test]
+fn default_entry_is_main_not_a_module_starting_with_main() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    write("Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n");
+    write("src/lib.rs", "pub mod a;\n");
+    write("src/a/mod.rs", "pub mod mainline;\n");
+    write("src/a/mainline.rs", "pub fn go() {}\n");
+    write("src/main.rs", "fn main() { run(); }\nfn run() {}\n");
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .args(["--no-config".as_ref(), "-q".as_ref(), "--workspace".as_ref(), dir.path().join("Cargo.toml").as_os_str(), "trace".as_ref()])
+        .output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[0] app::main"), "{}", stdout);
+    assert!(!stdout.contains("mainline"), "{}", stdout);
+}
+
+#[test]
+fn external_stubs_are_opt_in() {
+    let export = |flags: &[&str]| {
+        let out = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+            .args(["--no-config", "-q", "--eval", "fn main() { serde_json::from_str(\"\"); }", "-o", "-", "--format", "json"])
+            .args(flags)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        let graph: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+        graph["nodes"].as_array().unwrap().iter()
+            .map(|n| (n["id"].as_str().unwrap().to_string(), n["external"].as_str().map(String::from)))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(export(&[]), vec![("snippet::main".to_string(), None)]);
+    let stubbed = export(&["--include-external"]);
+    assert_eq!(stubbed[0], ("snippet::main".to_string(), None));
+    assert_eq!(stubbed[1].1.as_deref(), Some("serde_json"));
+    let collapsed = export(&["--include-external", "--collapse-external", "*"]);
+    assert_eq!(collapsed[1], ("serde_json::*".to_string(), Some("serde_json".to_string())));
+}
+
+#[test]
+fn given_features_keep_the_default_ones() {
+    use mr_hedgehog::domain::cfg::CfgOptions;
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[features]\ndefault = [\"std\"]\nstd = []\ntls = []\n").unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "#[cfg(feature = \"std\")]\npub fn with_std() {}\n#[cfg(feature = \"tls\")]\npub fn with_tls() {}\n").unwrap();
+
+    let ids = |no_default_features: bool| {
+        let cfg = CfgOptions { features: Some(["tls".to_string()].into()), no_default_features, ..CfgOptions::default() };
+        let graph = analyze(&AnalysisConfig { cfg, ..AnalysisConfig::new(dir.path()) }).unwrap();
+        let mut ids: Vec<String> = graph.nodes.into_iter().map(|n| n.id.to_string()).collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(ids(false), vec!["app::with_std", "app::with_tls"]);
+    assert_eq!(ids(true), vec!["app::with_tls"]);
+}
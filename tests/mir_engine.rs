@@ -0,0 +1,24 @@
+//! The `--mir` backend end to end. It needs the nightly driver, so it only
+//! runs on request:
+//! `cargo +nightly install --path mir_driver && cargo test --features mir -- --ignored`
+#![cfg(feature = "mir")]
+
+use std::fs;
+
+use mr_hedgehog::infrastructure::mir_builder::MirCallGraphBuilder;
+
+#[test]
+#[ignore = "needs a nightly toolchain with rustc-dev and mr_hedgehog_mir_driver on PATH"]
+fn mir_driver_resolves_trait_calls_to_the_impl() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "trait Op { fn apply(&self) -> i32; }\nstruct Add;\nimpl Op for Add {\n    fn apply(&self) -> i32 { helper() }\n}\nfn helper() -> i32 { 1 }\nfn run<T: Op>(op: &T) -> i32 { op.apply() }\nfn main() { run(&Add); }\n").unwrap();
+
+    let graph = MirCallGraphBuilder::new(dir.path()).build().unwrap();
+    let callees = |id: &str| graph.nodes.iter().find(|n| n.id == id)
+        .unwrap_or_else(|| panic!("no {} in {:?}", id, graph.nodes.iter().map(|n| &n.id).collect::<Vec<_>>()))
+        .callee_ids().map(String::from).collect::<Vec<_>>();
+    assert!(callees("app::main").contains(&"app::run".to_string()));
+    assert!(callees("Add::apply@app").contains(&"app::helper".to_string()));
+}
@@ -1,5 +1,5 @@
-/// Phase 2 Verification Tests: SCIP Engine
-/// Tests the enclosing-range algorithm in ScipIngestor.
+//! Phase 2 Verification Tests: SCIP Engine
+//! Tests the enclosing-range algorithm in ScipIngestor.
 
 use mr_hedgehog::domain::scip_ingest::ScipIngestor;
 use tempfile::tempdir;
@@ -47,11 +47,11 @@ fn test_enclosing_range_basic() {
     // Setup: main function (lines 10-20) contains a call to target at line 15
     let index = create_mock_scip_index(
         vec![
-            ("pkg::main", 10, 0, 20, 0),    // main function definition
-            ("pkg::target", 25, 0, 30, 0),  // target function definition
+            ("pkg . . . main().", 10, 0, 20, 0),    // main function definition
+            ("pkg . . . target().", 25, 0, 30, 0),  // target function definition
         ],
         vec![
-            ("pkg::target", 15, 5, 20),  // Reference to target inside main (line 15)
+            ("pkg . . . target().", 15, 5, 20),  // Reference to target inside main (line 15)
         ],
     );
 
@@ -67,12 +67,12 @@ fn test_enclosing_range_basic() {
     let graph = result.unwrap();
 
     // Assert: main node should have target as callee
-    let main_node = graph.nodes.iter().find(|n| n.id == "pkg::main");
+    let main_node = graph.nodes.iter().find(|n| n.id == "pkg . . . main().");
     assert!(main_node.is_some(), "main node not found");
     
     let main_node = main_node.unwrap();
     assert!(
-        main_node.callees.contains(&"pkg::target".to_string()),
+        main_node.calls("pkg . . . target()."),
         "main should call target. Callees: {:?}", main_node.callees
     );
 }
@@ -85,10 +85,10 @@ fn test_reference_outside_function_ignored() {
     // Setup: reference at line 5 is BEFORE any function definition
     let index = create_mock_scip_index(
         vec![
-            ("pkg::main", 10, 0, 20, 0),  // main function starts at line 10
+            ("pkg . . . main().", 10, 0, 20, 0),  // main function starts at line 10
         ],
         vec![
-            ("pkg::global_const", 5, 0, 10),  // Reference outside any function
+            ("pkg . . . global_const().", 5, 0, 10),  // Reference outside any function
         ],
     );
 
@@ -100,13 +100,13 @@ fn test_reference_outside_function_ignored() {
     assert!(result.is_ok());
 
     let graph = result.unwrap();
-    let main_node = graph.nodes.iter().find(|n| n.id == "pkg::main");
+    let main_node = graph.nodes.iter().find(|n| n.id == "pkg . . . main().");
     assert!(main_node.is_some());
     
     // The reference at line 5 should NOT be linked to main (it's before main starts)
     let main_node = main_node.unwrap();
     assert!(
-        !main_node.callees.contains(&"pkg::global_const".to_string()),
+        !main_node.calls("pkg . . . global_const()."),
         "main should NOT call global_const (reference is outside). Callees: {:?}", main_node.callees
     );
 }
@@ -119,12 +119,12 @@ fn test_nested_functions() {
     // Setup: inner function inside outer, call inside inner
     let index = create_mock_scip_index(
         vec![
-            ("pkg::outer", 10, 0, 30, 0),   // outer function
-            ("pkg::inner", 15, 0, 25, 0),   // inner function (nested)
-            ("pkg::target", 40, 0, 45, 0),  // target function
+            ("pkg . . . outer().", 10, 0, 30, 0),   // outer function
+            ("pkg . . . inner().", 15, 0, 25, 0),   // inner function (nested)
+            ("pkg . . . target().", 40, 0, 45, 0),  // target function
         ],
         vec![
-            ("pkg::target", 20, 5, 20),  // Call at line 20, inside inner
+            ("pkg . . . target().", 20, 5, 20),  // Call at line 20, inside inner
         ],
     );
 
@@ -143,7 +143,7 @@ fn test_nested_functions() {
     // This means outer will match first. This is a known limitation.
     // For now, we just verify SOME caller is linked.
     
-    let has_edge = graph.nodes.iter().any(|n| n.callees.contains(&"pkg::target".to_string()));
+    let has_edge = graph.nodes.iter().any(|n| n.calls("pkg . . . target()."));
     assert!(has_edge, "Expected at least one caller to target");
 }
 
@@ -155,10 +155,10 @@ fn test_self_reference_ignored() {
     // Setup: main references itself (recursive call)
     let index = create_mock_scip_index(
         vec![
-            ("pkg::main", 10, 0, 20, 0),
+            ("pkg . . . main().", 10, 0, 20, 0),
         ],
         vec![
-            ("pkg::main", 15, 5, 10),  // Self-reference
+            ("pkg . . . main().", 15, 5, 10),  // Self-reference
         ],
     );
 
@@ -170,11 +170,45 @@ fn test_self_reference_ignored() {
     assert!(result.is_ok());
 
     let graph = result.unwrap();
-    let main_node = graph.nodes.iter().find(|n| n.id == "pkg::main").unwrap();
+    let main_node = graph.nodes.iter().find(|n| n.id == "pkg . . . main().").unwrap();
     
     // Self-references should be filtered out
     assert!(
-        !main_node.callees.contains(&"pkg::main".to_string()),
+        !main_node.calls("pkg . . . main()."),
         "Self-references should be ignored"
     );
 }
+
+#[test]
+fn test_cli_scip_flag_exports_graph() {
+    let dir = tempdir().unwrap();
+    let scip_path = dir.path().join("index.scip");
+    let out_path = dir.path().join("graph.json");
+
+    let index = create_mock_scip_index(
+        vec![
+            ("pkg . . . main().", 10, 0, 20, 0),
+            ("pkg . . . target().", 25, 0, 30, 0),
+        ],
+        vec![
+            ("pkg . . . target().", 15, 5, 20),
+        ],
+    );
+    let bytes = index.write_to_bytes().unwrap();
+    File::create(&scip_path).unwrap().write_all(&bytes).unwrap();
+
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_mr_hedgehog"))
+        .arg("--scip").arg(&scip_path)
+        .arg("--output").arg(&out_path)
+        .arg("--format").arg("json")
+        .output()
+        .unwrap();
+    assert!(status.status.success(), "stderr: {}", String::from_utf8_lossy(&status.stderr));
+
+    let dto: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&out_path).unwrap()).unwrap();
+    let edges = dto["edges"].as_array().unwrap();
+    assert!(
+        edges.iter().any(|e| e["from"] == "pkg . . . main()." && e["to"] == "pkg . . . target()."),
+        "edges: {:?}", edges
+    );
+}
@@ -1,7 +1,7 @@
-/// Phase 3 Python Support Verification Tests
-/// 
-/// Tests the multi-language SCIP infrastructure without requiring
-/// external tools (scip-python, rust-analyzer) to be installed.
+//! Phase 3 Python Support Verification Tests
+//! 
+//! Tests the multi-language SCIP infrastructure without requiring
+//! external tools (scip-python, rust-analyzer) to be installed.
 
 use mr_hedgehog::domain::language::Language;
 use mr_hedgehog::infrastructure::scip_runner::build_command_spec;
@@ -60,3 +60,41 @@ fn test_ipc_server_lifecycle() {
     // Better to NOT send shutdown here to be safe, just close connection.
     // server.rs `handle_connection` loop breaks on connection close.
 }
+
+#[test]
+fn test_http_api_serves_graph() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use mr_hedgehog::api::http::{serve_on, GraphService};
+    use mr_hedgehog::domain::source::{SourceFile, SourceSet};
+    use mr_hedgehog::infrastructure::SimpleCallGraphBuilder;
+    use mr_hedgehog::ports::CallGraphBuilder;
+
+    let code = "fn main() { helper(); }\nfn helper() {}\n";
+    let files = SourceSet::from(vec![SourceFile::new("app", "src/main.rs", code)]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&files);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let service = Arc::new(GraphService::new(cg, &files));
+    thread::spawn(move || serve_on(listener, service));
+
+    let get = |target: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let response = get("/callers/helper");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.contains("\"from\":\"app::main\""), "{}", response);
+
+    let response = get("/trace?from=main");
+    assert!(response.contains("\"id\":\"app::helper\""), "{}", response);
+
+    let response = get("/node/missing");
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+}
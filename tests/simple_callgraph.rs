@@ -1,3 +1,9 @@
+use mr_hedgehog::domain::callgraph::{EdgeKind, TypeKind};
+use std::sync::Arc;
+
+use mr_hedgehog::domain::callgraph::CallGraph;
+use mr_hedgehog::domain::source::{SourceFile, SourceSet};
+use mr_hedgehog::infrastructure::analysis_cache::AnalysisCache;
 use mr_hedgehog::infrastructure::SimpleCallGraphBuilder;
 use mr_hedgehog::ports::CallGraphBuilder;
 
@@ -12,17 +18,857 @@ fn node_ids_include_crate_names() {
         fn baz() {}
     "#;
 
-    let sources = vec![
-        ("crate_one".to_string(), "lib.rs".to_string(), crate_one.to_string()),
-        ("crate_two".to_string(), "lib.rs".to_string(), crate_two.to_string()),
-    ];
+    let sources = SourceSet::from(vec![
+        SourceFile::new("crate_one", "lib.rs", crate_one),
+        SourceFile::new("crate_two", "lib.rs", crate_two),
+    ]);
 
     let builder = SimpleCallGraphBuilder::new();
     let cg = builder.build_call_graph(&sources);
-    let mut ids: Vec<String> = cg.nodes.iter().map(|n| n.id.clone()).collect();
+    let mut ids: Vec<String> = cg.nodes.iter().map(|n| n.id.to_string()).collect();
     ids.sort();
 
     assert!(ids.contains(&"crate_one::foo".to_string()), "Expected foo, found: {:?}", ids);
     assert!(ids.contains(&"crate_one::bar".to_string()), "Expected bar, found: {:?}", ids);
     assert!(ids.contains(&"crate_two::baz".to_string()), "Expected baz, found: {:?}", ids);
 }
+
+#[test]
+fn calls_resolve_across_crates() {
+    let crate_one = r#"
+        pub fn shared() {}
+        pub fn foo() {}
+        fn bar() { foo(); }
+    "#;
+    let crate_two = r#"
+        fn foo() {}
+        fn baz() { shared(); crate_one::foo(); foo(); }
+    "#;
+
+    let sources = SourceSet::from(vec![
+        SourceFile::new("crate_one", "one/lib.rs", crate_one),
+        SourceFile::new("crate_two", "two/lib.rs", crate_two),
+    ]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let callees = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap().callee_ids().map(String::from).collect::<Vec<_>>();
+
+    // Same-crate call links to the real node id
+    assert_eq!(callees("crate_one::bar"), vec!["crate_one::foo".to_string()]);
+
+    // Unique name in another crate, explicit crate path, and same-crate preference
+    let baz = callees("crate_two::baz");
+    assert_eq!(baz, vec![
+        "crate_one::shared".to_string(),
+        "crate_one::foo".to_string(),
+        "crate_two::foo".to_string(),
+    ]);
+}
+
+#[test]
+fn calls_follow_use_imports_and_reexports() {
+    // Mirrors test_advanced_ws: bin_demo calls lib_base functions through lib_derive aliases
+    let lib_base = r#"
+        pub fn base_fn<T>(val: T) -> T { val }
+        pub mod util_mod {
+            pub fn util_fn() {}
+        }
+        pub use base_fn as alias_base_fn;
+        pub use util_mod::util_fn as alias_util_fn;
+    "#;
+    let lib_derive = r#"
+        pub use lib_base::alias_base_fn as super_base_fn;
+        pub use lib_base::alias_util_fn as super_util_fn;
+    "#;
+    let other = r#"
+        pub fn super_util_fn() {}
+    "#;
+    let bin_demo = r#"
+        use lib_derive::{super_base_fn, super_util_fn};
+        fn main() {
+            super_base_fn(123);
+            super_util_fn();
+        }
+    "#;
+
+    let sources = SourceSet::from(vec![
+        SourceFile::new("lib_base", "lib_base/src/lib.rs", lib_base),
+        SourceFile::new("lib_derive", "lib_derive/src/lib.rs", lib_derive),
+        SourceFile::new("other", "other/src/lib.rs", other),
+        SourceFile::new("bin_demo", "bin_demo/src/main.rs", bin_demo),
+    ]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let main = cg.nodes.iter().find(|n| n.id == "bin_demo::main").unwrap();
+    // `super_util_fn` exists in `other` too; the import disambiguates it
+    assert_eq!(main.callee_ids().collect::<Vec<_>>(), vec![
+        "lib_base::base_fn",
+        "lib_base::util_mod::util_fn",
+    ]);
+}
+
+#[test]
+fn imports_of_an_inline_module_stay_in_it() {
+    let code = r#"
+        mod wire { pub fn send() {} }
+        mod disk { pub fn send() {} }
+        mod net {
+            use crate::wire::send;
+            pub fn flush() { send(); }
+        }
+        mod db {
+            use crate::disk::send;
+            pub fn flush() { send(); }
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "src/lib.rs", code)]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let callees = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap().callee_ids().map(String::from).collect::<Vec<_>>();
+    assert_eq!(callees("app::net::flush"), vec!["app::wire::send"]);
+    assert_eq!(callees("app::db::flush"), vec!["app::disk::send"]);
+}
+
+#[test]
+fn trait_object_calls_link_to_every_impl() {
+    let code = r#"
+        trait Op {
+            fn apply(&self, x: i32) -> i32;
+        }
+        struct Add;
+        impl Op for Add {
+            fn apply(&self, x: i32) -> i32 { x + 1 }
+        }
+        struct Mul;
+        impl Op for Mul {
+            fn apply(&self, x: i32) -> i32 { x * 2 }
+        }
+        struct Other;
+        impl Other {
+            fn apply(&self, x: i32) -> i32 { x }
+        }
+        fn run(op: &dyn Op, x: i32) -> i32 { op.apply(x) }
+        fn run_boxed(op: Box<dyn Op>, x: i32) -> i32 { op.apply(x) }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    for caller in ["app::run", "app::run_boxed"] {
+        let mut callees = cg.nodes.iter().find(|n| n.id == caller).unwrap().callee_ids().map(String::from).collect::<Vec<_>>();
+        callees.sort();
+        // Only implementors of `Op`, not the unrelated `Other::apply`
+        assert_eq!(callees, vec!["Add::apply@app".to_string(), "Mul::apply@app".to_string()]);
+    }
+}
+
+#[test]
+fn trait_methods_link_to_their_implementations() {
+    let code = r#"
+        pub trait Op {
+            fn apply(&self, x: i32) -> i32;
+            fn twice(&self, x: i32) -> i32 { x }
+        }
+        struct Add;
+        impl Op for Add {
+            fn apply(&self, x: i32) -> i32 { x + 1 }
+        }
+        struct Other;
+        impl Other {
+            fn apply(&self, x: i32) -> i32 { x }
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let node = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap();
+    let apply = node("Op::apply@app");
+    assert!(apply.is_public);
+    assert_eq!(apply.signature.as_deref(), Some("fn apply(&self, x: i32) -> i32"));
+    let edges: Vec<(&str, EdgeKind)> = apply.callees.iter().map(|e| (e.target.as_str(), e.kind)).collect();
+    assert_eq!(edges, vec![("Add::apply@app", EdgeKind::Implements)]);
+    // Provided methods are nodes too; `Add` doesn't override this one
+    assert!(node("Op::twice@app").callees.is_empty());
+}
+
+#[test]
+fn dot_signature_labels_name_the_type() {
+    use mr_hedgehog::infrastructure::{DotExporter, DotStyle};
+    use mr_hedgehog::ports::OutputExporter;
+
+    let code = r#"
+        struct Add;
+        impl Add {
+            fn apply(&self, x: i32) -> i32 { x + 1 }
+        }
+        struct Mul;
+        impl Mul {
+            fn apply(&self, x: i32) -> i32 { x * 2 }
+        }
+        fn apply(x: i32) -> i32 { x }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+
+    let style = DotStyle { signatures: true, ..Default::default() };
+    let mut dot = Vec::new();
+    DotExporter { style, ..Default::default() }.export_to_writer(&cg, &mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+    // The same signature in two impls reads differently once qualified
+    assert!(dot.contains("\"Add::apply@app\" [label=\"fn Add::apply(&self, x: i32) -> i32\"]"), "{}", dot);
+    assert!(dot.contains("\"Mul::apply@app\" [label=\"fn Mul::apply(&self, x: i32) -> i32\"]"), "{}", dot);
+    assert!(dot.contains("\"app::apply\" [label=\"fn app::apply(x: i32) -> i32\"]"), "{}", dot);
+}
+
+#[test]
+fn implementations_link_to_the_trait_of_their_crate() {
+    let app = r#"
+        use other::Op as Operation;
+        trait Op {
+            fn apply(&self);
+        }
+        struct Add;
+        impl Op for Add {
+            fn apply(&self) {}
+        }
+        struct Mul;
+        impl Operation for Mul {
+            fn apply(&self) {}
+        }
+        struct Show;
+        impl std::fmt::Op for Show {
+            fn apply(&self) {}
+        }
+    "#;
+    let other = "pub trait Op {\n    fn apply(&self);\n}\n";
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", app), SourceFile::new("other", "lib.rs", other)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let node = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap();
+    let implementors = |id: &str| node(id).callee_ids().collect::<Vec<_>>();
+    assert_eq!(implementors("Op::apply@app"), vec!["Add::apply@app"]);
+    assert_eq!(implementors("Op::apply@other"), vec!["Mul::apply@app"]);
+    // Trait methods are as visible as their trait
+    assert!(!node("Op::apply@app").is_public);
+    assert!(node("Op::apply@other").is_public);
+}
+
+#[test]
+fn repeated_calls_are_weighted() {
+    let code = r#"
+        fn log() {}
+        fn run() { log(); log(); log(); }
+        fn once() { log(); }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let run = cg.nodes.iter().find(|n| n.id == "app::run").unwrap();
+    // Every call site keeps its edge; the weighted view folds them
+    assert_eq!(run.callees.len(), 3);
+    let weighted: Vec<(&str, usize)> = run.weighted_callees().iter().map(|(e, w)| (e.target.as_str(), *w)).collect();
+    assert_eq!(weighted, vec![("app::log", 3)]);
+
+    use mr_hedgehog::infrastructure::DotExporter;
+    use mr_hedgehog::ports::OutputExporter;
+    let mut dot = Vec::new();
+    DotExporter::default().export_to_writer(&cg, &mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+    assert_eq!(dot.matches("\"app::run\" -> \"app::log\"").count(), 1, "{}", dot);
+    assert!(dot.contains("\"app::run\" -> \"app::log\" [penwidth=2.6, weight=3, tooltip=\"3 calls\"];"), "{}", dot);
+    assert!(dot.contains("\"app::once\" -> \"app::log\";"), "{}", dot);
+}
+
+#[test]
+fn type_nodes_link_methods_fields_and_constructors() {
+    let code = r#"
+        /// Settings.
+        pub struct Config { mode: Mode, items: Vec<Item> }
+        enum Mode { Fast, Slow(Item) }
+        struct Item;
+        type Items = Vec<Item>;
+        impl Config {
+            fn new() -> Self { Self { mode: Mode::Fast, items: Vec::new() } }
+            fn len(&self) -> usize { self.items.len() }
+        }
+        fn make() -> Config { let c = Config::new(); Config { mode: Mode::Slow(Item), ..c } }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let plain = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    assert!(plain.nodes.iter().all(|n| n.type_kind.is_none()));
+
+    let cg = SimpleCallGraphBuilder::new().with_types().build_call_graph(&sources);
+    let node = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap_or_else(|| panic!("missing node {}", id));
+    let edges = |id: &str, kind: EdgeKind| -> Vec<&str> {
+        node(id).callees.iter().filter(|e| e.kind == kind).map(|e| e.target.as_str()).collect()
+    };
+    let config = node("Config@app");
+    assert_eq!(config.type_kind, Some(TypeKind::Struct));
+    assert_eq!((config.is_public, config.doc.as_deref(), config.location.as_deref()), (true, Some("Settings."), Some("lib.rs:3")));
+    assert_eq!(node("Mode@app").type_kind, Some(TypeKind::Enum));
+    assert_eq!(node("Items@app").type_kind, Some(TypeKind::Alias));
+
+    assert_eq!(edges("Config@app", EdgeKind::DefinesMethod), vec!["Config::new@app", "Config::len@app"]);
+    assert_eq!(edges("Config@app", EdgeKind::HasFieldOfType), vec!["Mode@app", "Item@app"]);
+    assert_eq!(edges("Mode@app", EdgeKind::HasFieldOfType), vec!["Item@app"]);
+    assert_eq!(edges("Items@app", EdgeKind::HasFieldOfType), vec!["Item@app"]);
+    assert_eq!(edges("Config::new@app", EdgeKind::Constructs), vec!["Config@app", "Mode@app"]);
+    assert_eq!(edges("app::make", EdgeKind::Constructs), vec!["Config@app", "Mode@app", "Item@app"]);
+    // Calls are unchanged, and traces don't walk into types
+    let calls: Vec<&str> = node("app::make").call_edges().map(|e| e.target.as_str()).collect();
+    assert!(calls.contains(&"Config::new@app") && !calls.contains(&"Config@app"), "{:?}", calls);
+}
+
+#[test]
+fn closures_become_nodes() {
+    let code = r#"
+        fn process(x: i32) -> i32 { x }
+        fn log() {}
+        fn foo(items: Vec<i32>) {
+            let out: Vec<i32> = items.into_iter().map(|x| process(x)).collect();
+            out.iter().for_each(|_| { log(); let inner = || process(1); });
+        }
+        struct S;
+        impl S {
+            fn run(&self) { let f = || log(); }
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let node = |id: &str| cg.nodes.iter().find(|n| n.id == id)
+        .unwrap_or_else(|| panic!("missing node {}", id));
+
+    let foo = node("app::foo");
+    let ids = |id: &str| node(id).callee_ids().collect::<Vec<_>>();
+    assert!(foo.calls("app::foo::{closure#0}"), "{:?}", foo.callees);
+    assert!(foo.calls("app::foo::{closure#1}"), "{:?}", foo.callees);
+    assert_eq!(ids("app::foo::{closure#0}"), vec!["app::process"]);
+
+    // Nested closure hangs off the closure that defines it
+    assert_eq!(ids("app::foo::{closure#1}"), vec!["app::log", "app::foo::{closure#2}"]);
+    assert_eq!(ids("app::foo::{closure#2}"), vec!["app::process"]);
+
+    // Method closures keep the `@crate` suffix
+    assert_eq!(ids("S::run::{closure#0}@app"), vec!["app::log"]);
+}
+
+#[test]
+fn nested_modules_functions_and_impls_get_qualified_nodes() {
+    let code = r#"
+        mod utils {
+            pub fn helper() {}
+            pub mod deep {
+                pub fn helper() {}
+            }
+            pub struct Tool;
+            impl Tool {
+                pub fn work(&self) { helper(); }
+            }
+        }
+        fn helper() {}
+        fn outer() {
+            fn inner() { helper(); }
+            inner();
+            utils::helper();
+            utils::deep::helper();
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let ids: Vec<&str> = cg.nodes.iter().map(|n| n.id.as_str()).collect();
+    for expected in ["app::utils::helper", "app::utils::deep::helper", "app::helper", "app::outer", "app::outer::inner", "utils::Tool::work@app"] {
+        assert!(ids.contains(&expected), "missing {} in {:?}", expected, ids);
+    }
+
+    let callees = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap().callee_ids().map(String::from).collect::<Vec<_>>();
+    assert_eq!(callees("app::outer"), vec![
+        "app::outer::inner".to_string(),
+        "app::utils::helper".to_string(),
+        "app::utils::deep::helper".to_string(),
+    ]);
+    // Bare names resolve to the caller's own module first
+    assert_eq!(callees("app::outer::inner"), vec!["app::helper".to_string()]);
+    assert_eq!(callees("utils::Tool::work@app"), vec!["app::utils::helper".to_string()]);
+}
+
+#[test]
+fn self_calls_link_to_the_enclosing_impl() {
+    let code = r#"
+        struct Parser;
+        impl Parser {
+            fn new() -> Self { Self::reset(); Parser }
+            fn reset() {}
+            fn helper(&self) {}
+            fn run(&self) { self.helper(); }
+        }
+        struct Lexer;
+        impl Lexer {
+            fn helper(&self) {}
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let callees = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap().callee_ids().map(String::from).collect::<Vec<_>>();
+    // Not fanned out to `Lexer::helper`, which only shares the name
+    assert_eq!(callees("Parser::run@app"), vec!["Parser::helper@app".to_string()]);
+    assert_eq!(callees("Parser::new@app"), vec!["Parser::reset@app".to_string()]);
+}
+
+#[test]
+fn field_receivers_resolve_through_struct_field_types() {
+    let code = r#"
+        struct Client;
+        impl Client { fn send(&self) {} }
+        struct Loader;
+        impl Loader { fn load(&self) {} }
+        struct Config { loader: Loader }
+        struct Cache;
+        impl Cache { fn send(&self) {} fn load(&self) {} }
+        struct App { client: Box<Client> }
+        impl App {
+            fn run(&self, config: &Config) {
+                self.client.send();
+                config.loader.load();
+            }
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let run = cg.nodes.iter().find(|n| n.id == "App::run@app").unwrap();
+    let callees: Vec<&str> = run.callee_ids().collect();
+    // Without field types both calls would also fan out to `Cache`
+    assert_eq!(callees, vec!["Client::send@app", "Loader::load@app"]);
+}
+
+#[test]
+fn let_bindings_take_the_constructed_type() {
+    let code = r#"
+        struct Server;
+        impl Server {
+            fn new() -> Self { Server }
+            fn run(&self) {}
+        }
+        struct Worker;
+        impl Worker { fn run(&self) {} }
+        fn main() {
+            let s = Server::new();
+            s.run();
+            let w: Worker = make_worker();
+            w.run();
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let main = cg.nodes.iter().find(|n| n.id == "app::main").unwrap();
+    let runs: Vec<&str> = main.callee_ids().filter(|id| id.contains("::run@")).collect();
+    assert_eq!(runs, vec!["Server::run@app", "Worker::run@app"]);
+}
+
+#[test]
+fn ufcs_calls_resolve_to_the_receiver_impl() {
+    let code = r#"
+        trait Shape { fn area(&self) -> f64; }
+        struct Square;
+        impl Shape for Square { fn area(&self) -> f64 { 1.0 } }
+        struct Circle;
+        impl Shape for Circle { fn area(&self) -> f64 { 3.14 } }
+        fn total(sq: &Square, any: &dyn Shape) {
+            Shape::area(sq);
+            <Circle as Shape>::area(&Circle);
+            Shape::area(any);
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let total = cg.nodes.iter().find(|n| n.id == "app::total").unwrap();
+    let edges: Vec<(&str, EdgeKind)> = total.callees.iter().map(|e| (e.target.as_str(), e.kind)).collect();
+    assert_eq!(edges, vec![
+        ("Square::area@app", EdgeKind::Call),
+        ("Circle::area@app", EdgeKind::Call),
+        // Receiver type unknown: every implementor
+        ("Square::area@app", EdgeKind::Dynamic),
+        ("Circle::area@app", EdgeKind::Dynamic),
+    ]);
+}
+
+#[test]
+fn spawned_tasks_get_spawn_edges() {
+    let code = r#"
+        async fn handle() {}
+        fn log() {}
+        async fn serve() {
+            tokio::spawn(async move {
+                handle().await;
+            });
+            tokio::spawn(handle());
+            log();
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let node = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap();
+    let serve = node("app::serve");
+    assert!(serve.is_async);
+    assert!(!node("app::log").is_async);
+
+    let spawned: Vec<&str> = serve.callees.iter().filter(|e| e.kind == EdgeKind::Spawn).map(|e| e.target.as_str()).collect();
+    assert_eq!(spawned, vec!["app::serve::{async#0}", "app::handle"]);
+    let task = node("app::serve::{async#0}");
+    assert!(task.is_async);
+    assert_eq!(task.callee_ids().collect::<Vec<_>>(), vec!["app::handle"]);
+    // Ordinary calls keep their kind
+    assert!(serve.callees.iter().any(|e| e.target == "app::log" && e.kind == EdgeKind::Call));
+}
+
+#[test]
+fn functions_passed_as_values_get_reference_edges() {
+    let code = r#"
+        fn process_item(x: &u32) -> u32 { *x }
+        fn on_event() {}
+        fn register(f: fn()) { f(); }
+        fn run(items: Vec<u32>) {
+            let count = 3;
+            items.iter().map(process_item);
+            register(on_event);
+            consume(count);
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let run = cg.nodes.iter().find(|n| n.id == "app::run").unwrap();
+    let refs: Vec<&str> = run.callees.iter().filter(|e| e.kind == EdgeKind::Reference).map(|e| e.target.as_str()).collect();
+    // Plain values that are not functions (`count`) produce no edges
+    assert_eq!(refs, vec!["app::process_item", "app::on_event"]);
+}
+
+#[test]
+fn generic_calls_are_devirtualized_from_call_sites() {
+    let code = r#"
+        trait Op { fn apply(&self); fn create() -> Self; }
+        struct Add;
+        struct Mul;
+        impl Op for Add { fn apply(&self) {} fn create() -> Self { Add } }
+        impl Op for Mul { fn apply(&self) {} fn create() -> Self { Mul } }
+        fn run<T: Op>(t: T) { t.apply(); }
+        fn make<T: Op>() -> T { T::create() }
+        fn forward<U: Op>(u: U) { run(u); }
+        fn main() {
+            let add = Add;
+            run(add);
+            make::<Add>();
+        }
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let node = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap();
+
+    let run = node("app::run");
+    assert_eq!(run.callees.len(), 1);
+    assert_eq!(run.callees[0].target, "Add::apply@app");
+    assert_eq!(run.callees[0].kind, EdgeKind::Inferred);
+
+    let make = node("app::make");
+    assert_eq!(make.callee_ids().collect::<Vec<_>>(), vec!["Add::create@app"]);
+    assert_eq!(make.callees[0].kind, EdgeKind::Inferred);
+
+    // `forward` is never instantiated, and `run(u)` does not pin `T` to `U`
+    let forward = node("app::forward");
+    assert_eq!(forward.callee_ids().collect::<Vec<_>>(), vec!["app::run"]);
+}
+
+#[test]
+fn external_calls_get_stub_nodes() {
+    use mr_hedgehog::domain::external::{add_external_stubs, collapse_external};
+    use mr_hedgehog::infrastructure::DotExporter;
+    use mr_hedgehog::ports::OutputExporter;
+
+    let code = r#"
+        fn main() {
+            let v = serde_json::from_str(input);
+            serde_json::to_string(&v);
+            tokio::spawn(work());
+        }
+        fn work() {}
+    "#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "lib.rs", code)]);
+    let cg = add_external_stubs(&SimpleCallGraphBuilder::new().build_call_graph(&sources));
+
+    let stub = |id: &str| cg.nodes.iter().find(|n| n.id == id).and_then(|n| n.external.as_deref());
+    assert_eq!(stub("serde_json::from_str@app"), Some("serde_json"));
+    assert_eq!(stub("tokio::spawn@app"), Some("tokio"));
+    assert_eq!(stub("app::work"), None);
+
+    let collapsed = collapse_external(&cg, &["serde_json".to_string()]);
+    let main = collapsed.nodes.iter().find(|n| n.id == "app::main").unwrap();
+    assert_eq!(main.callees.iter().filter(|e| e.target == "serde_json::*").count(), 1);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("graph.dot");
+    DotExporter::default().export(&collapsed, path.to_str().unwrap()).unwrap();
+    let dot = std::fs::read_to_string(path).unwrap();
+    assert!(dot.contains("subgraph \"cluster_external_serde_json\""), "{}", dot);
+    assert!(dot.contains("\"tokio::spawn@app\" [label=\"tokio::spawn\", style=dashed"), "{}", dot);
+}
+
+#[test]
+fn dot_export_clusters_by_crate_and_module() {
+    use mr_hedgehog::infrastructure::{DotClustering, DotExporter};
+    use mr_hedgehog::ports::OutputExporter;
+
+    let sources = SourceSet::from(vec![
+        SourceFile::new("app", "src/main.rs", "mod net { pub fn send() {} }\nfn main() { net::send(); util::helper(); }\n"),
+        SourceFile::new("util", "src/lib.rs", "pub struct Tool;\nimpl Tool { pub fn run(&self) {} }\npub fn helper() {}\n"),
+    ]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let dir = tempfile::tempdir().unwrap();
+    let export = |exporter: DotExporter| {
+        let path = dir.path().join("graph.dot");
+        exporter.export(&cg, path.to_str().unwrap()).unwrap();
+        std::fs::read_to_string(path).unwrap()
+    };
+
+    let by_crate = export(DotExporter { clustering: DotClustering::Crate, cluster_colors: vec!["red".to_string()], ..Default::default() });
+    assert!(by_crate.contains("subgraph \"cluster_app\" {"), "{}", by_crate);
+    assert!(by_crate.contains("subgraph \"cluster_util\" {"), "{}", by_crate);
+    assert_eq!(by_crate.matches("fillcolor=\"red\"").count(), 2);
+    assert!(by_crate.contains("        \"Tool::run@util\""), "{}", by_crate);
+    // Edges stay outside the boxes
+    assert!(by_crate.contains("\n    \"app::main\" -> \"app::net::send\";"), "{}", by_crate);
+
+    let by_module = export(DotExporter { clustering: DotClustering::Module, ..Default::default() });
+    assert!(by_module.contains("subgraph \"cluster_app::net\" {"), "{}", by_module);
+    assert!(by_module.contains("            \"app::net::send\""), "{}", by_module);
+
+    let flat = export(DotExporter::default());
+    assert!(!flat.contains("subgraph"), "{}", flat);
+}
+
+#[test]
+fn dot_style_options() {
+    use mr_hedgehog::infrastructure::{DotExporter, DotStyle};
+    use mr_hedgehog::ports::OutputExporter;
+
+    let code = "fn a_rather_long_function_name() {}\nfn main() {\n    a_rather_long_function_name();\n}\n";
+    let sources = SourceSet::from(vec![SourceFile::new("app", "src/main.rs", code)]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let style = DotStyle {
+        rankdir: Some("LR".to_string()),
+        node_shape: Some("box".to_string()),
+        font: Some("Helvetica".to_string()),
+        edge_colors: [(EdgeKind::Call, "red".to_string())].into(),
+        max_label_len: Some(12),
+        source_url: Some("vscode://file/{file}:{line}".to_string()),
+        links: Some(mr_hedgehog::domain::links::SourceLinks::new("https://github.com/org/repo/blob/abc", "/repo")),
+        signatures: false,
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("graph.dot");
+    DotExporter { style, ..Default::default() }
+        .export(&cg, path.to_str().unwrap()).unwrap();
+    let dot = std::fs::read_to_string(path).unwrap();
+
+    assert!(dot.contains("    rankdir=\"LR\";\n"), "{}", dot);
+    assert!(dot.contains("    node [shape=\"box\", fontname=\"Helvetica\"];\n"), "{}", dot);
+    assert!(dot.contains("label=\"app::a_rath…\""), "{}", dot);
+    assert!(dot.contains("color=\"red\""), "{}", dot);
+    assert!(dot.contains("URL=\"vscode://file/src/main.rs:3\""), "{}", dot);
+    assert!(dot.contains("[label=\"app::main\", URL=\"https://github.com/org/repo/blob/abc/src/main.rs#L2\"]"), "{}", dot);
+}
+
+#[test]
+fn coverage_colors_dot_nodes() {
+    use mr_hedgehog::domain::coverage::{annotate, CoverageData};
+    use mr_hedgehog::infrastructure::DotExporter;
+    use mr_hedgehog::ports::OutputExporter;
+
+    let code = "fn untested() {}\nfn main() {\n    untested();\n}\n";
+    let sources = SourceSet::from(vec![SourceFile::new("app", "src/main.rs", code)]);
+    let mut cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let lcov = "SF:/ci/app/src/main.rs\nFN:1,untested\nFN:2,main\nFNDA:0,untested\nFNDA:1,main\nend_of_record\n";
+    assert_eq!(annotate(&mut cg, &CoverageData::parse(lcov).unwrap()), 2);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("graph.dot");
+    DotExporter::default()
+        .export(&cg, path.to_str().unwrap()).unwrap();
+    let dot = std::fs::read_to_string(path).unwrap();
+    assert!(dot.contains("\"app::untested\" [label=\"app::untested\", style=filled, fillcolor=\"#f8d7da\"];"), "{}", dot);
+    assert!(dot.contains("\"app::main\" [label=\"app::main\", style=filled, fillcolor=\"#d4edda\"];"), "{}", dot);
+}
+
+#[test]
+fn edges_record_call_site_locations() {
+    let code = r#"trait Op { fn apply(&self); }
+struct Add;
+impl Op for Add { fn apply(&self) {} }
+fn helper() {}
+fn run(op: &dyn Op) {
+    helper();
+    op
+        .apply();
+}
+"#;
+    let sources = SourceSet::from(vec![SourceFile::new("app", "src/lib.rs", code)]);
+
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let run = cg.nodes.iter().find(|n| n.id == "app::run").unwrap();
+    assert_eq!(run.callees.len(), 2);
+
+    assert_eq!(run.callees[0].target, "app::helper");
+    assert_eq!(run.callees[0].location().as_deref(), Some("src/lib.rs:6"));
+    assert_eq!(run.callees[0].kind, EdgeKind::Call);
+
+    // Method calls point at the method name, not the start of the receiver
+    assert_eq!(run.callees[1].target, "Add::apply@app");
+    assert_eq!(run.callees[1].location().as_deref(), Some("src/lib.rs:8"));
+    assert_eq!(run.callees[1].kind, EdgeKind::Dynamic);
+}
+
+#[test]
+fn cached_rebuild_matches_fresh_build() {
+    let tmp = tempfile::tempdir().unwrap();
+    let lib = r#"
+        pub mod utils { pub fn helper() {} }
+        pub trait Op { fn apply(&self); }
+        pub struct Add;
+        impl Op for Add { fn apply(&self) { utils::helper(); } }
+    "#;
+    let main = r#"
+        use lib_a::{Op, utils::helper};
+        fn run(op: &dyn Op) { op.apply(); [1].iter().for_each(|_| helper()); }
+        fn main() { run(&lib_a::Add); }
+    "#;
+    let sources = SourceSet::from(vec![
+        SourceFile::new("lib_a", "lib_a/src/lib.rs", lib),
+        SourceFile::new("app", "app/src/main.rs", main),
+    ]);
+    let edges = |cg: &CallGraph| cg.nodes.iter()
+        .map(|n| (n.id.clone(), n.callees.clone()))
+        .collect::<Vec<_>>();
+
+    let fresh = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let cache = Arc::new(AnalysisCache::new(tmp.path()));
+    let cold = SimpleCallGraphBuilder::new().with_cache(cache.clone()).build_call_graph(&sources);
+    assert_eq!(cache.stats(), (0, 2));
+
+    let cache = Arc::new(AnalysisCache::new(tmp.path()));
+    let warm = SimpleCallGraphBuilder::new().with_cache(cache.clone()).build_call_graph(&sources);
+    assert_eq!(cache.stats(), (2, 0));
+    assert_eq!(edges(&cold), edges(&fresh));
+    assert_eq!(edges(&warm), edges(&fresh));
+
+    // Only the edited file is re-analyzed
+    let sources = SourceSet::from(vec![
+        sources.files()[0].clone(),
+        SourceFile::new("app", "app/src/main.rs", main.replace("fn main() {", "fn main() { lib_a::utils::helper();")),
+    ]);
+    let cache = Arc::new(AnalysisCache::new(tmp.path()));
+    let edited = SimpleCallGraphBuilder::new().with_cache(cache.clone()).build_call_graph(&sources);
+    assert_eq!(cache.stats(), (1, 1));
+    let main_node = edited.nodes.iter().find(|n| n.id == "app::main").unwrap();
+    assert!(main_node.calls("lib_a::utils::helper"), "{:?}", main_node.callees);
+}
+
+#[test]
+fn nodes_record_definition_locations() {
+    let code = "struct Tool;\nimpl Tool {\n    fn run(&self) {\n        let f = || helper();\n        f();\n    }\n}\nfn helper() {}\n";
+    let sources = SourceSet::from(vec![SourceFile::new("app", "src/lib.rs", code)]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let location = |id: &str| cg.nodes.iter().find(|n| n.id == id).and_then(|n| n.location.clone());
+
+    assert_eq!(location("Tool::run@app").as_deref(), Some("src/lib.rs:3"));
+    assert_eq!(location("Tool::run::{closure#0}@app").as_deref(), Some("src/lib.rs:4"));
+    assert_eq!(location("app::helper").as_deref(), Some("src/lib.rs:8"));
+}
+
+#[test]
+fn parallel_linking_is_deterministic() {
+    // Many files calling into each other, so linking spreads across threads
+    let files: Vec<SourceFile> = (0..64).map(|i| {
+        let code = format!(
+            "pub fn f{i}() {{ crate::m{next}::f{next}(); let c = || crate::m{prev}::f{prev}(); c(); }}\n",
+            i = i, next = (i + 1) % 64, prev = (i + 63) % 64,
+        );
+        SourceFile::new("app", format!("src/m{}.rs", i), code)
+    }).collect();
+    let sources = SourceSet::from(files);
+
+    let render = |cg: &CallGraph| -> Vec<String> {
+        cg.nodes.iter().map(|n| format!("{} -> {:?}", n.id, n.callee_ids().collect::<Vec<_>>())).collect()
+    };
+    let first = render(&SimpleCallGraphBuilder::new().build_call_graph(&sources));
+    for _ in 0..3 {
+        assert_eq!(render(&SimpleCallGraphBuilder::new().build_call_graph(&sources)), first);
+    }
+    assert!(first.iter().any(|l| l.starts_with("app::m0::f0 -> ") && l.contains("\"app::m1::f1\"")), "{:#?}", &first[..4]);
+}
+
+#[test]
+fn exporters_stream_to_writers() {
+    use mr_hedgehog::infrastructure::DotExporter;
+    use mr_hedgehog::ports::json_exporter::JsonExporter;
+    use mr_hedgehog::ports::OutputExporter;
+
+    let sources = SourceSet::from(vec![SourceFile::new("app", "src/main.rs", "fn helper() {}\nfn main() { helper(); }\n")]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let dir = tempfile::tempdir().unwrap();
+    let exporters: [(&str, Box<dyn OutputExporter>); 2] = [("dot", Box::new(DotExporter::default())), ("json", Box::new(JsonExporter))];
+    for (name, exporter) in exporters {
+        let mut streamed = Vec::new();
+        exporter.export_to_writer(&cg, &mut streamed).unwrap();
+        let path = dir.path().join(format!("graph.{}", name));
+        exporter.export(&cg, path.to_str().unwrap()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), streamed, "{}", name);
+        assert!(String::from_utf8_lossy(&streamed).contains("app::helper"), "{}", name);
+    }
+}
+
+#[test]
+fn same_named_functions_in_different_files_stay_apart() {
+    let sources = SourceSet::from(vec![
+        SourceFile::new("app", "src/main.rs", "mod utils;\nmod net;\nfn main() { utils::helper(); net::tcp::helper(); }\n"),
+        SourceFile::new("app", "src/utils.rs", "pub fn helper() {}\n"),
+        SourceFile::new("app", "src/net/mod.rs", "pub mod tcp;\n"),
+        SourceFile::new("app", "src/net/tcp.rs", "pub fn helper() { super::super::utils::helper(); }\n"),
+    ]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let callees = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap().callee_ids().map(String::from).collect::<Vec<_>>();
+    assert_eq!(callees("app::main"), vec!["app::utils::helper".to_string(), "app::net::tcp::helper".to_string()]);
+    assert!(callees("app::utils::helper").is_empty());
+}
+
+#[test]
+fn same_named_types_in_different_modules_stay_apart() {
+    let sources = SourceSet::from(vec![
+        SourceFile::new("app", "src/main.rs", "mod a;\nmod b;\nfn main() { a::Config::new(); b::Config::new(); }\n"),
+        SourceFile::new("app", "src/a.rs", "pub struct Config;\nimpl Config { pub fn new() { helper() } }\nfn helper() {}\n"),
+        SourceFile::new("app", "src/b.rs", "pub struct Config;\nimpl Config { pub fn new() { other() } }\nfn other() {}\n"),
+    ]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let callees = |id: &str| cg.nodes.iter().find(|n| n.id == id).unwrap().callee_ids().map(String::from).collect::<Vec<_>>();
+    assert_eq!(cg.nodes.iter().filter(|n| n.id.ends_with("Config::new@app")).count(), 2);
+    assert_eq!(callees("a::Config::new@app"), vec!["app::a::helper".to_string()]);
+    assert_eq!(callees("b::Config::new@app"), vec!["app::b::other".to_string()]);
+}
+
+#[test]
+fn module_paths_are_read_below_the_package_directory() {
+    // A checkout under ~/src with a module named `src`
+    let file = |path: &str, code: &str| SourceFile::new("app", format!("/home/me/src/app/{}", path), code).with_package_dir("/home/me/src/app");
+    let sources = SourceSet::from(vec![
+        file("src/main.rs", "mod gen;\nfn main() { gen::src::api::call(); }\n"),
+        file("src/gen/mod.rs", "pub mod src;\n"),
+        file("src/gen/src/mod.rs", "pub mod api;\n"),
+        file("src/gen/src/api.rs", "pub fn call() {}\n"),
+    ]);
+    let cg = SimpleCallGraphBuilder::new().build_call_graph(&sources);
+    let main = cg.nodes.iter().find(|n| n.id == "app::main").unwrap();
+    assert_eq!(main.callee_ids().collect::<Vec<_>>(), ["app::gen::src::api::call"]);
+}